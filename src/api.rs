@@ -10,7 +10,7 @@
 //!
 //! All functions in this module are blocking and require the `reqwest` feature.
 
-use anyhow::{Context, Result};
+use anyhow::{Context, Result, bail};
 
 #[cfg(feature = "reqwest")]
 use cached::proc_macro::cached;
@@ -28,6 +28,8 @@ use std::time::Duration;
 use std::time::Instant;
 
 use crate::client;
+#[cfg(feature = "reqwest")]
+use crate::client::SendTrackedBlocking;
 
 /// Fetches `id.json` from the contest's Google Cloud Storage bucket.
 ///
@@ -48,7 +50,7 @@ pub fn get_id_json() -> anyhow::Result<Vec<u8>> {
             "https://storage.googleapis.com/icfpc2025-data/{}/id.json",
             unagi_password
         ))
-        .send()
+        .send_tracked()
         .context("Failed to get id.json")?;
     res.bytes()
         .map(|b| b.to_vec())
@@ -83,23 +85,96 @@ pub fn get_id() -> anyhow::Result<String> {
     Ok(id)
 }
 
-/// Returns the base URL for the Aedificium API.
+/// The AWS API Gateway endpoint used in direct mode.
+#[cfg(feature = "reqwest")]
+const DIRECT_ENDPOINT: &str = "https://31pwr5t6ij.execute-api.eu-west-2.amazonaws.com";
+
+/// The default relay endpoint.
+#[cfg(feature = "reqwest")]
+const DEFAULT_ENDPOINT: &str = "https://icfpc.sx9.jp/api";
+
+/// Number of consecutive failed attempts against one endpoint before failing over
+/// to the next one in the list.
+#[cfg(feature = "reqwest")]
+const FAILOVER_AFTER_FAILURES: usize = 3;
+
+/// Parses an `AEDIFICIUM_ENDPOINT` value into a list of base URLs.
 ///
-/// It uses the `AEDIFICIUM_ENDPOINT` environment variable if set, otherwise
-/// defaults to `https://icfpc.sx9.jp/api`.
+/// The value is a comma-separated list; each entry is either a URL or `direct`
+/// (the AWS API Gateway endpoint). Empty entries are ignored, and an unset or
+/// empty value yields the default relay.
+#[cfg(feature = "reqwest")]
+fn parse_endpoints(value: Option<&str>) -> Vec<String> {
+    let endpoints: Vec<String> = value
+        .unwrap_or("")
+        .split(',')
+        .map(str::trim)
+        .filter(|v| !v.is_empty())
+        .map(|v| match v {
+            "direct" => DIRECT_ENDPOINT.to_string(),
+            v => v.trim_end_matches('/').to_string(),
+        })
+        .collect();
+    if endpoints.is_empty() {
+        vec![DEFAULT_ENDPOINT.to_string()]
+    } else {
+        endpoints
+    }
+}
+
+/// Returns the list of base URLs for the Aedificium API, in failover order.
+///
+/// It uses the `AEDIFICIUM_ENDPOINT` environment variable if set (see
+/// `parse_endpoints`), otherwise defaults to `https://icfpc.sx9.jp/api`.
 #[cfg(feature = "reqwest")]
 #[once]
+fn aedificium_bases() -> Vec<String> {
+    parse_endpoints(std::env::var("AEDIFICIUM_ENDPOINT").ok().as_deref())
+}
+
+/// Index into `aedificium_bases()` of the endpoint currently in use.
+#[cfg(feature = "reqwest")]
+static ACTIVE_ENDPOINT: std::sync::atomic::AtomicUsize = std::sync::atomic::AtomicUsize::new(0);
+
+/// Returns the base URL of the endpoint currently in use.
+#[cfg(feature = "reqwest")]
 fn aedificium_base() -> String {
-    match std::env::var("AEDIFICIUM_ENDPOINT").ok() {
-        Some(ref v) if v == "direct" => {
-            // Direct mode: talk to the AWS API Gateway endpoint directly
-            "https://31pwr5t6ij.execute-api.eu-west-2.amazonaws.com".to_string()
-        }
-        Some(v) => v.trim_end_matches('/').to_string(),
-        None => "https://icfpc.sx9.jp/api".to_string(),
+    let bases = aedificium_bases();
+    let i = ACTIVE_ENDPOINT.load(std::sync::atomic::Ordering::Relaxed);
+    bases[i % bases.len()].clone()
+}
+
+/// Switches to the endpoint after `failed` (if it is still the active one) and logs it.
+#[cfg(feature = "reqwest")]
+fn fail_over_from(failed: &str) {
+    use std::sync::atomic::Ordering;
+    let bases = aedificium_bases();
+    if bases.len() <= 1 {
+        return;
+    }
+    let cur = ACTIVE_ENDPOINT.load(Ordering::Relaxed);
+    if bases[cur % bases.len()] != failed {
+        // Another request already failed over.
+        return;
+    }
+    let next = (cur + 1) % bases.len();
+    if ACTIVE_ENDPOINT
+        .compare_exchange(cur, next, Ordering::Relaxed, Ordering::Relaxed)
+        .is_ok()
+    {
+        eprintln!(
+            "Aedificium endpoint {} keeps failing; failing over to {}",
+            failed, bases[next]
+        );
     }
 }
 
+/// True if every configured endpoint is the direct AWS endpoint (no relay, no lock).
+#[cfg(feature = "reqwest")]
+fn is_direct_mode() -> bool {
+    aedificium_bases().iter().all(|b| b == DIRECT_ENDPOINT)
+}
+
 /// Logs the value of the `x-unagi-log` header if present in the response.
 #[cfg(feature = "reqwest")]
 fn log_unagi_header(res: &reqwest::blocking::Response) {
@@ -111,6 +186,98 @@ fn log_unagi_header(res: &reqwest::blocking::Response) {
     }
 }
 
+/// A request to the contest server that failed for good.
+///
+/// Transient failures are retried inside this module; these errors come back
+/// once the retry window is over (or for answers not worth retrying), so a
+/// long-running solver can wait, retry or change strategy instead of dying.
+#[cfg(feature = "reqwest")]
+#[derive(thiserror::Error, Debug)]
+pub enum ApiError {
+    /// The server kept answering 429 Too Many Requests for the whole retry window.
+    #[error("{context} was rate limited for over {elapsed:?}")]
+    RateLimited { context: String, elapsed: Duration },
+    /// The server answered with an error status, either one that is not
+    /// retried or one that outlasted the retry window.
+    #[error("{context} returned {status}: {body}")]
+    ServerError {
+        context: String,
+        status: reqwest::StatusCode,
+        body: String,
+    },
+    /// The server could not be reached (network errors, timeouts) for the
+    /// whole retry window.
+    #[error("{context} got no response for over {elapsed:?}: {last_error}")]
+    DeadlineExceeded {
+        context: String,
+        elapsed: Duration,
+        last_error: String,
+    },
+    /// The response body was not what the endpoint returns.
+    #[error("failed to parse {context} response: {source}")]
+    Decode {
+        context: String,
+        source: reqwest::Error,
+    },
+    /// A split `/explore` (see [`explore_chunks`]) failed after some of its
+    /// POSTs went through. Those were answered and charged, so `done` holds
+    /// the results of the first `done.results.len()` plans.
+    #[error("/explore failed after {} plans were answered: {source}", .done.results.len())]
+    PartialExplore {
+        done: ExploreResponse,
+        source: Box<ApiError>,
+    },
+    /// A failure before the request was sent, e.g. reading the team id.
+    #[error(transparent)]
+    Other(#[from] anyhow::Error),
+}
+
+#[cfg(feature = "reqwest")]
+impl ApiError {
+    /// Whether the same request may succeed if sent again later.
+    pub fn is_retryable(&self) -> bool {
+        match self {
+            ApiError::RateLimited { .. } | ApiError::DeadlineExceeded { .. } => true,
+            ApiError::ServerError { status, .. } => status.is_server_error(),
+            // Sending the same plans again would pay for the answered ones twice.
+            ApiError::Decode { .. } | ApiError::PartialExplore { .. } | ApiError::Other(_) => false,
+        }
+    }
+
+    /// The error for a response with `status` that is not (or no longer) retried.
+    fn from_status(
+        context: &str,
+        status: reqwest::StatusCode,
+        body: String,
+        elapsed: Duration,
+    ) -> Self {
+        if status == reqwest::StatusCode::TOO_MANY_REQUESTS {
+            ApiError::RateLimited {
+                context: context.to_string(),
+                elapsed,
+            }
+        } else {
+            ApiError::ServerError {
+                context: context.to_string(),
+                status,
+                body,
+            }
+        }
+    }
+}
+
+/// Parses a JSON response body, reporting failures as [`ApiError::Decode`].
+#[cfg(feature = "reqwest")]
+fn decode<T: serde::de::DeserializeOwned>(
+    res: reqwest::blocking::Response,
+    context: &str,
+) -> Result<T, ApiError> {
+    res.json().map_err(|source| ApiError::Decode {
+        context: context.to_string(),
+        source,
+    })
+}
+
 /// Returns a retry window for the given HTTP status if it should be retried.
 ///
 /// - 5xx: retry up to 30 minutes
@@ -127,47 +294,97 @@ fn retry_window_for_status(status: reqwest::StatusCode) -> Option<Duration> {
     }
 }
 
-/// Performs a POST with JSON body and retries on transient failures.
+/// Name of the solver making requests, sent as `X-Unagi-Solver` so that the
+/// logging proxy can attribute query costs: `UNAGI_SOLVER` (the executor sets
+/// it to the agent name), or else the executable's name.
+#[cfg(feature = "reqwest")]
+#[once]
+fn solver_name() -> String {
+    std::env::var("UNAGI_SOLVER")
+        .ok()
+        .filter(|s| !s.is_empty())
+        .or_else(|| {
+            std::env::current_exe()
+                .ok()?
+                .file_stem()
+                .map(|s| s.to_string_lossy().into_owned())
+        })
+        .unwrap_or_else(|| "unknown".to_string())
+}
+
+/// Performs a POST with JSON body to `path` on the active endpoint and retries
+/// on transient failures.
 ///
 /// Backoff waits 1, 2, 4, ..., up to 32 seconds between attempts, then keeps
 /// retrying every 32 seconds until 30 minutes have elapsed since the first
-/// attempt. If it still hasn't succeeded by then, the function panics.
+/// attempt (one minute for 4xx answers). If it still hasn't succeeded by then,
+/// the last failure is returned as an [`ApiError`].
+///
+/// After `FAILOVER_AFTER_FAILURES` consecutive transient failures against one
+/// endpoint, later attempts go to the next endpoint in `aedificium_bases()`.
+/// The endpoint that finally served the request is logged.
 #[cfg(feature = "reqwest")]
 fn post_json_with_retry<T: Serialize + ?Sized>(
     client: &Client,
-    url: &str,
+    path: &str,
     body: &T,
     context: &str,
-) -> Result<reqwest::blocking::Response> {
+) -> Result<reqwest::blocking::Response, ApiError> {
     let start = Instant::now();
     let network_deadline = Duration::from_secs(30 * 60);
     let mut delay = Duration::from_secs(1);
+    let mut failures = 0;
     loop {
-        match client.post(url).json(body).send() {
+        let base = aedificium_base();
+        let url = format!("{}{}", base, path);
+        match client
+            .post(&url)
+            .header("X-Unagi-Solver", solver_name())
+            .json(body)
+            .send_tracked()
+        {
             Ok(res) => {
                 let status = res.status();
                 log_unagi_header(&res);
                 if status.is_success() {
+                    if aedificium_bases().len() > 1 {
+                        eprintln!("{} served by {}", context, base);
+                    }
                     return Ok(res);
                 }
-                if let Some(limit) = retry_window_for_status(status) {
-                    if start.elapsed() >= limit {
-                        panic!("{} failed for over {:?} — aborting", context, limit);
+                match retry_window_for_status(status) {
+                    Some(limit) if start.elapsed() < limit => {
+                        // transient error: fallthrough to sleep and retry
+                        eprintln!("{} returned {} from {} — will retry", context, status, base);
+                    }
+                    _ => {
+                        let body = res.text().unwrap_or_default();
+                        return Err(ApiError::from_status(
+                            context,
+                            status,
+                            body,
+                            start.elapsed(),
+                        ));
                     }
-                    // transient error: fallthrough to sleep and retry
-                } else {
-                    let body = res.text().unwrap_or_default();
-                    anyhow::bail!("{} returned {}: {}", context, status, body);
                 }
             }
             Err(err) => {
                 // Network/timeout errors: retry until deadline
                 eprintln!("{} request error: {} — will retry", context, err);
                 if start.elapsed() >= network_deadline {
-                    panic!("{} failed for over 30 minutes — aborting", context);
+                    return Err(ApiError::DeadlineExceeded {
+                        context: context.to_string(),
+                        elapsed: start.elapsed(),
+                        last_error: err.to_string(),
+                    });
                 }
             }
         }
+        failures += 1;
+        if failures >= FAILOVER_AFTER_FAILURES {
+            fail_over_from(&base);
+            failures = 0;
+        }
         std::thread::sleep(delay);
         if delay < Duration::from_secs(32) {
             delay = std::cmp::min(delay.saturating_mul(2), Duration::from_secs(32));
@@ -214,17 +431,12 @@ struct SelectResponse {
 ///
 /// The `problemName` echoed by the service on success.
 #[cfg(feature = "reqwest")]
-pub fn select(problem_name: &str) -> Result<String> {
+pub fn select(problem_name: &str) -> Result<String, ApiError> {
     // Acquire process-wide lock and start renewal thread, unless in direct mode.
-    let is_direct = matches!(
-        std::env::var("AEDIFICIUM_ENDPOINT").ok().as_deref(),
-        Some("direct")
-    );
-    if !is_direct {
+    if !is_direct_mode() {
         start_lock_manager_blocking()?;
     }
     let client = &*client::BLOCKING_CLIENT;
-    let url = format!("{}/select", aedificium_base());
 
     // Obtain id via get_id (parsed from id.json).
     let id = get_id()?;
@@ -232,12 +444,24 @@ pub fn select(problem_name: &str) -> Result<String> {
         id: id.as_str(),
         problem_name,
     };
-    let res = post_json_with_retry(client, &url, &req, "/select")?;
+    let res = post_json_with_retry(client, "/select", &req, "/select")?;
 
-    let body: SelectResponse = res.json().context("Failed to parse /select response")?;
+    let body: SelectResponse = decode(res, "/select")?;
     Ok(body.problem_name)
 }
 
+/// Takes the lock again for a problem `/select`ed by an earlier process,
+/// without selecting it anew, so that the server keeps that session's map
+/// (see `RemoteJudge::resume`). Like `select`, the lock is held until
+/// `guess()`.
+#[cfg(feature = "reqwest")]
+pub fn reattach() -> Result<(), ApiError> {
+    if !is_direct_mode() {
+        start_lock_manager_blocking()?;
+    }
+    Ok(())
+}
+
 /// Represents the JSON request body for the `/explore` endpoint.
 #[cfg(feature = "reqwest")]
 #[derive(Serialize)]
@@ -259,11 +483,65 @@ pub struct ExploreResponse {
     /// The total number of queries consumed by this request.
     #[serde(rename = "queryCount")]
     pub query_count: u64,
+    /// Number of POSTs the plans were split into (see [`explore_chunks`]).
+    /// Each one is charged a request query on top of its plans.
+    #[serde(skip, default = "one")]
+    pub chunks: usize,
+}
+
+#[cfg(feature = "reqwest")]
+fn one() -> usize {
+    1
+}
+
+/// Default for `UNAGI_EXPLORE_MAX_BYTES`.
+#[cfg(feature = "reqwest")]
+pub const DEFAULT_EXPLORE_MAX_BYTES: usize = 1 << 20;
+
+/// Bytes of an `/explore` body besides its plans, with room for the id.
+#[cfg(feature = "reqwest")]
+const EXPLORE_BODY_OVERHEAD: usize = 128;
+
+/// Largest `/explore` body to send in one POST, from `UNAGI_EXPLORE_MAX_BYTES`
+/// (default [`DEFAULT_EXPLORE_MAX_BYTES`]).
+#[cfg(feature = "reqwest")]
+pub fn explore_max_bytes() -> usize {
+    std::env::var("UNAGI_EXPLORE_MAX_BYTES")
+        .ok()
+        .and_then(|v| v.trim().parse().ok())
+        .unwrap_or(DEFAULT_EXPLORE_MAX_BYTES)
+}
+
+/// Splits `plans` into consecutive ranges whose request bodies stay within
+/// `max_bytes`. A plan too long to fit any body is sent on its own.
+#[cfg(feature = "reqwest")]
+pub fn explore_chunks(plans: &[String], max_bytes: usize) -> Vec<std::ops::Range<usize>> {
+    let mut chunks = vec![];
+    let mut start = 0;
+    let mut size = EXPLORE_BODY_OVERHEAD;
+    for (i, plan) in plans.iter().enumerate() {
+        // The quotes and the separating comma.
+        let len = plan.len() + 3;
+        if i > start && size + len > max_bytes {
+            chunks.push(start..i);
+            start = i;
+            size = EXPLORE_BODY_OVERHEAD;
+        }
+        size += len;
+    }
+    if start < plans.len() || chunks.is_empty() {
+        chunks.push(start..plans.len());
+    }
+    chunks
 }
 
 /// Submits one or more route plans for exploration via `POST /explore`.
 ///
-/// This function fetches the team `id` internally.
+/// This function fetches the team `id` internally. Plans that would make the
+/// body larger than [`explore_max_bytes`] are sent as several POSTs (see
+/// [`explore_chunks`]); the results are merged in the order of `plans`. If a
+/// POST other than the first fails, the error is an
+/// [`ApiError::PartialExplore`] carrying what the earlier ones returned.
 ///
 /// # Arguments
 ///
@@ -274,25 +552,56 @@ pub struct ExploreResponse {
 ///
 /// An `ExploreResponse` containing the results of the exploration.
 #[cfg(feature = "reqwest")]
-pub fn explore<I, S>(plans: I) -> Result<ExploreResponse>
+pub fn explore<I, S>(plans: I) -> Result<ExploreResponse, ApiError>
 where
     I: IntoIterator<Item = S>,
     S: AsRef<str>,
 {
     let client = &*client::BLOCKING_CLIENT;
-    let url = format!("{}/explore", aedificium_base());
     let id = get_id()?;
     // Convert the plans from Vec<usize> to strings of digits for the JSON request.
     let plans_vec: Vec<String> = plans.into_iter().map(|s| s.as_ref().to_string()).collect();
-    let req = ExploreRequest {
-        id: id.as_str(),
-        plans: &plans_vec,
-    };
-
-    let res = post_json_with_retry(client, &url, &req, "/explore")?;
+    let chunks = explore_chunks(&plans_vec, explore_max_bytes());
+    merge_explore_chunks(&plans_vec, chunks, |plans| {
+        let req = ExploreRequest {
+            id: id.as_str(),
+            plans,
+        };
+        let res = post_json_with_retry(client, "/explore", &req, "/explore")?;
+        decode(res, "/explore")
+    })
+}
 
-    let body: ExploreResponse = res.json().context("Failed to parse /explore response")?;
-    Ok(body)
+/// Sends each range of `plans` with `post` and merges the responses, stopping
+/// at the first failure (see [`explore`]).
+#[cfg(feature = "reqwest")]
+fn merge_explore_chunks(
+    plans: &[String],
+    chunks: Vec<std::ops::Range<usize>>,
+    mut post: impl FnMut(&[String]) -> Result<ExploreResponse, ApiError>,
+) -> Result<ExploreResponse, ApiError> {
+    let mut merged = ExploreResponse {
+        results: Vec::with_capacity(plans.len()),
+        query_count: 0,
+        chunks: 0,
+    };
+    for range in chunks {
+        let res = match post(&plans[range]) {
+            Ok(res) => res,
+            Err(e) if merged.chunks == 0 => return Err(e),
+            Err(e) => {
+                return Err(ApiError::PartialExplore {
+                    done: merged,
+                    source: Box::new(e),
+                });
+            }
+        };
+        merged.results.extend(res.results);
+        merged.chunks += 1;
+        // The server's count is for the whole session, so the last is the total.
+        merged.query_count = merged.query_count.max(res.query_count);
+    }
+    Ok(merged)
 }
 
 /// Represents one end of a passage, specified by a room and a door index.
@@ -339,6 +648,15 @@ pub struct GuessRequest {
     pub map: Map,
 }
 
+/// Represents a logged `/explore` request body (`api_logs.api_log_request`),
+/// read back by the dashboard and `unagi verify`.
+#[cfg(feature = "reqwest")]
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ExploreLog {
+    /// The exploration plans as sent.
+    pub plans: Vec<String>,
+}
+
 /// Represents the JSON response from the `/guess` endpoint.
 #[cfg(feature = "reqwest")]
 #[derive(Deserialize)]
@@ -360,22 +678,63 @@ struct GuessResponse {
 ///
 /// `true` if the map was correct, `false` otherwise.
 #[cfg(feature = "reqwest")]
-pub fn guess(map: &Map) -> Result<bool> {
-    let client = &*client::BLOCKING_CLIENT;
-    let url = format!("{}/guess", aedificium_base());
+pub fn guess(map: &Map) -> Result<bool, ApiError> {
+    guess_logged(map).map(|(correct, _)| correct)
+}
 
+/// Like [`guess`], but also returns the `api_logs` id the proxy recorded for
+/// the request (its `X-Unagi-Log` header), if the request went through it.
+#[cfg(feature = "reqwest")]
+pub fn guess_logged(map: &Map) -> Result<(bool, Option<u64>), ApiError> {
+    let client = &*client::BLOCKING_CLIENT;
     let id = get_id()?;
     let req = GuessRequest {
         id,
         map: map.clone(),
     };
 
-    let res = post_json_with_retry(client, &url, &req, "/guess")?;
+    let res = post_json_with_retry(client, "/guess", &req, "/guess")?;
+    let log_id = res
+        .headers()
+        .get("X-Unagi-Log")
+        .and_then(|v| v.to_str().ok())
+        .and_then(|v| serde_json::from_str::<serde_json::Value>(v).ok())
+        .and_then(|v| v["api_log_id"].as_u64())
+        .filter(|&id| id > 0);
 
-    let body: GuessResponse = res.json().context("Failed to parse /guess response")?;
+    let body: GuessResponse = decode(res, "/guess")?;
     // Stop renewal and unlock immediately after a guess is made.
     stop_lock_manager_blocking();
-    Ok(body.correct)
+    Ok((body.correct, log_id))
+}
+
+/// Checks value ranges of a map before it is submitted: labels are 2-bit,
+/// the starting room exists and every connection names a valid room and door.
+#[cfg(feature = "reqwest")]
+pub fn validate_map(map: &Map) -> Result<()> {
+    // rooms must be 2-bit integers 0..=3
+    for (i, &v) in map.rooms.iter().enumerate() {
+        if v > 3 {
+            bail!("rooms[{}] must be in 0..=3 (2-bit)", i);
+        }
+    }
+    // starting_room must be valid index
+    if map.starting_room >= map.rooms.len() {
+        bail!("startingRoom is out of range");
+    }
+    let n = map.rooms.len();
+    // connections: room indices valid and door numbers 0..=5
+    for (i, c) in map.connections.iter().enumerate() {
+        for (side, end) in [("from", &c.from), ("to", &c.to)] {
+            if end.room >= n {
+                bail!("connections[{}].{}.room out of range", i, side);
+            }
+            if end.door > 5 {
+                bail!("connections[{}].{}.door must be in 0..=5", i, side);
+            }
+        }
+    }
+    Ok(())
 }
 
 #[cfg(feature = "reqwest")]
@@ -383,13 +742,13 @@ pub fn guess(map: &Map) -> Result<bool> {
 pub fn scores() -> Result<HashMap<String, i64>> {
     let client = &*client::BLOCKING_CLIENT;
     // This endpoint is not proxied.
-    let url = "https://31pwr5t6ij.execute-api.eu-west-2.amazonaws.com/";
+    let url = format!("{}/", DIRECT_ENDPOINT);
 
     let id = get_id()?;
     let res = client
-        .get(url)
+        .get(&url)
         .query(&[("id", &id)])
-        .send()
+        .send_tracked()
         .context("Failed to GET scores")?;
     let status = res.status();
     if !status.is_success() {
@@ -401,6 +760,25 @@ pub fn scores() -> Result<HashMap<String, i64>> {
     Ok(body)
 }
 
+/// Fetches the problem list from `GET /select`, including problems published
+/// after `problems.json` was last updated. Like [`scores`], this goes to the
+/// contest server directly.
+#[cfg(feature = "reqwest")]
+pub fn problems() -> Result<Vec<crate::problems::Problem>> {
+    let client = &*client::BLOCKING_CLIENT;
+    let url = format!("{}/select", DIRECT_ENDPOINT);
+    let res = client
+        .get(&url)
+        .send_tracked()
+        .context("Failed to GET /select")?;
+    let status = res.status();
+    if !status.is_success() {
+        let body = res.text().unwrap_or_default();
+        anyhow::bail!("/select (problems) returned {}: {}", status, body);
+    }
+    res.json().context("Failed to parse problem list")
+}
+
 /// Issues a GET request to `aedificium_base()/` and discards the response.
 ///
 /// Intended for basic latency measurements; it does not interpret the body.
@@ -408,7 +786,7 @@ pub fn scores() -> Result<HashMap<String, i64>> {
 pub fn ping_root() -> Result<()> {
     let client = &*client::BLOCKING_CLIENT;
     let url = format!("{}/", aedificium_base());
-    let res = client.get(&url).send().context("Failed to GET /")?;
+    let res = client.get(&url).send_tracked().context("Failed to GET /")?;
     let status = res.status();
     log_unagi_header(&res);
     // Treat 2xx as well as 400/404 as normal for measurement purposes.
@@ -429,6 +807,106 @@ mod tests {
     use super::*;
     use anyhow::Result;
 
+    #[cfg(feature = "reqwest")]
+    #[test]
+    fn status_errors_after_the_retry_window() {
+        use reqwest::StatusCode;
+        let elapsed = Duration::from_secs(61);
+        let e = ApiError::from_status(
+            "/explore",
+            StatusCode::TOO_MANY_REQUESTS,
+            "".into(),
+            elapsed,
+        );
+        assert!(matches!(e, ApiError::RateLimited { .. }));
+        assert!(e.is_retryable());
+        let e = ApiError::from_status("/explore", StatusCode::BAD_GATEWAY, "down".into(), elapsed);
+        assert!(e.is_retryable());
+        assert_eq!(e.to_string(), "/explore returned 502 Bad Gateway: down");
+        let e = ApiError::from_status("/guess", StatusCode::BAD_REQUEST, "bad map".into(), elapsed);
+        assert!(matches!(e, ApiError::ServerError { .. }));
+        assert!(!e.is_retryable());
+        // Anything an `ApiError` carries still goes through `?` into `anyhow`.
+        let e: anyhow::Error = e.into();
+        assert!(e.downcast_ref::<ApiError>().is_some());
+    }
+
+    #[cfg(feature = "reqwest")]
+    #[test]
+    fn failed_explore_chunks_keep_the_answered_ones() {
+        let plans = ["0", "1", "2"].map(String::from);
+        let answer = |plans: &[String]| ExploreResponse {
+            results: plans.iter().map(|p| vec![p.len()]).collect(),
+            query_count: 7,
+            chunks: 1,
+        };
+        let down = || ApiError::Other(anyhow::anyhow!("server is down"));
+
+        let mut posts = 0;
+        let e = merge_explore_chunks(&plans, vec![0..2, 2..3], |p| {
+            posts += 1;
+            if posts == 1 {
+                Ok(answer(p))
+            } else {
+                Err(down())
+            }
+        })
+        .unwrap_err();
+        let ApiError::PartialExplore { done, .. } = &e else {
+            panic!("expected a partial result: {}", e);
+        };
+        assert_eq!(done.results, vec![vec![1], vec![1]]);
+        assert_eq!((done.chunks, done.query_count), (1, 7));
+        assert!(!e.is_retryable());
+
+        // Nothing was charged if the first POST fails.
+        let e = merge_explore_chunks(&plans, vec![0..2, 2..3], |_| Err(down())).unwrap_err();
+        assert!(matches!(e, ApiError::Other(_)));
+        let all = merge_explore_chunks(&plans, vec![0..2, 2..3], |p| Ok(answer(p))).unwrap();
+        assert_eq!((all.results.len(), all.chunks), (3, 2));
+    }
+
+    #[cfg(feature = "reqwest")]
+    #[test]
+    fn explore_chunks_keep_bodies_small() {
+        let plans = vec![
+            "0".repeat(40),
+            "1".repeat(40),
+            "2".repeat(200),
+            "3".repeat(10),
+        ];
+        let budget = EXPLORE_BODY_OVERHEAD + 2 * 43;
+        assert_eq!(explore_chunks(&plans, budget), vec![0..2, 2..3, 3..4]);
+        assert_eq!(
+            explore_chunks(&plans, budget - 1),
+            vec![0..1, 1..2, 2..3, 3..4]
+        );
+        assert_eq!(explore_chunks(&plans, usize::MAX), vec![0..4]);
+        assert_eq!(explore_chunks(&[], 0), vec![0..0]);
+    }
+
+    #[cfg(feature = "reqwest")]
+    #[test]
+    fn parse_endpoints_list() {
+        assert_eq!(parse_endpoints(None), vec![DEFAULT_ENDPOINT.to_string()]);
+        assert_eq!(
+            parse_endpoints(Some("")),
+            vec![DEFAULT_ENDPOINT.to_string()]
+        );
+        assert_eq!(
+            parse_endpoints(Some("direct")),
+            vec![DIRECT_ENDPOINT.to_string()]
+        );
+        assert_eq!(
+            parse_endpoints(Some("http://a.example/api/, direct,,http://b.example")),
+            vec![
+                "http://a.example/api".to_string(),
+                DIRECT_ENDPOINT.to_string(),
+                "http://b.example".to_string(),
+            ]
+        );
+    }
+
     // Runs only when explicitly enabled (e.g., `make test/unagi`).
     // Requires `UNAGI_PASSWORD` to be set to access the remote object.
     #[ignore]
@@ -0,0 +1,311 @@
+//! # Compact Session Archives
+//!
+//! Archived explore sessions are mostly long plans and label sequences, which
+//! the JSON replay format (the `{"problemName", "plans", "results", ...}` input
+//! of `judge::get_judge_from_stdin`) spells out one digit at a time. This
+//! module stores them in a small binary layout, gzip-compressed:
+//!
+//! - header: magic `UNGS`, format version
+//! - problem name, room count and (optional) map
+//! - per plan: one byte per step (`door + 6 * (rewrite + 1)`, or just the door
+//!   without a rewrite) and the result labels packed four per byte
+//!
+//! Integers are LEB128 varints. Solver checkpoints that are not sessions can
+//! use [`write_json_gz`] / [`read_json_gz`] instead.
+
+use anyhow::{Context, Result, bail, ensure};
+use flate2::Compression;
+use flate2::read::GzDecoder;
+use flate2::write::GzEncoder;
+use std::io::{Read, Write};
+
+use crate::api;
+use crate::judge::{self, JsonIn, Step};
+
+const MAGIC: &[u8; 4] = b"UNGS";
+const VERSION: u8 = 1;
+
+/// An archived session: what the JSON replay format carries.
+#[derive(Clone, Debug)]
+pub struct Session {
+    pub problem_name: Option<String>,
+    pub num_rooms: Option<usize>,
+    pub map: Option<api::Map>,
+    pub plans: Vec<Vec<Step>>,
+    pub results: Vec<Vec<usize>>,
+}
+
+impl Session {
+    /// Parses the JSON replay format.
+    pub fn from_json_replay(json: &str) -> Result<Self> {
+        let parsed: JsonIn = serde_json::from_str(json).context("invalid replay JSON")?;
+        let plans = parsed.plans.unwrap_or_default();
+        let results = parsed.results.unwrap_or_default();
+        ensure!(
+            plans.len() == results.len(),
+            "replay has {} plans but {} results",
+            plans.len(),
+            results.len()
+        );
+        Ok(Session {
+            problem_name: parsed.problem_name,
+            num_rooms: parsed.num_rooms,
+            map: parsed.map,
+            plans: plans.iter().map(|p| judge::parse_plan(p)).collect(),
+            results,
+        })
+    }
+
+    /// Renders the JSON replay format.
+    pub fn to_json_replay(&self) -> serde_json::Value {
+        let mut v = serde_json::json!({
+            "plans": self.plans.iter().map(|p| judge::format_plan(p)).collect::<Vec<_>>(),
+            "results": self.results,
+        });
+        if let Some(name) = &self.problem_name {
+            v["problemName"] = name.as_str().into();
+        }
+        if let Some(n) = self.num_rooms {
+            v["numRooms"] = n.into();
+        }
+        if let Some(map) = &self.map {
+            v["map"] = serde_json::to_value(map).unwrap();
+        }
+        v
+    }
+
+    /// Encodes the session in the compact gzip-compressed format.
+    pub fn encode(&self) -> Result<Vec<u8>> {
+        let mut buf = MAGIC.to_vec();
+        buf.push(VERSION);
+        let name = self.problem_name.as_deref().unwrap_or("");
+        put_varint(&mut buf, name.len() as u64);
+        buf.extend_from_slice(name.as_bytes());
+        // Room counts are stored off by one so that 0 means unknown.
+        put_varint(&mut buf, self.num_rooms.map_or(0, |n| n as u64 + 1));
+        match &self.map {
+            None => buf.push(0),
+            Some(map) => {
+                buf.push(1);
+                put_labels(&mut buf, &map.rooms)?;
+                put_varint(&mut buf, map.starting_room as u64);
+                put_varint(&mut buf, map.connections.len() as u64);
+                for c in &map.connections {
+                    for x in [c.from.room, c.from.door, c.to.room, c.to.door] {
+                        put_varint(&mut buf, x as u64);
+                    }
+                }
+            }
+        }
+        ensure!(
+            self.plans.len() == self.results.len(),
+            "plans/results mismatch"
+        );
+        put_varint(&mut buf, self.plans.len() as u64);
+        for (plan, result) in self.plans.iter().zip(&self.results) {
+            put_varint(&mut buf, plan.len() as u64);
+            for &(rewrite, door) in plan {
+                ensure!(door < 6, "door {} out of range", door);
+                let code = match rewrite {
+                    None => door,
+                    Some(k) if k < 4 => door + 6 * (k + 1),
+                    Some(k) => bail!("label {} out of range", k),
+                };
+                buf.push(code as u8);
+            }
+            put_labels(&mut buf, result)?;
+        }
+        let mut enc = GzEncoder::new(Vec::new(), Compression::best());
+        enc.write_all(&buf)?;
+        Ok(enc.finish()?)
+    }
+
+    /// Decodes a session written by [`Session::encode`].
+    pub fn decode(data: &[u8]) -> Result<Self> {
+        let mut buf = vec![];
+        GzDecoder::new(data)
+            .read_to_end(&mut buf)
+            .context("not a gzip stream")?;
+        let mut r = Reader { buf: &buf, pos: 0 };
+        ensure!(r.bytes(4)? == MAGIC, "not a session archive");
+        let version = r.byte()?;
+        ensure!(
+            version == VERSION,
+            "unsupported archive version {}",
+            version
+        );
+        let name_len = r.varint()? as usize;
+        let name = String::from_utf8(r.bytes(name_len)?.to_vec())?;
+        let num_rooms = match r.varint()? {
+            0 => None,
+            n => Some(n as usize - 1),
+        };
+        let map = match r.byte()? {
+            0 => None,
+            _ => {
+                let rooms = r.labels()?;
+                let starting_room = r.varint()? as usize;
+                let count = r.varint()? as usize;
+                let mut connections = Vec::with_capacity(count.min(r.remaining()));
+                for _ in 0..count {
+                    let mut end = || -> Result<api::MapConnectionEnd> {
+                        Ok(api::MapConnectionEnd {
+                            room: r.varint()? as usize,
+                            door: r.varint()? as usize,
+                        })
+                    };
+                    let from = end()?;
+                    let to = end()?;
+                    connections.push(api::MapConnection { from, to });
+                }
+                Some(api::Map {
+                    rooms,
+                    starting_room,
+                    connections,
+                })
+            }
+        };
+        let count = r.varint()? as usize;
+        let mut plans = Vec::with_capacity(count.min(r.remaining()));
+        let mut results = Vec::with_capacity(count.min(r.remaining()));
+        for _ in 0..count {
+            let len = r.varint()? as usize;
+            let plan = r
+                .bytes(len)?
+                .iter()
+                .map(|&c| {
+                    let c = c as usize;
+                    ensure!(c < 30, "invalid step code {}", c);
+                    Ok(((c >= 6).then(|| c / 6 - 1), c % 6))
+                })
+                .collect::<Result<Vec<_>>>()?;
+            plans.push(plan);
+            results.push(r.labels()?);
+        }
+        ensure!(r.remaining() == 0, "trailing bytes in archive");
+        Ok(Session {
+            problem_name: (!name.is_empty()).then_some(name),
+            num_rooms,
+            map,
+            plans,
+            results,
+        })
+    }
+}
+
+fn put_varint(buf: &mut Vec<u8>, mut x: u64) {
+    while x >= 0x80 {
+        buf.push((x as u8) | 0x80);
+        x >>= 7;
+    }
+    buf.push(x as u8);
+}
+
+/// Writes a length followed by 2-bit labels, four per byte.
+fn put_labels(buf: &mut Vec<u8>, labels: &[usize]) -> Result<()> {
+    put_varint(buf, labels.len() as u64);
+    for chunk in labels.chunks(4) {
+        let mut b = 0u8;
+        for (i, &l) in chunk.iter().enumerate() {
+            ensure!(l < 4, "label {} out of range", l);
+            b |= (l as u8) << (2 * i);
+        }
+        buf.push(b);
+    }
+    Ok(())
+}
+
+struct Reader<'a> {
+    buf: &'a [u8],
+    pos: usize,
+}
+
+impl<'a> Reader<'a> {
+    fn remaining(&self) -> usize {
+        self.buf.len() - self.pos
+    }
+
+    fn bytes(&mut self, n: usize) -> Result<&'a [u8]> {
+        ensure!(n <= self.remaining(), "truncated archive");
+        let s = &self.buf[self.pos..self.pos + n];
+        self.pos += n;
+        Ok(s)
+    }
+
+    fn byte(&mut self) -> Result<u8> {
+        Ok(self.bytes(1)?[0])
+    }
+
+    fn varint(&mut self) -> Result<u64> {
+        let mut x = 0u64;
+        for shift in (0..64).step_by(7) {
+            let b = self.byte()?;
+            x |= ((b & 0x7f) as u64) << shift;
+            if b < 0x80 {
+                return Ok(x);
+            }
+        }
+        bail!("varint too long")
+    }
+
+    fn labels(&mut self) -> Result<Vec<usize>> {
+        let len = self.varint()? as usize;
+        let packed = self.bytes(len.div_ceil(4))?;
+        Ok((0..len)
+            .map(|i| ((packed[i / 4] >> (2 * (i % 4))) & 3) as usize)
+            .collect())
+    }
+}
+
+/// Serializes any value as gzip-compressed JSON (for solver checkpoints).
+pub fn write_json_gz<T: serde::Serialize>(value: &T) -> Result<Vec<u8>> {
+    let mut enc = GzEncoder::new(Vec::new(), Compression::default());
+    serde_json::to_writer(&mut enc, value)?;
+    Ok(enc.finish()?)
+}
+
+/// Reads a value written by [`write_json_gz`].
+pub fn read_json_gz<T: serde::de::DeserializeOwned>(data: &[u8]) -> Result<T> {
+    Ok(serde_json::from_reader(GzDecoder::new(data))?)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::judge::Judge;
+
+    #[test]
+    fn session_roundtrip_and_size() {
+        let mut judge = judge::LocalJudge::new("random", 30, 5);
+        let mut plans = vec![
+            crate::routes::long_plan(30)
+                .into_iter()
+                .map(|d| (None, d))
+                .collect::<Vec<_>>(),
+        ];
+        plans.push(judge::parse_plan("0[1]2[3]45"));
+        let results = judge.explore(&plans);
+        let replay = serde_json::json!({
+            "problemName": "quintus",
+            "numRooms": 30,
+            "plans": plans.iter().map(|p| judge::format_plan(p)).collect::<Vec<_>>(),
+            "results": results,
+            "map": judge.dump_json(),
+        })
+        .to_string();
+
+        let session = Session::from_json_replay(&replay).unwrap();
+        let packed = session.encode().unwrap();
+        assert!(packed.len() * 4 < replay.len());
+        let back = Session::decode(&packed).unwrap();
+        assert_eq!(back.problem_name.as_deref(), Some("quintus"));
+        assert_eq!(back.num_rooms, Some(30));
+        assert_eq!(back.plans, plans);
+        assert_eq!(back.results, results);
+        assert_eq!(
+            serde_json::to_value(back.map.unwrap()).unwrap(),
+            serde_json::to_value(session.map.unwrap()).unwrap()
+        );
+        assert!(Session::decode(&packed[..packed.len() / 2]).is_err());
+    }
+}
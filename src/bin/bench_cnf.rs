@@ -0,0 +1,77 @@
+//! Times CNF construction of `solve_no_marks` on one thread and on the whole
+//! rayon pool.
+//!
+//! Usage: `bench_cnf [num_rooms...]` (default `30 60 90`). Each size explores
+//! one random plan of `18 * num_rooms` doors on a random local map, then
+//! builds the CNF a few times per configuration and reports the fastest run.
+
+use icfpc2025::judge::{Judge, LocalJudge};
+use rand::prelude::*;
+use std::time::{Duration, Instant};
+
+const RUNS: usize = 3;
+
+fn fastest(
+    pool: &rayon::ThreadPool,
+    n: usize,
+    plans: &Vec<Vec<usize>>,
+    labels: &Vec<Vec<usize>>,
+) -> (Duration, usize) {
+    let mut best = Duration::MAX;
+    let mut clauses = 0;
+    for _ in 0..RUNS {
+        let start = Instant::now();
+        clauses = pool.install(|| icfpc2025::solve_no_marks::cnf_size(n, plans, labels).1);
+        best = best.min(start.elapsed());
+    }
+    (best, clauses)
+}
+
+fn main() {
+    let sizes: Vec<usize> = std::env::args()
+        .skip(1)
+        .map(|s| s.parse().expect("num_rooms must be a number"))
+        .collect();
+    let sizes = if sizes.is_empty() {
+        vec![30, 60, 90]
+    } else {
+        sizes
+    };
+    let single = rayon::ThreadPoolBuilder::new()
+        .num_threads(1)
+        .build()
+        .unwrap();
+    let all = rayon::ThreadPoolBuilder::new().build().unwrap();
+    println!(
+        "{:>5} {:>10} {:>10} {:>10} {:>8}",
+        "rooms",
+        "clauses",
+        "1 thread",
+        format!("{} threads", all.current_num_threads()),
+        "speedup"
+    );
+    for n in sizes {
+        let mut judge = LocalJudge::new("random", n, n as u64);
+        let mut rng = StdRng::seed_from_u64(n as u64);
+        let plans = vec![
+            (0..18 * n)
+                .map(|_| rng.random_range(0..6))
+                .collect::<Vec<_>>(),
+        ];
+        let steps: Vec<Vec<_>> = plans
+            .iter()
+            .map(|p| p.iter().map(|&d| (None, d)).collect())
+            .collect();
+        let labels = judge.explore(&steps);
+        let (t1, clauses) = fastest(&single, n, &plans, &labels);
+        let (tn, _) = fastest(&all, n, &plans, &labels);
+        println!(
+            "{:>5} {:>10} {:>9.2}s {:>9.2}s {:>7.2}x",
+            n,
+            clauses,
+            t1.as_secs_f64(),
+            tn.as_secs_f64(),
+            t1.as_secs_f64() / tn.as_secs_f64()
+        );
+    }
+}
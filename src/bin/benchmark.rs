@@ -0,0 +1,350 @@
+//! Solver benchmark.
+//!
+//! Runs solvers of the `solvers` registry against `LocalJudge` maps over every combination
+//! of `--solvers`, `--generators`, `--sizes` and seeds, several at a time.
+//! Each run is a child process of this binary (`--run`), so a run that
+//! exceeds `--timeout-secs` is killed rather than left spinning. The report
+//! has one row per solver, generator and size with the success rate, the
+//! mean query cost of the solved runs and the wall time; `--format json`
+//! adds every run.
+//!
+//! Example:
+//!   benchmark --solvers no-marks,anneal --sizes 6,12,18 --seeds 20
+//!   benchmark --solvers layered2 --generators random_2layers --sizes 12 --format json
+
+use anyhow::{Context, Result, bail};
+use clap::{Parser, ValueEnum};
+use itertools::Itertools;
+use rayon::prelude::*;
+use serde::{Deserialize, Serialize};
+use std::io::Read;
+use std::process::{Command, Stdio};
+use std::time::{Duration, Instant};
+
+use icfpc2025::judge::LocalJudge;
+use icfpc2025::solvers;
+
+#[derive(Parser, Debug)]
+#[command(name = "benchmark", about = "Compare solvers on local maps")]
+struct Args {
+    /// Solvers to run (comma separated; all if omitted)
+    #[arg(long, value_delimiter = ',')]
+    solvers: Vec<String>,
+    /// `LocalJudge` map generators (comma separated)
+    #[arg(long, value_delimiter = ',', default_value = "random2")]
+    generators: Vec<String>,
+    /// Numbers of rooms (comma separated)
+    #[arg(long, value_delimiter = ',', default_value = "6,12")]
+    sizes: Vec<usize>,
+    /// Seeds per solver, generator and size
+    #[arg(long, default_value_t = 10)]
+    seeds: u64,
+    #[arg(long = "first-seed", default_value_t = 0)]
+    first_seed: u64,
+    /// Seconds before a run is killed and counted as a timeout
+    #[arg(long = "timeout-secs", default_value_t = 60.0)]
+    timeout_secs: f64,
+    /// Runs in parallel (default: number of CPUs)
+    #[arg(long)]
+    jobs: Option<usize>,
+    #[arg(long, value_enum, default_value_t = Format::Csv)]
+    format: Format,
+    /// Write the report here instead of stdout
+    #[arg(long)]
+    output: Option<String>,
+    /// Show the solvers' stderr
+    #[arg(long)]
+    verbose: bool,
+    /// Run a single SOLVER,GENERATOR,SIZE,SEED and print its outcome (used
+    /// for the child processes)
+    #[arg(long, hide = true)]
+    run: Option<String>,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, ValueEnum)]
+enum Format {
+    Csv,
+    Json,
+}
+
+/// Prefix of the outcome line a child prints to stdout.
+const OUTCOME_PREFIX: &str = "<BENCHMARK>:";
+
+/// What a child reports about its run.
+#[derive(Debug, Serialize, Deserialize)]
+struct Outcome {
+    status: Status,
+    cost: usize,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+enum Status {
+    /// The guess is the map.
+    Solved,
+    /// The guess is wrong.
+    Wrong,
+    /// The solver gave up.
+    Failed,
+    /// Killed at the timeout.
+    Timeout,
+    /// The child crashed or reported nothing.
+    Error,
+}
+
+#[derive(Clone, Debug)]
+struct Job {
+    solver: String,
+    generator: String,
+    size: usize,
+    seed: u64,
+}
+
+#[derive(Debug, Serialize)]
+struct Run {
+    solver: String,
+    generator: String,
+    size: usize,
+    seed: u64,
+    status: Status,
+    /// Queries charged, unless the run timed out or crashed.
+    cost: Option<usize>,
+    secs: f64,
+}
+
+#[derive(Debug, Serialize)]
+struct Summary {
+    solver: String,
+    generator: String,
+    size: usize,
+    runs: usize,
+    solved: usize,
+    success_rate: f64,
+    timeouts: usize,
+    /// Mean cost of the solved runs.
+    mean_cost: Option<f64>,
+    mean_secs: f64,
+    max_secs: f64,
+}
+
+fn main() {
+    if let Err(e) = run() {
+        eprintln!("{:#}", e);
+        std::process::exit(1);
+    }
+}
+
+fn run() -> Result<()> {
+    let args = Args::parse();
+    if let Some(spec) = &args.run {
+        return run_child(spec);
+    }
+    let solvers = if args.solvers.is_empty() {
+        solvers::SOLVERS
+            .iter()
+            .map(|s| s.name.to_string())
+            .collect()
+    } else {
+        args.solvers.clone()
+    };
+    for solver in &solvers {
+        if solvers::find(solver).is_none() {
+            bail!(
+                "unknown solver: {}\nknown solvers:\n{}",
+                solver,
+                solvers::list()
+            );
+        }
+    }
+    let jobs = solvers
+        .iter()
+        .cartesian_product(&args.generators)
+        .cartesian_product(&args.sizes)
+        .cartesian_product(args.first_seed..args.first_seed + args.seeds)
+        .map(|(((solver, generator), &size), seed)| Job {
+            solver: solver.clone(),
+            generator: generator.clone(),
+            size,
+            seed,
+        })
+        .collect_vec();
+    let exe = std::env::current_exe().context("cannot locate the benchmark binary")?;
+    let timeout = Duration::from_secs_f64(args.timeout_secs);
+    let pool = rayon::ThreadPoolBuilder::new()
+        .num_threads(args.jobs.unwrap_or(0))
+        .build()?;
+    let runs: Vec<Run> = pool.install(|| {
+        jobs.par_iter()
+            .map(|job| {
+                let run = spawn_run(&exe, job, timeout, args.verbose);
+                eprintln!(
+                    "{} {} n={} seed={}: {:?} ({:.1}s)",
+                    run.solver, run.generator, run.size, run.seed, run.status, run.secs
+                );
+                run
+            })
+            .collect()
+    });
+    let summaries = summarize(&runs);
+
+    let report = match args.format {
+        Format::Csv => to_csv(&summaries),
+        Format::Json => {
+            serde_json::to_string_pretty(&serde_json::json!({
+                "summary": summaries,
+                "runs": runs,
+            }))? + "\n"
+        }
+    };
+    match &args.output {
+        Some(path) => {
+            std::fs::write(path, report).with_context(|| format!("failed to write {}", path))?
+        }
+        None => print!("{}", report),
+    }
+    Ok(())
+}
+
+/// Runs one job in this process and prints its [`Outcome`].
+fn run_child(spec: &str) -> Result<()> {
+    let parts = spec.split(',').collect_vec();
+    let [solver, generator, size, seed] = parts[..] else {
+        bail!("--run expects SOLVER,GENERATOR,SIZE,SEED: {}", spec);
+    };
+    let solver = solvers::find(solver).with_context(|| format!("unknown solver: {}", solver))?;
+    let mut judge = LocalJudge::new(generator, size.parse()?, seed.parse()?);
+    let status = if (solver.run)(&mut judge) {
+        Status::Solved
+    } else if judge.guesses_made() > 0 {
+        Status::Wrong
+    } else {
+        Status::Failed
+    };
+    let outcome = Outcome {
+        status,
+        cost: judge.cost(),
+    };
+    println!("{}{}", OUTCOME_PREFIX, serde_json::to_string(&outcome)?);
+    Ok(())
+}
+
+/// Runs `job` in a child process, killing it after `timeout`.
+fn spawn_run(exe: &std::path::Path, job: &Job, timeout: Duration, verbose: bool) -> Run {
+    let start = Instant::now();
+    let spec = format!("{},{},{},{}", job.solver, job.generator, job.size, job.seed);
+    let (status, cost) = match child_outcome(exe, &spec, timeout, verbose) {
+        Ok(Some(outcome)) => (outcome.status, Some(outcome.cost)),
+        Ok(None) => (Status::Timeout, None),
+        Err(e) => {
+            eprintln!("{}: {:#}", spec, e);
+            (Status::Error, None)
+        }
+    };
+    Run {
+        solver: job.solver.clone(),
+        generator: job.generator.clone(),
+        size: job.size,
+        seed: job.seed,
+        status,
+        cost,
+        secs: start.elapsed().as_secs_f64(),
+    }
+}
+
+/// The outcome the child printed, or `None` if it timed out.
+fn child_outcome(
+    exe: &std::path::Path,
+    spec: &str,
+    timeout: Duration,
+    verbose: bool,
+) -> Result<Option<Outcome>> {
+    let mut child = Command::new(exe)
+        .args(["--run", spec])
+        .stdout(Stdio::piped())
+        .stderr(if verbose {
+            Stdio::inherit()
+        } else {
+            Stdio::null()
+        })
+        .spawn()?;
+    // Drain stdout while waiting so a chatty solver cannot block on the pipe.
+    let mut stdout = child.stdout.take().unwrap();
+    let reader = std::thread::spawn(move || {
+        let mut out = String::new();
+        stdout.read_to_string(&mut out).map(|_| out)
+    });
+    let deadline = Instant::now() + timeout;
+    let status = loop {
+        if let Some(status) = child.try_wait()? {
+            break status;
+        }
+        if Instant::now() >= deadline {
+            let _ = child.kill();
+            let _ = child.wait();
+            return Ok(None);
+        }
+        std::thread::sleep(Duration::from_millis(20));
+    };
+    let out = reader.join().unwrap()?;
+    if !status.success() {
+        bail!("exited with {}", status);
+    }
+    let line = out
+        .lines()
+        .rev()
+        .find_map(|l| l.strip_prefix(OUTCOME_PREFIX))
+        .context("no outcome reported")?;
+    Ok(Some(serde_json::from_str(line)?))
+}
+
+fn summarize(runs: &[Run]) -> Vec<Summary> {
+    let groups = runs
+        .iter()
+        .into_group_map_by(|r| (r.solver.clone(), r.generator.clone(), r.size));
+    groups
+        .into_iter()
+        .sorted_by(|a, b| a.0.cmp(&b.0))
+        .map(|((solver, generator, size), runs)| {
+            let solved = runs
+                .iter()
+                .filter(|r| r.status == Status::Solved)
+                .collect_vec();
+            let costs = solved.iter().filter_map(|r| r.cost).collect_vec();
+            Summary {
+                solver,
+                generator,
+                size,
+                runs: runs.len(),
+                solved: solved.len(),
+                success_rate: solved.len() as f64 / runs.len() as f64,
+                timeouts: runs.iter().filter(|r| r.status == Status::Timeout).count(),
+                mean_cost: (!costs.is_empty())
+                    .then(|| costs.iter().sum::<usize>() as f64 / costs.len() as f64),
+                mean_secs: runs.iter().map(|r| r.secs).sum::<f64>() / runs.len() as f64,
+                max_secs: runs.iter().map(|r| r.secs).fold(0.0, f64::max),
+            }
+        })
+        .collect()
+}
+
+fn to_csv(summaries: &[Summary]) -> String {
+    let mut out = String::from(
+        "solver,generator,size,runs,solved,success_rate,timeouts,mean_cost,mean_secs,max_secs\n",
+    );
+    for s in summaries {
+        out += &format!(
+            "{},{},{},{},{},{:.3},{},{},{:.3},{:.3}\n",
+            s.solver,
+            s.generator,
+            s.size,
+            s.runs,
+            s.solved,
+            s.success_rate,
+            s.timeouts,
+            s.mean_cost.map_or(String::new(), |c| format!("{:.1}", c)),
+            s.mean_secs,
+            s.max_secs,
+        );
+    }
+    out
+}
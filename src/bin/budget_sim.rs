@@ -0,0 +1,301 @@
+//! Endgame budget simulator.
+//!
+//! Given per-problem success probabilities, run durations and score samples
+//! (taken from the `tasks` table, or from a JSON file dumped earlier with
+//! `--dump-stats`), simulates how the remaining machine time would play out
+//! under different allocation policies and reports the expected outcome.
+//!
+//! Example:
+//!   budget_sim --machines 16 --hours 3 --trials 200
+//!   budget_sim --stats stats.json --policy greedy --machines 8 --hours 1.5
+use anyhow::{Context, Result, bail};
+use clap::{Parser, ValueEnum};
+use icfpc2025::{problems, sql};
+use itertools::Itertools;
+use rand::prelude::*;
+use rand_chacha::ChaCha12Rng;
+use serde::{Deserialize, Serialize};
+
+#[derive(Parser, Debug)]
+#[command(name = "budget_sim", about = "Simulate machine allocation policies")]
+struct Args {
+    /// Number of machines (executors) running in parallel
+    #[arg(long, default_value_t = 8)]
+    machines: usize,
+    /// Remaining contest time in hours
+    #[arg(long, default_value_t = 1.0)]
+    hours: f64,
+    /// Number of Monte Carlo trials per policy
+    #[arg(long, default_value_t = 100)]
+    trials: usize,
+    #[arg(long, default_value_t = 0)]
+    seed: u64,
+    /// Policies to compare (all if omitted)
+    #[arg(long, value_enum)]
+    policy: Vec<Policy>,
+    /// Read stats from a JSON file instead of the database
+    #[arg(long)]
+    stats: Option<String>,
+    /// Write the stats used for the simulation to a JSON file
+    #[arg(long = "dump-stats")]
+    dump_stats: Option<String>,
+    /// Only consider these problems (comma separated)
+    #[arg(long, value_delimiter = ',')]
+    problems: Vec<String>,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, ValueEnum)]
+enum Policy {
+    /// Round-robin over all problems
+    Uniform,
+    /// Round-robin over problems without any successful run, then uniform
+    Unsolved,
+    /// Pick the problem with the largest expected improvement per second
+    Greedy,
+}
+
+/// Observed statistics of one problem.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+struct ProblemStats {
+    problem: String,
+    runs: u64,
+    successes: u64,
+    /// Average wall time of a run in seconds.
+    avg_duration_s: f64,
+    /// Scores (query counts) of successful runs.
+    scores: Vec<i64>,
+}
+
+impl ProblemStats {
+    /// Success probability with Laplace smoothing, so unseen problems are not
+    /// treated as hopeless.
+    fn success_rate(&self) -> f64 {
+        (self.successes as f64 + 1.0) / (self.runs as f64 + 2.0)
+    }
+
+    fn best(&self) -> Option<i64> {
+        self.scores.iter().copied().min()
+    }
+
+    /// Expected score improvement of one more run given the current best.
+    fn expected_gain(&self, best: Option<i64>) -> f64 {
+        let p = self.success_rate();
+        match best {
+            // Solving an unsolved problem dominates any score improvement.
+            None => p * UNSOLVED_BONUS,
+            Some(best) if !self.scores.is_empty() => {
+                let gain: i64 = self.scores.iter().map(|&s| (best - s).max(0)).sum();
+                p * gain as f64 / self.scores.len() as f64
+            }
+            Some(_) => 0.0,
+        }
+    }
+
+    fn sample_score(&self, rng: &mut impl Rng) -> i64 {
+        match self.scores.choose(rng) {
+            Some(&s) => s,
+            // No sample yet: assume a modest query count for the problem size.
+            None => problems::get_problem(&self.problem)
+                .map(|p| p.size as i64 * 2)
+                .unwrap_or(100),
+        }
+    }
+}
+
+const UNSOLVED_BONUS: f64 = 1e6;
+
+fn main() {
+    if let Err(e) = run() {
+        eprintln!("budget_sim error: {e}");
+        std::process::exit(1);
+    }
+}
+
+fn run() -> Result<()> {
+    let args = Args::parse();
+    if args.machines == 0 || args.hours <= 0.0 {
+        bail!("--machines and --hours must be positive");
+    }
+
+    let mut stats = match &args.stats {
+        Some(path) => {
+            let text = std::fs::read_to_string(path)
+                .with_context(|| format!("failed to read stats file: {}", path))?;
+            serde_json::from_str::<Vec<ProblemStats>>(&text)?
+        }
+        None => load_stats_from_db()?,
+    };
+    if !args.problems.is_empty() {
+        stats.retain(|s| args.problems.contains(&s.problem));
+    }
+    if stats.is_empty() {
+        bail!("no problem stats available");
+    }
+    if let Some(path) = &args.dump_stats {
+        std::fs::write(path, serde_json::to_string_pretty(&stats)?)?;
+    }
+
+    eprintln!(
+        "{:<10} {:>6} {:>6} {:>8} {:>8}",
+        "problem", "runs", "succ", "avg_sec", "best"
+    );
+    for s in &stats {
+        eprintln!(
+            "{:<10} {:>6} {:>6} {:>8.1} {:>8}",
+            s.problem,
+            s.runs,
+            s.successes,
+            s.avg_duration_s,
+            s.best()
+                .map(|b| b.to_string())
+                .unwrap_or_else(|| "-".into())
+        );
+    }
+
+    let policies = if args.policy.is_empty() {
+        Policy::value_variants().to_vec()
+    } else {
+        args.policy.clone()
+    };
+    let horizon = args.hours * 3600.0;
+    println!(
+        "{:<10} {:>10} {:>10} {:>12} {:>12}",
+        "policy", "solved", "improved", "score_sum", "runs"
+    );
+    for policy in policies {
+        let mut rng = ChaCha12Rng::seed_from_u64(args.seed);
+        let outcomes = (0..args.trials)
+            .map(|_| simulate(&stats, policy, args.machines, horizon, &mut rng))
+            .collect_vec();
+        let n = outcomes.len() as f64;
+        let mean = |f: fn(&Outcome) -> f64| outcomes.iter().map(f).sum::<f64>() / n;
+        println!(
+            "{:<10} {:>10.2} {:>10.2} {:>12.1} {:>12.1}",
+            format!("{:?}", policy).to_lowercase(),
+            mean(|o| o.solved as f64),
+            mean(|o| o.improved as f64),
+            mean(|o| o.score_sum as f64),
+            mean(|o| o.runs as f64),
+        );
+    }
+    Ok(())
+}
+
+/// Result of one simulated endgame.
+struct Outcome {
+    /// Number of problems with at least one successful run at the end.
+    solved: usize,
+    /// Number of problems whose best score improved during the simulation.
+    improved: usize,
+    /// Sum of best scores over solved problems (lower is better).
+    score_sum: i64,
+    runs: usize,
+}
+
+fn simulate(
+    stats: &[ProblemStats],
+    policy: Policy,
+    machines: usize,
+    horizon: f64,
+    rng: &mut impl Rng,
+) -> Outcome {
+    let initial = stats.iter().map(|s| s.best()).collect_vec();
+    let mut best = initial.clone();
+    let mut free_at = vec![0.0f64; machines];
+    let mut cursor = 0;
+    let mut runs = 0;
+    loop {
+        let (m, &t) = free_at
+            .iter()
+            .enumerate()
+            .min_by(|a, b| a.1.total_cmp(b.1))
+            .unwrap();
+        let i = match policy {
+            Policy::Uniform => {
+                cursor += 1;
+                (cursor - 1) % stats.len()
+            }
+            Policy::Unsolved => {
+                let unsolved = (0..stats.len())
+                    .filter(|&i| best[i].is_none())
+                    .collect_vec();
+                cursor += 1;
+                if unsolved.is_empty() {
+                    (cursor - 1) % stats.len()
+                } else {
+                    unsolved[(cursor - 1) % unsolved.len()]
+                }
+            }
+            Policy::Greedy => (0..stats.len())
+                .max_by(|&a, &b| {
+                    let ga = stats[a].expected_gain(best[a]) / stats[a].avg_duration_s.max(1.0);
+                    let gb = stats[b].expected_gain(best[b]) / stats[b].avg_duration_s.max(1.0);
+                    ga.total_cmp(&gb)
+                })
+                .unwrap(),
+        };
+        // Run times fluctuate around the observed average.
+        let duration = stats[i].avg_duration_s.max(1.0) * rng.random_range(0.5..1.5);
+        if t + duration > horizon {
+            free_at[m] = f64::INFINITY;
+            if free_at.iter().all(|x| x.is_infinite()) {
+                break;
+            }
+            continue;
+        }
+        free_at[m] = t + duration;
+        runs += 1;
+        if rng.random_bool(stats[i].success_rate()) {
+            let s = stats[i].sample_score(rng);
+            if best[i].is_none_or(|b| s < b) {
+                best[i] = Some(s);
+            }
+        }
+    }
+    Outcome {
+        solved: best.iter().filter(|b| b.is_some()).count(),
+        improved: (0..stats.len()).filter(|&i| best[i] != initial[i]).count(),
+        score_sum: best.iter().flatten().sum(),
+        runs,
+    }
+}
+
+/// Aggregates finished executor tasks per problem.
+fn load_stats_from_db() -> Result<Vec<ProblemStats>> {
+    let rows = sql::select(
+        r#"
+        SELECT problem_name,
+               COUNT(*) AS runs,
+               SUM(task_score IS NOT NULL) AS successes,
+               AVG(task_duration_ms) AS avg_duration_ms
+        FROM tasks
+        WHERE task_locked IS NULL
+          AND task_duration_ms IS NOT NULL
+        GROUP BY problem_name
+        ORDER BY problem_name
+        "#,
+        (),
+    )?;
+    let mut stats = Vec::new();
+    for row in rows {
+        let problem: String = row.get("problem_name")?;
+        let avg_ms: f64 = row.get_option("avg_duration_ms")?.unwrap_or(0.0);
+        stats.push(ProblemStats {
+            problem,
+            runs: row.get("runs")?,
+            successes: row.get_option("successes")?.unwrap_or(0),
+            avg_duration_s: avg_ms / 1000.0,
+            scores: Vec::new(),
+        });
+    }
+    for row in sql::select(
+        "SELECT problem_name, task_score FROM tasks WHERE task_score IS NOT NULL",
+        (),
+    )? {
+        let problem: String = row.get("problem_name")?;
+        if let Some(s) = stats.iter_mut().find(|s| s.problem == problem) {
+            s.scores.push(row.get("task_score")?);
+        }
+    }
+    Ok(stats)
+}
@@ -77,13 +77,15 @@ fn main() {
         }
     }
 
+    let mut rnd = rand::rng();
+    let mut controller = RestartController::new();
     loop {
-        //ランダムにlabelを割り当てる
-        let mut ans = vec![0; m.label.len()];
-        let mut rnd = rand::rng();
-        for i in 0..m.door.len() {
-            ans[i] = label_start[m.label[i]] + rnd.random_range(0..nums[m.label[i]]);
-        }
+        //初期のlabel割り当て: 遷移統計からの事後確率に比例してサンプルし、エントロピーが目標に近いものを使う
+        let (mut ans, entropy) = controller.next_seed(&m, &label_start, &nums, &mut rnd);
+        eprintln!(
+            "restart #{}: entropy = {:.3}, target = {:.3}, stall_cap = {}",
+            controller.restarts, entropy, controller.target_entropy, controller.stall_cap
+        );
 
         let mut loop_cnt = 0;
         let mut wrong = error_check(&ans, &m, n);
@@ -92,6 +94,8 @@ fn main() {
         let mut not_update = 0;
         let mut best_ans = ans.clone();
         let mut best_to = to.clone();
+        let mut best_at_shake = best_wrong;
+        let mut futile_shakes = 0;
 
         loop {
             loop_cnt += 1;
@@ -162,7 +166,7 @@ fn main() {
                 break;
             }
 
-            if not_update >= 20000 {
+            if not_update >= controller.stall_cap {
                 //toだけで上手く行くか一応チェックする
                 let (wrong2, new_ans) = to_check(&ans, &label_id, &to, &m);
                 if wrong2 == 0 {
@@ -175,6 +179,18 @@ fn main() {
                     //eprint!("to_check wrong: {}\n", wrong2);
                 }
 
+                //前回のシャッフルから改善したかで打ち切り回数を調整し、改善しない状態が続いたらリスタート
+                controller.after_shake(best_wrong < best_at_shake);
+                if best_wrong < best_at_shake {
+                    futile_shakes = 0;
+                } else {
+                    futile_shakes += 1;
+                }
+                best_at_shake = best_wrong;
+                if futile_shakes >= MAX_FUTILE_SHAKES {
+                    break;
+                }
+
                 //いったん最強解に戻す
                 ans = best_ans.clone();
                 to = best_to.clone();
@@ -213,6 +229,7 @@ fn main() {
                 break;
             }
         }
+        controller.after_restart(entropy, best_wrong);
         if wrong.0 != 0 {
             eprintln!("error count: {}", best_wrong);
             continue;
@@ -368,3 +385,133 @@ fn to_check(
     }
     (wrong, ret_ans)
 }
+
+//シャッフルしても最良解が改善しない回数がこれに達したらリスタートする
+const MAX_FUTILE_SHAKES: usize = 40;
+//初期割り当ての事後確率の鋭さの候補(0は一様)
+const BETAS: [f64; 4] = [0.0, 1.0, 2.0, 4.0];
+
+//リスタートごとの初期割り当てのエントロピーの目標と、シャッフルまでの停滞回数を決める
+struct RestartController {
+    restarts: usize,
+    //この回数最良解が更新されなければシャッフルする
+    stall_cap: usize,
+    //初期割り当ての平均正規化エントロピーの目標(1なら一様、0なら決定的)
+    target_entropy: f64,
+    //これまでのリスタートの最良エラーの指数移動平均
+    mean_wrong: Option<f64>,
+}
+
+impl RestartController {
+    fn new() -> Self {
+        Self {
+            restarts: 0,
+            stall_cap: 20000,
+            target_entropy: 1.0,
+            mean_wrong: None,
+        }
+    }
+
+    //各 beta で1つずつサンプルし、エントロピーが目標に最も近い割り当てを返す
+    fn next_seed(
+        &mut self,
+        m: &Moves,
+        label_start: &[usize],
+        nums: &[usize],
+        rnd: &mut impl Rng,
+    ) -> (Vec<usize>, f64) {
+        self.restarts += 1;
+        BETAS
+            .iter()
+            .map(|&beta| sample_initial(m, label_start, nums, beta, rnd))
+            .min_by(|a, b| {
+                (a.1 - self.target_entropy)
+                    .abs()
+                    .total_cmp(&(b.1 - self.target_entropy).abs())
+            })
+            .unwrap()
+    }
+
+    //改善が続いているなら長く粘り、止まっているなら早めにシャッフルする
+    fn after_shake(&mut self, improved: bool) {
+        if improved {
+            self.stall_cap = (self.stall_cap * 3 / 2).min(200000);
+        } else {
+            self.stall_cap = (self.stall_cap * 4 / 5).max(5000);
+        }
+    }
+
+    //平均より良かった回のエントロピーには目標を近づけ、悪かった回のエントロピーからは遠ざける。
+    //初回は一様(エントロピー1)から始めるので、まず鋭い側を試すよう少し下げる
+    fn after_restart(&mut self, entropy: f64, best_wrong: usize) {
+        let wrong = best_wrong as f64;
+        match self.mean_wrong {
+            None => {
+                self.target_entropy = (self.target_entropy - 0.2).max(0.0);
+                self.mean_wrong = Some(wrong);
+            }
+            Some(mean) => {
+                let diff = entropy - self.target_entropy;
+                let step = if wrong < mean { 0.5 } else { -0.25 };
+                self.target_entropy = (self.target_entropy + step * diff).clamp(0.0, 1.0);
+                self.mean_wrong = Some(0.8 * mean + 0.2 * wrong);
+            }
+        }
+    }
+}
+
+//各時刻の部屋を、同じlabelの部屋の中から「その部屋でこれまでに観測された(door, 次のlabel)」の頻度に
+//比例した確率で順にサンプルする。beta で鋭さを調整し、0なら一様。割り当ての平均正規化エントロピーも返す
+fn sample_initial(
+    m: &Moves,
+    label_start: &[usize],
+    nums: &[usize],
+    beta: f64,
+    rnd: &mut impl Rng,
+) -> (Vec<usize>, f64) {
+    let n = label_start[3] + nums[3];
+    //profile[r][door][next_label]
+    let mut profile = vec![[[0.0f64; 4]; 6]; n];
+    let mut ans = vec![0; m.label.len()];
+    let mut entropy_sum = 0.0;
+    let mut weights = vec![];
+    for i in 0..m.label.len() {
+        let l = m.label[i];
+        weights.clear();
+        for r in label_start[l]..label_start[l] + nums[l] {
+            let w = if i < m.door.len() {
+                let d = m.door[i];
+                let total: f64 = profile[r][d].iter().sum();
+                //未観測のドアは中立(1/4)、観測済みなら次のlabelとの一致度
+                ((profile[r][d][m.label[i + 1]] + 0.25) / (total + 1.0)).powf(beta)
+            } else {
+                1.0
+            };
+            weights.push(w);
+        }
+        let total: f64 = weights.iter().sum();
+        if nums[l] > 1 {
+            let h: f64 = weights
+                .iter()
+                .map(|&w| w / total)
+                .filter(|&p| p > 0.0)
+                .map(|p| -p * p.ln())
+                .sum();
+            entropy_sum += h / (nums[l] as f64).ln();
+        }
+        let mut x = rnd.random_range(0.0..total);
+        let mut k = weights.len() - 1;
+        for (j, &w) in weights.iter().enumerate() {
+            if x < w {
+                k = j;
+                break;
+            }
+            x -= w;
+        }
+        ans[i] = label_start[l] + k;
+        if i < m.door.len() {
+            profile[ans[i]][m.door[i]][m.label[i + 1]] += 1.0;
+        }
+    }
+    (ans, entropy_sum / m.label.len() as f64)
+}
@@ -15,13 +15,13 @@ fn check_samenum(a: usize, n: usize) -> bool {
 fn main() {
     let mut judge = get_judge_from_stdin_with(false);
     let n = judge.num_rooms();
-    let q_limit = n * 6;
+    let q_limit = judge.max_plan_len();
 
     let mut steps = vec![];
 
     let mut rnd = rand::rng();
 
-    let iwi_vec = icfpc2025::routes::get_plan(n);
+    let iwi_vec = icfpc2025::routes::get_plan(n, q_limit);
 
     let mut qnum = 2;
     let mut q_perf = 12;
@@ -55,7 +55,7 @@ fn main() {
     let mut vec_label = vec![];
     let mut vec_nums = 0;
 
-    let r = judge.explore(&steps);
+    let r = judge.explore_batch(&steps).unwrap();
 
     for i in 0..q_limit + 1 {
         let mut num = 0;
@@ -257,7 +257,7 @@ fn main() {
             }
         }
 
-        let r2 = judge.explore(&step2);
+        let r2 = judge.explore_batch(&step2).unwrap();
 
         //graphの更新
         for i in dstart..q_limit {
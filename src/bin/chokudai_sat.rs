@@ -321,7 +321,10 @@ fn main() {
 
     // 解けたらうれしいな
     //assert_eq!(cnf.sat.solve(), Some(true));
-    solve_no_marks::solve_cnf_parallel(&mut cnf, 25, 25);
+    if let Err(e) = solve_no_marks::solve_cnf_parallel(&mut cnf, 25, 25) {
+        eprintln!("{:#}", e);
+        std::process::exit(1);
+    }
 
     let mut guess = Guess {
         start: first_room,
@@ -26,8 +26,16 @@ fn run() -> Result<()> {
         match exec::acquire_task()? {
             Some(task) => {
                 // Optionally heartbeat could be added with a separate thread calling extend_lock.
-                let (score, exit_code, duration_ms) = exec::run_task(&task)?;
-                exec::update_task(&task, score, exit_code, duration_ms)?;
+                let (score, exit_code, duration_ms, diagnostics, stderr_tail) =
+                    exec::run_task(&task)?;
+                exec::update_task(
+                    &task,
+                    score,
+                    exit_code,
+                    duration_ms,
+                    &diagnostics,
+                    &stderr_tail,
+                )?;
             }
             None => {
                 thread::sleep(Duration::from_millis(args.sleep_ms));
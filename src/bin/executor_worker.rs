@@ -0,0 +1,133 @@
+//! Long-running executor worker.
+//!
+//! Runs `--concurrency` loops of `acquire_task` / `run_task` / `update_task`
+//! in parallel. Unlike `executor`, a failing database or upload does not end
+//! the process: the error is logged and the loop backs off and carries on.
+//!
+//! SIGTERM or SIGINT starts a graceful drain: no new task is acquired, the
+//! running ones finish and report as usual, and the process exits once they
+//! are done. Under systemd this needs `KillMode=mixed` (and a `TimeoutStopSec`
+//! above the 600 s task timeout), or the tasks' processes are killed along
+//! with the worker.
+
+use anyhow::Result;
+use clap::Parser;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::thread;
+use std::time::{Duration, Instant};
+
+use icfpc2025::executor as exec;
+
+#[derive(Parser, Debug)]
+#[command(
+    name = "executor_worker",
+    about = "Task executor with parallel workers"
+)]
+struct Args {
+    /// Number of tasks run in parallel
+    #[arg(long, default_value_t = 1)]
+    concurrency: usize,
+    /// Sleep milliseconds when no task is available
+    #[arg(long = "sleep-ms", default_value_t = 1000)]
+    sleep_ms: u64,
+    /// Sleep milliseconds after an error before trying again
+    #[arg(long = "error-sleep-ms", default_value_t = 10000)]
+    error_sleep_ms: u64,
+    /// Let each worker try to acquire one task, run it and exit
+    #[arg(long)]
+    once: bool,
+}
+
+/// Set by SIGTERM / SIGINT.
+static STOP: AtomicBool = AtomicBool::new(false);
+
+extern "C" fn on_signal(_: libc::c_int) {
+    STOP.store(true, Ordering::SeqCst);
+}
+
+fn install_signal_handlers() {
+    for sig in [libc::SIGTERM, libc::SIGINT] {
+        // SAFETY: the handler only stores to an atomic, which is
+        // async-signal-safe.
+        unsafe {
+            libc::signal(sig, on_signal as *const () as libc::sighandler_t);
+        }
+    }
+}
+
+/// Sleeps for `duration`, waking up early on a stop signal.
+fn sleep_unless_stopped(duration: Duration) {
+    let end = Instant::now() + duration;
+    while !STOP.load(Ordering::SeqCst) {
+        let now = Instant::now();
+        if now >= end {
+            break;
+        }
+        thread::sleep((end - now).min(Duration::from_millis(100)));
+    }
+}
+
+/// Acquires and runs one task. Returns whether there was one.
+fn step() -> Result<bool> {
+    let Some(task) = exec::acquire_task()? else {
+        return Ok(false);
+    };
+    let (score, exit_code, duration_ms, diagnostics, stderr_tail) = exec::run_task(&task)?;
+    exec::update_task(
+        &task,
+        score,
+        exit_code,
+        duration_ms,
+        &diagnostics,
+        &stderr_tail,
+    )?;
+    Ok(true)
+}
+
+fn worker(id: usize, args: &Args) -> Result<()> {
+    while !STOP.load(Ordering::SeqCst) {
+        match step() {
+            Ok(_) if args.once => return Ok(()),
+            Ok(true) => {}
+            Ok(false) => sleep_unless_stopped(Duration::from_millis(args.sleep_ms)),
+            Err(e) if args.once => return Err(e),
+            Err(e) => {
+                eprintln!("[executor] worker {} error: {:#}", id, e);
+                sleep_unless_stopped(Duration::from_millis(args.error_sleep_ms));
+            }
+        }
+    }
+    eprintln!("[executor] worker {} stopped", id);
+    Ok(())
+}
+
+fn main() {
+    let args = Args::parse();
+    if args.concurrency == 0 {
+        eprintln!("--concurrency must be at least 1");
+        std::process::exit(2);
+    }
+    install_signal_handlers();
+    let failed = thread::scope(|s| {
+        let handles = (0..args.concurrency)
+            .map(|id| {
+                let args = &args;
+                s.spawn(move || worker(id, args))
+            })
+            .collect::<Vec<_>>();
+        let mut failed = false;
+        for handle in handles {
+            if let Err(e) = handle.join().expect("worker panicked") {
+                eprintln!("{:#}", e);
+                failed = true;
+            }
+        }
+        failed
+    });
+    if STOP.load(Ordering::SeqCst) {
+        eprintln!("[executor] drained after stop signal");
+    }
+    if failed {
+        std::process::exit(1);
+    }
+}
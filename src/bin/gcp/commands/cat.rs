@@ -1,6 +1,16 @@
 use anyhow::{Context, Result, bail};
+use std::io::Write;
 
-pub async fn run(url: &str) -> Result<()> {
+/// Block size used when scanning an object backwards for `--tail`.
+const TAIL_BLOCK: u64 = 1 << 20;
+
+pub async fn run(
+    url: &str,
+    head: Option<usize>,
+    tail: Option<usize>,
+    range: Option<&str>,
+    jsonl: bool,
+) -> Result<()> {
     let (bucket, object) = icfpc2025::gcp::gcs::parse_gs_url(url)?;
     if object.is_empty() || object.ends_with('/') {
         bail!(
@@ -8,11 +18,164 @@ pub async fn run(url: &str) -> Result<()> {
             url
         );
     }
-    let bytes = icfpc2025::gcp::gcs::download_object(&bucket, &object)
-        .await
-        .with_context(|| format!("Failed to download gs://{}/{}", bucket, object))?;
-    use std::io::Write;
-    let mut out = std::io::stdout().lock();
-    out.write_all(&bytes)?;
+    if head == Some(0) {
+        return Ok(());
+    }
+    let mut out = Output {
+        out: std::io::stdout().lock(),
+        jsonl: jsonl.then(Vec::new),
+    };
+    if let Some(n) = tail {
+        cat_tail(&bucket, &object, n, &mut out).await?;
+        return out.finish();
+    }
+    let (start, end) = match range {
+        Some(spec) => resolve_range(&bucket, &object, spec).await?,
+        None => (0, None),
+    };
+
+    let mut remaining = head;
+    icfpc2025::gcp::gcs::stream_object(&bucket, &object, start, end, |chunk| {
+        let Some(n) = remaining.as_mut() else {
+            out.write(chunk)?;
+            return Ok(true);
+        };
+        // Emit up to and including the n-th newline, then stop.
+        for (i, _) in chunk.iter().enumerate().filter(|&(_, &b)| b == b'\n') {
+            *n -= 1;
+            if *n == 0 {
+                out.write(&chunk[..=i])?;
+                return Ok(false);
+            }
+        }
+        out.write(chunk)?;
+        Ok(*n > 0)
+    })
+    .await
+    .with_context(|| format!("Failed to download gs://{}/{}", bucket, object))?;
+    out.finish()
+}
+
+/// Resolves a `--range` spec to `start..end` byte offsets. `START-END` is
+/// inclusive as in an HTTP `Range` header, `START-` runs to the end of the
+/// object and `-N` is its last N bytes.
+async fn resolve_range(bucket: &str, object: &str, spec: &str) -> Result<(u64, Option<u64>)> {
+    let (start, end) = spec
+        .split_once('-')
+        .with_context(|| format!("Invalid range (expected START-END): {}", spec))?;
+    let parse = |s: &str| -> Result<u64> {
+        s.trim()
+            .parse()
+            .with_context(|| format!("Invalid range offset: {:?}", s))
+    };
+    match (start.trim().is_empty(), end.trim().is_empty()) {
+        (true, true) => bail!("Invalid range: {}", spec),
+        (true, false) => {
+            let size = object_size(bucket, object).await?;
+            Ok((size.saturating_sub(parse(end)?), None))
+        }
+        (false, true) => Ok((parse(start)?, None)),
+        (false, false) => {
+            let (start, end) = (parse(start)?, parse(end)?);
+            if end < start {
+                bail!("Invalid range (END before START): {}", spec);
+            }
+            Ok((start, Some(end + 1)))
+        }
+    }
+}
+
+async fn object_size(bucket: &str, object: &str) -> Result<u64> {
+    let meta = icfpc2025::gcp::gcs::get_object_metadata(bucket, object).await?;
+    meta.size
+        .as_deref()
+        .and_then(|s| s.parse().ok())
+        .context("Object size is unknown")
+}
+
+/// Stdout, optionally pretty-printing each line that parses as JSON
+/// (`--jsonl`). Lines that do not parse, such as the cut-off first line of a
+/// `--range`, are printed as they are.
+struct Output<'a> {
+    out: std::io::StdoutLock<'a>,
+    /// The incomplete last line so far, in `--jsonl` mode.
+    jsonl: Option<Vec<u8>>,
+}
+
+impl Output<'_> {
+    fn write(&mut self, chunk: &[u8]) -> Result<()> {
+        let Some(pending) = self.jsonl.as_mut() else {
+            self.out.write_all(chunk)?;
+            return Ok(());
+        };
+        pending.extend_from_slice(chunk);
+        if let Some(last) = pending.iter().rposition(|&b| b == b'\n') {
+            let rest = pending.split_off(last + 1);
+            let lines = std::mem::replace(pending, rest);
+            for line in lines.split_inclusive(|&b| b == b'\n') {
+                write_jsonl_line(&mut self.out, line)?;
+            }
+        }
+        Ok(())
+    }
+
+    fn finish(mut self) -> Result<()> {
+        if let Some(pending) = self.jsonl.take()
+            && !pending.is_empty()
+        {
+            write_jsonl_line(&mut self.out, &pending)?;
+        }
+        self.out.flush()?;
+        Ok(())
+    }
+}
+
+fn write_jsonl_line(out: &mut impl Write, line: &[u8]) -> Result<()> {
+    match serde_json::from_slice::<serde_json::Value>(line) {
+        Ok(value) if !line.trim_ascii().is_empty() => {
+            serde_json::to_writer_pretty(&mut *out, &value)?;
+            out.write_all(b"\n")?;
+        }
+        _ => out.write_all(line)?,
+    }
     Ok(())
 }
+
+/// Prints the last `n` lines of an object by fetching fixed-size blocks from the
+/// end until enough newlines have been seen.
+async fn cat_tail(bucket: &str, object: &str, n: usize, out: &mut Output<'_>) -> Result<()> {
+    let size = object_size(bucket, object).await?;
+
+    let mut end = size;
+    let mut buf: Vec<u8> = Vec::new();
+    while end > 0 {
+        let start = end.saturating_sub(TAIL_BLOCK);
+        let mut block =
+            icfpc2025::gcp::gcs::download_object_range(bucket, object, start, Some(end))
+                .await
+                .with_context(|| format!("Failed to download gs://{}/{}", bucket, object))?;
+        block.extend_from_slice(&buf);
+        buf = block;
+        end = start;
+        if tail_start(&buf, n).is_some() {
+            break;
+        }
+    }
+    let from = tail_start(&buf, n).unwrap_or(0);
+    out.write(&buf[from..])
+}
+
+/// Returns the offset in `buf` where its last `n` lines begin, or `None` if `buf`
+/// holds fewer than `n` complete lines. A trailing newline does not start a new line.
+fn tail_start(buf: &[u8], n: usize) -> Option<usize> {
+    if n == 0 {
+        return Some(buf.len());
+    }
+    let body = buf.strip_suffix(b"\n").unwrap_or(buf);
+    body.iter()
+        .enumerate()
+        .rev()
+        .filter(|&(_, &b)| b == b'\n')
+        .nth(n - 1)
+        .map(|(i, _)| i + 1)
+}
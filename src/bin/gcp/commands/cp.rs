@@ -0,0 +1,106 @@
+use anyhow::{Context, Result, bail};
+use std::io::Write;
+use std::path::{Path, PathBuf};
+
+pub async fn run(src: &str, dst: &str) -> Result<()> {
+    match (src.starts_with("gs://"), dst.starts_with("gs://")) {
+        (false, true) => upload(src, dst).await,
+        (true, false) => download(src, dst).await,
+        (true, true) => {
+            let (src_bucket, src_object) = object_url(src)?;
+            let (dst_bucket, dst_object) = icfpc2025::gcp::gcs::parse_gs_url(dst)?;
+            let dst_object = join_object(&dst_object, basename(&src_object));
+            icfpc2025::gcp::gcs::copy_object(&src_bucket, &src_object, &dst_bucket, &dst_object)
+                .await
+                .with_context(|| format!("Failed to copy {} to {}", src, dst))?;
+            println!("Copied {} to gs://{}/{}", src, dst_bucket, dst_object);
+            Ok(())
+        }
+        (false, false) => bail!("cp needs a gs:// URL on at least one side"),
+    }
+}
+
+/// Parses a URL that must name an object, not a bucket or prefix.
+fn object_url(url: &str) -> Result<(String, String)> {
+    let (bucket, object) = icfpc2025::gcp::gcs::parse_gs_url(url)?;
+    if object.is_empty() || object.ends_with('/') {
+        bail!(
+            "Expected a full object path, not a bucket or prefix: {}",
+            url
+        );
+    }
+    Ok((bucket, object))
+}
+
+fn basename(path: &str) -> &str {
+    path.rsplit('/').next().unwrap_or(path)
+}
+
+/// A destination ending with `/` (or a bucket) is a directory: the source's
+/// file name is appended, as `cp` does.
+fn join_object(dst: &str, name: &str) -> String {
+    if dst.is_empty() || dst.ends_with('/') {
+        format!("{}{}", dst, name)
+    } else {
+        dst.to_string()
+    }
+}
+
+async fn upload(src: &str, dst: &str) -> Result<()> {
+    let (bucket, object) = icfpc2025::gcp::gcs::parse_gs_url(dst)?;
+    let name = Path::new(src)
+        .file_name()
+        .and_then(|n| n.to_str())
+        .with_context(|| format!("Not a file: {}", src))?;
+    let object = join_object(&object, name);
+    let file = tokio::fs::File::open(src)
+        .await
+        .with_context(|| format!("Failed to open {}", src))?;
+    let item = icfpc2025::gcp::gcs::upload_object_streaming(
+        &bucket,
+        &object,
+        file,
+        "application/octet-stream",
+    )
+    .await
+    .with_context(|| format!("Failed to upload {}", src))?;
+    println!(
+        "Uploaded {} to gs://{}/{} ({} bytes)",
+        src,
+        bucket,
+        object,
+        item.size.as_deref().unwrap_or("?")
+    );
+    Ok(())
+}
+
+async fn download(src: &str, dst: &str) -> Result<()> {
+    let (bucket, object) = object_url(src)?;
+    let mut path = PathBuf::from(dst);
+    if dst.ends_with('/') || path.is_dir() {
+        path.push(basename(&object));
+    }
+    // Written next to the destination and renamed, so that a failed download
+    // does not leave a truncated file behind.
+    let tmp = PathBuf::from(format!("{}.partial", path.display()));
+    let mut file = std::io::BufWriter::new(
+        std::fs::File::create(&tmp)
+            .with_context(|| format!("Failed to create {}", tmp.display()))?,
+    );
+    let result = icfpc2025::gcp::gcs::stream_object(&bucket, &object, 0, None, |chunk| {
+        file.write_all(chunk)?;
+        Ok(true)
+    })
+    .await
+    .and_then(|n| Ok((n, file.into_inner()?)));
+    let bytes = match result {
+        Ok((bytes, _)) => bytes,
+        Err(e) => {
+            let _ = std::fs::remove_file(&tmp);
+            return Err(e).with_context(|| format!("Failed to download {}", src));
+        }
+    };
+    std::fs::rename(&tmp, &path).with_context(|| format!("Failed to write {}", path.display()))?;
+    println!("Downloaded {} to {} ({} bytes)", src, path.display(), bytes);
+    Ok(())
+}
@@ -0,0 +1,16 @@
+use anyhow::{Context, Result};
+
+pub async fn run(project_id: &str, zone: &str, names: &[String]) -> Result<()> {
+    for name in names {
+        println!("Deleting GCE instance '{}' in zone '{}'...", name, zone);
+        let op = icfpc2025::gcp::gce::delete_instance(project_id, zone, name)
+            .await
+            .with_context(|| format!("Failed to delete {}", name))?;
+        println!(
+            "Operation {}: {}",
+            op["name"].as_str().unwrap_or("?"),
+            op["status"].as_str().unwrap_or("?")
+        );
+    }
+    Ok(())
+}
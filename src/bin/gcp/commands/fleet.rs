@@ -0,0 +1,172 @@
+use anyhow::{Context, Result, bail};
+use std::time::{Duration, Instant};
+
+use icfpc2025::gcp::gce::{self, MetadataItem};
+
+use crate::common::{last_segment, print_table};
+
+// `create_default_instance_request` is fixed to this project and zone.
+const PROJECT: &str = "icfpc-primary";
+const ZONE: &str = "asia-northeast1-b";
+
+/// Metadata key the startup script sets to `READY` once the executor runs.
+const STATUS_KEY: &str = "unagi-status";
+
+/// Secret Manager secret holding `UNAGI_PASSWORD`, readable by the workers'
+/// service account.
+const PASSWORD_SECRET: &str = "unagi-password";
+
+/// File the executor unit reads its environment from (root-only).
+const ENV_FILE: &str = "/etc/unagi/executor.env";
+
+/// Startup script of a worker: installs the executor binary, runs it as a
+/// restarting systemd unit and reports `READY` through instance metadata.
+/// The VM fetches the password from Secret Manager with its own service
+/// account and hands it to the unit through a root-only `EnvironmentFile`,
+/// so it is never in instance metadata, process arguments or (the script
+/// does not trace commands) the serial console log.
+fn startup_script(executor_bin: &str) -> String {
+    format!(
+        r#"#!/bin/bash
+set -euo pipefail
+md() {{ curl -fsS -H 'Metadata-Flavor: Google' "http://metadata.google.internal/computeMetadata/v1/instance/$1"; }}
+install -d -m 700 "$(dirname '{env_file}')"
+(
+  umask 077
+  pw="$(gcloud secrets versions access latest --secret='{secret}' --project='{project}')"
+  printf 'UNAGI_PASSWORD=%s\n' "$pw" > '{env_file}'
+)
+gcloud storage cp '{bin}' /usr/local/bin/executor
+chmod +x /usr/local/bin/executor
+systemctl stop unagi-executor 2>/dev/null || true
+systemd-run --unit=unagi-executor -p Restart=always -p RestartSec=5 \
+  -p EnvironmentFile='{env_file}' /usr/local/bin/executor
+gcloud compute instances add-metadata "$(md name)" --zone "$(md zone | sed 's|.*/||')" \
+  --metadata {key}=READY
+"#,
+        bin = executor_bin,
+        key = STATUS_KEY,
+        env_file = ENV_FILE,
+        secret = PASSWORD_SECRET,
+        project = PROJECT,
+    )
+}
+
+/// Next `count` names `<prefix>-NN` not used by an existing instance.
+fn free_names(existing: &[String], prefix: &str, count: usize) -> Vec<String> {
+    (1..)
+        .map(|i| format!("{}-{:02}", prefix, i))
+        .filter(|name| !existing.contains(name))
+        .take(count)
+        .collect()
+}
+
+pub async fn run(count: usize, prefix: &str, executor_bin: &str, wait: Duration) -> Result<()> {
+    let existing = gce::list_instances(PROJECT, ZONE)
+        .await
+        .context("Failed to list instances")?
+        .into_iter()
+        .map(|it| it.name)
+        .collect::<Vec<_>>();
+    let names = free_names(&existing, prefix, count);
+
+    let script = startup_script(executor_bin);
+    for name in &names {
+        let mut request = gce::create_default_instance_request(name);
+        request.metadata.items.push(MetadataItem {
+            key: "startup-script".to_string(),
+            value: script.clone(),
+        });
+        println!("Creating GCE instance '{}'...", name);
+        gce::create_instance(PROJECT, ZONE, &request)
+            .await
+            .with_context(|| format!("Failed to create {}", name))?;
+    }
+
+    // Poll until every worker reports READY, is gone or the wait is over.
+    let start = Instant::now();
+    let mut instances = vec![None; names.len()];
+    loop {
+        for (name, slot) in names.iter().zip(instances.iter_mut()) {
+            if slot
+                .as_ref()
+                .is_some_and(|it: &gce::Instance| it.metadata_value(STATUS_KEY) == Some("READY"))
+            {
+                continue;
+            }
+            match gce::get_instance(PROJECT, ZONE, name).await {
+                Ok(it) => *slot = Some(it),
+                Err(e) => eprintln!("{}: {:#}", name, e),
+            }
+        }
+        let ready = instances
+            .iter()
+            .flatten()
+            .filter(|it| it.metadata_value(STATUS_KEY) == Some("READY"))
+            .count();
+        let stopped = instances
+            .iter()
+            .flatten()
+            .filter(|it| it.status == "TERMINATED" || it.status == "STOPPING")
+            .count();
+        println!(
+            "[{:>4}s] {}/{} ready, {} stopped",
+            start.elapsed().as_secs(),
+            ready,
+            names.len(),
+            stopped
+        );
+        if ready + stopped == names.len() || start.elapsed() >= wait {
+            break;
+        }
+        tokio::time::sleep(Duration::from_secs(10)).await;
+    }
+
+    let rows: Vec<[String; 5]> = names
+        .iter()
+        .zip(&instances)
+        .map(|(name, it)| match it {
+            Some(it) => [
+                it.name.clone(),
+                it.status.clone(),
+                it.metadata_value(STATUS_KEY).unwrap_or("-").to_string(),
+                last_segment(&it.machine_type).to_string(),
+                it.external_ip().unwrap_or("-").to_string(),
+            ],
+            None => [
+                name.clone(),
+                "UNKNOWN".to_string(),
+                "-".to_string(),
+                "-".to_string(),
+                "-".to_string(),
+            ],
+        })
+        .collect();
+    print_table(
+        &["Name", "Status", "Executor", "Machine Type", "External IP"],
+        &rows,
+    );
+    let ready = rows.iter().filter(|r| r[2] == "READY").count();
+    if ready < names.len() {
+        bail!(
+            "{} of {} workers are not ready",
+            names.len() - ready,
+            names.len()
+        );
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn startup_script_keeps_the_password_out_of_metadata_and_args() {
+        let script = startup_script("gs://icfpc2025-data/bin/executor");
+        assert!(!script.contains("attributes/unagi-password"));
+        assert!(!script.contains("--setenv"));
+        assert!(script.contains("gcloud secrets versions access latest --secret='unagi-password'"));
+        assert!(script.contains("-p EnvironmentFile='/etc/unagi/executor.env'"));
+    }
+}
@@ -1,8 +1,24 @@
 use anyhow::Result;
+use icfpc2025::gcp::gcs::ListOptions;
+use icfpc2025::gcp::gcs::types::FileInfo;
 
-pub async fn run(long: bool, recursive: bool, url: &str) -> Result<()> {
+pub async fn run(long: bool, recursive: bool, limit: Option<usize>, url: &str) -> Result<()> {
     let (bucket, prefix) = icfpc2025::gcp::gcs::parse_gs_url(url)?;
 
+    let (dir, glob) = icfpc2025::gcp::gcs::split_glob(&prefix);
+    if glob.is_some() {
+        // Matches are listed flat, by full URL, as `gsutil ls` does.
+        let opts = ListOptions { glob, limit };
+        icfpc2025::gcp::gcs::list_dir_detailed(&bucket, &dir, &opts, |_, files| {
+            for mut f in files {
+                f.name = format!("gs://{}/{}{}", bucket, dir, f.name);
+                print_file(long, &f);
+            }
+        })
+        .await?;
+        return Ok(());
+    }
+
     if !prefix.is_empty()
         && !prefix.ends_with('/')
         && let Ok(meta) = icfpc2025::gcp::gcs::get_object_metadata(&bucket, &prefix).await
@@ -12,25 +28,36 @@ pub async fn run(long: bool, recursive: bool, url: &str) -> Result<()> {
     }
 
     if recursive {
-        walk_recursive(&bucket, &prefix, long).await
-    } else if long {
-        let (dirs, files) = icfpc2025::gcp::gcs::list_dir_detailed(&bucket, &prefix).await?;
-        for d in dirs {
-            print_long_dir(&d);
-        }
-        for f in files {
-            print_long_file(&f);
-        }
-        Ok(())
+        walk_recursive(&bucket, &prefix, long, limit).await
     } else {
-        let (dirs, files) = icfpc2025::gcp::gcs::list_dir(&bucket, &prefix).await?;
-        for d in dirs {
+        let opts = ListOptions { glob: None, limit };
+        icfpc2025::gcp::gcs::list_dir_detailed(&bucket, &prefix, &opts, |dirs, files| {
+            print_page(long, &dirs, &files)
+        })
+        .await?;
+        Ok(())
+    }
+}
+
+/// Prints one page of a listing as it arrives.
+fn print_page(long: bool, dirs: &[String], files: &[FileInfo]) {
+    for d in dirs {
+        if long {
+            print_long_dir(d);
+        } else {
             println!("{}/", d.trim_end_matches('/'));
         }
-        for f in files {
-            println!("{}", f);
-        }
-        Ok(())
+    }
+    for f in files {
+        print_file(long, f);
+    }
+}
+
+fn print_file(long: bool, f: &FileInfo) {
+    if long {
+        print_long_file(f);
+    } else {
+        println!("{}", f.name);
     }
 }
 
@@ -38,7 +65,7 @@ fn print_long_dir(name: &str) {
     println!("{:>12}  {:<20}  {}/", "-", "-", name.trim_end_matches('/'));
 }
 
-fn print_long_file(f: &icfpc2025::gcp::gcs::types::FileInfo) {
+fn print_long_file(f: &FileInfo) {
     let size = f
         .size
         .map(|v| v.to_string())
@@ -47,9 +74,19 @@ fn print_long_file(f: &icfpc2025::gcp::gcs::types::FileInfo) {
     println!("{:>12}  {:<20}  {}", size, updated, f.name);
 }
 
-async fn walk_recursive(bucket: &str, prefix: &str, long: bool) -> Result<()> {
+/// Lists `prefix` and everything below it, `limit` entries in total.
+async fn walk_recursive(
+    bucket: &str,
+    prefix: &str,
+    long: bool,
+    limit: Option<usize>,
+) -> Result<()> {
+    let mut remaining = limit.unwrap_or(usize::MAX);
     let mut stack: Vec<String> = vec![prefix.to_string()];
     while let Some(current) = stack.pop() {
+        if remaining == 0 {
+            break;
+        }
         let header = if current.is_empty() {
             format!("gs://{}/", bucket)
         } else {
@@ -57,43 +94,26 @@ async fn walk_recursive(bucket: &str, prefix: &str, long: bool) -> Result<()> {
         };
         println!("{}:", header);
 
-        if long {
-            let (mut dirs, files) =
-                icfpc2025::gcp::gcs::list_dir_detailed(bucket, &current).await?;
-            for d in &dirs {
-                print_long_dir(d);
-            }
-            for f in &files {
-                print_long_file(f);
-            }
-            println!();
-            dirs.sort();
-            for d in dirs.into_iter().rev() {
-                let new_prefix = if current.is_empty() {
-                    d
-                } else {
-                    format!("{}/{}", current.trim_end_matches('/'), d)
-                };
-                stack.push(new_prefix);
-            }
-        } else {
-            let (mut dirs, files) = icfpc2025::gcp::gcs::list_dir(bucket, &current).await?;
-            for d in &dirs {
-                println!("{}/", d.trim_end_matches('/'));
-            }
-            for f in &files {
-                println!("{}", f);
-            }
-            println!();
-            dirs.sort();
-            for d in dirs.into_iter().rev() {
-                let new_prefix = if current.is_empty() {
-                    d
-                } else {
-                    format!("{}/{}", current.trim_end_matches('/'), d)
-                };
-                stack.push(new_prefix);
-            }
+        let opts = ListOptions {
+            glob: None,
+            limit: Some(remaining),
+        };
+        let mut subdirs = vec![];
+        remaining -=
+            icfpc2025::gcp::gcs::list_dir_detailed(bucket, &current, &opts, |dirs, files| {
+                print_page(long, &dirs, &files);
+                subdirs.extend(dirs);
+            })
+            .await?;
+        println!();
+        subdirs.sort();
+        for d in subdirs.into_iter().rev() {
+            let new_prefix = if current.is_empty() {
+                d
+            } else {
+                format!("{}/{}", current.trim_end_matches('/'), d)
+            };
+            stack.push(new_prefix);
         }
     }
     Ok(())
@@ -1,4 +1,10 @@
 pub mod cat;
+pub mod cp;
+pub mod delete;
+pub mod fleet;
 pub mod instances;
 pub mod ls;
+pub mod rm;
 pub mod run;
+pub mod start;
+pub mod stop;
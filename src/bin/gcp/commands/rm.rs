@@ -0,0 +1,49 @@
+use anyhow::{Context, Result, bail};
+use icfpc2025::gcp::gcs::ListOptions;
+
+pub async fn run(recursive: bool, url: &str) -> Result<()> {
+    let (bucket, object) = icfpc2025::gcp::gcs::parse_gs_url(url)?;
+    if !recursive {
+        if object.is_empty() || object.ends_with('/') {
+            bail!("rm needs -r to remove a prefix: {}", url);
+        }
+        return remove(&bucket, &object).await;
+    }
+
+    if object.trim_matches('/').is_empty() {
+        bail!("Refusing to remove a whole bucket: {}", url);
+    }
+    let prefix = format!("{}/", object.trim_end_matches('/'));
+    let opts = ListOptions {
+        glob: Some(format!("{}**", prefix)),
+        limit: None,
+    };
+    let mut objects = vec![];
+    icfpc2025::gcp::gcs::list_dir_detailed(&bucket, &prefix, &opts, |_, files| {
+        objects.extend(files.into_iter().map(|f| format!("{}{}", prefix, f.name)));
+    })
+    .await?;
+    // `gs://bucket/dir` may also name an object next to the prefix.
+    if !object.ends_with('/')
+        && icfpc2025::gcp::gcs::get_object_metadata(&bucket, &object)
+            .await
+            .is_ok()
+    {
+        objects.push(object);
+    }
+    if objects.is_empty() {
+        bail!("No objects match {}", url);
+    }
+    for object in &objects {
+        remove(&bucket, object).await?;
+    }
+    println!("Removed {} objects", objects.len());
+    Ok(())
+}
+
+async fn remove(bucket: &str, object: &str) -> Result<()> {
+    println!("Removing gs://{}/{}", bucket, object);
+    icfpc2025::gcp::gcs::delete_object(bucket, object)
+        .await
+        .with_context(|| format!("Failed to remove gs://{}/{}", bucket, object))
+}
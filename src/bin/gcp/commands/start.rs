@@ -0,0 +1,16 @@
+use anyhow::{Context, Result};
+
+pub async fn run(project_id: &str, zone: &str, names: &[String]) -> Result<()> {
+    for name in names {
+        println!("Starting GCE instance '{}' in zone '{}'...", name, zone);
+        let op = icfpc2025::gcp::gce::start_instance(project_id, zone, name)
+            .await
+            .with_context(|| format!("Failed to start {}", name))?;
+        println!(
+            "Operation {}: {}",
+            op["name"].as_str().unwrap_or("?"),
+            op["status"].as_str().unwrap_or("?")
+        );
+    }
+    Ok(())
+}
@@ -0,0 +1,16 @@
+use anyhow::{Context, Result};
+
+pub async fn run(project_id: &str, zone: &str, names: &[String]) -> Result<()> {
+    for name in names {
+        println!("Stopping GCE instance '{}' in zone '{}'...", name, zone);
+        let op = icfpc2025::gcp::gce::stop_instance(project_id, zone, name)
+            .await
+            .with_context(|| format!("Failed to stop {}", name))?;
+        println!(
+            "Operation {}: {}",
+            op["name"].as_str().unwrap_or("?"),
+            op["status"].as_str().unwrap_or("?")
+        );
+    }
+    Ok(())
+}
@@ -2,7 +2,10 @@ use anyhow::Result;
 use clap::{Parser, Subcommand};
 
 #[derive(Parser, Debug)]
-#[command(name = "gcp", about = "GCP utilities: instances/run/ls")]
+#[command(
+    name = "gcp",
+    about = "GCP utilities: instances/run/fleet/delete/stop/start/ls/cat/cp/rm"
+)]
 struct Cli {
     #[command(subcommand)]
     cmd: Commands,
@@ -32,17 +35,93 @@ enum Commands {
         cmd: Vec<String>,
     },
 
+    /// Create N executor workers from the default instance template and wait until they are ready
+    Fleet {
+        #[arg(long)]
+        count: usize,
+        #[arg(long, default_value = "worker")]
+        prefix: String,
+        /// gs:// URL of a static executor binary (see `unagi build-agent --bin executor`)
+        #[arg(long)]
+        executor_bin: String,
+        /// Seconds to wait for the workers to report READY
+        #[arg(long, default_value_t = 600)]
+        wait_secs: u64,
+    },
+
+    /// Delete GCE instances
+    Delete {
+        #[arg(long, default_value = "asia-northeast1-b")]
+        zone: String,
+        #[arg(long, default_value = "icfpc-primary")]
+        project: String,
+        #[arg(name = "INSTANCE_NAME", required = true)]
+        names: Vec<String>,
+    },
+
+    /// Stop GCE instances (disks are kept)
+    Stop {
+        #[arg(long, default_value = "asia-northeast1-b")]
+        zone: String,
+        #[arg(long, default_value = "icfpc-primary")]
+        project: String,
+        #[arg(name = "INSTANCE_NAME", required = true)]
+        names: Vec<String>,
+    },
+
+    /// Start stopped GCE instances
+    Start {
+        #[arg(long, default_value = "asia-northeast1-b")]
+        zone: String,
+        #[arg(long, default_value = "icfpc-primary")]
+        project: String,
+        #[arg(name = "INSTANCE_NAME", required = true)]
+        names: Vec<String>,
+    },
+
     /// List GCS objects like ls for a gs:// URL
     Ls {
         #[arg(short = 'l', long = "long")]
         long: bool,
         #[arg(short = 'R', long = "recursive")]
         recursive: bool,
+        /// Stop after N entries
+        #[arg(long = "limit", value_name = "N")]
+        limit: Option<usize>,
+        /// gs:// URL; the object path may contain globs (`*`, `**`, `?`, `[...]`, `{a,b}`)
+        url: String,
+    },
+
+    /// Print a GCS object's content to stdout (streamed)
+    Cat {
+        /// Print only the first N lines
+        #[arg(long = "head", value_name = "N")]
+        head: Option<usize>,
+        /// Print only the last N lines
+        #[arg(long = "tail", value_name = "N", conflicts_with = "head")]
+        tail: Option<usize>,
+        /// Print only bytes START-END (inclusive; `START-` to the end, `-N` the last N bytes)
+        #[arg(long = "range", value_name = "START-END", conflicts_with = "tail")]
+        range: Option<String>,
+        /// Pretty-print each line that parses as JSON
+        #[arg(long = "jsonl")]
+        jsonl: bool,
         url: String,
     },
 
-    /// Print a GCS object's content to stdout
-    Cat { url: String },
+    /// Copy a file or object: local→gs://, gs://→local or gs://→gs://
+    Cp {
+        src: String,
+        /// Destination; a trailing `/` (or an existing local directory) keeps the source's file name
+        dst: String,
+    },
+
+    /// Remove a GCS object, or with -r every object under a prefix
+    Rm {
+        #[arg(short = 'r', long = "recursive")]
+        recursive: bool,
+        url: String,
+    },
 }
 
 #[tokio::main]
@@ -57,12 +136,50 @@ async fn main() -> Result<()> {
             name,
             cmd,
         } => commands::run::run(&project, &zone, &machine_type, &name, &cmd).await,
+        Commands::Fleet {
+            count,
+            prefix,
+            executor_bin,
+            wait_secs,
+        } => {
+            commands::fleet::run(
+                count,
+                &prefix,
+                &executor_bin,
+                std::time::Duration::from_secs(wait_secs),
+            )
+            .await
+        }
+        Commands::Delete {
+            zone,
+            project,
+            names,
+        } => commands::delete::run(&project, &zone, &names).await,
+        Commands::Stop {
+            zone,
+            project,
+            names,
+        } => commands::stop::run(&project, &zone, &names).await,
+        Commands::Start {
+            zone,
+            project,
+            names,
+        } => commands::start::run(&project, &zone, &names).await,
         Commands::Ls {
             long,
             recursive,
+            limit,
+            url,
+        } => commands::ls::run(long, recursive, limit, &url).await,
+        Commands::Cat {
+            head,
+            tail,
+            range,
+            jsonl,
             url,
-        } => commands::ls::run(long, recursive, &url).await,
-        Commands::Cat { url } => commands::cat::run(&url).await,
+        } => commands::cat::run(&url, head, tail, range.as_deref(), jsonl).await,
+        Commands::Cp { src, dst } => commands::cp::run(&src, &dst).await,
+        Commands::Rm { recursive, url } => commands::rm::run(recursive, &url).await,
     }
 }
 
@@ -238,10 +238,12 @@ fn main() {
         .map(|seed| SATSolver {
             path: "/home/iwiwi/tmp/cadical-rel-2.1.3/build/cadical".to_owned(),
             args: [format!("--seed={}", seed), "--sat".to_owned()].to_vec(),
+            stdin: false,
         })
         .chain((0..25).map(|seed| SATSolver {
             path: "/home/iwiwi/tmp/kissat-4.0.3-linux-amd64".to_owned(),
             args: [format!("--seed={}", seed), "--sat".to_owned()].to_vec(),
+            stdin: false,
         }))
         .collect_vec();
 
@@ -251,7 +253,12 @@ fn main() {
         std::fs::create_dir_all(parent).unwrap();
     }
     cnf.write_dimacs(dimacs_path).unwrap();
-    let solution = solve_no_marks::launch_portfolio(dimacs_path, &solvers);
+    let solution = solve_no_marks::launch_portfolio(dimacs_path, &solvers)
+        .into_model()
+        .unwrap_or_else(|e| {
+            eprintln!("{:#}", e);
+            std::process::exit(1)
+        });
 
     for &v in &solution {
         cnf.clause([v]);
@@ -157,26 +157,18 @@ fn acquire_plan_and_labels(judge: &mut dyn icfpc2025::judge::Judge) -> PlanInfo
 fn acquire_plan_and_labels(judge: &mut dyn icfpc2025::judge::Judge) -> PlanInfo {
     let n = judge.num_rooms();
 
-    // Default plan strings for supported sizes
-    let default_plan_str = if n == 30 {
-        "413022551403315200442351124530532105441250342013450431221500235401332245541100524301142035531432234405215311350240015425104334025123304521120534103522445503114230032542135431452051002415012340523321554433021451132041025533014400351220440115324321342250451305502315412031045522433500142534115203442231104350310540221435132441500553020145133425523012412033443004231254411023052214500135410325345320121545042102113352405514315340154304422003355523411201445331322002514054225105033004323315550112134421441352532400511024124025405311304432221235"
-    } else if n == 24 {
-        "053421124355003145223044132102540153351203445023114200554324125133051042215014033152443520411325530244002234511032054154230134552103501221433402532514310044152332500144551240530123153410521354220330420524115043021334514011522400543355322502431104320154423513402104531230554420011342541350314220511225053310324405552341300214450322545125330150043123141012421453202513005434045013322443102352331551412002403415510035111204255404452032"
-    } else {
-        panic!("Unsupported number of rooms: {}", n);
-    };
-
-    // Allow override via environment variable PLAN_STR (string of digits 0-5)
-    let plan_str = env::var("PLAN_STR")
+    // Preset plan for this size, overridable via environment variable PLAN_STR
+    // (string of digits 0-5)
+    let plan = env::var("PLAN_STR")
         .ok()
         .map(|s| s.trim().to_string())
         .filter(|s| !s.is_empty())
-        .unwrap_or_else(|| default_plan_str.to_string());
-
-    let plan = plan_str
-        .chars()
-        .map(|c| c.to_digit(10).unwrap() as usize)
-        .collect::<Vec<_>>();
+        .map(|s| {
+            s.chars()
+                .map(|c| c.to_digit(10).expect("PLAN_STR must be digits") as usize)
+                .collect()
+        })
+        .unwrap_or_else(|| icfpc2025::routes::long_plan(n));
 
     let steps: Vec<(Option<usize>, usize)> = plan.iter().copied().map(|d| (None, d)).collect();
     let labels = judge.explore(&[steps])[0].clone();
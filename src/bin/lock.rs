@@ -12,6 +12,10 @@ struct Args {
     #[arg(short = 'f', long = "force", action = ArgAction::SetTrue)]
     force: bool,
 
+    /// Wait in the FIFO queue until the lock is free instead of failing
+    #[arg(short = 'q', long = "queue", action = ArgAction::SetTrue)]
+    queue: bool,
+
     /// Lock duration in seconds
     #[arg(short = 'd', long = "duration")]
     duration: Option<u64>,
@@ -32,6 +36,14 @@ fn run() -> Result<()> {
         lockmod::unlock("", true)?;
     }
 
+    if args.queue {
+        let token = lockmod::wait_for_lock(ttl, Duration::from_secs(2), |status| {
+            eprintln!("[lock] queued: {}", status)
+        })?;
+        println!("{}", token);
+        return Ok(());
+    }
+
     match lockmod::lock(ttl)? {
         Some(token) => {
             println!("{}", token);
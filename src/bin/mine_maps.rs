@@ -0,0 +1,76 @@
+//! Mines hard maps for benchmarks (see `mapgen::miner`).
+//!
+//! Usage: `mine_maps -n 30 --count 2000 --sat-timeout 20 -o gs://icfpc2025-data/mined/30.jsonl`
+
+use clap::Parser;
+use icfpc2025::mapgen::miner::{self, MinerConfig};
+use std::time::Duration;
+
+#[derive(Parser)]
+struct Cli {
+    /// Number of rooms.
+    #[clap(long, short = 'n', default_value_t = 30)]
+    n_rooms: usize,
+    /// Maps per family.
+    #[clap(long, default_value_t = 1000)]
+    count: usize,
+    /// Comma-separated map families (default: all of `miner::FAMILIES`).
+    #[clap(long, value_delimiter = ',')]
+    families: Vec<String>,
+    #[clap(long, short = 's', default_value_t = 0)]
+    seed: u64,
+    /// Seconds per bounded SAT solve; 0 ranks by the heuristics only.
+    #[clap(long, default_value_t = 0.0)]
+    sat_timeout: f64,
+    /// Heuristically hardest maps that get a SAT solve.
+    #[clap(long, default_value_t = 100)]
+    shortlist: usize,
+    /// Candidates written to the dataset.
+    #[clap(long, default_value_t = 100)]
+    keep: usize,
+    /// Local path or `gs://` URL of the JSON lines dataset. If not provided,
+    /// only the ranking is printed.
+    #[clap(long, short = 'o', default_value = "")]
+    output: String,
+}
+
+fn main() -> anyhow::Result<()> {
+    let args = Cli::parse();
+    let mut config = MinerConfig {
+        num_rooms: args.n_rooms,
+        count: args.count,
+        first_seed: args.seed,
+        sat_timeout: (args.sat_timeout > 0.0).then(|| Duration::from_secs_f64(args.sat_timeout)),
+        sat_shortlist: args.shortlist,
+        keep: args.keep,
+        ..Default::default()
+    };
+    if !args.families.is_empty() {
+        config.families = args.families;
+    }
+    let mined = miner::mine(&config)?;
+    eprintln!(
+        "{:>10} {:>8} {:>10} {:>10} {:>6} {:>8}",
+        "family", "seed", "diff", "chi2", "aib", "sat"
+    );
+    for c in &mined {
+        eprintln!(
+            "{:>10} {:>8} {:>10} {:>10.1} {:>6} {:>8}",
+            c.family,
+            c.seed,
+            c.diff_count,
+            c.label_door_chi2,
+            c.aib_missing,
+            match (c.sat_secs, c.sat_decided) {
+                (Some(secs), Some(true)) => format!("{:.2}s", secs),
+                (Some(_), _) => "timeout".to_string(),
+                _ => "-".to_string(),
+            }
+        );
+    }
+    if !args.output.is_empty() {
+        miner::write_dataset(&args.output, &mined)?;
+        eprintln!("wrote {} candidates to {}", mined.len(), args.output);
+    }
+    Ok(())
+}
@@ -0,0 +1,37 @@
+//! A local stand-in for the contest server (see `www::handlers::mock_api`).
+//!
+//! Usage: `mock_aedificium --port 8123 [--seed 1] [--fail-every 5]`, then run
+//! a solver with `AEDIFICIUM_ENDPOINT=http://localhost:8123`.
+
+use actix_web::{App, HttpServer, web};
+use clap::Parser;
+use icfpc2025::www::handlers::mock_api::{self, MockApi};
+
+#[derive(Parser)]
+struct Cli {
+    #[clap(long, default_value = "127.0.0.1")]
+    bind: String,
+    #[clap(long, short = 'p', default_value_t = 8123)]
+    port: u16,
+    /// Seed of the first selected map; each `/select` takes the next one.
+    #[clap(long, short = 's', default_value_t = 0)]
+    seed: u64,
+    /// Answer every N-th request with 503 (0: never).
+    #[clap(long, default_value_t = 0)]
+    fail_every: usize,
+}
+
+#[actix_web::main]
+async fn main() -> std::io::Result<()> {
+    let args = Cli::parse();
+    let state = web::Data::new(MockApi::new(args.seed, args.fail_every));
+    eprintln!("Mock Aedificium at http://{}:{}", args.bind, args.port);
+    HttpServer::new(move || {
+        App::new()
+            .app_data(state.clone())
+            .configure(mock_api::configure)
+    })
+    .bind((args.bind.as_str(), args.port))?
+    .run()
+    .await
+}
@@ -0,0 +1,67 @@
+//! Rust counterpart of the `./run` launcher.
+//!
+//! `run post select|explore|guess '<json>'` takes the same arguments as
+//! `./run post ...` but calls the API client directly, so scripts get the
+//! client's endpoint failover, retries and request logging without a
+//! `cargo build` per call. Any other `run <bin> [args...]` execs the sibling
+//! binary from the same target directory.
+//!
+//! Example:
+//!   run post explore '{"plans":["0123"]}'
+//!   run gacha --help
+use anyhow::{Context, Result, bail};
+use clap::{Parser, Subcommand};
+use icfpc2025::post::{self, PostCommand};
+use std::os::unix::process::CommandExt;
+
+#[derive(Parser, Debug)]
+#[command(name = "run")]
+struct Cli {
+    #[command(subcommand)]
+    command: Commands,
+}
+
+#[derive(Subcommand, Debug)]
+enum Commands {
+    /// Call the official API (same interface as the `post` binary)
+    Post {
+        #[command(subcommand)]
+        command: PostCommand,
+    },
+    #[command(external_subcommand)]
+    External(Vec<String>),
+}
+
+fn main() {
+    if let Err(e) = run() {
+        eprintln!("{:#}", e);
+        std::process::exit(1);
+    }
+}
+
+fn run() -> Result<()> {
+    match Cli::parse().command {
+        Commands::Post { command } => post::run(command),
+        Commands::External(args) => exec_sibling(&args),
+    }
+}
+
+/// Replaces the current process with `<dir of this binary>/<name> args...`.
+fn exec_sibling(args: &[String]) -> Result<()> {
+    let (name, rest) = args.split_first().context("missing binary name")?;
+    if name.contains('/') {
+        bail!("invalid binary name: {}", name);
+    }
+    let exe = std::env::current_exe().context("locate current executable")?;
+    let path = exe.with_file_name(name);
+    if !path.exists() {
+        bail!(
+            "{} not found next to {}; build it with `cargo build --release --bin {}`",
+            name,
+            exe.display(),
+            name
+        );
+    }
+    let err = std::process::Command::new(&path).args(rest).exec();
+    Err(err).with_context(|| format!("exec {}", path.display()))
+}
@@ -1,29 +1,198 @@
+use clap::Parser;
+use icfpc2025::judge::{Guess, Judge, LocalJudge};
+use icfpc2025::solve_no_marks::{Coverage, CoverageGate};
 use itertools::Itertools;
 use rand::prelude::*;
 use rand_chacha::ChaCha12Rng;
+use std::io::Read;
+use std::time::{Duration, Instant};
 
-fn balanced_plan_len(len: usize, rng: &mut ChaCha12Rng) -> Vec<usize> {
-    let mut plan = Vec::with_capacity(len);
-    for d in 0..6 {
-        for _ in 0..(len / 6) {
-            plan.push(d);
+#[derive(Parser, Debug)]
+struct Args {
+    /// Minimum observations of each door per observed label before solving
+    #[clap(long, default_value_t = 2)]
+    min_label_door: usize,
+    /// Maximum number of unseen label-door-label transitions before solving
+    #[clap(long)]
+    max_aib_missing: Option<usize>,
+    /// Maximum number of extra explore rounds to satisfy the thresholds
+    #[clap(long, default_value_t = 2)]
+    max_topups: usize,
+    /// Submit even if the coverage gate failed and only one guess attempt remains
+    #[clap(long)]
+    allow_risky_last_guess: bool,
+    /// Before creating the judge, solve random local instances of the same size
+    /// and stop unless the success rate and time limit below are met
+    #[clap(long)]
+    self_test: bool,
+    /// Number of local instances (seeds 0..N) solved by --self-test
+    #[clap(long, default_value_t = 100)]
+    self_test_seeds: u64,
+    /// Minimum fraction of local instances that must be solved
+    #[clap(long, default_value_t = 0.95)]
+    self_test_min_success: f64,
+    /// Maximum seconds per local instance
+    #[clap(long, default_value_t = 60.0)]
+    self_test_max_secs: f64,
+    /// Internal: solve the local instance with this seed (and
+    /// --self-test-rooms rooms) as one --self-test run, then exit
+    #[clap(long, hide = true)]
+    self_test_seed: Option<u64>,
+    /// Internal: number of rooms of --self-test-seed
+    #[clap(long, hide = true, default_value_t = 0)]
+    self_test_rooms: usize,
+    /// How the initial plan is generated (random-balanced, coverage-greedy[:SEEDS],
+    /// precomputed)
+    #[clap(long, default_value = "random-balanced")]
+    plan_strategy: String,
+    /// Prefer maps with at most this many self-loops per room (a soft prior;
+    /// rooms that the data contradicts are relaxed)
+    #[clap(long)]
+    max_self_loops: Option<usize>,
+    /// Number of rooms that may break --max-self-loops before falling back to
+    /// solving without priors
+    #[clap(long, default_value_t = 2)]
+    max_prior_violations: usize,
+}
+
+fn main() {
+    let args = Args::parse();
+    if let Err(e) = icfpc2025::routes::planner::parse_strategy(&args.plan_strategy) {
+        eprintln!("{}", e);
+        std::process::exit(2);
+    }
+    let gate = CoverageGate {
+        min_label_door: args.min_label_door,
+        max_aib_missing: args.max_aib_missing,
+    };
+    if let Some(seed) = args.self_test_seed {
+        let mut judge = LocalJudge::new("random", args.self_test_rooms, seed);
+        let (guess, _) = explore_and_solve(&mut judge, &args, &gate);
+        if let Err(reason) = judge.check_guess(&guess) {
+            eprintln!("self-test seed {}: WA ({})", seed, reason);
+            std::process::exit(1);
+        }
+        return;
+    }
+    let mut input = String::new();
+    std::io::stdin().read_to_string(&mut input).unwrap();
+    if args.self_test {
+        let n = icfpc2025::judge::input_num_rooms(&input)
+            .expect("--self-test: cannot determine the number of rooms from the input");
+        if let Err(e) = self_test(n, &args) {
+            eprintln!("self-test failed: {}", e);
+            std::process::exit(1);
+        }
+    }
+    let mut judge = icfpc2025::judge::get_judge_from_input(&input, false);
+    let (guess, gate_passed) = explore_and_solve(judge.as_mut(), &args, &gate);
+    icfpc2025::profiling::print_summary();
+    match judge.guesses_remaining() {
+        Some(0) => {
+            eprintln!("no guess attempts left; not submitting");
+            std::process::exit(1);
         }
+        Some(1) if !gate_passed && !args.allow_risky_last_guess => {
+            eprintln!(
+                "only one guess attempt left and the coverage gate was not met; not submitting (use --allow-risky-last-guess to override)"
+            );
+            std::process::exit(1);
+        }
+        _ => {}
     }
-    plan.shuffle(rng);
-    plan
+    judge.guess(&guess);
 }
 
-fn main() {
-    let mut judge = icfpc2025::judge::get_judge_from_stdin();
+/// Runs the strategy on `--self-test-seeds` random local instances with `n`
+/// rooms, each in a child process (see `--self-test-seed`) whose output is
+/// discarded, so that the executor only sees the real run, and which is
+/// killed once it runs over `--self-test-max-secs`.
+fn self_test(n: usize, args: &Args) -> Result<(), String> {
+    let limit = Duration::from_secs_f64(args.self_test_max_secs);
+    let total = args.self_test_seeds;
+    let mut solved = 0;
+    for seed in 0..total {
+        if solve_instance(n, seed, limit)? {
+            solved += 1;
+        }
+        // Stop as soon as the required success rate is out of reach.
+        let best = (solved + total - seed - 1) as f64 / total as f64;
+        if best < args.self_test_min_success {
+            return Err(format!(
+                "{} of {} instances solved so far; {:.0}% required",
+                solved,
+                seed + 1,
+                args.self_test_min_success * 100.0
+            ));
+        }
+    }
+    eprintln!("self-test passed: {}/{} instances solved", solved, total);
+    Ok(())
+}
+
+/// Solves one self-test instance in a child process with the same options.
+/// Returns whether the guess was right; running over `limit` is an error.
+fn solve_instance(n: usize, seed: u64, limit: Duration) -> Result<bool, String> {
+    use std::io::BufRead;
+    use std::process::{Command, Stdio};
+
+    let exe = std::env::current_exe().map_err(|e| e.to_string())?;
+    let mut child = Command::new(exe)
+        .args(std::env::args().skip(1))
+        .args(["--self-test-rooms", &n.to_string()])
+        .args(["--self-test-seed", &seed.to_string()])
+        .stdin(Stdio::null())
+        .stdout(Stdio::null())
+        .stderr(Stdio::piped())
+        .spawn()
+        .map_err(|e| format!("failed to start seed {}: {}", seed, e))?;
+    // Keep the last line of stderr, which says why a guess was wrong.
+    let stderr = child.stderr.take().unwrap();
+    let last_line = std::thread::spawn(move || {
+        std::io::BufReader::new(stderr)
+            .lines()
+            .map_while(Result::ok)
+            .last()
+    });
+    let start = Instant::now();
+    let status = loop {
+        if let Some(status) = child.try_wait().map_err(|e| e.to_string())? {
+            break status;
+        }
+        if start.elapsed() > limit {
+            let _ = child.kill();
+            let _ = child.wait();
+            return Err(format!(
+                "seed {} took over {:.1}s",
+                seed,
+                limit.as_secs_f64()
+            ));
+        }
+        std::thread::sleep(Duration::from_millis(20));
+    };
+    if status.success() {
+        return Ok(true);
+    }
+    match last_line.join().ok().flatten() {
+        Some(line) if line.starts_with("self-test seed") => eprintln!("{}", line),
+        _ => eprintln!("self-test seed {}: failed ({})", seed, status),
+    }
+    Ok(false)
+}
+
+/// Explores with the coverage gate and solves. Returns the guess and whether
+/// the gate was met.
+fn explore_and_solve(judge: &mut dyn Judge, args: &Args, gate: &CoverageGate) -> (Guess, bool) {
     let n = judge.num_rooms();
 
     // Multiple plans setup
     let n_plans = 1;
     let len_plan = 18 * n;
     let mut rng = ChaCha12Rng::seed_from_u64(0xC0FF_EE42);
+    let strategy = icfpc2025::routes::planner::parse_strategy(&args.plan_strategy).unwrap();
 
-    let plans: Vec<Vec<usize>> = (0..n_plans)
-        .map(|_| balanced_plan_len(len_plan, &mut rng))
+    let mut plans: Vec<Vec<usize>> = (0..n_plans)
+        .map(|_| strategy.plan(n, len_plan, &mut rng))
         .collect();
 
     for plan in &plans {
@@ -34,9 +203,61 @@ fn main() {
         .iter()
         .map(|p| p.iter().copied().map(|d| (None, d)).collect())
         .collect();
-    let labels: Vec<Vec<usize>> = judge.explore(&steps);
+    let mut labels: Vec<Vec<usize>> = judge.explore(&steps);
 
-    // Solve using the shared solver and submit the guess
-    let guess = icfpc2025::solve_no_marks::solve(n, &plans, &labels);
-    judge.guess(&guess);
+    // Top up label×door transitions that are too thin to pin down the map.
+    let mut gate_passed = false;
+    for round in 0.. {
+        let cov = Coverage::new(&plans, &labels);
+        eprintln!("aib_missing = {}", cov.aib_missing());
+        if gate.passes(&cov) {
+            gate_passed = true;
+            break;
+        }
+        let targets = cov.underrepresented(gate.min_label_door);
+        if round >= args.max_topups || targets.is_empty() {
+            eprintln!("coverage gate not met; solving anyway");
+            break;
+        }
+        let extra =
+            icfpc2025::solve_no_marks::top_up_plans(&plans, &labels, &targets, len_plan, &mut rng);
+        if extra.is_empty() {
+            break;
+        }
+        eprintln!(
+            "top-up {}: {} plans for {:?}",
+            round + 1,
+            extra.len(),
+            targets
+        );
+        let steps: Vec<Vec<(Option<usize>, usize)>> = extra
+            .iter()
+            .map(|p| p.iter().copied().map(|d| (None, d)).collect())
+            .collect();
+        labels.extend(judge.explore(&steps));
+        plans.extend(extra);
+    }
+
+    // Solve using the shared solver
+    if let Some(max_self_loops) = args.max_self_loops {
+        let priors = icfpc2025::solve_no_marks::Priors {
+            max_self_loops,
+            max_violations: args.max_prior_violations,
+        };
+        if let Some((guess, violations)) = icfpc2025::solve_no_marks::solve_with_priors(
+            n,
+            &plans,
+            &labels,
+            icfpc2025::solve_no_marks::LabelMode::Auto,
+            &priors,
+        ) {
+            eprintln!("priors: {} room(s) relaxed", violations);
+            return (guess, gate_passed);
+        }
+        eprintln!("priors: too many violations, solving without them");
+    }
+    (
+        icfpc2025::solve_no_marks::solve(n, &plans, &labels),
+        gate_passed,
+    )
 }
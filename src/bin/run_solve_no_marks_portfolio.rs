@@ -73,44 +73,54 @@ fn main() {
             ]
             .map(|s| s.to_owned())
             .to_vec(),
+            stdin: false,
         },
         SATSolver {
             path: "/home/iwiwi/tmp/kissat-4.0.3-linux-amd64".to_owned(),
             args: ["--seed=0", "--sat"].map(|s| s.to_owned()).to_vec(),
+            stdin: false,
         },
         SATSolver {
             path: "/home/iwiwi/tmp/kissat-4.0.3-linux-amd64".to_owned(),
             args: ["--seed=1", "--sat"].map(|s| s.to_owned()).to_vec(),
+            stdin: false,
         },
         SATSolver {
             path: "/home/iwiwi/tmp/kissat-4.0.3-linux-amd64".to_owned(),
             args: ["--seed=2", "--sat"].map(|s| s.to_owned()).to_vec(),
+            stdin: false,
         },
         SATSolver {
             path: "/home/iwiwi/tmp/kissat-4.0.3-linux-amd64".to_owned(),
             args: ["--seed=3", "--sat"].map(|s| s.to_owned()).to_vec(),
+            stdin: false,
         },
         SATSolver {
             path: "/home/iwiwi/tmp/kissat-4.0.3-linux-amd64".to_owned(),
             args: ["--sat", "--walkinitially=true", "--walkeffort=100"]
                 .map(|s| s.to_owned())
                 .to_vec(),
+            stdin: false,
         },
         SATSolver {
             path: "/home/iwiwi/tmp/cadical-rel-2.1.3/build/cadical".to_owned(),
             args: ["--seed=0", "--sat"].map(|s| s.to_owned()).to_vec(),
+            stdin: false,
         },
         SATSolver {
             path: "/home/iwiwi/tmp/cadical-rel-2.1.3/build/cadical".to_owned(),
             args: ["--seed=1", "--sat"].map(|s| s.to_owned()).to_vec(),
+            stdin: false,
         },
         SATSolver {
             path: "/home/iwiwi/tmp/cadical-rel-2.1.3/build/cadical".to_owned(),
             args: ["--seed=2", "--sat"].map(|s| s.to_owned()).to_vec(),
+            stdin: false,
         },
         SATSolver {
             path: "/home/iwiwi/tmp/cadical-rel-2.1.3/build/cadical".to_owned(),
             args: ["--seed=3", "--sat"].map(|s| s.to_owned()).to_vec(),
+            stdin: false,
         },
     ];
 
@@ -118,6 +128,7 @@ fn main() {
         .map(|seed| SATSolver {
             path: "/home/iwiwi/tmp/cadical-rel-2.1.3/build/cadical".to_owned(),
             args: [format!("--seed={}", seed), "--sat".to_owned()].to_vec(),
+            stdin: false,
         })
         .collect_vec();
 
@@ -125,10 +136,12 @@ fn main() {
         .map(|seed| SATSolver {
             path: "/home/iwiwi/tmp/cadical-rel-2.1.3/build/cadical".to_owned(),
             args: [format!("--seed={}", seed), "--sat".to_owned()].to_vec(),
+            stdin: false,
         })
         .chain((0..25).map(|seed| SATSolver {
             path: "/home/iwiwi/tmp/kissat-4.0.3-linux-amd64".to_owned(),
             args: [format!("--seed={}", seed), "--sat".to_owned()].to_vec(),
+            stdin: false,
         }))
         .collect_vec();
 
@@ -148,7 +161,11 @@ fn main() {
         &labels,
         &solvers,
         dimacs_path,
-    );
+    )
+    .unwrap_or_else(|e| {
+        eprintln!("{:#}", e);
+        std::process::exit(1)
+    });
     judge.guess(&guess);
 }
 
@@ -0,0 +1,70 @@
+//! Runs a solver chosen by name on the judge read from stdin.
+//!
+//! The solver is the first argument or `SOLVER`. Names of the `solvers`
+//! registry run in this process; any other name execs the sibling binary of
+//! that name (with the same stdin), so standalone solvers not yet in the
+//! registry are selected the same way.
+//!
+//! Example:
+//!   echo 'local random2 12 0' | SOLVER=no-marks ./run solve
+//!   SOLVER=iwiwi_evo_gen276 ./run solve < input.txt
+use anyhow::{Context, Result, bail};
+use clap::Parser;
+use icfpc2025::{judge, solvers};
+use std::os::unix::process::CommandExt;
+
+#[derive(Parser, Debug)]
+#[command(
+    name = "solve",
+    about = "Run a solver by name on the judge read from stdin",
+    after_help = format!("Registered solvers:\n{}", solvers::list())
+)]
+struct Args {
+    /// Solver name (default: `SOLVER`)
+    solver: Option<String>,
+}
+
+fn main() {
+    match run() {
+        Ok(true) => {}
+        Ok(false) => std::process::exit(1),
+        Err(e) => {
+            eprintln!("{:#}", e);
+            std::process::exit(1);
+        }
+    }
+}
+
+/// Whether the guess was correct.
+fn run() -> Result<bool> {
+    let args = Args::parse();
+    let name = match args.solver {
+        Some(name) => name,
+        None => std::env::var("SOLVER").context("give a solver name or set SOLVER")?,
+    };
+    let Some(solver) = solvers::find(&name) else {
+        return exec_sibling(&name);
+    };
+    let mut judge = judge::get_judge_from_stdin();
+    Ok((solver.run)(judge.as_mut()))
+}
+
+/// Replaces the current process with the binary `name` next to this one.
+fn exec_sibling(name: &str) -> Result<bool> {
+    if name.contains('/') {
+        bail!("invalid solver name: {}", name);
+    }
+    let exe = std::env::current_exe().context("locate current executable")?;
+    let path = exe.with_file_name(name);
+    if !path.exists() {
+        bail!(
+            "unknown solver: {}\nregistered solvers:\n{}\n\
+             (a standalone solver must be built with `cargo build --release --bin {}`)",
+            name,
+            solvers::list(),
+            name
+        );
+    }
+    let err = std::process::Command::new(&path).exec();
+    Err(err).with_context(|| format!("exec {}", path.display()))
+}
@@ -0,0 +1,607 @@
+//! Team utility commands.
+//!
+//! `unagi verify` replays every exploration recorded in `api_logs` for a
+//! problem (across all `/select` sessions, not only the latest one) on a
+//! candidate map, so inconsistencies show up before the map is submitted.
+//!
+//! `unagi export` writes every recorded session of a problem to
+//! `<out>/<select id>.json` in the JSON replay format, plus an `index.json`
+//! manifest. Sessions are fetched in batches (one query per batch) by a small
+//! number of worker threads, and queries failing under load are retried with
+//! backoff, so the shared database is not flooded.
+//!
+//! `unagi pack` / `unagi unpack` convert between the JSON replay format and
+//! the compact session archive format (see `icfpc2025::archive`).
+//!
+//! `unagi build-agent` builds a solver binary statically (musl by default),
+//! strips it, uploads it to `gs://icfpc2025-data/agents/` under a name
+//! containing its MD5, and prints the URL to put in `agents.agent_bin`. The
+//! executor's `prepare_agent_bin` then runs it on any GCE image regardless of
+//! the glibc there. The target needs `rustup target add` and, since CaDiCaL
+//! is C++, a musl C/C++ toolchain (`CC_<target>`/`CXX_<target>` are passed
+//! through to cargo).
+//!
+//! `unagi tasks export` writes every task (optionally only those updated since
+//! a time) joined with its agent as CSV or Parquet. Numeric top-level keys of
+//! `task_diagnostics` become `diag.<key>` columns next to the raw JSON. Rows
+//! are read in `task_id` order in batches with a pause in between, so an
+//! export during the contest does not load the database.
+//!
+//! Example:
+//!   unagi verify --problem probatio --map map.json
+//!   unagi export --problem probatio --out replays/probatio
+//!   unagi pack session.json session.ungs.gz
+//!   unagi build-agent --target x86_64-unknown-linux-musl
+//!   unagi tasks export --since 2025-09-06 --format parquet --out tasks.parquet
+use anyhow::{Context, Result};
+use clap::{Parser, Subcommand};
+use icfpc2025::columnar::{Column, Format, Table};
+use icfpc2025::judge::{self, Explored, Guess};
+use icfpc2025::{api, archive, sql};
+use itertools::Itertools;
+use mysql::params;
+use rayon::prelude::*;
+use serde::{Deserialize, Serialize};
+use std::path::{Path, PathBuf};
+use std::process::Command;
+use std::time::Duration;
+
+#[derive(Parser, Debug)]
+#[command(name = "unagi", about = "Team utility commands")]
+struct Cli {
+    #[command(subcommand)]
+    command: Commands,
+}
+
+#[derive(Subcommand, Debug)]
+enum Commands {
+    /// Check a candidate map against all recorded explorations of a problem
+    Verify {
+        /// Problem name (e.g. probatio)
+        #[arg(long)]
+        problem: String,
+        /// Map JSON file (a bare map or a /guess request body)
+        #[arg(long)]
+        map: String,
+    },
+    /// Export all recorded sessions of a problem as JSON replay files
+    Export {
+        /// Problem name (e.g. probatio)
+        #[arg(long)]
+        problem: String,
+        /// Output directory
+        #[arg(long)]
+        out: String,
+        /// Number of concurrent queries
+        #[arg(long, default_value_t = 4)]
+        jobs: usize,
+        /// Sessions fetched per query
+        #[arg(long, default_value_t = 50)]
+        batch: usize,
+        /// Pause after each query of a worker, in milliseconds
+        #[arg(long, default_value_t = 100)]
+        delay_ms: u64,
+    },
+    /// Convert a JSON replay file into a compact session archive
+    Pack {
+        /// JSON replay file
+        input: String,
+        /// Archive file to write
+        output: String,
+    },
+    /// Convert a compact session archive back into a JSON replay file
+    Unpack {
+        /// Archive file
+        input: String,
+        /// JSON replay file to write
+        output: String,
+    },
+    /// Build a static solver binary, upload it and print its agent_bin URL
+    BuildAgent {
+        /// Rust target triple
+        #[arg(long, default_value = "x86_64-unknown-linux-musl")]
+        target: String,
+        /// Binary to build
+        #[arg(long, default_value = "run_solve_no_marks")]
+        bin: String,
+        /// Destination prefix in GCS
+        #[arg(long, default_value = "gs://icfpc2025-data/agents/")]
+        dest: String,
+        /// Build and strip only
+        #[arg(long)]
+        no_upload: bool,
+    },
+    /// Task queue utilities
+    Tasks {
+        #[command(subcommand)]
+        command: TasksCommand,
+    },
+}
+
+#[derive(Subcommand, Debug)]
+enum TasksCommand {
+    /// Export tasks joined with agents and diagnostics for offline analysis
+    Export {
+        /// Only tasks updated at or after this time ("YYYY-MM-DD[ HH:MM:SS]")
+        #[arg(long)]
+        since: Option<String>,
+        /// csv or parquet
+        #[arg(long, default_value = "parquet")]
+        format: String,
+        /// Output file
+        #[arg(long)]
+        out: String,
+        /// Tasks fetched per query
+        #[arg(long, default_value_t = 5000)]
+        batch: usize,
+        /// Pause after each query, in milliseconds
+        #[arg(long, default_value_t = 100)]
+        delay_ms: u64,
+    },
+}
+
+/// Accepts either `{"map": {...}}` (a /guess request body) or a bare map.
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum MapFile {
+    Guess { map: api::Map },
+    Map(api::Map),
+}
+
+fn main() {
+    if let Err(e) = run() {
+        eprintln!("{:#}", e);
+        std::process::exit(1);
+    }
+}
+
+fn run() -> Result<()> {
+    let cli = Cli::parse();
+    match cli.command {
+        Commands::Verify { problem, map } => {
+            if !verify(&problem, &map)? {
+                std::process::exit(1);
+            }
+        }
+        Commands::Export {
+            problem,
+            out,
+            jobs,
+            batch,
+            delay_ms,
+        } => export(&problem, Path::new(&out), jobs, batch, delay_ms)?,
+        Commands::Pack { input, output } => {
+            let json =
+                std::fs::read_to_string(&input).with_context(|| format!("read {}", input))?;
+            let packed = archive::Session::from_json_replay(&json)?.encode()?;
+            std::fs::write(&output, &packed).with_context(|| format!("write {}", output))?;
+            eprintln!("{} bytes -> {} bytes", json.len(), packed.len());
+        }
+        Commands::Unpack { input, output } => {
+            let data = std::fs::read(&input).with_context(|| format!("read {}", input))?;
+            let json = archive::Session::decode(&data)?.to_json_replay();
+            std::fs::write(&output, serde_json::to_string(&json)?)
+                .with_context(|| format!("write {}", output))?;
+        }
+        Commands::BuildAgent {
+            target,
+            bin,
+            dest,
+            no_upload,
+        } => {
+            let path = build_agent(&target, &bin)?;
+            if no_upload {
+                println!("{}", path.display());
+            } else {
+                println!("{}", upload_agent(&path, &bin, &dest)?);
+            }
+        }
+        Commands::Tasks {
+            command:
+                TasksCommand::Export {
+                    since,
+                    format,
+                    out,
+                    batch,
+                    delay_ms,
+                },
+        } => {
+            let format: Format = format.parse()?;
+            let since = since.as_deref().map(parse_since).transpose()?;
+            let table = export_tasks(since, batch, delay_ms)?;
+            let file = std::fs::File::create(&out).with_context(|| format!("create {}", out))?;
+            table.write(format, std::io::BufWriter::new(file))?;
+            eprintln!("exported {} tasks to {}", table.num_rows(), out);
+        }
+    }
+    Ok(())
+}
+
+/// Builds `bin` for `target` with a static C runtime and returns the path of a
+/// stripped copy.
+fn build_agent(target: &str, bin: &str) -> Result<PathBuf> {
+    let mut rustflags = std::env::var("RUSTFLAGS").unwrap_or_default();
+    if !rustflags.contains("crt-static") {
+        rustflags.push_str(" -C target-feature=+crt-static");
+    }
+    let status = Command::new(std::env::var("CARGO").unwrap_or_else(|_| "cargo".to_string()))
+        .args(["build", "--release", "--target", target, "--bin", bin])
+        .env("RUSTFLAGS", rustflags.trim())
+        .status()
+        .context("run cargo")?;
+    anyhow::ensure!(status.success(), "cargo build failed: {}", status);
+
+    let target_dir = std::env::var("CARGO_TARGET_DIR").unwrap_or_else(|_| "target".to_string());
+    let built = Path::new(&target_dir)
+        .join(target)
+        .join("release")
+        .join(bin);
+    let stripped = built.with_file_name(format!("{}.stripped", bin));
+    let status = Command::new("strip")
+        .arg("-o")
+        .arg(&stripped)
+        .arg(&built)
+        .status()
+        .context("run strip")?;
+    anyhow::ensure!(status.success(), "strip failed: {}", status);
+    eprintln!(
+        "{}: {} bytes (stripped from {})",
+        stripped.display(),
+        std::fs::metadata(&stripped)?.len(),
+        std::fs::metadata(&built)?.len()
+    );
+    Ok(stripped)
+}
+
+/// Uploads the binary as `<dest><bin>-<md5>` and returns its `gs://` URL.
+fn upload_agent(path: &Path, bin: &str, dest: &str) -> Result<String> {
+    let data = std::fs::read(path).with_context(|| format!("read {}", path.display()))?;
+    let (bucket, prefix) = icfpc2025::gcp::gcs::parse_gs_url(dest)?;
+    let name = agent_object_name(&prefix, bin, &data);
+    let rt = tokio::runtime::Runtime::new()?;
+    rt.block_on(icfpc2025::gcp::gcs::upload_object(
+        &bucket,
+        &name,
+        &data,
+        "application/octet-stream",
+    ))?;
+    Ok(format!("gs://{}/{}", bucket, name))
+}
+
+/// Content-addressed object name, so uploading the same build twice is harmless
+/// and a task never runs a binary that was replaced under it.
+fn agent_object_name(prefix: &str, bin: &str, data: &[u8]) -> String {
+    let prefix = if prefix.is_empty() || prefix.ends_with('/') {
+        prefix.to_string()
+    } else {
+        format!("{}/", prefix)
+    };
+    format!("{}{}-{:x}", prefix, bin, md5::compute(data))
+}
+
+/// Returns `true` if the map reproduces every recorded exploration.
+fn verify(problem: &str, map_path: &str) -> Result<bool> {
+    let text = std::fs::read_to_string(map_path).with_context(|| format!("read {}", map_path))?;
+    let map = match serde_json::from_str::<MapFile>(&text).context("parse map JSON")? {
+        MapFile::Guess { map } | MapFile::Map(map) => map,
+    };
+    let guess = Guess::from(&map);
+
+    let rows = sql::select(
+        "
+        SELECT e.api_log_select_id AS sid,
+               e.api_log_request AS req,
+               e.api_log_response AS resp
+        FROM api_logs e
+        JOIN api_logs s
+          ON e.api_log_select_id = s.api_log_id
+            AND e.api_log_path = '/explore'
+            AND s.api_log_path = '/select'
+        WHERE s.api_log_request__problem_name = :problem
+          AND e.api_log_response_code = 200
+        ORDER BY e.api_log_id",
+        params! { "problem" => problem },
+    )?;
+
+    // Group explorations by session, keeping the log order.
+    let mut sessions: Vec<(i64, Explored)> = vec![];
+    for row in rows {
+        let sid = row.at::<i64>(0)?;
+        let req: api::ExploreLog = serde_json::from_str(&row.at::<String>(1)?)?;
+        let resp: api::ExploreResponse = serde_json::from_str(&row.at::<String>(2)?)?;
+        if req.plans.len() != resp.results.len() {
+            eprintln!("session {}: skipping malformed explore log", sid);
+            continue;
+        }
+        if sessions.last().is_none_or(|s| s.0 != sid) {
+            sessions.push((
+                sid,
+                Explored {
+                    plans: vec![],
+                    results: vec![],
+                },
+            ));
+        }
+        let explored = &mut sessions.last_mut().unwrap().1;
+        explored
+            .plans
+            .extend(req.plans.iter().map(|p| judge::parse_plan(p)));
+        explored.results.extend(resp.results);
+    }
+    if sessions.is_empty() {
+        eprintln!("no recorded explorations for {}", problem);
+        return Ok(true);
+    }
+
+    let mut failed = vec![];
+    for (sid, explored) in &sessions {
+        eprintln!("session {} ({} plans):", sid, explored.plans.len());
+        if judge::check_explore2(&guess, &explored.plans, &explored.results) {
+            eprintln!("  consistent");
+        } else {
+            failed.push(*sid);
+        }
+    }
+    if failed.is_empty() {
+        println!("OK: consistent with all {} sessions", sessions.len());
+        Ok(true)
+    } else {
+        println!(
+            "NG: inconsistent with {}/{} sessions: {}",
+            failed.len(),
+            sessions.len(),
+            failed.iter().join(", ")
+        );
+        Ok(false)
+    }
+}
+
+/// One line of the export manifest.
+#[derive(Serialize)]
+struct IndexEntry {
+    session_id: i64,
+    created: String,
+    file: String,
+    explores: usize,
+    plans: usize,
+    steps: usize,
+}
+
+/// Retries `f` with exponential backoff; the database refuses connections
+/// rather than queueing them when it is busy.
+fn with_retry<T>(what: &str, mut f: impl FnMut() -> Result<T>) -> Result<T> {
+    let mut wait = Duration::from_millis(500);
+    for attempt in 1.. {
+        match f() {
+            Ok(v) => return Ok(v),
+            Err(e) if attempt < 6 => {
+                eprintln!("{} failed (attempt {}): {:#}; retrying", what, attempt, e);
+                std::thread::sleep(wait);
+                wait *= 2;
+            }
+            Err(e) => return Err(e.context(format!("{} failed", what))),
+        }
+    }
+    unreachable!()
+}
+
+fn export(problem: &str, out: &Path, jobs: usize, batch: usize, delay_ms: u64) -> Result<()> {
+    let num_rooms = icfpc2025::problems::find_problem(problem)?.size;
+    std::fs::create_dir_all(out).with_context(|| format!("create {}", out.display()))?;
+    let selects = with_retry("list sessions", || {
+        sql::select(
+            "
+            SELECT api_log_id, api_log_created
+            FROM api_logs
+            WHERE api_log_path = '/select'
+              AND api_log_request__problem_name = :problem
+            ORDER BY api_log_id",
+            params! { "problem" => problem },
+        )
+    })?
+    .iter()
+    .map(|r| Ok((r.at::<i64>(0)?, r.at::<chrono::NaiveDateTime>(1)?)))
+    .collect::<Result<Vec<_>>>()?;
+    eprintln!("{}: {} sessions", problem, selects.len());
+
+    let pool = rayon::ThreadPoolBuilder::new()
+        .num_threads(jobs.max(1))
+        .build()?;
+    let entries = pool.install(|| {
+        selects
+            .par_chunks(batch.max(1))
+            .map(|chunk| {
+                let ids = chunk.iter().map(|(sid, _)| sid).join(",");
+                let rows = with_retry(&format!("sessions {}..", chunk[0].0), || {
+                    sql::select(
+                        &format!(
+                            "
+                            SELECT api_log_select_id, api_log_request, api_log_response
+                            FROM api_logs
+                            WHERE api_log_select_id IN ({})
+                              AND api_log_path = '/explore'
+                              AND api_log_response_code = 200
+                            ORDER BY api_log_id",
+                            ids
+                        ),
+                        (),
+                    )
+                })?;
+                std::thread::sleep(Duration::from_millis(delay_ms));
+                let mut entries = vec![];
+                for (sid, created) in chunk {
+                    let mut explores = 0;
+                    let mut plans = vec![];
+                    let mut results = vec![];
+                    for row in rows.iter().filter(|r| r.at::<i64>(0).ok() == Some(*sid)) {
+                        let req: api::ExploreLog = serde_json::from_str(&row.at::<String>(1)?)?;
+                        let resp: api::ExploreResponse =
+                            serde_json::from_str(&row.at::<String>(2)?)?;
+                        if req.plans.len() != resp.results.len() {
+                            eprintln!("session {}: skipping malformed explore log", sid);
+                            continue;
+                        }
+                        explores += 1;
+                        plans.extend(req.plans);
+                        results.extend(resp.results);
+                    }
+                    if explores == 0 {
+                        continue;
+                    }
+                    let file = format!("{}.json", sid);
+                    let replay = serde_json::json!({
+                        "problemName": problem,
+                        "numRooms": num_rooms,
+                        "plans": plans,
+                        "results": results,
+                    });
+                    std::fs::write(out.join(&file), replay.to_string())
+                        .with_context(|| format!("write {}", file))?;
+                    entries.push(IndexEntry {
+                        session_id: *sid,
+                        created: created.to_string(),
+                        file,
+                        explores,
+                        steps: plans.iter().map(|p| judge::parse_plan(p).len()).sum(),
+                        plans: plans.len(),
+                    });
+                }
+                Ok(entries)
+            })
+            .collect::<Result<Vec<_>>>()
+    })?;
+    let entries = entries.into_iter().flatten().collect::<Vec<_>>();
+    std::fs::write(
+        out.join("index.json"),
+        serde_json::to_string_pretty(&serde_json::json!({
+            "problem": problem,
+            "sessions": entries,
+        }))?,
+    )?;
+    println!(
+        "exported {} sessions with explorations to {}",
+        entries.len(),
+        out.display()
+    );
+    Ok(())
+}
+
+fn parse_since(s: &str) -> Result<chrono::NaiveDateTime> {
+    chrono::NaiveDateTime::parse_from_str(s, "%Y-%m-%d %H:%M:%S")
+        .or_else(|_| {
+            chrono::NaiveDate::parse_from_str(s, "%Y-%m-%d")
+                .map(|d| d.and_hms_opt(0, 0, 0).unwrap())
+        })
+        .with_context(|| format!("invalid --since: {}", s))
+}
+
+/// Reads tasks in `task_id` order, `batch` at a time, into export columns.
+fn export_tasks(
+    since: Option<chrono::NaiveDateTime>,
+    batch: usize,
+    delay_ms: u64,
+) -> Result<Table> {
+    let mut rows = vec![];
+    let mut after = 0i64;
+    loop {
+        let chunk = with_retry(&format!("tasks after {}", after), || {
+            sql::select(
+                "
+                SELECT t.task_id, t.agent_id, a.agent_name, t.problem_name, t.problem_variant,
+                       t.task_priority, t.task_host, t.task_exit_code, t.task_score,
+                       t.task_duration_ms, t.task_failed, t.task_created, t.task_updated,
+                       t.task_diagnostics
+                FROM tasks t
+                LEFT JOIN agents a ON a.agent_id = t.agent_id
+                WHERE t.task_id > :after
+                  AND (:since IS NULL OR t.task_updated >= :since)
+                ORDER BY t.task_id
+                LIMIT :batch",
+                params! { "after" => after, "since" => since, "batch" => batch.max(1) as u64 },
+            )
+        })?;
+        let Some(last) = chunk.last() else {
+            break;
+        };
+        after = last.get("task_id")?;
+        let done = chunk.len() < batch.max(1);
+        rows.extend(chunk);
+        eprintln!("{} tasks", rows.len());
+        if done {
+            break;
+        }
+        std::thread::sleep(Duration::from_millis(delay_ms));
+    }
+
+    let int = |name: &str| -> Result<Column> {
+        Ok(Column::Int64(
+            rows.iter()
+                .map(|r| r.get_option(name))
+                .collect::<Result<_>>()?,
+        ))
+    };
+    let text = |name: &str| -> Result<Column> {
+        Ok(Column::Utf8(
+            rows.iter()
+                .map(|r| r.get_option(name))
+                .collect::<Result<_>>()?,
+        ))
+    };
+    let time = |name: &str| -> Result<Column> {
+        Ok(Column::Timestamp(
+            rows.iter()
+                .map(|r| r.get_option(name))
+                .collect::<Result<_>>()?,
+        ))
+    };
+    let mut table = Table::new();
+    table
+        .push("task_id", int("task_id")?)
+        .push("agent_id", int("agent_id")?)
+        .push("agent_name", text("agent_name")?)
+        .push("problem_name", text("problem_name")?)
+        .push("problem_variant", int("problem_variant")?)
+        .push("task_priority", int("task_priority")?)
+        .push("task_host", text("task_host")?)
+        .push("task_exit_code", int("task_exit_code")?)
+        .push("task_score", int("task_score")?)
+        .push("task_duration_ms", int("task_duration_ms")?)
+        .push("task_failed", int("task_failed")?)
+        .push("task_created", time("task_created")?)
+        .push("task_updated", time("task_updated")?)
+        .push("task_diagnostics", text("task_diagnostics")?);
+
+    // Diagnostics keys that only ever hold numbers get their own column.
+    let diagnostics = rows
+        .iter()
+        .map(|r| {
+            let json: Option<String> = r.get_option("task_diagnostics")?;
+            Ok(json.and_then(|j| serde_json::from_str::<serde_json::Value>(&j).ok()))
+        })
+        .collect::<Result<Vec<_>>>()?;
+    let mut keys: Vec<String> = vec![];
+    for obj in diagnostics.iter().flatten().filter_map(|d| d.as_object()) {
+        for k in obj.keys() {
+            if !keys.contains(k) {
+                keys.push(k.clone());
+            }
+        }
+    }
+    for key in keys {
+        let values = diagnostics
+            .iter()
+            .map(|d| d.as_ref().and_then(|d| d.get(&key)))
+            .collect::<Vec<_>>();
+        if values
+            .iter()
+            .flatten()
+            .all(|v| v.is_number() || v.is_null())
+        {
+            table.push(
+                &format!("diag.{}", key),
+                Column::Double(values.iter().map(|v| v.and_then(|v| v.as_f64())).collect()),
+            );
+        }
+    }
+    Ok(table)
+}
@@ -127,7 +127,11 @@ fn main() {
         }
         // let super_guess = solve_no_marks::solve(judge.num_rooms() / D, &plans0, &labels0);
         let super_guess =
-            solve_no_marks::solve_cadical_multi(judge.num_rooms() / D, &plans0, &labels0, 50);
+            solve_no_marks::solve_cadical_multi(judge.num_rooms() / D, &plans0, &labels0, 50)
+                .unwrap_or_else(|e| {
+                    eprintln!("{:#}", e);
+                    std::process::exit(1)
+                });
         eprintln!("!!!! super_guess done");
         while plans[0].iter().all(|x| x.0.is_none()) {
             plans.remove(0);
@@ -304,7 +308,10 @@ fn main() {
         u = v;
     }
     // assert_eq!(cnf.sat.solve(), Some(true));
-    solve_no_marks::solve_cnf_parallel(&mut cnf, 25, 25);
+    if let Err(e) = solve_no_marks::solve_cnf_parallel(&mut cnf, 25, 25) {
+        eprintln!("{:#}", e);
+        std::process::exit(1);
+    }
     let mut guess = Guess {
         start: super_guess.start * D,
         graph: vec![[(!0, !0); 6]; judge.num_rooms()],
@@ -38,7 +38,7 @@ fn main() {
     let mut judge = icfpc2025::judge::get_judge_from_stdin();
     let H = judge.num_rooms() * 2; // 色を塗らずに動く回数
     let n = judge.num_rooms() / 3;
-    let mut plans = balanced_plan(judge.num_rooms() * 6, 6, &mut rng)
+    let mut plans = balanced_plan(judge.max_plan_len(), 6, &mut rng)
         .into_iter()
         .map(|e| (None, e))
         .collect_vec();
@@ -46,8 +46,7 @@ fn main() {
     for i in H..plans.len() {
         plans[i].0 = Some(cs[i - H]);
     }
-    let labels = judge.explore(&[plans.clone()])[0].clone();
-    assert_eq!(plans.len() + 1, labels.len());
+    let labels = judge.explore_batch(&[plans.clone()]).unwrap()[0].clone();
     let mut L = vec![0; n];
     for i in 0..n {
         L[i] = i % 4;
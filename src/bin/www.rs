@@ -20,15 +20,38 @@ async fn main() -> std::io::Result<()> {
         std::io::Error::other("GCP Auth error")
     })?;
 
+    if www::scores_ingester::enabled() {
+        www::scores_ingester::spawn();
+    }
+
+    let listener = www::listener::listener_from_env(&bind_address)?;
     eprintln!(
         "Starting server at: http://{}/leaderboard/global",
-        bind_address
+        listener.local_addr()?
     );
     HttpServer::new(|| {
         App::new()
             .route("/", web::get().to(www::handlers::index))
+            .route("/agents", web::get().to(www::handlers::agents::index))
+            .route("/agents/edit", web::get().to(www::handlers::agents::edit))
+            .service(
+                web::resource("/agents/save")
+                    .app_data(web::FormConfig::default().limit(1 << 20))
+                    .route(web::post().to(www::handlers::agents::save)),
+            )
+            .route(
+                "/agents/sweep",
+                web::post().to(www::handlers::agents::sweep_post),
+            )
             .route("/comm", web::get().to(www::handlers::comm))
             .route("/cron", web::get().to(www::handlers::cron::run))
+            .route("/cron/digest", web::get().to(www::handlers::digest::run))
+            .route("/cron/queue", web::get().to(www::handlers::queue::run))
+            .route("/digests", web::get().to(www::handlers::digest::index))
+            .route(
+                "/digests/{date}",
+                web::get().to(www::handlers::digest::show),
+            )
             .route(
                 "/leaderboard",
                 web::get().to(www::handlers::leaderboard::index),
@@ -37,6 +60,16 @@ async fn main() -> std::io::Result<()> {
                 "/leaderboard/{problem}",
                 web::get().to(www::handlers::leaderboard::show),
             )
+            .route(
+                "/leaderboard/{problem}/events",
+                web::get().to(www::handlers::leaderboard::events),
+            )
+            .route(
+                "/leaderboard/{problem}/history",
+                web::get().to(www::handlers::leaderboard::history),
+            )
+            .route("/owners", web::get().to(www::handlers::owners::index))
+            .route("/owners", web::post().to(www::handlers::owners::save))
             .route("/unlock", web::get().to(www::handlers::unlock::unlock_get))
             .route(
                 "/unlock",
@@ -51,11 +84,30 @@ async fn main() -> std::io::Result<()> {
                 web::post().to(www::handlers::api::post_explore),
             )
             .route("/api/guess", web::post().to(www::handlers::api::post_guess))
+            .route("/explores", web::get().to(www::handlers::explores::index))
+            .route("/costs", web::get().to(www::handlers::costs::index))
+            .route("/prefs", web::get().to(www::handlers::prefs::show))
+            .route("/prefs", web::post().to(www::handlers::prefs::save))
+            .route("/queue", web::get().to(www::handlers::queue::index))
+            .route("/replay", web::get().to(www::handlers::replay::show))
+            .service(
+                web::resource("/replay")
+                    // Explore logs easily exceed the default 16 KiB form limit.
+                    .app_data(web::FormConfig::default().limit(64 << 20))
+                    .route(web::post().to(www::handlers::replay::run)),
+            )
             .route("/task", web::get().to(www::handlers::task::show))
+            .route("/task/log", web::get().to(www::handlers::task::log))
+            .route(
+                "/task/requeue",
+                web::post().to(www::handlers::task::requeue),
+            )
+            .route("/task/cancel", web::post().to(www::handlers::task::cancel))
             .route("/tasks", web::get().to(www::handlers::tasks::index))
             .service(Files::new("/", "/www"))
     })
-    .bind(bind_address)?
+    .shutdown_timeout(www::listener::shutdown_timeout_secs())
+    .listen(listener)?
     .run()
     .await
 }
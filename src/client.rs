@@ -1,5 +1,20 @@
+//! # Shared HTTP Clients
+//!
+//! `CLIENT` (async) and `BLOCKING_CLIENT` are shared by everything that talks
+//! to the contest API or GCP. Requests sent with [`SendTracked::send_tracked`]
+//! (or its blocking twin) instead of `send` go through a small middleware:
+//!
+//! - a process-wide rate limiter, off by default. `UNAGI_RATE_LIMIT` (requests
+//!   per second) or [`set_rate_limit`] spaces out requests so that parallel
+//!   solvers in one process do not trip the contest server's throttling.
+//! - per-host counters of requests, statuses, bytes and latency (up to the
+//!   response headers), readable with [`stats`].
+
 use once_cell::sync::Lazy;
+use std::collections::BTreeMap;
 use std::net::{IpAddr, SocketAddr};
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
 
 pub static CLIENT: Lazy<reqwest::Client> = Lazy::new(reqwest::Client::new);
 
@@ -23,3 +38,219 @@ pub static BLOCKING_CLIENT: Lazy<reqwest::blocking::Client> = Lazy::new(|| {
         .build()
         .expect("failed to build blocking reqwest client")
 });
+
+/// Counters of the requests sent to one host.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct HostStats {
+    pub requests: u64,
+    /// Requests that got no response at all (connection errors, timeouts).
+    pub failures: u64,
+    /// Responses by HTTP status code.
+    pub statuses: BTreeMap<u16, u64>,
+    /// Request body bytes.
+    pub bytes_sent: u64,
+    /// Response bytes, as far as `Content-Length` tells.
+    pub bytes_received: u64,
+    pub total_latency: Duration,
+    pub max_latency: Duration,
+}
+
+impl HostStats {
+    pub fn mean_latency(&self) -> Duration {
+        if self.requests == 0 {
+            Duration::ZERO
+        } else {
+            self.total_latency / self.requests as u32
+        }
+    }
+
+    fn record(&mut self, sent: u64, status: Option<u16>, latency: Duration) {
+        self.requests += 1;
+        self.bytes_sent += sent;
+        match status {
+            Some(status) => *self.statuses.entry(status).or_default() += 1,
+            None => self.failures += 1,
+        }
+        self.total_latency += latency;
+        self.max_latency = self.max_latency.max(latency);
+    }
+}
+
+static STATS: Lazy<Mutex<BTreeMap<String, HostStats>>> = Lazy::new(Default::default);
+
+/// A snapshot of the counters of every host requested so far.
+pub fn stats() -> BTreeMap<String, HostStats> {
+    STATS.lock().unwrap().clone()
+}
+
+fn record(host: &str, sent: u64, status: Option<u16>, received: Option<u64>, latency: Duration) {
+    let mut stats = STATS.lock().unwrap();
+    let host = stats.entry(host.to_string()).or_default();
+    host.record(sent, status, latency);
+    host.bytes_received += received.unwrap_or(0);
+}
+
+/// Spaces requests at least `interval` apart by handing out send slots.
+#[derive(Debug)]
+struct RateLimiter {
+    interval: Option<Duration>,
+    next: Option<Instant>,
+}
+
+impl RateLimiter {
+    /// Reserves the next slot at or after `now` and returns it.
+    fn reserve(&mut self, now: Instant) -> Instant {
+        let Some(interval) = self.interval else {
+            return now;
+        };
+        let slot = self.next.map_or(now, |next| next.max(now));
+        self.next = Some(slot + interval);
+        slot
+    }
+}
+
+fn rate_limit_interval(per_sec: Option<f64>) -> Option<Duration> {
+    per_sec
+        .filter(|&r| r > 0.0)
+        .map(|r| Duration::from_secs_f64(1.0 / r))
+}
+
+static LIMITER: Lazy<Mutex<RateLimiter>> = Lazy::new(|| {
+    let per_sec = std::env::var("UNAGI_RATE_LIMIT")
+        .ok()
+        .and_then(|v| v.parse().ok());
+    Mutex::new(RateLimiter {
+        interval: rate_limit_interval(per_sec),
+        next: None,
+    })
+});
+
+/// Sets the process-wide limit in requests per second (`None` for no limit),
+/// overriding `UNAGI_RATE_LIMIT`.
+pub fn set_rate_limit(per_sec: Option<f64>) {
+    let mut limiter = LIMITER.lock().unwrap();
+    limiter.interval = rate_limit_interval(per_sec);
+    limiter.next = None;
+}
+
+/// How long to wait for this request's slot.
+fn rate_limit_delay() -> Duration {
+    let now = Instant::now();
+    LIMITER.lock().unwrap().reserve(now) - now
+}
+
+/// `send` with rate limiting and [`stats`] for async requests.
+pub trait SendTracked {
+    fn send_tracked(
+        self,
+    ) -> impl std::future::Future<Output = reqwest::Result<reqwest::Response>> + Send;
+}
+
+impl SendTracked for reqwest::RequestBuilder {
+    async fn send_tracked(self) -> reqwest::Result<reqwest::Response> {
+        let (client, req) = self.build_split();
+        let req = req?;
+        let host = req.url().host_str().unwrap_or_default().to_string();
+        let sent = req
+            .body()
+            .and_then(|b| b.as_bytes())
+            .map_or(0, |b| b.len() as u64);
+        let delay = rate_limit_delay();
+        if !delay.is_zero() {
+            #[cfg(feature = "tokio")]
+            tokio::time::sleep(delay).await;
+            #[cfg(not(feature = "tokio"))]
+            std::thread::sleep(delay);
+        }
+        let start = Instant::now();
+        let res = client.execute(req).await;
+        let latency = start.elapsed();
+        match &res {
+            Ok(r) => record(
+                &host,
+                sent,
+                Some(r.status().as_u16()),
+                r.content_length(),
+                latency,
+            ),
+            Err(_) => record(&host, sent, None, None, latency),
+        }
+        res
+    }
+}
+
+/// `send` with rate limiting and [`stats`] for blocking requests.
+pub trait SendTrackedBlocking {
+    fn send_tracked(self) -> reqwest::Result<reqwest::blocking::Response>;
+}
+
+impl SendTrackedBlocking for reqwest::blocking::RequestBuilder {
+    fn send_tracked(self) -> reqwest::Result<reqwest::blocking::Response> {
+        let (client, req) = self.build_split();
+        let req = req?;
+        let host = req.url().host_str().unwrap_or_default().to_string();
+        let sent = req
+            .body()
+            .and_then(|b| b.as_bytes())
+            .map_or(0, |b| b.len() as u64);
+        std::thread::sleep(rate_limit_delay());
+        let start = Instant::now();
+        let res = client.execute(req);
+        let latency = start.elapsed();
+        match &res {
+            Ok(r) => record(
+                &host,
+                sent,
+                Some(r.status().as_u16()),
+                r.content_length(),
+                latency,
+            ),
+            Err(_) => record(&host, sent, None, None, latency),
+        }
+        res
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn limiter_spaces_out_slots() {
+        let now = Instant::now();
+        let mut off = RateLimiter {
+            interval: None,
+            next: None,
+        };
+        assert_eq!(off.reserve(now), now);
+        assert_eq!(off.reserve(now), now);
+
+        let ms = Duration::from_millis;
+        let mut limiter = RateLimiter {
+            interval: rate_limit_interval(Some(10.0)),
+            next: None,
+        };
+        assert_eq!(limiter.reserve(now), now);
+        assert_eq!(limiter.reserve(now), now + ms(100));
+        assert_eq!(limiter.reserve(now + ms(50)), now + ms(200));
+        // An idle limiter does not bank slots for a burst.
+        assert_eq!(limiter.reserve(now + ms(1000)), now + ms(1000));
+        assert_eq!(limiter.reserve(now + ms(1000)), now + ms(1100));
+        assert_eq!(rate_limit_interval(Some(0.0)), None);
+    }
+
+    #[test]
+    fn host_stats_count_statuses_and_failures() {
+        let ms = Duration::from_millis;
+        let mut stats = HostStats::default();
+        stats.record(10, Some(200), ms(30));
+        stats.record(0, Some(429), ms(10));
+        stats.record(5, None, ms(20));
+        assert_eq!(stats.requests, 3);
+        assert_eq!(stats.failures, 1);
+        assert_eq!(stats.statuses, BTreeMap::from([(200, 1), (429, 1)]));
+        assert_eq!(stats.bytes_sent, 15);
+        assert_eq!(stats.mean_latency(), ms(20));
+        assert_eq!(stats.max_latency, ms(30));
+    }
+}
@@ -0,0 +1,429 @@
+//! # CNF Preprocessing
+//!
+//! Simplifies a clause set before it is handed to the external portfolio
+//! solvers (`solve_no_marks::launch_portfolio`). kissat and CaDiCaL both run
+//! their own preprocessing, so without this every portfolio member repeats the
+//! same work on an identical formula.
+//!
+//! Passes: unit propagation, subsumption and bounded variable elimination
+//! (BVE). Eliminated variables are not reconstructed here. The caller keeps
+//! the original clauses, injects the model restricted to the variables that
+//! still occur ([`Preprocessed::restrict_model`]) and lets its own CaDiCaL
+//! instance assign the rest, which always succeeds because BVE preserves
+//! satisfiability for every model of the simplified formula.
+//!
+//! [`normalize`] is the cheap, equivalence-preserving part on its own (sorted
+//! and deduplicated clauses, subsumption); `solve_no_marks::Cnf::write_dimacs`
+//! applies it to every DIMACS file it writes.
+
+use std::collections::HashSet;
+use std::io::Write;
+use std::path::Path;
+
+/// Limits for bounded variable elimination.
+#[derive(Clone, Debug)]
+pub struct Options {
+    /// Variables with more occurrences than this in either polarity are kept.
+    pub max_occurrences: usize,
+    /// Eliminations producing a longer resolvent are rejected.
+    pub max_resolvent_len: usize,
+    /// Number of subsumption + BVE rounds.
+    pub rounds: usize,
+}
+
+impl Default for Options {
+    fn default() -> Self {
+        Options {
+            max_occurrences: 16,
+            max_resolvent_len: 24,
+            rounds: 3,
+        }
+    }
+}
+
+/// A simplified clause set over the same variable numbering as the input.
+pub struct Preprocessed {
+    pub num_vars: usize,
+    pub clauses: Vec<Vec<i32>>,
+    /// Variables removed by BVE.
+    pub eliminated: Vec<i32>,
+}
+
+impl Preprocessed {
+    pub fn write_dimacs(&self, path: &Path) -> std::io::Result<()> {
+        let mut f = std::io::BufWriter::new(std::fs::File::create(path)?);
+        writeln!(f, "p cnf {} {}", self.num_vars, self.clauses.len())?;
+        for c in &self.clauses {
+            for &l in c {
+                write!(f, "{} ", l)?;
+            }
+            writeln!(f, "0")?;
+        }
+        f.flush()
+    }
+
+    /// Drops literals of variables that no longer occur in the simplified
+    /// clauses. Solvers print arbitrary values for those, which need not be
+    /// consistent with the original formula.
+    pub fn restrict_model(&self, model: &HashSet<i32>) -> HashSet<i32> {
+        let occurring: HashSet<i32> = self.clauses.iter().flatten().map(|l| l.abs()).collect();
+        model
+            .iter()
+            .copied()
+            .filter(|l| occurring.contains(&l.abs()))
+            .collect()
+    }
+}
+
+#[inline]
+fn lit_index(l: i32) -> usize {
+    (l.unsigned_abs() as usize) * 2 + (l < 0) as usize
+}
+
+struct State {
+    clauses: Vec<Vec<i32>>,
+    alive: Vec<bool>,
+    /// Clause indices per literal; may contain dead clauses.
+    occ: Vec<Vec<usize>>,
+    units: Vec<i32>,
+    assigned: Vec<bool>,
+    eliminated: Vec<bool>,
+    conflict: bool,
+}
+
+impl State {
+    fn new(num_vars: usize) -> Self {
+        State {
+            clauses: vec![],
+            alive: vec![],
+            occ: vec![vec![]; 2 * num_vars + 2],
+            units: vec![],
+            assigned: vec![false; num_vars + 1],
+            eliminated: vec![false; num_vars + 1],
+            conflict: false,
+        }
+    }
+
+    /// Adds a clause after sorting and deduplicating it; tautologies are dropped.
+    fn add(&mut self, mut c: Vec<i32>) {
+        c.sort_unstable();
+        c.dedup();
+        if c.iter().any(|&l| c.binary_search(&-l).is_ok()) {
+            return;
+        }
+        if c.is_empty() {
+            self.conflict = true;
+        }
+        let ci = self.clauses.len();
+        for &l in &c {
+            self.occ[lit_index(l)].push(ci);
+        }
+        self.clauses.push(c);
+        self.alive.push(true);
+    }
+
+    fn occurrences(&self, l: i32) -> Vec<usize> {
+        self.occ[lit_index(l)]
+            .iter()
+            .copied()
+            .filter(|&ci| self.alive[ci])
+            .collect()
+    }
+
+    /// Assigns all unit clauses and simplifies the rest. Units are moved out
+    /// of the clause database into `units`.
+    fn propagate(&mut self) {
+        let mut queue: Vec<usize> = (0..self.clauses.len())
+            .filter(|&ci| self.alive[ci] && self.clauses[ci].len() == 1)
+            .collect();
+        while let Some(ci) = queue.pop() {
+            if self.conflict {
+                return;
+            }
+            if !self.alive[ci] {
+                continue;
+            }
+            let l = self.clauses[ci][0];
+            self.alive[ci] = false;
+            if self.assigned[l.unsigned_abs() as usize] {
+                // Either a duplicate unit or, if `l` was stripped as false, a
+                // clause that became empty and was already recorded.
+                continue;
+            }
+            self.assigned[l.unsigned_abs() as usize] = true;
+            self.units.push(l);
+            for cj in self.occurrences(l) {
+                self.alive[cj] = false;
+            }
+            for cj in self.occurrences(-l) {
+                self.alive[cj] = false;
+                let c = self.clauses[cj]
+                    .iter()
+                    .copied()
+                    .filter(|&x| x != -l)
+                    .collect::<Vec<_>>();
+                let len = c.len();
+                self.add(c);
+                if len == 1 {
+                    queue.push(self.clauses.len() - 1);
+                }
+            }
+        }
+    }
+
+    /// Removes clauses that are supersets of another clause.
+    fn subsume(&mut self) -> bool {
+        let mut order = (0..self.clauses.len())
+            .filter(|&ci| self.alive[ci])
+            .collect::<Vec<_>>();
+        order.sort_by_key(|&ci| self.clauses[ci].len());
+        let mut changed = false;
+        for ci in order {
+            if !self.alive[ci] {
+                continue;
+            }
+            let c = &self.clauses[ci];
+            let Some(&pivot) = c.iter().min_by_key(|&&l| self.occ[lit_index(l)].len()) else {
+                continue;
+            };
+            for &cj in &self.occ[lit_index(pivot)] {
+                if cj != ci
+                    && self.alive[cj]
+                    && self.clauses[cj].len() >= c.len()
+                    && is_subset(c, &self.clauses[cj])
+                {
+                    self.alive[cj] = false;
+                    changed = true;
+                }
+            }
+        }
+        changed
+    }
+
+    /// Eliminates variables whose resolvents do not outnumber their clauses.
+    fn eliminate(&mut self, num_vars: usize, opts: &Options) -> bool {
+        let mut vars = (1..=num_vars as i32)
+            .filter(|&v| !self.assigned[v as usize] && !self.eliminated[v as usize])
+            .map(|v| (self.occurrences(v).len() * self.occurrences(-v).len(), v))
+            .collect::<Vec<_>>();
+        vars.sort_unstable();
+        let mut changed = false;
+        for (_, v) in vars {
+            if self.conflict {
+                break;
+            }
+            let pos = self.occurrences(v);
+            let neg = self.occurrences(-v);
+            if pos.is_empty() && neg.is_empty() {
+                continue;
+            }
+            if pos.len() > opts.max_occurrences || neg.len() > opts.max_occurrences {
+                continue;
+            }
+            let Some(resolvents) = self.resolvents(v, &pos, &neg, opts) else {
+                continue;
+            };
+            for &ci in pos.iter().chain(&neg) {
+                self.alive[ci] = false;
+            }
+            for r in resolvents {
+                self.add(r);
+            }
+            self.eliminated[v as usize] = true;
+            changed = true;
+        }
+        changed
+    }
+
+    /// Non-tautological resolvents on `v`, or `None` if eliminating `v` would
+    /// grow the formula or exceed the resolvent length limit.
+    fn resolvents(
+        &self,
+        v: i32,
+        pos: &[usize],
+        neg: &[usize],
+        opts: &Options,
+    ) -> Option<Vec<Vec<i32>>> {
+        let mut out = vec![];
+        for &p in pos {
+            for &n in neg {
+                let mut r = self.clauses[p]
+                    .iter()
+                    .chain(&self.clauses[n])
+                    .copied()
+                    .filter(|&l| l != v && l != -v)
+                    .collect::<Vec<_>>();
+                r.sort_unstable();
+                r.dedup();
+                if r.iter().any(|&l| r.binary_search(&-l).is_ok()) {
+                    continue;
+                }
+                if r.len() > opts.max_resolvent_len || out.len() >= pos.len() + neg.len() {
+                    return None;
+                }
+                out.push(r);
+            }
+        }
+        Some(out)
+    }
+}
+
+/// `a ⊆ b` for sorted literal lists.
+fn is_subset(a: &[i32], b: &[i32]) -> bool {
+    let mut j = 0;
+    for &x in a {
+        while j < b.len() && b[j] < x {
+            j += 1;
+        }
+        if j == b.len() || b[j] != x {
+            return false;
+        }
+        j += 1;
+    }
+    true
+}
+
+/// Simplifies `clauses` over variables `1..=num_vars`.
+///
+/// If a conflict is found the result contains the empty clause, so external
+/// solvers report UNSAT exactly as they would on the original formula.
+pub fn preprocess(num_vars: usize, clauses: &[Vec<i32>], opts: &Options) -> Preprocessed {
+    let mut st = State::new(num_vars);
+    for c in clauses {
+        st.add(c.clone());
+    }
+    st.propagate();
+    for _ in 0..opts.rounds {
+        if st.conflict {
+            break;
+        }
+        let subsumed = st.subsume();
+        let eliminated = st.eliminate(num_vars, opts);
+        // Resolvents may be units.
+        st.propagate();
+        if !subsumed && !eliminated {
+            break;
+        }
+    }
+    let mut out: Vec<Vec<i32>> = st.units.iter().map(|&l| vec![l]).collect();
+    if st.conflict {
+        out.push(vec![]);
+    }
+    out.extend(
+        (0..st.clauses.len())
+            .filter(|&ci| st.alive[ci] && !st.clauses[ci].is_empty())
+            .map(|ci| st.clauses[ci].clone()),
+    );
+    Preprocessed {
+        num_vars,
+        clauses: out,
+        eliminated: (1..=num_vars as i32)
+            .filter(|&v| st.eliminated[v as usize])
+            .collect(),
+    }
+}
+
+/// Clauses equivalent to `clauses` over variables `1..=num_vars`, each with
+/// sorted and deduplicated literals, without tautologies, duplicates or
+/// clauses subsumed by another. Unlike [`preprocess`] no variable is assigned
+/// or eliminated, so models carry over unchanged in both directions. The
+/// remaining clauses keep their original order.
+pub fn normalize(num_vars: usize, clauses: &[Vec<i32>]) -> Vec<Vec<i32>> {
+    let mut st = State::new(num_vars);
+    for c in clauses {
+        st.add(c.clone());
+    }
+    st.subsume();
+    (0..st.clauses.len())
+        .filter(|&ci| st.alive[ci])
+        .map(|ci| st.clauses[ci].clone())
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rand::prelude::*;
+
+    fn satisfies(clauses: &[Vec<i32>], bits: u32) -> bool {
+        clauses.iter().all(|c| {
+            c.iter()
+                .any(|&l| ((bits >> (l.unsigned_abs() - 1)) & 1 == 1) == (l > 0))
+        })
+    }
+
+    #[test]
+    fn normalize_keeps_models_and_drops_redundant_clauses() {
+        let clauses = vec![
+            vec![2, -1],
+            vec![-1, 2, 2],
+            vec![3, 2, -1],
+            vec![1, -1, 3],
+            vec![-3],
+            vec![-3, 1],
+        ];
+        assert_eq!(normalize(3, &clauses), vec![vec![-1, 2], vec![-3]]);
+
+        let mut rng = rand_chacha::ChaCha12Rng::seed_from_u64(11);
+        let n = 8;
+        for _ in 0..200 {
+            let m = rng.random_range(5..40);
+            let clauses = (0..m)
+                .map(|_| {
+                    let k = rng.random_range(1..5);
+                    (0..k)
+                        .map(|_| {
+                            let v = rng.random_range(1..=n as i32);
+                            if rng.random_bool(0.5) { v } else { -v }
+                        })
+                        .collect::<Vec<_>>()
+                })
+                .collect::<Vec<_>>();
+            let normalized = normalize(n, &clauses);
+            assert!(normalized.len() <= clauses.len());
+            for b in 0..1u32 << n {
+                assert_eq!(satisfies(&clauses, b), satisfies(&normalized, b));
+            }
+        }
+    }
+
+    #[test]
+    fn preprocess_preserves_models() {
+        let mut rng = rand_chacha::ChaCha12Rng::seed_from_u64(7);
+        let n = 10;
+        for _ in 0..200 {
+            let m = rng.random_range(10..45);
+            let clauses = (0..m)
+                .map(|_| {
+                    let k = rng.random_range(1..4);
+                    (0..k)
+                        .map(|_| {
+                            let v = rng.random_range(1..=n as i32);
+                            if rng.random_bool(0.5) { v } else { -v }
+                        })
+                        .collect::<Vec<_>>()
+                })
+                .collect::<Vec<_>>();
+            let pre = preprocess(n, &clauses, &Options::default());
+            let sat = (0..1u32 << n).any(|b| satisfies(&clauses, b));
+            let pre_models = (0..1u32 << n)
+                .filter(|&b| satisfies(&pre.clauses, b))
+                .collect::<Vec<_>>();
+            assert_eq!(sat, !pre_models.is_empty());
+            // Every restricted model of the simplified formula extends to a
+            // model of the original one.
+            for &b in pre_models.iter().take(8) {
+                let model = (1..=n as i32)
+                    .map(|v| if (b >> (v - 1)) & 1 == 1 { v } else { -v })
+                    .collect::<HashSet<_>>();
+                let fixed = pre.restrict_model(&model);
+                assert!((0..1u32 << n).any(|b2| {
+                    satisfies(&clauses, b2)
+                        && fixed
+                            .iter()
+                            .all(|&l| ((b2 >> (l.unsigned_abs() - 1)) & 1 == 1) == (l > 0))
+                }));
+            }
+        }
+    }
+}
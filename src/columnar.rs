@@ -0,0 +1,489 @@
+//! # Columnar Export
+//!
+//! A small in-memory table that can be written as CSV or as a Parquet file, so
+//! task results can be analyzed in notebooks (pandas, polars, DuckDB) instead
+//! of running analytical queries against the contest database.
+//!
+//! The Parquet writer covers exactly what the exports need: one row group,
+//! one uncompressed `PLAIN` data page per column, and every column `OPTIONAL`
+//! (NULLs are encoded as definition levels). The footer is Thrift compact
+//! protocol, written by hand below; the tests read the output back with the
+//! `parquet` crate.
+
+use anyhow::{Result, bail};
+use chrono::NaiveDateTime;
+use std::io::Write;
+use std::str::FromStr;
+
+/// Values of one column; `None` is NULL.
+#[derive(Clone, Debug, PartialEq)]
+pub enum Column {
+    Int64(Vec<Option<i64>>),
+    Double(Vec<Option<f64>>),
+    Utf8(Vec<Option<String>>),
+    /// Written to Parquet as `TIMESTAMP_MILLIS` (no time zone, as stored in MySQL).
+    Timestamp(Vec<Option<NaiveDateTime>>),
+}
+
+impl Column {
+    pub fn len(&self) -> usize {
+        match self {
+            Column::Int64(v) => v.len(),
+            Column::Double(v) => v.len(),
+            Column::Utf8(v) => v.len(),
+            Column::Timestamp(v) => v.len(),
+        }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    fn is_null(&self, i: usize) -> bool {
+        match self {
+            Column::Int64(v) => v[i].is_none(),
+            Column::Double(v) => v[i].is_none(),
+            Column::Utf8(v) => v[i].is_none(),
+            Column::Timestamp(v) => v[i].is_none(),
+        }
+    }
+
+    fn csv_cell(&self, i: usize) -> String {
+        match self {
+            Column::Int64(v) => v[i].map(|x| x.to_string()),
+            Column::Double(v) => v[i].map(|x| x.to_string()),
+            Column::Utf8(v) => v[i].as_deref().map(csv_escape),
+            Column::Timestamp(v) => v[i].map(|t| t.format("%Y-%m-%d %H:%M:%S").to_string()),
+        }
+        .unwrap_or_default()
+    }
+}
+
+fn csv_escape(s: &str) -> String {
+    if s.contains([',', '"', '\n', '\r']) {
+        format!("\"{}\"", s.replace('"', "\"\""))
+    } else {
+        s.to_string()
+    }
+}
+
+/// Output formats of [`Table::write`].
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Format {
+    Csv,
+    Parquet,
+}
+
+impl FromStr for Format {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        Ok(match s {
+            "csv" => Format::Csv,
+            "parquet" => Format::Parquet,
+            _ => bail!("unknown format: {} (expected csv or parquet)", s),
+        })
+    }
+}
+
+/// Named columns of equal length.
+#[derive(Clone, Debug, Default)]
+pub struct Table {
+    columns: Vec<(String, Column)>,
+}
+
+impl Table {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Appends a column; it must have as many rows as the columns before it.
+    pub fn push(&mut self, name: &str, column: Column) -> &mut Self {
+        if let Some((first, c)) = self.columns.first() {
+            assert_eq!(
+                c.len(),
+                column.len(),
+                "column {} has a different length from {}",
+                name,
+                first
+            );
+        }
+        self.columns.push((name.to_string(), column));
+        self
+    }
+
+    pub fn num_rows(&self) -> usize {
+        self.columns.first().map_or(0, |(_, c)| c.len())
+    }
+
+    pub fn write<W: Write>(&self, format: Format, w: W) -> Result<()> {
+        match format {
+            Format::Csv => self.write_csv(w),
+            Format::Parquet => self.write_parquet(w),
+        }
+    }
+
+    /// RFC 4180 CSV with a header line; NULLs are empty cells.
+    pub fn write_csv<W: Write>(&self, mut w: W) -> Result<()> {
+        let header = self.columns.iter().map(|(n, _)| csv_escape(n));
+        writeln!(w, "{}", header.collect::<Vec<_>>().join(","))?;
+        for i in 0..self.num_rows() {
+            let row = self.columns.iter().map(|(_, c)| c.csv_cell(i));
+            writeln!(w, "{}", row.collect::<Vec<_>>().join(","))?;
+        }
+        Ok(())
+    }
+
+    pub fn write_parquet<W: Write>(&self, mut w: W) -> Result<()> {
+        const MAGIC: &[u8] = b"PAR1";
+        let n = self.num_rows();
+        w.write_all(MAGIC)?;
+        let mut offset = MAGIC.len() as i64;
+        // (column index, data page offset, chunk size)
+        let mut chunks = vec![];
+        for (i, (_, column)) in self.columns.iter().enumerate() {
+            let page = data_page(column);
+            let mut header = Thrift::default();
+            header.i32(1, 0); // type: DATA_PAGE
+            header.i32(2, page.len() as i32); // uncompressed_page_size
+            header.i32(3, page.len() as i32); // compressed_page_size
+            header.begin_struct(5); // data_page_header
+            header.i32(1, n as i32); // num_values
+            header.i32(2, ENCODING_PLAIN);
+            header.i32(3, ENCODING_RLE); // definition levels
+            header.i32(4, ENCODING_RLE); // repetition levels
+            header.end_struct();
+            header.stop();
+            w.write_all(&header.buf)?;
+            w.write_all(&page)?;
+            let size = (header.buf.len() + page.len()) as i64;
+            chunks.push((i, offset, size));
+            offset += size;
+        }
+
+        let mut meta = Thrift::default();
+        meta.i32(1, 1); // version
+        meta.list(2, THRIFT_STRUCT, self.columns.len() + 1); // schema
+        meta.begin_elem();
+        meta.binary(4, b"schema");
+        meta.i32(5, self.columns.len() as i32); // num_children
+        meta.end_struct();
+        for (name, column) in &self.columns {
+            meta.begin_elem();
+            meta.i32(1, physical_type(column));
+            meta.i32(3, 1); // repetition_type: OPTIONAL
+            meta.binary(4, name.as_bytes());
+            match column {
+                Column::Utf8(_) => meta.i32(6, 0),      // converted_type: UTF8
+                Column::Timestamp(_) => meta.i32(6, 9), // converted_type: TIMESTAMP_MILLIS
+                _ => {}
+            }
+            meta.end_struct();
+        }
+        meta.i64(3, n as i64); // num_rows
+        meta.list(4, THRIFT_STRUCT, 1); // row_groups
+        meta.begin_elem();
+        meta.list(1, THRIFT_STRUCT, chunks.len()); // columns
+        for &(i, page_offset, size) in &chunks {
+            let (name, column) = &self.columns[i];
+            meta.begin_elem();
+            meta.i64(2, page_offset); // file_offset
+            meta.begin_struct(3); // meta_data
+            meta.i32(1, physical_type(column));
+            meta.list(2, THRIFT_I32, 2); // encodings
+            meta.list_i32(ENCODING_PLAIN);
+            meta.list_i32(ENCODING_RLE);
+            meta.list(3, THRIFT_BINARY, 1); // path_in_schema
+            meta.list_binary(name.as_bytes());
+            meta.i32(4, 0); // codec: UNCOMPRESSED
+            meta.i64(5, n as i64); // num_values
+            meta.i64(6, size); // total_uncompressed_size
+            meta.i64(7, size); // total_compressed_size
+            meta.i64(9, page_offset); // data_page_offset
+            meta.end_struct();
+            meta.end_struct();
+        }
+        meta.i64(2, chunks.iter().map(|c| c.2).sum()); // total_byte_size
+        meta.i64(3, n as i64); // num_rows
+        meta.end_struct();
+        meta.binary(6, b"icfpc2025 columnar"); // created_by
+        meta.stop();
+
+        w.write_all(&meta.buf)?;
+        w.write_all(&(meta.buf.len() as u32).to_le_bytes())?;
+        w.write_all(MAGIC)?;
+        Ok(())
+    }
+}
+
+const ENCODING_PLAIN: i32 = 0;
+const ENCODING_RLE: i32 = 3;
+
+fn physical_type(column: &Column) -> i32 {
+    match column {
+        Column::Int64(_) | Column::Timestamp(_) => 2,
+        Column::Double(_) => 5,
+        Column::Utf8(_) => 6, // BYTE_ARRAY
+    }
+}
+
+/// Body of a v1 data page: length-prefixed definition levels (one bit-packed
+/// run, bit width 1) followed by the non-NULL values in `PLAIN` encoding.
+fn data_page(column: &Column) -> Vec<u8> {
+    let n = column.len();
+    let groups = n.div_ceil(8);
+    let mut levels = vec![];
+    put_varint(&mut levels, ((groups as u64) << 1) | 1);
+    let mut packed = vec![0u8; groups];
+    for i in 0..n {
+        if !column.is_null(i) {
+            packed[i / 8] |= 1 << (i % 8);
+        }
+    }
+    levels.extend(packed);
+
+    let mut page = (levels.len() as u32).to_le_bytes().to_vec();
+    page.extend(levels);
+    match column {
+        Column::Int64(v) => v
+            .iter()
+            .flatten()
+            .for_each(|x| page.extend(x.to_le_bytes())),
+        Column::Double(v) => v
+            .iter()
+            .flatten()
+            .for_each(|x| page.extend(x.to_le_bytes())),
+        Column::Utf8(v) => v.iter().flatten().for_each(|s| {
+            page.extend((s.len() as u32).to_le_bytes());
+            page.extend(s.as_bytes());
+        }),
+        Column::Timestamp(v) => v.iter().flatten().for_each(|t| {
+            page.extend(t.and_utc().timestamp_millis().to_le_bytes());
+        }),
+    }
+    page
+}
+
+fn put_varint(buf: &mut Vec<u8>, mut v: u64) {
+    while v >= 0x80 {
+        buf.push((v as u8) | 0x80);
+        v >>= 7;
+    }
+    buf.push(v as u8);
+}
+
+const THRIFT_I32: u8 = 5;
+const THRIFT_I64: u8 = 6;
+const THRIFT_BINARY: u8 = 8;
+const THRIFT_LIST: u8 = 9;
+const THRIFT_STRUCT: u8 = 12;
+
+/// Thrift compact protocol writer for the few field kinds Parquet metadata uses.
+#[derive(Default)]
+struct Thrift {
+    buf: Vec<u8>,
+    /// Last field id of each open struct (the outermost one is implicit).
+    last: Vec<i16>,
+}
+
+impl Thrift {
+    fn field(&mut self, id: i16, ty: u8) {
+        if self.last.is_empty() {
+            self.last.push(0);
+        }
+        let last = self.last.last_mut().unwrap();
+        let delta = id - *last;
+        if (1..=15).contains(&delta) {
+            self.buf.push(((delta as u8) << 4) | ty);
+        } else {
+            self.buf.push(ty);
+            put_varint(&mut self.buf, ((id << 1) ^ (id >> 15)) as u16 as u64);
+        }
+        *last = id;
+    }
+
+    fn i32(&mut self, id: i16, v: i32) {
+        self.field(id, THRIFT_I32);
+        put_varint(&mut self.buf, ((v << 1) ^ (v >> 31)) as u32 as u64);
+    }
+
+    fn i64(&mut self, id: i16, v: i64) {
+        self.field(id, THRIFT_I64);
+        put_varint(&mut self.buf, ((v << 1) ^ (v >> 63)) as u64);
+    }
+
+    fn binary(&mut self, id: i16, v: &[u8]) {
+        self.field(id, THRIFT_BINARY);
+        self.list_binary(v);
+    }
+
+    fn begin_struct(&mut self, id: i16) {
+        self.field(id, THRIFT_STRUCT);
+        self.last.push(0);
+    }
+
+    fn end_struct(&mut self) {
+        self.buf.push(0);
+        self.last.pop();
+    }
+
+    /// Terminates the outermost struct.
+    fn stop(&mut self) {
+        self.buf.push(0);
+    }
+
+    fn list(&mut self, id: i16, elem: u8, len: usize) {
+        self.field(id, THRIFT_LIST);
+        if len < 15 {
+            self.buf.push(((len as u8) << 4) | elem);
+        } else {
+            self.buf.push(0xf0 | elem);
+            put_varint(&mut self.buf, len as u64);
+        }
+    }
+
+    /// Starts a struct element of a list; close it with [`Thrift::end_struct`].
+    fn begin_elem(&mut self) {
+        if self.last.is_empty() {
+            self.last.push(0);
+        }
+        self.last.push(0);
+    }
+
+    fn list_i32(&mut self, v: i32) {
+        put_varint(&mut self.buf, ((v << 1) ^ (v >> 31)) as u32 as u64);
+    }
+
+    fn list_binary(&mut self, v: &[u8]) {
+        put_varint(&mut self.buf, v.len() as u64);
+        self.buf.extend_from_slice(v);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn sample() -> Table {
+        let t = NaiveDateTime::parse_from_str("2025-09-06 12:00:00", "%Y-%m-%d %H:%M:%S").unwrap();
+        let mut table = Table::new();
+        table
+            .push("task_id", Column::Int64(vec![Some(1), Some(2), Some(3)]))
+            .push("score", Column::Double(vec![Some(0.5), None, Some(2.0)]))
+            .push(
+                "agent",
+                Column::Utf8(vec![Some("a,b".into()), Some("say \"hi\"".into()), None]),
+            )
+            .push("created", Column::Timestamp(vec![Some(t), None, None]));
+        table
+    }
+
+    #[test]
+    fn csv_quotes_and_nulls() {
+        let mut out = vec![];
+        sample().write(Format::Csv, &mut out).unwrap();
+        assert_eq!(
+            String::from_utf8(out).unwrap(),
+            "task_id,score,agent,created\n\
+             1,0.5,\"a,b\",2025-09-06 12:00:00\n\
+             2,,\"say \"\"hi\"\"\",\n\
+             3,2,,\n"
+        );
+        assert!("xml".parse::<Format>().is_err());
+    }
+
+    #[test]
+    fn parquet_layout() {
+        let mut out = vec![];
+        sample().write(Format::Parquet, &mut out).unwrap();
+        assert_eq!(&out[..4], b"PAR1");
+        assert_eq!(&out[out.len() - 4..], b"PAR1");
+        let footer_len = u32::from_le_bytes(out[out.len() - 8..out.len() - 4].try_into().unwrap());
+        let footer = &out[out.len() - 8 - footer_len as usize..out.len() - 8];
+        // FileMetaData starts with version = 1 (field 1, i32, zigzag 2).
+        assert_eq!(&footer[..2], &[0x15, 0x02]);
+        let contains = |s: &[u8]| footer.windows(s.len()).any(|w| w == s);
+        assert!(
+            ["task_id", "score", "agent", "created"]
+                .iter()
+                .all(|n| contains(n.as_bytes()))
+        );
+
+        // Definition levels of three values: one bit-packed group of width 1.
+        let page = data_page(&sample().columns[0].1);
+        assert_eq!(&page[..4], &2u32.to_le_bytes());
+        assert_eq!(&page[4..6], &[0x03, 0b111]);
+        assert_eq!(page.len(), 6 + 3 * 8);
+        let nulls = data_page(&sample().columns[1].1);
+        assert_eq!(&nulls[4..6], &[0x03, 0b101]);
+        assert_eq!(nulls.len(), 6 + 2 * 8);
+    }
+
+    #[test]
+    fn parquet_reads_back() {
+        use parquet::file::reader::{FileReader, SerializedFileReader};
+        use parquet::record::Field;
+
+        let path = std::env::temp_dir().join(format!("columnar-{}.parquet", std::process::id()));
+        sample()
+            .write(Format::Parquet, std::fs::File::create(&path).unwrap())
+            .unwrap();
+        let reader = SerializedFileReader::new(std::fs::File::open(&path).unwrap()).unwrap();
+        let meta = reader.metadata().file_metadata();
+        assert_eq!(meta.num_rows(), 3);
+        let names = meta
+            .schema_descr()
+            .columns()
+            .iter()
+            .map(|c| c.name().to_string())
+            .collect::<Vec<_>>();
+        assert_eq!(names, ["task_id", "score", "agent", "created"]);
+        let rows = reader
+            .get_row_iter(None)
+            .unwrap()
+            .map(|row| {
+                row.unwrap()
+                    .get_column_iter()
+                    .map(|(_, field)| field.clone())
+                    .collect::<Vec<_>>()
+            })
+            .collect::<Vec<_>>();
+        std::fs::remove_file(&path).unwrap();
+        let millis = NaiveDateTime::parse_from_str("2025-09-06 12:00:00", "%Y-%m-%d %H:%M:%S")
+            .unwrap()
+            .and_utc()
+            .timestamp_millis();
+        assert_eq!(
+            rows,
+            [
+                vec![
+                    Field::Long(1),
+                    Field::Double(0.5),
+                    Field::Str("a,b".into()),
+                    Field::TimestampMillis(millis),
+                ],
+                vec![
+                    Field::Long(2),
+                    Field::Null,
+                    Field::Str("say \"hi\"".into()),
+                    Field::Null,
+                ],
+                vec![Field::Long(3), Field::Double(2.0), Field::Null, Field::Null],
+            ]
+        );
+    }
+
+    #[test]
+    fn thrift_compact_encoding() {
+        let mut t = Thrift::default();
+        t.i32(1, -1);
+        t.i64(20, 300);
+        t.list(21, THRIFT_I32, 16);
+        t.stop();
+        // Short field header; long field header with zigzag id 40; list with a varint size.
+        assert_eq!(
+            t.buf,
+            vec![0x15, 0x01, 0x06, 0x28, 0xd8, 0x04, 0x19, 0xf5, 0x10, 0x00]
+        );
+    }
+}
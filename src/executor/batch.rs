@@ -0,0 +1,246 @@
+//! Write-behind batching for non-critical task updates.
+//!
+//! Progress and metrics writes do not need to land immediately, and with
+//! hundreds of runners one statement (and connection checkout) per event adds
+//! up. [`WriteBehind`] queues them in a bounded channel and a background thread
+//! flushes the queue periodically in one transaction. Writes sharing a key
+//! replace each other until flushed, so a chatty solver costs one statement
+//! per key per interval. When the queue is full, writes are dropped rather than
+//! blocking the runner.
+//!
+//! Lock handling and the final task update are not batched; they go through
+//! `sql` directly.
+
+use anyhow::Result;
+use mysql::Params;
+use std::sync::{
+    Arc,
+    atomic::{AtomicU64, Ordering},
+    mpsc,
+};
+use std::thread::JoinHandle;
+use std::time::{Duration, Instant};
+
+use crate::sql;
+
+/// Pending writes that trigger a flush before the interval is over.
+const MAX_BATCH: usize = 256;
+
+/// A queued statement.
+pub struct Write {
+    /// Writes with the same key replace each other while queued.
+    pub key: Option<String>,
+    pub query: &'static str,
+    pub params: Params,
+}
+
+enum Msg {
+    Write(Write),
+    Flush(mpsc::Sender<()>),
+}
+
+pub struct WriteBehind {
+    tx: Option<mpsc::SyncSender<Msg>>,
+    dropped: Arc<AtomicU64>,
+    handle: Option<JoinHandle<()>>,
+}
+
+impl WriteBehind {
+    /// A batcher writing to the database, holding at most `capacity` queued writes.
+    pub fn new(capacity: usize, interval: Duration) -> Self {
+        Self::with_sink(capacity, interval, |writes| {
+            sql::exec_transaction(writes.iter().map(|w| (w.query, w.params.clone())))
+        })
+    }
+
+    /// A batcher handing each batch to `sink`. A failed batch is logged and
+    /// discarded.
+    pub fn with_sink<F>(capacity: usize, interval: Duration, mut sink: F) -> Self
+    where
+        F: FnMut(&[Write]) -> Result<()> + Send + 'static,
+    {
+        let (tx, rx) = mpsc::sync_channel(capacity);
+        let handle = std::thread::spawn(move || {
+            let mut pending: Vec<Write> = vec![];
+            let mut flush = |pending: &mut Vec<Write>| {
+                if pending.is_empty() {
+                    return;
+                }
+                if let Err(e) = sink(pending) {
+                    eprintln!(
+                        "[executor] dropped {} batched writes: {:#}",
+                        pending.len(),
+                        e
+                    );
+                }
+                pending.clear();
+            };
+            let mut deadline = Instant::now() + interval;
+            loop {
+                match rx.recv_timeout(deadline.saturating_duration_since(Instant::now())) {
+                    Ok(Msg::Write(w)) => {
+                        match pending
+                            .iter_mut()
+                            .find(|p| p.key.is_some() && p.key == w.key)
+                        {
+                            Some(p) => *p = w,
+                            None => pending.push(w),
+                        }
+                        if pending.len() >= MAX_BATCH {
+                            flush(&mut pending);
+                        }
+                    }
+                    Ok(Msg::Flush(ack)) => {
+                        flush(&mut pending);
+                        let _ = ack.send(());
+                    }
+                    Err(mpsc::RecvTimeoutError::Timeout) => {
+                        flush(&mut pending);
+                        deadline = Instant::now() + interval;
+                    }
+                    Err(mpsc::RecvTimeoutError::Disconnected) => {
+                        flush(&mut pending);
+                        break;
+                    }
+                }
+            }
+        });
+        Self {
+            tx: Some(tx),
+            dropped: Arc::new(AtomicU64::new(0)),
+            handle: Some(handle),
+        }
+    }
+
+    /// Queues a write; returns `false` if it was dropped because the queue is full.
+    pub fn push(&self, key: Option<String>, query: &'static str, params: Params) -> bool {
+        let w = Write { key, query, params };
+        let sent = self
+            .tx
+            .as_ref()
+            .is_some_and(|tx| tx.try_send(Msg::Write(w)).is_ok());
+        if !sent {
+            self.dropped.fetch_add(1, Ordering::Relaxed);
+        }
+        sent
+    }
+
+    /// Writes everything queued so far and waits until it is done.
+    pub fn flush(&self) {
+        let (ack_tx, ack_rx) = mpsc::channel();
+        if let Some(tx) = &self.tx
+            && tx.send(Msg::Flush(ack_tx)).is_ok()
+        {
+            let _ = ack_rx.recv();
+        }
+    }
+
+    /// Number of writes dropped because the queue was full.
+    pub fn dropped(&self) -> u64 {
+        self.dropped.load(Ordering::Relaxed)
+    }
+}
+
+impl Drop for WriteBehind {
+    /// Flushes what is left and stops the background thread.
+    fn drop(&mut self) {
+        self.tx.take();
+        if let Some(handle) = self.handle.take() {
+            let _ = handle.join();
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::Mutex;
+
+    /// Queries of each batch written, in order.
+    type Batches = Arc<Mutex<Vec<Vec<&'static str>>>>;
+
+    fn recording_sink() -> (Batches, impl FnMut(&[Write]) -> Result<()> + Send + 'static) {
+        let batches = Arc::new(Mutex::new(vec![]));
+        let sink_batches = Arc::clone(&batches);
+        (batches, move |writes: &[Write]| {
+            sink_batches
+                .lock()
+                .unwrap()
+                .push(writes.iter().map(|w| w.query).collect());
+            Ok(())
+        })
+    }
+
+    #[test]
+    fn writes_with_the_same_key_are_coalesced() {
+        let (batches, sink) = recording_sink();
+        let batcher = WriteBehind::with_sink(16, Duration::from_secs(3600), sink);
+        assert!(batcher.push(Some("progress:1".into()), "p1", Params::Empty));
+        assert!(batcher.push(None, "metric", Params::Empty));
+        assert!(batcher.push(Some("progress:1".into()), "p2", Params::Empty));
+        assert!(batcher.push(Some("progress:2".into()), "q1", Params::Empty));
+        batcher.flush();
+        assert_eq!(*batches.lock().unwrap(), [vec!["p2", "metric", "q1"]]);
+        // Nothing queued, nothing written.
+        batcher.flush();
+        assert_eq!(batches.lock().unwrap().len(), 1);
+    }
+
+    #[test]
+    fn flushes_periodically_and_on_drop() {
+        let (batches, sink) = recording_sink();
+        let batcher = WriteBehind::with_sink(16, Duration::from_millis(20), sink);
+        batcher.push(None, "a", Params::Empty);
+        let start = Instant::now();
+        while batches.lock().unwrap().is_empty() {
+            assert!(
+                start.elapsed() < Duration::from_secs(5),
+                "no periodic flush"
+            );
+            std::thread::sleep(Duration::from_millis(5));
+        }
+        batcher.push(None, "b", Params::Empty);
+        drop(batcher);
+        assert_eq!(*batches.lock().unwrap(), [vec!["a"], vec!["b"]]);
+    }
+
+    #[test]
+    fn full_queue_drops_instead_of_blocking() {
+        let (entered_tx, entered_rx) = mpsc::channel();
+        let (release_tx, release_rx) = mpsc::channel::<()>();
+        let (batches, mut record) = recording_sink();
+        let batcher = WriteBehind::with_sink(1, Duration::from_millis(1), move |writes| {
+            entered_tx.send(()).unwrap();
+            release_rx.recv().unwrap();
+            record(writes)
+        });
+        batcher.push(None, "a", Params::Empty);
+        // The background thread is now stuck writing "a"; one more fits the queue.
+        entered_rx.recv().unwrap();
+        assert!(batcher.push(None, "b", Params::Empty));
+        assert!(!batcher.push(None, "c", Params::Empty));
+        assert_eq!(batcher.dropped(), 1);
+        release_tx.send(()).unwrap();
+        release_tx.send(()).unwrap();
+        drop(batcher);
+        assert_eq!(*batches.lock().unwrap(), [vec!["a"], vec!["b"]]);
+    }
+
+    #[test]
+    fn failed_batches_are_discarded() {
+        let (batches, mut record) = recording_sink();
+        let mut calls = 0;
+        let batcher = WriteBehind::with_sink(4, Duration::from_secs(3600), move |writes| {
+            calls += 1;
+            if calls == 1 {
+                anyhow::bail!("database is down");
+            }
+            record(writes)
+        });
+        batcher.push(None, "a", Params::Empty);
+        batcher.flush();
+        batcher.push(None, "b", Params::Empty);
+        batcher.flush();
+        assert_eq!(*batches.lock().unwrap(), [vec!["b"]]);
+    }
+}
@@ -1,55 +1,133 @@
+//! Per-task locks of the executor, kept alive by the runner's heartbeat.
+//!
+//! The heartbeat is also the only writer of the solver's progress while the
+//! task runs: on each beat it queues the latest progress event (see
+//! `crate::progress`) on the runner's write-behind batcher (see
+//! `super::batch`), which stores it in `task_diagnostics` as `progress`, and
+//! its one-line summary (see `crate::progress::summary`) in a column the task
+//! lists show:
+//!
+//! ```sql
+//! ALTER TABLE tasks ADD COLUMN task_progress TEXT NULL;
+//! ```
+
 use anyhow::Result;
+use chrono::NaiveDateTime;
 use mysql::params;
 use std::time::Duration;
 
 use crate::sql;
 
-/// Acquire a per-task lock by setting `task_locked` to now + 30s and writing `task_lock`.
+/// Lock lifetime in seconds. Expiry is always computed by the database
+/// (`DATE_ADD(CURRENT_TIMESTAMP, ...)`), never from the local clock.
+pub const LOCK_TTL_SECS: u64 = 30;
+
+/// Acquire a per-task lock by setting `task_locked` to now + `LOCK_TTL_SECS` and writing `task_lock`.
 /// Returns true if the lock was acquired.
 pub fn acquire_lock(task_id: i64, task_lock: &str) -> Result<bool> {
     let affected = sql::exec(
         r#"
         UPDATE tasks
-        SET task_locked = DATE_ADD(CURRENT_TIMESTAMP, INTERVAL 30 SECOND),
+        SET task_locked = DATE_ADD(CURRENT_TIMESTAMP, INTERVAL :ttl SECOND),
             task_lock = :task_lock
         WHERE task_id = :task_id
         "#,
-        params! { "task_id" => task_id, "task_lock" => task_lock },
+        params! { "task_id" => task_id, "task_lock" => task_lock, "ttl" => LOCK_TTL_SECS },
     )?;
     Ok(affected > 0)
 }
 
 /// Extends the lock if `task_lock` matches and `task_locked` is still in the future.
-pub fn extend_lock(task_id: i64, task_lock: &str) -> Result<bool> {
+///
+/// Returns the new expiry as stored by the database, or `None` if the lock was lost.
+pub fn extend_lock(task_id: i64, task_lock: &str) -> Result<Option<NaiveDateTime>> {
     let affected = sql::exec(
         r#"
         UPDATE tasks
-        SET task_locked = DATE_ADD(CURRENT_TIMESTAMP, INTERVAL 30 SECOND)
+        SET task_locked = DATE_ADD(CURRENT_TIMESTAMP, INTERVAL :ttl SECOND)
         WHERE task_id = :task_id
           AND task_lock = :task_lock
           AND task_locked > CURRENT_TIMESTAMP
         "#,
-        params! { "task_id" => task_id, "task_lock" => task_lock },
+        params! { "task_id" => task_id, "task_lock" => task_lock, "ttl" => LOCK_TTL_SECS },
     )?;
-    Ok(affected > 0)
+    if affected == 0 {
+        return Ok(None);
+    }
+    sql::cell(
+        "SELECT task_locked FROM tasks WHERE task_id = :task_id AND task_lock = :task_lock",
+        params! { "task_id" => task_id, "task_lock" => task_lock },
+    )
 }
 
-/// Releases the lock by setting `task_locked` to NULL if conditions match.
-pub fn release_lock(task_id: i64, task_lock: &str) -> Result<bool> {
-    let affected = sql::exec(
+/// Queues the progress `event` for `task_diagnostics` and `task_progress` on
+/// `writes`. A later event of the same task replaces it until flushed, and a
+/// write landing after the final update leaves it alone.
+pub fn queue_progress(
+    writes: &super::batch::WriteBehind,
+    task_id: i64,
+    task_lock: &str,
+    event: &serde_json::Value,
+) -> bool {
+    writes.push(
+        Some(format!("progress:{}", task_id)),
         r#"
         UPDATE tasks
-        SET task_locked = NULL
-        WHERE task_id = :task_id
-          AND task_lock = :task_lock
-          AND task_locked > CURRENT_TIMESTAMP
+        SET task_progress = :summary,
+            task_diagnostics = JSON_SET(COALESCE(task_diagnostics, JSON_OBJECT()),
+                                        '$.progress', CAST(:event AS JSON))
+        WHERE task_id = :task_id AND task_lock = :task_lock AND task_exit_code IS NULL
         "#,
-        params! { "task_id" => task_id, "task_lock" => task_lock },
-    )?;
-    Ok(affected > 0)
+        params! {
+            "task_id" => task_id,
+            "task_lock" => task_lock,
+            "summary" => crate::progress::summary(event),
+            "event" => event.to_string(),
+        },
+    )
+}
+
+/// Time left until `expiry`, measured against the database clock.
+pub fn lock_remaining(expiry: NaiveDateTime) -> Result<Duration> {
+    let left = expiry - sql::db_now()?;
+    Ok(left.to_std().unwrap_or(Duration::ZERO))
+}
+
+/// Delay before the next heartbeat given the time left on the lock.
+///
+/// Extends at a third of the remaining lifetime so two consecutive failures
+/// still leave room for a retry, bounded to `[1s, LOCK_TTL_SECS / 3]`.
+pub fn heartbeat_interval(remaining: Duration) -> Duration {
+    (remaining / 3).clamp(
+        Duration::from_secs(1),
+        Duration::from_secs(LOCK_TTL_SECS / 3),
+    )
 }
 
 #[allow(dead_code)]
 fn _secs(d: Duration) -> i64 {
     (d.as_secs().min(i64::MAX as u64)) as i64
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn heartbeat_interval_follows_remaining_lifetime() {
+        assert_eq!(
+            heartbeat_interval(Duration::from_secs(30)),
+            Duration::from_secs(10)
+        );
+        assert_eq!(
+            heartbeat_interval(Duration::from_secs(12)),
+            Duration::from_secs(4)
+        );
+        // Never busy-loop, never wait longer than a fresh lock would allow.
+        assert_eq!(heartbeat_interval(Duration::ZERO), Duration::from_secs(1));
+        assert_eq!(
+            heartbeat_interval(Duration::from_secs(300)),
+            Duration::from_secs(10)
+        );
+    }
+}
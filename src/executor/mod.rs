@@ -7,9 +7,16 @@ use std::time::{Duration, Instant};
 use crate::sql;
 use std::path::Path;
 
+pub mod batch;
 pub mod lock;
+pub mod queue;
+pub mod retry;
 pub mod run;
 
+/// Progress and metrics writes of this runner, flushed every few seconds.
+static WRITES: once_cell::sync::Lazy<batch::WriteBehind> =
+    once_cell::sync::Lazy::new(|| batch::WriteBehind::new(1024, Duration::from_secs(5)));
+
 /// Information required to execute a task.
 pub struct Task {
     pub task_id: i64,
@@ -19,16 +26,31 @@ pub struct Task {
     pub agent_code: String,
     pub agent_bin: Option<String>,
     pub task_lock: String,
+    /// Failed attempts before this one.
+    pub task_failed: i64,
 }
 
+/// Lines of stderr kept in `task_error` when an attempt fails.
+const ERROR_TAIL_LINES: usize = 50;
+/// Bytes of stderr kept in `task_error` when an attempt fails.
+const ERROR_TAIL_BYTES: usize = 8192;
+/// Default total size of the files uploaded from `root/artifacts/` per run.
+const ARTIFACTS_MAX_BYTES: u64 = 512 * 1024 * 1024;
+
 /// Attempts to acquire the next available task.
 ///
 /// Algorithm:
-/// - Pick the row with the oldest `task_locked` that is NOT NULL and not in the future.
-/// - Set `task_locked` to now + 30s, set a new random `task_lock` token.
-/// - If previous `task_lock` was NOT NULL, increment `task_failed` by 1.
-/// - If the resulting `task_failed` is >= 3, set `task_locked` to NULL and give up the task.
+/// - Record a failed attempt for running tasks whose lock expired (see `retry`).
+/// - Pick the row with the highest `task_priority`, then the oldest `task_locked`, among
+///   `pending` or `failed` rows whose `task_locked` is NOT NULL and not in the future
+///   (see `queue`).
+/// - Set `task_locked` to now + `LOCK_TTL_SECS`, set a new random `task_lock` token and mark it `running`.
 pub fn acquire_task() -> Result<Option<Task>> {
+    let reaped = retry::reap_expired(&retry::RetryPolicy::from_env())?;
+    if reaped > 0 {
+        eprintln!("[executor] {} tasks lost their runner", reaped);
+    }
+
     // 1) Generate new lock token
     let lock_token = gen_lock_token();
 
@@ -43,20 +65,26 @@ pub fn acquire_task() -> Result<Option<Task>> {
         r#"
         UPDATE tasks t
         JOIN (
-            SELECT task_id, task_lock
+            SELECT task_id
             FROM tasks
-            WHERE task_locked IS NOT NULL
+            WHERE task_state IN ('pending', 'failed')
+              AND task_locked IS NOT NULL
               AND task_locked <= CURRENT_TIMESTAMP
-            ORDER BY task_locked ASC
+            ORDER BY task_priority DESC, task_locked ASC
             LIMIT 1
         ) sel ON t.task_id = sel.task_id
         SET
-            t.task_failed = t.task_failed + IF(sel.task_lock IS NULL, 0, 1),
+            t.task_state = 'running',
             t.task_lock = :task_lock,
-            t.task_locked = DATE_ADD(CURRENT_TIMESTAMP, INTERVAL 30 SECOND),
-            t.task_host = :task_host
+            t.task_locked = DATE_ADD(CURRENT_TIMESTAMP, INTERVAL :ttl SECOND),
+            t.task_host = :task_host,
+            t.task_progress = NULL
         "#,
-        params! { "task_lock" => &lock_token, "task_host" => &task_host },
+        params! {
+            "task_lock" => &lock_token,
+            "task_host" => &task_host,
+            "ttl" => crate::executor::lock::LOCK_TTL_SECS,
+        },
     )?;
 
     if affected == 0 {
@@ -84,25 +112,7 @@ pub fn acquire_task() -> Result<Option<Task>> {
     let problem_variant: i64 = row.get("problem_variant")?;
     let task_failed: i64 = row.get("task_failed")?;
 
-    eprintln!(
-        "[executor] candidate acquired: token={} (checking failures)",
-        lock_token
-    );
-
-    // 4) If task_failed >= 3, release this task by clearing task_locked
-    if task_failed >= 3 {
-        eprintln!(
-            "[executor] skipping task_id={} due to task_failed={} (clearing lock)",
-            task_id, task_failed
-        );
-        let _ = sql::exec(
-            r#"UPDATE tasks SET task_locked = NULL WHERE task_id = :task_id AND task_lock = :task_lock"#,
-            params! { "task_id" => task_id, "task_lock" => &lock_token },
-        )?;
-        return Ok(None);
-    }
-
-    // 5) Join agents to get the code
+    // 4) Join agents to get the code
     let row = sql::row(
         r#"
         SELECT t.task_id, t.problem_name, t.problem_variant, a.agent_name, a.agent_code, a.agent_bin
@@ -131,6 +141,7 @@ pub fn acquire_task() -> Result<Option<Task>> {
         agent_code,
         agent_bin,
         task_lock: lock_token,
+        task_failed,
     }))
 }
 
@@ -140,8 +151,14 @@ pub fn acquire_task() -> Result<Option<Task>> {
 /// - Runs using `bash -lc` with a 600s timeout.
 /// - Writes stdout/stderr as JSONL lines to `target/logs/{task_id}/stdout.jsonl` and `stderr.jsonl`.
 /// - Uploads both files to `gs://icfpc2025-data/logs/{task_id}/`.
-/// - Returns the parsed `score` from the last line starting with "<UNAGI>:" in stdout.
-pub fn run_task(task: &Task) -> Result<(Option<i64>, i32, u128)> {
+/// - Uploads files the agent left under `root/artifacts/` to
+///   `gs://icfpc2025-data/logs/{task_id}/artifacts/`, up to `UNAGI_ARTIFACTS_MAX_BYTES`
+///   in total, and records their manifest as `artifacts` in the diagnostics.
+/// - Returns the parsed `score` from the last line starting with "<UNAGI>:" in stdout
+///   that has one, and the diagnostics collected from stderr (see `run::collect_diagnostics`)
+///   plus the last progress event as `progress` (see `crate::progress`).
+/// - Also returns the tail of stderr, for `task_error` if the attempt failed.
+pub fn run_task(task: &Task) -> Result<(Option<i64>, i32, u128, serde_json::Value, String)> {
     // Prepare command by substituting placeholders
     let mut script = task.agent_code.clone();
     script = script.replace("\r", "");
@@ -158,13 +175,27 @@ pub fn run_task(task: &Task) -> Result<(Option<i64>, i32, u128)> {
     };
     let cancel = Arc::new(AtomicBool::new(false));
     let stop_flag = Arc::new(AtomicBool::new(false));
+    // Keeps the lock alive until this function returns, uploads included. The
+    // lock is then cleared by `update_task`, or expires and the task is reaped
+    // (see `retry::reap_expired`) if the runner dies before recording it.
+    struct StopOnDrop(Arc<AtomicBool>);
+    impl Drop for StopOnDrop {
+        fn drop(&mut self) {
+            self.0.store(true, Ordering::Relaxed);
+        }
+    }
+    let _stop_heartbeat = StopOnDrop(Arc::clone(&stop_flag));
     let hb_task_id = task.task_id;
     let hb_lock = task.task_lock.clone();
     let hb_stop = Arc::clone(&stop_flag);
     let hb_cancel = Arc::clone(&cancel);
+    // Latest progress event not yet written by the heartbeat.
+    let latest_progress: Arc<std::sync::Mutex<Option<serde_json::Value>>> = Default::default();
+    let hb_progress = Arc::clone(&latest_progress);
     let _hb = std::thread::spawn(move || {
         let mut failed_count = 0usize;
-        let mut next_extend = Instant::now() + Duration::from_secs(10);
+        let mut next_extend =
+            Instant::now() + Duration::from_secs(crate::executor::lock::LOCK_TTL_SECS / 3);
         loop {
             if hb_stop.load(Ordering::Relaxed) {
                 break;
@@ -173,12 +204,22 @@ pub fn run_task(task: &Task) -> Result<(Option<i64>, i32, u128)> {
                 std::thread::sleep(Duration::from_millis(100));
                 continue;
             }
-            match crate::executor::lock::extend_lock(hb_task_id, &hb_lock) {
-                Ok(true) => {
+            // Hand the latest progress to the batcher; the lock itself is
+            // extended synchronously below.
+            if let Some(progress) = hb_progress.lock().unwrap().take() {
+                crate::executor::lock::queue_progress(&WRITES, hb_task_id, &hb_lock, &progress);
+            }
+            // Schedule from the expiry the database reports rather than a
+            // fixed local cadence, so a skewed VM clock cannot outlive the lock.
+            match crate::executor::lock::extend_lock(hb_task_id, &hb_lock)
+                .and_then(|e| e.map(crate::executor::lock::lock_remaining).transpose())
+            {
+                Ok(Some(remaining)) => {
                     failed_count = 0;
-                    next_extend = Instant::now() + Duration::from_secs(10);
+                    next_extend =
+                        Instant::now() + crate::executor::lock::heartbeat_interval(remaining);
                 }
-                Ok(false) => {
+                Ok(None) => {
                     eprintln!(
                         "[executor] lock extend returned false for task_id={}, cancelling",
                         hb_task_id
@@ -206,6 +247,18 @@ pub fn run_task(task: &Task) -> Result<(Option<i64>, i32, u128)> {
         }
     });
 
+    // Keep the latest progress event for the next heartbeat, which queues it
+    // for the task (see `lock::queue_progress`); events in between are
+    // superseded, so a chatty solver costs no extra writes.
+    let opts = run::RunOptions {
+        on_progress: Some(Arc::new(move |event: &serde_json::Value| {
+            *latest_progress.lock().unwrap() = Some(event.clone());
+        })),
+        // Lets the API proxy attribute the task's queries to its agent.
+        env: vec![("UNAGI_SOLVER".to_string(), task.agent_name.clone())],
+        ..run::RunOptions::default()
+    };
+
     // Execute the script (execution only)
     let start = Instant::now();
     let (score, status, artifacts): (Option<i64>, std::process::ExitStatus, run::Artifacts) =
@@ -219,7 +272,7 @@ pub fn run_task(task: &Task) -> Result<(Option<i64>, i32, u128)> {
                 }
                 Ok(())
             },
-            &run::RunOptions::default(),
+            &opts,
         ) {
             (Ok((s, st)), arts) => (s, st, arts),
             (Err(e), arts) => {
@@ -258,14 +311,28 @@ pub fn run_task(task: &Task) -> Result<(Option<i64>, i32, u128)> {
         }
     };
 
-    // Stop heartbeat and attempt to release lock (best-effort)
-    stop_flag.store(true, Ordering::Relaxed);
-    let _ = crate::executor::lock::release_lock(task.task_id, &task.task_lock);
+    // Write the queued progress before the final update; the heartbeat keeps
+    // the lock until we return.
+    WRITES.flush();
     eprintln!(
-        "[executor] finished task_id={} in {} ms (releasing lock)",
+        "[executor] finished task_id={} in {} ms",
         task.task_id, duration_ms
     );
 
+    let mut diagnostics = run::collect_diagnostics(&artifacts.stderr_file()).unwrap_or_else(|e| {
+        eprintln!(
+            "[executor] failed to collect diagnostics for task_id={}: {}",
+            task.task_id, e
+        );
+        serde_json::json!({})
+    });
+    if let Ok(Some(progress)) = run::collect_progress(&artifacts.stdout_file()) {
+        diagnostics["progress"] = progress;
+    }
+    let stderr_tail =
+        run::collect_tail(&artifacts.stderr_file(), ERROR_TAIL_LINES, ERROR_TAIL_BYTES)
+            .unwrap_or_default();
+
     // Upload logs to GCS (only if artifacts exist)
     eprintln!(
         "[executor] uploading logs for task_id={} to gs://icfpc2025-data/logs/{}/",
@@ -273,6 +340,17 @@ pub fn run_task(task: &Task) -> Result<(Option<i64>, i32, u128)> {
     );
     upload_logs(task.task_id, &artifacts)?;
     eprintln!("[executor] uploaded logs for task_id={}", task.task_id);
+    match upload_artifacts(task.task_id, &artifacts) {
+        Ok(None) => {}
+        Ok(Some(manifest)) => diagnostics["artifacts"] = manifest,
+        Err(e) => {
+            eprintln!(
+                "[executor] failed to upload artifacts for task_id={}: {:#}",
+                task.task_id, e
+            );
+            diagnostics["artifacts"] = serde_json::json!({ "error": format!("{:#}", e) });
+        }
+    }
 
     if let Some(s) = score {
         eprintln!(
@@ -286,26 +364,63 @@ pub fn run_task(task: &Task) -> Result<(Option<i64>, i32, u128)> {
         );
     }
 
-    Ok((score, exit_code, duration_ms))
+    Ok((score, exit_code, duration_ms, diagnostics, stderr_tail))
 }
 
-/// Updates the task with the given score and duration, and releases the lock.
+/// Updates the task with the given score, duration and diagnostics, and releases the lock.
+///
+/// Diagnostics are stored as a JSON object in `task_diagnostics` (a `JSON` column), so
+/// they can be queried with e.g. `task_diagnostics->>'$.diff_count'`. A non-zero exit is a
+/// failed attempt: the task is retried or given up as in `retry`, with `stderr_tail` as
+/// its `task_error`.
 pub fn update_task(
     task: &Task,
     score: Option<i64>,
     exit_code: i32,
     duration_ms: u128,
+    diagnostics: &serde_json::Value,
+    stderr_tail: &str,
 ) -> Result<()> {
     eprintln!(
-        "[executor] updating task_id={} score={:?} exit_code={} duration_ms={}",
-        task.task_id, score, exit_code, duration_ms
+        "[executor] updating task_id={} score={:?} exit_code={} duration_ms={} diagnostics={}",
+        task.task_id, score, exit_code, duration_ms, diagnostics
     );
+    if exit_code != 0 {
+        sql::exec(
+            r#"
+            UPDATE tasks
+            SET task_score = :task_score,
+                task_duration_ms = :task_duration_ms,
+                task_diagnostics = :task_diagnostics
+            WHERE task_id = :task_id AND task_lock = :task_lock
+            "#,
+            params! {
+                "task_score" => score,
+                "task_duration_ms" => (duration_ms as i64),
+                "task_diagnostics" => diagnostics.to_string(),
+                "task_id" => task.task_id,
+                "task_lock" => &task.task_lock,
+            },
+        )?;
+        retry::record_failure(
+            &retry::RetryPolicy::from_env(),
+            task.task_id,
+            &task.task_lock,
+            task.task_failed,
+            Some(exit_code),
+            stderr_tail,
+        )?;
+        return Ok(());
+    }
     let _ = sql::exec(
         r#"
         UPDATE tasks
         SET task_score = :task_score,
             task_exit_code = :task_exit_code,
             task_duration_ms = :task_duration_ms,
+            task_diagnostics = :task_diagnostics,
+            task_state = 'done',
+            task_error = NULL,
             task_locked = NULL
         WHERE task_id = :task_id AND task_lock = :task_lock
         "#,
@@ -313,6 +428,7 @@ pub fn update_task(
             "task_score" => score,
             "task_exit_code" => exit_code,
             "task_duration_ms" => (duration_ms as i64),
+            "task_diagnostics" => diagnostics.to_string(),
             "task_id" => task.task_id,
             "task_lock" => &task.task_lock,
         },
@@ -330,47 +446,95 @@ fn upload_logs(task_id: i64, artifacts: &run::Artifacts) -> Result<()> {
     // Build object names
     let bucket = "icfpc2025-data";
     let prefix = format!("logs/{}/", task_id);
-    let stdout_name = format!("{}stdout.jsonl", prefix);
-    let stderr_name = format!("{}stderr.jsonl", prefix);
-
-    // Read files
-    let stdout_bytes = std::fs::read(artifacts.stdout_file())?;
-    let stderr_bytes = std::fs::read(artifacts.stderr_file())?;
-
-    // Use a local runtime to perform async uploads
+    let files = [
+        (
+            "stdout",
+            artifacts.stdout_file(),
+            format!("{}stdout.jsonl", prefix),
+        ),
+        (
+            "stderr",
+            artifacts.stderr_file(),
+            format!("{}stderr.jsonl", prefix),
+        ),
+    ];
+
+    // Use a local runtime to perform async uploads. Logs can be several GB, so
+    // they are streamed from disk instead of read into memory.
     let rt = tokio::runtime::Runtime::new()?;
     rt.block_on(async {
-        if stdout_bytes.is_empty() {
-            eprintln!(
-                "[executor] skipping upload (stdout is empty) for task_id={}",
-                task_id
-            );
-        } else {
-            let _ = crate::gcp::gcs::upload_object(
+        for (label, path, name) in &files {
+            let file = tokio::fs::File::open(path).await?;
+            if file.metadata().await?.len() == 0 {
+                eprintln!(
+                    "[executor] skipping upload ({} is empty) for task_id={}",
+                    label, task_id
+                );
+                continue;
+            }
+            let _ = crate::gcp::gcs::upload_object_streaming(
                 bucket,
-                &stdout_name,
-                &stdout_bytes,
+                name,
+                file,
                 "application/x-ndjson",
             )
             .await?;
         }
+        anyhow::Ok(())
+    })
+}
 
-        if stderr_bytes.is_empty() {
-            eprintln!(
-                "[executor] skipping upload (stderr is empty) for task_id={}",
-                task_id
-            );
-        } else {
-            let _ = crate::gcp::gcs::upload_object(
+/// Size cap of `upload_artifacts`: `UNAGI_ARTIFACTS_MAX_BYTES`, default
+/// `ARTIFACTS_MAX_BYTES`.
+fn artifacts_max_bytes() -> u64 {
+    std::env::var("UNAGI_ARTIFACTS_MAX_BYTES")
+        .ok()
+        .and_then(|v| v.trim().parse().ok())
+        .unwrap_or(ARTIFACTS_MAX_BYTES)
+}
+
+/// Uploads the files under `root/artifacts/` next to the logs and returns
+/// their manifest (see `run::collect_artifacts`), or `None` if there are none.
+fn upload_artifacts(task_id: i64, artifacts: &run::Artifacts) -> Result<Option<serde_json::Value>> {
+    let dir = artifacts.artifacts_dir();
+    let manifest = run::collect_artifacts(&dir, artifacts_max_bytes())?;
+    if manifest.files.is_empty() && manifest.skipped.is_empty() {
+        return Ok(None);
+    }
+    let bucket = "icfpc2025-data";
+    let prefix = format!("logs/{}/artifacts/", task_id);
+    for file in &manifest.skipped {
+        eprintln!(
+            "[executor] skipping artifact {} ({} bytes, over the cap) for task_id={}",
+            file.path, file.bytes, task_id
+        );
+    }
+    let rt = tokio::runtime::Runtime::new()?;
+    rt.block_on(async {
+        for file in &manifest.files {
+            let reader = tokio::fs::File::open(dir.join(&file.path)).await?;
+            crate::gcp::gcs::upload_object_streaming(
                 bucket,
-                &stderr_name,
-                &stderr_bytes,
-                "application/x-ndjson",
+                &format!("{}{}", prefix, file.path),
+                reader,
+                "application/octet-stream",
             )
-            .await?;
+            .await
+            .with_context(|| format!("upload artifact {}", file.path))?;
         }
         anyhow::Ok(())
-    })
+    })?;
+    eprintln!(
+        "[executor] uploaded {} artifacts ({} bytes) for task_id={} to gs://{}/{}",
+        manifest.files.len(),
+        manifest.bytes,
+        task_id,
+        bucket,
+        prefix
+    );
+    let mut value = serde_json::to_value(&manifest)?;
+    value["prefix"] = format!("gs://{}/{}", bucket, prefix).into();
+    Ok(Some(value))
 }
 
 fn current_hostname() -> String {
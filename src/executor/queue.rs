@@ -0,0 +1,143 @@
+//! # Task Queue
+//!
+//! Tasks wait in `tasks` with `task_locked` set to the time they become
+//! runnable. [`super::acquire_task`] takes the highest `task_priority` first and
+//! the oldest `task_locked` within a priority, so an urgent re-run during the
+//! contest does not sit behind a bulk experiment.
+//!
+//! ```sql
+//! ALTER TABLE tasks
+//!   ADD COLUMN task_priority INT NOT NULL DEFAULT 0,
+//!   ADD INDEX tasks_queue (task_priority, task_locked);
+//! ```
+
+use anyhow::{Result, bail};
+use mysql::params;
+use std::str::FromStr;
+
+use crate::sql;
+
+/// Scheduling classes; any `i32` priority works, these are the usual ones.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum TaskClass {
+    /// Re-runs someone is waiting on.
+    Urgent,
+    /// The default for tasks inserted without a priority.
+    Normal,
+    /// Parameter sweeps and other large batches.
+    Bulk,
+}
+
+impl TaskClass {
+    pub fn priority(self) -> i32 {
+        match self {
+            TaskClass::Urgent => 100,
+            TaskClass::Normal => 0,
+            TaskClass::Bulk => -100,
+        }
+    }
+}
+
+/// Parses a class name (`urgent`, `normal`, `bulk`) or a raw priority into a priority.
+pub fn parse_priority(s: &str) -> Result<i32> {
+    if let Ok(p) = s.parse() {
+        return Ok(p);
+    }
+    Ok(TaskClass::from_str(s)?.priority())
+}
+
+impl FromStr for TaskClass {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        Ok(match s {
+            "urgent" => TaskClass::Urgent,
+            "normal" => TaskClass::Normal,
+            "bulk" => TaskClass::Bulk,
+            _ => bail!(
+                "unknown task class: {} (expected urgent, normal or bulk)",
+                s
+            ),
+        })
+    }
+}
+
+/// Queues `agent_id` on a problem, runnable immediately, and returns the new `task_id`.
+pub fn enqueue_task(
+    priority: i32,
+    agent_id: i64,
+    problem_name: &str,
+    problem_variant: i64,
+) -> Result<u64> {
+    sql::insert(
+        r#"
+        INSERT INTO tasks (agent_id, problem_name, problem_variant, task_priority, task_locked)
+        VALUES (:agent_id, :problem_name, :problem_variant, :task_priority, CURRENT_TIMESTAMP)
+        "#,
+        params! {
+            "agent_id" => agent_id,
+            "problem_name" => problem_name,
+            "problem_variant" => problem_variant,
+            "task_priority" => priority,
+        },
+    )
+}
+
+/// Changes the priority of a task that has not finished yet.
+pub fn set_priority(task_id: i64, priority: i32) -> Result<bool> {
+    let affected = sql::exec(
+        "UPDATE tasks SET task_priority = :task_priority WHERE task_id = :task_id AND task_locked IS NOT NULL",
+        params! { "task_id" => task_id, "task_priority" => priority },
+    )?;
+    Ok(affected > 0)
+}
+
+/// Puts a finished, failed or dead task back in the queue as a fresh task,
+/// runnable immediately. Returns `false` if the task is queued or running.
+pub fn requeue_task(task_id: i64) -> Result<bool> {
+    let affected = sql::exec(
+        r#"
+        UPDATE tasks
+        SET task_state = 'pending',
+            task_locked = CURRENT_TIMESTAMP,
+            task_lock = NULL,
+            task_exit_code = NULL,
+            task_failed = 0,
+            task_error = NULL
+        WHERE task_id = :task_id AND task_state IN ('failed', 'dead', 'done')
+        "#,
+        params! { "task_id" => task_id },
+    )?;
+    Ok(affected > 0)
+}
+
+/// Gives up a queued or running task. A running task loses its lock, so its
+/// runner stops it at the next heartbeat. Returns `false` if it already ended.
+pub fn cancel_task(task_id: i64) -> Result<bool> {
+    let affected = sql::exec(
+        r#"
+        UPDATE tasks
+        SET task_state = 'dead',
+            task_locked = NULL,
+            task_lock = NULL,
+            task_error = 'cancelled'
+        WHERE task_id = :task_id AND task_state IN ('pending', 'running', 'failed')
+        "#,
+        params! { "task_id" => task_id },
+    )?;
+    Ok(affected > 0)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn priorities_from_names_and_numbers() {
+        assert_eq!(parse_priority("urgent").unwrap(), 100);
+        assert_eq!(parse_priority("bulk").unwrap(), -100);
+        assert_eq!(parse_priority("-5").unwrap(), -5);
+        assert!(parse_priority("asap").is_err());
+        assert!(TaskClass::Urgent.priority() > TaskClass::Normal.priority());
+    }
+}
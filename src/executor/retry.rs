@@ -0,0 +1,238 @@
+//! # Retries
+//!
+//! A task attempt fails when the command exits non-zero or when its runner
+//! disappears and the lock expires. Failed tasks go back to the queue after an
+//! exponential backoff; once [`RetryPolicy::max_attempts`] attempts have failed
+//! the task is `dead` and stays in the table with `task_error` holding the tail
+//! of its stderr (or the reason it was given up), instead of silently dropping
+//! out of the queue.
+//!
+//! ```sql
+//! ALTER TABLE tasks
+//!   ADD COLUMN task_state ENUM('pending', 'running', 'failed', 'dead', 'done')
+//!     NOT NULL DEFAULT 'pending',
+//!   ADD COLUMN task_error TEXT NULL;
+//! UPDATE tasks SET task_state = IF(task_exit_code = 0, 'done', 'dead')
+//!   WHERE task_exit_code IS NOT NULL;
+//! UPDATE tasks SET task_state = 'dead'
+//!   WHERE task_exit_code IS NULL AND task_locked IS NULL;
+//! ```
+//!
+//! - `pending`: waiting for its first attempt.
+//! - `running`: locked by a runner.
+//! - `failed`: the last attempt failed; runnable again once `task_locked` passes.
+//! - `dead`: given up after too many failed attempts.
+//! - `done`: exited with 0.
+
+use anyhow::{Result, bail};
+use mysql::params;
+use std::str::FromStr;
+use std::time::Duration;
+
+use crate::sql;
+
+/// Upper bound of the backoff, however many attempts failed.
+const MAX_BACKOFF: Duration = Duration::from_secs(3600);
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum TaskState {
+    Pending,
+    Running,
+    Failed,
+    Dead,
+    Done,
+}
+
+impl TaskState {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            TaskState::Pending => "pending",
+            TaskState::Running => "running",
+            TaskState::Failed => "failed",
+            TaskState::Dead => "dead",
+            TaskState::Done => "done",
+        }
+    }
+}
+
+impl FromStr for TaskState {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        Ok(match s {
+            "pending" => TaskState::Pending,
+            "running" => TaskState::Running,
+            "failed" => TaskState::Failed,
+            "dead" => TaskState::Dead,
+            "done" => TaskState::Done,
+            _ => bail!("unknown task state: {}", s),
+        })
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct RetryPolicy {
+    /// Failed attempts after which a task is dead.
+    pub max_attempts: i64,
+    /// Backoff after the first failure; it doubles with every further one.
+    pub base_backoff: Duration,
+}
+
+impl Default for RetryPolicy {
+    fn default() -> Self {
+        RetryPolicy {
+            max_attempts: 3,
+            base_backoff: Duration::from_secs(60),
+        }
+    }
+}
+
+impl RetryPolicy {
+    /// The default policy, overridden by `UNAGI_TASK_MAX_ATTEMPTS` and
+    /// `UNAGI_TASK_BACKOFF_SECS`.
+    pub fn from_env() -> Self {
+        let mut policy = Self::default();
+        if let Some(n) = std::env::var("UNAGI_TASK_MAX_ATTEMPTS")
+            .ok()
+            .and_then(|s| s.parse().ok())
+        {
+            policy.max_attempts = n;
+        }
+        if let Some(secs) = std::env::var("UNAGI_TASK_BACKOFF_SECS")
+            .ok()
+            .and_then(|s| s.parse().ok())
+        {
+            policy.base_backoff = Duration::from_secs(secs);
+        }
+        policy
+    }
+
+    /// Whether a task with `failures` failed attempts is given up.
+    pub fn gives_up(&self, failures: i64) -> bool {
+        failures >= self.max_attempts
+    }
+
+    /// Delay before the attempt following the `failures`-th failure.
+    pub fn backoff(&self, failures: i64) -> Duration {
+        let doublings = failures.clamp(1, 32) as u32 - 1;
+        self.base_backoff
+            .saturating_mul(1u32 << doublings.min(31))
+            .min(MAX_BACKOFF)
+    }
+}
+
+/// Records a failed attempt of a task locked with `task_lock`, which had
+/// failed `failures` times before: the task is rescheduled after the backoff,
+/// or marked dead. `exit_code` is `None` when the runner lost its lock.
+pub fn record_failure(
+    policy: &RetryPolicy,
+    task_id: i64,
+    task_lock: &str,
+    failures: i64,
+    exit_code: Option<i32>,
+    error: &str,
+) -> Result<TaskState> {
+    let failures = failures + 1;
+    let state = if policy.gives_up(failures) {
+        TaskState::Dead
+    } else {
+        TaskState::Failed
+    };
+    let delay = match state {
+        TaskState::Dead => None,
+        _ => Some(policy.backoff(failures).as_secs() as i64),
+    };
+    sql::exec(
+        r#"
+        UPDATE tasks
+        SET task_failed = :task_failed,
+            task_state = :task_state,
+            task_error = :task_error,
+            task_exit_code = IF(:dead, :task_exit_code, NULL),
+            task_locked = IF(:dead, NULL, DATE_ADD(CURRENT_TIMESTAMP, INTERVAL :delay SECOND)),
+            task_lock = NULL
+        WHERE task_id = :task_id AND task_lock = :task_lock
+        "#,
+        params! {
+            "task_failed" => failures,
+            "task_state" => state.as_str(),
+            "task_error" => error,
+            "dead" => delay.is_none(),
+            "task_exit_code" => exit_code,
+            "delay" => delay.unwrap_or(0),
+            "task_id" => task_id,
+            "task_lock" => task_lock,
+        },
+    )?;
+    eprintln!(
+        "[executor] task_id={} failed (attempt {} of {}): {}",
+        task_id,
+        failures,
+        policy.max_attempts,
+        match delay {
+            Some(d) => format!("retrying in {}s", d),
+            None => "giving up".to_string(),
+        }
+    );
+    Ok(state)
+}
+
+/// Records a failed attempt for every running task whose lock has expired,
+/// and returns how many there were.
+pub fn reap_expired(policy: &RetryPolicy) -> Result<usize> {
+    let rows = sql::select(
+        r#"
+        SELECT task_id, task_lock, task_failed
+        FROM tasks
+        WHERE task_state = 'running' AND task_locked <= CURRENT_TIMESTAMP
+        "#,
+        params::Params::Empty,
+    )?;
+    for r in &rows {
+        let task_id: i64 = r.get("task_id")?;
+        let task_lock: String = r.get("task_lock")?;
+        record_failure(
+            policy,
+            task_id,
+            &task_lock,
+            r.get("task_failed")?,
+            None,
+            "runner lost its lock",
+        )?;
+    }
+    Ok(rows.len())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn backoff_doubles_up_to_the_cap() {
+        let policy = RetryPolicy {
+            max_attempts: 5,
+            base_backoff: Duration::from_secs(60),
+        };
+        let secs = (1..=8)
+            .map(|f| policy.backoff(f).as_secs())
+            .collect::<Vec<_>>();
+        assert_eq!(secs, [60, 120, 240, 480, 960, 1920, 3600, 3600]);
+        assert_eq!(policy.backoff(1000), MAX_BACKOFF);
+        assert!(!policy.gives_up(4));
+        assert!(policy.gives_up(5));
+    }
+
+    #[test]
+    fn states_round_trip() {
+        for s in [
+            TaskState::Pending,
+            TaskState::Running,
+            TaskState::Failed,
+            TaskState::Dead,
+            TaskState::Done,
+        ] {
+            assert_eq!(s.as_str().parse::<TaskState>().unwrap(), s);
+        }
+        assert!("lost".parse::<TaskState>().is_err());
+    }
+}
@@ -22,8 +22,14 @@ pub struct RunOptions {
     pub log_tail_bytes: usize,
     pub flush_interval: Duration,
     pub join_grace: Duration,
+    /// Called with each progress event (see `crate::progress`) seen on stdout.
+    pub on_progress: Option<ProgressCallback>,
+    /// Extra environment variables for the command.
+    pub env: Vec<(String, String)>,
 }
 
+pub type ProgressCallback = Arc<dyn Fn(&JsonValue) + Send + Sync>;
+
 impl Default for RunOptions {
     fn default() -> Self {
         Self {
@@ -31,6 +37,8 @@ impl Default for RunOptions {
             log_tail_bytes: 10 * 1024 * 1024, // 10MB
             flush_interval: Duration::from_millis(500),
             join_grace: Duration::from_secs(7),
+            on_progress: None,
+            env: vec![],
         }
     }
 }
@@ -55,6 +63,9 @@ where
     if let Err(e) = fs::create_dir_all(artifacts.log_dir()) {
         return (Err(e.into()), artifacts);
     }
+    if let Err(e) = fs::create_dir_all(artifacts.artifacts_dir()) {
+        return (Err(e.into()), artifacts);
+    }
 
     // Allow caller to prepare files under the temp directory before execution
     if let Err(e) = prepare(&artifacts) {
@@ -67,7 +78,7 @@ where
             Ok(v) => v,
             Err(e) => return (Err(e), artifacts),
         };
-    let mut child = match spawn_bash(script, artifacts.root_dir()) {
+    let mut child = match spawn_bash(script, artifacts.root_dir(), &opts.env) {
         Ok(c) => c,
         Err(e) => return (Err(e), artifacts),
     };
@@ -88,7 +99,11 @@ where
         Some(Arc::clone(&last_json)),
         opts.clone(),
     );
-    let err_thread = spawn_log_thread(err_pipe, stderr_file, None, opts.clone());
+    let err_opts = RunOptions {
+        on_progress: None,
+        ..opts.clone()
+    };
+    let err_thread = spawn_log_thread(err_pipe, stderr_file, None, err_opts);
 
     // Supervise
     let (terminated_due_to_timeout_or_cancel, status_opt) =
@@ -163,7 +178,7 @@ fn open_logs(stdout_path: &Path, stderr_path: &Path) -> Result<(File, File)> {
     Ok((stdout_file, stderr_file))
 }
 
-fn spawn_bash(script: &str, workdir: &Path) -> Result<Child> {
+fn spawn_bash(script: &str, workdir: &Path, env: &[(String, String)]) -> Result<Child> {
     let mut cmd = Command::new("bash");
     #[cfg(unix)]
     {
@@ -178,6 +193,7 @@ fn spawn_bash(script: &str, workdir: &Path) -> Result<Child> {
     let child = cmd
         .arg("-lc")
         .arg(script)
+        .envs(env.iter().map(|(k, v)| (k, v)))
         .current_dir(workdir)
         .stdout(Stdio::piped())
         .stderr(Stdio::piped())
@@ -238,8 +254,15 @@ fn spawn_log_thread<R: std::io::Read + Send + 'static>(
                     tail.extend(rec);
                 }
             }
+            if let Some(ref on_progress) = opts.on_progress
+                && let Some(event) = crate::progress::parse(&line)
+            {
+                on_progress(&event);
+            }
+            // Progress events (see `crate::progress`) share the prefix but carry no score.
             if let Some(ref slot_arc) = last_json
                 && let Some(json) = parse_unagi_line(&line)
+                && json.get("score").is_some()
                 && let Ok(mut slot) = slot_arc.lock()
             {
                 *slot = Some(json);
@@ -391,6 +414,169 @@ fn parse_unagi_line(line: &str) -> Option<JsonValue> {
     }
 }
 
+/// Solver statistics printed to stderr as `<key> = <value>` (e.g. by iwiwi_evo_gen276)
+/// that are worth keeping per task for later SQL analysis.
+pub const DIAGNOSTIC_KEYS: &[&str] = &["diff_count", "aib_missing", "label-door-chi2"];
+
+/// Parses a stderr line into a diagnostic entry.
+///
+/// Recognized forms:
+/// - `<key> = <value>` for keys listed in `DIAGNOSTIC_KEYS`.
+/// - `!log <key> <value>` as emitted by the judge (e.g. `!log status AC`).
+///
+/// Numeric values become JSON numbers; anything else is kept as a string.
+fn parse_diagnostic_line(line: &str) -> Option<(String, JsonValue)> {
+    let line = line.trim();
+    let (key, value) = if let Some(rest) = line.strip_prefix("!log ") {
+        rest.trim_start().split_once(char::is_whitespace)?
+    } else {
+        let (key, value) = line.split_once('=')?;
+        let key = key.trim();
+        if !DIAGNOSTIC_KEYS.contains(&key) {
+            return None;
+        }
+        (key, value)
+    };
+    let value = value.trim();
+    if key.is_empty() || value.is_empty() {
+        return None;
+    }
+    let json = if let Ok(v) = value.parse::<i64>() {
+        JsonValue::from(v)
+    } else if let Some(v) = value.parse::<f64>().ok().filter(|v| v.is_finite()) {
+        JsonValue::from(v)
+    } else {
+        JsonValue::from(value)
+    };
+    Some((key.to_string(), json))
+}
+
+/// Collects diagnostics from a JSONL log written by the log thread into a JSON object.
+/// When a key appears more than once, the last value wins.
+pub fn collect_diagnostics(log_path: &Path) -> Result<JsonValue> {
+    let file = File::open(log_path)
+        .with_context(|| format!("Failed to open log file: {}", log_path.display()))?;
+    let mut out = serde_json::Map::new();
+    for line in BufReader::new(file).lines() {
+        let rec: JsonValue = match serde_json::from_str(&line?) {
+            Ok(v) => v,
+            Err(_) => continue,
+        };
+        if let Some(text) = rec.get("text").and_then(|t| t.as_str())
+            && let Some((key, value)) = parse_diagnostic_line(text)
+        {
+            out.insert(key, value);
+        }
+    }
+    Ok(JsonValue::Object(out))
+}
+
+/// Returns the last progress event (see `crate::progress`) in a stdout JSONL log.
+pub fn collect_progress(log_path: &Path) -> Result<Option<JsonValue>> {
+    let file = File::open(log_path)
+        .with_context(|| format!("Failed to open log file: {}", log_path.display()))?;
+    let mut last = None;
+    for line in BufReader::new(file).lines() {
+        let rec: JsonValue = match serde_json::from_str(&line?) {
+            Ok(v) => v,
+            Err(_) => continue,
+        };
+        if let Some(text) = rec.get("text").and_then(|t| t.as_str())
+            && let Some(event) = crate::progress::parse(text)
+        {
+            last = Some(event);
+        }
+    }
+    Ok(last)
+}
+
+/// Returns the last `max_lines` lines of a JSONL log, at most `max_bytes` long
+/// (older text is cut first).
+pub fn collect_tail(log_path: &Path, max_lines: usize, max_bytes: usize) -> Result<String> {
+    let file = File::open(log_path)
+        .with_context(|| format!("Failed to open log file: {}", log_path.display()))?;
+    let mut lines = std::collections::VecDeque::with_capacity(max_lines + 1);
+    for line in BufReader::new(file).lines() {
+        let rec: JsonValue = match serde_json::from_str(&line?) {
+            Ok(v) => v,
+            Err(_) => continue,
+        };
+        if let Some(text) = rec.get("text").and_then(|t| t.as_str()) {
+            lines.push_back(text.trim_end_matches(['\n', '\r']).to_string());
+            if lines.len() > max_lines {
+                lines.pop_front();
+            }
+        }
+    }
+    let tail = Vec::from(lines).join("\n");
+    let mut from = tail.len().saturating_sub(max_bytes);
+    while !tail.is_char_boundary(from) {
+        from += 1;
+    }
+    Ok(tail[from..].to_string())
+}
+
+/// A file under `root/artifacts/`, by its path relative to that directory.
+#[derive(Clone, Debug, PartialEq, Eq, serde::Serialize)]
+pub struct ArtifactFile {
+    pub path: String,
+    pub bytes: u64,
+}
+
+/// Files an agent left under `root/artifacts/`, split by the upload size cap.
+#[derive(Clone, Debug, Default, PartialEq, Eq, serde::Serialize)]
+pub struct ArtifactManifest {
+    /// Files to upload, in path order.
+    pub files: Vec<ArtifactFile>,
+    /// Files left out because they did not fit in the cap.
+    pub skipped: Vec<ArtifactFile>,
+    /// Total size of `files`.
+    pub bytes: u64,
+}
+
+/// Lists the regular files under `dir` (recursively, symlinks ignored) and
+/// keeps, in path order, each one that still fits in `max_bytes` in total.
+/// A missing `dir` gives an empty manifest.
+pub fn collect_artifacts(dir: &Path, max_bytes: u64) -> Result<ArtifactManifest> {
+    let mut found = vec![];
+    let mut stack = vec![dir.to_path_buf()];
+    while let Some(d) = stack.pop() {
+        let entries = match fs::read_dir(&d) {
+            Ok(entries) => entries,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => continue,
+            Err(e) => return Err(e).with_context(|| format!("read {}", d.display())),
+        };
+        for entry in entries {
+            let entry = entry?;
+            let meta = entry.path().symlink_metadata()?;
+            if meta.is_dir() {
+                stack.push(entry.path());
+            } else if meta.is_file() {
+                let rel = entry
+                    .path()
+                    .strip_prefix(dir)?
+                    .to_string_lossy()
+                    .into_owned();
+                found.push(ArtifactFile {
+                    path: rel,
+                    bytes: meta.len(),
+                });
+            }
+        }
+    }
+    found.sort_by(|a, b| a.path.cmp(&b.path));
+    let mut manifest = ArtifactManifest::default();
+    for file in found {
+        if manifest.bytes + file.bytes <= max_bytes {
+            manifest.bytes += file.bytes;
+            manifest.files.push(file);
+        } else {
+            manifest.skipped.push(file);
+        }
+    }
+    Ok(manifest)
+}
+
 fn make_artifacts_paths() -> Artifacts {
     let now = chrono::Utc::now();
     let ts = format!(
@@ -550,6 +736,129 @@ mod tests {
         Ok(())
     }
 
+    #[test]
+    fn progress_events_do_not_replace_score() -> Result<()> {
+        let script = "echo \"<UNAGI>: {\\\"score\\\": 7}\"; \
+                      echo \"<UNAGI>: {\\\"event\\\": \\\"phase\\\", \\\"phase\\\": \\\"cnf\\\"}\"";
+        let (res, artifacts) = run_command_with_timeout(
+            script,
+            Duration::from_secs(5),
+            Arc::new(AtomicBool::new(false)),
+            |_| Ok(()),
+            &RunOptions::default(),
+        );
+        let (score, status) = res?;
+        assert!(status.success());
+        assert_eq!(score, Some(7));
+        let progress = collect_progress(&artifacts.stdout_file())?.unwrap();
+        assert_eq!(progress["phase"], "cnf");
+        Ok(())
+    }
+
+    #[test]
+    fn run_command_reports_progress_events() -> Result<()> {
+        let script = "echo '<UNAGI>: {\"event\":\"explore\",\"elapsed\":0.1}'; \
+            echo '<UNAGI>: {\"score\": 3}'; \
+            echo '<UNAGI>: {\"event\":\"guess\",\"elapsed\":0.2}' >&2";
+        let seen = Arc::new(Mutex::new(vec![]));
+        let sink = Arc::clone(&seen);
+        let opts = RunOptions {
+            on_progress: Some(Arc::new(move |event: &JsonValue| {
+                sink.lock().unwrap().push(event["event"].clone());
+            })),
+            ..RunOptions::default()
+        };
+        let (res, _artifacts) =
+            run_command(script, Arc::new(AtomicBool::new(false)), |_| Ok(()), &opts);
+        assert_eq!(res?.0, Some(3));
+        // Only stdout events count; the score line is not progress.
+        assert_eq!(*seen.lock().unwrap(), [serde_json::json!("explore")]);
+        Ok(())
+    }
+
+    #[test]
+    fn parse_diagnostic_line_forms() {
+        assert_eq!(
+            parse_diagnostic_line("diff_count = 1234\n"),
+            Some(("diff_count".to_string(), serde_json::json!(1234)))
+        );
+        assert_eq!(
+            parse_diagnostic_line("label-door-chi2 = 12.5"),
+            Some(("label-door-chi2".to_string(), serde_json::json!(12.5)))
+        );
+        assert_eq!(
+            parse_diagnostic_line("!log status AC"),
+            Some(("status".to_string(), serde_json::json!("AC")))
+        );
+        assert_eq!(parse_diagnostic_line("unknown_key = 3"), None);
+        assert_eq!(parse_diagnostic_line("plain text"), None);
+    }
+
+    #[test]
+    fn run_command_collects_stderr_diagnostics() -> Result<()> {
+        let script = "echo 'diff_count = 10' 1>&2; \
+                      echo 'aib_missing = 2' 1>&2; \
+                      echo 'diff_count = 11' 1>&2; \
+                      echo '!log status AC' 1>&2";
+        let (res, artifacts) = run_command(
+            script,
+            Arc::new(AtomicBool::new(false)),
+            |_| Ok(()),
+            &RunOptions::default(),
+        );
+        let (_score, status) = res?;
+        assert!(status.success());
+        let diag = collect_diagnostics(&artifacts.stderr_file())?;
+        assert_eq!(
+            diag,
+            serde_json::json!({"diff_count": 11, "aib_missing": 2, "status": "AC"})
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn collect_tail_keeps_the_last_lines() -> Result<()> {
+        let script = "echo first 1>&2; echo second 1>&2; echo 'thread panicked' 1>&2";
+        let (res, artifacts) = run_command(
+            script,
+            Arc::new(AtomicBool::new(false)),
+            |_| Ok(()),
+            &RunOptions::default(),
+        );
+        res?;
+        let path = artifacts.stderr_file();
+        assert_eq!(collect_tail(&path, 2, 1024)?, "second\nthread panicked");
+        assert_eq!(collect_tail(&path, 10, 8)?, "panicked");
+        Ok(())
+    }
+
+    #[test]
+    fn collect_artifacts_lists_files_within_cap() -> Result<()> {
+        let script = "mkdir -p artifacts/sub && printf 12345 > artifacts/b.cnf \
+            && printf 123 > artifacts/sub/a.txt && printf 1234567 > artifacts/c.bin \
+            && ln -s b.cnf artifacts/link";
+        let (res, artifacts) = run_command(
+            script,
+            Arc::new(AtomicBool::new(false)),
+            |_| Ok(()),
+            &RunOptions::default(),
+        );
+        assert!(res?.1.success());
+        let file = |path: &str, bytes| ArtifactFile {
+            path: path.to_string(),
+            bytes,
+        };
+        let manifest = collect_artifacts(&artifacts.artifacts_dir(), 9)?;
+        assert_eq!(manifest.files, [file("b.cnf", 5), file("sub/a.txt", 3)]);
+        assert_eq!(manifest.skipped, [file("c.bin", 7)]);
+        assert_eq!(manifest.bytes, 8);
+        assert_eq!(
+            collect_artifacts(&artifacts.base_dir().join("missing"), 9)?,
+            ArtifactManifest::default()
+        );
+        Ok(())
+    }
+
     #[test]
     fn artifacts_cleanup_on_drop() -> Result<()> {
         let script = "echo hello; echo \"<UNAGI>: {\\\"score\\\": 0}\"";
@@ -641,6 +950,10 @@ impl Artifacts {
     pub fn stderr_file(&self) -> PathBuf {
         self.log_dir.join("stderr.jsonl")
     }
+    /// Directory whose files are uploaded after the run (`root/artifacts/`).
+    pub fn artifacts_dir(&self) -> PathBuf {
+        self.root_dir.join("artifacts")
+    }
 }
 
 impl Drop for Artifacts {
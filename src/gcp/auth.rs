@@ -11,7 +11,7 @@ use serde::{Deserialize, Serialize};
 use std::sync::Mutex;
 use std::time::{Duration, Instant};
 
-use crate::client::CLIENT;
+use crate::client::{CLIENT, SendTracked};
 use crate::gcp::types::{AccessToken, ServiceAccount};
 
 /// The Google OAuth2 token endpoint.
@@ -84,7 +84,7 @@ pub async fn get_access_token() -> Result<String> {
         let client = &*CLIENT;
         let service_account_json = client
             .get(sa_url)
-            .send()
+            .send_tracked()
             .await
             .context("Failed to download service_account.json")?
             .error_for_status()
@@ -125,7 +125,7 @@ pub async fn get_access_token() -> Result<String> {
     ];
 
     let client = &*CLIENT;
-    let response = client.post(TOKEN_URL).form(&params).send().await?;
+    let response = client.post(TOKEN_URL).form(&params).send_tracked().await?;
     if !response.status().is_success() {
         let error_text = response.text().await.unwrap_or_else(|_| "<no body>".into());
         anyhow::bail!("Failed to get access token: {}", error_text);
@@ -1,13 +1,14 @@
 //! # GCE API Client Logic
 //!
-//! This module contains the core client function for making API requests to
-//! Google Compute Engine.
+//! This module contains the client functions for making API requests to
+//! Google Compute Engine: creating, listing, inspecting, stopping, starting and
+//! deleting instances and updating their metadata. The `gcp` CLI and executors share these.
 
-use anyhow::Result;
+use anyhow::{Context, Result, bail};
 use serde_json::Value;
 
-use crate::client::CLIENT;
-use crate::gcp::gce::types::InstanceRequest;
+use crate::client::{CLIENT, SendTracked};
+use crate::gcp::gce::types::{Instance, InstanceList, InstanceRequest, MetadataItem};
 use crate::gcp::get_access_token;
 
 /// The base URL for the Google Compute Engine v1 API.
@@ -46,7 +47,7 @@ pub async fn create_instance(
         .header("Authorization", format!("Bearer {}", token))
         .header("Content-Type", "application/json")
         .json(instance_request)
-        .send()
+        .send_tracked()
         .await?;
 
     if !response.status().is_success() {
@@ -59,6 +60,102 @@ pub async fn create_instance(
     Ok(result)
 }
 
+/// Sends an authorized request and returns the JSON body, or an error with
+/// the response text on a non-success status.
+async fn send(request: reqwest::RequestBuilder, what: &str) -> Result<Value> {
+    let token = get_access_token().await?;
+    let response = request
+        .header("Authorization", format!("Bearer {}", token))
+        .send_tracked()
+        .await
+        .with_context(|| format!("Failed to call GCE API to {}", what))?;
+    if !response.status().is_success() {
+        let status = response.status();
+        let body = response.text().await.unwrap_or_default();
+        bail!("Failed to {} (status {}): {}", what, status, body);
+    }
+    response
+        .json()
+        .await
+        .with_context(|| format!("Invalid GCE response to {}", what))
+}
+
+fn instances_url(project_id: &str, zone: &str) -> String {
+    format!(
+        "{}/projects/{}/zones/{}/instances",
+        GCE_API_BASE, project_id, zone
+    )
+}
+
+/// Lists all instances in a zone, following pagination.
+pub async fn list_instances(project_id: &str, zone: &str) -> Result<Vec<Instance>> {
+    let url = instances_url(project_id, zone);
+    let mut instances = vec![];
+    let mut page_token: Option<String> = None;
+    loop {
+        let mut request = CLIENT.get(&url);
+        if let Some(token) = &page_token {
+            request = request.query(&[("pageToken", token)]);
+        }
+        let page: InstanceList = serde_json::from_value(send(request, "list instances").await?)
+            .context("Invalid instance list")?;
+        instances.extend(page.items);
+        match page.next_page_token {
+            Some(token) => page_token = Some(token),
+            None => return Ok(instances),
+        }
+    }
+}
+
+/// Fetches a single instance.
+pub async fn get_instance(project_id: &str, zone: &str, name: &str) -> Result<Instance> {
+    let url = format!("{}/{}", instances_url(project_id, zone), name);
+    let value = send(CLIENT.get(&url), &format!("get instance {}", name)).await?;
+    serde_json::from_value(value).context("Invalid instance resource")
+}
+
+/// Deletes an instance. Returns the long-running Operation resource.
+pub async fn delete_instance(project_id: &str, zone: &str, name: &str) -> Result<Value> {
+    let url = format!("{}/{}", instances_url(project_id, zone), name);
+    send(CLIENT.delete(&url), &format!("delete instance {}", name)).await
+}
+
+/// Stops a running instance; its disks are kept. Returns the long-running
+/// Operation resource.
+pub async fn stop_instance(project_id: &str, zone: &str, name: &str) -> Result<Value> {
+    let url = format!("{}/{}/stop", instances_url(project_id, zone), name);
+    send(CLIENT.post(&url), &format!("stop instance {}", name)).await
+}
+
+/// Starts a stopped instance. Returns the long-running Operation resource.
+pub async fn start_instance(project_id: &str, zone: &str, name: &str) -> Result<Value> {
+    let url = format!("{}/{}/start", instances_url(project_id, zone), name);
+    send(CLIENT.post(&url), &format!("start instance {}", name)).await
+}
+
+/// Sets (adds or overwrites) metadata keys on an instance, keeping the others.
+/// Returns the long-running Operation resource.
+pub async fn set_instance_metadata(
+    project_id: &str,
+    zone: &str,
+    name: &str,
+    items: &[MetadataItem],
+) -> Result<Value> {
+    let mut metadata = get_instance(project_id, zone, name).await?.metadata;
+    for item in items {
+        match metadata.items.iter_mut().find(|it| it.key == item.key) {
+            Some(it) => it.value = item.value.clone(),
+            None => metadata.items.push(item.clone()),
+        }
+    }
+    let url = format!("{}/{}/setMetadata", instances_url(project_id, zone), name);
+    send(
+        CLIENT.post(&url).json(&metadata),
+        &format!("set metadata of {}", name),
+    )
+    .await
+}
+
 #[cfg(test)]
 mod tests {
     use anyhow::Result;
@@ -71,4 +168,31 @@ mod tests {
         assert!(!token.is_empty());
         Ok(())
     }
+
+    #[test]
+    fn instance_list_parses() {
+        let json = r#"{
+            "items": [{
+                "name": "worker-1",
+                "status": "RUNNING",
+                "machineType": "https://www.googleapis.com/compute/v1/projects/p/zones/z/machineTypes/c2d-standard-4",
+                "networkInterfaces": [{"accessConfigs": [{"natIP": "203.0.113.5"}]}],
+                "metadata": {"fingerprint": "abc=", "items": [{"key": "startup-script", "value": "echo hi"}]}
+            }, {
+                "name": "worker-2",
+                "status": "TERMINATED",
+                "networkInterfaces": [{"accessConfigs": [{}]}]
+            }],
+            "nextPageToken": "t"
+        }"#;
+        let list: crate::gcp::gce::InstanceList = serde_json::from_str(json).unwrap();
+        assert_eq!(list.next_page_token.as_deref(), Some("t"));
+        assert_eq!(list.items[0].external_ip(), Some("203.0.113.5"));
+        assert_eq!(
+            list.items[0].metadata_value("startup-script"),
+            Some("echo hi")
+        );
+        assert_eq!(list.items[1].external_ip(), None);
+        assert!(list.items[1].metadata.items.is_empty());
+    }
 }
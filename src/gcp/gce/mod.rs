@@ -1,8 +1,9 @@
 //! # Google Compute Engine (GCE) Client
 //!
 //! This module provides a client for interacting with the Google Compute Engine API.
-//! It is used for managing GCE virtual machine instances, such as creating new
-//! instances based on templates or specific configurations.
+//! It is used for managing GCE virtual machine instances: creating new instances
+//! based on templates or specific configurations, listing, inspecting, stopping,
+//! starting and deleting them, and updating their metadata.
 //!
 //! ## Submodules
 //! - `client`: Contains the core client logic for making API requests to GCE.
@@ -18,6 +19,9 @@ pub mod defaults;
 pub mod types;
 
 // Re-export key components to provide a convenient public API for this module.
-pub use crate::gcp::gce::client::create_instance;
+pub use crate::gcp::gce::client::{
+    create_instance, delete_instance, get_instance, list_instances, set_instance_metadata,
+    start_instance, stop_instance,
+};
 pub use crate::gcp::gce::defaults::{create_default_instance_request, create_instance_request};
 pub use crate::gcp::gce::types::*;
@@ -1,9 +1,9 @@
 //! # Google Compute Engine (GCE) Data Types
 //!
 //! This module defines the Rust structs that model the JSON objects used in the
-//! Google Compute Engine API, specifically for creating new VM instances.
-//! These structs are designed to be serialized into the JSON payload for an
-//! `instances.insert` API request.
+//! Google Compute Engine API. `InstanceRequest` and its parts are serialized
+//! into the payload of an `instances.insert` request; `Instance` is the
+//! (partial) resource returned by `instances.get` and `instances.list`.
 //!
 //! For detailed information on each field, refer to the official GCE API documentation.
 
@@ -120,7 +120,7 @@ pub struct Metadata {
 }
 
 /// A single metadata key-value pair.
-#[derive(Debug, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct MetadataItem {
     pub key: String,
     pub value: String,
@@ -194,3 +194,79 @@ pub struct ShieldedInstanceConfig {
 pub struct Tags {
     pub items: Vec<String>,
 }
+
+/// An existing instance, as returned by `instances.get` / `instances.list`.
+/// Only the fields used by the CLI and executors are modeled.
+#[derive(Debug, Clone, Deserialize)]
+pub struct Instance {
+    pub name: String,
+    /// e.g. `RUNNING`, `TERMINATED`.
+    #[serde(default)]
+    pub status: String,
+    /// Full URL of the machine type.
+    #[serde(rename = "machineType", default)]
+    pub machine_type: String,
+    /// Full URL of the zone.
+    #[serde(default)]
+    pub zone: String,
+    #[serde(rename = "networkInterfaces", default)]
+    pub network_interfaces: Vec<InstanceNetworkInterface>,
+    #[serde(default)]
+    pub metadata: InstanceMetadata,
+    #[serde(default)]
+    pub labels: HashMap<String, String>,
+}
+
+impl Instance {
+    /// External IP of the first access config, if any.
+    pub fn external_ip(&self) -> Option<&str> {
+        self.network_interfaces
+            .first()?
+            .access_configs
+            .first()?
+            .nat_ip
+            .as_deref()
+    }
+
+    /// Value of a metadata key.
+    pub fn metadata_value(&self, key: &str) -> Option<&str> {
+        self.metadata
+            .items
+            .iter()
+            .find(|it| it.key == key)
+            .map(|it| it.value.as_str())
+    }
+}
+
+/// Network interface of an existing instance.
+#[derive(Debug, Clone, Deserialize)]
+pub struct InstanceNetworkInterface {
+    #[serde(rename = "accessConfigs", default)]
+    pub access_configs: Vec<InstanceAccessConfig>,
+}
+
+/// Access config of an existing instance.
+#[derive(Debug, Clone, Deserialize)]
+pub struct InstanceAccessConfig {
+    #[serde(rename = "natIP")]
+    pub nat_ip: Option<String>,
+}
+
+/// Metadata of an existing instance. The fingerprint must be sent back when
+/// updating it.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct InstanceMetadata {
+    #[serde(default)]
+    pub fingerprint: String,
+    #[serde(default)]
+    pub items: Vec<MetadataItem>,
+}
+
+/// One page of `instances.list`.
+#[derive(Debug, Deserialize)]
+pub struct InstanceList {
+    #[serde(default)]
+    pub items: Vec<Instance>,
+    #[serde(rename = "nextPageToken")]
+    pub next_page_token: Option<String>,
+}
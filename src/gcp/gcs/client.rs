@@ -8,8 +8,8 @@ use cached::proc_macro::cached;
 use reqwest::Url;
 use std::time::Duration;
 
-use crate::client::CLIENT;
-use crate::gcp::gcs::types::{FileInfo, ListResponse, ObjectItem};
+use crate::client::{CLIENT, SendTracked};
+use crate::gcp::gcs::types::{FileInfo, ListResponse, ObjectItem, RewriteResponse};
 use crate::gcp::get_access_token;
 
 /// Parses a GCS URL string (`gs://bucket/object/path`) into a bucket and object prefix.
@@ -27,16 +27,45 @@ pub fn parse_gs_url(s: &str) -> Result<(String, String)> {
     Ok((bucket, prefix))
 }
 
+/// Splits an object path with glob wildcards (`*`, `?`, `[`, `{`) into the
+/// literal directory prefix before the first wildcard and the pattern, e.g.
+/// `logs/*/stderr.jsonl` into `logs/` and `Some("logs/*/stderr.jsonl")`.
+/// A path without wildcards is returned as is with no pattern.
+pub fn split_glob(path: &str) -> (String, Option<String>) {
+    match path.find(['*', '?', '[', '{']) {
+        Some(i) => {
+            let dir = path[..i].rfind('/').map_or(0, |j| j + 1);
+            (path[..dir].to_string(), Some(path.to_string()))
+        }
+        None => (path.to_string(), None),
+    }
+}
+
+/// Filters for [`list_dir_detailed`].
+#[derive(Clone, Debug, Default)]
+pub struct ListOptions {
+    /// A `matchGlob` pattern on full object names (`*` stays within a path
+    /// segment, `**` spans segments). With a pattern the listing is flat:
+    /// every matching object below the prefix, and no directories.
+    pub glob: Option<String>,
+    /// Stop after this many entries (directories and files).
+    pub limit: Option<usize>,
+}
+
 /// Internal generic function for listing objects in a GCS directory.
-/// It handles pagination and separates results into "directories" (prefixes) and "files" (items).
-/// The `map` function allows customizing the output format for file items.
-async fn list_dir_internal<T, F>(
+/// It handles pagination and separates results into "directories" (prefixes) and "files" (items),
+/// handing each page to `on_page` as it arrives. The `map` function allows customizing the output
+/// format for file items. Returns the number of entries handed out.
+async fn list_dir_internal<T, F, P>(
     bucket: &str,
     prefix: &str,
+    opts: &ListOptions,
     map: F,
-) -> Result<(Vec<String>, Vec<T>)>
+    mut on_page: P,
+) -> Result<usize>
 where
     F: Fn(ObjectItem, &str) -> Option<T>,
+    P: FnMut(Vec<String>, Vec<T>),
 {
     // For directory-like listing, ensure prefix ends with '/'
     let mut eff_prefix = prefix.to_string();
@@ -50,21 +79,30 @@ where
     let client = &*CLIENT;
 
     let mut page_token: Option<String> = None;
-    let mut dirs: Vec<String> = Vec::new();
-    let mut out_items: Vec<T> = Vec::new();
+    let mut remaining = opts.limit.unwrap_or(usize::MAX);
 
     // Loop to handle paginated results from the GCS API.
-    loop {
+    while remaining > 0 {
         let mut url = Url::parse(&format!(
             "https://storage.googleapis.com/storage/v1/b/{}/o",
             bucket
         ))?;
         {
             let mut qp = url.query_pairs_mut();
-            qp.append_pair("delimiter", "/"); // Use delimiter to get directory-like behavior.
+            match &opts.glob {
+                Some(glob) => {
+                    qp.append_pair("matchGlob", glob);
+                }
+                None => {
+                    qp.append_pair("delimiter", "/"); // Use delimiter to get directory-like behavior.
+                }
+            }
             if !eff_prefix.is_empty() {
                 qp.append_pair("prefix", &eff_prefix);
             }
+            if remaining < 1000 {
+                qp.append_pair("maxResults", &remaining.to_string());
+            }
             if let Some(ref t) = page_token {
                 qp.append_pair("pageToken", t);
             }
@@ -73,7 +111,7 @@ where
         let res = client
             .get(url)
             .header("Authorization", format!("Bearer {}", token))
-            .send()
+            .send_tracked()
             .await
             .context("Failed to call GCS list API")?;
 
@@ -86,12 +124,20 @@ where
         let body: ListResponse = res.json().await.context("Invalid GCS response")?;
 
         // Prefixes are the "subdirectories".
-        for p in body.prefixes {
-            let name = p.strip_prefix(&eff_prefix).unwrap_or(&p).to_string();
-            dirs.push(name);
-        }
+        let mut dirs: Vec<String> = body
+            .prefixes
+            .iter()
+            .map(|p| p.strip_prefix(&eff_prefix).unwrap_or(p).to_string())
+            .take(remaining)
+            .collect();
+        dirs.sort();
+        remaining -= dirs.len();
         // Items are the "files".
+        let mut out_items: Vec<T> = Vec::new();
         for it in body.items {
+            if remaining == 0 {
+                break;
+            }
             let rel = it
                 .name
                 .strip_prefix(&eff_prefix)
@@ -103,8 +149,10 @@ where
             }
             if let Some(mapped) = map(it, &rel) {
                 out_items.push(mapped);
+                remaining -= 1;
             }
         }
+        on_page(dirs, out_items);
 
         page_token = body.next_page_token;
         if page_token.is_none() {
@@ -112,8 +160,7 @@ where
         }
     }
 
-    dirs.sort();
-    Ok((dirs, out_items))
+    Ok(opts.limit.unwrap_or(usize::MAX) - remaining)
 }
 
 /// Lists the contents of a "directory" in a GCS bucket.
@@ -123,30 +170,85 @@ where
 /// 1. A list of subdirectory names.
 /// 2. A list of file names.
 pub async fn list_dir(bucket: &str, prefix: &str) -> Result<(Vec<String>, Vec<String>)> {
-    list_dir_internal(bucket, prefix, |_it, rel| Some(rel.to_string())).await
+    let (mut dirs, mut files) = (vec![], vec![]);
+    list_dir_internal(
+        bucket,
+        prefix,
+        &ListOptions::default(),
+        |_it, rel| Some(rel.to_string()),
+        |d, f| {
+            dirs.extend(d);
+            files.extend(f);
+        },
+    )
+    .await?;
+    dirs.sort();
+    Ok((dirs, files))
 }
 
-/// Lists the contents of a "directory" in a GCS bucket with detailed file information.
+/// Lists the contents of a "directory" in a GCS bucket with detailed file information,
+/// filtered by `opts`.
 ///
-/// # Returns
-/// A tuple containing two vectors:
-/// 1. A list of subdirectory names.
-/// 2. A list of `FileInfo` structs for each file.
-pub async fn list_dir_detailed(bucket: &str, prefix: &str) -> Result<(Vec<String>, Vec<FileInfo>)> {
-    let (dirs, files) = list_dir_internal(bucket, prefix, |it, rel| {
-        let size = it.size.as_deref().and_then(|s| s.parse::<u64>().ok());
-        let updated = it.updated.clone();
-        Some(FileInfo {
-            name: rel.to_string(),
-            size,
-            updated,
-        })
-    })
-    .await?;
+/// Pages are streamed: `on_page` gets the subdirectory names and the `FileInfo` of the
+/// files of each page as soon as it arrives, so prefixes with tens of thousands of objects
+/// neither wait for the whole listing nor hold it in memory. Names are relative to the
+/// prefix. Returns the number of entries listed.
+pub async fn list_dir_detailed(
+    bucket: &str,
+    prefix: &str,
+    opts: &ListOptions,
+    on_page: impl FnMut(Vec<String>, Vec<FileInfo>),
+) -> Result<usize> {
+    list_dir_internal(
+        bucket,
+        prefix,
+        opts,
+        |it, rel| {
+            let size = it.size.as_deref().and_then(|s| s.parse::<u64>().ok());
+            let updated = it.updated.clone();
+            Some(FileInfo {
+                name: rel.to_string(),
+                size,
+                updated,
+            })
+        },
+        on_page,
+    )
+    .await
+}
 
-    let mut files = files;
-    files.sort_by(|a, b| a.name.cmp(&b.name));
-    Ok((dirs, files))
+/// GCS API requires object paths to be percent-encoded as a single path segment.
+/// This helper ensures characters like '/' are correctly encoded.
+fn encode_component(s: &str) -> String {
+    let mut out = String::with_capacity(s.len() * 3);
+    for b in s.as_bytes() {
+        let c = *b as char;
+        if c.is_ascii_alphanumeric() || matches!(c, '-' | '_' | '.' | '~') {
+            out.push(c);
+        } else {
+            out.push('%');
+            out.push_str(&format!("{:02X}", b));
+        }
+    }
+    out
+}
+
+/// Builds the `alt=media` URL used to fetch an object's content.
+fn media_url(bucket: &str, object: &str) -> Result<Url> {
+    Ok(Url::parse(&format!(
+        "https://storage.googleapis.com/storage/v1/b/{}/o/{}?alt=media",
+        bucket,
+        encode_component(object)
+    ))?)
+}
+
+/// Formats an HTTP `Range` header value for bytes `start..end` (`end` exclusive).
+/// An open-ended range is used when `end` is `None`.
+fn range_header(start: u64, end: Option<u64>) -> String {
+    match end {
+        Some(end) => format!("bytes={}-{}", start, end.saturating_sub(1)),
+        None => format!("bytes={}-", start),
+    }
 }
 
 /// Downloads an object from a GCS bucket.
@@ -169,31 +271,12 @@ pub async fn download_object(bucket: &str, object: &str) -> Result<Vec<u8>> {
         .context("Failed to get access token")?;
     let client = &*CLIENT;
 
-    // GCS API requires object paths to be percent-encoded as a single path segment.
-    // This helper ensures characters like '/' are correctly encoded.
-    fn encode_component(s: &str) -> String {
-        let mut out = String::with_capacity(s.len() * 3);
-        for b in s.as_bytes() {
-            let c = *b as char;
-            if c.is_ascii_alphanumeric() || matches!(c, '-' | '_' | '.' | '~') {
-                out.push(c);
-            } else {
-                out.push('%');
-                out.push_str(&format!("{:02X}", b));
-            }
-        }
-        out
-    }
-    let encoded = encode_component(object);
-    let url = Url::parse(&format!(
-        "https://storage.googleapis.com/storage/v1/b/{}/o/{}?alt=media",
-        bucket, encoded
-    ))?;
+    let url = media_url(bucket, object)?;
 
     let res = client
         .get(url)
         .header("Authorization", format!("Bearer {}", token))
-        .send()
+        .send_tracked()
         .await
         .context("Failed to download GCS object")?;
 
@@ -207,6 +290,111 @@ pub async fn download_object(bucket: &str, object: &str) -> Result<Vec<u8>> {
     Ok(bytes.to_vec())
 }
 
+/// Maximum number of times `stream_object` re-issues a range request after the
+/// body stream breaks mid-transfer.
+const STREAM_MAX_RESUMES: usize = 5;
+
+/// Streams the bytes `start..end` of an object to `sink` chunk by chunk, without
+/// buffering the whole object in memory.
+///
+/// If the connection drops mid-body, the download resumes from the last delivered
+/// byte with a new `Range` request (up to `STREAM_MAX_RESUMES` times). `sink` may
+/// return `Ok(false)` to stop early. A `start` at or past the end of the object
+/// yields no bytes.
+///
+/// # Returns
+/// The number of bytes passed to `sink`.
+pub async fn stream_object<F>(
+    bucket: &str,
+    object: &str,
+    start: u64,
+    end: Option<u64>,
+    mut sink: F,
+) -> Result<u64>
+where
+    F: FnMut(&[u8]) -> Result<bool>,
+{
+    if end.is_some_and(|end| end <= start) {
+        return Ok(0);
+    }
+    let token = get_access_token()
+        .await
+        .context("Failed to get access token")?;
+    let client = &*CLIENT;
+    let url = media_url(bucket, object)?;
+
+    let mut delivered: u64 = 0;
+    let mut resumes = 0;
+    'request: loop {
+        let offset = start + delivered;
+        let mut res = client
+            .get(url.clone())
+            .header("Authorization", format!("Bearer {}", token))
+            .header("Range", range_header(offset, end))
+            .send_tracked()
+            .await
+            .context("Failed to download GCS object")?;
+
+        let status = res.status();
+        if status == reqwest::StatusCode::RANGE_NOT_SATISFIABLE {
+            // The requested offset lies beyond the end of the object.
+            return Ok(delivered);
+        }
+        if !status.is_success() {
+            let body = res.text().await.unwrap_or_default();
+            bail!("GCS download failed ({}): {}", status, body);
+        }
+        if offset > 0 && status != reqwest::StatusCode::PARTIAL_CONTENT {
+            bail!("GCS ignored range request (status {})", status);
+        }
+
+        loop {
+            match res.chunk().await {
+                Ok(Some(chunk)) => {
+                    delivered += chunk.len() as u64;
+                    if !sink(&chunk)? {
+                        return Ok(delivered);
+                    }
+                }
+                Ok(None) => return Ok(delivered),
+                Err(e) => {
+                    resumes += 1;
+                    if resumes > STREAM_MAX_RESUMES {
+                        return Err(e).context("GCS download stream broke too many times");
+                    }
+                    eprintln!(
+                        "GCS download interrupted at byte {} ({}); resuming ({}/{})",
+                        start + delivered,
+                        e,
+                        resumes,
+                        STREAM_MAX_RESUMES
+                    );
+                    tokio::time::sleep(Duration::from_secs(1 << resumes.min(4))).await;
+                    continue 'request;
+                }
+            }
+        }
+    }
+}
+
+/// Downloads the bytes `start..end` of an object (to its end if `end` is `None`)
+/// with an HTTP `Range` request, for looking at part of an object too large to
+/// download whole. Unlike [`download_object`] the result is not cached.
+pub async fn download_object_range(
+    bucket: &str,
+    object: &str,
+    start: u64,
+    end: Option<u64>,
+) -> Result<Vec<u8>> {
+    let mut data = Vec::new();
+    stream_object(bucket, object, start, end, |chunk| {
+        data.extend_from_slice(chunk);
+        Ok(true)
+    })
+    .await?;
+    Ok(data)
+}
+
 /// Uploads data as a new object to a GCS bucket.
 ///
 /// # Arguments
@@ -244,7 +432,7 @@ pub async fn upload_object(
         .header("Authorization", format!("Bearer {}", token))
         .header("Content-Type", content_type)
         .body(data.to_vec())
-        .send()
+        .send_tracked()
         .await
         .context("Failed to call GCS upload API")?;
 
@@ -258,6 +446,267 @@ pub async fn upload_object(
     Ok(item)
 }
 
+/// Size of one chunk of a resumable upload. GCS requires a multiple of 256 KiB.
+const UPLOAD_CHUNK_SIZE: usize = 8 * 1024 * 1024;
+
+/// Maximum number of attempts for a single chunk of `upload_object_streaming`.
+const UPLOAD_CHUNK_ATTEMPTS: usize = 5;
+
+/// Uploads everything `reader` yields as a new object, using a resumable upload
+/// session so that only one chunk is held in memory at a time.
+///
+/// A chunk that fails (network error, 429 or 5xx) is retried after asking GCS
+/// how much of it was persisted, up to `UPLOAD_CHUNK_ATTEMPTS` times. Use this
+/// instead of `upload_object` for anything that may not fit in memory, such as
+/// solver logs.
+///
+/// # Returns
+/// An `ObjectItem` containing the metadata of the newly created object.
+pub async fn upload_object_streaming<R>(
+    bucket: &str,
+    name: &str,
+    mut reader: R,
+    content_type: &str,
+) -> Result<ObjectItem>
+where
+    R: tokio::io::AsyncRead + Unpin,
+{
+    use tokio::io::AsyncReadExt;
+
+    let token = get_access_token()
+        .await
+        .context("Failed to get access token")?;
+    let client = &*CLIENT;
+
+    // 1) Open the upload session; its URI comes back in `Location`.
+    let mut url = Url::parse(&format!(
+        "https://storage.googleapis.com/upload/storage/v1/b/{}/o",
+        bucket
+    ))?;
+    {
+        let mut qp = url.query_pairs_mut();
+        qp.append_pair("uploadType", "resumable");
+        qp.append_pair("name", name);
+    }
+    let res = client
+        .post(url)
+        .header("Authorization", format!("Bearer {}", token))
+        .header("X-Upload-Content-Type", content_type)
+        .header("Content-Length", "0")
+        .send_tracked()
+        .await
+        .context("Failed to start GCS resumable upload")?;
+    if !res.status().is_success() {
+        let status = res.status();
+        let body = res.text().await.unwrap_or_default();
+        bail!("GCS resumable upload start failed ({}): {}", status, body);
+    }
+    let session = res
+        .headers()
+        .get(reqwest::header::LOCATION)
+        .and_then(|v| v.to_str().ok())
+        .context("GCS resumable upload returned no session URI")?
+        .to_string();
+
+    // 2) Send full chunks until the reader runs dry; the short (or empty) last
+    // chunk carries the total size and finalizes the object.
+    let mut buf = vec![0u8; UPLOAD_CHUNK_SIZE];
+    let mut offset: u64 = 0;
+    loop {
+        let mut len = 0;
+        while len < buf.len() {
+            let n = reader
+                .read(&mut buf[len..])
+                .await
+                .context("Failed to read upload data")?;
+            if n == 0 {
+                break;
+            }
+            len += n;
+        }
+        let last = len < buf.len();
+        let total = last.then_some(offset + len as u64);
+
+        let mut sent = 0;
+        let mut attempts = 0;
+        loop {
+            attempts += 1;
+            let token = get_access_token()
+                .await
+                .context("Failed to get access token")?;
+            let res = client
+                .put(&session)
+                .header("Authorization", format!("Bearer {}", token))
+                .header(
+                    "Content-Range",
+                    content_range(offset + sent as u64, len - sent, total),
+                )
+                .body(buf[sent..len].to_vec())
+                .send_tracked()
+                .await;
+            let error = match res {
+                Ok(res) if res.status().is_success() => {
+                    if !last {
+                        bail!("GCS finalized the upload before the last chunk");
+                    }
+                    return res.json().await.context("Invalid GCS upload response");
+                }
+                Ok(res) if res.status().as_u16() == 308 && !last => break,
+                Ok(res) => {
+                    let status = res.status();
+                    let body = res.text().await.unwrap_or_default();
+                    if status.as_u16() != 429 && !status.is_server_error() {
+                        bail!("GCS chunk upload failed ({}): {}", status, body);
+                    }
+                    anyhow::anyhow!("GCS chunk upload failed ({}): {}", status, body)
+                }
+                Err(e) => anyhow::Error::from(e).context("Failed to call GCS upload API"),
+            };
+            if attempts >= UPLOAD_CHUNK_ATTEMPTS {
+                return Err(error.context(format!(
+                    "giving up on gs://{}/{} at byte {}",
+                    bucket,
+                    name,
+                    offset + sent as u64
+                )));
+            }
+            eprintln!(
+                "GCS upload of gs://{}/{} failed at byte {} ({:#}); retrying ({}/{})",
+                bucket,
+                name,
+                offset + sent as u64,
+                error,
+                attempts,
+                UPLOAD_CHUNK_ATTEMPTS
+            );
+            tokio::time::sleep(Duration::from_secs(1 << attempts.min(4))).await;
+            // Ask how much GCS kept, so the retry sends only the rest of the chunk.
+            let res = client
+                .put(&session)
+                .header("Authorization", format!("Bearer {}", token))
+                .header("Content-Range", content_range(0, 0, total))
+                .header("Content-Length", "0")
+                .send_tracked()
+                .await;
+            if let Ok(res) = res {
+                if res.status().is_success() {
+                    return res.json().await.context("Invalid GCS upload response");
+                }
+                if res.status().as_u16() == 308 {
+                    let persisted = res
+                        .headers()
+                        .get(reqwest::header::RANGE)
+                        .and_then(|v| v.to_str().ok())
+                        .and_then(persisted_bytes)
+                        .unwrap_or(0);
+                    sent = persisted.saturating_sub(offset).min(len as u64) as usize;
+                }
+            }
+        }
+        offset += len as u64;
+    }
+}
+
+/// `Content-Range` of a resumable upload request sending `len` bytes from
+/// `start`; `total` is known only for the last chunk. `len == 0` queries the
+/// upload status (or finalizes an object whose size is a multiple of the chunk).
+fn content_range(start: u64, len: usize, total: Option<u64>) -> String {
+    let total = total.map_or("*".to_string(), |t| t.to_string());
+    if len == 0 {
+        format!("bytes */{}", total)
+    } else {
+        format!("bytes {}-{}/{}", start, start + len as u64 - 1, total)
+    }
+}
+
+/// Number of bytes GCS reports as persisted in the `Range: bytes=0-N` header of
+/// a 308 response.
+fn persisted_bytes(range: &str) -> Option<u64> {
+    let (_, last) = range.strip_prefix("bytes=")?.split_once('-')?;
+    Some(last.trim().parse::<u64>().ok()? + 1)
+}
+
+/// Copies an object within GCS (across buckets too) without downloading it,
+/// using the `rewrite` API and following its rewrite tokens until done.
+///
+/// # Returns
+/// An `ObjectItem` containing the metadata of the new object.
+pub async fn copy_object(
+    src_bucket: &str,
+    src_object: &str,
+    dst_bucket: &str,
+    dst_object: &str,
+) -> Result<ObjectItem> {
+    let token = get_access_token()
+        .await
+        .context("Failed to get access token")?;
+    let client = &*CLIENT;
+
+    let mut rewrite_token: Option<String> = None;
+    loop {
+        let mut url = Url::parse(&format!(
+            "https://storage.googleapis.com/storage/v1/b/{}/o/{}/rewriteTo/b/{}/o/{}",
+            src_bucket,
+            encode_component(src_object),
+            dst_bucket,
+            encode_component(dst_object)
+        ))?;
+        if let Some(ref t) = rewrite_token {
+            url.query_pairs_mut().append_pair("rewriteToken", t);
+        }
+
+        let res = client
+            .post(url)
+            .header("Authorization", format!("Bearer {}", token))
+            .header("Content-Length", "0")
+            .send_tracked()
+            .await
+            .context("Failed to call GCS rewrite API")?;
+        if !res.status().is_success() {
+            let status = res.status();
+            let body = res.text().await.unwrap_or_default();
+            bail!("GCS copy failed ({}): {}", status, body);
+        }
+        let body: RewriteResponse = res.json().await.context("Invalid GCS rewrite response")?;
+        if body.done {
+            return body
+                .resource
+                .context("GCS rewrite response has no resource");
+        }
+        rewrite_token = Some(
+            body.rewrite_token
+                .context("GCS rewrite response has no rewriteToken")?,
+        );
+    }
+}
+
+/// Deletes an object from a GCS bucket. Deleting an object that does not
+/// exist is an error.
+pub async fn delete_object(bucket: &str, object: &str) -> Result<()> {
+    let token = get_access_token()
+        .await
+        .context("Failed to get access token")?;
+    let client = &*CLIENT;
+
+    let url = Url::parse(&format!(
+        "https://storage.googleapis.com/storage/v1/b/{}/o/{}",
+        bucket,
+        encode_component(object)
+    ))?;
+    let res = client
+        .delete(url)
+        .header("Authorization", format!("Bearer {}", token))
+        .send_tracked()
+        .await
+        .context("Failed to call GCS delete API")?;
+    if !res.status().is_success() {
+        let status = res.status();
+        let body = res.text().await.unwrap_or_default();
+        bail!("GCS delete failed ({}): {}", status, body);
+    }
+    Ok(())
+}
+
 /// Fetches the metadata for a single object in a GCS bucket.
 ///
 /// This function uses the `list` API with a `prefix` filter to find the exact
@@ -291,7 +740,7 @@ pub async fn get_object_metadata(bucket: &str, object: &str) -> Result<ObjectIte
         let res = client
             .get(url)
             .header("Authorization", format!("Bearer {}", token))
-            .send()
+            .send_tracked()
             .await
             .context("Failed to call GCS get object via list API")?;
         if !res.status().is_success() {
@@ -329,6 +778,41 @@ mod tests {
         Ok(())
     }
 
+    #[test]
+    fn globs_split_at_the_last_literal_directory() {
+        assert_eq!(
+            split_glob("logs/*/stderr.jsonl"),
+            ("logs/".to_string(), Some("logs/*/stderr.jsonl".to_string()))
+        );
+        assert_eq!(
+            split_glob("logs/12*"),
+            ("logs/".to_string(), Some("logs/12*".to_string()))
+        );
+        assert_eq!(
+            split_glob("*.json"),
+            (String::new(), Some("*.json".to_string()))
+        );
+        assert_eq!(split_glob("logs/123/"), ("logs/123/".to_string(), None));
+    }
+
+    #[test]
+    fn range_header_format() {
+        assert_eq!(range_header(0, None), "bytes=0-");
+        assert_eq!(range_header(100, None), "bytes=100-");
+        assert_eq!(range_header(10, Some(20)), "bytes=10-19");
+    }
+
+    #[test]
+    fn resumable_upload_headers() {
+        assert_eq!(content_range(0, 10, None), "bytes 0-9/*");
+        assert_eq!(content_range(10, 5, Some(15)), "bytes 10-14/15");
+        assert_eq!(content_range(0, 0, None), "bytes */*");
+        assert_eq!(content_range(0, 0, Some(16)), "bytes */16");
+        assert_eq!(persisted_bytes("bytes=0-262143"), Some(262144));
+        assert_eq!(persisted_bytes("garbage"), None);
+        assert_eq!(UPLOAD_CHUNK_SIZE % (256 * 1024), 0);
+    }
+
     #[tokio::test]
     #[ignore]
     async fn list_dir_smoke() -> Result<()> {
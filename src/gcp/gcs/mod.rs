@@ -16,6 +16,8 @@ pub mod types;
 
 // Re-export key components to provide a convenient public API for this module.
 pub use client::{
-    download_object, get_object_metadata, list_dir, list_dir_detailed, parse_gs_url, upload_object,
+    ListOptions, copy_object, delete_object, download_object, download_object_range,
+    get_object_metadata, list_dir, list_dir_detailed, parse_gs_url, split_glob, stream_object,
+    upload_object, upload_object_streaming,
 };
 pub use types::*;
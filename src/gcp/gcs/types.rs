@@ -72,3 +72,21 @@ pub struct FileInfo {
     /// The last modification time as a string.
     pub updated: Option<String>,
 }
+
+/// Represents the response from a GCS `objects.rewrite` API call.
+///
+/// Large copies may take several calls; each returns a token to continue with
+/// until `done` is true, at which point `resource` holds the new object.
+#[derive(Debug, Deserialize, Default)]
+pub struct RewriteResponse {
+    /// Whether the copy has finished.
+    #[serde(default)]
+    pub done: bool,
+    /// A token to pass to the next call while `done` is false.
+    #[serde(rename = "rewriteToken")]
+    #[serde(default)]
+    pub rewrite_token: Option<String>,
+    /// The metadata of the copied object, once `done`.
+    #[serde(default)]
+    pub resource: Option<ObjectItem>,
+}
@@ -0,0 +1,192 @@
+//! # Map Isomorphism
+//!
+//! Two maps are the same answer if renumbering the rooms turns one into the
+//! other while keeping the starting room, the room labels and where each
+//! numbered door leads. Doors are ordered, so a BFS from a room that follows
+//! doors `0..6` in order numbers its component the same way in every
+//! isomorphic copy: [`canonical_form`] does that from the start, and numbers
+//! each component the start cannot reach from whichever of its rooms gives
+//! the smallest form, so that two maps are isomorphic exactly when their
+//! forms are equal.
+//!
+//! Only the room behind each door is compared, not which of its doors the
+//! passage arrives at, as in `LocalJudge::check_guess`.
+
+use crate::judge::{Guess, MAX_DOORS};
+
+/// A map with its rooms renumbered canonically: the rooms reachable from the
+/// start first, in BFS order (the start is room 0), then the other
+/// components.
+#[derive(Clone, Debug, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct CanonicalForm {
+    /// Number of rooms reachable from the start.
+    pub reachable: usize,
+    pub rooms: Vec<usize>,
+    pub graph: Vec<[usize; MAX_DOORS]>,
+}
+
+/// Rooms in BFS order from `root`, skipping the ones in `taken`.
+fn bfs_order(graph: &[[usize; MAX_DOORS]], root: usize, taken: &[bool]) -> Vec<usize> {
+    let mut seen = taken.to_vec();
+    seen[root] = true;
+    let mut order = vec![root];
+    let mut i = 0;
+    while i < order.len() {
+        for &v in &graph[order[i]] {
+            if !seen[v] {
+                seen[v] = true;
+                order.push(v);
+            }
+        }
+        i += 1;
+    }
+    order
+}
+
+/// Labels and door targets of `order`, numbered from `offset` on; targets
+/// outside `order` keep the number in `id`.
+fn relabel(
+    rooms: &[usize],
+    graph: &[[usize; MAX_DOORS]],
+    order: &[usize],
+    id: &[usize],
+    offset: usize,
+) -> (Vec<usize>, Vec<[usize; MAX_DOORS]>) {
+    let mut local = id.to_vec();
+    for (i, &u) in order.iter().enumerate() {
+        local[u] = offset + i;
+    }
+    (
+        order.iter().map(|&u| rooms[u]).collect(),
+        order.iter().map(|&u| graph[u].map(|v| local[v])).collect(),
+    )
+}
+
+/// The canonical form of the map with room labels `rooms`, starting room
+/// `start` and `graph[u][e]` the room behind door `e` of room `u`.
+pub fn canonical_form(
+    rooms: &[usize],
+    start: usize,
+    graph: &[[usize; MAX_DOORS]],
+) -> CanonicalForm {
+    let n = rooms.len();
+    let mut taken = vec![false; n];
+    let mut id = vec![!0; n];
+    let reached = bfs_order(graph, start, &taken);
+    for (i, &u) in reached.iter().enumerate() {
+        taken[u] = true;
+        id[u] = i;
+    }
+    // Every other component with the numbering of its smallest form over
+    // all its roots, in order of those forms.
+    let mut components = vec![];
+    let mut rest = taken.clone();
+    while let Some(first) = (0..n).find(|&u| !rest[u]) {
+        let component = bfs_order(graph, first, &rest);
+        for &u in &component {
+            rest[u] = true;
+        }
+        let best = component
+            .iter()
+            .map(|&root| {
+                let order = bfs_order(graph, root, &taken);
+                (relabel(rooms, graph, &order, &id, 0), order)
+            })
+            .min()
+            .unwrap();
+        components.push(best);
+    }
+    components.sort();
+
+    let mut form = CanonicalForm {
+        reachable: reached.len(),
+        rooms: vec![],
+        graph: vec![],
+    };
+    for order in std::iter::once(reached).chain(components.into_iter().map(|c| c.1)) {
+        let offset = form.rooms.len();
+        for (i, &u) in order.iter().enumerate() {
+            id[u] = offset + i;
+        }
+        let (r, g) = relabel(rooms, graph, &order, &id, offset);
+        form.rooms.extend(r);
+        form.graph.extend(g);
+    }
+    form
+}
+
+/// The canonical form of a guess.
+pub fn guess_form(guess: &Guess) -> CanonicalForm {
+    let graph: Vec<[usize; MAX_DOORS]> = guess.graph.iter().map(|a| a.map(|(v, _)| v)).collect();
+    canonical_form(&guess.rooms, guess.start, &graph)
+}
+
+/// Whether two guesses are the same map (see the module docs).
+pub fn isomorphic(a: &Guess, b: &Guess) -> bool {
+    a.rooms.len() == b.rooms.len() && guess_form(a) == guess_form(b)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// `guess` with rooms renumbered by `perm` (old room `u` becomes `perm[u]`).
+    fn permuted(guess: &Guess, perm: &[usize]) -> Guess {
+        let n = guess.rooms.len();
+        let mut out = Guess {
+            rooms: vec![0; n],
+            start: perm[guess.start],
+            graph: vec![[(0, 0); MAX_DOORS]; n],
+        };
+        for u in 0..n {
+            out.rooms[perm[u]] = guess.rooms[u];
+            out.graph[perm[u]] = guess.graph[u].map(|(v, f)| (perm[v], f));
+        }
+        out
+    }
+
+    #[test]
+    fn renumbered_maps_are_isomorphic_and_changed_ones_are_not() {
+        let judge = crate::judge::LocalJudge::new("random", 8, 2);
+        let guess = Guess {
+            rooms: judge.rooms.clone(),
+            start: judge.starting_room,
+            graph: judge.graph.iter().map(|a| a.map(|v| (v, 0))).collect(),
+        };
+        let perm = [3, 0, 7, 5, 1, 6, 2, 4];
+        let other = permuted(&guess, &perm);
+        assert_ne!(other, guess);
+        assert!(isomorphic(&guess, &other));
+        assert_eq!(guess_form(&guess).reachable, 8);
+
+        let mut relabeled = other.clone();
+        relabeled.rooms[0] = (relabeled.rooms[0] + 1) % 4;
+        assert!(!isomorphic(&guess, &relabeled));
+        let mut restarted = other;
+        restarted.start = perm[(guess.start + 1) % 8];
+        assert!(!isomorphic(&guess, &restarted));
+    }
+
+    #[test]
+    fn unreachable_components_are_compared_up_to_renumbering() {
+        // Room 0 loops on itself; rooms 1-2 and 3-4 form two unreachable
+        // pairs joined through door 0, with every other door looping.
+        let mut graph = vec![[0; MAX_DOORS]; 5];
+        for (u, doors) in graph.iter_mut().enumerate() {
+            *doors = [u; MAX_DOORS];
+        }
+        graph[1][0] = 2;
+        graph[2][0] = 1;
+        graph[3][0] = 4;
+        graph[4][0] = 3;
+        let a = canonical_form(&[0, 1, 2, 2, 1], 0, &graph);
+        assert_eq!(a.reachable, 1);
+        // The same pairs, listed the other way round.
+        let b = canonical_form(&[0, 2, 1, 1, 2], 0, &graph);
+        assert_eq!(a, b);
+        let c = canonical_form(&[0, 2, 3, 1, 2], 0, &graph);
+        let d = canonical_form(&[0, 1, 2, 3, 2], 0, &graph);
+        assert_eq!(c, d);
+        assert_ne!(a, c);
+    }
+}
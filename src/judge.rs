@@ -10,6 +10,8 @@
 //! The `get_judge_from_stdin` function acts as a factory, creating the appropriate
 //! judge instance based on command-line arguments or piped input, allowing the same
 //! solver binary to be used for both local testing and remote submission.
+//!
+//! Remote sessions are recorded for later replay; see [`session`].
 
 use crate::*;
 use itertools::Itertools;
@@ -35,8 +37,152 @@ pub struct JsonIn {
     pub results: Option<Vec<Vec<usize>>>,
 }
 
+pub mod session;
+
 pub type Step = (Option<usize>, usize); // (newlabel, door)
 
+/// Width of the per-room door arrays (`Guess::graph`, `LocalJudge::graph`, ...).
+pub const MAX_DOORS: usize = 6;
+
+/// The rules an instance is played under.
+///
+/// A [`LocalJudge`] carries the rules of its map and solvers take them as a
+/// parameter; code without either falls back to [`Rules::global`].
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Rules {
+    /// Doors per room (1..=[`MAX_DOORS`]). Door arrays stay [`MAX_DOORS`]
+    /// wide; doors `doors..MAX_DOORS` are treated as nonexistent and
+    /// modelled as self-loops (door `d` of room `u` leads back to door `d` of
+    /// `u`), so they are never walked, never sent to the server, and compare
+    /// equal between a guess and the true map.
+    pub doors: usize,
+    /// Size of the label alphabet (1..=[`MAX_LABELS`]); every label is
+    /// below it, and random maps label room `i` with `i % labels`.
+    pub labels: usize,
+    /// Room each plan starts from.
+    pub start_policy: StartPolicy,
+}
+
+impl Default for Rules {
+    fn default() -> Self {
+        Rules {
+            doors: MAX_DOORS,
+            labels: 4,
+            start_policy: StartPolicy::FixedRoom,
+        }
+    }
+}
+
+impl Rules {
+    /// Rules from the environment: `UNAGI_DOORS` (1..=6, default 6),
+    /// `UNAGI_LABELS` (1..=8, default 4) and `UNAGI_START_POLICY` (`fixed` or
+    /// `per-plan`, default `fixed`).
+    pub fn from_env() -> Result<Self, String> {
+        let count = |name: &str, max: usize, default: usize| match std::env::var(name) {
+            Ok(v) => match v.trim().parse::<usize>() {
+                Ok(k) if (1..=max).contains(&k) => Ok(k),
+                _ => Err(format!("{} must be 1..={}, got {:?}", name, max, v)),
+            },
+            Err(_) => Ok(default),
+        };
+        let default = Rules::default();
+        Ok(Rules {
+            doors: count("UNAGI_DOORS", MAX_DOORS, default.doors)?,
+            labels: count("UNAGI_LABELS", MAX_LABELS, default.labels)?,
+            start_policy: match std::env::var("UNAGI_START_POLICY") {
+                Ok(v) => v
+                    .parse()
+                    .map_err(|e| format!("UNAGI_START_POLICY: {}", e))?,
+                Err(_) => default.start_policy,
+            },
+        })
+    }
+
+    /// The process-wide rules: [`Rules::from_env`], read once and shared by
+    /// all threads. Panics if the environment is invalid.
+    pub fn global() -> Rules {
+        static RULES: std::sync::OnceLock<Rules> = std::sync::OnceLock::new();
+        *RULES.get_or_init(|| Rules::from_env().unwrap_or_else(|e| panic!("{}", e)))
+    }
+}
+
+/// Number of doors per room under the process-wide rules ([`Rules::doors`]
+/// of [`Rules::global`]).
+pub fn num_doors() -> usize {
+    Rules::global().doors
+}
+
+/// Largest label alphabet [`Rules::labels`] accepts.
+pub const MAX_LABELS: usize = 8;
+
+/// Number of distinct room labels under the process-wide rules
+/// ([`Rules::labels`] of [`Rules::global`]).
+pub fn num_labels() -> usize {
+    Rules::global().labels
+}
+
+/// Room each plan of an exploration starts from.
+///
+/// Serialized with the names `UNAGI_START_POLICY` takes.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+pub enum StartPolicy {
+    /// Every plan starts from the starting room (the rules so far).
+    #[default]
+    #[serde(rename = "fixed")]
+    FixedRoom,
+    /// Every plan starts from a room chosen at random and not revealed, so
+    /// only the walks themselves tell whether two plans started together.
+    #[serde(rename = "per-plan")]
+    PerPlanUnknown,
+}
+
+impl std::str::FromStr for StartPolicy {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.trim() {
+            "fixed" => Ok(StartPolicy::FixedRoom),
+            "per-plan" => Ok(StartPolicy::PerPlanUnknown),
+            _ => Err(format!("unknown start policy {:?} (fixed|per-plan)", s)),
+        }
+    }
+}
+
+/// Start policy under the process-wide rules ([`Rules::start_policy`] of
+/// [`Rules::global`]).
+pub fn start_policy() -> StartPolicy {
+    Rules::global().start_policy
+}
+
+/// Start rooms of `guess` from which walking `plan` observes `result`.
+///
+/// Under [`StartPolicy::FixedRoom`] only `guess.start` is tried.
+pub fn explaining_starts(
+    guess: &Guess,
+    plan: &[usize],
+    result: &[usize],
+    policy: StartPolicy,
+) -> Vec<usize> {
+    let candidates = match policy {
+        StartPolicy::FixedRoom => guess.start..guess.start + 1,
+        StartPolicy::PerPlanUnknown => 0..guess.rooms.len(),
+    };
+    candidates
+        .filter(|&s| walk_labels(guess, s, plan) == result)
+        .collect()
+}
+
+/// Labels observed when walking `plan` on `guess` from room `start`.
+fn walk_labels(guess: &Guess, start: usize, plan: &[usize]) -> Vec<usize> {
+    let mut u = start;
+    let mut route = vec![guess.rooms[u]];
+    for &door in plan {
+        u = guess.graph[u][door].0;
+        route.push(guess.rooms[u]);
+    }
+    route
+}
+
 fn format_step(step: Step) -> String {
     match step.0 {
         Some(newlabel) => format!("[{}]{}", newlabel, step.1),
@@ -44,7 +190,13 @@ fn format_step(step: Step) -> String {
     }
 }
 
-fn parse_plan(plan: &str) -> Vec<Step> {
+/// Formats steps as a plan string such as `"0[1]23"` (inverse of [`parse_plan`]).
+pub fn format_plan(plan: &[Step]) -> String {
+    plan.iter().map(|&step| format_step(step)).join("")
+}
+
+/// Parses a plan string such as `"0[1]23"` into steps.
+pub fn parse_plan(plan: &str) -> Vec<Step> {
     let mut res = vec![];
     // p.chars().map(|c| (c as u8 - b'0') as usize).collect()
     let mut state = 0;
@@ -67,8 +219,9 @@ fn parse_plan(plan: &str) -> Vec<Step> {
                     newlabel = None;
                 }
                 1 => {
-                    assert!(c < '4');
-                    newlabel = Some((c as u8 - b'0') as usize);
+                    let label = (c as u8).wrapping_sub(b'0') as usize;
+                    assert!(label < MAX_LABELS, "invalid label in plan: {}", c);
+                    newlabel = Some(label);
                     state = 2;
                 }
                 _ => panic!("Unexpected character in plan: {}", c),
@@ -78,6 +231,27 @@ fn parse_plan(plan: &str) -> Vec<Step> {
     res
 }
 
+/// The score increment charged for one explore request with the given plans.
+pub fn explore_cost(plans: &[Vec<Step>]) -> usize {
+    plans.len() + 1
+}
+
+/// Splits a plan into segments of at most `max_len` steps.
+///
+/// A mark is part of the step that follows it, so a rewrite always stays in the
+/// same segment as its door. An empty plan yields one empty segment.
+///
+/// The segments are separate walks: explored as plans, each starts from the
+/// starting room with the original labels, so they do not observe the marks
+/// of earlier segments. [`Judge::explore_batch`] therefore never splits a plan.
+pub fn split_plan(plan: &[Step], max_len: usize) -> Vec<Vec<Step>> {
+    assert!(max_len > 0);
+    if plan.is_empty() {
+        return vec![vec![]];
+    }
+    plan.chunks(max_len).map(|c| c.to_vec()).collect()
+}
+
 /// A trait abstracting the problem environment.
 ///
 /// This allows solver logic to be written once and used against both a local
@@ -90,8 +264,75 @@ pub trait Judge {
     /// Submits exploration plans to the judge and returns the results.
     /// The results are sequences of room signatures observed during traversal.
     fn explore(&mut self, plans: &[Vec<Step>]) -> Vec<Vec<usize>>;
+    /// Like [`Judge::explore`], but a request the server gave up on is returned
+    /// as an error instead of panicking, so a main loop can wait and retry or
+    /// switch strategy. Only [`RemoteJudge`] can fail.
+    fn try_explore(&mut self, plans: &[Vec<Step>]) -> Result<Vec<Vec<usize>>, api::ApiError> {
+        Ok(self.explore(plans))
+    }
+    /// Returns how much `explore(plans)` would add to the score, without exploring.
+    ///
+    /// Every explore request costs one query plus one per plan, independent of plan
+    /// lengths, so batching many plans into one request is always cheaper than
+    /// splitting them.
+    fn cost_of(&self, plans: &[Vec<Step>]) -> usize {
+        explore_cost(plans)
+    }
+    /// Longest plan accepted by the judge (`6 * n` doors).
+    fn max_plan_len(&self) -> usize {
+        6 * self.num_rooms()
+    }
+    /// Like [`Judge::try_explore`], with the length checks solvers used to repeat:
+    /// every plan must fit in [`Judge::max_plan_len`], and the result of each
+    /// plan is one walk of `plan.len() + 1` labels that saw all of its marks.
+    ///
+    /// A plan is never split, since every plan of a request starts from the
+    /// starting room with the original labels: a continuation could neither
+    /// reach the room where the previous part ended nor see its marks without
+    /// replaying it, which would exceed the limit again. Plans that are too
+    /// long are an error, returned before anything is explored; cut them with
+    /// [`split_plan`] if independent walks will do.
+    fn explore_batch(&mut self, plans: &[Vec<Step>]) -> anyhow::Result<Vec<Vec<usize>>> {
+        let max_len = self.max_plan_len();
+        for (i, plan) in plans.iter().enumerate() {
+            anyhow::ensure!(
+                plan.len() <= max_len,
+                "plan {} has {} doors, over the limit of {}",
+                i,
+                plan.len(),
+                max_len
+            );
+        }
+        let results = self.try_explore(plans)?;
+        anyhow::ensure!(
+            results.len() == plans.len(),
+            "{} results for {} plans",
+            results.len(),
+            plans.len()
+        );
+        for (i, (plan, result)) in plans.iter().zip(&results).enumerate() {
+            anyhow::ensure!(
+                result.len() == plan.len() + 1,
+                "plan {} has {} doors but {} labels",
+                i,
+                plan.len(),
+                result.len()
+            );
+        }
+        Ok(results)
+    }
     /// Submits a final map guess to the judge. Returns `true` if the guess is correct.
     fn guess(&self, out: &Guess) -> bool;
+    /// Like [`Judge::guess`], but failures to submit are returned (see [`Judge::try_explore`]).
+    fn try_guess(&self, out: &Guess) -> Result<bool, api::ApiError> {
+        Ok(self.guess(out))
+    }
+    /// Guess attempts left for this problem, or `None` if unlimited or unknown.
+    ///
+    /// The limit is taken from `UNAGI_GUESS_LIMIT` (see [`guess_limit`]).
+    fn guesses_remaining(&self) -> Option<usize> {
+        None
+    }
     /// Returns a log of all explorations made so far.
     fn explored(&self) -> Explored;
     /// Sets the exploration log, useful for replaying or resuming a state.
@@ -107,8 +348,43 @@ pub trait Judge {
     fn dump_json(&self) -> serde_json::Value;
 }
 
+/// Maximum number of guesses per problem, from `UNAGI_GUESS_LIMIT`.
+/// Unset (or unparsable) means unlimited.
+pub fn guess_limit() -> Option<usize> {
+    std::env::var("UNAGI_GUESS_LIMIT").ok()?.trim().parse().ok()
+}
+
+/// Number of successful `/guess` calls for `problem` recorded in `api_logs`
+/// by the team's API proxy, across all sessions.
+#[cfg(feature = "mysql")]
+fn count_logged_guesses(problem: &str) -> anyhow::Result<usize> {
+    use mysql::params;
+    let count: Option<i64> = crate::sql::cell(
+        "
+        SELECT COUNT(*)
+        FROM api_logs g
+        JOIN api_logs s
+          ON g.api_log_select_id = s.api_log_id
+            AND g.api_log_path = '/guess'
+            AND s.api_log_path = '/select'
+        WHERE s.api_log_request__problem_name = :problem
+          AND g.api_log_response_code = 200",
+        params! { "problem" => problem },
+    )?;
+    Ok(count.unwrap_or(0) as usize)
+}
+
+#[cfg(not(feature = "mysql"))]
+fn count_logged_guesses(_problem: &str) -> anyhow::Result<usize> {
+    anyhow::bail!("guess logs need the mysql feature")
+}
+
 /// Represents a solver's guess for the map's structure.
-#[derive(Clone, Debug, PartialEq, Eq)]
+///
+/// Converts to and from the contest's [`api::Map`] with `api::Map::try_from`
+/// (which checks that the graph is undirected) and [`Guess::from_map`]. The
+/// serde form is the struct itself; use `api::Map` for the contest's JSON.
+#[derive(Clone, Debug, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
 pub struct Guess {
     /// The signature of each room. `rooms[i]` is the signature of room `i`.
     /// A room's signature is the number of passages connected to it.
@@ -117,13 +393,19 @@ pub struct Guess {
     pub start: usize,
     /// The connections (passages) of the map. `graph[i][d]` is a tuple `(room, door)`
     /// indicating that door `d` of room `i` connects to the specified door of the other room.
+    /// Doors at or beyond [`num_doors`] are self-loops.
     pub graph: Vec<[(usize, usize); 6]>,
 }
 
 impl From<&api::Map> for Guess {
     fn from(map: &api::Map) -> Self {
         let n = map.rooms.len();
-        let mut graph = vec![[(!0, !0); 6]; n];
+        let mut graph = vec![[(!0, !0); MAX_DOORS]; n];
+        for (u, doors) in graph.iter_mut().enumerate() {
+            for d in num_doors()..MAX_DOORS {
+                doors[d] = (u, d);
+            }
+        }
         for c in map.connections.iter() {
             let fr = &c.from;
             let to = &c.to;
@@ -138,10 +420,43 @@ impl From<&api::Map> for Guess {
     }
 }
 
-#[derive(thiserror::Error, Debug)]
+impl Guess {
+    /// Checked version of `Guess::from(&api::Map)`: fails on rooms or doors
+    /// out of range and on a door used by two connections, where the
+    /// unchecked conversion panics or keeps the last one.
+    pub fn from_map(map: &api::Map) -> Result<Self, ParseGuessError> {
+        let n = map.rooms.len();
+        if map.starting_room >= n {
+            return Err(ParseGuessError::OutOfRange(map.starting_room, 0));
+        }
+        let mut used = vec![[false; MAX_DOORS]; n];
+        for c in &map.connections {
+            let ends = [(c.from.room, c.from.door), (c.to.room, c.to.door)];
+            for (i, &(room, door)) in ends.iter().enumerate() {
+                if room >= n || door >= num_doors() {
+                    return Err(ParseGuessError::OutOfRange(room, door));
+                }
+                // A self-loop on one door uses it once.
+                if i == 1 && ends[0] == ends[1] {
+                    continue;
+                }
+                if std::mem::replace(&mut used[room][door], true) {
+                    return Err(ParseGuessError::DoorConnectedTwice(room, door));
+                }
+            }
+        }
+        Ok(Self::from(map))
+    }
+}
+
+#[derive(thiserror::Error, Debug, PartialEq, Eq)]
 pub enum ParseGuessError {
     #[error("Graph is not undirected {0} {1} -> {2} {3} -> {4} {5}")]
     GraphIsNotDirected(usize, usize, usize, usize, usize, usize),
+    #[error("Room {0} or door {1} is out of range")]
+    OutOfRange(usize, usize),
+    #[error("Door {1} of room {0} is connected twice")]
+    DoorConnectedTwice(usize, usize),
 }
 
 impl TryFrom<&Guess> for api::Map {
@@ -157,7 +472,7 @@ impl TryFrom<&Guess> for api::Map {
         // Convert the Guess struct into the format required by the API.
         let mut connections = vec![];
         for i in 0..graph.len() {
-            for door in 0..6 {
+            for door in 0..num_doors() {
                 let (i2, door2) = graph[i][door];
                 let (i3, door3) = graph[i2][door2];
                 if (i3, door3) != (i, door) {
@@ -213,8 +528,16 @@ pub struct LocalJudge {
     pub graph: Vec<[usize; 6]>,
     /// The cumulative cost of explorations.
     cost: usize,
+    /// Number of guesses submitted so far (kept across `restart`).
+    guesses_made: std::cell::Cell<usize>,
     /// A log of all explorations performed.
     explored_log: Explored,
+    /// Probability of misreporting each observed label, and the RNG deciding it.
+    noise: Option<(f64, rand_chacha::ChaCha20Rng)>,
+    /// Draws the start room of each plan under [`StartPolicy::PerPlanUnknown`].
+    start_rng: rand_chacha::ChaCha20Rng,
+    /// The rules of this map.
+    rules: Rules,
 }
 
 impl Judge for LocalJudge {
@@ -226,20 +549,31 @@ impl Judge for LocalJudge {
     }
     fn explore(&mut self, plans: &[Vec<Step>]) -> Vec<Vec<usize>> {
         eprintln!("explore {}", plans.len());
-        self.cost += plans.len() + 1;
+        self.cost += explore_cost(plans);
         let mut ret = vec![];
         for plan in plans {
             let mut labels = self.rooms.clone();
-            let mut u = self.starting_room;
+            let mut u = match self.rules.start_policy {
+                StartPolicy::FixedRoom => self.starting_room,
+                StartPolicy::PerPlanUnknown => self.start_rng.random_range(0..self.rooms.len()),
+            };
             let mut route = vec![labels[u]];
             for &(newlabel, door) in plan {
                 if let Some(newlabel) = newlabel {
                     labels[u] = newlabel;
                 }
-                assert!(door < 6);
+                assert!(door < self.rules.doors);
                 u = self.graph[u][door];
                 route.push(labels[u]);
             }
+            if let Some((p, rng)) = &mut self.noise {
+                let k = self.rules.labels;
+                for label in route.iter_mut() {
+                    if k > 1 && rng.random_bool(*p) {
+                        *label = (*label + rng.random_range(1..k)) % k;
+                    }
+                }
+            }
             ret.push(route);
             // assert!(plan.len() <= 6 * self.num_rooms());
         }
@@ -258,9 +592,17 @@ impl Judge for LocalJudge {
         );
         self.explored_log.plans.extend(plans.to_vec());
         self.explored_log.results.extend(ret.clone());
+        progress::emit(
+            "explore",
+            serde_json::json!({ "queries": plans.len(), "cost": self.cost }),
+        );
         ret
     }
+    fn guesses_remaining(&self) -> Option<usize> {
+        guess_limit().map(|limit| limit.saturating_sub(self.guesses_made.get()))
+    }
     fn guess(&self, out: &Guess) -> bool {
+        self.guesses_made.set(self.guesses_made.get() + 1);
         println!("guess");
         println!("{}", out.rooms.iter().map(|&r| r.to_string()).join(""));
         for i in 0..out.graph.len() {
@@ -272,59 +614,20 @@ impl Judge for LocalJudge {
                     .join(" ")
             );
         }
-        // Basic validation of the guess structure.
-        if out.rooms.len() != self.rooms.len() {
-            eprintln!("!log status WA (incorrect number of rooms)");
+        if let Err(reason) = self.check_guess(out) {
+            eprintln!("!log status WA ({})", reason);
+            progress::emit(
+                "guess",
+                serde_json::json!({ "correct": false, "cost": self.cost }),
+            );
             return false;
         }
-        for i in 0..out.graph.len() {
-            for door in 0..6 {
-                let (i2, door2) = out.graph[i][door];
-                assert_eq!(out.graph[i2][door2], (i, door), "Graph is not undirected");
-            }
-        }
-        fn get_ids(graph: &[[usize; 6]], s: usize) -> Vec<usize> {
-            let n = graph.len();
-            let mut ids = vec![!0; n];
-            let mut stack = vec![];
-            ids[s] = 0;
-            stack.push(s);
-            let mut id = 1;
-            while let Some(u) = stack.pop() {
-                for &v in &graph[u] {
-                    if ids[v] == !0 {
-                        ids[v] = id;
-                        id += 1;
-                        stack.push(v);
-                    }
-                }
-            }
-            ids
-        }
-
-        let n = self.rooms.len();
-        let ids = get_ids(&self.graph, self.starting_room);
-        let out_ids = get_ids(
-            &out.graph.iter().map(|a| a.map(|(r, _d)| r)).collect_vec(),
-            out.start,
-        );
-        for i in 0..n {
-            assert!(ids[i] != !0);
-            if let Some(j) = out_ids.iter().position(|&x| x == ids[i]) {
-                // Find corresponding room in guess
-                for d in 0..6 {
-                    if ids[self.graph[i][d]] != out_ids[out.graph[j][d].0] {
-                        eprintln!("!log status WA (edge mismatch)");
-                        return false;
-                    }
-                }
-            } else {
-                eprintln!("!log status WA (disconnected room in guess)");
-                return false;
-            }
-        }
         eprintln!("!log status AC");
         eprintln!("!log score {}", self.cost);
+        progress::emit(
+            "guess",
+            serde_json::json!({ "correct": true, "cost": self.cost }),
+        );
 
         // DO NOT REMOVE HERE. THIS IS USED FOR SYSTEM TESTING!!!
         // Output JSON-encoded result for the executor to parse.
@@ -354,10 +657,10 @@ impl Judge for LocalJudge {
         let n = self.graph.len();
         let mut connections = Vec::new();
         for u in 0..n {
-            for d in 0..6 {
+            for d in 0..self.rules.doors {
                 let v = self.graph[u][d];
                 // Find the door on v that returns to u
-                let d2 = (0..6)
+                let d2 = (0..self.rules.doors)
                     .find(|&dd| self.graph[v][dd] == u)
                     .expect("graph must be undirected");
                 if (u, d) <= (v, d2) {
@@ -390,6 +693,12 @@ pub struct RemoteJudge {
     cost: usize,
     /// A log of all explorations performed.
     explored_log: Explored,
+    /// Record unexpected mark echoes instead of panicking (`UNAGI_TOLERANT_ECHOES=1`).
+    tolerant_echoes: bool,
+    /// Echo anomalies seen so far in tolerant mode.
+    echo_warnings: Vec<EchoWarning>,
+    /// The recording of this session (a `RefCell` since guessing takes `&self`).
+    recorder: std::cell::RefCell<session::Recorder>,
 }
 
 impl Judge for RemoteJudge {
@@ -399,9 +708,19 @@ impl Judge for RemoteJudge {
     fn problem_name(&self) -> &str {
         &self.problem_name
     }
+    fn cost_of(&self, plans: &[Vec<Step>]) -> usize {
+        let str_plans = plans
+            .iter()
+            .map(|p| p.iter().map(|&step| format_step(step)).join(""))
+            .collect_vec();
+        explore_cost(plans) + api::explore_chunks(&str_plans, api::explore_max_bytes()).len() - 1
+    }
     fn explore(&mut self, plans: &[Vec<Step>]) -> Vec<Vec<usize>> {
+        self.try_explore(plans)
+            .unwrap_or_else(|e| panic!("Failed to explore: {}", e))
+    }
+    fn try_explore(&mut self, plans: &[Vec<Step>]) -> Result<Vec<Vec<usize>>, api::ApiError> {
         println!("explore {}", plans.len());
-        self.cost += plans.len() + 1;
         for plan in plans {
             println!("{}", plan.iter().map(|&step| format_step(step)).join(""));
             // assert!(plan.len() <= 6 * self.num_rooms());
@@ -411,34 +730,32 @@ impl Judge for RemoteJudge {
             .map(|p| p.iter().map(|&step| format_step(step)).join(""))
             .collect();
         // Delegate the actual exploration to the API client.
-        let raw_response = api::explore(&str_plans).expect("Failed to explore");
-        assert_eq!(raw_response.results.len(), plans.len());
-        let results = plans
-            .iter()
-            .zip(raw_response.results.iter())
-            .map(|(plan, response)| {
-                let mut filtered_response = vec![response[0]];
-                let mut ix = 1;
-                for &(rewrite, _door) in plan.iter() {
-                    if let Some(rewrite) = rewrite {
-                        assert_eq!(response[ix], rewrite);
-                        ix += 1;
-                    }
-                    filtered_response.push(response[ix]);
-                    ix += 1;
-                }
-                assert_eq!(ix, response.len());
-                filtered_response
-            })
-            .collect_vec();
-        self.explored_log.plans.extend(plans.to_vec());
-        self.explored_log.results.extend(results.clone());
-        for r in &results {
-            println!("{}", r.iter().join(""));
+        let raw_response = match api::explore(&str_plans) {
+            Ok(res) => res,
+            Err(api::ApiError::PartialExplore { done, source }) => {
+                // The answered POSTs were charged: keep their results.
+                self.record(&plans[..done.results.len()], &done);
+                return Err(api::ApiError::PartialExplore { done, source });
+            }
+            Err(e) => return Err(e),
+        };
+        Ok(self.record(plans, &raw_response))
+    }
+    fn guesses_remaining(&self) -> Option<usize> {
+        let limit = guess_limit()?;
+        match count_logged_guesses(&self.problem_name) {
+            Ok(used) => Some(limit.saturating_sub(used)),
+            Err(e) => {
+                eprintln!("failed to count guesses of {}: {}", self.problem_name, e);
+                None
+            }
         }
-        results
     }
     fn guess(&self, out: &Guess) -> bool {
+        self.try_guess(out)
+            .unwrap_or_else(|e| panic!("Failed to guess: {}", e))
+    }
+    fn try_guess(&self, out: &Guess) -> Result<bool, api::ApiError> {
         println!("guess");
         println!("{}", out.rooms.iter().map(|&r| r.to_string()).join(""));
         for i in 0..out.graph.len() {
@@ -453,15 +770,31 @@ impl Judge for RemoteJudge {
         // Convert the Guess struct into the format required by the API.
         let map = api::Map::try_from(out).unwrap();
         // Delegate the guess to the API client.
-        let ret = api::guess(&map).expect("Failed to guess");
+        let (ret, log_id) = api::guess_logged(&map)?;
+        self.recorder.borrow_mut().guess(&map, ret);
 
         if ret {
             eprintln!("!log status AC");
             eprintln!("!log score {}", self.cost);
         } else {
             eprintln!("!log status WA");
+            let analysis =
+                wa_analysis::analyze_wrong_guess(out, self.num_rooms, &self.explored_log);
+            eprintln!("!log wa_reason {}", analysis.reason.as_str());
+            for hint in &analysis.hints {
+                eprintln!("wa hint: {}", hint);
+            }
+            if let Some(log_id) = log_id
+                && let Err(e) = wa_analysis::record(log_id, &analysis)
+            {
+                eprintln!("failed to record WA analysis: {}", e);
+            }
         }
-        ret
+        progress::emit(
+            "guess",
+            serde_json::json!({ "correct": ret, "cost": self.cost }),
+        );
+        Ok(ret)
     }
     fn explored(&self) -> Explored {
         self.explored_log.clone()
@@ -473,14 +806,15 @@ impl Judge for RemoteJudge {
         api::select(&self.problem_name).expect("Failed to select problem");
         *self = Self {
             problem_name: self.problem_name.to_string(),
-            num_rooms: problems::get_problem(&self.problem_name)
-                .unwrap_or_else(|| panic!("Unknown problem: {}", &self.problem_name))
-                .size,
+            num_rooms: self.num_rooms,
             cost: 0,
             explored_log: Explored {
                 plans: vec![],
                 results: vec![],
             },
+            tolerant_echoes: self.tolerant_echoes,
+            echo_warnings: vec![],
+            recorder: session::Recorder::new(&self.problem_name, self.num_rooms).into(),
         }
     }
     fn dump_json(&self) -> serde_json::Value {
@@ -492,24 +826,456 @@ impl Judge for RemoteJudge {
     }
 }
 
+/// A judge that answers plans already run in a prior session from that
+/// session's log and forwards only unseen plans to the wrapped judge.
+///
+/// A resumed solver can then re-issue its whole exploration schedule and
+/// treat old and new results uniformly: repeated plans cost nothing, and
+/// `explored()` holds the prior log followed by everything forwarded since.
+/// The prior log must come from the same session as `inner` (the same map),
+/// otherwise the replayed results are meaningless: a remote `inner` must be
+/// that session resumed, never a fresh `/select`.
+pub struct ChainedJudge {
+    inner: Box<dyn Judge>,
+    prior: std::collections::HashMap<Vec<Step>, Vec<usize>>,
+    explored_log: Explored,
+    /// Plans answered from the prior log so far.
+    replayed: usize,
+}
+
+impl ChainedJudge {
+    pub fn new(inner: Box<dyn Judge>, prior: Explored) -> Self {
+        let mut judge = Self {
+            inner,
+            prior: Default::default(),
+            explored_log: Explored {
+                plans: vec![],
+                results: vec![],
+            },
+            replayed: 0,
+        };
+        judge.set_explored(prior);
+        judge
+    }
+
+    /// Number of plans answered from the prior log instead of the inner judge.
+    pub fn replayed(&self) -> usize {
+        self.replayed
+    }
+}
+
+impl Judge for ChainedJudge {
+    fn num_rooms(&self) -> usize {
+        self.inner.num_rooms()
+    }
+    fn problem_name(&self) -> &str {
+        self.inner.problem_name()
+    }
+    fn explore(&mut self, plans: &[Vec<Step>]) -> Vec<Vec<usize>> {
+        self.try_explore(plans)
+            .unwrap_or_else(|e| panic!("Failed to explore: {}", e))
+    }
+    fn try_explore(&mut self, plans: &[Vec<Step>]) -> Result<Vec<Vec<usize>>, api::ApiError> {
+        let unseen = plans
+            .iter()
+            .filter(|p| !self.prior.contains_key(*p))
+            .cloned()
+            .collect_vec();
+        let mut fresh = if unseen.is_empty() {
+            vec![]
+        } else {
+            self.inner.try_explore(&unseen)?
+        }
+        .into_iter();
+        self.replayed += plans.len() - unseen.len();
+        let results = plans
+            .iter()
+            .map(|p| match self.prior.get(p) {
+                Some(r) => r.clone(),
+                None => fresh.next().unwrap(),
+            })
+            .collect_vec();
+        for (plan, result) in plans.iter().zip(&results) {
+            if !self.prior.contains_key(plan) {
+                self.prior.insert(plan.clone(), result.clone());
+                self.explored_log.plans.push(plan.clone());
+                self.explored_log.results.push(result.clone());
+            }
+        }
+        Ok(results)
+    }
+    fn cost_of(&self, plans: &[Vec<Step>]) -> usize {
+        let unseen = plans
+            .iter()
+            .filter(|p| !self.prior.contains_key(*p))
+            .cloned()
+            .collect_vec();
+        if unseen.is_empty() {
+            0
+        } else {
+            self.inner.cost_of(&unseen)
+        }
+    }
+    fn max_plan_len(&self) -> usize {
+        self.inner.max_plan_len()
+    }
+    fn guess(&self, out: &Guess) -> bool {
+        self.inner.guess(out)
+    }
+    fn try_guess(&self, out: &Guess) -> Result<bool, api::ApiError> {
+        self.inner.try_guess(out)
+    }
+    fn guesses_remaining(&self) -> Option<usize> {
+        self.inner.guesses_remaining()
+    }
+    fn explored(&self) -> Explored {
+        self.explored_log.clone()
+    }
+    fn set_explored(&mut self, explored: Explored) {
+        assert_eq!(explored.plans.len(), explored.results.len());
+        self.prior = explored
+            .plans
+            .iter()
+            .cloned()
+            .zip(explored.results.iter().cloned())
+            .collect();
+        self.explored_log = explored;
+    }
+    fn restart(&mut self) {
+        // A new session has a new map, so the prior log no longer applies.
+        self.inner.restart();
+        self.set_explored(Explored {
+            plans: vec![],
+            results: vec![],
+        });
+    }
+    fn dump_json(&self) -> serde_json::Value {
+        self.inner.dump_json()
+    }
+}
+
+/// A remote judge for `problem_name` that answers the plans of `prior` from
+/// that log, for the remote mode of [`get_judge_from_input`].
+///
+/// A prior log only describes the map of the session it was recorded in, so it
+/// is chained onto that session resumed (see [`RemoteJudge::resume`]), and only
+/// if every plan of the log is in the session's journal with the same result.
+/// Otherwise the log is dropped with a warning and a new session is selected:
+/// seeding a fresh map with old results would give the solver a contradiction.
+fn chain_onto_open_session(problem_name: &str, prior: Explored) -> Box<dyn Judge> {
+    let resumed = RemoteJudge::resume(problem_name).and_then(|judge| {
+        let explored = judge.explored();
+        let journal: std::collections::HashMap<_, _> =
+            explored.plans.iter().zip(&explored.results).collect();
+        let mismatch = prior
+            .plans
+            .iter()
+            .zip(&prior.results)
+            .position(|(plan, result)| journal.get(plan) != Some(&result));
+        if let Some(i) = mismatch {
+            anyhow::bail!(
+                "plan {} of the prior log is not in the open session {}",
+                i,
+                judge.session_url()
+            );
+        }
+        Ok(judge)
+    });
+    match resumed {
+        Ok(judge) => {
+            let explored = judge.explored();
+            Box::new(ChainedJudge::new(Box::new(judge), explored))
+        }
+        Err(e) => {
+            eprintln!(
+                "dropping the prior log of {} plans: {:#}",
+                prior.plans.len(),
+                e
+            );
+            Box::new(RemoteJudge::new(problem_name))
+        }
+    }
+}
+
 impl RemoteJudge {
     /// Creates a new `RemoteJudge` for a given problem.
     ///
     /// This function calls `api::select` to lock the problem on the server.
+    ///
+    /// Panics if the problem is unknown or the selection fails; see [`Self::try_new`].
     pub fn new(problem_name: &str) -> Self {
-        api::select(problem_name).expect("Failed to select problem");
-        Self {
-            problem_name: problem_name.to_string(),
-            num_rooms: problems::get_problem(problem_name)
-                .unwrap_or_else(|| panic!("Unknown problem: {}", problem_name))
-                .size,
+        Self::try_new(problem_name).unwrap_or_else(|e| panic!("{:#}", e))
+    }
+
+    /// Fallible version of [`Self::new`].
+    ///
+    /// The name is resolved (case-insensitively, with aliases) before anything
+    /// is sent, so a typo fails with a did-you-mean message without starting a
+    /// session on the server. A name missing from the bundled list is looked up
+    /// in the server's current list first.
+    ///
+    /// With `UNAGI_RESUME=1`, an open session of the problem in the local
+    /// journal is resumed (see [`Self::resume`]) instead of selecting anew;
+    /// if the server no longer answers like that session, a new one is
+    /// selected.
+    pub fn try_new(problem_name: &str) -> anyhow::Result<Self> {
+        let problem = problems::find_problem_or_refresh(problem_name)?;
+        if std::env::var("UNAGI_RESUME").is_ok_and(|v| v == "1")
+            && let Some(session) =
+                session::Session::latest_open(&session::journal_dir(), &problem.problem)?
+        {
+            match Self::from_session(session) {
+                Ok(judge) => return Ok(judge),
+                Err(e) => eprintln!("not resuming {}: {:#}", problem.problem, e),
+            }
+        }
+        api::select(&problem.problem)?;
+        Ok(Self {
+            problem_name: problem.problem.clone(),
+            num_rooms: problem.size,
             cost: 0,
             explored_log: Explored {
                 plans: vec![],
                 results: vec![],
             },
+            tolerant_echoes: std::env::var("UNAGI_TOLERANT_ECHOES").is_ok_and(|v| v == "1"),
+            echo_warnings: vec![],
+            recorder: session::Recorder::new(&problem.problem, problem.size).into(),
+        })
+    }
+
+    /// Picks up the latest session of `problem_name` that an earlier process
+    /// selected and explored but did not guess, from the local journal (see
+    /// [`session`]), without `/select`ing again. `explored()` and the cost
+    /// start from that session's log, so a solver can feed the prior results
+    /// to the SAT solver and only explore what is still missing.
+    ///
+    /// Only the newest journaled session of the problem is resumed, and only
+    /// while it is open. The server must still be on it, which the lock
+    /// normally guarantees until it expires; before the log is trusted, one
+    /// of its plans is explored again to check (see [`Self::check_resumed`]).
+    pub fn resume(problem_name: &str) -> anyhow::Result<Self> {
+        use anyhow::Context;
+
+        let problem = problems::find_problem_or_refresh(problem_name)?;
+        let session = session::Session::latest_open(&session::journal_dir(), &problem.problem)?
+            .with_context(|| format!("no open session of {} to resume", problem.problem))?;
+        Self::from_session(session)
+    }
+
+    /// Like [`Self::resume`], but for the session journaled at `url` (a local
+    /// file or a `gs://` journal uploaded with `UNAGI_SESSION_SYNC=1`).
+    pub fn resume_from(url: &str) -> anyhow::Result<Self> {
+        Self::from_session(session::Session::load(url)?)
+    }
+
+    fn from_session(session: session::Session) -> anyhow::Result<Self> {
+        anyhow::ensure!(
+            session.is_open(),
+            "session {} has already guessed",
+            session.session_id
+        );
+        api::reattach()?;
+        let explored_log = session.explored();
+        let cost = session.cost();
+        eprintln!(
+            "resuming session {} of {}: {} plans, cost {}",
+            session.session_id,
+            session.problem_name(),
+            explored_log.plans.len(),
+            cost
+        );
+        let mut judge = Self {
+            problem_name: session.problem_name().to_string(),
+            num_rooms: session.num_rooms(),
+            cost,
+            explored_log,
+            tolerant_echoes: std::env::var("UNAGI_TOLERANT_ECHOES").is_ok_and(|v| v == "1"),
+            echo_warnings: vec![],
+            recorder: session::Recorder::resume(session).into(),
+        };
+        judge.check_resumed()?;
+        Ok(judge)
+    }
+
+    /// Re-explores the longest plan of the resumed log and checks that the
+    /// server answers it as recorded, i.e. that it is still on the journaled
+    /// session's map: `api::reattach` takes the lock without asking the
+    /// server anything. The probe costs one request like any other and is
+    /// logged and recorded with the session.
+    fn check_resumed(&mut self) -> anyhow::Result<()> {
+        let log = &self.explored_log;
+        let (plan, expected) = log
+            .plans
+            .iter()
+            .zip(&log.results)
+            .max_by_key(|(plan, _)| plan.len())
+            .map(|(plan, result)| (plan.clone(), result.clone()))
+            .ok_or_else(|| anyhow::anyhow!("the session has nothing to resume"))?;
+        let results = self.try_explore(std::slice::from_ref(&plan))?;
+        anyhow::ensure!(
+            results[0] == expected,
+            "the server is no longer on session {}: {} answered {} instead of {}",
+            self.session_url(),
+            format_plan(&plan),
+            results[0].iter().join(""),
+            expected.iter().join("")
+        );
+        Ok(())
+    }
+
+    /// Charges and logs an `/explore` response for `plans`, stripping mark
+    /// echoes, and returns its results.
+    fn record(
+        &mut self,
+        plans: &[Vec<Step>],
+        raw_response: &api::ExploreResponse,
+    ) -> Vec<Vec<usize>> {
+        // Every extra POST of a split request is charged like a request.
+        self.cost += explore_cost(plans) + raw_response.chunks - 1;
+        let (results, warnings) = filter_explore_response(plans, &raw_response.results);
+        for w in warnings {
+            assert!(self.tolerant_echoes, "{}", w);
+            eprintln!("warning: {}", w);
+            self.echo_warnings.push(w);
+        }
+        self.explored_log.plans.extend(plans.to_vec());
+        self.explored_log.results.extend(results.clone());
+        self.recorder.borrow_mut().explore(plans, &results);
+        for r in &results {
+            println!("{}", r.iter().join(""));
         }
+        progress::emit(
+            "explore",
+            serde_json::json!({ "queries": plans.len(), "cost": self.cost }),
+        );
+        let counts = label_histogram(
+            &self.explored_log.plans,
+            &self.explored_log.results,
+            num_labels(),
+        );
+        for warning in label_histogram_warnings(self.num_rooms, &counts) {
+            eprintln!(
+                "warning: label histogram of {}: {}",
+                self.problem_name, warning
+            );
+        }
+        results
+    }
+
+    /// Enables or disables tolerant handling of mark echoes (see [`strip_echoes`]).
+    pub fn set_tolerant_echoes(&mut self, tolerant: bool) {
+        self.tolerant_echoes = tolerant;
+    }
+
+    /// Echo anomalies recorded so far in tolerant mode.
+    pub fn echo_warnings(&self) -> &[EchoWarning] {
+        &self.echo_warnings
     }
+
+    /// `gs://` URL the current session is recorded to.
+    pub fn session_url(&self) -> String {
+        self.recorder.borrow().session().url()
+    }
+}
+
+/// An unexpected label echo in an `/explore` response for a plan with `[k]` rewrites.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum EchoWarning {
+    /// The echo after rewriting with `written` at `step` reported `echoed`.
+    Mismatch {
+        plan: usize,
+        step: usize,
+        written: usize,
+        echoed: usize,
+    },
+    /// The response had no echoes at all (one label per step plus the start).
+    Missing { plan: usize },
+}
+
+impl std::fmt::Display for EchoWarning {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            EchoWarning::Mismatch {
+                plan,
+                step,
+                written,
+                echoed,
+            } => write!(
+                f,
+                "plan #{} step {}: wrote label {} but the server echoed {}",
+                plan, step, written, echoed
+            ),
+            EchoWarning::Missing { plan } => {
+                write!(f, "plan #{}: response contains no mark echoes", plan)
+            }
+        }
+    }
+}
+
+/// Drops the labels the server echoes back after each `[k]` rewrite, leaving
+/// one label per room visited.
+///
+/// Echoes that differ from the written label, or responses without any echo,
+/// are reported as warnings rather than treated as fatal. Panics only if the
+/// response length fits neither layout.
+pub fn strip_echoes(
+    plan_index: usize,
+    plan: &[Step],
+    response: &[usize],
+) -> (Vec<usize>, Vec<EchoWarning>) {
+    let rewrites = plan.iter().filter(|s| s.0.is_some()).count();
+    if rewrites > 0 && response.len() == plan.len() + 1 {
+        return (
+            response.to_vec(),
+            vec![EchoWarning::Missing { plan: plan_index }],
+        );
+    }
+    assert_eq!(
+        response.len(),
+        plan.len() + 1 + rewrites,
+        "plan #{}: unexpected response length",
+        plan_index
+    );
+    let mut filtered = vec![response[0]];
+    let mut warnings = vec![];
+    let mut ix = 1;
+    for (step, &(rewrite, _door)) in plan.iter().enumerate() {
+        if let Some(written) = rewrite {
+            if response[ix] != written {
+                warnings.push(EchoWarning::Mismatch {
+                    plan: plan_index,
+                    step,
+                    written,
+                    echoed: response[ix],
+                });
+            }
+            ix += 1;
+        }
+        filtered.push(response[ix]);
+        ix += 1;
+    }
+    (filtered, warnings)
+}
+
+/// Turns the raw `/explore` results into one label per room visited for each
+/// plan, collecting the echo anomalies of all plans (see [`strip_echoes`]).
+///
+/// This is everything [`RemoteJudge::explore`] does to the server's answer, kept
+/// apart so it can be checked against responses synthesized from a [`LocalJudge`].
+pub fn filter_explore_response(
+    plans: &[Vec<Step>],
+    raw_results: &[Vec<usize>],
+) -> (Vec<Vec<usize>>, Vec<EchoWarning>) {
+    assert_eq!(raw_results.len(), plans.len());
+    let mut results = Vec::with_capacity(plans.len());
+    let mut warnings = vec![];
+    for (i, (plan, response)) in plans.iter().zip(raw_results).enumerate() {
+        let (filtered, w) = strip_echoes(i, plan, response);
+        warnings.extend(w);
+        results.push(filtered);
+    }
+    (results, warnings)
 }
 
 pub fn generate_random_edges_v2(
@@ -520,7 +1286,7 @@ pub fn generate_random_edges_v2(
     let mut list1 = vec![];
     let mut list2 = vec![];
     for i in 0..num_rooms {
-        for door in 0..6 {
+        for door in 0..num_doors() {
             list1.push((i, door));
             list2.push((i, door));
         }
@@ -590,7 +1356,14 @@ fn perm_self_loop(n: usize, rng: &mut impl Rng) -> Vec<(usize, usize)> {
     connections
 }
 
-pub fn generate_marks_instance(num_rooms: usize, num_layers: usize, seed: u64) -> MarksInstance {
+/// A random map of `num_layers` copies of a random super map, whose rooms are
+/// labelled `i % num_labels` (shared by all copies of super room `i`).
+pub fn generate_marks_instance(
+    num_rooms: usize,
+    num_layers: usize,
+    num_labels: usize,
+    seed: u64,
+) -> MarksInstance {
     let mut rng = rand_chacha::ChaCha20Rng::seed_from_u64(seed);
     assert!(num_rooms.is_multiple_of(num_layers));
     let num_supers = num_rooms / num_layers;
@@ -632,7 +1405,7 @@ pub fn generate_marks_instance(num_rooms: usize, num_layers: usize, seed: u64) -
         }
     }
 
-    let super_to_label = (0..num_supers).map(|i| i % 4).collect_vec();
+    let super_to_label = (0..num_supers).map(|i| i % num_labels).collect_vec();
     let mut room_to_label = vec![!0; num_rooms];
     let mut room_to_layer = vec![!0; num_rooms];
     let mut room_to_super = vec![!0; num_rooms];
@@ -676,22 +1449,105 @@ fn check_graph(graph: &[[usize; 6]]) {
 }
 
 impl LocalJudge {
-    /// Creates a new `LocalJudge` with a randomly generated map.
+    /// Makes `explore` misreport each observed label with probability `p`
+    /// (replaced by one of the other three labels uniformly), to see how
+    /// solvers cope with corrupted observations. The noise is deterministic
+    /// for a given `seed`; `check_guess` still compares against the true map.
+    pub fn with_noise(mut self, p: f64, seed: u64) -> Self {
+        assert!(
+            (0.0..=1.0).contains(&p),
+            "noise probability out of range: {}",
+            p
+        );
+        self.noise = (p > 0.0).then(|| (p, rand_chacha::ChaCha20Rng::seed_from_u64(seed)));
+        self
+    }
+
+    /// The rules of this map.
+    pub fn rules(&self) -> Rules {
+        self.rules
+    }
+
+    /// Queries charged for the explorations so far (since the last `restart`).
+    pub fn cost(&self) -> usize {
+        self.cost
+    }
+
+    /// Guesses submitted so far, counted across `restart`.
+    pub fn guesses_made(&self) -> usize {
+        self.guesses_made.get()
+    }
+
+    /// Checks a guess against the true map without printing anything or
+    /// counting it as an attempt. `Err` carries the reason of a wrong answer.
+    pub fn check_guess(&self, out: &Guess) -> Result<(), &'static str> {
+        // Basic validation of the guess structure.
+        if out.rooms.len() != self.rooms.len() {
+            return Err("incorrect number of rooms");
+        }
+        for i in 0..out.graph.len() {
+            for door in 0..6 {
+                let (i2, door2) = out.graph[i][door];
+                assert_eq!(out.graph[i2][door2], (i, door), "Graph is not undirected");
+            }
+        }
+        // Compared up to renumbering the rooms from the start (see `graph_iso`).
+        let truth = graph_iso::canonical_form(&self.rooms, self.starting_room, &self.graph);
+        assert_eq!(truth.reachable, self.rooms.len());
+        let mut form = graph_iso::guess_form(out);
+        if self.rules.start_policy == StartPolicy::PerPlanUnknown {
+            // No plan reveals the starting room, so any room of the guess may play it.
+            let graph: Vec<[usize; 6]> = out.graph.iter().map(|a| a.map(|(v, _)| v)).collect();
+            if let Some(f) = (0..out.rooms.len())
+                .map(|s| graph_iso::canonical_form(&out.rooms, s, &graph))
+                .find(|f| f.graph == truth.graph && f.rooms == truth.rooms)
+            {
+                form = f;
+            }
+        }
+        if form.reachable < truth.reachable {
+            return Err("disconnected room in guess");
+        }
+        if form.graph != truth.graph {
+            return Err("edge mismatch");
+        }
+        if form.rooms != truth.rooms {
+            return Err("room label mismatch");
+        }
+        Ok(())
+    }
+
+    /// Creates a new `LocalJudge` with a randomly generated map under [`Rules::global`].
     pub fn new(problem_type: &str, num_rooms: usize, seed: u64) -> Self {
+        Self::new_with_rules(problem_type, num_rooms, seed, Rules::global())
+    }
+
+    /// Like [`LocalJudge::new`], under `rules`. Map kinds other than `random`
+    /// are generated for the process-wide door count, so they must agree.
+    pub fn new_with_rules(problem_type: &str, num_rooms: usize, seed: u64, rules: Rules) -> Self {
+        assert!(
+            problem_type == "random" || rules.doors == num_doors(),
+            "{} maps are generated with {} doors",
+            problem_type,
+            num_doors()
+        );
         let mut rng = rand_chacha::ChaCha20Rng::seed_from_u64(seed);
         let problem_args = format!("{} {} {}", problem_type, num_rooms, seed);
         let j = match problem_type {
             "random" => {
                 // Generate room signatures.
-                let mut rooms = (0..num_rooms).map(|i| i % 4).collect_vec();
+                let mut rooms = (0..num_rooms).map(|i| i % rules.labels).collect_vec();
                 rooms.shuffle(&mut rng);
                 // Generate a random perfect matching on the set of all doors to create the graph's passages.
-                let mut graph = vec![[!0; 6]; num_rooms];
+                let mut graph = vec![[!0; MAX_DOORS]; num_rooms];
                 let mut list = vec![];
                 for i in 0..num_rooms {
-                    for door in 0..6 {
+                    for door in 0..rules.doors {
                         list.push((i, door));
                     }
+                    for door in rules.doors..MAX_DOORS {
+                        graph[i][door] = i;
+                    }
                 }
                 list.shuffle(&mut rng);
                 for i in 0..list.len() / 2 {
@@ -707,17 +1563,29 @@ impl LocalJudge {
                     starting_room: 0, // Start at room 0 (the fixed starting room in the problem spec)
                     graph,
                     cost: 0,
+                    guesses_made: Default::default(),
+                    start_rng: rand_chacha::ChaCha20Rng::seed_from_u64(seed),
                     explored_log: Explored {
                         plans: vec![],
                         results: vec![],
                     },
+                    noise: None,
+                    rules,
                 }
             }
-            "random2" => {
-                let mut rooms = (0..num_rooms).map(|i| i % 4).collect_vec();
+            "random2" | "grid" | "tree" | "bottleneck" | "expander" => {
+                let mut rooms = (0..num_rooms).map(|i| i % rules.labels).collect_vec();
                 rooms.shuffle(&mut rng);
-                let edges = generate_random_edges_v2(num_rooms, seed);
-                let mut graph = vec![[!0; 6]; num_rooms];
+                let edges = match problem_type {
+                    "random2" => generate_random_edges_v2(num_rooms, seed),
+                    kind => crate::mapgen::structured::generate(kind, num_rooms, seed).unwrap(),
+                };
+                let mut graph = vec![[!0; MAX_DOORS]; num_rooms];
+                for (u, doors) in graph.iter_mut().enumerate() {
+                    for d in num_doors()..MAX_DOORS {
+                        doors[d] = u;
+                    }
+                }
                 for ((u1, d1), (u2, d2)) in edges {
                     if (u1 == u2) && (d1 == d2) {
                         eprintln!("Self-loop: {} {}", u1, d1);
@@ -733,10 +1601,14 @@ impl LocalJudge {
                     starting_room: 0, // Start at room 0 (the fixed starting room in the problem spec)
                     graph,
                     cost: 0,
+                    guesses_made: Default::default(),
+                    start_rng: rand_chacha::ChaCha20Rng::seed_from_u64(seed),
                     explored_log: Explored {
                         plans: vec![],
                         results: vec![],
                     },
+                    noise: None,
+                    rules,
                 }
             }
             "random_2layers" | "random_3layers" => {
@@ -746,7 +1618,7 @@ impl LocalJudge {
                     3
                 };
 
-                let instance = generate_marks_instance(num_rooms, num_layers, seed);
+                let instance = generate_marks_instance(num_rooms, num_layers, rules.labels, seed);
                 let mut graph = vec![[!0; 6]; num_rooms];
                 for ((u1, d1), (u2, d2)) in instance.edges {
                     graph[u1][d1] = u2;
@@ -761,10 +1633,14 @@ impl LocalJudge {
                     starting_room: 0, // Start at room 0 (the fixed starting room in the problem spec)
                     graph,
                     cost: 0,
+                    guesses_made: Default::default(),
+                    start_rng: rand_chacha::ChaCha20Rng::seed_from_u64(seed),
                     explored_log: Explored {
                         plans: vec![],
                         results: vec![],
                     },
+                    noise: None,
+                    rules,
                 }
             }
             _ => panic!("Unknown problem type: {}", problem_type),
@@ -778,10 +1654,24 @@ impl LocalJudge {
         j
     }
 
+    /// Creates a new `LocalJudge` from an edge-list or DOT graph file
+    /// (see [`crate::mapgen::graph_file`]). The problem name is the file stem.
+    pub fn from_graph_file(path: &std::path::Path) -> anyhow::Result<Self> {
+        let map = crate::mapgen::graph_file::load(path)?;
+        let name = path.file_stem().map(|s| s.to_string_lossy().into_owned());
+        Ok(Self::new_json(name, &map))
+    }
+
     /// Creates a new `LocalJudge` from a map structure provided in an `api::Map`.
     pub fn new_json(problem_name: Option<String>, map: &api::Map) -> Self {
+        let rules = Rules::global();
         let n = map.rooms.len();
-        let mut graph = vec![[0usize; 6]; n];
+        let mut graph = vec![[0usize; MAX_DOORS]; n];
+        for (u, doors) in graph.iter_mut().enumerate() {
+            for d in rules.doors..MAX_DOORS {
+                doors[d] = u;
+            }
+        }
 
         // Initialize RNG from env var SEED (fallback to 0)
         let seed: u64 = std::env::var("SEED")
@@ -797,14 +1687,14 @@ impl LocalJudge {
             .unwrap_or(0);
         let mut rng = rand_chacha::ChaCha20Rng::seed_from_u64(seed);
 
-        // Build per-room door remapping (0..5 -> shuffled 0..5)
-        let mut door_maps: Vec<[usize; 6]> = Vec::with_capacity(n);
+        // Build per-room door remapping (active doors -> shuffled active doors)
+        let mut door_maps: Vec<[usize; MAX_DOORS]> = Vec::with_capacity(n);
         for _ in 0..n {
-            let mut m = [0usize; 6];
+            let mut m = [0usize; MAX_DOORS];
             for (d, slot) in m.iter_mut().enumerate() {
                 *slot = d;
             }
-            m.shuffle(&mut rng);
+            m[..rules.doors].shuffle(&mut rng);
             door_maps.push(m);
         }
 
@@ -812,7 +1702,8 @@ impl LocalJudge {
         for c in &map.connections {
             let fr = &c.from;
             let to = &c.to;
-            if fr.room < n && fr.door < 6 && to.room < n && to.door < 6 {
+            let k = rules.doors;
+            if fr.room < n && fr.door < k && to.room < n && to.door < k {
                 let new_fd = door_maps[fr.room][fr.door];
                 let new_td = door_maps[to.room][to.door];
                 graph[fr.room][new_fd] = to.room;
@@ -826,10 +1717,14 @@ impl LocalJudge {
             rooms: map.rooms.clone(),
             graph,
             cost: 0,
+            guesses_made: Default::default(),
+            start_rng: rand_chacha::ChaCha20Rng::seed_from_u64(0),
             explored_log: Explored {
                 plans: vec![],
                 results: vec![],
             },
+            noise: None,
+            rules,
         };
         // Emit map dump for UNAGI harness
         let map_json = j.dump_json();
@@ -852,7 +1747,53 @@ pub fn get_judge_from_stdin_with(explored: bool) -> Box<dyn Judge> {
     use std::io::Read;
     let mut input = String::new();
     std::io::stdin().read_to_string(&mut input).unwrap();
+    get_judge_from_input(&input, explored)
+}
 
+/// Room count of the problem described by judge input (the stdin format of
+/// [`get_judge_from_stdin`]), without constructing the judge. Unlike the
+/// judge itself this never contacts the server, so it is safe to call before
+/// deciding whether to start a remote session.
+pub fn input_num_rooms(input: &str) -> Option<usize> {
+    let s = input.trim_start();
+    if s.starts_with('{') {
+        let parsed: JsonIn = serde_json::from_str(s).ok()?;
+        return parsed
+            .num_rooms
+            .or(parsed.map.as_ref().map(|m| m.rooms.len()))
+            .or_else(|| Some(problems::get_problem(parsed.problem_name.as_deref()?)?.size));
+    }
+    let tokens = s.split_whitespace().collect_vec();
+    match tokens.as_slice() {
+        ["local", _, n, ..] => n.parse().ok(),
+        ["remote", name, ..] => Some(problems::get_problem(name)?.size),
+        ["file", path, ..] => Some(
+            crate::mapgen::graph_file::load(std::path::Path::new(path))
+                .ok()?
+                .rooms
+                .len(),
+        ),
+        _ => None,
+    }
+}
+
+/// Applies `UNAGI_LABEL_NOISE` (a probability, see [`LocalJudge::with_noise`])
+/// to a judge that knows the true map.
+fn with_noise_from_env(j: LocalJudge) -> LocalJudge {
+    match std::env::var("UNAGI_LABEL_NOISE") {
+        Ok(v) => {
+            let p = v
+                .trim()
+                .parse()
+                .unwrap_or_else(|_| panic!("invalid UNAGI_LABEL_NOISE: {:?}", v));
+            j.with_noise(p, 0)
+        }
+        Err(_) => j,
+    }
+}
+
+/// Like [`get_judge_from_stdin_with`], but reads the input from a string.
+pub fn get_judge_from_input(input: &str, explored: bool) -> Box<dyn Judge> {
     let s = input.trim_start();
     // If input begins with '{', treat the entire input as a single JSON object.
     // This provides a flexible way to configure the judge for local testing,
@@ -875,18 +1816,24 @@ pub fn get_judge_from_stdin_with(explored: bool) -> Box<dyn Judge> {
                     .problem_name
                     .as_ref()
                     .expect("problemName is required for remote mode");
-                let mut jr = RemoteJudge::new(name);
                 if let (Some(plans), Some(results)) =
                     (parsed.plans.as_ref(), parsed.results.as_ref())
                 {
-                    jr.set_explored(single_to_explored(plans.clone(), results.clone()));
+                    chain_onto_open_session(
+                        name,
+                        single_to_explored(plans.clone(), results.clone()),
+                    )
+                } else {
+                    Box::new(RemoteJudge::new(name))
                 }
-                Box::new(jr)
             }
             Some("local") | None => {
                 if let Some(map) = parsed.map {
                     // Create a local judge from a complete map definition.
-                    Box::new(LocalJudge::new_json(parsed.problem_name, &map))
+                    Box::new(with_noise_from_env(LocalJudge::new_json(
+                        parsed.problem_name,
+                        &map,
+                    )))
                 } else if let (Some(plans), Some(results)) = (parsed.plans, parsed.results) {
                     // Create a local judge from existing exploration results, without the true map.
                     // This is useful for "replaying" a remote session locally.
@@ -894,9 +1841,9 @@ pub fn get_judge_from_stdin_with(explored: bool) -> Box<dyn Judge> {
                     let num_rooms = if let Some(n) = parsed.num_rooms {
                         n
                     } else if let Some(ref name) = parsed.problem_name {
-                        problems::get_problem(name.as_str())
-                            .map(|p| p.size)
-                            .expect("numRooms missing and unknown problemName")
+                        problems::find_problem(name.as_str())
+                            .unwrap_or_else(|e| panic!("numRooms missing and {}", e))
+                            .size
                     } else {
                         panic!("numRooms missing and problemName not provided");
                     };
@@ -907,7 +1854,11 @@ pub fn get_judge_from_stdin_with(explored: bool) -> Box<dyn Judge> {
                         starting_room: 0, // Start at room 0 (the fixed starting room in the problem spec)
                         graph: vec![[0; 6]; num_rooms], // True graph is unknown
                         cost: 0,
+                        guesses_made: Default::default(),
+                        start_rng: rand_chacha::ChaCha20Rng::seed_from_u64(0),
                         explored_log,
+                        noise: None,
+                        rules: Rules::global(),
                     })
                 } else {
                     panic!("JSON must contain either 'map' or ('plans' & 'results')");
@@ -922,7 +1873,7 @@ pub fn get_judge_from_stdin_with(explored: bool) -> Box<dyn Judge> {
             let mut rng = rand::rng();
             let mut plan = Vec::with_capacity(6 * n);
             for _ in 0..(6 * n) {
-                plan.push((None, rng.random_range(0..6)));
+                plan.push((None, rng.random_range(0..num_doors())));
             }
             let _ = j.explore(&[plan]);
         }
@@ -942,7 +1893,11 @@ pub fn get_judge_from_stdin_with(explored: bool) -> Box<dyn Judge> {
                 num_rooms: usize,
                 seed: u64,
             }
-            Box::new(LocalJudge::new(&problem_type, num_rooms, seed))
+            Box::new(with_noise_from_env(LocalJudge::new(
+                &problem_type,
+                num_rooms,
+                seed,
+            )))
         }
         "remote" => {
             input! {
@@ -951,7 +1906,17 @@ pub fn get_judge_from_stdin_with(explored: bool) -> Box<dyn Judge> {
             }
             Box::new(RemoteJudge::new(&problem_name))
         }
-        _ => panic!("local_remote must be 'local' or 'remote'"),
+        "file" => {
+            input! {
+                from &mut src,
+                path: String,
+            }
+            Box::new(with_noise_from_env(
+                LocalJudge::from_graph_file(std::path::Path::new(&path))
+                    .unwrap_or_else(|e| panic!("{:#}", e)),
+            ))
+        }
+        _ => panic!("local_remote must be 'local', 'remote' or 'file'"),
     };
 
     // Optionally pre-populate with a random exploration if requested.
@@ -960,7 +1925,7 @@ pub fn get_judge_from_stdin_with(explored: bool) -> Box<dyn Judge> {
         let mut rng = rand::rng();
         let mut plan = Vec::with_capacity(6 * n);
         for _ in 0..(6 * n) {
-            plan.push((None, rng.random_range(0..6)));
+            plan.push((None, rng.random_range(0..num_doors())));
         }
         let _ = j.explore(&[plan]);
     }
@@ -979,17 +1944,22 @@ pub fn get_judge_from_stdin_with(explored: bool) -> Box<dyn Judge> {
 /// # Returns
 /// `true` if the guess perfectly reproduces the results for all given plans.
 pub fn check_explore(guess: &Guess, plans: &[Vec<usize>], results: &[Vec<usize>]) -> bool {
+    check_explore_with_policy(guess, plans, results, start_policy())
+}
+
+/// [`check_explore`] for plans started under `policy` instead of the
+/// process-wide [`start_policy`].
+pub fn check_explore_with_policy(
+    guess: &Guess,
+    plans: &[Vec<usize>],
+    results: &[Vec<usize>],
+    policy: StartPolicy,
+) -> bool {
     assert_eq!(plans.len(), results.len());
     for (plan, result) in plans.iter().zip(results.iter()) {
-        // Simulate the plan on the guessed map.
-        let mut u = guess.start;
-        let mut route = vec![guess.rooms[u]];
-        for &door in plan {
-            u = guess.graph[u][door].0;
-            route.push(guess.rooms[u]);
-        }
-        // Check if the simulated route matches the actual result.
-        if &route != result {
+        // Simulate the plan on the guessed map from every allowed start.
+        if explaining_starts(guess, plan, result, policy).is_empty() {
+            let route = walk_labels(guess, guess.start, plan);
             eprintln!("expected: {}", result.iter().join(""));
             eprintln!("actual  : {}", route.iter().join(""));
             return false;
@@ -1003,26 +1973,759 @@ pub fn check_explore2(
     plans: &[Vec<(Option<usize>, usize)>],
     results: &[Vec<usize>],
 ) -> bool {
-    assert_eq!(plans.len(), results.len());
-    for (id, (plan, result)) in plans.iter().zip(results.iter()).enumerate() {
-        // Simulate the plan on the guessed map.
+    let check = check_explore_detailed(guess, plans, results);
+    match check.first_mismatch() {
+        Some((id, replay)) => {
+            eprintln!("mismatch in plan #{}:", id);
+            eprint!("{}", replay);
+            false
+        }
+        None => true,
+    }
+}
+
+/// The first place where a guess fails to reproduce an exploration log.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Divergence {
+    /// Index of the plan.
+    pub plan: usize,
+    /// Index into the plan's result (0 is the starting room's label, `i + 1`
+    /// the label seen after step `i`), or the shorter length if only the
+    /// lengths differ.
+    pub step: usize,
+    /// The labels the guess produces for the whole plan.
+    pub route: Vec<usize>,
+}
+
+/// One plan of an exploration log replayed on a guess.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct PlanReplay {
+    /// First index where `actual` differs from `expected`, as in
+    /// [`Divergence::step`], or `None` if the plan is reproduced.
+    pub mismatch: Option<usize>,
+    /// The labels the judge returned.
+    pub expected: Vec<usize>,
+    /// The labels the guess produces.
+    pub actual: Vec<usize>,
+    /// Rooms of the guess the walk is in, the starting room first; one per
+    /// label of `actual`.
+    pub rooms: Vec<usize>,
+}
+
+impl PlanReplay {
+    fn new(guess: &Guess, plan: &[Step], expected: &[usize]) -> Self {
         let mut labels = guess.rooms.clone();
         let mut u = guess.start;
-        let mut route = vec![labels[u]];
+        let mut actual = vec![labels[u]];
+        let mut rooms = vec![u];
         for &(new_label, door) in plan {
             if let Some(new_label) = new_label {
                 labels[u] = new_label;
             }
             u = guess.graph[u][door].0;
-            route.push(labels[u]);
+            actual.push(labels[u]);
+            rooms.push(u);
         }
-        // Check if the simulated route matches the actual result.
-        if &route != result {
-            eprintln!("mismatch in plan #{}:", id);
-            eprintln!("expected: {}", result.iter().join(""));
-            eprintln!("actual  : {}", route.iter().join(""));
-            return false;
+        let mismatch = (actual != expected).then(|| {
+            actual
+                .iter()
+                .zip(expected)
+                .position(|(a, b)| a != b)
+                .unwrap_or(actual.len().min(expected.len()))
+        });
+        PlanReplay {
+            mismatch,
+            expected: expected.to_vec(),
+            actual,
+            rooms,
         }
     }
-    true
+}
+
+impl std::fmt::Display for PlanReplay {
+    /// The label sequences and the room trace, one line each, with a caret
+    /// under the first mismatch.
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        writeln!(f, "expected: {}", self.expected.iter().join(""))?;
+        writeln!(f, "actual  : {}", self.actual.iter().join(""))?;
+        if let Some(step) = self.mismatch {
+            writeln!(f, "          {}^ label {}", " ".repeat(step), step)?;
+        }
+        writeln!(f, "rooms   : {}", self.rooms.iter().join(" "))
+    }
+}
+
+/// Every plan of an exploration log replayed on a guess.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct ExploreCheck {
+    pub plans: Vec<PlanReplay>,
+}
+
+impl ExploreCheck {
+    /// Whether the guess reproduces every plan.
+    pub fn is_ok(&self) -> bool {
+        self.plans.iter().all(|p| p.mismatch.is_none())
+    }
+
+    /// The first plan the guess does not reproduce, with its index.
+    pub fn first_mismatch(&self) -> Option<(usize, &PlanReplay)> {
+        self.plans
+            .iter()
+            .enumerate()
+            .find(|(_, p)| p.mismatch.is_some())
+    }
+}
+
+/// Replays every plan (with `[k]` rewrites) on `guess`, keeping the labels
+/// and rooms of each walk, for callers that report more than a bool.
+pub fn check_explore_detailed(
+    guess: &Guess,
+    plans: &[Vec<Step>],
+    results: &[Vec<usize>],
+) -> ExploreCheck {
+    assert_eq!(plans.len(), results.len());
+    ExploreCheck {
+        plans: plans
+            .iter()
+            .zip(results)
+            .map(|(plan, result)| PlanReplay::new(guess, plan, result))
+            .collect(),
+    }
+}
+
+/// Replays `plans` (with `[k]` rewrites) on `guess` and returns the first
+/// label that differs from `results`, or `None` if every plan matches.
+pub fn first_divergence(
+    guess: &Guess,
+    plans: &[Vec<Step>],
+    results: &[Vec<usize>],
+) -> Option<Divergence> {
+    assert_eq!(plans.len(), results.len());
+    for (id, (plan, result)) in plans.iter().zip(results.iter()).enumerate() {
+        let replay = PlanReplay::new(guess, plan, result);
+        if let Some(step) = replay.mismatch {
+            return Some(Divergence {
+                plan: id,
+                step,
+                route: replay.actual,
+            });
+        }
+    }
+    None
+}
+
+/// Rewires passages that no plan walks through so that every room of `guess`
+/// is reachable from the start.
+///
+/// A SAT model is only pinned down where the explorations went, so rooms the
+/// data never reached can end up in a separate component, which the judge
+/// rejects as "disconnected room in guess". For each such component, an unused
+/// passage of the reachable part and one of the component are swapped, which
+/// leaves every explored walk (and thus `check_explore`) unchanged. Returns the
+/// number of swaps, or `Err` if the reachable part has no unused passage left.
+/// The plans were started under `policy`.
+pub fn repair_connectivity(
+    guess: &mut Guess,
+    plans: &[Vec<usize>],
+    policy: StartPolicy,
+) -> Result<usize, &'static str> {
+    let n = guess.rooms.len();
+    // Ports that an explored walk or an earlier repair depends on.
+    // Under `StartPolicy::PerPlanUnknown` a walk may have started anywhere, so
+    // the walks from every room are kept.
+    let starts = match policy {
+        StartPolicy::FixedRoom => guess.start..guess.start + 1,
+        StartPolicy::PerPlanUnknown => 0..n,
+    };
+    let mut locked = vec![[false; 6]; n];
+    for plan in plans {
+        for start in starts.clone() {
+            let mut u = start;
+            for &door in plan {
+                let (v, f) = guess.graph[u][door];
+                locked[u][door] = true;
+                locked[v][f] = true;
+                u = v;
+            }
+        }
+    }
+    let mut swaps = 0;
+    loop {
+        let mut reached = vec![false; n];
+        reached[guess.start] = true;
+        let mut stack = vec![guess.start];
+        while let Some(u) = stack.pop() {
+            for &(v, _) in &guess.graph[u] {
+                if !reached[v] {
+                    reached[v] = true;
+                    stack.push(v);
+                }
+            }
+        }
+        let Some(c) = (0..n).find(|&u| !reached[u]) else {
+            return Ok(swaps);
+        };
+        let Some((a, da)) = (0..n)
+            .filter(|&u| reached[u])
+            .flat_map(|u| (0..num_doors()).map(move |d| (u, d)))
+            .find(|&(u, d)| {
+                let (v, f) = guess.graph[u][d];
+                !locked[u][d] && !locked[v][f]
+            })
+        else {
+            return Err("no unused passage to rewire");
+        };
+        let x1 = (a, da);
+        let x2 = guess.graph[a][da];
+        let y1 = (c, 0);
+        let y2 = guess.graph[c][0];
+        let mut link = |p: (usize, usize), q: (usize, usize)| {
+            guess.graph[p.0][p.1] = q;
+            guess.graph[q.0][q.1] = p;
+        };
+        // x1-x2 and y1-y2 become x1-y1 and x2-y2; a one-door loop on either
+        // side leaves the other side's spare door as a loop.
+        link(x1, y1);
+        if x1 != x2 && y1 != y2 {
+            link(x2, y2);
+        } else if x1 != x2 {
+            link(x2, x2);
+        } else if y1 != y2 {
+            link(y2, y2);
+        }
+        locked[x1.0][x1.1] = true;
+        locked[y1.0][y1.1] = true;
+        swaps += 1;
+    }
+}
+
+/// Counts observed labels `0..num_labels`, ignoring everything after the
+/// first charcoal rewrite of each plan (marks make later labels
+/// unrepresentative).
+pub fn label_histogram(
+    plans: &[Vec<Step>],
+    results: &[Vec<usize>],
+    num_labels: usize,
+) -> Vec<usize> {
+    let mut counts = vec![0; num_labels];
+    for (plan, result) in plans.iter().zip(results) {
+        let clean = plan
+            .iter()
+            .position(|&(rewrite, _)| rewrite.is_some())
+            .map_or(result.len(), |i| i + 1);
+        for &label in result.iter().take(clean) {
+            if label < num_labels {
+                counts[label] += 1;
+            }
+        }
+    }
+    counts
+}
+
+/// Expected share of each label when room `i` is labelled `i % num_labels`.
+///
+/// Every room has the same number of doors, so a long walk visits rooms
+/// uniformly and label frequencies converge to the share of rooms carrying
+/// each label.
+pub fn expected_label_shares(num_rooms: usize, num_labels: usize) -> Vec<f64> {
+    let mut shares = vec![0.0; num_labels];
+    for i in 0..num_rooms {
+        shares[i % num_labels] += 1.0 / num_rooms as f64;
+    }
+    shares
+}
+
+/// Observations below this many per room are too few to judge the histogram.
+const LABEL_CHECK_MIN_OBS_PER_ROOM: usize = 18;
+/// Allowed absolute deviation of a label share from its expected value.
+/// Walks are strongly correlated, so this is loose: simulated 18n-step walks on
+/// random maps stay within it in well over 99% of cases for n >= 6.
+const LABEL_SHARE_TOLERANCE: f64 = 0.2;
+
+/// Compares observed label frequencies with what a `num_rooms`-room problem
+/// should produce and describes anything suspicious, e.g. a label that cannot
+/// exist for this size or a share far from `n/k` (often a sign that the
+/// problem or its variant was misdetected). The alphabet is that of `counts`
+/// (see [`label_histogram`]).
+pub fn label_histogram_warnings(num_rooms: usize, counts: &[usize]) -> Vec<String> {
+    let total: usize = counts.iter().sum();
+    let expected = expected_label_shares(num_rooms, counts.len());
+    let mut warnings = vec![];
+    for label in 0..counts.len() {
+        if expected[label] == 0.0 && counts[label] > 0 {
+            warnings.push(format!(
+                "label {} observed {} times but a {}-room map has no such room",
+                label, counts[label], num_rooms
+            ));
+        }
+    }
+    if total < LABEL_CHECK_MIN_OBS_PER_ROOM * num_rooms {
+        return warnings;
+    }
+    for label in 0..counts.len() {
+        let share = counts[label] as f64 / total as f64;
+        if expected[label] > 0.0 && (share - expected[label]).abs() > LABEL_SHARE_TOLERANCE {
+            warnings.push(format!(
+                "label {} share {:.3} deviates from expected {:.3} ({} of {} observations)",
+                label, share, expected[label], counts[label], total
+            ));
+        }
+    }
+    warnings
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn guesses_round_trip_through_maps_and_json() {
+        // Room 0: a self-loop on door 0 and a loop between its doors 1 and 2;
+        // rooms 0 and 1 are joined twice (doors 3-3 and 4-5).
+        let mut graph = vec![[(0, 0); MAX_DOORS]; 2];
+        for (u, doors) in graph.iter_mut().enumerate() {
+            for (d, door) in doors.iter_mut().enumerate() {
+                *door = (u, d);
+            }
+        }
+        graph[0][1] = (0, 2);
+        graph[0][2] = (0, 1);
+        graph[0][3] = (1, 3);
+        graph[1][3] = (0, 3);
+        graph[0][4] = (1, 5);
+        graph[1][5] = (0, 4);
+        let guess = Guess {
+            rooms: vec![0, 1],
+            start: 1,
+            graph,
+        };
+        let map = api::Map::try_from(&guess).unwrap();
+        // Besides the three passages, two doors of room 0 and four of room 1
+        // lead back to themselves.
+        assert_eq!(map.connections.len(), 9);
+        assert_eq!(Guess::from_map(&map).unwrap(), guess);
+        let json = serde_json::to_string(&map).unwrap();
+        let map: api::Map = serde_json::from_str(&json).unwrap();
+        assert_eq!(Guess::from_map(&map).unwrap(), guess);
+        let json = serde_json::to_string(&guess).unwrap();
+        assert_eq!(serde_json::from_str::<Guess>(&json).unwrap(), guess);
+
+        let mut broken = guess.clone();
+        broken.graph[1][0] = (0, 0);
+        assert_eq!(
+            api::Map::try_from(&broken).unwrap_err(),
+            ParseGuessError::GraphIsNotDirected(1, 0, 0, 0, 0, 0)
+        );
+        let mut twice = map.clone();
+        twice.connections.push(twice.connections[1].clone());
+        assert!(matches!(
+            Guess::from_map(&twice),
+            Err(ParseGuessError::DoorConnectedTwice(..))
+        ));
+        let mut outside = map;
+        outside.connections[0].to.room = 2;
+        assert_eq!(
+            Guess::from_map(&outside).unwrap_err(),
+            ParseGuessError::OutOfRange(2, outside.connections[0].to.door)
+        );
+    }
+
+    #[test]
+    fn label_histogram_flags_wrong_size() {
+        let mut judge = LocalJudge::new("random", 12, 1);
+        let plans = vec![
+            crate::routes::long_plan(12)
+                .into_iter()
+                .map(|d| (None, d))
+                .collect_vec(),
+        ];
+        let results = judge.explore(&plans);
+        let counts = label_histogram(&plans, &results, 4);
+        assert_eq!(counts.iter().sum::<usize>(), plans[0].len() + 1);
+        assert!(label_histogram_warnings(12, &counts).is_empty());
+        // Treating the same observations as a 3-room problem must be flagged.
+        assert!(!label_histogram_warnings(3, &counts).is_empty());
+    }
+
+    #[test]
+    fn label_histogram_stops_at_first_mark() {
+        let plans = vec![parse_plan("0[1]23")];
+        let results = vec![vec![0, 2, 1, 1]];
+        assert_eq!(label_histogram(&plans, &results, 4), [1, 0, 1, 0]);
+        assert_eq!(label_histogram(&plans, &results, 2), [1, 0]);
+    }
+
+    #[test]
+    fn explore_batch_matches_unsplit_runs_with_marks() {
+        let mut judge = LocalJudge::new("random", 3, 4);
+        let marked = (0..18)
+            .map(|i| (if i % 7 == 0 { Some(i % 4) } else { None }, i % 6))
+            .collect_vec();
+        let short = parse_plan("[3]012");
+        let results = judge
+            .explore_batch(&[marked.clone(), short.clone()])
+            .unwrap();
+        assert_eq!(results.len(), 2);
+        assert_eq!(results[0].len(), marked.len() + 1);
+        let mut fresh = LocalJudge::new("random", 3, 4);
+        assert_eq!(results, fresh.explore(&[marked, short]));
+        assert_eq!(judge.explored().plans.len(), 2);
+
+        let segments = split_plan(&parse_plan("0123450123450123450"), 18);
+        assert_eq!(segments.iter().map(|s| s.len()).collect_vec(), [18, 1]);
+    }
+
+    #[test]
+    fn explore_batch_rejects_plans_over_the_limit() {
+        let mut judge = LocalJudge::new("random", 3, 4);
+        let err = judge
+            .explore_batch(&[vec![(None, 0); 2], vec![(None, 0); 19]])
+            .unwrap_err();
+        assert_eq!(err.to_string(), "plan 1 has 19 doors, over the limit of 18");
+        assert!(judge.explored().plans.is_empty());
+    }
+
+    #[test]
+    fn input_num_rooms_does_not_need_a_judge() {
+        assert_eq!(input_num_rooms("local random 12 1"), Some(12));
+        assert_eq!(input_num_rooms("remote probatio"), Some(3));
+        assert_eq!(
+            input_num_rooms(r#"{"problemName": "primus", "plans": [], "results": []}"#),
+            Some(6)
+        );
+        assert_eq!(input_num_rooms("remote nosuchproblem"), None);
+    }
+
+    #[test]
+    fn strip_echoes_reports_anomalies() {
+        let plan = parse_plan("0[1]23");
+        let (labels, warnings) = strip_echoes(0, &plan, &[0, 2, 1, 1, 3]);
+        assert_eq!(labels, vec![0, 2, 1, 3]);
+        assert!(warnings.is_empty());
+
+        let (labels, warnings) = strip_echoes(1, &plan, &[0, 2, 3, 1, 3]);
+        assert_eq!(labels, vec![0, 2, 1, 3]);
+        assert_eq!(
+            warnings,
+            vec![EchoWarning::Mismatch {
+                plan: 1,
+                step: 1,
+                written: 1,
+                echoed: 3
+            }]
+        );
+
+        let (labels, warnings) = strip_echoes(2, &plan, &[0, 2, 1, 3]);
+        assert_eq!(labels, vec![0, 2, 1, 3]);
+        assert_eq!(warnings, vec![EchoWarning::Missing { plan: 2 }]);
+    }
+
+    /// What the server sends for `plans` on `judge`'s map: the start label,
+    /// then per step the echo of a `[k]` rewrite (if any) and the next label.
+    fn remote_response(judge: &LocalJudge, plans: &[Vec<Step>]) -> Vec<Vec<usize>> {
+        plans
+            .iter()
+            .map(|plan| {
+                let mut labels = judge.rooms.clone();
+                let mut u = judge.starting_room;
+                let mut raw = vec![labels[u]];
+                for &(rewrite, door) in plan {
+                    if let Some(k) = rewrite {
+                        labels[u] = k;
+                        raw.push(k);
+                    }
+                    u = judge.graph[u][door];
+                    raw.push(labels[u]);
+                }
+                raw
+            })
+            .collect()
+    }
+
+    #[test]
+    fn filter_explore_response_matches_local_judge() {
+        let mut rng = rand_chacha::ChaCha20Rng::seed_from_u64(4516);
+        for seed in 0..100 {
+            let n = [3, 6, 12][seed as usize % 3];
+            let mut judge = LocalJudge::new("random", n, seed);
+            let plans = (0..rng.random_range(1..5))
+                .map(|_| {
+                    (0..rng.random_range(0..=6 * n))
+                        .map(|_| {
+                            let rewrite = rng.random_bool(0.3).then(|| rng.random_range(0..4));
+                            (rewrite, rng.random_range(0..num_doors()))
+                        })
+                        .collect_vec()
+                })
+                .collect_vec();
+            let mut raw = remote_response(&judge, &plans);
+            let expected = judge.explore(&plans);
+            let (results, warnings) = filter_explore_response(&plans, &raw);
+            assert_eq!(results, expected, "seed {}", seed);
+            assert!(warnings.is_empty(), "seed {}: {:?}", seed, warnings);
+
+            // A wrong echo is reported at its own plan and step and never
+            // shifts the labels around it.
+            let echoes = plans
+                .iter()
+                .enumerate()
+                .flat_map(|(i, plan)| {
+                    let mut ix = 0;
+                    plan.iter()
+                        .enumerate()
+                        .filter_map(move |(step, &(rewrite, _))| {
+                            ix += 1 + rewrite.is_some() as usize;
+                            rewrite.map(|k| (i, step, ix - 1, k))
+                        })
+                        .collect_vec()
+                })
+                .collect_vec();
+            if let Some(&(i, step, ix, written)) = echoes.choose(&mut rng) {
+                let echoed = (written + rng.random_range(1..4)) % 4;
+                raw[i][ix] = echoed;
+                let (results, warnings) = filter_explore_response(&plans, &raw);
+                assert_eq!(results, expected, "seed {}", seed);
+                assert_eq!(
+                    warnings,
+                    vec![EchoWarning::Mismatch {
+                        plan: i,
+                        step,
+                        written,
+                        echoed
+                    }],
+                    "seed {}",
+                    seed
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn first_divergence_finds_the_first_wrong_label() {
+        let mut judge = LocalJudge::new("random", 6, 3);
+        let plans = vec![parse_plan("012345"), parse_plan("0[3]1[2]2345")];
+        let mut results = judge.explore(&plans);
+        let guess = Guess {
+            rooms: judge.rooms.clone(),
+            start: judge.starting_room,
+            graph: (0..6)
+                .map(|u| std::array::from_fn(|d| (judge.graph[u][d], !0)))
+                .collect(),
+        };
+        assert_eq!(first_divergence(&guess, &plans, &results), None);
+        assert!(check_explore2(&guess, &plans, &results));
+        let route = results[1].clone();
+        results[1][4] = (results[1][4] + 1) % 4;
+        assert_eq!(
+            first_divergence(&guess, &plans, &results),
+            Some(Divergence {
+                plan: 1,
+                step: 4,
+                route
+            })
+        );
+        assert!(!check_explore2(&guess, &plans, &results));
+
+        let check = check_explore_detailed(&guess, &plans, &results);
+        assert!(!check.is_ok());
+        assert_eq!(check.plans[0].mismatch, None);
+        let (id, replay) = check.first_mismatch().unwrap();
+        assert_eq!((id, replay.mismatch), (1, Some(4)));
+        assert_eq!(replay.expected, results[1]);
+        assert_eq!(replay.rooms.len(), replay.actual.len());
+        assert_eq!(replay.rooms[0], judge.starting_room);
+        for (t, &(_, door)) in plans[1].iter().enumerate() {
+            assert_eq!(replay.rooms[t + 1], judge.graph[replay.rooms[t]][door]);
+        }
+        assert!(replay.to_string().contains("              ^ label 4\n"));
+    }
+
+    #[test]
+    fn repair_connectivity_keeps_explorations() {
+        // Rooms 0-1 and 2-3 form two components; the plan only walks 0 -> 1 -> 0.
+        let mut graph = vec![[(!0, !0); 6]; 4];
+        for (u, v) in [(0, 1), (2, 3)] {
+            for d in 0..6 {
+                graph[u][d] = (v, d);
+                graph[v][d] = (u, d);
+            }
+        }
+        let mut guess = Guess {
+            rooms: vec![0, 1, 2, 3],
+            start: 0,
+            graph,
+        };
+        let plans = vec![vec![2, 2]];
+        let results = vec![vec![0, 1, 0]];
+        assert_eq!(
+            repair_connectivity(&mut guess, &plans, StartPolicy::FixedRoom),
+            Ok(1)
+        );
+        assert!(check_explore(&guess, &plans, &results));
+        // 0 -> 2 and 1 -> 3 now join the two components.
+        assert_eq!(guess.graph[0][0], (2, 0));
+        assert_eq!(guess.graph[1][0], (3, 0));
+        for u in 0..4 {
+            for d in 0..6 {
+                let (v, f) = guess.graph[u][d];
+                assert_eq!(guess.graph[v][f], (u, d));
+            }
+        }
+
+        // With every passage of the reachable part explored, nothing can move.
+        let mut guess = Guess {
+            rooms: vec![0, 1, 2, 3],
+            start: 0,
+            graph: guess.graph.clone(),
+        };
+        guess.graph[0][0] = (1, 0);
+        guess.graph[1][0] = (0, 0);
+        guess.graph[2][0] = (3, 0);
+        guess.graph[3][0] = (2, 0);
+        let all = vec![(0..6).flat_map(|d| [d, d]).collect_vec()];
+        assert_eq!(
+            repair_connectivity(&mut guess, &all, StartPolicy::FixedRoom),
+            Err("no unused passage to rewire")
+        );
+    }
+
+    #[test]
+    fn per_plan_starts_are_not_tied_to_the_starting_room() {
+        let rules = Rules {
+            start_policy: StartPolicy::PerPlanUnknown,
+            ..Rules::default()
+        };
+        let mut judge = LocalJudge::new_with_rules("random", 12, 5, rules);
+        let plans = vec![vec![]; 40];
+        let results = judge.explore(&plans);
+        let firsts = results.iter().map(|r| r[0]).unique().count();
+        assert!(firsts > 1, "every plan started on the same label");
+
+        // The true map with another room as start is still the answer.
+        let map: api::Map = serde_json::from_value(judge.dump_json()).unwrap();
+        let mut guess = Guess::from(&map);
+        guess.start = (0..12)
+            .find(|&u| judge.rooms[u] != judge.rooms[judge.starting_room])
+            .unwrap();
+        assert_eq!(judge.check_guess(&guess), Ok(()));
+
+        let plans = (0..10)
+            .map(|i| (0..8).map(|t| (None, (i + t * 5) % 6)).collect_vec())
+            .collect_vec();
+        let results = judge.explore(&plans);
+        let doors = plans
+            .iter()
+            .map(|p| p.iter().map(|s| s.1).collect_vec())
+            .collect_vec();
+        assert!(check_explore_with_policy(
+            &guess,
+            &doors,
+            &results,
+            StartPolicy::PerPlanUnknown
+        ));
+
+        judge.rules.start_policy = StartPolicy::FixedRoom;
+        assert!(judge.check_guess(&guess).is_err());
+    }
+
+    #[test]
+    fn chained_judge_forwards_only_unseen_plans() {
+        let mut first = LocalJudge::new("random", 6, 3);
+        let old = parse_plan("012345");
+        let new = parse_plan("[1]5432");
+        first.explore(std::slice::from_ref(&old));
+        let mut chained =
+            ChainedJudge::new(Box::new(LocalJudge::new("random", 6, 3)), first.explored());
+        assert_eq!(chained.cost_of(std::slice::from_ref(&old)), 0);
+        let results = chained.explore(&[new.clone(), old.clone()]);
+        assert_eq!(results, first.explore(&[new.clone(), old.clone()]));
+        assert_eq!(chained.replayed(), 1);
+        assert_eq!(chained.inner.explored().plans, vec![new.clone()]);
+        assert_eq!(chained.explored().plans, vec![old, new.clone()]);
+        // A plan forwarded once is served from the log afterwards.
+        chained.explore(&[new]);
+        assert_eq!(chained.replayed(), 2);
+        assert_eq!(chained.inner.explored().plans.len(), 1);
+    }
+
+    /// A judge whose server is down: every request fails.
+    struct DownJudge(LocalJudge);
+
+    impl Judge for DownJudge {
+        fn num_rooms(&self) -> usize {
+            self.0.num_rooms()
+        }
+        fn problem_name(&self) -> &str {
+            self.0.problem_name()
+        }
+        fn explore(&mut self, plans: &[Vec<Step>]) -> Vec<Vec<usize>> {
+            self.try_explore(plans).unwrap()
+        }
+        fn try_explore(&mut self, _: &[Vec<Step>]) -> Result<Vec<Vec<usize>>, api::ApiError> {
+            Err(api::ApiError::Other(anyhow::anyhow!("server is down")))
+        }
+        fn guess(&self, out: &Guess) -> bool {
+            self.0.guess(out)
+        }
+        fn explored(&self) -> Explored {
+            self.0.explored()
+        }
+        fn set_explored(&mut self, explored: Explored) {
+            self.0.set_explored(explored)
+        }
+        fn restart(&mut self) {
+            self.0.restart()
+        }
+        fn dump_json(&self) -> serde_json::Value {
+            self.0.dump_json()
+        }
+    }
+
+    #[test]
+    fn chained_judge_passes_api_errors_through() {
+        let mut first = LocalJudge::new("random", 6, 3);
+        let old = parse_plan("012345");
+        first.explore(std::slice::from_ref(&old));
+        let mut chained = ChainedJudge::new(
+            Box::new(DownJudge(LocalJudge::new("random", 6, 3))),
+            first.explored(),
+        );
+        // Replayed plans need no server; anything else reports the failure
+        // and leaves the log as it was, so the call can simply be retried.
+        assert_eq!(
+            chained.try_explore(std::slice::from_ref(&old)).unwrap(),
+            first.explored().results
+        );
+        let err = chained
+            .try_explore(&[old.clone(), parse_plan("0")])
+            .unwrap_err();
+        assert_eq!(err.to_string(), "server is down");
+        assert_eq!(chained.replayed(), 1);
+        assert_eq!(chained.explored().plans, vec![old]);
+    }
+
+    #[test]
+    fn noisy_judge_flips_labels_at_the_given_rate() {
+        let plan = vec![parse_plan(&"012345".repeat(200))];
+        let clean = LocalJudge::new("random", 12, 2).explore(&plan);
+        let mut noisy = LocalJudge::new("random", 12, 2).with_noise(0.2, 7);
+        let observed = noisy.explore(&plan);
+        let flipped = clean[0]
+            .iter()
+            .zip(&observed[0])
+            .filter(|(a, b)| a != b)
+            .count();
+        let rate = flipped as f64 / clean[0].len() as f64;
+        assert!((0.15..0.25).contains(&rate), "{}", rate);
+        assert!(observed[0].iter().all(|&l| l < 4));
+        // Same seed, same noise; no noise, no change.
+        let again = LocalJudge::new("random", 12, 2)
+            .with_noise(0.2, 7)
+            .explore(&plan);
+        assert_eq!(observed, again);
+        let zero = LocalJudge::new("random", 12, 2)
+            .with_noise(0.0, 7)
+            .explore(&plan);
+        assert_eq!(zero, clean);
+    }
 }
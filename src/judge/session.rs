@@ -0,0 +1,621 @@
+//! # Session recordings
+//!
+//! Every [`RemoteJudge`](super::RemoteJudge) lifecycle, from `/select` to the
+//! next `restart` or the end of the process, is a session: a [`Session`]
+//! holds its explorations as an [`archive::Session`], plus its guesses.
+//!
+//! Sessions are journaled to a local file, one JSON [`Record`] per line
+//! (`$UNAGI_SESSION_DIR/{problem}/{session_id}.jsonl`, by default under the
+//! temp dir): each `/explore` and `/guess` appends a line instead of
+//! rewriting the session. A session without a guess is still open on the
+//! server, so a solver that crashed after exploring can pick it up again with
+//! [`RemoteJudge::resume`](super::RemoteJudge::resume) instead of spending
+//! the queries twice.
+//!
+//! With `UNAGI_RECORD_SESSIONS=1` the journal is also uploaded to
+//! `gs://icfpc2025-data/sessions/{problem}/{session_id}.jsonl` after each
+//! guess and when the session ends, and with `UNAGI_SESSION_SYNC=1` after
+//! every `/explore` as well.
+//!
+//! A session that ended with a correct guess knows its map, so
+//! [`LocalJudge::from_session`] can replay it locally: the same plans give
+//! the same results, which makes a remote run debuggable after the fact.
+
+use anyhow::{Context, Result};
+use rand::SeedableRng;
+use serde::{Deserialize, Serialize};
+use std::io::Write;
+use std::path::{Path, PathBuf};
+
+use super::{Explored, LocalJudge, Step, format_plan, parse_plan};
+use crate::{api, archive};
+
+/// Bucket and prefix the sessions are uploaded to.
+const BUCKET: &str = "icfpc2025-data";
+const PREFIX: &str = "sessions";
+
+/// A line of a session journal.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "lowercase")]
+pub enum Record {
+    /// The first line: the `/select`, with the time it was made (RFC 3339).
+    Start {
+        session_id: String,
+        problem_name: String,
+        num_rooms: usize,
+        started: String,
+    },
+    /// An `/explore` call; `results` are one label per room visited, with
+    /// mark echoes already stripped.
+    Explore {
+        plans: Vec<String>,
+        results: Vec<Vec<usize>>,
+    },
+    /// A `/guess` call and its verdict.
+    Guess { map: api::Map, correct: bool },
+}
+
+#[derive(Clone, Debug)]
+pub struct Session {
+    pub session_id: String,
+    /// RFC 3339 time of the `/select`.
+    pub started: String,
+    /// Problem name, room count and explorations, and the map once a guess
+    /// was correct.
+    pub archive: archive::Session,
+    /// Number of plans of each `/explore` request, in order.
+    pub requests: Vec<usize>,
+    /// Verdicts of the guesses, in order.
+    pub guesses: Vec<bool>,
+}
+
+impl Session {
+    pub fn new(problem_name: &str, num_rooms: usize) -> Self {
+        let now = chrono::Utc::now();
+        Self::start(
+            format!(
+                "{}-{:08x}",
+                now.format("%Y%m%d-%H%M%S"),
+                rand::random::<u32>()
+            ),
+            problem_name,
+            num_rooms,
+            now.to_rfc3339(),
+        )
+    }
+
+    fn start(session_id: String, problem_name: &str, num_rooms: usize, started: String) -> Self {
+        Session {
+            session_id,
+            started,
+            archive: archive::Session {
+                problem_name: Some(problem_name.to_string()),
+                num_rooms: Some(num_rooms),
+                map: None,
+                plans: vec![],
+                results: vec![],
+            },
+            requests: vec![],
+            guesses: vec![],
+        }
+    }
+
+    pub fn problem_name(&self) -> &str {
+        self.archive.problem_name.as_deref().unwrap_or_default()
+    }
+
+    pub fn num_rooms(&self) -> usize {
+        self.archive.num_rooms.unwrap_or_default()
+    }
+
+    /// `gs://` URL the session is uploaded to.
+    pub fn url(&self) -> String {
+        format!("gs://{}/{}", BUCKET, self.object())
+    }
+
+    fn object(&self) -> String {
+        format!(
+            "{}/{}/{}.jsonl",
+            PREFIX,
+            self.problem_name(),
+            self.session_id
+        )
+    }
+
+    /// The journal line that starts this session.
+    pub fn start_record(&self) -> Record {
+        Record::Start {
+            session_id: self.session_id.clone(),
+            problem_name: self.problem_name().to_string(),
+            num_rooms: self.num_rooms(),
+            started: self.started.clone(),
+        }
+    }
+
+    /// Adds an exploration or a guess to the session.
+    pub fn apply(&mut self, record: &Record) {
+        match record {
+            Record::Start { .. } => {}
+            Record::Explore { plans, results } => {
+                self.archive
+                    .plans
+                    .extend(plans.iter().map(|p| parse_plan(p)));
+                self.archive.results.extend(results.iter().cloned());
+                self.requests.push(plans.len());
+            }
+            Record::Guess { map, correct } => {
+                if *correct && self.archive.map.is_none() {
+                    self.archive.map = Some(map.clone());
+                }
+                self.guesses.push(*correct);
+            }
+        }
+    }
+
+    /// All explorations of the session, in order.
+    pub fn explored(&self) -> Explored {
+        Explored {
+            plans: self.archive.plans.clone(),
+            results: self.archive.results.clone(),
+        }
+    }
+
+    /// Whether the session has not guessed yet, so that the server still
+    /// answers `/explore` on its map.
+    pub fn is_open(&self) -> bool {
+        self.guesses.is_empty()
+    }
+
+    /// The summed `explore_cost` of the session's explorations.
+    pub fn cost(&self) -> usize {
+        self.requests.iter().map(|&n| n + 1).sum()
+    }
+
+    fn journal_path(&self, dir: &Path) -> PathBuf {
+        dir.join(self.problem_name())
+            .join(format!("{}.jsonl", self.session_id))
+    }
+
+    /// Appends `record` to the session's journal under `dir`; a
+    /// [`Record::Start`] creates the journal.
+    pub fn append_journal(&self, dir: &Path, record: &Record) -> Result<PathBuf> {
+        let path = self.journal_path(dir);
+        let parent = path.parent().unwrap();
+        std::fs::create_dir_all(parent)
+            .with_context(|| format!("failed to create {}", parent.display()))?;
+        let mut line = serde_json::to_vec(record)?;
+        line.push(b'\n');
+        std::fs::OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(&path)
+            .and_then(|mut f| f.write_all(&line))
+            .with_context(|| format!("failed to write {}", path.display()))?;
+        Ok(path)
+    }
+
+    /// Writes the whole journal of an open session under `dir`, e.g. for one
+    /// loaded from GCS and resumed on another machine. Guesses are not
+    /// written: a session that guessed cannot be resumed.
+    pub fn save_journal(&self, dir: &Path) -> Result<PathBuf> {
+        anyhow::ensure!(self.is_open(), "session {} has guessed", self.session_id);
+        let mut data = serde_json::to_vec(&self.start_record())?;
+        data.push(b'\n');
+        let mut first = 0;
+        for &n in &self.requests {
+            let range = first..first + n;
+            first += n;
+            serde_json::to_writer(
+                &mut data,
+                &Record::Explore {
+                    plans: self.archive.plans[range.clone()]
+                        .iter()
+                        .map(|p| format_plan(p))
+                        .collect(),
+                    results: self.archive.results[range].to_vec(),
+                },
+            )?;
+            data.push(b'\n');
+        }
+        let path = self.journal_path(dir);
+        let parent = path.parent().unwrap();
+        std::fs::create_dir_all(parent)
+            .with_context(|| format!("failed to create {}", parent.display()))?;
+        let tmp = path.with_extension("jsonl.tmp");
+        std::fs::write(&tmp, &data)
+            .with_context(|| format!("failed to write {}", tmp.display()))?;
+        std::fs::rename(&tmp, &path)
+            .with_context(|| format!("failed to write {}", path.display()))?;
+        Ok(path)
+    }
+
+    /// The most recently started session of `problem_name` journaled under
+    /// `dir`, if it is still open. An older open journal is never returned:
+    /// once a newer session has been selected, the server has left its map.
+    pub fn latest_open(dir: &Path, problem_name: &str) -> Result<Option<Self>> {
+        let dir = dir.join(problem_name);
+        let entries = match std::fs::read_dir(&dir) {
+            Ok(entries) => entries,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(None),
+            Err(e) => return Err(e).with_context(|| format!("failed to read {}", dir.display())),
+        };
+        let mut latest: Option<Self> = None;
+        for entry in entries {
+            let path = entry?.path();
+            if path.extension().is_none_or(|e| e != "jsonl") {
+                continue;
+            }
+            let session = match Self::load(&path.to_string_lossy()) {
+                Ok(session) => session,
+                Err(e) => {
+                    eprintln!("skipping session journal {}: {:#}", path.display(), e);
+                    continue;
+                }
+            };
+            if latest.as_ref().is_none_or(|l| l.started < session.started) {
+                latest = Some(session);
+            }
+        }
+        Ok(latest.filter(Self::is_open))
+    }
+
+    /// The map of the first correct guess, if any.
+    pub fn solution(&self) -> Option<&api::Map> {
+        self.archive.map.as_ref()
+    }
+
+    /// Reads a session journal from a `gs://` URL or a local file. A last
+    /// line cut short by a crash is skipped.
+    pub fn load(url: &str) -> Result<Self> {
+        let bytes = if url.starts_with("gs://") {
+            download(url)?
+        } else {
+            std::fs::read(url).with_context(|| format!("failed to read {}", url))?
+        };
+        Self::parse(&bytes).with_context(|| format!("invalid session: {}", url))
+    }
+
+    fn parse(bytes: &[u8]) -> Result<Self> {
+        let text = std::str::from_utf8(bytes)?;
+        let mut lines = text.lines().filter(|l| !l.trim().is_empty()).peekable();
+        let first = lines.next().context("empty journal")?;
+        let Record::Start {
+            session_id,
+            problem_name,
+            num_rooms,
+            started,
+        } = serde_json::from_str(first)?
+        else {
+            anyhow::bail!("the journal does not start with a start record");
+        };
+        let mut session = Self::start(session_id, &problem_name, num_rooms, started);
+        while let Some(line) = lines.next() {
+            match serde_json::from_str(line) {
+                Ok(record) => session.apply(&record),
+                Err(e) if lines.peek().is_none() && !text.ends_with('\n') => {
+                    eprintln!("skipping truncated journal line: {}", e);
+                }
+                Err(e) => return Err(e.into()),
+            }
+        }
+        Ok(session)
+    }
+}
+
+#[cfg(feature = "tokio")]
+fn download(url: &str) -> Result<Vec<u8>> {
+    let (bucket, object) = crate::gcp::gcs::parse_gs_url(url)?;
+    tokio::runtime::Runtime::new()?
+        .block_on(crate::gcp::gcs::download_object(&bucket, &object))
+        .with_context(|| format!("failed to download {}", url))
+}
+
+#[cfg(not(feature = "tokio"))]
+fn download(url: &str) -> Result<Vec<u8>> {
+    anyhow::bail!("downloading {} requires the tokio feature", url)
+}
+
+/// Directory of the local session journals: `UNAGI_SESSION_DIR`, or
+/// `unagi-sessions` under the temp dir.
+pub fn journal_dir() -> PathBuf {
+    std::env::var_os("UNAGI_SESSION_DIR")
+        .map(PathBuf::from)
+        .unwrap_or_else(|| std::env::temp_dir().join("unagi-sessions"))
+}
+
+/// Records the session of a remote judge: every event is appended to the
+/// local journal, which is uploaded (if enabled) whenever a guess is made and
+/// when the recorder is dropped, if anything happened since the last upload.
+pub struct Recorder {
+    session: Session,
+    /// Whether there are events not uploaded yet.
+    dirty: bool,
+    /// Upload the journal (`UNAGI_RECORD_SESSIONS=1`).
+    enabled: bool,
+    /// Upload after every exploration too (`UNAGI_SESSION_SYNC=1`).
+    sync: bool,
+    journal_dir: PathBuf,
+}
+
+impl Recorder {
+    pub fn new(problem_name: &str, num_rooms: usize) -> Self {
+        let recorder = Self::with_session(Session::new(problem_name, num_rooms));
+        recorder.journal(&recorder.session.start_record());
+        recorder
+    }
+
+    /// Continues recording `session`, e.g. one loaded from its journal. A
+    /// session loaded from elsewhere gets a local journal first.
+    pub fn resume(session: Session) -> Self {
+        let recorder = Self::with_session(session);
+        if !recorder
+            .session
+            .journal_path(&recorder.journal_dir)
+            .exists()
+            && let Err(e) = recorder.session.save_journal(&recorder.journal_dir)
+        {
+            eprintln!(
+                "failed to journal session {}: {:#}",
+                recorder.session.session_id, e
+            );
+        }
+        recorder
+    }
+
+    fn with_session(session: Session) -> Self {
+        let sync = std::env::var("UNAGI_SESSION_SYNC").is_ok_and(|v| v == "1");
+        Recorder {
+            session,
+            dirty: false,
+            enabled: sync || std::env::var("UNAGI_RECORD_SESSIONS").is_ok_and(|v| v == "1"),
+            sync,
+            journal_dir: journal_dir(),
+        }
+    }
+
+    pub fn session(&self) -> &Session {
+        &self.session
+    }
+
+    pub fn explore(&mut self, plans: &[Vec<Step>], results: &[Vec<usize>]) {
+        self.record(Record::Explore {
+            plans: plans.iter().map(|p| format_plan(p)).collect(),
+            results: results.to_vec(),
+        });
+        if self.sync {
+            self.flush();
+        }
+    }
+
+    pub fn guess(&mut self, map: &api::Map, correct: bool) {
+        self.record(Record::Guess {
+            map: map.clone(),
+            correct,
+        });
+        self.flush();
+    }
+
+    fn record(&mut self, record: Record) {
+        self.session.apply(&record);
+        self.journal(&record);
+        self.dirty = true;
+    }
+
+    /// Like uploads, a failed journal write is only logged.
+    fn journal(&self, record: &Record) {
+        if let Err(e) = self.session.append_journal(&self.journal_dir, record) {
+            eprintln!(
+                "failed to journal session {}: {:#}",
+                self.session.session_id, e
+            );
+        }
+    }
+
+    /// Uploads the journal if it has new events. Failures are only logged:
+    /// a lost recording must not cost a run.
+    pub fn flush(&mut self) {
+        if !self.enabled || !self.dirty {
+            return;
+        }
+        let url = self.session.url();
+        let path = self.session.journal_path(&self.journal_dir);
+        match std::fs::read(&path)
+            .with_context(|| format!("failed to read {}", path.display()))
+            .and_then(|data| upload(&self.session.object(), data))
+        {
+            Ok(()) => {
+                self.dirty = false;
+                eprintln!("session recorded: {}", url);
+            }
+            Err(e) => eprintln!("failed to record session {}: {:#}", url, e),
+        }
+    }
+}
+
+impl Drop for Recorder {
+    fn drop(&mut self) {
+        self.flush();
+    }
+}
+
+/// Uploads on a thread of its own, so that a recorder dropped inside an
+/// async context does not start a runtime within a runtime.
+#[cfg(feature = "tokio")]
+fn upload(object: &str, data: Vec<u8>) -> Result<()> {
+    let object = object.to_string();
+    std::thread::spawn(move || -> Result<()> {
+        tokio::runtime::Runtime::new()?.block_on(crate::gcp::gcs::upload_object(
+            BUCKET,
+            &object,
+            &data,
+            "application/x-ndjson",
+        ))?;
+        Ok(())
+    })
+    .join()
+    .map_err(|_| anyhow::anyhow!("upload thread panicked"))?
+}
+
+#[cfg(not(feature = "tokio"))]
+fn upload(_object: &str, _data: Vec<u8>) -> Result<()> {
+    anyhow::bail!("uploading requires the tokio feature")
+}
+
+impl LocalJudge {
+    /// A judge on the map a recorded session solved, given a `gs://` URL or a
+    /// local file, so that the session's plans give the results it recorded.
+    ///
+    /// Unlike [`LocalJudge::new_json`], doors are not shuffled. Fails if the
+    /// session has no correct guess, since nothing else pins down its map;
+    /// [`Session::explored`] still gives its explorations.
+    pub fn from_session(url: &str) -> Result<Self> {
+        let session = Session::load(url)?;
+        let map = session
+            .solution()
+            .with_context(|| format!("session {} has no correct guess", session.session_id))?;
+        let n = map.rooms.len();
+        let mut graph: Vec<[usize; super::MAX_DOORS]> =
+            (0..n).map(|u| [u; super::MAX_DOORS]).collect();
+        for c in &map.connections {
+            anyhow::ensure!(
+                [c.from.room, c.to.room].iter().all(|&r| r < n)
+                    && [c.from.door, c.to.door]
+                        .iter()
+                        .all(|&d| d < super::MAX_DOORS),
+                "connection out of range in session {}",
+                session.session_id
+            );
+            graph[c.from.room][c.from.door] = c.to.room;
+            graph[c.to.room][c.to.door] = c.from.room;
+        }
+        Ok(LocalJudge {
+            problem_name: session.problem_name().to_string(),
+            problem_args: String::new(),
+            rooms: map.rooms.clone(),
+            starting_room: map.starting_room,
+            graph,
+            cost: 0,
+            guesses_made: Default::default(),
+            explored_log: Explored {
+                plans: vec![],
+                results: vec![],
+            },
+            noise: None,
+            start_rng: rand_chacha::ChaCha20Rng::seed_from_u64(0),
+            rules: super::Rules::global(),
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::judge::Judge;
+
+    #[test]
+    fn sessions_replay_locally() {
+        // Rooms 0-1-2 in a triangle through doors 0, 1 and 2; other doors loop.
+        let map: api::Map = serde_json::from_value(serde_json::json!({
+            "rooms": [0, 1, 2],
+            "startingRoom": 0,
+            "connections": [
+                { "from": { "room": 0, "door": 0 }, "to": { "room": 1, "door": 0 } },
+                { "from": { "room": 1, "door": 1 }, "to": { "room": 2, "door": 1 } },
+                { "from": { "room": 2, "door": 2 }, "to": { "room": 0, "door": 2 } },
+            ],
+        }))
+        .unwrap();
+        let plans = vec![parse_plan("0125"), parse_plan("[3]00")];
+        let results = vec![vec![0, 1, 2, 0, 0], vec![0, 1, 3]];
+
+        let explore = Record::Explore {
+            plans: plans.iter().map(|p| format_plan(p)).collect(),
+            results: results.clone(),
+        };
+        let record = |session: &mut Session, dir: &Path, record: &Record| {
+            session.apply(record);
+            session.append_journal(dir, record).unwrap();
+        };
+
+        let mut session = Session::new("probatio", 3);
+        assert_eq!(
+            session.url(),
+            format!(
+                "gs://icfpc2025-data/sessions/probatio/{}.jsonl",
+                session.session_id
+            )
+        );
+        let dir = std::env::temp_dir().join(format!("journal-{}", session.session_id));
+        assert!(Session::latest_open(&dir, "probatio").unwrap().is_none());
+        for r in [
+            session.start_record(),
+            explore.clone(),
+            Record::Guess {
+                map: map.clone(),
+                correct: true,
+            },
+        ] {
+            record(&mut session, &dir, &r);
+        }
+        let path = session.journal_path(&dir);
+        let loaded = Session::load(path.to_str().unwrap()).unwrap();
+        assert_eq!(loaded.explored().plans, plans);
+        assert_eq!(loaded.explored().results, results);
+        assert!(!loaded.is_open() && loaded.solution().is_some());
+
+        let mut replay = LocalJudge::from_session(path.to_str().unwrap()).unwrap();
+        assert_eq!(replay.explore(&plans), results);
+
+        // Journals: the newest session is the one to resume, if it has not guessed.
+        assert!(Session::latest_open(&dir, "probatio").unwrap().is_none());
+        let mut open = Session::new("probatio", 3);
+        open.session_id.push_str("-open");
+        for r in [open.start_record(), explore.clone()] {
+            record(&mut open, &dir, &r);
+        }
+        // A line cut short by a crash is skipped.
+        std::fs::OpenOptions::new()
+            .append(true)
+            .open(open.journal_path(&dir))
+            .unwrap()
+            .write_all(br#"{"type":"explore","pla"#)
+            .unwrap();
+        let resumed = Session::latest_open(&dir, "probatio").unwrap().unwrap();
+        assert_eq!(resumed.session_id, open.session_id);
+        assert!(resumed.is_open());
+        assert_eq!(resumed.explored().plans, plans);
+        assert_eq!(resumed.cost(), 3);
+        // A resumed session rewrites its journal in full elsewhere.
+        let copy = dir.join("copy");
+        let copied = resumed.save_journal(&copy).unwrap();
+        let copied = Session::load(copied.to_str().unwrap()).unwrap();
+        assert_eq!(
+            (copied.explored().results, copied.requests),
+            (results, vec![2])
+        );
+
+        // A newer session that guessed hides the older open one.
+        let mut newer = Session::start(
+            format!("{}-newer", session.session_id),
+            "probatio",
+            3,
+            (chrono::Utc::now() + chrono::Duration::seconds(1)).to_rfc3339(),
+        );
+        for r in [
+            newer.start_record(),
+            explore,
+            Record::Guess {
+                map,
+                correct: false,
+            },
+        ] {
+            record(&mut newer, &dir, &r);
+        }
+        assert!(Session::latest_open(&dir, "probatio").unwrap().is_none());
+
+        // Without a correct guess the map is unknown.
+        let path = newer.journal_path(&dir);
+        assert!(LocalJudge::from_session(path.to_str().unwrap()).is_err());
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+}
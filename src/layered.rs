@@ -5,15 +5,20 @@
 use crate::{SetMinMax as _, api, judge::Guess};
 use anyhow::Result;
 
+/// A map reduced to its quotient by indistinguishable rooms.
 #[derive(serde::Serialize, Debug)]
 pub struct JsonOut {
-    map: api::Map,
-    classes: Vec<Vec<usize>>,
-    permutations: Vec<Vec<Perm3>>,
+    /// The quotient map, one room per class.
+    pub(crate) map: api::Map,
+    /// The original rooms of each class; a room's index in its class is its layer.
+    pub(crate) classes: Vec<Vec<usize>>,
+    /// `permutations[c][d]` maps the layer of a room of class `c` to the layer
+    /// of the room behind its door `d`.
+    pub(crate) permutations: Vec<Vec<Perm3>>,
 }
 
 #[derive(Clone, Copy, PartialEq, Eq, Debug, serde::Serialize)]
-enum Perm3 {
+pub(crate) enum Perm3 {
     // identity
     I,
     // swap
@@ -42,6 +47,21 @@ impl TryFrom<&Vec<usize>> for Perm3 {
     }
 }
 
+impl Perm3 {
+    /// The image of layer `j`.
+    pub(crate) fn apply(self, j: usize) -> usize {
+        let image: [usize; 3] = match self {
+            Perm3::I => [0, 1, 2],
+            Perm3::X => [1, 0, 2],
+            Perm3::Y => [2, 1, 0],
+            Perm3::Z => [0, 2, 1],
+            Perm3::P => [1, 2, 0],
+            Perm3::Q => [2, 0, 1],
+        };
+        image[j]
+    }
+}
+
 impl std::ops::Neg for Perm3 {
     type Output = Self;
     fn neg(self) -> Self::Output {
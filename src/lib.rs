@@ -63,6 +63,85 @@ where
     }
 }
 
+/// Keeps the `k` items with the largest keys among those pushed so far.
+///
+/// Among equal keys the earlier item ranks higher, so callers that shuffle their
+/// candidates get a random tie-break instead of one biased by the value type's
+/// ordering.
+#[derive(Clone, Debug)]
+pub struct TopK<T, K = f64> {
+    k: usize,
+    /// Sorted by key, largest first.
+    items: Vec<(K, T)>,
+}
+
+impl<T, K: PartialOrd> TopK<T, K> {
+    pub fn new(k: usize) -> Self {
+        TopK {
+            k,
+            items: Vec::with_capacity(k.min(1024)),
+        }
+    }
+
+    /// Offers an item. Returns `true` if it is kept.
+    pub fn push(&mut self, key: K, value: T) -> bool {
+        if self.items.len() == self.k && !self.items.last().is_some_and(|(last, _)| *last < key) {
+            return false;
+        }
+        let pos = self
+            .items
+            .partition_point(|(k, _)| k.partial_cmp(&key) != Some(std::cmp::Ordering::Less));
+        self.items.insert(pos, (key, value));
+        self.items.truncate(self.k);
+        true
+    }
+
+    /// The item with the largest key.
+    pub fn best(&self) -> Option<&(K, T)> {
+        self.items.first()
+    }
+
+    pub fn len(&self) -> usize {
+        self.items.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.items.is_empty()
+    }
+
+    /// Kept items, largest key first.
+    pub fn iter(&self) -> impl Iterator<Item = &(K, T)> {
+        self.items.iter()
+    }
+
+    /// Kept items, largest key first.
+    pub fn into_sorted_vec(self) -> Vec<(K, T)> {
+        self.items
+    }
+}
+
+/// Index of the first largest key, or `None` for an empty sequence.
+pub fn argmax<K: PartialOrd>(keys: impl IntoIterator<Item = K>) -> Option<usize> {
+    let mut best: Option<(usize, K)> = None;
+    for (i, k) in keys.into_iter().enumerate() {
+        if best.as_ref().is_none_or(|(_, b)| *b < k) {
+            best = Some((i, k));
+        }
+    }
+    best.map(|(i, _)| i)
+}
+
+/// Index of the first smallest key, or `None` for an empty sequence.
+pub fn argmin<K: PartialOrd>(keys: impl IntoIterator<Item = K>) -> Option<usize> {
+    let mut best: Option<(usize, K)> = None;
+    for (i, k) in keys.into_iter().enumerate() {
+        if best.as_ref().is_none_or(|(_, b)| k < *b) {
+            best = Some((i, k));
+        }
+    }
+    best.map(|(i, _)| i)
+}
+
 /// A macro for convenient initialization of vectors, including nested vectors for multi-dimensional arrays.
 ///
 /// # Examples
@@ -90,6 +169,8 @@ macro_rules! mat {
 /// This function is available when the `reqwest` feature is enabled.
 #[cfg(feature = "reqwest")]
 pub async fn get_bearer_async() -> anyhow::Result<String> {
+    use client::SendTracked;
+
     let unagi_password = std::env::var("UNAGI_PASSWORD").context("UNAGI_PASSWORD not set")?;
     let client = &*client::CLIENT;
     let res = client
@@ -97,7 +178,7 @@ pub async fn get_bearer_async() -> anyhow::Result<String> {
             "https://storage.googleapis.com/icfpc2025-data/{}/bearer.txt",
             unagi_password,
         ))
-        .send()
+        .send_tracked()
         .await
         .context("Failed to get bearer")?;
     res.text()
@@ -116,28 +197,105 @@ pub fn get_bearer() -> anyhow::Result<String> {
 }
 
 #[cfg(test)]
-mod tests {}
+mod tests {
+    use super::*;
+
+    #[test]
+    fn top_k_keeps_largest_and_earliest_ties() {
+        let mut top = TopK::new(3);
+        for (i, key) in [2.0, 5.0, 1.0, 5.0, 3.0, 0.5].into_iter().enumerate() {
+            top.push(key, i);
+        }
+        assert_eq!(top.into_sorted_vec(), vec![(5.0, 1), (5.0, 3), (3.0, 4)]);
+
+        let mut best = TopK::new(1);
+        assert!(best.push((1, 2), 'a'));
+        assert!(!best.push((1, 2), 'b'));
+        assert!(best.push((1, 3), 'c'));
+        assert_eq!(best.best(), Some(&((1, 3), 'c')));
+        assert!(!TopK::new(0).push(1, ()));
+    }
+
+    #[test]
+    fn argmin_argmax_pick_first() {
+        assert_eq!(argmax([1, 3, 2, 3]), Some(1));
+        assert_eq!(argmin([4, 1, 2, 1]), Some(1));
+        assert_eq!(argmax(Vec::<f64>::new()), None);
+        assert_eq!(argmin([2.0, f64::NAN, 1.0]), Some(2));
+    }
+}
 
 /// Client for the official contest web service (Aedificium).
 /// Enabled with the `reqwest` feature.
 #[cfg(feature = "reqwest")]
 pub mod api;
 
+/// `post select|explore|guess` command implementation shared by CLI binaries.
+#[cfg(feature = "reqwest")]
+pub mod post;
+
+/// Compact gzip-compressed format for archived sessions and checkpoints.
+#[cfg(feature = "reqwest")]
+pub mod archive;
+
+/// Root-cause classification of wrong guesses.
+#[cfg(feature = "reqwest")]
+pub mod wa_analysis;
+
 /// Definitions and data for the contest problems.
 pub mod problems;
 
 /// Abstraction for the problem environment (the "Aedificium"), with local and remote implementations.
 pub mod judge;
 
+/// Incremental room identification that can end an exploration early.
+pub mod online;
+
 /// Utilities for generating SVG visualizations of maps.
 pub mod svg;
 
 /// Tools for generating problem maps.
 pub mod mapgen {
+    /// Loading hand-written maps from edge-list and DOT files.
+    pub mod graph_file;
+    /// Ranking generated maps by how hard they are to solve.
+    pub mod miner;
     /// A module for generating random maps.
     pub mod random;
+    /// Grid, tree, bottleneck and high-girth map families for `LocalJudge`.
+    pub mod structured;
 }
 
 pub mod routes;
 
+/// Clause-level simplification run before the external SAT portfolio.
+pub mod cnf_preprocess;
+
+/// CSV and Parquet writers for analysis exports.
+pub mod columnar;
+
+/// Phase timings behind the `profiling` feature.
+pub mod profiling;
+
+/// Structured `<UNAGI>:` progress events for the executor.
+pub mod progress;
+
 pub mod solve_no_marks;
+
+/// Simulated-annealing room assignment, a SAT-free fallback for no-marks explorations.
+pub mod solve_anneal;
+
+/// SAT solver for explorations that rewrite room labels with charcoal marks.
+pub mod solve_marks;
+
+/// SAT solver for maps made of D copies of a smaller map.
+pub mod solve_layered;
+
+/// Whether the explorations pin the map down before a guess is spent.
+pub mod verify;
+
+/// Canonical forms and isomorphism of maps, for comparing guesses.
+pub mod graph_iso;
+
+/// Name-to-solver registry shared by `solve` and `benchmark`.
+pub mod solvers;
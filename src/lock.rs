@@ -12,6 +12,23 @@
 //!
 //! This implementation is used by the `api` module to manage the lifecycle of
 //! a problem-solving session (`select` -> `explore`* -> `guess`).
+//!
+//! `lock()` is grab-or-fail. To wait for the lock instead, [`wait_for_lock`]
+//! registers in the `lock_waiters` table and only tries to grab the lock once
+//! every live waiter registered before it is gone, so waiters get the lock in
+//! FIFO order. A waiter is live while it refreshes its heartbeat; one that
+//! stops for `WAITER_TIMEOUT_SECS` (e.g. a killed process) no longer blocks
+//! the queue and is purged by the next `enqueue`. Plain `lock()` callers do
+//! not queue and may still take the lock first.
+//!
+//! ```sql
+//! CREATE TABLE lock_waiters (
+//!   waiter_id BIGINT NOT NULL AUTO_INCREMENT PRIMARY KEY,
+//!   waiter_user VARCHAR(255) NOT NULL,
+//!   waiter_created TIMESTAMP NOT NULL DEFAULT CURRENT_TIMESTAMP,
+//!   waiter_heartbeat TIMESTAMP NOT NULL DEFAULT CURRENT_TIMESTAMP
+//! );
+//! ```
 
 use anyhow::Result;
 use cached::proc_macro::once;
@@ -30,6 +47,12 @@ fn current_username() -> String {
         .unwrap_or_else(|_| "unknown".to_string())
 }
 
+/// The `lock_user` recorded for this process: the user and the program name.
+fn current_lock_user() -> String {
+    let arg0 = std::env::args().next().unwrap_or_default();
+    format!("{}:{}", current_username(), arg0)
+}
+
 /// Generates a random 40-bit token represented as a 10-character hex string.
 fn gen_lock_token() -> String {
     let buf: [u8; 5] = rand::random();
@@ -50,9 +73,7 @@ fn gen_lock_token() -> String {
 /// * `Ok(None)` if the lock is currently held by another process.
 /// * `Err` if a database error occurs.
 pub fn lock(ttl: Duration) -> Result<Option<String>> {
-    let user = current_username();
-    let arg0 = std::env::args().next().unwrap_or_default();
-    let lock_user = format!("{}:{}", user, arg0);
+    let lock_user = current_lock_user();
     let token = gen_lock_token();
     let ttl_secs = (ttl.as_secs().min(i64::MAX as u64)) as i64;
 
@@ -188,3 +209,160 @@ pub fn unlock(lock_token: &str, force: bool) -> Result<()> {
     );
     Ok(())
 }
+
+/// Seconds without a heartbeat after which a waiter is considered gone.
+const WAITER_TIMEOUT_SECS: i64 = 30;
+
+/// Registers a waiter at the end of the queue and returns its id. Waiters
+/// that timed out are purged first.
+pub fn enqueue() -> Result<u64> {
+    sql::exec(
+        "DELETE FROM lock_waiters WHERE waiter_heartbeat < DATE_SUB(CURRENT_TIMESTAMP, INTERVAL :timeout SECOND)",
+        params! { "timeout" => WAITER_TIMEOUT_SECS },
+    )?;
+    sql::insert(
+        "INSERT INTO lock_waiters (waiter_user) VALUES (:user)",
+        params! { "user" => current_lock_user() },
+    )
+}
+
+/// Refreshes the heartbeat of a waiter. Returns `false` if its row is gone,
+/// i.e. it timed out and was purged.
+pub fn heartbeat(waiter_id: u64) -> Result<bool> {
+    sql::exec(
+        "UPDATE lock_waiters SET waiter_heartbeat = CURRENT_TIMESTAMP WHERE waiter_id = :id",
+        params! { "id" => waiter_id },
+    )?;
+    // The update reports no change within the same second, so look the row up.
+    let row = sql::cell::<u64>(
+        "SELECT waiter_id FROM lock_waiters WHERE waiter_id = :id",
+        params! { "id" => waiter_id },
+    )?;
+    Ok(row.is_some())
+}
+
+/// Removes a waiter from the queue.
+pub fn dequeue(waiter_id: u64) -> Result<()> {
+    sql::exec(
+        "DELETE FROM lock_waiters WHERE waiter_id = :id",
+        params! { "id" => waiter_id },
+    )?;
+    Ok(())
+}
+
+/// Number of live waiters ahead of `waiter_id`, or of all live waiters for
+/// `None`.
+pub fn waiters_ahead(waiter_id: Option<u64>) -> Result<usize> {
+    let count = sql::cell::<u64>(
+        r#"
+        SELECT COUNT(*) FROM lock_waiters
+        WHERE waiter_id < :id
+          AND waiter_heartbeat > DATE_SUB(CURRENT_TIMESTAMP, INTERVAL :timeout SECOND)
+        "#,
+        params! { "id" => waiter_id.unwrap_or(u64::MAX), "timeout" => WAITER_TIMEOUT_SECS },
+    )?;
+    Ok(count.unwrap_or(0) as usize)
+}
+
+/// The `lock_user` of the active lock, if any.
+pub fn holder() -> Result<Option<String>> {
+    sql::cell::<String>(
+        "SELECT lock_user FROM locks WHERE lock_id = 1 AND lock_expired > CURRENT_TIMESTAMP",
+        (),
+    )
+}
+
+/// Like [`lock`], but only succeeds for the head of the queue: no live waiter
+/// registered before `waiter_id` may be left. The check and the grab are one
+/// statement, so two waiters cannot both take the lock.
+pub fn lock_queued(waiter_id: u64, ttl: Duration) -> Result<Option<String>> {
+    let token = gen_lock_token();
+    let ttl_secs = (ttl.as_secs().min(i64::MAX as u64)) as i64;
+    let affected = sql::exec(
+        r#"
+        UPDATE locks
+        SET
+            lock_user = :lock_user,
+            lock_token = :lock_token,
+            lock_expired = DATE_ADD(CURRENT_TIMESTAMP, INTERVAL :ttl SECOND)
+        WHERE lock_id = 1 AND lock_expired < CURRENT_TIMESTAMP
+          AND NOT EXISTS (
+            SELECT 1 FROM lock_waiters
+            WHERE waiter_id < :waiter_id
+              AND waiter_heartbeat > DATE_SUB(CURRENT_TIMESTAMP, INTERVAL :timeout SECOND)
+          )
+        "#,
+        params! {
+            "lock_user" => current_lock_user(),
+            "lock_token" => &token,
+            "ttl" => ttl_secs,
+            "waiter_id" => waiter_id,
+            "timeout" => WAITER_TIMEOUT_SECS,
+        },
+    )?;
+    if affected > 0 {
+        eprintln!(
+            "[lock] acquired from queue: token={} ttl_secs={}",
+            token, ttl_secs
+        );
+        Ok(Some(token))
+    } else {
+        Ok(None)
+    }
+}
+
+/// Where a queued waiter stands, as reported to the callback of [`wait_for_lock`].
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct WaitStatus {
+    /// Live waiters ahead in the queue.
+    pub ahead: usize,
+    /// `lock_user` of the current holder, if the lock is held.
+    pub holder: Option<String>,
+}
+
+impl std::fmt::Display for WaitStatus {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{} waiting ahead", self.ahead)?;
+        match &self.holder {
+            Some(user) => write!(f, ", held by {}", user),
+            None => write!(f, ", lock free"),
+        }
+    }
+}
+
+/// Waits in the queue until the lock is acquired and returns its token.
+///
+/// Polls every `poll`, which has to stay well below `WAITER_TIMEOUT_SECS`,
+/// and calls `notify` whenever the [`WaitStatus`] changes. The waiter leaves
+/// the queue when this returns, also on errors.
+pub fn wait_for_lock(
+    ttl: Duration,
+    poll: Duration,
+    mut notify: impl FnMut(&WaitStatus),
+) -> Result<String> {
+    let waiter_id = enqueue()?;
+    let result = (|| {
+        let mut last = None;
+        loop {
+            if !heartbeat(waiter_id)? {
+                anyhow::bail!("lock waiter {} timed out and left the queue", waiter_id);
+            }
+            if let Some(token) = lock_queued(waiter_id, ttl)? {
+                return Ok(token);
+            }
+            let status = WaitStatus {
+                ahead: waiters_ahead(Some(waiter_id))?,
+                holder: holder()?,
+            };
+            if last.as_ref() != Some(&status) {
+                notify(&status);
+                last = Some(status);
+            }
+            std::thread::sleep(poll);
+        }
+    })();
+    if let Err(e) = dequeue(waiter_id) {
+        eprintln!("[lock] failed to leave the queue: {}", e);
+    }
+    result
+}
@@ -85,7 +85,14 @@ pub fn start_lock_manager_blocking() -> Result<()> {
             }
         }
     };
+    run_lock_manager(token);
+    Ok(())
+}
 
+/// Starts renewing the lock `token` in the background, with the Ctrl+C,
+/// panic and normal-exit unlock handling.
+#[cfg(feature = "reqwest")]
+fn run_lock_manager(token: String) {
     let stop = Arc::new(AtomicBool::new(false));
     // Ctrl+C handler once; best-effort unlock
     if !CTRL_C_INSTALLED.swap(true, Ordering::SeqCst) {
@@ -139,7 +146,42 @@ pub fn start_lock_manager_blocking() -> Result<()> {
     // Install panic hook and thread-local guard for normal exit.
     install_panic_hook_once();
     NORMAL_EXIT_GUARD.with(|_| {});
-    Ok(())
+}
+
+/// The global lock, held by the lock manager until the guard is dropped (or
+/// a `/guess` releases it first).
+#[cfg(feature = "reqwest")]
+pub struct LockGuard {
+    _private: (),
+}
+
+#[cfg(feature = "reqwest")]
+impl LockGuard {
+    /// Waits for the lock in the FIFO queue of [`crate::lock::wait_for_lock`]
+    /// instead of retrying, printing each change of the queue position, and
+    /// keeps it renewed. A later `api::select` reuses the lock. Returns at
+    /// once if this process already holds it.
+    pub fn acquire_queued() -> Result<LockGuard> {
+        if LOCK_MANAGER
+            .lock()
+            .expect("LOCK_MANAGER mutex was poisoned")
+            .is_none()
+        {
+            let token = crate::lock::wait_for_lock(LOCK_TTL, LOCK_RENEW_INTERVAL, |status| {
+                eprintln!("[lock] queued: {}", status)
+            })?;
+            eprintln!("Lock acquired.");
+            run_lock_manager(token);
+        }
+        Ok(LockGuard { _private: () })
+    }
+}
+
+#[cfg(feature = "reqwest")]
+impl Drop for LockGuard {
+    fn drop(&mut self) {
+        stop_lock_manager_blocking();
+    }
 }
 
 /// Stops the lock manager and unlocks.
@@ -0,0 +1,356 @@
+//! # Maps from Graph Files
+//!
+//! Reads hand-written maps in two formats so adversarial topologies can be
+//! tried on a `LocalJudge` (`LocalJudge::from_graph_file`) without writing the
+//! contest JSON by hand.
+//!
+//! DIMACS-style edge list (room ids are 1-based as in DIMACS):
+//!
+//! ```text
+//! c a triangle with a double edge
+//! p edge 3 4
+//! n 2 3        label of room 2 (default: index % 4)
+//! s 1          starting room (default: 1)
+//! e 1 2
+//! e 2 3 5 0    explicit doors: door 5 of room 2 to door 0 of room 3
+//! e 3 1
+//! e 1 2
+//! ```
+//!
+//! Graphviz DOT, with arbitrary node names numbered in order of appearance:
+//!
+//! ```text
+//! graph G {
+//!   a [label=1, start=true];
+//!   a -- b [taillabel=0, headlabel=3];
+//!   b -- c -- a;
+//! }
+//! ```
+//!
+//! Edges without explicit doors take the lowest free door of each room. Doors
+//! left unconnected lead back to themselves, which the contest rules allow.
+
+use anyhow::{Context, Result, bail, ensure};
+use std::collections::HashMap;
+use std::path::Path;
+
+use crate::api;
+
+/// Collects rooms and passages while doors are being assigned.
+#[derive(Default)]
+struct Builder {
+    labels: Vec<Option<usize>>,
+    doors: Vec<[Option<(usize, usize)>; 6]>,
+    start: Option<usize>,
+}
+
+impl Builder {
+    fn ensure_room(&mut self, u: usize) {
+        if self.labels.len() <= u {
+            self.labels.resize(u + 1, None);
+            self.doors.resize(u + 1, [None; 6]);
+        }
+    }
+
+    fn free_door(&self, u: usize, skip: Option<usize>) -> Result<usize> {
+        (0..6)
+            .find(|&d| self.doors[u][d].is_none() && Some(d) != skip)
+            .with_context(|| format!("room {} has more than 6 passages", u))
+    }
+
+    fn connect(&mut self, u: usize, du: Option<usize>, v: usize, dv: Option<usize>) -> Result<()> {
+        self.ensure_room(u.max(v));
+        let du = match du {
+            Some(d) => d,
+            None => self.free_door(u, None)?,
+        };
+        let dv = match dv {
+            Some(d) => d,
+            // A self-loop without doors needs two distinct doors.
+            None => self.free_door(v, (u == v).then_some(du))?,
+        };
+        for (r, d) in [(u, du), (v, dv)] {
+            ensure!(d < 6, "door {} of room {} out of range", d, r);
+            ensure!(
+                self.doors[r][d].is_none(),
+                "door {} of room {} used twice",
+                d,
+                r
+            );
+        }
+        self.doors[u][du] = Some((v, dv));
+        self.doors[v][dv] = Some((u, du));
+        Ok(())
+    }
+
+    fn set_label(&mut self, u: usize, label: usize) -> Result<()> {
+        ensure!(label < 4, "label {} of room {} out of range", label, u);
+        self.ensure_room(u);
+        self.labels[u] = Some(label);
+        Ok(())
+    }
+
+    fn finish(self) -> Result<api::Map> {
+        let n = self.labels.len();
+        ensure!(n > 0, "graph has no rooms");
+        let starting_room = self.start.unwrap_or(0);
+        ensure!(
+            starting_room < n,
+            "starting room {} out of range",
+            starting_room
+        );
+        let mut connections = vec![];
+        for u in 0..n {
+            for d in 0..6 {
+                let (v, e) = self.doors[u][d].unwrap_or((u, d));
+                if (u, d) <= (v, e) {
+                    connections.push(api::MapConnection {
+                        from: api::MapConnectionEnd { room: u, door: d },
+                        to: api::MapConnectionEnd { room: v, door: e },
+                    });
+                }
+            }
+        }
+        Ok(api::Map {
+            rooms: (0..n).map(|u| self.labels[u].unwrap_or(u % 4)).collect(),
+            starting_room,
+            connections,
+        })
+    }
+}
+
+/// Parses the DIMACS-style edge list described in the module docs.
+pub fn parse_edge_list(text: &str) -> Result<api::Map> {
+    let mut b = Builder::default();
+    for (lineno, line) in text.lines().enumerate() {
+        let tokens = line.split_whitespace().collect::<Vec<_>>();
+        let num = |i: usize| -> Result<usize> {
+            tokens
+                .get(i)
+                .with_context(|| format!("line {}: missing field", lineno + 1))?
+                .parse::<usize>()
+                .with_context(|| format!("line {}: not a number", lineno + 1))
+        };
+        let room = |i: usize| -> Result<usize> {
+            match num(i)? {
+                0 => bail!("line {}: room ids start at 1", lineno + 1),
+                u => Ok(u - 1),
+            }
+        };
+        match tokens.first().copied() {
+            None | Some("c") => {}
+            Some("p") => b.ensure_room(num(2)?.max(1) - 1),
+            Some("n") => b.set_label(room(1)?, num(2)?)?,
+            Some("s") => b.start = Some(room(1)?),
+            Some("e") => {
+                let doors = if tokens.len() >= 5 {
+                    (Some(num(3)?), Some(num(4)?))
+                } else {
+                    (None, None)
+                };
+                b.connect(room(1)?, doors.0, room(2)?, doors.1)
+                    .with_context(|| format!("line {}", lineno + 1))?;
+            }
+            Some(t) => bail!("line {}: unknown line type {:?}", lineno + 1, t),
+        }
+    }
+    b.finish()
+}
+
+/// Splits DOT source into identifiers/strings and punctuation, dropping comments.
+fn dot_tokens(text: &str) -> Vec<String> {
+    let mut tokens = vec![];
+    let mut chars = text.chars().peekable();
+    while let Some(c) = chars.next() {
+        match c {
+            '/' if chars.peek() == Some(&'/') => {
+                chars.by_ref().take_while(|&c| c != '\n').for_each(drop);
+            }
+            '#' => {
+                chars.by_ref().take_while(|&c| c != '\n').for_each(drop);
+            }
+            '/' if chars.peek() == Some(&'*') => {
+                chars.next();
+                let mut prev = ' ';
+                for c in chars.by_ref() {
+                    if prev == '*' && c == '/' {
+                        break;
+                    }
+                    prev = c;
+                }
+            }
+            '"' => {
+                let mut s = String::new();
+                while let Some(c) = chars.next() {
+                    match c {
+                        '"' => break,
+                        '\\' => s.extend(chars.next()),
+                        _ => s.push(c),
+                    }
+                }
+                tokens.push(s);
+            }
+            '-' if matches!(chars.peek(), Some('-') | Some('>')) => {
+                chars.next();
+                tokens.push("--".to_string());
+            }
+            '{' | '}' | '[' | ']' | ';' | ',' | '=' => tokens.push(c.to_string()),
+            c if c.is_whitespace() => {}
+            _ => {
+                let mut s = c.to_string();
+                while let Some(&c) = chars.peek() {
+                    if c.is_alphanumeric() || c == '_' || c == '.' {
+                        s.push(c);
+                        chars.next();
+                    } else {
+                        break;
+                    }
+                }
+                tokens.push(s);
+            }
+        }
+    }
+    tokens
+}
+
+/// Parses the Graphviz DOT subset described in the module docs.
+pub fn parse_dot(text: &str) -> Result<api::Map> {
+    let tokens = dot_tokens(text);
+    let Some(open) = tokens.iter().position(|t| t == "{") else {
+        bail!("DOT graph body not found");
+    };
+    let mut b = Builder::default();
+    let mut ids: HashMap<String, usize> = HashMap::new();
+    let mut id_of = |b: &mut Builder, name: &str| -> usize {
+        let next = ids.len();
+        let u = *ids.entry(name.to_string()).or_insert(next);
+        b.ensure_room(u);
+        u
+    };
+    let mut i = open + 1;
+    while i < tokens.len() && tokens[i] != "}" {
+        if tokens[i] == ";" {
+            i += 1;
+            continue;
+        }
+        // Statement: ID (-- ID)* [attrs]?
+        let mut chain = vec![tokens[i].clone()];
+        i += 1;
+        while i + 1 < tokens.len() && tokens[i] == "--" {
+            chain.push(tokens[i + 1].clone());
+            i += 2;
+        }
+        let mut attrs = HashMap::new();
+        if tokens.get(i).map(String::as_str) == Some("[") {
+            i += 1;
+            while i < tokens.len() && tokens[i] != "]" {
+                if tokens[i] == "," || tokens[i] == ";" {
+                    i += 1;
+                    continue;
+                }
+                ensure!(
+                    tokens.get(i + 1).map(String::as_str) == Some("="),
+                    "attribute {} has no value",
+                    tokens[i]
+                );
+                let value = tokens.get(i + 2).context("unterminated attribute list")?;
+                attrs.insert(tokens[i].as_str(), value.as_str());
+                i += 3;
+            }
+            i += 1;
+        }
+        let door = |key: &str| -> Result<Option<usize>> {
+            attrs
+                .get(key)
+                .map(|v| {
+                    v.parse()
+                        .with_context(|| format!("{}={} is not a door", key, v))
+                })
+                .transpose()
+        };
+        if chain.len() == 1 {
+            // Graph-level attribute statements (`node [...]`, `rankdir=LR`) are ignored.
+            if matches!(chain[0].as_str(), "graph" | "node" | "edge") {
+                continue;
+            }
+            if tokens.get(i).map(String::as_str) == Some("=") {
+                i += 2;
+                continue;
+            }
+            let u = id_of(&mut b, &chain[0]);
+            if let Some(label) = attrs.get("label") {
+                let label = label
+                    .parse()
+                    .with_context(|| format!("label of {} is not a number", chain[0]))?;
+                b.set_label(u, label)?;
+            }
+            if attrs.get("start").is_some_and(|v| *v == "true") {
+                b.start = Some(u);
+            }
+        } else {
+            let (tail, head) = (door("taillabel")?, door("headlabel")?);
+            for pair in chain.windows(2) {
+                let u = id_of(&mut b, &pair[0]);
+                let v = id_of(&mut b, &pair[1]);
+                b.connect(u, tail, v, head)
+                    .with_context(|| format!("edge {} -- {}", pair[0], pair[1]))?;
+            }
+        }
+    }
+    b.finish()
+}
+
+/// Loads a map, choosing the format by extension (`.dot`/`.gv` for DOT,
+/// anything else for the edge list).
+pub fn load(path: &Path) -> Result<api::Map> {
+    let text = std::fs::read_to_string(path).with_context(|| format!("read {}", path.display()))?;
+    let map = match path.extension().and_then(|e| e.to_str()) {
+        Some("dot") | Some("gv") => parse_dot(&text),
+        _ => parse_edge_list(&text),
+    };
+    map.with_context(|| format!("parse {}", path.display()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::judge::{Judge, LocalJudge, parse_plan};
+
+    #[test]
+    fn edge_list_and_dot_describe_the_same_map() {
+        let edges =
+            parse_edge_list("c triangle\np edge 3 4\nn 2 3\ns 1\ne 1 2\ne 2 3 5 0\ne 3 1\ne 1 2\n")
+                .unwrap();
+        let dot = parse_dot(
+            "graph G {\n  // same triangle\n  a [start=true];\n  b [label=3];\n  a -- b;\n  b -- c [taillabel=5, headlabel=0];\n  c -- a;\n  a -- b;\n}\n",
+        )
+        .unwrap();
+        assert_eq!(edges.rooms, vec![0, 3, 2]);
+        assert_eq!(edges.starting_room, 0);
+        // Every door appears exactly once, unused ones as self-connections.
+        assert_eq!(edges.connections.len(), 4 + (18 - 8));
+        assert_eq!(
+            serde_json::to_value(&edges).unwrap(),
+            serde_json::to_value(&dot).unwrap()
+        );
+
+        let mut judge = LocalJudge::new_json(None, &edges);
+        let graph = judge.graph.clone();
+        let walk = parse_plan("000111222");
+        let mut u = judge.starting_room;
+        let mut expected = vec![judge.rooms[u]];
+        for &(_, d) in &walk {
+            u = graph[u][d];
+            expected.push(judge.rooms[u]);
+        }
+        assert_eq!(judge.explore(&[walk]), vec![expected]);
+    }
+
+    #[test]
+    fn rejects_overfull_rooms() {
+        let text = (0..7).map(|_| "e 1 2\n").collect::<String>();
+        assert!(parse_edge_list(&text).is_err());
+        assert!(parse_dot("graph { a -- a -- a -- a; }").is_ok());
+        assert!(parse_dot("graph { a -- a -- a -- a -- a; }").is_err());
+    }
+}
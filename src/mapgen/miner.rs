@@ -0,0 +1,294 @@
+//! # Instance Miner
+//!
+//! Most generated maps are easy: the preset plan tells almost every pair of
+//! time steps apart and the SAT solve takes well under a second. To benchmark
+//! solver changes on the cases that matter, [`mine`] generates many maps of
+//! the `LocalJudge` families, explores each with `routes::long_plan` and
+//! ranks them by hardness:
+//!
+//! 1. Fast heuristics on every map, the ones `bin/gacha.rs` and
+//!    `bin/iwiwi_evo_gen276.rs` print: `diff_count` (pairs of time steps
+//!    that are provably in different rooms; fewer means more ambiguity),
+//!    `label_door_chi2` (how unevenly doors were taken per label) and
+//!    `aib_missing` (label-door-label transitions never seen).
+//! 2. Optionally, a `solve_no_marks` SAT solve bounded by a timeout on the
+//!    heuristically hardest maps, whose time then becomes the hardness.
+//!
+//! The ranked [`Candidate`]s are written as JSON lines to a file or a `gs://`
+//! URL; `LocalJudge::new(&c.family, c.num_rooms, c.seed)` rebuilds each map.
+
+use anyhow::{Context, Result, ensure};
+use rayon::prelude::*;
+use serde::{Deserialize, Serialize};
+use std::time::{Duration, Instant};
+
+use crate::judge::{Judge, LocalJudge, Step, num_labels};
+use crate::solve_no_marks::Coverage;
+
+/// Families of `LocalJudge::new` whose maps are explored with the preset
+/// plan.
+pub const FAMILIES: [&str; 6] = [
+    "random",
+    "random2",
+    "grid",
+    "tree",
+    "bottleneck",
+    "expander",
+];
+
+/// A mined map and how hard it looked.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct Candidate {
+    pub family: String,
+    pub num_rooms: usize,
+    pub seed: u64,
+    pub diff_count: usize,
+    pub label_door_chi2: f64,
+    pub aib_missing: usize,
+    /// Seconds the bounded SAT solve ran, if it was run.
+    pub sat_secs: Option<f64>,
+    /// Whether the SAT solve finished before its timeout, if it was run.
+    pub sat_decided: Option<bool>,
+    /// What the ranking sorts by: `sat_secs` if the solve was run, otherwise
+    /// the number of step pairs not told apart.
+    pub hardness: f64,
+}
+
+#[derive(Clone, Debug)]
+pub struct MinerConfig {
+    pub families: Vec<String>,
+    pub num_rooms: usize,
+    /// Maps per family, with seeds `first_seed..first_seed + count`.
+    pub count: usize,
+    pub first_seed: u64,
+    /// Timeout of the SAT solve; `None` ranks by the heuristics only.
+    pub sat_timeout: Option<Duration>,
+    /// How many of the heuristically hardest maps get a SAT solve; the others
+    /// are dropped.
+    pub sat_shortlist: usize,
+    /// How many candidates are returned.
+    pub keep: usize,
+}
+
+impl Default for MinerConfig {
+    fn default() -> Self {
+        MinerConfig {
+            families: FAMILIES.iter().map(|f| f.to_string()).collect(),
+            num_rooms: 30,
+            count: 1000,
+            first_seed: 0,
+            sat_timeout: None,
+            sat_shortlist: 100,
+            keep: 100,
+        }
+    }
+}
+
+/// Counts the pairs of time steps that are provably in different rooms:
+/// their labels differ, or they take the same door to steps that are.
+fn diff_count(plan: &[usize], labels: &[usize]) -> usize {
+    let m = labels.len();
+    // Each diagonal j - i = d is scanned backwards; `next` is whether steps
+    // i + 1 and j + 1 differ.
+    let mut count = 0;
+    for d in 1..m {
+        let mut next = false;
+        for i in (0..m - d).rev() {
+            let j = i + d;
+            next = labels[i] != labels[j] || (j < plan.len() && plan[i] == plan[j] && next);
+            count += next as usize;
+        }
+    }
+    count
+}
+
+/// Squared deviation of `label_door` from the counts a balanced plan gives
+/// on a map whose room `u` has label `u % k`.
+fn label_door_chi2(cov: &Coverage, num_rooms: usize, plan_len: usize) -> f64 {
+    let k = num_labels();
+    let mut num = vec![0; k];
+    for u in 0..num_rooms {
+        num[u % k] += 1;
+    }
+    let mut sum = 0.0;
+    for (a, counts) in cov.label_door.iter().enumerate() {
+        for &c in &counts[..crate::judge::num_doors()] {
+            let expected = num[a] as f64 / num_rooms as f64 * plan_len as f64
+                / crate::judge::num_doors() as f64;
+            sum += (expected - c as f64).powi(2);
+        }
+    }
+    sum
+}
+
+fn explore(family: &str, num_rooms: usize, seed: u64, plan: &[usize]) -> Vec<usize> {
+    let steps: Vec<Step> = plan.iter().map(|&d| (None, d)).collect();
+    LocalJudge::new(family, num_rooms, seed).explore(&[steps])[0].clone()
+}
+
+/// The heuristics of one map.
+fn score(family: &str, num_rooms: usize, seed: u64, plan: &[usize]) -> Candidate {
+    let labels = explore(family, num_rooms, seed, plan);
+    let diff_count = diff_count(plan, &labels);
+    let cov = Coverage::new(&[plan.to_vec()], std::slice::from_ref(&labels));
+    let pairs = labels.len() * (labels.len() - 1) / 2;
+    Candidate {
+        family: family.to_string(),
+        num_rooms,
+        seed,
+        diff_count,
+        label_door_chi2: label_door_chi2(&cov, num_rooms, plan.len()),
+        aib_missing: cov.aib_missing(),
+        sat_secs: None,
+        sat_decided: None,
+        hardness: (pairs - diff_count) as f64,
+    }
+}
+
+/// Hardest first; ties go to the more uneven label-door counts.
+fn rank(candidates: &mut [Candidate]) {
+    candidates.sort_by(|a, b| {
+        b.hardness
+            .total_cmp(&a.hardness)
+            .then(b.label_door_chi2.total_cmp(&a.label_door_chi2))
+    });
+}
+
+/// Generates and ranks maps as described in the module docs, returning the
+/// `keep` hardest.
+pub fn mine(config: &MinerConfig) -> Result<Vec<Candidate>> {
+    for family in &config.families {
+        ensure!(
+            FAMILIES.contains(&family.as_str()),
+            "unknown map family: {}",
+            family
+        );
+    }
+    let n = config.num_rooms;
+    let plan = crate::routes::table_plan(n, crate::routes::long_plan_len(n))
+        .with_context(|| format!("no long plan for {} rooms", n))?;
+    let jobs: Vec<(&str, u64)> = config
+        .families
+        .iter()
+        .flat_map(|f| (0..config.count as u64).map(move |i| (f.as_str(), config.first_seed + i)))
+        .collect();
+    let mut candidates: Vec<Candidate> = jobs
+        .par_iter()
+        .map(|&(family, seed)| score(family, n, seed, &plan))
+        .collect();
+    rank(&mut candidates);
+    if let Some(timeout) = config.sat_timeout {
+        candidates.truncate(config.sat_shortlist);
+        // One at a time: the solve times are the measurement.
+        for c in &mut candidates {
+            let labels = explore(&c.family, n, c.seed, &plan);
+            let start = Instant::now();
+            let decided = crate::solve_no_marks::is_satisfiable_within(
+                n,
+                &vec![plan.clone()],
+                &vec![labels],
+                timeout,
+            );
+            let secs = start.elapsed().as_secs_f64();
+            c.sat_secs = Some(secs);
+            c.sat_decided = Some(decided.is_some());
+            c.hardness = secs;
+        }
+        rank(&mut candidates);
+    }
+    candidates.truncate(config.keep);
+    Ok(candidates)
+}
+
+/// Writes `candidates` as JSON lines to a local file or a `gs://` URL.
+pub fn write_dataset(dest: &str, candidates: &[Candidate]) -> Result<()> {
+    let mut data = vec![];
+    for c in candidates {
+        serde_json::to_writer(&mut data, c)?;
+        data.push(b'\n');
+    }
+    if dest.starts_with("gs://") {
+        upload(dest, data)
+    } else {
+        std::fs::write(dest, data).with_context(|| format!("failed to write {}", dest))
+    }
+}
+
+/// Reads a dataset written by [`write_dataset`] from a local file.
+pub fn read_dataset(path: &str) -> Result<Vec<Candidate>> {
+    let text = std::fs::read_to_string(path).with_context(|| format!("failed to read {}", path))?;
+    text.lines()
+        .filter(|l| !l.trim().is_empty())
+        .map(|l| serde_json::from_str(l).with_context(|| format!("invalid candidate: {}", l)))
+        .collect()
+}
+
+#[cfg(feature = "tokio")]
+fn upload(url: &str, data: Vec<u8>) -> Result<()> {
+    let (bucket, object) = crate::gcp::gcs::parse_gs_url(url)?;
+    tokio::runtime::Runtime::new()?
+        .block_on(crate::gcp::gcs::upload_object(
+            &bucket,
+            &object,
+            &data,
+            "application/x-ndjson",
+        ))
+        .with_context(|| format!("failed to upload {}", url))?;
+    Ok(())
+}
+
+#[cfg(not(feature = "tokio"))]
+fn upload(url: &str, _data: Vec<u8>) -> Result<()> {
+    anyhow::bail!("uploading {} requires the tokio feature", url)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn diff_count_follows_doors() {
+        // Steps 0 and 1 share a label, but door 1 leads on to different ones.
+        assert_eq!(diff_count(&[1, 1], &[0, 0, 1]), 3);
+        // Same labels and same doors throughout: nothing is told apart.
+        assert_eq!(diff_count(&[0, 0], &[2, 2, 2]), 0);
+        // Different doors do not propagate.
+        assert_eq!(diff_count(&[0, 1], &[0, 0, 1]), 2);
+    }
+
+    #[test]
+    fn mined_maps_are_ranked_and_round_trip() {
+        let config = MinerConfig {
+            families: vec!["random2".to_string(), "bottleneck".to_string()],
+            num_rooms: 6,
+            count: 4,
+            first_seed: 10,
+            sat_timeout: Some(Duration::from_secs(10)),
+            sat_shortlist: 5,
+            keep: 3,
+        };
+        let mined = mine(&config).unwrap();
+        assert_eq!(mined.len(), 3);
+        assert!(mined.windows(2).all(|w| w[0].hardness >= w[1].hardness));
+        assert!(mined.iter().all(|c| c.sat_decided == Some(true)));
+
+        // The heuristics are reproducible from the family and seed.
+        let plan = crate::routes::long_plan(6);
+        let c = &mined[0];
+        let again = score(&c.family, c.num_rooms, c.seed, &plan);
+        assert_eq!(again.diff_count, c.diff_count);
+        assert_eq!(again.label_door_chi2, c.label_door_chi2);
+
+        let path = std::env::temp_dir().join(format!("mined-{}.jsonl", std::process::id()));
+        let path = path.to_str().unwrap();
+        write_dataset(path, &mined).unwrap();
+        assert_eq!(read_dataset(path).unwrap(), mined);
+        std::fs::remove_file(path).unwrap();
+
+        let bad = MinerConfig {
+            families: vec!["moebius".to_string()],
+            ..config
+        };
+        assert!(mine(&bad).is_err());
+    }
+}
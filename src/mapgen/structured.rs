@@ -0,0 +1,333 @@
+//! # Structured Map Families
+//!
+//! `random` and `random2` wire doors uniformly at random, which gives
+//! near-regular expanders with few short cycles. Contest maps are not always
+//! like that, so these families give solvers other shapes to be tested on
+//! (`LocalJudge::new("grid", n, seed)` etc.):
+//!
+//! - `grid`: rooms on a `ceil(sqrt(n))`-wide grid, joined to the right and
+//!   down; the remaining doors pair up within grid distance 2.
+//! - `tree`: a random recursive tree; the remaining doors are back edges
+//!   between a room and one of its ancestors.
+//! - `bottleneck`: two halves, each a random tree plus doors paired within
+//!   the half, joined by a single passage.
+//! - `expander`: a random spanning tree, then each passage joins rooms as
+//!   far apart as the free doors allow, which keeps short cycles (and thus
+//!   locally ambiguous neighborhoods) rare.
+//!
+//! Every map is connected and uses each of the first [`num_doors`] doors of
+//! every room exactly once; a door left without a partner loops back to
+//! itself. Labels are assigned by `LocalJudge`, as for `random2`.
+
+use rand::prelude::*;
+use std::collections::VecDeque;
+
+use crate::judge::num_doors;
+
+/// Names accepted by [`generate`].
+pub const KINDS: [&str; 4] = ["grid", "tree", "bottleneck", "expander"];
+
+/// A passage between `(room, door)` pairs, as in `judge::generate_random_edges_v2`.
+pub type Passage = ((usize, usize), (usize, usize));
+
+/// Passages of a map of family `kind`, or `None` for an unknown family.
+pub fn generate(kind: &str, num_rooms: usize, seed: u64) -> Option<Vec<Passage>> {
+    let mut doors = Doors::new(num_rooms, seed);
+    let n = num_rooms;
+    match kind {
+        "grid" => {
+            let w = (1..).find(|w| w * w >= n).unwrap();
+            for u in 0..n {
+                if u % w + 1 < w && u + 1 < n {
+                    doors.connect(u, u + 1);
+                }
+                if u + w < n {
+                    doors.connect(u, u + w);
+                }
+            }
+            let dist = |u: usize, v: usize| (u % w).abs_diff(v % w) + (u / w).abs_diff(v / w);
+            doors.fill(false, |_, u| {
+                (0..n).map(|v| u != v && dist(u, v) <= 2).collect()
+            });
+        }
+        "tree" => {
+            let parent = doors.random_tree(&(0..n).collect::<Vec<_>>());
+            let ancestors = |u: usize| {
+                let mut mask = vec![false; n];
+                let mut v = u;
+                while let Some(p) = parent[v] {
+                    mask[p] = true;
+                    v = p;
+                }
+                mask
+            };
+            let masks = (0..n).map(ancestors).collect::<Vec<_>>();
+            doors.fill(false, |_, u| {
+                (0..n).map(|v| masks[u][v] || masks[v][u]).collect()
+            });
+        }
+        "bottleneck" => {
+            let half = n.div_ceil(2);
+            let (a, b) = ((0..half).collect::<Vec<_>>(), (half..n).collect::<Vec<_>>());
+            doors.random_tree(&a);
+            doors.random_tree(&b);
+            if !b.is_empty() {
+                let a = a
+                    .into_iter()
+                    .filter(|&u| doors.has_free(u))
+                    .collect::<Vec<_>>();
+                let b = b
+                    .into_iter()
+                    .filter(|&v| doors.has_free(v))
+                    .collect::<Vec<_>>();
+                let u = *a.choose(&mut doors.rng)?;
+                let v = *b.choose(&mut doors.rng)?;
+                doors.connect(u, v);
+            }
+            // Strict, so that an odd door loops back instead of crossing over.
+            doors.fill(true, |_, u| {
+                (0..n).map(|v| (u < half) == (v < half)).collect()
+            });
+        }
+        "expander" => {
+            doors.random_tree(&(0..n).collect::<Vec<_>>());
+            doors.fill(false, |doors, u| {
+                let dist = doors.distances(u);
+                let farthest = (0..n)
+                    .filter(|&v| v != u && doors.has_free(v))
+                    .map(|v| dist[v])
+                    .max();
+                dist.iter().map(|&d| Some(d) == farthest).collect()
+            });
+        }
+        _ => return None,
+    }
+    Some(doors.edges)
+}
+
+/// Doors being paired up.
+struct Doors {
+    /// Unused doors of each room, in random order.
+    free: Vec<Vec<usize>>,
+    edges: Vec<Passage>,
+    rng: rand_chacha::ChaCha20Rng,
+}
+
+impl Doors {
+    fn new(num_rooms: usize, seed: u64) -> Self {
+        let mut rng = rand_chacha::ChaCha20Rng::seed_from_u64(seed);
+        let free = (0..num_rooms)
+            .map(|_| {
+                let mut d = (0..num_doors()).collect::<Vec<_>>();
+                d.shuffle(&mut rng);
+                d
+            })
+            .collect();
+        Doors {
+            free,
+            edges: vec![],
+            rng,
+        }
+    }
+
+    fn has_free(&self, u: usize) -> bool {
+        !self.free[u].is_empty()
+    }
+
+    /// Joins `u` and `v` through a free door of each, if they have one.
+    fn connect(&mut self, u: usize, v: usize) -> bool {
+        let enough = if u == v {
+            self.free[u].len() >= 2
+        } else {
+            self.has_free(u) && self.has_free(v)
+        };
+        if !enough {
+            return false;
+        }
+        let d = self.free[u].pop().unwrap();
+        let f = self.free[v].pop().unwrap();
+        self.edges.push(((u, d), (v, f)));
+        true
+    }
+
+    /// Joins `rooms` into a random recursive tree: each room after the first
+    /// hangs off a random earlier one with a free door. Returns the parents.
+    fn random_tree(&mut self, rooms: &[usize]) -> Vec<Option<usize>> {
+        let n = self.free.len();
+        let mut parent = vec![None; n];
+        for (i, &u) in rooms.iter().enumerate().skip(1) {
+            let candidates = rooms[..i]
+                .iter()
+                .copied()
+                .filter(|&p| self.has_free(p))
+                .collect::<Vec<_>>();
+            let Some(&p) = candidates.choose(&mut self.rng) else {
+                break;
+            };
+            self.connect(u, p);
+            parent[u] = Some(p);
+        }
+        parent
+    }
+
+    /// Shortest distances from `u` over the passages so far (`usize::MAX` if
+    /// unreachable).
+    fn distances(&self, u: usize) -> Vec<usize> {
+        let n = self.free.len();
+        let mut adj = vec![vec![]; n];
+        for &((a, _), (b, _)) in &self.edges {
+            adj[a].push(b);
+            adj[b].push(a);
+        }
+        let mut dist = vec![usize::MAX; n];
+        dist[u] = 0;
+        let mut queue = VecDeque::from([u]);
+        while let Some(a) = queue.pop_front() {
+            for &b in &adj[a] {
+                if dist[b] == usize::MAX {
+                    dist[b] = dist[a] + 1;
+                    queue.push_back(b);
+                }
+            }
+        }
+        dist
+    }
+
+    /// Pairs every free door, preferring partners in the rooms `allowed`
+    /// marks for the door's room. Doors with no allowed partner take any free
+    /// door unless `strict`; a door with no partner at all loops back to
+    /// itself.
+    fn fill(&mut self, strict: bool, allowed: impl Fn(&Self, usize) -> Vec<bool>) {
+        let mut rooms = (0..self.free.len()).collect::<Vec<_>>();
+        rooms.shuffle(&mut self.rng);
+        for u in rooms {
+            while self.has_free(u) {
+                let mask = allowed(self, u);
+                let partners = (0..self.free.len())
+                    .filter(|&v| {
+                        if v == u {
+                            self.free[v].len() >= 2
+                        } else {
+                            self.has_free(v)
+                        }
+                    })
+                    .collect::<Vec<_>>();
+                let preferred = partners
+                    .iter()
+                    .copied()
+                    .filter(|&v| mask[v])
+                    .collect::<Vec<_>>();
+                let v = match (preferred.choose(&mut self.rng), strict) {
+                    (Some(&v), _) => Some(v),
+                    (None, false) => partners.choose(&mut self.rng).copied(),
+                    (None, true) => None,
+                };
+                match v {
+                    Some(v) => {
+                        self.connect(u, v);
+                    }
+                    None => {
+                        let d = self.free[u].pop().unwrap();
+                        self.edges.push(((u, d), (u, d)));
+                    }
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::judge::{Judge, LocalJudge};
+
+    fn adjacency(n: usize, edges: &[Passage]) -> Vec<Vec<usize>> {
+        let mut adj = vec![vec![]; n];
+        for &((u, _), (v, _)) in edges {
+            adj[u].push(v);
+            adj[v].push(u);
+        }
+        adj
+    }
+
+    /// Cycles of length at most 3 between distinct rooms: repeated passages
+    /// and triangles.
+    fn short_cycles(n: usize, edges: &[Passage]) -> usize {
+        let mut count = vec![vec![0usize; n]; n];
+        for &((u, _), (v, _)) in edges {
+            if u != v {
+                count[u][v] += 1;
+                count[v][u] += 1;
+            }
+        }
+        let mut cycles = 0;
+        for u in 0..n {
+            for v in u + 1..n {
+                cycles += count[u][v] * count[u][v].saturating_sub(1) / 2;
+                for w in v + 1..n {
+                    cycles += count[u][v] * count[v][w] * count[w][u];
+                }
+            }
+        }
+        cycles
+    }
+
+    #[test]
+    fn families_use_every_door_once_and_stay_connected() {
+        for kind in KINDS {
+            for (n, seed) in [(1, 0), (6, 1), (12, 2), (30, 3)] {
+                let edges = generate(kind, n, seed).unwrap();
+                let mut used = vec![vec![0; num_doors()]; n];
+                for &((u, d), (v, f)) in &edges {
+                    used[u][d] += 1;
+                    if (u, d) != (v, f) {
+                        used[v][f] += 1;
+                    }
+                }
+                assert!(used.iter().flatten().all(|&c| c == 1), "{kind} {n}");
+                let adj = adjacency(n, &edges);
+                let mut seen = vec![false; n];
+                let mut stack = vec![0];
+                seen[0] = true;
+                while let Some(u) = stack.pop() {
+                    for &v in &adj[u] {
+                        if !std::mem::replace(&mut seen[v], true) {
+                            stack.push(v);
+                        }
+                    }
+                }
+                assert!(seen.iter().all(|&s| s), "{kind} {n} is disconnected");
+            }
+        }
+        assert!(generate("moebius", 6, 0).is_none());
+    }
+
+    #[test]
+    fn families_have_their_shape() {
+        let n = 30;
+        let edges = generate("bottleneck", n, 4).unwrap();
+        let crossing = edges
+            .iter()
+            .filter(|&&((u, _), (v, _))| (u < n / 2) != (v < n / 2))
+            .count();
+        assert_eq!(crossing, 1);
+
+        // Random wiring has short cycles; the expander mostly avoids them.
+        let random = crate::judge::generate_random_edges_v2(n, 4);
+        let expander = generate("expander", n, 4).unwrap();
+        assert!(short_cycles(n, &expander) * 4 < short_cycles(n, &random));
+
+        // Grid passages stay local, but for the last few doors to be paired.
+        let w = 6;
+        let edges = generate("grid", n, 4).unwrap();
+        let far = edges
+            .iter()
+            .filter(|&&((u, _), (v, _))| (u % w).abs_diff(v % w) + (u / w).abs_diff(v / w) > 2)
+            .count();
+        assert!(far * 10 < edges.len(), "{} of {}", far, edges.len());
+
+        let mut judge = LocalJudge::new("tree", 12, 5);
+        assert_eq!(judge.num_rooms(), 12);
+        assert_eq!(judge.explore(&[vec![(None, 0); 24]])[0].len(), 25);
+    }
+}
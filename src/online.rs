@@ -0,0 +1,405 @@
+//! Online room identification for explore-driven sessions.
+//!
+//! [`Online`] keeps the same-room/different-room table of the `chokudai*`
+//! solvers up to date while explore results come in, instead of building it
+//! once from a finished log. Every label observed on a walk is a node; nodes
+//! known to be the same room are kept in a union-find, and pairs known to be
+//! different rooms are recorded per class. Facts propagate both ways:
+//!
+//! - the same room left through the same door reaches the same room;
+//! - different labels mean different rooms, and so do different rooms
+//!   reached through the same door.
+//!
+//! Once `num_rooms` pairwise different classes are known, a class that can be
+//! only one of them is merged into it. When every room has all its doors
+//! resolved, the map is determined and [`Online::guess`] returns it, so a
+//! session can stop exploring before its planned budget is spent
+//! ([`explore_until_determined`]).
+//!
+//! Labels observed after a charcoal mark of the same plan are not used, as the
+//! mark may have changed them; the doors taken are still used.
+
+use std::collections::{HashSet, VecDeque};
+
+use crate::judge::{Guess, Judge, MAX_DOORS, Step, num_doors};
+
+/// The observations cannot come from a map with `num_rooms` rooms, e.g.
+/// because a label was misread.
+#[derive(thiserror::Error, Debug, Clone, PartialEq, Eq)]
+#[error("inconsistent explore results: {0}")]
+pub struct Contradiction(&'static str);
+
+enum Fact {
+    Same(usize, usize),
+    Different(usize, usize),
+}
+
+pub struct Online {
+    num_rooms: usize,
+    parent: Vec<usize>,
+    size: Vec<usize>,
+    /// Per class (indexed by root): the label of the room, if seen unmarked.
+    label: Vec<Option<usize>>,
+    /// Per class: a node reached through each door.
+    next: Vec<[Option<usize>; MAX_DOORS]>,
+    /// Per class: nodes whose door `d` leads into the class.
+    prev: Vec<[Vec<usize>; MAX_DOORS]>,
+    /// Per class: roots of classes known to be other rooms, besides those with
+    /// another label.
+    different: Vec<HashSet<usize>>,
+    /// Pairwise different nodes, one per room found so far.
+    rooms: Vec<usize>,
+    queue: VecDeque<Fact>,
+}
+
+impl Online {
+    /// An empty table for a map with `num_rooms` rooms. Node 0 is the starting room.
+    pub fn new(num_rooms: usize) -> Self {
+        let mut online = Self {
+            num_rooms,
+            parent: vec![],
+            size: vec![],
+            label: vec![],
+            next: vec![],
+            prev: vec![],
+            different: vec![],
+            rooms: vec![],
+            queue: VecDeque::new(),
+        };
+        online.node();
+        online
+    }
+
+    fn node(&mut self) -> usize {
+        let id = self.parent.len();
+        self.parent.push(id);
+        self.size.push(1);
+        self.label.push(None);
+        self.next.push([None; MAX_DOORS]);
+        self.prev.push(Default::default());
+        self.different.push(HashSet::new());
+        id
+    }
+
+    fn find(&self, mut x: usize) -> usize {
+        while self.parent[x] != x {
+            x = self.parent[x];
+        }
+        x
+    }
+
+    fn roots(&self) -> impl Iterator<Item = usize> + '_ {
+        (0..self.parent.len()).filter(|&x| self.parent[x] == x)
+    }
+
+    /// Whether roots `a` and `b` are known to be different rooms.
+    fn is_different(&self, a: usize, b: usize) -> bool {
+        matches!((self.label[a], self.label[b]), (Some(x), Some(y)) if x != y)
+            || self.different[a].contains(&b)
+    }
+
+    /// Queues "different" for every pair of predecessors through the same
+    /// door, `preds` on one side and those of root `b` on the other.
+    fn separate(&mut self, preds: &[Vec<usize>; MAX_DOORS], b: usize) {
+        for d in 0..MAX_DOORS {
+            for &p in &preds[d] {
+                for &q in &self.prev[b][d] {
+                    self.queue.push_back(Fact::Different(p, q));
+                }
+            }
+        }
+    }
+
+    /// Separates `preds` from every class whose label differs from `label`.
+    fn separate_by_label(&mut self, preds: &[Vec<usize>; MAX_DOORS], label: usize) {
+        let others = self
+            .roots()
+            .filter(|&b| self.label[b].is_some_and(|l| l != label))
+            .collect::<Vec<_>>();
+        for b in others {
+            self.separate(preds, b);
+        }
+    }
+
+    fn set_label(&mut self, x: usize, label: usize) -> Result<(), Contradiction> {
+        let r = self.find(x);
+        match self.label[r] {
+            Some(l) if l != label => Err(Contradiction("one room seen with two labels")),
+            Some(_) => Ok(()),
+            None => {
+                self.label[r] = Some(label);
+                let preds = self.prev[r].clone();
+                self.separate_by_label(&preds, label);
+                Ok(())
+            }
+        }
+    }
+
+    /// The node reached from `x` through `door`, created if not seen yet.
+    fn step(&mut self, x: usize, door: usize) -> usize {
+        let r = self.find(x);
+        if let Some(y) = self.next[r][door] {
+            return y;
+        }
+        let y = self.node();
+        self.next[r][door] = Some(y);
+        self.prev[y][door].push(x);
+        y
+    }
+
+    fn merge(&mut self, a: usize, b: usize) -> Result<(), Contradiction> {
+        let (mut s, mut r) = (self.find(a), self.find(b));
+        if s == r {
+            return Ok(());
+        }
+        if self.is_different(s, r) {
+            return Err(Contradiction("one room proven to be two"));
+        }
+        if self.size[s] < self.size[r] {
+            std::mem::swap(&mut s, &mut r);
+        }
+        let s_preds = self.prev[s].clone();
+        let r_preds = std::mem::take(&mut self.prev[r]);
+        let s_label = self.label[s];
+        let r_label = self.label[r];
+        let r_different = std::mem::take(&mut self.different[r]);
+        let s_different = self.different[s].clone();
+
+        self.parent[r] = s;
+        self.size[s] += self.size[r];
+        self.label[s] = s_label.or(r_label);
+        for x in &r_different {
+            self.different[*x].remove(&r);
+            self.different[*x].insert(s);
+            self.different[s].insert(*x);
+        }
+        for d in 0..MAX_DOORS {
+            match (self.next[s][d], self.next[r][d]) {
+                (Some(y), Some(z)) => self.queue.push_back(Fact::Same(y, z)),
+                (None, Some(z)) => self.next[s][d] = Some(z),
+                _ => {}
+            }
+            self.prev[s][d].extend_from_slice(&r_preds[d]);
+        }
+
+        // Pairs that became different through the merge, seen from each side.
+        for &x in r_different.difference(&s_different) {
+            self.separate(&s_preds, x);
+        }
+        for &x in s_different.difference(&r_different) {
+            self.separate(&r_preds, x);
+        }
+        match (s_label, r_label) {
+            (None, Some(l)) => self.separate_by_label(&s_preds, l),
+            (Some(l), None) => self.separate_by_label(&r_preds, l),
+            _ => {}
+        }
+        Ok(())
+    }
+
+    fn set_different(&mut self, a: usize, b: usize) -> Result<(), Contradiction> {
+        let (a, b) = (self.find(a), self.find(b));
+        if a == b {
+            return Err(Contradiction("two rooms proven to be one"));
+        }
+        if self.is_different(a, b) {
+            return Ok(());
+        }
+        self.different[a].insert(b);
+        self.different[b].insert(a);
+        let preds = self.prev[a].clone();
+        self.separate(&preds, b);
+        Ok(())
+    }
+
+    fn propagate(&mut self) -> Result<(), Contradiction> {
+        loop {
+            while let Some(fact) = self.queue.pop_front() {
+                match fact {
+                    Fact::Same(a, b) => self.merge(a, b)?,
+                    Fact::Different(a, b) => self.set_different(a, b)?,
+                }
+            }
+            // Look for more pairwise different rooms, starting from the
+            // largest classes as they have the most facts attached.
+            let mut roots = self.roots().collect::<Vec<_>>();
+            if self.rooms.len() < self.num_rooms {
+                roots.sort_by_key(|&c| std::cmp::Reverse(self.size[c]));
+                let mut rooms: Vec<usize> = vec![];
+                for &c in &roots {
+                    if rooms.len() < self.num_rooms
+                        && rooms.iter().all(|&k| self.is_different(c, k))
+                    {
+                        rooms.push(c);
+                    }
+                }
+                if rooms.len() > self.rooms.len() {
+                    self.rooms = rooms;
+                }
+            }
+            if self.rooms.len() < self.num_rooms {
+                return Ok(());
+            }
+            // With every room found, a class that can be only one of them is it.
+            let rooms = self.rooms.iter().map(|&k| self.find(k)).collect::<Vec<_>>();
+            for c in roots {
+                let c = self.find(c);
+                let mut candidates = rooms.iter().filter(|&&k| !self.is_different(c, k));
+                match (candidates.next(), candidates.next()) {
+                    (None, _) => return Err(Contradiction("more rooms than the map has")),
+                    (Some(&k), None) if k != c => self.queue.push_back(Fact::Same(c, k)),
+                    _ => {}
+                }
+            }
+            if self.queue.is_empty() {
+                return Ok(());
+            }
+        }
+    }
+
+    /// Adds the labels `result` observed along `plan`, and everything that follows.
+    pub fn add(&mut self, plan: &[Step], result: &[usize]) -> Result<(), Contradiction> {
+        assert_eq!(result.len(), plan.len() + 1);
+        let mut x = 0;
+        let mut marked = false;
+        self.set_label(x, result[0])?;
+        for (&(mark, door), &label) in plan.iter().zip(&result[1..]) {
+            marked |= mark.is_some();
+            x = self.step(x, door);
+            if !marked {
+                self.set_label(x, label)?;
+            }
+        }
+        self.propagate()
+    }
+
+    /// Number of rooms known to be pairwise different so far.
+    pub fn rooms_found(&self) -> usize {
+        self.rooms.len()
+    }
+
+    /// Whether the observations so far leave only one possible map.
+    pub fn is_determined(&self) -> bool {
+        self.guess().is_some()
+    }
+
+    /// The map, once it is determined. Where several passages join the same
+    /// two rooms, the pairing of their doors is not observable and one is
+    /// picked arbitrarily.
+    pub fn guess(&self) -> Option<Guess> {
+        if self.rooms.len() < self.num_rooms {
+            return None;
+        }
+        let rooms = self.rooms.iter().map(|&k| self.find(k)).collect::<Vec<_>>();
+        if self.roots().count() != rooms.len() {
+            return None;
+        }
+        let index = |x: usize| rooms.iter().position(|&k| k == self.find(x));
+        let mut to = vec![[0; MAX_DOORS]; rooms.len()];
+        for (u, &k) in rooms.iter().enumerate() {
+            for d in 0..MAX_DOORS {
+                to[u][d] = if d < num_doors() {
+                    index(self.next[k][d]?)?
+                } else {
+                    u
+                };
+            }
+        }
+        let mut graph = vec![[(!0, !0); MAX_DOORS]; rooms.len()];
+        for u in 0..rooms.len() {
+            for d in 0..MAX_DOORS {
+                if graph[u][d].0 != !0 {
+                    continue;
+                }
+                let v = to[u][d];
+                let e = (0..MAX_DOORS).find(|&e| to[v][e] == u && graph[v][e].0 == !0)?;
+                graph[u][d] = (v, e);
+                graph[v][e] = (u, d);
+            }
+        }
+        Some(Guess {
+            rooms: rooms
+                .iter()
+                .map(|&k| self.label[k])
+                .collect::<Option<_>>()?,
+            start: index(0)?,
+            graph,
+        })
+    }
+}
+
+/// Sends `batches` of plans to `judge` one request at a time and stops as soon
+/// as the results determine the map, returning it. Returns `None` if the map
+/// is still ambiguous after the last batch.
+pub fn explore_until_determined(
+    judge: &mut dyn Judge,
+    batches: &[Vec<Vec<Step>>],
+) -> Result<Option<Guess>, Contradiction> {
+    let mut online = Online::new(judge.num_rooms());
+    for plans in batches {
+        let results = judge.explore(plans);
+        for (plan, result) in plans.iter().zip(&results) {
+            online.add(plan, result)?;
+        }
+        if let Some(guess) = online.guess() {
+            return Ok(Some(guess));
+        }
+    }
+    Ok(None)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::judge::LocalJudge;
+    use rand::prelude::*;
+
+    fn random_plan(rng: &mut impl Rng, len: usize) -> Vec<Step> {
+        (0..len).map(|_| (None, rng.random_range(0..6))).collect()
+    }
+
+    #[test]
+    fn random_walks_determine_small_maps_early() {
+        let mut rng = rand_chacha::ChaCha20Rng::seed_from_u64(1);
+        let mut determined = 0;
+        for seed in 0..30 {
+            let n = [3, 6, 12][seed as usize % 3];
+            let mut judge = LocalJudge::new("random", n, seed);
+            let batches = (0..20)
+                .map(|_| vec![random_plan(&mut rng, 18 * n)])
+                .collect::<Vec<_>>();
+            // Propagation alone does not always pin down 12 rooms.
+            if let Some(guess) = explore_until_determined(&mut judge, &batches).unwrap() {
+                judge.check_guess(&guess).unwrap();
+                assert!(judge.explored().plans.len() < batches.len());
+                determined += 1;
+            } else {
+                assert_eq!(n, 12, "seed {}", seed);
+            }
+        }
+        // Every small map, and some of the larger ones.
+        assert!(determined > 20, "{}", determined);
+    }
+
+    #[test]
+    fn labels_after_a_mark_are_ignored() {
+        let mut judge = LocalJudge::new("random", 3, 0);
+        let plan = vec![(Some(3), 0), (None, 1), (None, 2)];
+        let result = judge.explore(std::slice::from_ref(&plan)).remove(0);
+        let mut online = Online::new(3);
+        online.add(&plan, &result).unwrap();
+        assert_eq!(online.label[online.find(0)], Some(result[0]));
+        for x in 1..online.parent.len() {
+            assert_eq!(online.label[online.find(x)], None);
+        }
+    }
+
+    #[test]
+    fn misread_labels_are_contradictions() {
+        let mut online = Online::new(1);
+        let plan = vec![(None, 0), (None, 0)];
+        online.add(&plan, &[1, 1, 1]).unwrap();
+        assert_eq!(online.rooms_found(), 1);
+        assert!(online.add(&plan, &[1, 2, 1]).is_err());
+    }
+}
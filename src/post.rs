@@ -0,0 +1,146 @@
+//! Implementation of `post select|explore|guess`, shared by the `post` and
+//! `run` binaries.
+//!
+//! Arguments are JSON request bodies as in the official API. The "id" field is
+//! optional but if provided must match local id.json.
+//!
+//! Example usage:
+//!   post select '{"problemName":"foo"}'
+//!   post explore '{"plans":["0123"]}'
+//!   post guess '{"map":{...}}'
+use crate::api;
+use crate::problems;
+use anyhow::{Context, Result, bail};
+use clap::Subcommand;
+use serde_json::Value;
+
+#[derive(Subcommand, Debug)]
+pub enum PostCommand {
+    /// Select a problem
+    Select {
+        /// JSON string argument
+        json: String,
+    },
+    /// Explore plans
+    Explore {
+        /// JSON string argument
+        json: String,
+    },
+    /// Guess the map
+    Guess {
+        /// JSON string argument
+        json: String,
+    },
+}
+
+/// Validates the request, sends it through [`api`] (with its retries and
+/// request logging) and prints the response JSON to stdout.
+pub fn run(command: PostCommand) -> Result<()> {
+    match command {
+        PostCommand::Select { json } => handle_select(&json),
+        PostCommand::Explore { json } => handle_explore(&json),
+        PostCommand::Guess { json } => handle_guess(&json),
+    }
+}
+
+fn handle_select(json_arg: &str) -> Result<()> {
+    let v: Value = serde_json::from_str(json_arg).context("invalid JSON for select")?;
+    let obj = v.as_object().context("select expects a JSON object")?;
+
+    let problem_name = obj
+        .get("problemName")
+        .and_then(|v| v.as_str())
+        .context("select requires field 'problemName': string")?;
+
+    // Validate problem name using local list (also resolves case and aliases),
+    // refreshed from the server if the name is new.
+    let problem_name = problems::find_problem_or_refresh(problem_name)?
+        .problem
+        .as_str();
+
+    // Validate optional id if provided matches fetched id.
+    let input_id = obj.get("id").and_then(|v| v.as_str());
+    let id = api::get_id()?;
+    if let Some(given) = input_id
+        && given != id
+    {
+        bail!("provided id does not match local id.json");
+    }
+
+    let selected = api::select(problem_name)?;
+    let out = serde_json::json!({ "problemName": selected });
+    println!("{}", serde_json::to_string(&out)?);
+    Ok(())
+}
+
+fn validate_plan(s: &str) -> bool {
+    s.bytes()
+        .all(|b| matches!(b, b'0' | b'1' | b'2' | b'3' | b'4' | b'5'))
+}
+
+fn handle_explore(json_arg: &str) -> Result<()> {
+    let v: Value = serde_json::from_str(json_arg).context("invalid JSON for explore")?;
+    let obj = v.as_object().context("explore expects a JSON object")?;
+
+    // Validate optional id if provided.
+    let input_id = obj.get("id").and_then(|v| v.as_str());
+    let id = api::get_id()?;
+    if let Some(given) = input_id
+        && given != id
+    {
+        bail!("provided id does not match local id.json");
+    }
+
+    let plans_v = obj
+        .get("plans")
+        .context("explore requires field 'plans': [string]")?;
+    let plans_arr = plans_v.as_array().context("'plans' must be an array")?;
+    let mut plans_strs: Vec<String> = Vec::with_capacity(plans_arr.len());
+    for (i, p) in plans_arr.iter().enumerate() {
+        let s = p
+            .as_str()
+            .with_context(|| format!("plans[{}] must be a string", i))?;
+        if !validate_plan(s) {
+            bail!(
+                "plans[{}] contains non-digit or out-of-range digit (allowed: 0-5)",
+                i
+            );
+        }
+        plans_strs.push(s.to_string());
+    }
+
+    let resp = api::explore(plans_strs)?;
+    let out = serde_json::json!({
+        "results": resp.results,
+        "queryCount": resp.query_count,
+    });
+    println!("{}", serde_json::to_string(&out)?);
+    Ok(())
+}
+
+fn handle_guess(json_arg: &str) -> Result<()> {
+    let v: Value = serde_json::from_str(json_arg).context("invalid JSON for guess")?;
+    let obj = v.as_object().context("guess expects a JSON object")?;
+
+    // Validate optional id if provided.
+    let input_id = obj.get("id").and_then(|v| v.as_str());
+    let id = api::get_id()?;
+    if let Some(given) = input_id
+        && given != id
+    {
+        bail!("provided id does not match local id.json");
+    }
+
+    // Deserialize map using the API types.
+    #[derive(serde::Deserialize)]
+    struct GuessIn {
+        map: api::Map,
+    }
+    let guess: GuessIn = serde_json::from_value(v).context("'map' is required for guess")?;
+    api::validate_map(&guess.map)?;
+
+    let correct = api::guess(&guess.map)?;
+    let out = serde_json::json!({ "correct": correct });
+    println!("{}", serde_json::to_string(&out)?);
+    Ok(())
+}
@@ -1,16 +1,21 @@
 //! # Contest Problem Definitions
 //!
-//! This module contains the static definitions for the official contest problems,
+//! This module contains the definitions for the official contest problems,
 //! including their names and sizes (number of rooms). It provides convenient
 //! functions for accessing this data.
+//!
+//! The registry starts from the bundled `problems.json`. Problems published
+//! later are picked up by [`refresh_from_api`], which merges the server's list
+//! into it; [`find_problem_or_refresh`] does so on a lookup miss.
 
 use once_cell::sync::Lazy;
-use serde::Deserialize;
+use serde::{Deserialize, Serialize};
 use serde_json;
 use std::collections::HashMap;
+use std::sync::{RwLock, RwLockReadGuard};
 
 /// Represents a single contest problem.
-#[derive(Debug, Clone, PartialEq, Eq, Deserialize)]
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
 pub struct Problem {
     /// The official name of the problem, e.g., "probatio".
     pub problem: String,
@@ -18,33 +23,133 @@ pub struct Problem {
     pub size: usize,
 }
 
-/// A static array containing the data for all known contest problems.
+/// The problems bundled at build time.
 /// Run the following command to update the data:
 /// ```bash
 ///   curl -L https://31pwr5t6ij.execute-api.eu-west-2.amazonaws.com/select -o ./src/problems.json
 /// ```
-static PROBLEMS_DATA: Lazy<Vec<Problem>> = Lazy::new(|| {
+fn static_problems() -> Vec<Problem> {
     const PROBLEMS_JSON: &str = include_str!("problems.json");
     serde_json::from_str(PROBLEMS_JSON).expect("failed to parse problems.json")
-});
+}
 
-/// Returns a slice containing all defined contest problems.
-pub fn all_problems() -> &'static [Problem] {
-    &PROBLEMS_DATA
+/// Alternative spellings accepted by [`get_problem`], mapped to official names.
+/// Mostly other transliterations of the Hebrew letter names.
+const ALIASES: &[(&str, &str)] = &[
+    ("alef", "aleph"),
+    ("bet", "beth"),
+    ("dalet", "daleth"),
+    ("vav", "vau"),
+    ("waw", "vau"),
+    ("zayin", "zain"),
+    ("het", "hhet"),
+    ("chet", "hhet"),
+    ("heth", "hhet"),
+    ("tet", "teth"),
+    ("yod", "iod"),
+];
+
+/// The known problems and a lookup table by name.
+///
+/// Lookups hand out `&'static` references, so every version of the list is
+/// leaked; a refresh only builds a new one when the list changed, which
+/// happens a handful of times per contest.
+struct Registry {
+    all: &'static [Problem],
+    /// Keys are lowercase and include the [`ALIASES`].
+    map: HashMap<String, &'static Problem>,
 }
 
-/// A lazily-initialized HashMap for efficient lookup of problems by name.
-/// This avoids iterating through the `PROBLEMS_DATA` slice on every lookup.
-static PROBLEM_MAP: Lazy<HashMap<&str, &Problem>> = Lazy::new(|| {
-    let mut m = HashMap::new();
-    for p in PROBLEMS_DATA.iter() {
-        m.insert(p.problem.as_str(), p);
+impl Registry {
+    fn new(problems: Vec<Problem>) -> Self {
+        let all: &'static [Problem] = Box::leak(problems.into_boxed_slice());
+        let mut map = HashMap::new();
+        for p in all {
+            map.insert(p.problem.to_lowercase(), p);
+        }
+        for &(alias, name) in ALIASES {
+            if let Some(&p) = map.get(name) {
+                map.insert(alias.to_string(), p);
+            }
+        }
+        Self { all, map }
+    }
+
+    /// See [`get_problem`].
+    fn get(&self, name: &str) -> Option<&'static Problem> {
+        self.map.get(&name.trim().to_lowercase()).copied()
     }
-    m
-});
+
+    /// See [`register`].
+    fn register(&mut self, problems: &[Problem]) -> usize {
+        let mut all = self.all.to_vec();
+        let mut changed = 0;
+        for p in problems {
+            match all
+                .iter_mut()
+                .find(|q| q.problem.eq_ignore_ascii_case(&p.problem))
+            {
+                Some(q) if q.size == p.size => {}
+                Some(q) => {
+                    q.size = p.size;
+                    changed += 1;
+                }
+                None => {
+                    all.push(p.clone());
+                    changed += 1;
+                }
+            }
+        }
+        if changed > 0 {
+            *self = Registry::new(all);
+        }
+        changed
+    }
+}
+
+static REGISTRY: Lazy<RwLock<Registry>> =
+    Lazy::new(|| RwLock::new(Registry::new(static_problems())));
+
+fn registry() -> RwLockReadGuard<'static, Registry> {
+    REGISTRY.read().unwrap()
+}
+
+/// Returns a slice containing all known contest problems.
+pub fn all_problems() -> &'static [Problem] {
+    registry().all
+}
+
+/// Merges `problems` into the registry: new names are appended and a known
+/// name takes the given size. Returns the number of problems added or changed.
+pub fn register(problems: &[Problem]) -> usize {
+    REGISTRY.write().unwrap().register(problems)
+}
+
+/// Fetches the problem list and merges it into the registry (see
+/// [`register`]). The list is read from `UNAGI_PROBLEMS_URL` if set (e.g. a
+/// copy on GCS), otherwise from the contest server.
+#[cfg(feature = "reqwest")]
+pub fn refresh_from_api() -> anyhow::Result<usize> {
+    use crate::client::SendTrackedBlocking;
+    use anyhow::Context;
+
+    let problems = match std::env::var("UNAGI_PROBLEMS_URL") {
+        Ok(url) => crate::client::BLOCKING_CLIENT
+            .get(&url)
+            .send_tracked()
+            .and_then(|res| res.error_for_status())
+            .with_context(|| format!("Failed to GET {}", url))?
+            .json::<Vec<Problem>>()
+            .with_context(|| format!("Failed to parse problem list from {}", url))?,
+        Err(_) => crate::api::problems()?,
+    };
+    Ok(register(&problems))
+}
 
 /// Looks up a problem by its name.
 ///
+/// The lookup is case-insensitive and accepts the spellings in [`ALIASES`].
+///
 /// # Arguments
 /// * `name` - The name of the problem to find.
 ///
@@ -52,7 +157,92 @@ static PROBLEM_MAP: Lazy<HashMap<&str, &Problem>> = Lazy::new(|| {
 /// An `Option<&'static Problem>` which is `Some` if a problem with the
 /// given name exists, and `None` otherwise.
 pub fn get_problem(name: &str) -> Option<&'static Problem> {
-    PROBLEM_MAP.get(name).copied()
+    registry().get(name)
+}
+
+/// Error returned by [`find_problem`] for an unknown name.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct UnknownProblem {
+    /// The name as given.
+    pub name: String,
+    /// The closest official problem name, if any is reasonably close.
+    pub suggestion: Option<&'static str>,
+}
+
+impl std::fmt::Display for UnknownProblem {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "unknown problem: {}", self.name)?;
+        match self.suggestion {
+            Some(s) => write!(f, " (did you mean {}?)", s),
+            None => write!(
+                f,
+                " (known problems: {})",
+                all_problems()
+                    .iter()
+                    .map(|p| p.problem.as_str())
+                    .collect::<Vec<_>>()
+                    .join(", ")
+            ),
+        }
+    }
+}
+
+impl std::error::Error for UnknownProblem {}
+
+/// Like [`get_problem`], but explains a failed lookup with a did-you-mean
+/// suggestion instead of returning `None`.
+pub fn find_problem(name: &str) -> Result<&'static Problem, UnknownProblem> {
+    get_problem(name).ok_or_else(|| UnknownProblem {
+        name: name.to_string(),
+        suggestion: suggest(name),
+    })
+}
+
+/// Like [`find_problem`], but on a miss refreshes the registry from the
+/// server (see [`refresh_from_api`]) and looks again, so that a problem
+/// published after this binary was built can still be selected.
+pub fn find_problem_or_refresh(name: &str) -> Result<&'static Problem, UnknownProblem> {
+    let err = match find_problem(name) {
+        Ok(p) => return Ok(p),
+        Err(e) => e,
+    };
+    #[cfg(feature = "reqwest")]
+    match refresh_from_api() {
+        Ok(0) => {}
+        Ok(_) => return find_problem(name),
+        Err(e) => eprintln!("Failed to refresh the problem list: {:#}", e),
+    }
+    Err(err)
+}
+
+/// Returns the official name closest to `name` in edit distance, if within a
+/// third of its length (at least 2 edits).
+fn suggest(name: &str) -> Option<&'static str> {
+    let name = name.trim().to_lowercase();
+    let max_dist = (name.chars().count() / 3).max(2);
+    registry()
+        .map
+        .iter()
+        .map(|(key, p)| (edit_distance(&name, key), p.problem.as_str()))
+        .filter(|&(d, _)| d <= max_dist)
+        .min()
+        .map(|(_, p)| p)
+}
+
+/// Levenshtein distance between two strings.
+fn edit_distance(a: &str, b: &str) -> usize {
+    let b = b.chars().collect::<Vec<_>>();
+    let mut prev = (0..=b.len()).collect::<Vec<_>>();
+    for (i, ca) in a.chars().enumerate() {
+        let mut cur = vec![i + 1; b.len() + 1];
+        for (j, &cb) in b.iter().enumerate() {
+            cur[j + 1] = (prev[j] + (ca != cb) as usize)
+                .min(prev[j + 1] + 1)
+                .min(cur[j] + 1);
+        }
+        prev = cur;
+    }
+    prev[b.len()]
 }
 
 #[cfg(test)]
@@ -84,4 +274,56 @@ mod tests {
         assert_eq!(p.size, 30);
         assert!(get_problem("unknown").is_none());
     }
+
+    #[test]
+    fn get_problem_accepts_case_and_aliases() {
+        assert_eq!(get_problem("Quintus").unwrap().problem, "quintus");
+        assert_eq!(get_problem("ALEF").unwrap().problem, "aleph");
+        assert_eq!(get_problem("vav").unwrap().size, 18);
+    }
+
+    #[test]
+    fn register_adds_and_resizes_problems() {
+        // A registry of its own, so other tests keep seeing the bundled list.
+        let mut registry = Registry::new(static_problems());
+        let added = registry.register(&[
+            Problem {
+                problem: "novum".to_string(),
+                size: 42,
+            },
+            Problem {
+                problem: "probatio".to_string(),
+                size: 3,
+            },
+        ]);
+        assert_eq!(added, 1);
+        assert_eq!(registry.get("NOVUM").unwrap().size, 42);
+        assert!(registry.all.iter().any(|p| p.problem == "novum"));
+        assert!(get_problem("novum").is_none());
+        // Known problems and aliases survive the rebuild.
+        assert_eq!(registry.get("alef").unwrap().problem, "aleph");
+        assert_eq!(
+            registry.register(&[Problem {
+                problem: "novum".to_string(),
+                size: 42,
+            }]),
+            0
+        );
+        assert_eq!(
+            registry.register(&[Problem {
+                problem: "Novum".to_string(),
+                size: 43,
+            }]),
+            1
+        );
+        assert_eq!(registry.get("novum").unwrap().size, 43);
+    }
+
+    #[test]
+    fn find_problem_suggests_close_names() {
+        let err = find_problem("quintis").unwrap_err();
+        assert_eq!(err.suggestion, Some("quintus"));
+        assert!(err.to_string().contains("did you mean quintus?"));
+        assert_eq!(find_problem("xyzzy").unwrap_err().suggestion, None);
+    }
 }
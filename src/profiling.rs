@@ -0,0 +1,149 @@
+//! # Phase Profiling
+//!
+//! Lightweight wall-clock accounting for the expensive phases of the no-marks
+//! pipeline (CNF construction steps, SAT solving). Code marks a phase with
+//!
+//! ```ignore
+//! let _p = profiling::scope("sbp");
+//! ```
+//!
+//! and the time until the guard is dropped is added to that phase. Binaries call
+//! [`print_summary`] at the end. Without the `profiling` feature every call is
+//! a no-op, so the hooks can stay in release builds.
+
+#[cfg(feature = "profiling")]
+mod imp {
+    use std::sync::Mutex;
+    use std::time::{Duration, Instant};
+
+    /// (phase, total time, number of scopes), in order of first use.
+    static PHASES: Mutex<Vec<(&'static str, Duration, usize)>> = Mutex::new(Vec::new());
+
+    pub struct Scope {
+        name: &'static str,
+        start: Instant,
+    }
+
+    impl Drop for Scope {
+        fn drop(&mut self) {
+            let elapsed = self.start.elapsed();
+            let mut phases = PHASES.lock().unwrap();
+            match phases.iter_mut().find(|(n, _, _)| *n == self.name) {
+                Some((_, total, count)) => {
+                    *total += elapsed;
+                    *count += 1;
+                }
+                None => phases.push((self.name, elapsed, 1)),
+            }
+        }
+    }
+
+    pub fn scope(name: &'static str) -> Scope {
+        Scope {
+            name,
+            start: Instant::now(),
+        }
+    }
+
+    pub fn phases() -> Vec<(&'static str, Duration, usize)> {
+        PHASES.lock().unwrap().clone()
+    }
+
+    pub fn reset() {
+        PHASES.lock().unwrap().clear();
+    }
+}
+
+#[cfg(not(feature = "profiling"))]
+mod imp {
+    use std::time::Duration;
+
+    pub struct Scope;
+
+    #[inline(always)]
+    pub fn scope(_name: &'static str) -> Scope {
+        Scope
+    }
+
+    pub fn phases() -> Vec<(&'static str, Duration, usize)> {
+        vec![]
+    }
+
+    pub fn reset() {}
+}
+
+pub use imp::Scope;
+
+/// Whether the crate was built with the `profiling` feature.
+pub const ENABLED: bool = cfg!(feature = "profiling");
+
+/// Starts timing `name`; the time is recorded when the guard is dropped.
+#[inline(always)]
+pub fn scope(name: &'static str) -> Scope {
+    imp::scope(name)
+}
+
+/// Recorded phases as `(name, total time, number of scopes)`, in order of first use.
+pub fn phases() -> Vec<(&'static str, std::time::Duration, usize)> {
+    imp::phases()
+}
+
+/// Forgets everything recorded so far.
+pub fn reset() {
+    imp::reset()
+}
+
+/// Table of the recorded phases with their share of the total, or an empty
+/// string if nothing was recorded.
+pub fn summary() -> String {
+    let phases = phases();
+    if phases.is_empty() {
+        return String::new();
+    }
+    let total: f64 = phases.iter().map(|(_, t, _)| t.as_secs_f64()).sum();
+    let width = phases.iter().map(|(n, _, _)| n.len()).max().unwrap();
+    let mut out = String::from("profile:\n");
+    for (name, t, count) in &phases {
+        out.push_str(&format!(
+            "  {:<width$}  {:>9.3}s  {:>5.1}%  x{}\n",
+            name,
+            t.as_secs_f64(),
+            100.0 * t.as_secs_f64() / total.max(1e-9),
+            count,
+        ));
+    }
+    out
+}
+
+/// Prints [`summary`] to stderr (nothing without the `profiling` feature).
+pub fn print_summary() {
+    let s = summary();
+    if !s.is_empty() {
+        eprint!("{}", s);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn scopes_accumulate_only_when_enabled() {
+        {
+            let _p = scope("profiling-test");
+        }
+        {
+            let _p = scope("profiling-test");
+        }
+        let recorded = phases()
+            .into_iter()
+            .find(|(n, _, _)| *n == "profiling-test");
+        if ENABLED {
+            assert_eq!(recorded.unwrap().2, 2);
+            assert!(summary().contains("profiling-test"));
+        } else {
+            assert!(recorded.is_none());
+            assert_eq!(summary(), "");
+        }
+    }
+}
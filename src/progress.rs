@@ -0,0 +1,137 @@
+//! # Solver Progress Events
+//!
+//! Structured progress lines on stdout, in the same `<UNAGI>: {json}` form the
+//! executor already scans for scores:
+//!
+//! ```text
+//! <UNAGI>: {"event":"explore","elapsed":0.41,"queries":1,"cost":2}
+//! <UNAGI>: {"event":"phase","elapsed":1.93,"phase":"cnf","vars":48211,"clauses":902114}
+//! ```
+//!
+//! Every event has `event` and `elapsed` (seconds since the first event of the
+//! process). Events never carry a `score` key, so they do not disturb score
+//! extraction; the executor keeps the last one as `progress` in the task
+//! diagnostics. Set `UNAGI_PROGRESS=0` to silence them.
+
+use serde_json::{Map, Value};
+use std::sync::OnceLock;
+use std::time::Instant;
+
+/// Line prefix shared with the executor's score lines.
+pub const PREFIX: &str = "<UNAGI>:";
+
+fn start() -> Instant {
+    static START: OnceLock<Instant> = OnceLock::new();
+    *START.get_or_init(Instant::now)
+}
+
+/// Whether events are printed (`UNAGI_PROGRESS` is not `0`).
+pub fn enabled() -> bool {
+    static ENABLED: OnceLock<bool> = OnceLock::new();
+    *ENABLED.get_or_init(|| std::env::var("UNAGI_PROGRESS").map_or(true, |v| v != "0"))
+}
+
+/// Builds the event object; `fields` must be a JSON object (anything else is ignored).
+pub fn event(name: &str, elapsed: f64, fields: Value) -> Value {
+    let mut obj = Map::new();
+    obj.insert("event".to_string(), name.into());
+    obj.insert(
+        "elapsed".to_string(),
+        ((elapsed * 1000.0).round() / 1000.0).into(),
+    );
+    if let Value::Object(fields) = fields {
+        for (k, v) in fields {
+            if k != "score" {
+                obj.insert(k, v);
+            }
+        }
+    }
+    Value::Object(obj)
+}
+
+/// Prints one event line to stdout.
+pub fn emit(name: &str, fields: Value) {
+    if !enabled() {
+        return;
+    }
+    let elapsed = start().elapsed().as_secs_f64();
+    println!("{} {}", PREFIX, event(name, elapsed, fields));
+}
+
+/// Shorthand for a `phase` event.
+pub fn phase(phase: &str, mut fields: Value) {
+    if let Value::Object(obj) = &mut fields {
+        obj.insert("phase".to_string(), phase.into());
+    }
+    emit("phase", fields);
+}
+
+/// Parses a progress line; score lines and other output give `None`.
+pub fn parse(line: &str) -> Option<Value> {
+    let rest = line.trim_start().strip_prefix(PREFIX)?;
+    let value: Value = serde_json::from_str(rest.trim()).ok()?;
+    value.get("event")?;
+    Some(value)
+}
+
+/// A one-line summary of an event for the task dashboards, e.g.
+/// `cnf vars=48211 clauses=902114 (1.9s)`: the phase (or the event name for
+/// other events) followed by its scalar fields.
+pub fn summary(event: &Value) -> String {
+    let name = event
+        .get("phase")
+        .or_else(|| event.get("event"))
+        .and_then(Value::as_str)
+        .unwrap_or("?");
+    let mut parts = vec![name.to_string()];
+    if let Value::Object(obj) = event {
+        for (k, v) in obj {
+            if matches!(k.as_str(), "event" | "phase" | "elapsed") {
+                continue;
+            }
+            match v {
+                Value::String(s) => parts.push(format!("{}={}", k, s)),
+                Value::Number(_) | Value::Bool(_) => parts.push(format!("{}={}", k, v)),
+                _ => {}
+            }
+        }
+    }
+    if let Some(elapsed) = event.get("elapsed").and_then(Value::as_f64) {
+        parts.push(format!("({:.1}s)", elapsed));
+    }
+    parts.join(" ")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn events_round_trip_and_never_carry_scores() {
+        let e = event(
+            "phase",
+            1.23456,
+            json!({"phase": "cnf", "clauses": 10, "score": 5}),
+        );
+        assert_eq!(
+            e,
+            json!({"event": "phase", "elapsed": 1.235, "phase": "cnf", "clauses": 10})
+        );
+        assert_eq!(parse(&format!("{} {}", PREFIX, e)), Some(e));
+        assert_eq!(parse(r#"<UNAGI>: {"score": 3}"#), None);
+        assert_eq!(parse("explore 1"), None);
+    }
+
+    #[test]
+    fn summaries_name_the_phase_and_skip_nested_fields() {
+        let e = event(
+            "phase",
+            1.93,
+            json!({"phase": "cnf", "vars": 48211, "amo": {"pairwise": {}}}),
+        );
+        assert_eq!(summary(&e), "cnf vars=48211 (1.9s)");
+        let e = event("explore", 0.41, json!({"queries": 1, "cost": 2}));
+        assert_eq!(summary(&e), "explore queries=1 cost=2 (0.4s)");
+    }
+}
@@ -1,6 +1,22 @@
-pub fn get_plan(n_rooms: usize) -> Vec<usize> {
-    match n_rooms {
-        48 => vec![
+//! # Exploration Routes
+//!
+//! Single-plan door sequences found offline, keyed by size and length: plans
+//! of `6 * n` doors from the coverage-greedy search (`iwiwi_routing_6n`), and
+//! [`long_plan`]s of `18 * n` doors for every official size, evolved for 24
+//! and 30 rooms and made with [`planner::coverage_greedy_plan`] (1000 maps,
+//! seeded with the size) for the others. Also the [`planner`] module that
+//! generates plans at run time, the [`belief`] module that plans around the
+//! passages of a partly known map, and [`compress`], which merges probes that
+//! share prefixes before they are sent.
+
+pub mod belief;
+pub mod compress;
+pub mod planner;
+
+/// Precomputed plan of `len` doors for `n_rooms`, if the table has one.
+pub fn table_plan(n_rooms: usize, len: usize) -> Option<Vec<usize>> {
+    Some(match (n_rooms, len) {
+        (48, 288) => vec![
             3, 2, 4, 5, 0, 1, 5, 4, 1, 0, 2, 3, 4, 2, 5, 1, 3, 0, 0, 5, 2, 1, 4, 3, 1, 2, 0, 4, 5,
             3, 4, 0, 3, 5, 2, 2, 1, 1, 5, 3, 0, 4, 1, 2, 4, 0, 5, 4, 3, 3, 0, 1, 4, 2, 2, 5, 5, 0,
             3, 1, 1, 3, 2, 0, 5, 4, 4, 5, 1, 0, 0, 2, 3, 1, 5, 5, 2, 4, 1, 3, 3, 5, 0, 4, 4, 2, 1,
@@ -12,7 +28,7 @@ pub fn get_plan(n_rooms: usize) -> Vec<usize> {
             4, 3, 3, 4, 5, 1, 2, 0, 0, 2, 5, 3, 1, 2, 3, 4, 1, 5, 4, 0, 3, 1, 0, 5, 5, 1, 2, 5, 0,
             0, 4, 2, 3, 3, 2, 5, 1, 1, 4, 4, 0, 1, 3, 3, 4, 4, 2, 0, 3, 2, 5, 5, 1, 4, 2, 1,
         ],
-        36 => vec![
+        (36, 216) => vec![
             3, 5, 4, 1, 0, 2, 1, 4, 5, 3, 2, 0, 1, 2, 3, 4, 0, 5, 5, 0, 4, 3, 1, 5, 2, 1, 3, 0, 4,
             2, 5, 1, 2, 4, 0, 3, 3, 5, 1, 4, 4, 2, 2, 0, 0, 1, 1, 5, 3, 4, 5, 0, 2, 3, 0, 5, 2, 4,
             3, 2, 1, 0, 3, 1, 2, 5, 5, 4, 4, 1, 1, 3, 0, 0, 3, 2, 4, 1, 5, 5, 2, 0, 4, 4, 5, 1, 3,
@@ -22,7 +38,7 @@ pub fn get_plan(n_rooms: usize) -> Vec<usize> {
             2, 1, 1, 4, 5, 5, 4, 0, 0, 3, 4, 2, 0, 4, 5, 0, 3, 3, 1, 0, 4, 1, 5, 3, 3, 2, 2, 5, 1,
             2, 0, 5, 3, 4, 1, 5, 4, 0, 3, 2, 2, 3,
         ],
-        72 => vec![
+        (72, 432) => vec![
             0, 4, 3, 5, 1, 2, 5, 3, 4, 0, 2, 1, 5, 2, 3, 4, 1, 3, 2, 0, 4, 5, 0, 3, 1, 4, 5, 2, 2,
             1, 0, 5, 3, 0, 1, 2, 4, 4, 2, 3, 3, 1, 5, 5, 4, 0, 0, 1, 1, 4, 3, 2, 2, 0, 5, 5, 0, 3,
             2, 4, 1, 1, 3, 5, 4, 2, 5, 1, 0, 3, 3, 0, 4, 4, 1, 5, 0, 2, 3, 0, 2, 4, 5, 3, 1, 2, 0,
@@ -39,7 +55,7 @@ pub fn get_plan(n_rooms: usize) -> Vec<usize> {
             5, 2, 2, 0, 0, 2, 5, 3, 1, 1, 4, 2, 0, 0, 1, 5, 3, 2, 4, 4, 3, 1, 4, 5, 1, 3, 4, 2, 4,
             1, 3, 0, 1, 2, 2, 4, 3, 3, 2, 5, 1, 1, 5, 2, 0, 0, 1, 4, 3, 5, 4, 0, 2, 3, 1,
         ],
-        90 => vec![
+        (90, 540) => vec![
             1, 4, 3, 0, 2, 5, 4, 2, 0, 3, 5, 1, 3, 4, 1, 5, 2, 0, 5, 3, 1, 2, 4, 0, 0, 4, 3, 2, 1,
             1, 5, 0, 4, 5, 2, 3, 3, 4, 0, 1, 4, 2, 2, 5, 1, 0, 5, 4, 0, 3, 3, 2, 4, 5, 5, 0, 1, 2,
             3, 1, 0, 4, 1, 3, 5, 0, 2, 1, 3, 4, 4, 2, 5, 3, 0, 5, 2, 2, 1, 1, 3, 0, 0, 4, 4, 3, 5,
@@ -60,12 +76,12 @@ pub fn get_plan(n_rooms: usize) -> Vec<usize> {
             4, 2, 2, 3, 3, 0, 5, 4, 4, 0, 1, 3, 0, 5, 1, 1, 5, 3, 4, 3, 5, 1, 2, 3, 1, 4, 3, 5, 0,
             4, 5, 0, 3, 2, 2, 4, 0, 2, 4, 0, 3, 5, 5, 2, 0, 4, 2,
         ],
-        12 => vec![
+        (12, 72) => vec![
             4, 0, 5, 3, 1, 2, 3, 5, 2, 1, 0, 2, 5, 4, 1, 3, 4, 5, 0, 4, 2, 0, 3, 5, 1, 1, 4, 3, 0,
             2, 2, 4, 1, 5, 3, 3, 2, 0, 0, 1, 5, 5, 4, 2, 3, 4, 0, 0, 5, 1, 2, 4, 3, 1, 4, 5, 0, 3,
             2, 2, 1, 1, 3, 0, 4, 4, 2, 5, 1, 0, 5, 2,
         ],
-        60 => vec![
+        (60, 360) => vec![
             3, 1, 0, 2, 5, 4, 2, 0, 1, 3, 4, 5, 2, 4, 3, 0, 5, 1, 2, 3, 1, 4, 0, 0, 3, 5, 5, 2, 2,
             1, 4, 4, 1, 5, 3, 2, 5, 0, 4, 1, 0, 3, 3, 5, 4, 0, 1, 2, 2, 4, 5, 3, 0, 1, 1, 5, 2, 0,
             4, 2, 3, 5, 1, 3, 0, 4, 4, 0, 2, 1, 5, 5, 3, 4, 1, 2, 0, 3, 2, 0, 5, 4, 3, 1, 4, 5, 0,
@@ -80,20 +96,20 @@ pub fn get_plan(n_rooms: usize) -> Vec<usize> {
             2, 0, 4, 3, 0, 2, 2, 5, 1, 1, 3, 3, 5, 0, 4, 2, 0, 1, 5, 4, 3, 2, 5, 1, 4, 2, 2, 5, 3,
             4, 5, 1, 0, 0, 1, 4, 2, 2, 4, 0, 5,
         ],
-        24 => vec![
+        (24, 144) => vec![
             5, 2, 4, 1, 3, 0, 1, 4, 0, 3, 2, 5, 1, 0, 4, 2, 3, 5, 0, 2, 1, 5, 4, 3, 3, 1, 2, 0, 4,
             4, 5, 5, 3, 2, 0, 1, 1, 0, 3, 4, 2, 2, 5, 3, 0, 5, 4, 4, 1, 0, 2, 5, 0, 3, 1, 1, 3, 5,
             2, 3, 4, 0, 0, 5, 1, 4, 3, 2, 2, 1, 4, 5, 0, 0, 1, 2, 4, 3, 5, 5, 3, 4, 4, 2, 1, 1, 5,
             3, 3, 0, 2, 2, 4, 0, 5, 2, 3, 1, 5, 5, 2, 0, 4, 1, 3, 3, 5, 4, 0, 2, 4, 5, 1, 1, 2, 2,
             3, 0, 0, 3, 1, 4, 4, 0, 1, 3, 4, 5, 5, 1, 0, 2, 1, 0, 4, 3, 0, 1, 2, 5, 4, 3, 2, 1,
         ],
-        18 => vec![
+        (18, 108) => vec![
             4, 2, 3, 1, 0, 5, 2, 0, 1, 3, 5, 4, 3, 2, 4, 5, 0, 2, 5, 1, 4, 0, 3, 4, 1, 1, 5, 3, 0,
             2, 2, 0, 4, 3, 1, 5, 5, 1, 2, 4, 0, 0, 3, 2, 1, 3, 0, 5, 3, 4, 4, 1, 2, 5, 0, 4, 2, 1,
             4, 5, 3, 3, 5, 0, 1, 2, 2, 1, 0, 3, 3, 4, 5, 2, 3, 0, 0, 5, 4, 1, 5, 2, 1, 1, 3, 4, 0,
             2, 4, 4, 5, 1, 3, 2, 2, 5, 5, 3, 1, 1, 0, 4, 2, 0, 0, 3, 5, 5,
         ],
-        54 => vec![
+        (54, 324) => vec![
             3, 4, 2, 1, 0, 2, 5, 0, 4, 5, 1, 3, 2, 4, 0, 1, 5, 2, 3, 5, 4, 3, 1, 2, 0, 0, 5, 1, 4,
             4, 1, 5, 3, 0, 2, 2, 0, 3, 1, 4, 3, 5, 5, 0, 1, 2, 4, 1, 3, 3, 0, 4, 2, 5, 4, 0, 5, 2,
             1, 1, 0, 3, 4, 5, 3, 2, 1, 4, 2, 3, 0, 5, 5, 2, 4, 1, 1, 5, 0, 3, 2, 0, 4, 4, 0, 2, 3,
@@ -107,6 +123,356 @@ pub fn get_plan(n_rooms: usize) -> Vec<usize> {
             2, 2, 5, 0, 1, 1, 5, 3, 3, 0, 1, 4, 5, 0, 3, 5, 1, 3, 3, 2, 0, 0, 2, 5, 4, 1, 3, 0, 5,
             5, 4, 2, 3, 1,
         ],
-        _ => panic!(),
+        (3, 54) => vec![
+            4, 2, 5, 0, 1, 3, 3, 1, 5, 4, 0, 3, 2, 1, 2, 0, 1, 4, 5, 5, 2, 3, 0, 2, 4, 1, 0, 5, 3,
+            4, 4, 5, 1, 0, 4, 3, 2, 3, 1, 1, 5, 2, 5, 0, 2, 0, 3, 0, 5, 4, 1, 5, 3, 4,
+        ],
+        (6, 108) => vec![
+            2, 0, 3, 1, 4, 0, 5, 1, 3, 4, 5, 3, 2, 1, 0, 4, 2, 3, 5, 0, 1, 5, 2, 2, 5, 1, 4, 4, 1,
+            2, 3, 3, 0, 0, 3, 3, 1, 5, 4, 0, 3, 4, 2, 1, 5, 1, 0, 0, 3, 2, 4, 5, 5, 0, 2, 2, 4, 1,
+            1, 3, 5, 4, 3, 2, 0, 5, 5, 4, 4, 3, 0, 1, 2, 4, 3, 0, 5, 3, 2, 5, 1, 4, 1, 1, 5, 0, 0,
+            2, 3, 0, 2, 5, 3, 5, 2, 3, 4, 1, 2, 4, 4, 2, 2, 5, 3, 1, 0, 3,
+        ],
+        (12, 216) => vec![
+            0, 2, 4, 5, 1, 1, 3, 5, 4, 3, 2, 0, 3, 4, 2, 1, 5, 0, 0, 5, 3, 1, 4, 4, 2, 3, 2, 5, 1,
+            0, 3, 1, 4, 0, 5, 5, 4, 1, 2, 3, 3, 2, 4, 0, 0, 1, 4, 1, 5, 0, 4, 5, 5, 2, 3, 5, 2, 0,
+            3, 0, 2, 4, 2, 5, 5, 1, 2, 0, 5, 1, 0, 5, 4, 4, 2, 2, 4, 3, 2, 1, 1, 3, 3, 0, 4, 1, 5,
+            3, 3, 4, 2, 5, 3, 2, 2, 0, 1, 4, 0, 3, 5, 4, 1, 0, 0, 2, 3, 4, 3, 1, 1, 0, 1, 2, 5, 3,
+            0, 1, 5, 0, 2, 2, 1, 3, 4, 5, 3, 3, 5, 4, 0, 4, 5, 2, 2, 4, 3, 0, 4, 4, 1, 0, 0, 3, 1,
+            2, 4, 4, 5, 1, 1, 2, 1, 5, 5, 0, 2, 1, 3, 0, 5, 3, 0, 2, 1, 4, 3, 2, 4, 5, 5, 0, 0, 2,
+            5, 4, 4, 1, 3, 0, 1, 2, 3, 1, 4, 5, 4, 3, 0, 3, 1, 3, 5, 5, 3, 1, 0, 1, 4, 4, 2, 4, 3,
+            4, 2, 5, 0, 1, 2, 0, 1, 2, 2, 1, 5, 5,
+        ],
+        (18, 324) => vec![
+            3, 5, 2, 1, 5, 0, 4, 4, 2, 5, 1, 0, 3, 5, 4, 3, 1, 4, 0, 1, 3, 2, 4, 0, 2, 2, 3, 0, 4,
+            5, 1, 2, 0, 0, 3, 1, 1, 3, 3, 4, 0, 5, 5, 0, 2, 4, 1, 5, 3, 2, 5, 5, 2, 1, 1, 0, 4, 4,
+            1, 5, 1, 3, 4, 5, 0, 2, 3, 4, 2, 5, 2, 3, 1, 0, 0, 0, 5, 1, 3, 2, 2, 4, 4, 3, 3, 5, 1,
+            2, 2, 0, 1, 4, 5, 3, 3, 0, 0, 4, 2, 1, 0, 1, 3, 4, 3, 5, 0, 0, 1, 2, 3, 4, 5, 5, 2, 2,
+            1, 5, 5, 0, 3, 4, 1, 2, 2, 5, 1, 2, 4, 3, 5, 4, 5, 4, 1, 4, 4, 4, 1, 1, 2, 0, 5, 3, 0,
+            2, 0, 4, 3, 2, 1, 3, 3, 1, 0, 2, 5, 4, 2, 0, 5, 2, 0, 4, 3, 1, 2, 5, 0, 1, 5, 3, 0, 1,
+            0, 3, 5, 4, 0, 1, 4, 0, 5, 3, 3, 4, 4, 1, 5, 5, 1, 4, 0, 3, 2, 4, 4, 0, 2, 5, 3, 0, 2,
+            1, 4, 5, 2, 2, 4, 5, 3, 2, 0, 2, 1, 1, 1, 2, 0, 2, 0, 5, 4, 3, 5, 1, 4, 3, 3, 1, 1, 3,
+            4, 1, 2, 5, 4, 2, 3, 0, 5, 0, 0, 3, 3, 3, 5, 0, 1, 5, 4, 2, 4, 1, 3, 1, 0, 4, 5, 2, 2,
+            5, 0, 4, 5, 1, 0, 2, 3, 4, 5, 3, 3, 5, 2, 1, 4, 4, 2, 0, 3, 2, 3, 0, 1, 1, 2, 4, 0, 0,
+            0, 3, 1, 4, 2, 2, 1, 5, 2, 1, 0, 0, 2, 1, 3, 0, 5, 5, 4, 1, 3, 2, 5, 3, 4, 4, 2, 2, 0,
+            0, 4, 1, 1, 1,
+        ],
+        (24, 432) => vec![
+            0, 5, 3, 4, 2, 1, 1, 2, 4, 3, 5, 5, 0, 0, 3, 1, 4, 5, 2, 2, 3, 0, 4, 4, 1, 3, 2, 1, 0,
+            2, 5, 4, 0, 1, 5, 3, 3, 5, 1, 2, 0, 3, 4, 4, 5, 0, 2, 3, 1, 1, 4, 2, 0, 0, 5, 5, 4, 3,
+            2, 4, 1, 2, 5, 1, 3, 3, 0, 5, 1, 0, 4, 2, 2, 1, 5, 0, 1, 4, 0, 3, 3, 1, 5, 2, 4, 4, 3,
+            5, 2, 0, 4, 1, 1, 3, 2, 5, 5, 3, 0, 2, 4, 4, 0, 0, 2, 2, 3, 4, 5, 1, 1, 0, 3, 2, 0, 5,
+            4, 1, 5, 4, 2, 3, 0, 1, 3, 4, 5, 5, 2, 1, 0, 3, 5, 0, 1, 2, 2, 1, 4, 3, 3, 4, 0, 2, 5,
+            3, 2, 5, 1, 4, 3, 1, 0, 0, 4, 4, 1, 5, 2, 3, 3, 2, 5, 0, 0, 1, 4, 4, 5, 5, 1, 2, 4, 0,
+            5, 3, 0, 1, 2, 3, 1, 5, 3, 4, 1, 0, 5, 2, 1, 3, 5, 4, 2, 2, 0, 3, 3, 0, 4, 2, 0, 5, 2,
+            4, 1, 1, 5, 0, 4, 3, 0, 2, 1, 3, 3, 4, 5, 1, 4, 0, 1, 1, 5, 2, 2, 4, 0, 0, 5, 4, 3, 3,
+            5, 5, 3, 2, 2, 5, 0, 2, 4, 3, 1, 1, 0, 4, 3, 2, 0, 1, 5, 4, 4, 2, 3, 5, 1, 3, 4, 0, 2,
+            1, 0, 4, 5, 3, 1, 2, 3, 0, 5, 5, 4, 4, 2, 0, 0, 1, 1, 3, 4, 2, 5, 4, 1, 3, 5, 0, 3, 1,
+            4, 2, 2, 0, 5, 1, 1, 2, 2, 5, 0, 5, 3, 3, 1, 0, 3, 2, 4, 4, 0, 5, 5, 5, 2, 3, 4, 1, 3,
+            0, 0, 2, 1, 4, 4, 5, 0, 3, 2, 2, 5, 4, 5, 1, 2, 5, 3, 3, 0, 1, 5, 0, 0, 4, 3, 1, 2, 3,
+            1, 4, 1, 0, 1, 2, 4, 2, 1, 4, 5, 3, 2, 0, 2, 5, 1, 3, 0, 0, 5, 4, 3, 4, 0, 4, 5, 0, 1,
+            3, 3, 2, 2, 4, 4, 3, 1, 0, 2, 3, 5, 2, 3, 3, 1, 5, 5, 1, 4, 1, 2, 0, 0, 2, 4, 0, 3, 4,
+            1, 5, 5, 1, 0, 0, 3, 5, 1, 1, 1, 2, 0, 4, 2, 5, 5, 4, 0, 4, 4, 5, 2, 0, 3, 2,
+        ],
+        (30, 540) => vec![
+            4, 1, 3, 0, 2, 2, 5, 5, 1, 4, 0, 3, 3, 1, 5, 2, 0, 0, 4, 4, 2, 3, 5, 1, 1, 2, 4, 5, 3,
+            0, 5, 3, 2, 1, 0, 5, 4, 4, 1, 2, 5, 0, 3, 4, 2, 0, 1, 3, 4, 5, 0, 4, 3, 1, 2, 2, 1, 5,
+            0, 0, 2, 3, 5, 4, 0, 1, 3, 3, 2, 2, 4, 5, 5, 4, 1, 1, 0, 0, 5, 2, 4, 3, 0, 1, 1, 4, 2,
+            0, 3, 5, 5, 3, 1, 4, 3, 2, 2, 3, 4, 4, 0, 5, 2, 1, 5, 3, 1, 1, 3, 5, 0, 2, 4, 0, 0, 1,
+            5, 4, 2, 5, 1, 0, 4, 3, 3, 4, 0, 2, 5, 1, 2, 3, 3, 0, 4, 5, 2, 1, 1, 2, 0, 5, 3, 4, 1,
+            0, 3, 5, 2, 2, 4, 4, 5, 5, 0, 3, 1, 1, 4, 2, 3, 0, 0, 3, 2, 5, 4, 2, 1, 3, 5, 4, 3, 1,
+            4, 5, 2, 0, 5, 1, 0, 0, 2, 4, 1, 5, 0, 1, 2, 3, 4, 0, 5, 2, 3, 3, 2, 1, 5, 5, 4, 4, 3,
+            3, 0, 2, 1, 4, 5, 1, 1, 3, 2, 0, 4, 1, 0, 2, 5, 5, 3, 3, 0, 1, 4, 4, 0, 0, 3, 5, 1, 2,
+            2, 0, 4, 4, 0, 1, 1, 5, 3, 2, 4, 3, 2, 1, 3, 4, 2, 2, 5, 0, 4, 5, 1, 3, 0, 5, 5, 0, 2,
+            3, 1, 5, 4, 1, 2, 0, 3, 1, 0, 4, 5, 5, 2, 2, 4, 3, 3, 5, 0, 0, 1, 4, 2, 5, 3, 4, 1, 1,
+            5, 2, 0, 3, 4, 4, 2, 2, 3, 1, 1, 0, 4, 3, 5, 0, 3, 1, 0, 5, 4, 0, 2, 2, 1, 4, 3, 5, 1,
+            3, 2, 4, 4, 1, 5, 0, 0, 5, 5, 3, 0, 2, 0, 1, 4, 5, 1, 3, 3, 4, 2, 5, 5, 2, 3, 0, 1, 2,
+            4, 1, 2, 0, 3, 3, 4, 4, 3, 0, 0, 4, 2, 3, 1, 2, 5, 4, 4, 1, 1, 0, 2, 3, 0, 5, 2, 2, 1,
+            4, 5, 0, 0, 1, 3, 5, 4, 1, 0, 3, 2, 5, 3, 4, 5, 3, 2, 0, 1, 2, 1, 5, 4, 5, 0, 4, 2, 1,
+            0, 2, 1, 1, 3, 3, 5, 2, 4, 0, 5, 5, 1, 4, 3, 1, 5, 3, 4, 0, 1, 5, 4, 3, 0, 4, 4, 2, 2,
+            0, 0, 3, 3, 5, 5, 5, 2, 3, 4, 1, 1, 2, 0, 1, 4, 4, 5, 3, 3, 1, 3, 2, 2, 0, 0, 2, 5, 1,
+            4, 0, 5, 4, 2, 2, 5, 1, 0, 5, 0, 3, 3, 0, 0, 4, 3, 2, 3, 3, 1, 5, 5, 5, 0, 1, 1, 2, 1,
+            3, 4, 4, 2, 1, 4, 4, 1, 3, 5, 2, 5, 3, 2, 4, 0, 0, 5, 1, 1, 0, 2, 4, 1, 2, 4, 0, 2, 5,
+            4, 0, 5, 3, 1, 1, 3, 0, 4, 4, 3, 2, 2, 2, 1, 2, 3, 5,
+        ],
+        (36, 648) => vec![
+            2, 5, 3, 1, 0, 4, 2, 0, 1, 3, 5, 4, 4, 0, 2, 3, 0, 5, 1, 4, 5, 0, 3, 4, 4, 5, 5, 2, 1,
+            3, 0, 1, 2, 4, 0, 3, 5, 5, 1, 0, 3, 4, 2, 2, 0, 5, 1, 3, 2, 2, 5, 4, 1, 1, 5, 0, 3, 2,
+            4, 3, 1, 2, 3, 5, 2, 1, 0, 4, 3, 3, 1, 5, 1, 2, 4, 4, 2, 1, 4, 5, 1, 0, 0, 0, 2, 3, 4,
+            5, 3, 4, 0, 2, 5, 5, 4, 3, 2, 1, 5, 3, 3, 0, 0, 5, 0, 2, 0, 4, 1, 3, 3, 5, 3, 3, 4, 0,
+            2, 0, 1, 5, 4, 4, 0, 1, 1, 5, 4, 2, 3, 5, 1, 1, 0, 2, 3, 4, 1, 1, 3, 3, 4, 3, 0, 0, 0,
+            3, 3, 2, 0, 2, 2, 4, 1, 3, 4, 1, 4, 4, 0, 0, 2, 2, 5, 5, 3, 3, 1, 2, 5, 2, 1, 5, 3, 2,
+            5, 0, 0, 5, 0, 4, 2, 0, 4, 1, 5, 2, 3, 1, 4, 1, 3, 2, 4, 2, 2, 1, 5, 4, 3, 2, 0, 0, 1,
+            1, 3, 4, 4, 5, 3, 5, 2, 4, 5, 1, 0, 3, 3, 1, 4, 0, 2, 1, 4, 5, 2, 5, 5, 5, 0, 1, 1, 2,
+            3, 5, 4, 0, 4, 5, 2, 5, 1, 3, 5, 1, 5, 1, 2, 3, 1, 1, 1, 2, 5, 3, 3, 5, 0, 5, 3, 4, 1,
+            2, 2, 4, 3, 3, 2, 4, 0, 0, 3, 0, 0, 1, 4, 0, 5, 1, 4, 3, 2, 0, 5, 4, 1, 0, 2, 4, 3, 2,
+            1, 0, 5, 3, 0, 4, 4, 5, 4, 0, 3, 1, 4, 5, 2, 0, 1, 3, 0, 5, 4, 3, 2, 2, 3, 1, 1, 1, 0,
+            4, 4, 2, 2, 0, 3, 5, 2, 4, 1, 0, 5, 5, 0, 2, 1, 4, 3, 0, 1, 2, 2, 3, 0, 1, 5, 0, 2, 5,
+            1, 1, 0, 5, 2, 1, 5, 5, 0, 4, 3, 4, 4, 4, 2, 4, 0, 5, 4, 3, 1, 1, 2, 0, 4, 3, 5, 0, 3,
+            1, 0, 1, 5, 5, 4, 0, 1, 2, 5, 4, 4, 2, 0, 3, 4, 3, 3, 5, 2, 2, 5, 0, 0, 2, 0, 3, 3, 3,
+            4, 1, 1, 5, 4, 2, 2, 0, 0, 4, 3, 1, 5, 4, 5, 3, 1, 2, 3, 3, 5, 3, 1, 4, 2, 2, 4, 5, 4,
+            2, 1, 3, 0, 1, 3, 1, 0, 5, 5, 1, 1, 0, 0, 1, 2, 5, 0, 3, 4, 4, 2, 4, 4, 3, 1, 0, 5, 5,
+            2, 3, 5, 4, 0, 2, 4, 5, 3, 0, 3, 2, 4, 5, 2, 5, 4, 3, 3, 4, 4, 1, 0, 0, 0, 4, 3, 5, 1,
+            4, 1, 0, 2, 5, 4, 2, 4, 5, 3, 3, 5, 2, 0, 1, 5, 1, 2, 5, 3, 2, 3, 4, 0, 0, 2, 1, 1, 4,
+            1, 5, 0, 1, 3, 2, 5, 2, 0, 1, 1, 5, 2, 3, 0, 3, 5, 4, 2, 5, 2, 0, 4, 5, 1, 3, 4, 2, 2,
+            3, 0, 2, 1, 0, 0, 4, 4, 1, 5, 3, 2, 1, 2, 0, 2, 1, 3, 3, 5, 2, 3, 1, 1, 4, 2, 0, 0, 3,
+            0, 3, 3, 3, 5, 3, 2, 5, 5, 3, 0, 5, 3, 1, 4, 2, 4, 2, 5, 3, 2, 1, 0, 1, 4, 4, 4, 5, 3,
+            1, 1, 2, 4, 1, 1, 4, 1, 4, 3, 5, 0, 5, 5, 1, 2, 4, 1, 2, 0, 5, 2, 4, 2, 3, 0, 1, 1, 1,
+            0, 5, 2, 2, 1, 5, 0, 3, 0, 5,
+        ],
+        (48, 864) => vec![
+            3, 0, 5, 1, 4, 2, 2, 3, 1, 5, 4, 1, 0, 4, 3, 2, 5, 1, 2, 4, 0, 3, 5, 4, 4, 2, 2, 1, 3,
+            0, 0, 5, 3, 4, 4, 1, 2, 5, 0, 0, 3, 2, 1, 5, 5, 4, 3, 3, 0, 2, 4, 5, 1, 1, 5, 4, 2, 5,
+            0, 1, 5, 2, 1, 3, 3, 5, 2, 0, 0, 1, 1, 2, 0, 0, 4, 5, 2, 4, 0, 1, 4, 3, 1, 2, 2, 0, 3,
+            1, 1, 5, 4, 3, 5, 0, 2, 3, 0, 5, 2, 4, 3, 4, 0, 4, 1, 4, 4, 1, 3, 4, 5, 5, 1, 2, 3, 3,
+            2, 2, 0, 1, 4, 0, 3, 5, 5, 0, 2, 3, 5, 2, 3, 1, 2, 5, 0, 5, 4, 2, 1, 2, 5, 3, 4, 5, 0,
+            1, 2, 2, 4, 3, 0, 5, 4, 2, 5, 1, 3, 4, 4, 2, 0, 3, 3, 4, 0, 5, 3, 3, 2, 1, 5, 1, 1, 3,
+            0, 1, 2, 4, 0, 5, 5, 2, 3, 0, 5, 5, 1, 0, 1, 5, 4, 5, 1, 0, 0, 2, 2, 0, 0, 5, 4, 4, 5,
+            2, 3, 4, 4, 0, 1, 3, 3, 4, 5, 1, 3, 3, 4, 2, 1, 1, 0, 2, 3, 0, 1, 5, 1, 0, 4, 4, 1, 5,
+            5, 3, 2, 0, 0, 4, 2, 1, 3, 0, 2, 3, 5, 4, 2, 2, 4, 0, 1, 0, 3, 1, 3, 2, 3, 3, 1, 1, 0,
+            2, 1, 4, 1, 4, 4, 3, 0, 5, 5, 1, 0, 3, 4, 4, 0, 0, 2, 3, 1, 2, 0, 1, 1, 4, 2, 1, 5, 5,
+            3, 3, 5, 1, 2, 5, 4, 1, 4, 5, 5, 2, 4, 3, 3, 4, 1, 1, 0, 5, 2, 5, 0, 3, 0, 0, 0, 3, 2,
+            2, 4, 4, 5, 0, 1, 4, 0, 2, 2, 1, 5, 0, 2, 4, 1, 2, 3, 2, 0, 5, 3, 1, 0, 4, 0, 0, 2, 4,
+            4, 5, 3, 5, 0, 3, 4, 3, 1, 0, 2, 5, 5, 1, 5, 5, 3, 2, 3, 4, 1, 3, 0, 2, 1, 0, 4, 2, 3,
+            4, 5, 2, 1, 0, 3, 2, 0, 1, 3, 0, 3, 1, 4, 4, 2, 0, 5, 5, 0, 3, 5, 2, 5, 4, 1, 5, 3, 3,
+            4, 1, 1, 4, 5, 0, 3, 5, 2, 2, 5, 3, 0, 4, 3, 1, 1, 2, 3, 3, 2, 5, 4, 0, 2, 0, 4, 2, 5,
+            5, 4, 4, 1, 3, 5, 4, 4, 0, 0, 4, 1, 1, 5, 3, 2, 4, 3, 1, 3, 5, 5, 2, 0, 5, 1, 4, 4, 1,
+            1, 1, 0, 3, 2, 2, 0, 1, 5, 3, 1, 5, 0, 0, 5, 2, 2, 2, 1, 4, 5, 1, 3, 3, 0, 3, 3, 0, 4,
+            3, 5, 5, 0, 4, 1, 0, 0, 1, 5, 0, 5, 4, 3, 2, 1, 2, 2, 3, 4, 4, 1, 2, 4, 1, 0, 1, 2, 1,
+            5, 2, 3, 0, 2, 3, 4, 5, 3, 5, 1, 4, 0, 1, 3, 2, 4, 1, 2, 2, 0, 4, 0, 3, 4, 2, 1, 3, 5,
+            0, 5, 4, 5, 1, 4, 3, 0, 3, 3, 5, 3, 1, 4, 2, 5, 4, 4, 3, 5, 2, 4, 2, 0, 1, 3, 1, 1, 3,
+            2, 2, 2, 1, 0, 2, 4, 4, 4, 2, 2, 0, 1, 2, 0, 3, 4, 5, 5, 0, 5, 3, 1, 3, 2, 0, 5, 5, 0,
+            2, 5, 3, 1, 0, 3, 2, 5, 5, 4, 2, 0, 4, 5, 0, 4, 0, 0, 4, 3, 0, 5, 0, 3, 1, 3, 5, 3, 2,
+            2, 5, 1, 5, 3, 3, 4, 2, 2, 4, 1, 1, 2, 1, 5, 2, 2, 5, 0, 1, 4, 5, 4, 0, 1, 2, 5, 1, 0,
+            3, 3, 0, 0, 2, 5, 2, 3, 4, 2, 3, 0, 1, 3, 5, 1, 1, 5, 2, 1, 4, 5, 2, 0, 0, 1, 1, 4, 4,
+            2, 3, 2, 4, 3, 2, 3, 1, 4, 4, 0, 5, 2, 0, 4, 0, 5, 4, 3, 0, 1, 3, 2, 5, 4, 5, 1, 2, 3,
+            5, 1, 1, 4, 3, 3, 2, 1, 5, 0, 3, 0, 2, 0, 4, 4, 5, 3, 3, 2, 0, 4, 1, 3, 1, 5, 2, 4, 4,
+            0, 3, 1, 1, 1, 0, 5, 5, 5, 5, 2, 4, 3, 5, 4, 1, 0, 0, 4, 3, 4, 4, 0, 2, 5, 0, 3, 4, 2,
+            3, 5, 2, 2, 2, 1, 1, 1, 5, 3, 3, 1, 5, 1, 0, 0, 2, 4, 0, 2, 2, 3, 0, 0, 0, 5, 3, 3, 1,
+            2, 0, 0, 1, 3, 0, 5, 4, 1, 4, 4, 2, 3, 4, 2, 2, 0, 1, 4, 5, 5, 4, 1, 3, 4, 5, 5, 0, 3,
+            2, 2, 4, 1, 0, 0, 4, 0, 2, 0, 3, 4, 3, 5, 5, 4, 1, 2, 0, 3, 5, 1, 2,
+        ],
+        (54, 972) => vec![
+            3, 0, 2, 1, 4, 2, 1, 3, 5, 4, 4, 5, 2, 0, 5, 1, 1, 2, 3, 4, 0, 3, 1, 0, 3, 2, 5, 3, 4,
+            5, 1, 0, 5, 2, 3, 1, 4, 0, 1, 3, 5, 5, 0, 2, 5, 0, 0, 4, 1, 1, 3, 0, 4, 4, 1, 2, 5, 4,
+            3, 2, 2, 0, 4, 5, 2, 4, 3, 1, 5, 2, 0, 3, 5, 5, 4, 0, 1, 1, 4, 2, 2, 3, 1, 4, 3, 2, 4,
+            1, 2, 0, 0, 5, 5, 3, 1, 1, 2, 4, 4, 3, 3, 0, 4, 5, 5, 4, 4, 2, 3, 1, 0, 0, 4, 5, 1, 3,
+            3, 4, 0, 1, 0, 5, 2, 2, 0, 3, 5, 4, 2, 5, 1, 3, 0, 1, 5, 0, 1, 1, 4, 1, 1, 5, 3, 4, 1,
+            3, 2, 1, 0, 0, 2, 2, 4, 5, 5, 0, 3, 4, 4, 2, 0, 3, 3, 5, 2, 2, 1, 4, 4, 0, 2, 1, 1, 5,
+            0, 2, 0, 5, 3, 3, 3, 2, 1, 3, 2, 0, 1, 2, 4, 0, 3, 1, 5, 5, 4, 1, 5, 3, 0, 4, 3, 3, 3,
+            4, 5, 1, 0, 2, 5, 1, 2, 0, 2, 5, 3, 1, 0, 3, 5, 5, 1, 1, 0, 5, 3, 4, 0, 0, 1, 1, 2, 2,
+            0, 4, 4, 4, 0, 2, 2, 5, 5, 3, 2, 4, 3, 0, 1, 5, 4, 1, 2, 2, 0, 0, 4, 5, 1, 4, 5, 4, 0,
+            5, 3, 2, 4, 2, 2, 1, 5, 0, 3, 2, 3, 4, 2, 3, 4, 5, 5, 4, 1, 4, 4, 3, 1, 3, 3, 4, 0, 0,
+            4, 2, 3, 5, 3, 2, 5, 0, 3, 4, 1, 4, 4, 3, 2, 2, 3, 5, 5, 3, 2, 2, 1, 5, 1, 3, 4, 2, 0,
+            0, 1, 3, 0, 4, 3, 5, 3, 4, 0, 0, 4, 2, 2, 1, 5, 3, 5, 2, 4, 4, 1, 1, 4, 1, 3, 3, 1, 5,
+            1, 4, 2, 5, 0, 2, 1, 1, 0, 0, 0, 1, 2, 0, 4, 3, 3, 1, 0, 3, 1, 4, 2, 0, 3, 3, 5, 0, 5,
+            3, 1, 1, 0, 0, 3, 0, 2, 1, 5, 4, 0, 2, 3, 5, 4, 5, 2, 0, 5, 5, 0, 4, 5, 3, 0, 1, 2, 2,
+            1, 0, 4, 5, 5, 1, 3, 0, 2, 4, 1, 5, 2, 0, 5, 5, 1, 2, 3, 4, 3, 1, 2, 0, 4, 2, 5, 2, 4,
+            5, 3, 0, 2, 4, 0, 3, 0, 5, 4, 1, 3, 5, 0, 1, 2, 1, 1, 3, 4, 3, 2, 5, 2, 3, 3, 0, 5, 2,
+            5, 5, 2, 4, 0, 5, 2, 1, 4, 4, 1, 5, 4, 3, 5, 0, 3, 3, 2, 2, 5, 3, 0, 4, 1, 4, 0, 1, 4,
+            5, 0, 0, 1, 4, 0, 3, 1, 5, 4, 2, 2, 1, 3, 2, 1, 1, 2, 4, 5, 0, 0, 5, 1, 1, 5, 5, 2, 3,
+            5, 2, 4, 4, 5, 0, 3, 0, 2, 0, 1, 4, 3, 5, 3, 2, 2, 3, 1, 1, 5, 3, 0, 0, 3, 2, 3, 5, 1,
+            0, 3, 4, 2, 1, 0, 2, 3, 0, 4, 1, 5, 4, 2, 0, 4, 1, 0, 4, 3, 0, 5, 5, 4, 4, 5, 2, 3, 5,
+            1, 2, 5, 4, 5, 2, 4, 3, 3, 5, 1, 1, 0, 5, 1, 2, 2, 4, 1, 3, 4, 0, 3, 3, 0, 0, 1, 5, 4,
+            2, 1, 0, 3, 5, 2, 3, 2, 5, 1, 4, 4, 2, 1, 4, 0, 3, 5, 5, 2, 5, 3, 1, 2, 0, 0, 3, 5, 1,
+            1, 2, 2, 3, 3, 4, 4, 4, 2, 0, 1, 0, 0, 1, 3, 4, 2, 1, 4, 0, 3, 5, 3, 3, 5, 5, 5, 0, 2,
+            1, 1, 5, 5, 5, 0, 1, 3, 2, 1, 5, 1, 3, 3, 2, 0, 2, 4, 2, 0, 2, 1, 2, 4, 3, 3, 4, 4, 0,
+            1, 1, 5, 0, 4, 1, 3, 0, 1, 5, 2, 2, 3, 0, 2, 2, 2, 5, 1, 3, 5, 3, 2, 4, 2, 1, 4, 2, 1,
+            4, 4, 4, 1, 5, 0, 2, 5, 1, 5, 2, 4, 3, 3, 5, 2, 1, 5, 2, 2, 0, 2, 4, 1, 4, 5, 2, 0, 4,
+            1, 2, 4, 5, 0, 1, 2, 5, 0, 0, 0, 3, 3, 2, 2, 4, 1, 1, 0, 5, 4, 0, 3, 0, 2, 4, 4, 2, 5,
+            5, 1, 4, 3, 1, 2, 1, 5, 1, 3, 2, 0, 1, 5, 3, 4, 1, 4, 3, 5, 5, 0, 1, 4, 4, 5, 4, 0, 2,
+            2, 4, 3, 3, 0, 1, 0, 5, 5, 0, 3, 4, 4, 5, 5, 2, 0, 3, 5, 4, 5, 1, 0, 3, 0, 0, 0, 0, 5,
+            3, 0, 5, 5, 0, 4, 3, 5, 4, 4, 2, 1, 3, 4, 1, 1, 2, 4, 3, 4, 5, 5, 1, 3, 0, 0, 0, 3, 1,
+            0, 4, 3, 2, 3, 0, 1, 3, 5, 4, 1, 2, 3, 3, 5, 2, 1, 3, 1, 4, 4, 5, 3, 0, 4, 3, 3, 1, 0,
+            3, 5, 2, 2, 1, 1, 0, 0, 3, 2, 5, 0, 4, 1, 4, 5, 0, 2, 0, 0, 1, 5, 1, 3, 2, 2, 4, 2, 2,
+            4, 2, 3, 2, 4, 1, 0, 4, 5, 4, 0, 5, 1, 2, 2, 5, 4, 1, 2, 1, 3, 5, 0, 0, 4, 0, 1, 2, 0,
+            3, 2, 1, 5, 0, 3, 1, 4, 5, 0, 3, 2, 4, 3, 2,
+        ],
+        (60, 1080) => vec![
+            1, 0, 2, 3, 0, 4, 5, 1, 3, 5, 4, 1, 5, 4, 2, 5, 0, 3, 1, 2, 3, 4, 5, 1, 0, 0, 2, 4, 3,
+            5, 5, 3, 2, 2, 4, 1, 0, 3, 3, 0, 1, 1, 5, 2, 4, 0, 2, 1, 3, 4, 1, 3, 2, 0, 1, 5, 0, 4,
+            5, 2, 0, 5, 2, 4, 4, 5, 3, 3, 1, 4, 2, 1, 5, 0, 1, 4, 3, 0, 0, 1, 0, 0, 4, 2, 3, 3, 2,
+            5, 0, 4, 2, 1, 4, 3, 5, 4, 0, 0, 5, 2, 1, 3, 2, 1, 1, 4, 4, 0, 2, 3, 5, 5, 4, 4, 5, 0,
+            2, 5, 3, 4, 0, 5, 4, 1, 1, 2, 2, 0, 3, 5, 1, 2, 5, 5, 3, 3, 3, 0, 5, 2, 4, 5, 1, 0, 2,
+            3, 1, 4, 2, 0, 3, 1, 0, 2, 1, 1, 3, 4, 5, 5, 0, 0, 1, 2, 3, 4, 4, 3, 1, 1, 3, 4, 1, 1,
+            4, 0, 2, 4, 0, 5, 1, 3, 5, 4, 3, 4, 0, 0, 2, 2, 3, 0, 4, 5, 3, 1, 2, 0, 2, 1, 3, 5, 1,
+            3, 2, 4, 3, 4, 0, 5, 5, 5, 4, 2, 3, 4, 0, 0, 1, 3, 4, 2, 0, 4, 3, 4, 4, 3, 0, 1, 2, 0,
+            5, 5, 0, 1, 1, 2, 5, 1, 5, 2, 1, 3, 3, 5, 2, 3, 0, 5, 5, 0, 1, 1, 5, 2, 3, 3, 5, 2, 2,
+            5, 0, 1, 2, 4, 4, 1, 1, 0, 4, 2, 3, 1, 5, 2, 4, 3, 2, 5, 4, 2, 2, 1, 1, 3, 3, 5, 0, 2,
+            1, 0, 3, 1, 4, 2, 2, 0, 5, 1, 0, 3, 2, 2, 1, 5, 3, 5, 4, 2, 2, 4, 5, 1, 2, 0, 0, 4, 4,
+            3, 0, 5, 4, 0, 2, 1, 5, 3, 0, 2, 2, 4, 0, 3, 3, 0, 4, 3, 3, 2, 5, 3, 4, 4, 2, 5, 5, 5,
+            1, 4, 4, 1, 0, 1, 0, 3, 2, 3, 5, 1, 4, 4, 1, 5, 4, 3, 2, 0, 5, 3, 3, 0, 2, 4, 4, 2, 1,
+            5, 5, 4, 3, 3, 1, 0, 3, 4, 1, 5, 2, 2, 1, 4, 1, 2, 3, 2, 2, 3, 4, 5, 4, 3, 0, 4, 5, 2,
+            4, 3, 0, 5, 1, 0, 0, 3, 2, 0, 4, 4, 2, 2, 1, 3, 5, 5, 0, 5, 2, 4, 1, 1, 3, 0, 1, 5, 5,
+            3, 0, 2, 5, 4, 1, 0, 0, 3, 1, 2, 5, 3, 0, 4, 5, 0, 2, 2, 0, 1, 1, 5, 0, 4, 1, 1, 4, 5,
+            2, 5, 0, 3, 4, 2, 1, 4, 3, 1, 1, 2, 0, 0, 2, 2, 5, 4, 4, 1, 3, 5, 0, 3, 0, 1, 4, 0, 0,
+            5, 3, 0, 1, 2, 3, 1, 5, 3, 3, 4, 1, 4, 0, 5, 3, 5, 2, 2, 0, 1, 4, 0, 3, 1, 2, 3, 0, 0,
+            4, 2, 2, 5, 1, 5, 5, 2, 0, 3, 5, 2, 2, 1, 1, 3, 3, 4, 1, 5, 1, 3, 4, 2, 5, 4, 4, 4, 1,
+            3, 3, 4, 4, 5, 5, 4, 1, 1, 0, 4, 5, 2, 0, 0, 2, 5, 2, 0, 4, 0, 0, 1, 5, 3, 0, 5, 0, 0,
+            5, 4, 1, 1, 2, 4, 5, 0, 0, 0, 3, 3, 1, 3, 5, 0, 2, 1, 1, 2, 5, 3, 2, 5, 1, 3, 3, 1, 1,
+            3, 2, 2, 3, 1, 0, 4, 2, 4, 4, 4, 0, 1, 3, 1, 1, 5, 5, 1, 2, 4, 4, 0, 3, 2, 5, 1, 4, 1,
+            0, 0, 5, 4, 3, 1, 4, 0, 2, 5, 1, 2, 3, 5, 5, 1, 2, 2, 1, 5, 4, 2, 3, 3, 4, 5, 5, 4, 4,
+            2, 0, 3, 3, 1, 1, 1, 2, 3, 5, 2, 4, 5, 5, 3, 3, 4, 5, 0, 4, 3, 2, 2, 0, 5, 5, 0, 0, 4,
+            1, 0, 3, 0, 2, 4, 3, 2, 4, 0, 0, 4, 2, 0, 3, 1, 4, 4, 0, 3, 3, 5, 4, 1, 5, 1, 0, 5, 2,
+            5, 1, 3, 3, 1, 4, 5, 1, 5, 5, 2, 5, 0, 5, 1, 4, 1, 2, 2, 4, 1, 0, 5, 4, 5, 3, 2, 4, 0,
+            4, 2, 5, 5, 1, 1, 3, 2, 5, 3, 1, 3, 0, 2, 3, 1, 3, 2, 2, 0, 2, 1, 0, 2, 2, 3, 5, 4, 3,
+            2, 0, 4, 4, 5, 0, 1, 4, 0, 3, 2, 3, 0, 3, 3, 0, 3, 4, 2, 0, 4, 0, 5, 0, 4, 3, 4, 0, 1,
+            5, 0, 3, 2, 1, 3, 2, 0, 1, 1, 2, 5, 1, 1, 4, 4, 3, 3, 5, 2, 0, 0, 5, 3, 5, 4, 3, 5, 5,
+            0, 0, 1, 0, 2, 2, 1, 3, 0, 3, 5, 1, 2, 1, 0, 1, 0, 4, 4, 0, 1, 3, 3, 5, 4, 3, 2, 4, 5,
+            2, 4, 4, 2, 0, 1, 2, 0, 1, 4, 1, 3, 5, 3, 2, 5, 1, 5, 0, 4, 5, 0, 4, 3, 0, 0, 1, 1, 5,
+            5, 1, 3, 4, 2, 1, 0, 0, 2, 3, 5, 4, 0, 3, 1, 3, 0, 5, 3, 1, 2, 1, 2, 0, 0, 3, 4, 3, 1,
+            0, 4, 4, 2, 1, 0, 0, 2, 0, 3, 4, 0, 1, 4, 3, 5, 0, 2, 5, 4, 0, 5, 4, 4, 3, 4, 4, 5, 2,
+            3, 5, 4, 5, 1, 2, 5, 2, 1, 4, 2, 5, 0, 5, 2, 1, 1, 0, 1, 5, 0, 5, 5, 5, 3, 4, 1, 1, 3,
+            3, 0, 4, 5, 3, 4, 5, 4, 0, 4, 1, 2, 3, 2, 3, 0, 1, 0, 0, 5, 3, 4, 0, 5, 2, 0, 1, 5, 1,
+            5, 4, 4, 0, 0, 3, 2, 5, 2, 5, 4, 1, 2, 1, 0, 3, 1, 0, 2, 4, 1, 4, 5, 2, 2, 4, 3, 5, 3,
+            3, 2, 4, 3, 1, 2, 2, 5, 5, 3, 2, 2, 4, 4, 5, 4, 3, 1, 3, 4, 5, 4, 2, 4, 3, 0, 2, 1, 4,
+            3, 3, 1, 0, 5, 2, 0,
+        ],
+        (72, 1296) => vec![
+            3, 1, 5, 4, 0, 2, 1, 0, 4, 2, 5, 1, 3, 5, 2, 4, 0, 1, 4, 5, 3, 4, 1, 0, 3, 2, 2, 5, 0,
+            2, 3, 0, 4, 4, 1, 5, 5, 3, 2, 1, 4, 5, 1, 2, 5, 3, 0, 5, 4, 2, 0, 0, 5, 1, 1, 3, 3, 5,
+            4, 4, 3, 2, 0, 1, 2, 3, 1, 2, 3, 4, 2, 0, 3, 3, 1, 1, 5, 2, 4, 3, 2, 5, 5, 1, 2, 0, 4,
+            2, 2, 4, 0, 5, 3, 2, 5, 4, 3, 0, 0, 5, 5, 3, 0, 1, 3, 4, 5, 3, 1, 0, 0, 1, 3, 4, 5, 0,
+            2, 2, 1, 0, 5, 5, 4, 1, 1, 2, 5, 3, 3, 2, 3, 1, 3, 0, 0, 5, 4, 3, 1, 4, 0, 4, 4, 3, 5,
+            0, 1, 2, 4, 0, 5, 2, 4, 5, 3, 3, 1, 2, 2, 5, 3, 4, 4, 0, 0, 1, 2, 0, 3, 2, 1, 5, 5, 3,
+            5, 1, 3, 5, 0, 5, 4, 1, 2, 2, 0, 3, 4, 4, 5, 2, 4, 1, 0, 2, 2, 3, 0, 4, 5, 2, 3, 5, 3,
+            1, 1, 4, 2, 0, 4, 4, 2, 1, 1, 3, 1, 4, 0, 2, 4, 0, 2, 2, 3, 0, 1, 4, 2, 5, 4, 3, 5, 2,
+            1, 3, 4, 1, 3, 2, 0, 5, 5, 0, 4, 0, 1, 1, 0, 5, 3, 2, 5, 3, 1, 0, 3, 5, 0, 1, 0, 4, 3,
+            4, 4, 1, 5, 5, 0, 3, 3, 4, 2, 1, 4, 2, 0, 2, 1, 5, 4, 4, 3, 2, 1, 0, 0, 3, 3, 5, 2, 3,
+            5, 1, 4, 5, 4, 1, 5, 2, 2, 4, 2, 2, 1, 0, 1, 5, 2, 4, 4, 0, 0, 5, 2, 3, 4, 3, 5, 3, 2,
+            4, 1, 1, 0, 2, 3, 3, 0, 5, 2, 2, 5, 1, 0, 0, 1, 2, 2, 4, 5, 2, 0, 3, 1, 4, 4, 0, 0, 0,
+            4, 1, 5, 2, 2, 3, 0, 4, 3, 3, 0, 5, 2, 0, 5, 1, 3, 3, 2, 1, 3, 5, 5, 4, 3, 3, 1, 5, 2,
+            2, 1, 0, 0, 3, 1, 3, 5, 2, 3, 3, 1, 5, 4, 0, 0, 4, 2, 5, 4, 3, 2, 4, 5, 1, 2, 1, 3, 4,
+            5, 2, 1, 0, 1, 1, 5, 0, 2, 2, 1, 4, 3, 0, 4, 2, 0, 4, 1, 3, 5, 3, 5, 5, 0, 4, 2, 4, 3,
+            4, 0, 1, 2, 5, 0, 3, 4, 4, 0, 4, 5, 0, 4, 1, 4, 0, 3, 0, 5, 0, 4, 1, 4, 5, 5, 3, 0, 1,
+            4, 0, 2, 1, 3, 4, 4, 2, 0, 1, 1, 5, 0, 5, 4, 0, 5, 2, 1, 5, 0, 0, 3, 0, 2, 4, 4, 5, 5,
+            1, 4, 4, 2, 3, 1, 1, 4, 5, 5, 2, 0, 0, 1, 0, 3, 2, 2, 2, 1, 2, 3, 0, 2, 5, 3, 1, 4, 3,
+            1, 1, 3, 3, 0, 3, 4, 2, 1, 1, 1, 5, 3, 4, 2, 3, 3, 5, 5, 0, 2, 0, 3, 5, 1, 1, 5, 2, 2,
+            0, 0, 5, 1, 1, 4, 1, 3, 3, 1, 2, 2, 4, 3, 5, 5, 1, 1, 0, 0, 2, 4, 1, 2, 5, 4, 0, 1, 4,
+            4, 2, 5, 5, 2, 3, 5, 0, 3, 2, 5, 1, 4, 4, 5, 5, 2, 4, 1, 2, 4, 5, 0, 4, 3, 0, 2, 3, 4,
+            0, 2, 1, 2, 0, 5, 3, 1, 5, 0, 3, 4, 4, 3, 2, 1, 1, 2, 5, 2, 3, 3, 1, 2, 0, 1, 5, 5, 3,
+            2, 0, 0, 3, 3, 4, 1, 0, 3, 2, 4, 5, 0, 1, 4, 0, 0, 1, 1, 5, 3, 3, 4, 5, 1, 3, 0, 5, 4,
+            3, 5, 1, 0, 4, 4, 3, 3, 0, 4, 2, 2, 2, 5, 0, 3, 3, 2, 5, 4, 1, 5, 1, 0, 1, 3, 4, 2, 2,
+            4, 4, 3, 2, 0, 4, 0, 0, 3, 3, 0, 1, 5, 5, 2, 3, 3, 5, 4, 2, 2, 4, 0, 1, 5, 3, 1, 0, 2,
+            1, 2, 0, 4, 4, 5, 1, 5, 5, 4, 5, 5, 0, 0, 2, 3, 1, 4, 1, 5, 4, 5, 0, 2, 1, 1, 2, 3, 4,
+            0, 5, 2, 5, 1, 4, 4, 5, 5, 1, 0, 4, 2, 1, 1, 0, 0, 2, 4, 1, 3, 0, 2, 2, 4, 0, 5, 4, 4,
+            4, 2, 5, 0, 0, 3, 1, 5, 1, 4, 0, 3, 5, 0, 2, 2, 3, 3, 2, 4, 1, 3, 2, 0, 5, 5, 0, 5, 2,
+            4, 3, 0, 1, 3, 0, 4, 3, 2, 4, 1, 3, 3, 5, 4, 1, 3, 3, 4, 5, 4, 1, 1, 1, 4, 3, 2, 0, 4,
+            1, 1, 0, 3, 4, 5, 5, 3, 0, 0, 1, 3, 5, 5, 3, 1, 2, 0, 4, 4, 5, 2, 1, 4, 2, 1, 5, 3, 4,
+            0, 2, 2, 2, 0, 0, 1, 3, 1, 5, 0, 2, 3, 5, 1, 1, 4, 5, 3, 0, 0, 5, 3, 0, 1, 1, 1, 4, 1,
+            2, 1, 0, 4, 4, 0, 5, 3, 3, 3, 5, 2, 1, 1, 4, 3, 5, 4, 3, 2, 3, 1, 0, 5, 0, 3, 1, 0, 4,
+            2, 5, 5, 2, 1, 1, 2, 2, 5, 2, 4, 2, 1, 0, 5, 5, 4, 0, 3, 3, 4, 4, 4, 0, 1, 3, 2, 2, 3,
+            5, 0, 3, 2, 1, 4, 2, 1, 3, 3, 0, 4, 3, 5, 4, 2, 5, 0, 4, 5, 3, 1, 0, 2, 4, 3, 3, 4, 0,
+            0, 1, 4, 5, 2, 3, 5, 4, 4, 5, 1, 2, 0, 2, 5, 5, 4, 5, 1, 0, 5, 2, 4, 0, 5, 1, 5, 5, 0,
+            0, 0, 2, 5, 1, 5, 0, 2, 4, 3, 4, 1, 3, 0, 2, 3, 4, 2, 3, 3, 1, 2, 0, 2, 5, 2, 4, 1, 4,
+            1, 1, 1, 3, 5, 2, 2, 5, 0, 4, 5, 1, 3, 3, 4, 3, 2, 5, 4, 2, 5, 3, 2, 4, 1, 0, 5, 5, 2,
+            3, 0, 0, 3, 0, 5, 3, 4, 3, 0, 4, 4, 0, 1, 1, 2, 0, 2, 5, 4, 3, 0, 4, 2, 4, 2, 3, 3, 2,
+            2, 0, 5, 5, 2, 1, 1, 0, 1, 4, 3, 5, 4, 2, 3, 0, 4, 2, 1, 3, 1, 0, 0, 4, 4, 2, 4, 5, 3,
+            2, 1, 2, 4, 1, 3, 3, 0, 4, 0, 5, 1, 3, 4, 2, 1, 1, 0, 2, 5, 0, 2, 2, 0, 0, 0, 4, 1, 5,
+            4, 3, 1, 0, 1, 3, 2, 2, 1, 3, 2, 1, 5, 0, 1, 3, 1, 4, 5, 5, 3, 0, 4, 2, 2, 1, 0, 1, 0,
+            3, 0, 4, 3, 2, 1, 2, 3, 1, 3, 3, 2, 0, 5, 0, 0, 5, 3, 4, 4, 1, 2, 5, 4, 5, 4, 2, 4, 5,
+            2, 1, 0, 4, 5, 5, 2, 2, 0, 1, 1, 2, 5, 0, 4, 5, 1, 4, 4, 5, 4, 2, 3, 0, 4, 5, 0, 3, 1,
+            4, 3, 1, 1, 0, 3, 4, 0, 0, 2, 5, 3, 5, 0, 4, 3, 5, 3, 1, 4, 1, 1, 0, 5, 4, 4, 5, 2, 2,
+            5, 3, 3, 4, 0, 0, 2, 2, 5, 5, 0, 1, 3, 4, 4, 0, 3, 2, 4, 4,
+        ],
+        (90, 1620) => vec![
+            2, 4, 5, 3, 1, 0, 3, 2, 5, 5, 4, 0, 0, 1, 1, 4, 5, 0, 4, 2, 3, 0, 5, 2, 1, 5, 0, 1, 2,
+            3, 5, 4, 1, 0, 5, 3, 2, 5, 4, 2, 0, 4, 1, 3, 3, 0, 1, 4, 0, 2, 4, 3, 5, 1, 3, 4, 4, 0,
+            5, 1, 2, 4, 0, 3, 5, 5, 4, 1, 2, 0, 1, 3, 2, 2, 5, 5, 3, 0, 0, 4, 5, 1, 4, 3, 1, 0, 5,
+            5, 4, 2, 3, 3, 5, 2, 1, 5, 2, 0, 3, 2, 4, 1, 0, 3, 4, 0, 2, 1, 3, 4, 1, 5, 3, 3, 2, 2,
+            1, 4, 3, 1, 5, 0, 2, 2, 0, 0, 5, 1, 1, 2, 2, 4, 5, 0, 3, 3, 5, 1, 0, 4, 3, 1, 2, 4, 0,
+            0, 5, 3, 0, 2, 4, 4, 2, 2, 5, 3, 1, 1, 3, 0, 5, 4, 2, 2, 5, 1, 0, 5, 2, 0, 1, 5, 0, 2,
+            3, 4, 0, 1, 4, 3, 2, 0, 5, 4, 4, 3, 5, 5, 1, 2, 0, 4, 4, 0, 1, 3, 5, 2, 4, 3, 3, 2, 4,
+            1, 1, 0, 2, 2, 3, 4, 4, 5, 1, 4, 2, 3, 5, 2, 1, 1, 2, 5, 3, 4, 0, 2, 1, 3, 3, 2, 0, 4,
+            2, 0, 5, 1, 3, 4, 5, 5, 0, 4, 3, 3, 5, 4, 4, 5, 0, 1, 2, 4, 3, 3, 0, 0, 0, 4, 2, 1, 1,
+            0, 0, 2, 1, 4, 4, 5, 1, 3, 5, 1, 3, 2, 1, 1, 5, 4, 2, 3, 1, 2, 3, 5, 0, 0, 2, 5, 3, 3,
+            1, 5, 5, 2, 4, 0, 0, 4, 1, 3, 3, 0, 2, 4, 4, 3, 0, 3, 2, 1, 4, 0, 1, 5, 5, 1, 3, 1, 5,
+            4, 4, 0, 0, 1, 5, 5, 2, 3, 1, 4, 1, 2, 3, 3, 2, 1, 1, 1, 0, 5, 5, 2, 2, 3, 0, 5, 2, 2,
+            1, 0, 1, 4, 5, 0, 2, 3, 5, 3, 2, 1, 3, 1, 2, 2, 0, 0, 2, 0, 3, 4, 2, 5, 2, 3, 2, 2, 4,
+            4, 4, 1, 5, 4, 1, 5, 0, 1, 3, 2, 5, 1, 0, 0, 5, 5, 1, 5, 5, 2, 1, 4, 5, 3, 0, 1, 4, 4,
+            5, 0, 2, 1, 0, 2, 3, 4, 0, 3, 1, 2, 0, 4, 3, 3, 5, 3, 3, 4, 0, 5, 2, 5, 1, 0, 0, 4, 4,
+            0, 4, 1, 5, 1, 2, 3, 4, 2, 4, 1, 0, 5, 5, 3, 5, 2, 1, 0, 4, 4, 3, 1, 1, 2, 1, 4, 5, 3,
+            4, 0, 3, 1, 2, 5, 0, 2, 3, 5, 4, 2, 0, 1, 0, 4, 5, 3, 1, 2, 5, 3, 2, 2, 1, 3, 4, 0, 4,
+            3, 0, 1, 4, 3, 1, 1, 5, 3, 4, 0, 3, 2, 4, 5, 0, 2, 3, 1, 5, 5, 3, 0, 3, 1, 2, 3, 4, 5,
+            0, 1, 4, 4, 2, 3, 0, 2, 4, 3, 1, 5, 4, 0, 1, 2, 5, 3, 5, 0, 3, 2, 2, 1, 4, 0, 0, 2, 2,
+            2, 5, 3, 5, 4, 4, 2, 4, 5, 2, 4, 3, 2, 2, 1, 4, 3, 3, 1, 1, 5, 0, 0, 4, 1, 3, 4, 1, 4,
+            2, 2, 3, 3, 1, 5, 4, 2, 4, 1, 1, 3, 0, 0, 2, 0, 1, 1, 4, 4, 1, 3, 0, 2, 4, 2, 2, 3, 3,
+            4, 5, 5, 1, 0, 2, 4, 0, 2, 5, 0, 4, 5, 5, 2, 1, 2, 0, 4, 5, 5, 4, 3, 0, 5, 1, 1, 0, 4,
+            4, 1, 3, 5, 1, 4, 4, 0, 3, 3, 1, 1, 1, 2, 2, 4, 0, 0, 5, 5, 0, 1, 3, 4, 5, 5, 4, 2, 0,
+            5, 2, 3, 4, 1, 0, 3, 5, 0, 1, 1, 3, 5, 0, 0, 5, 4, 1, 5, 3, 2, 3, 0, 0, 3, 1, 5, 2, 0,
+            4, 3, 5, 1, 2, 5, 5, 1, 1, 4, 4, 3, 0, 1, 2, 0, 0, 0, 3, 2, 2, 4, 1, 5, 2, 3, 1, 0, 2,
+            5, 5, 5, 3, 4, 4, 1, 5, 3, 0, 0, 3, 2, 3, 3, 0, 0, 4, 3, 3, 2, 5, 1, 0, 0, 3, 4, 5, 2,
+            1, 5, 2, 0, 0, 4, 4, 3, 2, 2, 5, 5, 1, 4, 4, 2, 1, 0, 3, 4, 2, 0, 1, 2, 2, 3, 5, 5, 1,
+            1, 0, 2, 2, 4, 0, 5, 4, 4, 3, 0, 0, 1, 4, 4, 3, 3, 3, 0, 4, 4, 2, 5, 4, 2, 5, 4, 0, 3,
+            4, 1, 2, 5, 5, 4, 1, 0, 3, 1, 4, 3, 5, 4, 1, 0, 4, 2, 5, 0, 3, 5, 0, 0, 5, 1, 3, 0, 2,
+            1, 5, 5, 3, 3, 4, 0, 1, 1, 5, 4, 0, 5, 2, 4, 2, 0, 0, 4, 3, 3, 0, 5, 5, 2, 1, 0, 5, 4,
+            2, 0, 3, 4, 4, 0, 0, 3, 3, 1, 2, 5, 4, 4, 3, 2, 0, 1, 4, 1, 1, 3, 3, 5, 2, 0, 0, 2, 1,
+            2, 5, 1, 1, 4, 2, 1, 5, 2, 1, 3, 3, 5, 2, 3, 1, 3, 0, 5, 3, 5, 1, 2, 2, 2, 0, 1, 1, 1,
+            3, 1, 5, 0, 3, 4, 1, 1, 4, 4, 2, 2, 1, 1, 5, 4, 4, 2, 1, 0, 5, 5, 0, 0, 2, 4, 5, 5, 1,
+            0, 2, 2, 3, 3, 4, 4, 0, 5, 4, 4, 5, 2, 5, 0, 1, 1, 3, 4, 2, 3, 5, 2, 3, 1, 5, 2, 2, 5,
+            4, 5, 0, 1, 2, 4, 1, 1, 3, 0, 4, 1, 5, 4, 1, 1, 5, 3, 1, 1, 4, 0, 3, 5, 1, 2, 3, 4, 2,
+            5, 5, 3, 0, 4, 1, 4, 3, 0, 0, 2, 3, 3, 3, 0, 0, 5, 3, 3, 0, 4, 0, 3, 4, 3, 5, 1, 3, 5,
+            4, 5, 0, 1, 3, 4, 2, 2, 0, 5, 5, 0, 2, 3, 4, 2, 5, 4, 0, 3, 1, 2, 2, 5, 3, 2, 4, 5, 0,
+            2, 4, 2, 1, 3, 2, 3, 0, 4, 4, 5, 3, 1, 1, 0, 0, 3, 3, 2, 0, 0, 1, 5, 2, 4, 3, 4, 2, 0,
+            1, 5, 5, 0, 4, 2, 1, 5, 2, 4, 4, 5, 5, 3, 2, 4, 1, 1, 2, 3, 5, 2, 0, 3, 3, 1, 1, 0, 4,
+            5, 1, 0, 5, 3, 0, 0, 2, 5, 0, 5, 4, 1, 0, 5, 2, 3, 3, 2, 4, 5, 2, 0, 1, 5, 0, 1, 2, 1,
+            1, 2, 0, 3, 1, 0, 4, 2, 4, 3, 0, 2, 0, 3, 3, 5, 1, 4, 5, 5, 2, 3, 3, 1, 0, 1, 3, 0, 3,
+            5, 3, 2, 1, 4, 3, 0, 2, 0, 2, 2, 4, 0, 5, 4, 5, 3, 5, 1, 0, 2, 5, 4, 2, 5, 5, 3, 5, 4,
+            4, 1, 3, 0, 5, 3, 0, 2, 1, 5, 2, 4, 1, 4, 3, 3, 5, 5, 1, 0, 2, 1, 5, 1, 3, 4, 1, 0, 1,
+            3, 2, 5, 2, 1, 4, 5, 4, 4, 4, 1, 5, 0, 3, 2, 2, 3, 2, 2, 4, 1, 3, 4, 1, 2, 2, 3, 0, 3,
+            2, 0, 3, 5, 4, 0, 1, 1, 2, 5, 0, 0, 5, 2, 3, 4, 2, 5, 3, 1, 3, 3, 4, 4, 1, 4, 5, 3, 2,
+            4, 0, 2, 4, 0, 5, 1, 2, 4, 3, 0, 2, 5, 4, 0, 1, 1, 3, 0, 3, 4, 0, 5, 0, 0, 4, 4, 0, 1,
+            1, 4, 4, 2, 0, 5, 3, 2, 5, 4, 1, 1, 5, 0, 0, 4, 4, 5, 1, 5, 3, 4, 5, 2, 1, 1, 0, 4, 3,
+            2, 4, 4, 3, 1, 3, 2, 1, 2, 4, 4, 1, 3, 2, 1, 3, 5, 5, 5, 4, 4, 5, 5, 0, 1, 3, 1, 4, 0,
+            5, 3, 3, 5, 4, 4, 2, 2, 5, 3, 2, 1, 4, 1, 2, 5, 5, 1, 3, 2, 2, 1, 3, 0, 4, 0, 0, 2, 0,
+            0, 1, 3, 3, 2, 1, 0, 3, 5, 5, 1, 5, 0, 2, 5, 0, 0, 3, 4, 3, 1, 1, 2, 4, 5, 3, 5, 4, 5,
+            5, 0, 3, 4, 2, 1, 0, 0, 0, 4, 2, 4, 5, 2, 2, 0, 3, 5, 1, 0, 2, 5, 2, 2, 4, 1, 0, 2, 4,
+            5, 4, 0, 5, 5, 3, 2, 0, 4, 1, 4, 3, 5, 5, 3, 3, 0, 1, 0, 0, 1, 2, 3, 1, 0, 3, 5, 0, 3,
+            1, 2, 0, 4, 5, 2, 2, 1, 3, 4, 1, 4, 1, 1, 4, 0, 2, 3, 3, 4, 3, 2, 4, 1, 3, 2, 5, 3, 1,
+            2, 0, 1, 2, 0, 5, 1, 4, 2, 3, 5, 5, 2, 3, 1, 3, 5, 3, 0, 5, 1, 4, 0, 4, 1, 5, 3, 2, 0,
+            1, 5, 3, 3, 0, 1, 5, 4, 5, 2, 1, 4, 2, 0, 5, 0, 0, 3, 1, 4, 2, 0, 4, 2, 4, 4, 3, 2, 3,
+            3, 5, 5, 1, 0, 3, 5, 4, 4, 3, 3, 4, 1, 2, 3, 1, 5, 3, 4, 2, 1, 3, 5, 5, 2,
+        ],
+        _ => return None,
+    })
+}
+
+/// Length of the single long plan the no-marks solvers send for `n_rooms`
+/// (`iwiwi_evo_gen276` and friends); the table has one for every official size.
+pub fn long_plan_len(n_rooms: usize) -> usize {
+    18 * n_rooms
+}
+
+/// Precomputed plan of `len` doors for `n_rooms`. Panics for plans missing
+/// from the table.
+pub fn get_plan(n_rooms: usize, len: usize) -> Vec<usize> {
+    table_plan(n_rooms, len)
+        .unwrap_or_else(|| panic!("no precomputed plan of {} doors for {} rooms", len, n_rooms))
+}
+
+/// The table's long plan for `n_rooms` (see [`long_plan_len`]).
+pub fn long_plan(n_rooms: usize) -> Vec<usize> {
+    get_plan(n_rooms, long_plan_len(n_rooms))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn every_official_size_has_a_long_plan() {
+        for p in crate::problems::all_problems() {
+            let plan = long_plan(p.size);
+            assert_eq!(plan.len(), long_plan_len(p.size), "{}", p.problem);
+            let mut counts = [0usize; 6];
+            for &d in &plan {
+                counts[d] += 1;
+            }
+            assert!(counts.iter().all(|&c| c > 0), "{}: {:?}", p.problem, counts);
+        }
+        for n in [12, 18, 24, 36, 48, 54, 60, 72, 90] {
+            assert_eq!(get_plan(n, 6 * n).len(), 6 * n);
+        }
+        assert_eq!(table_plan(7, long_plan_len(7)), None);
     }
 }
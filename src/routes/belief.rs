@@ -0,0 +1,281 @@
+//! # Belief Routes
+//!
+//! Plans for a map that is already partly known, e.g. from a solve whose
+//! passages were confirmed by earlier explorations. A [`PartialMap`] holds the
+//! confirmed passages; every other door is unknown and may lead to any free
+//! door. Instead of a balanced random plan, [`plan_routes`] spends the budget
+//! where something is left to learn:
+//!
+//! 1. While the walk is certainly in one room, it takes the shortest route of
+//!    known passages (BFS) to the nearest unknown door no earlier plan of the
+//!    batch aimed at, so each plan of a batch probes a different door.
+//! 2. Past an unknown door the walk is only known as a belief, a distribution
+//!    over rooms in which an unknown door is equally likely to lead to any
+//!    free door. Each further door is the one with the largest expected number
+//!    of unknown doors probed for the first time, over two steps.
+
+use rand::prelude::*;
+use std::collections::VecDeque;
+
+use crate::judge::{Guess, MAX_DOORS, num_doors};
+
+/// Confirmed passages of a map; `None` doors are unknown.
+#[derive(Clone, Debug)]
+pub struct PartialMap {
+    pub start: usize,
+    pub doors: Vec<[Option<(usize, usize)>; MAX_DOORS]>,
+}
+
+impl PartialMap {
+    /// A map with nothing known but the doors beyond [`num_doors`], which
+    /// loop back to themselves.
+    pub fn new(num_rooms: usize, start: usize) -> Self {
+        let doors = (0..num_rooms)
+            .map(|u| std::array::from_fn(|e| (e >= num_doors()).then_some((u, e))))
+            .collect();
+        PartialMap { start, doors }
+    }
+
+    /// The passages of `guess` through the doors in `confirmed`.
+    pub fn from_guess(guess: &Guess, confirmed: &[(usize, usize)]) -> Self {
+        let mut map = PartialMap::new(guess.graph.len(), guess.start);
+        for &(u, e) in confirmed {
+            map.connect((u, e), guess.graph[u][e]);
+        }
+        map
+    }
+
+    pub fn num_rooms(&self) -> usize {
+        self.doors.len()
+    }
+
+    /// Records the passage between door `e` of `u` and door `f` of `v`.
+    pub fn connect(&mut self, (u, e): (usize, usize), (v, f): (usize, usize)) {
+        self.doors[u][e] = Some((v, f));
+        self.doors[v][f] = Some((u, e));
+    }
+
+    /// Doors whose passage is unknown.
+    pub fn unknown_doors(&self) -> Vec<(usize, usize)> {
+        (0..self.num_rooms())
+            .flat_map(|u| (0..num_doors()).map(move |e| (u, e)))
+            .filter(|&(u, e)| self.doors[u][e].is_none())
+            .collect()
+    }
+
+    /// Shortest door sequence from `from` over known passages that ends by
+    /// taking a door accepted by `target`, or `None` if none is reachable.
+    pub fn shortest_route(
+        &self,
+        from: usize,
+        mut target: impl FnMut(usize, usize) -> bool,
+    ) -> Option<Vec<usize>> {
+        let n = self.num_rooms();
+        // prev[v] = (room, door) the BFS reached `v` through.
+        let mut prev = vec![None; n];
+        let mut visited = vec![false; n];
+        visited[from] = true;
+        let mut queue = VecDeque::from([from]);
+        while let Some(u) = queue.pop_front() {
+            for e in 0..num_doors() {
+                match self.doors[u][e] {
+                    _ if target(u, e) => {
+                        let mut route = vec![e];
+                        let mut v = u;
+                        while let Some((w, d)) = prev[v] {
+                            route.push(d);
+                            v = w;
+                        }
+                        route.reverse();
+                        return Some(route);
+                    }
+                    Some((v, _)) if !visited[v] => {
+                        visited[v] = true;
+                        prev[v] = Some((u, e));
+                        queue.push_back(v);
+                    }
+                    _ => {}
+                }
+            }
+        }
+        None
+    }
+
+    /// The belief after taking door `e` from `belief`.
+    fn step(&self, belief: &[f64], e: usize) -> Vec<f64> {
+        let free = self.free_doors();
+        let total: usize = free.iter().sum();
+        let mut next = vec![0.0; self.num_rooms()];
+        for (u, &p) in belief.iter().enumerate() {
+            if p == 0.0 {
+                continue;
+            }
+            match self.doors[u][e] {
+                Some((v, _)) => next[v] += p,
+                None => {
+                    for (v, &k) in free.iter().enumerate() {
+                        next[v] += p * k as f64 / total as f64;
+                    }
+                }
+            }
+        }
+        next
+    }
+
+    /// Unknown doors of each room.
+    fn free_doors(&self) -> Vec<usize> {
+        self.doors
+            .iter()
+            .map(|d| d[..num_doors()].iter().filter(|x| x.is_none()).count())
+            .collect()
+    }
+}
+
+/// Expected number of unknown doors probed for the first time by taking `e`
+/// from `belief`, given the probability each door was already probed.
+fn gain(map: &PartialMap, belief: &[f64], probed: &[[f64; MAX_DOORS]], e: usize) -> f64 {
+    belief
+        .iter()
+        .enumerate()
+        .filter(|&(u, _)| map.doors[u][e].is_none())
+        .map(|(u, &p)| p * (1.0 - probed[u][e]))
+        .sum()
+}
+
+/// Up to `count` plans of at most `budget` doors that probe the unknown doors
+/// of `map`, as described in the module docs. Fewer plans are returned when
+/// fewer unknown doors are reachable, and none when the map is fully known.
+pub fn plan_routes(
+    map: &PartialMap,
+    count: usize,
+    budget: usize,
+    rng: &mut impl Rng,
+) -> Vec<Vec<usize>> {
+    let n = map.num_rooms();
+    let mut aimed = vec![[false; MAX_DOORS]; n];
+    let mut plans = vec![];
+    while plans.len() < count {
+        let Some(mut plan) =
+            map.shortest_route(map.start, |u, e| map.doors[u][e].is_none() && !aimed[u][e])
+        else {
+            break;
+        };
+        if plan.len() > budget {
+            break;
+        }
+        // Replay the route; it is certain, so the belief stays a single room
+        // until the last door.
+        let mut belief = vec![0.0; n];
+        belief[map.start] = 1.0;
+        let mut probed = vec![[0.0; MAX_DOORS]; n];
+        for &e in &plan {
+            for (u, &p) in belief.iter().enumerate() {
+                probed[u][e] += p * (1.0 - probed[u][e]);
+            }
+            belief = map.step(&belief, e);
+        }
+        let (u, e) = last_door(map, &plan);
+        aimed[u][e] = true;
+
+        while plan.len() < budget {
+            let mut order = (0..num_doors()).collect::<Vec<_>>();
+            order.shuffle(rng);
+            let score = |e: usize| {
+                let next = map.step(&belief, e);
+                gain(map, &belief, &probed, e)
+                    + 0.5
+                        * (0..num_doors())
+                            .map(|f| gain(map, &next, &probed, f))
+                            .fold(0.0, f64::max)
+            };
+            let e = order
+                .into_iter()
+                .map(|e| (score(e), e))
+                .fold(None, |best: Option<(f64, usize)>, (s, e)| match best {
+                    Some((b, _)) if b >= s => best,
+                    _ => Some((s, e)),
+                })
+                .unwrap()
+                .1;
+            for (u, &p) in belief.iter().enumerate() {
+                probed[u][e] += p * (1.0 - probed[u][e]);
+            }
+            belief = map.step(&belief, e);
+            plan.push(e);
+        }
+        plans.push(plan);
+    }
+    plans
+}
+
+/// The door a route from the start ends with.
+fn last_door(map: &PartialMap, route: &[usize]) -> (usize, usize) {
+    let mut u = map.start;
+    for &e in &route[..route.len() - 1] {
+        u = map.doors[u][e].unwrap().0;
+    }
+    (u, route[route.len() - 1])
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::judge::generate_random_edges_v2;
+    use crate::routes::planner::{PlanStrategy, RandomBalanced};
+    use rand_chacha::ChaCha12Rng;
+
+    /// Distinct unknown doors of `map` that `plans` take on the true `graph`.
+    fn probed(map: &PartialMap, graph: &[[usize; MAX_DOORS]], plans: &[Vec<usize>]) -> usize {
+        let mut seen = std::collections::HashSet::new();
+        for plan in plans {
+            let mut u = map.start;
+            for &e in plan {
+                if map.doors[u][e].is_none() {
+                    seen.insert((u, e));
+                }
+                u = graph[u][e];
+            }
+        }
+        seen.len()
+    }
+
+    #[test]
+    fn routes_probe_unknown_doors() {
+        let n = 30;
+        let edges = generate_random_edges_v2(n, 3);
+        let mut graph = vec![[0; MAX_DOORS]; n];
+        let mut map = PartialMap::new(n, 0);
+        let mut rng = ChaCha12Rng::seed_from_u64(4);
+        for &((u, e), (v, f)) in &edges {
+            graph[u][e] = v;
+            graph[v][f] = u;
+            // Most of the map is known.
+            if rng.random_bool(0.9) {
+                map.connect((u, e), (v, f));
+            }
+        }
+        let unknown = map.unknown_doors();
+        assert!(!unknown.is_empty());
+
+        // The first plan goes straight to the nearest unknown door.
+        let plans = plan_routes(&map, 4, n, &mut rng);
+        assert_eq!(plans.len(), 4);
+        assert!(plans.iter().all(|p| p.len() == n));
+        let route = map
+            .shortest_route(0, |u, e| map.doors[u][e].is_none())
+            .unwrap();
+        assert_eq!(plans[0][..route.len()], route);
+
+        // Random plans of the same size mostly walk known passages.
+        let random = (0..4)
+            .map(|_| RandomBalanced.plan(n, n, &mut rng))
+            .collect::<Vec<_>>();
+        assert!(probed(&map, &graph, &plans) > probed(&map, &graph, &random));
+
+        // Nothing to learn on a known map.
+        for &((u, e), (v, f)) in &edges {
+            map.connect((u, e), (v, f));
+        }
+        assert!(plan_routes(&map, 4, n, &mut rng).is_empty());
+    }
+}
@@ -0,0 +1,101 @@
+//! # Plan Compression
+//!
+//! Every plan of an explore request walks from the starting room, and the
+//! request is charged one query per plan plus one (`judge::explore_cost`),
+//! however long the plans are. A probe that is a prefix of another probe is
+//! therefore free: the longer walk's result starts with the shorter one's,
+//! as long as their marks agree too. [`compress`] drops such probes and duplicates,
+//! leaving only the maximal walks, all sent in a single request (splitting
+//! them over several requests would only add the per-request query).
+//!
+//! The returned [`PlanSet`] remembers where each probe's result lies in the
+//! results of the compressed plans, and [`PlanSet::decode`] cuts them back
+//! out in the order of the probes.
+
+use anyhow::{Result, ensure};
+
+use crate::judge::{Step, explore_cost};
+
+/// Compressed plans and where each original probe's result is found in them.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct PlanSet {
+    pub plans: Vec<Vec<Step>>,
+    /// For each probe, the plan that walks it and the length of its result
+    /// (one label per step, plus the starting room's).
+    pub index: Vec<(usize, usize)>,
+}
+
+impl PlanSet {
+    /// Queries charged for sending [`PlanSet::plans`] in one request.
+    pub fn cost(&self) -> usize {
+        explore_cost(&self.plans)
+    }
+
+    /// Cuts the result of every probe out of the results of the compressed
+    /// plans.
+    pub fn decode(&self, results: &[Vec<usize>]) -> Vec<Vec<usize>> {
+        assert_eq!(results.len(), self.plans.len());
+        self.index
+            .iter()
+            .map(|&(plan, len)| results[plan][..len].to_vec())
+            .collect()
+    }
+}
+
+/// Rewrites `probes` into the fewest plans that walk all of them (see the
+/// module docs). Fails if a probe is longer than `max_len` steps, since a
+/// split walk restarts from the starting room and would not be the same
+/// probe.
+pub fn compress(probes: &[Vec<Step>], max_len: usize) -> Result<PlanSet> {
+    for (i, probe) in probes.iter().enumerate() {
+        ensure!(
+            probe.len() <= max_len,
+            "probe {} has {} steps, more than {}",
+            i,
+            probe.len(),
+            max_len
+        );
+    }
+    // In lexicographic order, a probe that is a prefix of any other is a
+    // prefix of the one right after it, so the maximal walks are the probes
+    // that are not a prefix of their successor.
+    let mut order = (0..probes.len()).collect::<Vec<_>>();
+    order.sort_by(|&a, &b| probes[a].cmp(&probes[b]));
+    let mut plans = vec![];
+    let mut index = vec![(0, 0); probes.len()];
+    for k in (0..order.len()).rev() {
+        let probe = &probes[order[k]];
+        let covered = k + 1 < order.len() && probes[order[k + 1]].starts_with(probe);
+        if !covered {
+            plans.push(probe.clone());
+        }
+        index[order[k]] = (plans.len() - 1, probe.len() + 1);
+    }
+    Ok(PlanSet { plans, index })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::judge::{Judge, LocalJudge, parse_plan};
+
+    #[test]
+    fn prefixes_and_duplicates_share_one_plan() {
+        let probes = ["012", "0[1]12", "01", "0[1]1", "012", "", "345", "34"]
+            .map(parse_plan)
+            .to_vec();
+        let set = compress(&probes, 6).unwrap();
+        assert_eq!(set.plans.len(), 3);
+        assert_eq!(set.cost(), 4);
+        assert_eq!(set.index[0], set.index[4]);
+        assert_eq!(set.index[0].0, set.index[2].0);
+        assert_eq!(set.index[1], (set.index[3].0, 4));
+        assert_eq!(set.index[3].1, 3);
+
+        let mut judge = LocalJudge::new("random", 6, 3);
+        let decoded = set.decode(&judge.explore(&set.plans));
+        assert_eq!(decoded, judge.explore(&probes));
+
+        assert!(compress(&[parse_plan("0123450")], 6).is_err());
+    }
+}
@@ -0,0 +1,443 @@
+//! # Exploration Planner
+//!
+//! Strategies for generating a single exploration plan (a door sequence)
+//! before anything is known about the map. Solver binaries pick one by name
+//! ([`parse_strategy`]) instead of carrying their own copy of a plan generator:
+//!
+//! - `random-balanced`: every door equally often (up to rounding), shuffled.
+//! - `coverage-greedy[:SEEDS]`: greedily maximizes the expected coverage of
+//!   rooms, passages and doors on `SEEDS` random maps (default 1000) with a
+//!   two-step lookahead. This is the search that produced most of [`super::table_plan`].
+//! - `precomputed`: the table plan of the requested length, or else the
+//!   `6 * n` one truncated to the requested length and padded with
+//!   `random-balanced` doors; sizes missing from the table fall back to
+//!   `random-balanced` entirely.
+//!
+//! Plans only use the first [`num_doors`] doors; the table is skipped unless
+//! all six exist.
+
+use anyhow::{Context, Result, bail};
+use rand::prelude::*;
+
+use crate::TopK;
+use crate::judge::{MAX_DOORS, generate_random_edges_v2, num_doors};
+
+/// A way to generate an exploration plan for a map of a given size.
+pub trait PlanStrategy {
+    /// Name accepted by [`parse_strategy`].
+    fn name(&self) -> String;
+    /// Generates a plan of `len` doors for a map with `num_rooms` rooms.
+    fn plan(&self, num_rooms: usize, len: usize, rng: &mut dyn RngCore) -> Vec<usize>;
+}
+
+/// Every door equally often, shuffled.
+pub struct RandomBalanced;
+
+impl PlanStrategy for RandomBalanced {
+    fn name(&self) -> String {
+        "random-balanced".to_string()
+    }
+
+    fn plan(&self, _num_rooms: usize, len: usize, rng: &mut dyn RngCore) -> Vec<usize> {
+        balanced_plan(num_doors(), len, rng)
+    }
+}
+
+/// [`RandomBalanced`] for maps with `doors` doors per room.
+fn balanced_plan(doors: usize, len: usize, rng: &mut dyn RngCore) -> Vec<usize> {
+    let mut plan = Vec::with_capacity(len);
+    for d in 0..doors {
+        for _ in 0..(len / doors) {
+            plan.push(d);
+        }
+    }
+    plan.shuffle(rng);
+    if !len.is_multiple_of(doors) {
+        // The remainder goes to distinct random doors.
+        let mut rest = (0..doors).collect::<Vec<_>>();
+        rest.shuffle(rng);
+        plan.extend_from_slice(&rest[..len % doors]);
+    }
+    plan
+}
+
+/// Greedy coverage maximization on random maps.
+pub struct CoverageGreedy {
+    /// Number of random maps the expected coverage is averaged over.
+    pub n_seeds: usize,
+}
+
+impl Default for CoverageGreedy {
+    fn default() -> Self {
+        CoverageGreedy { n_seeds: 1000 }
+    }
+}
+
+impl PlanStrategy for CoverageGreedy {
+    fn name(&self) -> String {
+        format!("coverage-greedy:{}", self.n_seeds)
+    }
+
+    fn plan(&self, num_rooms: usize, len: usize, rng: &mut dyn RngCore) -> Vec<usize> {
+        let instances = (0..self.n_seeds)
+            .map(|i| Instance::random(num_rooms, i as u64))
+            .collect::<Vec<_>>();
+        coverage_greedy_plan(&instances, len, rng)
+    }
+}
+
+/// The offline table of `routes`, padded as described in the module docs.
+pub struct Precomputed;
+
+impl PlanStrategy for Precomputed {
+    fn name(&self) -> String {
+        "precomputed".to_string()
+    }
+
+    fn plan(&self, num_rooms: usize, len: usize, rng: &mut dyn RngCore) -> Vec<usize> {
+        precomputed_plan(num_doors(), num_rooms, len, rng)
+    }
+}
+
+/// [`Precomputed`] for maps with `doors` doors per room.
+fn precomputed_plan(
+    doors: usize,
+    num_rooms: usize,
+    len: usize,
+    rng: &mut dyn RngCore,
+) -> Vec<usize> {
+    // The table was searched on six-door maps.
+    let mut plan = if doors == MAX_DOORS {
+        super::table_plan(num_rooms, len)
+            .or_else(|| super::table_plan(num_rooms, 6 * num_rooms))
+            .unwrap_or_default()
+    } else {
+        vec![]
+    };
+    plan.truncate(len);
+    let rest = balanced_plan(doors, len - plan.len(), rng);
+    plan.extend(rest);
+    plan
+}
+
+/// Looks up a strategy by the names listed in the module docs.
+pub fn parse_strategy(name: &str) -> Result<Box<dyn PlanStrategy + Send + Sync>> {
+    let (kind, arg) = match name.split_once(':') {
+        Some((kind, arg)) => (kind, Some(arg)),
+        None => (name, None),
+    };
+    Ok(match (kind, arg) {
+        ("random-balanced", None) => Box::new(RandomBalanced),
+        ("precomputed", None) => Box::new(Precomputed),
+        ("coverage-greedy", None) => Box::new(CoverageGreedy::default()),
+        ("coverage-greedy", Some(n)) => Box::new(CoverageGreedy {
+            n_seeds: n
+                .parse()
+                .with_context(|| format!("invalid number of seeds: {}", n))?,
+        }),
+        _ => bail!(
+            "unknown plan strategy {:?} (expected random-balanced, coverage-greedy[:SEEDS] or precomputed)",
+            name
+        ),
+    })
+}
+
+/// A passage between `(room, door)` pairs, as in `judge::generate_random_edges_v2`.
+pub type Passage = ((usize, usize), (usize, usize));
+
+/// A random map prepared for coverage evaluation.
+pub struct Instance {
+    pub num_rooms: usize,
+    /// Next room for (room, door).
+    pub graph: Vec<[usize; 6]>,
+    /// Undirected passage id of (room, door).
+    pub port_to_edge: Vec<[usize; 6]>,
+    pub edge_count: usize,
+}
+
+impl Instance {
+    pub fn new(num_rooms: usize, edges: &[Passage]) -> Self {
+        let mut graph = vec![[!0usize; 6]; num_rooms];
+        let mut port_to_edge = vec![[!0usize; 6]; num_rooms];
+        for (u, doors) in graph.iter_mut().enumerate() {
+            for d in num_doors()..MAX_DOORS {
+                doors[d] = u;
+            }
+        }
+        for (eid, &((u1, d1), (u2, d2))) in edges.iter().enumerate() {
+            graph[u1][d1] = u2;
+            graph[u2][d2] = u1;
+            port_to_edge[u1][d1] = eid;
+            port_to_edge[u2][d2] = eid;
+        }
+        Instance {
+            num_rooms,
+            graph,
+            port_to_edge,
+            edge_count: edges.len(),
+        }
+    }
+
+    /// The map `judge::generate_random_edges_v2` produces for `seed`.
+    pub fn random(num_rooms: usize, seed: u64) -> Self {
+        Instance::new(num_rooms, &generate_random_edges_v2(num_rooms, seed))
+    }
+
+    /// Fractions of rooms, undirected passages and (room, door) pairs that `plan`
+    /// visits when started from room 0.
+    pub fn coverage(&self, plan: &[usize]) -> (f32, f32, f32) {
+        let n = self.num_rooms;
+        let mut dir_covered = vec![[false; 6]; n];
+        let mut vertex_covered = vec![false; n];
+        let mut edge_covered = vec![false; self.edge_count];
+        let mut u = 0;
+        vertex_covered[0] = true;
+        for &d in plan {
+            dir_covered[u][d] = true;
+            let eid = self.port_to_edge[u][d];
+            if eid != !0usize {
+                edge_covered[eid] = true;
+            }
+            u = self.graph[u][d];
+            vertex_covered[u] = true;
+        }
+        let count = |v: &[bool]| v.iter().filter(|&&b| b).count() as f32;
+        (
+            count(&vertex_covered) / n as f32,
+            count(&edge_covered) / self.edge_count as f32,
+            dir_covered.iter().map(|x| count(x)).sum::<f32>() / (n * num_doors()) as f32,
+        )
+    }
+}
+
+/// Average of [`Instance::coverage`] over `instances`.
+pub fn average_coverage(instances: &[Instance], plan: &[usize]) -> (f32, f32, f32) {
+    let k = instances.len() as f32;
+    let (mut a, mut b, mut c) = (0.0, 0.0, 0.0);
+    for inst in instances {
+        let (x, y, z) = inst.coverage(plan);
+        a += x;
+        b += y;
+        c += z;
+    }
+    (a / k, b / k, c / k)
+}
+
+/// The coverage-greedy search: each step picks the door whose best two-step
+/// continuation maximizes (rooms, passages, doors) coverage, compared
+/// lexicographically and averaged over `instances`.
+pub fn coverage_greedy_plan(
+    instances: &[Instance],
+    len: usize,
+    rng: &mut dyn RngCore,
+) -> Vec<usize> {
+    assert!(!instances.is_empty(), "no instances to evaluate on");
+    let n_seeds = instances.len();
+    let num_rooms = instances[0].num_rooms;
+
+    // Per-instance incremental state
+    struct InstState {
+        cur: usize,
+        vertex_visit: Vec<u32>,   // number of visits per vertex
+        dir_visit: Vec<[u32; 6]>, // number of visits per (vertex, door)
+        edge_visit: Vec<u32>,     // number of traversals per undirected edge
+        covered_v: u32,           // # of vertices visited at least once
+        covered_dir: u32,         // # of directed (vertex, door) visited at least once
+        covered_edge: u32,        // # of undirected edges traversed at least once
+    }
+
+    let mut states: Vec<InstState> = instances
+        .iter()
+        .map(|inst| InstState {
+            cur: 0,
+            vertex_visit: {
+                let mut v = vec![0u32; inst.num_rooms];
+                v[0] = 1; // start at room 0
+                v
+            },
+            dir_visit: vec![[0u32; 6]; inst.num_rooms],
+            edge_visit: vec![0u32; inst.edge_count],
+            covered_v: 1,
+            covered_dir: 0,
+            covered_edge: 0,
+        })
+        .collect();
+
+    let mut plan = vec![];
+
+    // Denominators for averages (same across all instances)
+    let denom_vtx = (n_seeds as f32) * (num_rooms as f32);
+    let edge_count = instances[0].edge_count as f32; // 3 * num_rooms
+    let denom_uni = (n_seeds as f32) * edge_count;
+    let doors = num_doors();
+    let denom_dir = (n_seeds as f32) * ((num_rooms * doors) as f32);
+
+    for _ in 0..len {
+        // Ties go to the earlier candidate in the shuffled order.
+        let mut best = TopK::new(1);
+
+        // Randomize evaluation order of first moves
+        let mut order = (0..doors).collect::<Vec<_>>();
+        order.shuffle(rng);
+
+        for &d in &order {
+            // Precompute first-step hypotheticals across all instances
+            let mut v_after = vec![0usize; n_seeds];
+            let mut e1 = vec![!0usize; n_seeds];
+            let mut inc1_vtx = vec![0u32; n_seeds];
+            let mut inc1_dir = vec![0u32; n_seeds];
+            let mut inc1_uni = vec![0u32; n_seeds];
+
+            // Base sums after first hypothetical step (previous + inc1)
+            let mut base_v_sum: u32 = 0;
+            let mut base_dir_sum: u32 = 0;
+            let mut base_uni_sum: u32 = 0;
+
+            for (j, (inst, st)) in instances.iter().zip(states.iter()).enumerate() {
+                let u = st.cur;
+                let v = inst.graph[u][d];
+                v_after[j] = v;
+
+                // Directed (u,d)
+                let dir_new = (st.dir_visit[u][d] == 0) as u32;
+                inc1_dir[j] = dir_new;
+
+                // Undirected edge through (u,d)
+                let eid = inst.port_to_edge[u][d];
+                e1[j] = eid;
+                let uni_new = if eid != !0usize && st.edge_visit[eid] == 0 {
+                    1
+                } else {
+                    0
+                };
+                inc1_uni[j] = uni_new;
+
+                // Vertex v
+                let vtx_new = (st.vertex_visit[v] == 0) as u32;
+                inc1_vtx[j] = vtx_new;
+
+                base_v_sum += st.covered_v + vtx_new;
+                base_dir_sum += st.covered_dir + dir_new;
+                base_uni_sum += st.covered_edge + uni_new;
+            }
+
+            // Evaluate second step d2 for this first move d
+            for d2 in 0..doors {
+                let mut inc2_v_sum: u32 = 0;
+                let mut inc2_dir_sum: u32 = 0;
+                let mut inc2_uni_sum: u32 = 0;
+
+                for (j, (inst, st)) in instances.iter().zip(states.iter()).enumerate() {
+                    let u = st.cur;
+                    let v = v_after[j];
+
+                    // Directed at (v, d2)
+                    let mut dir_was = st.dir_visit[v][d2] > 0;
+                    if !dir_was && v == u && d2 == d && inc1_dir[j] == 1 {
+                        // First step already visits (u, d)
+                        dir_was = true;
+                    }
+                    inc2_dir_sum += (!dir_was) as u32;
+
+                    // Undirected edge at e2 = (v, d2)
+                    let e2 = inst.port_to_edge[v][d2];
+                    let mut edge_was = e2 != !0usize && st.edge_visit[e2] > 0;
+                    if !edge_was && e2 == e1[j] && inc1_uni[j] == 1 {
+                        edge_was = true;
+                    }
+                    inc2_uni_sum += (!edge_was && e2 != !0usize) as u32;
+
+                    // Vertex w after taking (v, d2)
+                    let w = inst.graph[v][d2];
+                    let mut vtx_was = st.vertex_visit[w] > 0;
+                    if !vtx_was && w == v && inc1_vtx[j] == 1 {
+                        vtx_was = true;
+                    }
+                    inc2_v_sum += (!vtx_was) as u32;
+                }
+
+                let cov_vtx = (base_v_sum + inc2_v_sum) as f32 / denom_vtx;
+                let cov_uni = (base_uni_sum + inc2_uni_sum) as f32 / denom_uni;
+                let cov_dir = (base_dir_sum + inc2_dir_sum) as f32 / denom_dir;
+
+                best.push((cov_vtx, cov_uni, cov_dir), d);
+            }
+        }
+
+        // Commit the chosen first move across all instances
+        let chosen_d = best.best().unwrap().1;
+        plan.push(chosen_d);
+
+        for (inst, st) in instances.iter().zip(states.iter_mut()) {
+            let u = st.cur;
+
+            // Directed (u, chosen_d)
+            if st.dir_visit[u][chosen_d] == 0 {
+                st.covered_dir += 1;
+            }
+            st.dir_visit[u][chosen_d] += 1;
+
+            // Undirected edge
+            let eid = inst.port_to_edge[u][chosen_d];
+            if eid != !0usize {
+                if st.edge_visit[eid] == 0 {
+                    st.covered_edge += 1;
+                }
+                st.edge_visit[eid] += 1;
+            }
+
+            // Move to next vertex
+            let v = inst.graph[u][chosen_d];
+            if st.vertex_visit[v] == 0 {
+                st.covered_v += 1;
+            }
+            st.vertex_visit[v] += 1;
+            st.cur = v;
+        }
+    }
+    plan
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rand_chacha::ChaCha12Rng;
+
+    #[test]
+    fn strategies_by_name() {
+        let mut rng = ChaCha12Rng::seed_from_u64(0);
+        for name in ["random-balanced", "precomputed", "coverage-greedy:50"] {
+            let strategy = parse_strategy(name).unwrap();
+            assert_eq!(strategy.name(), name);
+            for (n, len) in [(12, 72), (12, 100), (7, 20)] {
+                let plan = strategy.plan(n, len, &mut rng);
+                assert_eq!(plan.len(), len, "{} {} {}", name, n, len);
+                assert!(plan.iter().all(|&d| d < 6));
+            }
+        }
+        for plan in [
+            balanced_plan(4, 50, &mut rng),
+            precomputed_plan(4, 12, 50, &mut rng),
+        ] {
+            assert_eq!(plan.len(), 50);
+            assert!(plan.iter().all(|&d| d < 4));
+        }
+        assert!(parse_strategy("coverage-greedy:x").is_err());
+        assert!(parse_strategy("zigzag").is_err());
+
+        let plan = Precomputed.plan(12, 72, &mut rng);
+        assert_eq!(plan, super::super::get_plan(12, 72));
+        let plan = Precomputed.plan(12, 216, &mut rng);
+        assert_eq!(plan, super::super::long_plan(12));
+
+        // The greedy search beats a balanced random plan on fresh maps.
+        let test_maps = (1000..1100)
+            .map(|seed| Instance::random(12, seed))
+            .collect::<Vec<_>>();
+        let greedy = parse_strategy("coverage-greedy:50")
+            .unwrap()
+            .plan(12, 72, &mut rng);
+        let random = RandomBalanced.plan(12, 72, &mut rng);
+        assert!(average_coverage(&test_maps, &greedy).2 > average_coverage(&test_maps, &random).2);
+    }
+}
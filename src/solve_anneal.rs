@@ -0,0 +1,473 @@
+//! Simulated annealing over room assignments, a SAT-free solver for
+//! explorations without charcoal marks.
+//!
+//! Extracted from the hill climbing in `bin/chokudai1.rs`. Every observed
+//! time is assigned a room carrying the observed label (room `u` has label
+//! `u % k` for `k` labels, as in `solve_no_marks`). The door table is the
+//! majority vote over the assigned transitions, and the cost counts
+//! transitions that disagree with it, doors whose counts in the two directions
+//! differ, and rooms entered through more or fewer doors than they have. A
+//! zero-cost assignment is a map that explains the explorations.
+//!
+//! Each restart samples an initial assignment from the transition statistics
+//! seen so far (sharper or flatter per restart) and anneals it with a
+//! geometric temperature schedule.
+
+use rand::prelude::*;
+use rand_chacha::ChaCha20Rng;
+use std::time::{Duration, Instant};
+
+use crate::judge::{Guess, MAX_DOORS, check_explore, num_doors, num_labels};
+
+/// Cost of a transition that disagrees with the door table.
+const WRONG_TRANSITION: usize = 10001;
+/// Cost per door of a mismatch between `u -> v` and `v -> u` door counts.
+const DOOR_IMBALANCE: usize = 30100;
+/// Cost per squared difference between a room's incoming doors and its door count.
+const DEGREE_MISMATCH: usize = 50000;
+/// Random times tried when looking for one next to a wrong transition.
+const ERROR_TRIES: usize = 16;
+/// Sharpness of the initial sampling, cycled through by the restarts (0 is uniform).
+const BETAS: [f64; 4] = [0.0, 1.0, 2.0, 4.0];
+
+#[derive(Clone, Debug)]
+pub struct AnnealParams {
+    /// Moves per restart.
+    pub iterations: usize,
+    /// Restarts before giving up.
+    pub restarts: usize,
+    /// Temperature at the start of each restart, in cost units.
+    pub start_temp: f64,
+    /// Temperature at the end of each restart; it decays geometrically.
+    pub end_temp: f64,
+    /// Gives up once this much time has passed.
+    pub time_limit: Option<Duration>,
+    pub seed: u64,
+}
+
+impl Default for AnnealParams {
+    fn default() -> Self {
+        AnnealParams {
+            iterations: 200_000,
+            restarts: 20,
+            start_temp: 200_000.0,
+            end_temp: 10_000.0,
+            time_limit: None,
+            seed: 0,
+        }
+    }
+}
+
+/// The explorations flattened into one timeline.
+struct Timeline {
+    n: usize,
+    /// Number of labels; room `u` has label `u % k`.
+    k: usize,
+    doors: usize,
+    labels: Vec<usize>,
+    /// Door taken from time `i` to `i + 1`, `None` at the end of each plan.
+    door: Vec<Option<usize>>,
+    /// Whether time `i` starts a plan; all of them are the starting room.
+    is_start: Vec<bool>,
+}
+
+impl Timeline {
+    fn new(num_rooms: usize, plans: &[Vec<usize>], labels: &[Vec<usize>]) -> Self {
+        assert_eq!(plans.len(), labels.len());
+        let mut t = Timeline {
+            n: num_rooms,
+            k: num_labels(),
+            doors: num_doors(),
+            labels: vec![],
+            door: vec![],
+            is_start: vec![],
+        };
+        for (p, l) in plans.iter().zip(labels) {
+            assert_eq!(l.len(), p.len() + 1);
+            t.labels.extend_from_slice(l);
+            t.door.extend(p.iter().map(|&d| Some(d)));
+            t.door.push(None);
+            t.is_start.push(true);
+            t.is_start.extend(std::iter::repeat_n(false, p.len()));
+        }
+        t
+    }
+
+    /// Whether there is something to explain and every observed label has a room.
+    fn solvable(&self) -> bool {
+        !self.labels.is_empty() && self.labels.iter().all(|&l| self.count(l) > 0)
+    }
+
+    /// Number of rooms carrying label `l`.
+    fn count(&self, l: usize) -> usize {
+        (self.n + self.k - 1 - l) / self.k
+    }
+
+    fn random_room(&self, l: usize, rng: &mut impl Rng) -> usize {
+        l + self.k * rng.random_range(0..self.count(l))
+    }
+
+    /// The majority door table of `ans` and its cost. Doors never taken are
+    /// free: each goes back to a room owed a door by this one if there is
+    /// one, and is a self-loop otherwise.
+    fn evaluate(&self, ans: &[usize]) -> (usize, Vec<[usize; MAX_DOORS]>) {
+        let n = self.n;
+        let mut votes = vec![0usize; n * MAX_DOORS * n];
+        for (i, d) in self.door.iter().enumerate() {
+            if let &Some(d) = d {
+                votes[(ans[i] * MAX_DOORS + d) * n + ans[i + 1]] += 1;
+            }
+        }
+        let mut to = vec![[!0; MAX_DOORS]; n];
+        let mut door_cnt = vec![vec![0isize; n]; n];
+        for u in 0..n {
+            for d in 0..MAX_DOORS {
+                to[u][d] = if d >= self.doors {
+                    u
+                } else {
+                    let row = &votes[(u * MAX_DOORS + d) * n..][..n];
+                    match (0..n).filter(|&v| row[v] > 0).max_by_key(|&v| row[v]) {
+                        Some(v) => v,
+                        None => continue,
+                    }
+                };
+                door_cnt[u][to[u][d]] += 1;
+            }
+        }
+        for u in 0..n {
+            for d in 0..self.doors {
+                if to[u][d] == !0 {
+                    let v = (0..n)
+                        .find(|&v| door_cnt[v][u] > door_cnt[u][v])
+                        .unwrap_or(u);
+                    to[u][d] = v;
+                    door_cnt[u][v] += 1;
+                }
+            }
+        }
+
+        let mut cost = 0;
+        for (i, d) in self.door.iter().enumerate() {
+            if let &Some(d) = d
+                && to[ans[i]][d] != ans[i + 1]
+            {
+                cost += WRONG_TRANSITION;
+            }
+        }
+        for u in 0..n {
+            for v in 0..n {
+                cost += DOOR_IMBALANCE * (door_cnt[u][v] - door_cnt[v][u]).unsigned_abs();
+            }
+            let incoming = (0..n).map(|v| door_cnt[v][u]).sum::<isize>();
+            let diff = (incoming - MAX_DOORS as isize).unsigned_abs();
+            cost += DEGREE_MISMATCH * diff * diff;
+        }
+        (cost, to)
+    }
+
+    /// Samples each time's room among those with its label, in proportion to
+    /// how well the room's `(door, next label)` history so far fits; `beta`
+    /// sharpens the weights, and 0 is uniform.
+    fn sample_initial(&self, beta: f64, rng: &mut impl Rng) -> Vec<usize> {
+        let mut profile = vec![vec![vec![0.0f64; self.k]; MAX_DOORS]; self.n];
+        let mut ans = vec![0; self.labels.len()];
+        let mut weights = vec![];
+        for i in 0..self.labels.len() {
+            let l = self.labels[i];
+            if self.is_start[i] && i > 0 {
+                ans[i] = ans[0];
+            } else {
+                weights.clear();
+                for r in (l..self.n).step_by(self.k) {
+                    weights.push(match self.door[i] {
+                        Some(d) => {
+                            let total: f64 = profile[r][d].iter().sum();
+                            let fit = profile[r][d][self.labels[i + 1]] + 1.0 / self.k as f64;
+                            (fit / (total + 1.0)).powf(beta)
+                        }
+                        None => 1.0,
+                    });
+                }
+                let mut x = rng.random_range(0.0..weights.iter().sum::<f64>());
+                let mut j = weights.len() - 1;
+                for (c, &w) in weights.iter().enumerate() {
+                    if x < w {
+                        j = c;
+                        break;
+                    }
+                    x -= w;
+                }
+                ans[i] = l + self.k * j;
+            }
+            if let Some(d) = self.door[i] {
+                profile[ans[i]][d][self.labels[i + 1]] += 1.0;
+            }
+        }
+        ans
+    }
+
+    /// Whether the transition into or out of time `i` disagrees with `to`.
+    fn touches_error(&self, ans: &[usize], to: &[[usize; MAX_DOORS]], i: usize) -> bool {
+        let wrong = |t: usize| self.door[t].is_some_and(|d| to[ans[t]][d] != ans[t + 1]);
+        wrong(i) || (i > 0 && wrong(i - 1))
+    }
+
+    /// A neighbor of `ans`: either one time (preferably next to a wrong
+    /// transition) moves to another room with its
+    /// label (all plan starts move together), or a door of the table is
+    /// rewired to another room with the same label and every time leaving
+    /// through it follows. `None` if the move changes nothing.
+    fn propose(
+        &self,
+        ans: &[usize],
+        to: &[[usize; MAX_DOORS]],
+        rng: &mut impl Rng,
+    ) -> Option<Vec<usize>> {
+        let mut new = ans.to_vec();
+        if rng.random_range(0..10) < 9 {
+            let mut i = rng.random_range(0..ans.len());
+            for _ in 0..ERROR_TRIES {
+                if self.touches_error(ans, to, i) {
+                    break;
+                }
+                i = rng.random_range(0..ans.len());
+            }
+            let l = self.labels[i];
+            if self.count(l) < 2 {
+                return None;
+            }
+            let r = self.random_room(l, rng);
+            if r == ans[i] {
+                return None;
+            }
+            if self.is_start[i] {
+                for (j, &s) in self.is_start.iter().enumerate() {
+                    if s {
+                        new[j] = r;
+                    }
+                }
+            } else {
+                new[i] = r;
+            }
+            if rng.random_bool(0.5) {
+                // Re-walk the rest of the plan on the door table while the labels agree.
+                let mut t = i;
+                while let Some(d) = self.door[t] {
+                    let v = to[new[t]][d];
+                    if v % self.k != self.labels[t + 1] {
+                        break;
+                    }
+                    new[t + 1] = v;
+                    t += 1;
+                }
+            }
+        } else {
+            let u = rng.random_range(0..self.n);
+            let d = rng.random_range(0..self.doors);
+            let l = to[u][d] % self.k;
+            let v = self.random_room(l, rng);
+            for t in 0..self.door.len() {
+                if ans[t] == u && self.door[t] == Some(d) && self.labels[t + 1] == l {
+                    new[t + 1] = v;
+                }
+            }
+        }
+        Some(new)
+    }
+
+    /// Pairs the doors of a consistent table into passages. `None` if some
+    /// door has no free partner.
+    fn to_guess(&self, start: usize, to: &[[usize; MAX_DOORS]]) -> Option<Guess> {
+        let mut graph = vec![[(!0, !0); MAX_DOORS]; self.n];
+        for (u, doors) in graph.iter_mut().enumerate() {
+            for d in self.doors..MAX_DOORS {
+                doors[d] = (u, d);
+            }
+        }
+        for u in 0..self.n {
+            for d in 0..self.doors {
+                if graph[u][d].0 != !0 {
+                    continue;
+                }
+                let v = to[u][d];
+                let e = (0..self.doors).find(|&e| to[v][e] == u && graph[v][e].0 == !0)?;
+                graph[u][d] = (v, e);
+                graph[v][e] = (u, d);
+            }
+        }
+        Some(Guess {
+            rooms: (0..self.n).map(|u| u % self.k).collect(),
+            start,
+            graph,
+        })
+    }
+}
+
+/// Anneals the timeline. Returns the guess if some restart explained the
+/// explorations, and the lowest-cost assignment seen either way.
+fn anneal(
+    timeline: &Timeline,
+    plans: &[Vec<usize>],
+    labels: &[Vec<usize>],
+    params: &AnnealParams,
+) -> (Option<Guess>, Vec<usize>) {
+    let deadline = params.time_limit.map(|t| Instant::now() + t);
+    let mut rng = ChaCha20Rng::seed_from_u64(params.seed);
+    let mut best: Option<(usize, Vec<usize>)> = None;
+    for restart in 0..params.restarts {
+        let beta = BETAS[restart % BETAS.len()];
+        let mut ans = timeline.sample_initial(beta, &mut rng);
+        let (mut cost, mut to) = timeline.evaluate(&ans);
+        let mut restart_best = cost;
+        for it in 0..params.iterations {
+            if best.as_ref().is_none_or(|b| cost < b.0) {
+                best = Some((cost, ans.clone()));
+            }
+            if cost == 0
+                && let Some(guess) = timeline.to_guess(ans[0], &to)
+                && check_explore(&guess, plans, labels)
+            {
+                eprintln!(
+                    "solve_anneal: solved at restart {} iteration {}",
+                    restart, it
+                );
+                return (Some(guess), ans);
+            }
+            if it % 1024 == 0 && deadline.is_some_and(|d| Instant::now() >= d) {
+                return (None, best.unwrap().1);
+            }
+            let Some(new_ans) = timeline.propose(&ans, &to, &mut rng) else {
+                continue;
+            };
+            let (new_cost, new_to) = timeline.evaluate(&new_ans);
+            let progress = it as f64 / params.iterations as f64;
+            let temp = params.start_temp * (params.end_temp / params.start_temp).powf(progress);
+            if new_cost <= cost || rng.random_bool(((cost as f64 - new_cost as f64) / temp).exp()) {
+                ans = new_ans;
+                cost = new_cost;
+                to = new_to;
+                restart_best = restart_best.min(cost);
+            }
+        }
+        eprintln!(
+            "solve_anneal: restart {} (beta = {}) ended at cost {}, best {}",
+            restart, beta, cost, restart_best
+        );
+    }
+    match best {
+        Some((_, ans)) => (None, ans),
+        None => (None, timeline.sample_initial(0.0, &mut rng)),
+    }
+}
+
+/// Searches for a map explaining the explorations (`plans` without marks and
+/// the observed `labels`). Returns `None` if no restart found one within the
+/// budget of `params`.
+pub fn solve(
+    num_rooms: usize,
+    plans: &[Vec<usize>],
+    labels: &[Vec<usize>],
+    params: &AnnealParams,
+) -> Option<Guess> {
+    let timeline = Timeline::new(num_rooms, plans, labels);
+    if !timeline.solvable() {
+        return None;
+    }
+    anneal(&timeline, plans, labels, params).0
+}
+
+/// Edges `(u, e, v)` (door `e` of room `u` leads to `v`) of the best
+/// assignment found, kept when every transition through the door agrees and
+/// it was taken at least `min_support` times.
+///
+/// Rooms are renumbered within each label in order of first visit, the
+/// symmetry breaking of `solve_no_marks`, so the edges can be fixed there as
+/// they are.
+pub fn confident_edges(
+    num_rooms: usize,
+    plans: &[Vec<usize>],
+    labels: &[Vec<usize>],
+    params: &AnnealParams,
+    min_support: usize,
+) -> Vec<(usize, usize, usize)> {
+    let timeline = Timeline::new(num_rooms, plans, labels);
+    if !timeline.solvable() {
+        return vec![];
+    }
+    let (_, ans) = anneal(&timeline, plans, labels, params);
+
+    let k = timeline.k;
+    let mut id = vec![!0; num_rooms];
+    let mut used = vec![0; k];
+    for &u in &ans {
+        if id[u] == !0 {
+            id[u] = u % k + k * used[u % k];
+            used[u % k] += 1;
+        }
+    }
+    for u in 0..num_rooms {
+        if id[u] == !0 {
+            id[u] = u % k + k * used[u % k];
+            used[u % k] += 1;
+        }
+    }
+
+    // (target, support) per door, with `!0` once two targets disagree.
+    let mut seen = vec![[(!0, 0); MAX_DOORS]; num_rooms];
+    for (t, d) in timeline.door.iter().enumerate() {
+        if let &Some(d) = d {
+            let (u, v) = (id[ans[t]], id[ans[t + 1]]);
+            let (to, support) = &mut seen[u][d];
+            if *support == 0 {
+                *to = v;
+            } else if *to != v {
+                *to = !0;
+            }
+            *support += 1;
+        }
+    }
+    let mut edges = vec![];
+    for u in 0..num_rooms {
+        for d in 0..timeline.doors {
+            let (v, support) = seen[u][d];
+            if v != !0 && support >= min_support {
+                edges.push((u, d, v));
+            }
+        }
+    }
+    edges
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::judge::{Judge, LocalJudge};
+
+    #[test]
+    fn anneal_solves_small_maps() {
+        let mut judge = LocalJudge::new("random", 6, 2);
+        let mut rng = ChaCha20Rng::seed_from_u64(3);
+        let plans = (0..2)
+            .map(|_| (0..108).map(|_| rng.random_range(0..6)).collect::<Vec<_>>())
+            .collect::<Vec<_>>();
+        let steps = plans
+            .iter()
+            .map(|p| p.iter().map(|&d| (None, d)).collect())
+            .collect::<Vec<_>>();
+        let labels = judge.explore(&steps);
+        let params = AnnealParams {
+            iterations: 20_000,
+            ..Default::default()
+        };
+        let guess = solve(6, &plans, &labels, &params).expect("no map found");
+        assert!(check_explore(&guess, &plans, &labels));
+        assert!(guess.rooms.iter().enumerate().all(|(u, &l)| l == u % 4));
+    }
+
+    #[test]
+    fn missing_label_rooms_give_up_at_once() {
+        // Two rooms cannot carry label 3.
+        let params = AnnealParams::default();
+        assert!(solve(2, &[vec![0]], &[vec![0, 3]], &params).is_none());
+    }
+}
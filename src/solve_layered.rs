@@ -0,0 +1,256 @@
+#![allow(non_snake_case)]
+//! SAT solver for D-fold layered maps.
+//!
+//! Some problems duplicate a base map of `n` rooms `D` times: every base room
+//! becomes `D` rooms with its label, and every base passage `(u, e)-(v, f)`
+//! becomes `D` passages `(u, i, e)-(v, perm[i], f)` for some permutation of
+//! the copies. Room `u * D + i` is copy `i` of base room `u`, whose label is
+//! `u % k`, so the model only has to choose one permutation per base passage;
+//! charcoal marks, which tell the copies apart, are handled by
+//! `solve_marks`. The D=3 version of this lives in `bin/chokudai_sat_d3.rs`.
+
+use itertools::Itertools;
+use rand::prelude::*;
+
+use crate::judge::{Guess, Judge, MAX_DOORS, Step, num_doors, num_labels};
+use crate::solve_marks::{Colors, add_observation, next_colors};
+use crate::solve_no_marks::Cnf;
+
+/// The permutations a base passage may lift with. A door connected to itself
+/// (`self_loop`) is undirected only if its permutation is an involution:
+/// `(u, i, e)-(u, perm[i], e)` must also be `(u, perm[i], e)-(u, i, e)`.
+pub fn lift_permutations(d: usize, self_loop: bool) -> Vec<Vec<usize>> {
+    (0..d)
+        .permutations(d)
+        .filter(|p| !self_loop || (0..d).all(|i| p[p[i]] == i))
+        .collect()
+}
+
+/// A candidate passage from a lifted door: `(var, room, door)`.
+type Lift = (i32, usize, usize);
+
+/// The edge layer for `n` base rooms with `d` copies each: one variable per
+/// base passage and permutation, exactly one per base door, and for every
+/// lifted door the candidates it may lead to.
+fn add_layered_edges(cnf: &mut Cnf, n: usize, d: usize, doors: usize) -> Vec<Vec<Vec<Lift>>> {
+    let mut lifted = vec![vec![vec![]; MAX_DOORS]; n * d];
+    let mut base = vec![vec![vec![]; doors]; n];
+    for (u, e) in (0..n).cartesian_product(0..doors) {
+        for (v, f) in (u..n).cartesian_product(0..doors) {
+            if (v, f) < (u, e) {
+                continue;
+            }
+            let self_loop = (u, e) == (v, f);
+            for perm in lift_permutations(d, self_loop) {
+                let x = cnf.var();
+                for (i, &j) in perm.iter().enumerate() {
+                    lifted[u * d + i][e].push((x, v * d + j, f));
+                    if !self_loop {
+                        lifted[v * d + j][f].push((x, u * d + i, e));
+                    }
+                }
+                base[u][e].push(x);
+                if !self_loop {
+                    base[v][f].push(x);
+                }
+            }
+        }
+    }
+    for door in base.iter().flatten() {
+        cnf.choose_one(door);
+    }
+    lifted
+}
+
+/// Moving through door `e` of the room chosen by `V` leads to the room chosen
+/// by `next`; doors beyond the lifted ones loop back.
+fn add_move(cnf: &mut Cnf, lifted: &[Vec<Vec<Lift>>], V: &[i32], next: &[i32], e: usize) {
+    for (a, &va) in V.iter().enumerate() {
+        if lifted[a][e].is_empty() {
+            cnf.clause([-va, next[a]]);
+        }
+        for &(x, b, _) in &lifted[a][e] {
+            cnf.clause([-va, -x, next[b]]);
+        }
+    }
+}
+
+/// Copies of a base room are interchangeable, so copy `i + 1` may only be
+/// visited once copy `i` has been, over the plans concatenated in order.
+fn add_first_visit_order(cnf: &mut Cnf, timeline: &[Vec<i32>], n: usize, d: usize) {
+    for u in 0..n {
+        for i in 0..d - 1 {
+            let (a, b) = (u * d + i, u * d + i + 1);
+            let mut seen: Option<i32> = None;
+            for V in timeline {
+                match seen {
+                    None => cnf.clause([-V[b]]),
+                    Some(s) => cnf.clause([-V[b], s]),
+                }
+                let next = cnf.var();
+                cnf.clause([-V[a], next]);
+                match seen {
+                    None => cnf.clause([-next, V[a]]),
+                    Some(s) => {
+                        cnf.clause([-s, next]);
+                        cnf.clause([-next, s, V[a]]);
+                    }
+                }
+                seen = Some(next);
+            }
+        }
+    }
+}
+
+/// Finds a `d`-fold layered map on `num_rooms` rooms that reproduces
+/// `labels` for `plans` (mark echoes stripped), or `None` if there is none.
+pub fn solve_layered_explored(
+    num_rooms: usize,
+    d: usize,
+    plans: &[Vec<Step>],
+    labels: &[Vec<usize>],
+) -> Option<Guess> {
+    assert!(
+        d > 0 && num_rooms.is_multiple_of(d),
+        "{} rooms are not {} layers",
+        num_rooms,
+        d
+    );
+    assert_eq!(plans.len(), labels.len());
+    let n = num_rooms / d;
+    let k = num_labels();
+    let start = *labels.first()?.first()? * d;
+    if start >= num_rooms {
+        return None;
+    }
+    let mut cnf = Cnf::new();
+    let lifted = add_layered_edges(&mut cnf, n, d, num_doors());
+    let initial: Vec<usize> = (0..num_rooms).map(|a| a / d % k).collect();
+    let mut timeline = vec![];
+    for (plan, seen) in plans.iter().zip(labels) {
+        assert_eq!(plan.len() + 1, seen.len(), "one label per room visited");
+        let mut V: Vec<i32> = (0..num_rooms).map(|_| cnf.var()).collect();
+        cnf.choose_one(&V);
+        cnf.clause([V[start]]);
+        let mut colors = Colors::Initial(initial.clone());
+        add_observation(&mut cnf, &V, &colors, seen[0]);
+        for (&(rewrite, e), &label) in plan.iter().zip(&seen[1..]) {
+            let next: Vec<i32> = (0..num_rooms).map(|_| cnf.var()).collect();
+            cnf.choose_one(&next);
+            add_move(&mut cnf, &lifted, &V, &next, e);
+            colors = next_colors(&mut cnf, &V, &colors, rewrite);
+            add_observation(&mut cnf, &next, &colors, label);
+            timeline.push(std::mem::replace(&mut V, next));
+        }
+        timeline.push(V);
+    }
+    add_first_visit_order(&mut cnf, &timeline, n, d);
+    crate::progress::phase(
+        "cnf",
+        serde_json::json!({
+            "solver": "layered",
+            "rooms": num_rooms,
+            "layers": d,
+            "vars": cnf.num_vars(),
+            "clauses": cnf.num_clauses(),
+        }),
+    );
+    if cnf.sat.solve() != Some(true) {
+        return None;
+    }
+    let mut graph: Vec<[(usize, usize); MAX_DOORS]> = (0..num_rooms)
+        .map(|a| std::array::from_fn(|e| (a, e)))
+        .collect();
+    for (a, doors) in lifted.iter().enumerate() {
+        for (e, lifts) in doors.iter().enumerate() {
+            if let Some(&(_, b, f)) = lifts
+                .iter()
+                .find(|&&(x, _, _)| cnf.sat.value(x) == Some(true))
+            {
+                graph[a][e] = (b, f);
+            }
+        }
+    }
+    Some(Guess {
+        rooms: initial,
+        start,
+        graph,
+    })
+}
+
+/// Plans for a layered map: `count` plans of `6 * num_rooms` steps that walk
+/// the first third unmarked and then mark every room they leave, with doors
+/// and marks balanced as in `bin/chokudai_sat_d3.rs`.
+pub fn layered_plans(num_rooms: usize, count: usize, rng: &mut impl Rng) -> Vec<Vec<Step>> {
+    let len = 6 * num_rooms;
+    let unmarked = 2 * num_rooms;
+    (0..count)
+        .map(|_| {
+            let mut doors = (0..len).map(|i| i % num_doors()).collect_vec();
+            doors.shuffle(rng);
+            let mut marks = (0..len - unmarked).map(|i| i % num_labels()).collect_vec();
+            marks.shuffle(rng);
+            doors
+                .into_iter()
+                .enumerate()
+                .map(|(t, e)| (t.checked_sub(unmarked).map(|i| marks[i]), e))
+                .collect()
+        })
+        .collect()
+}
+
+/// Explores `judge` with one [`layered_plans`] plan and solves for a map made
+/// of `d` copies of a base map.
+pub fn solve_layered(num_rooms: usize, d: usize, judge: &mut dyn Judge) -> Option<Guess> {
+    let plans = layered_plans(num_rooms, 1, &mut rand::rng());
+    let labels = judge.explore(&plans);
+    solve_layered_explored(num_rooms, d, &plans, &labels)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::judge::{LocalJudge, first_divergence};
+
+    #[test]
+    fn self_loops_lift_with_involutions() {
+        for (d, all, involutions) in [(2, 2, 2), (3, 6, 4), (4, 24, 10)] {
+            assert_eq!(lift_permutations(d, false).len(), all);
+            let lifts = lift_permutations(d, true);
+            assert_eq!(lifts.len(), involutions);
+        }
+        // A 3-cycle would make a self-loop door one-way.
+        let lifts = lift_permutations(3, true);
+        assert!(!lifts.contains(&vec![1, 2, 0]));
+        assert!(!lifts.contains(&vec![2, 0, 1]));
+        assert!(lifts.contains(&vec![0, 2, 1]));
+    }
+
+    #[test]
+    fn lifted_doors_pair_up() {
+        // Every lifted door has a candidate for each base door and
+        // permutation, and each candidate has its reverse.
+        let (n, d) = (2, 3);
+        let mut cnf = Cnf::new();
+        let lifted = add_layered_edges(&mut cnf, n, d, num_doors());
+        for a in 0..n * d {
+            for e in 0..num_doors() {
+                for &(x, b, f) in &lifted[a][e] {
+                    assert!(lifted[b][f].contains(&(x, a, e)), "{a} {e} -> {b} {f}");
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn layered_maps_are_solved() {
+        for (problem, num_rooms, d) in [("random_2layers", 8, 2), ("random_3layers", 12, 3)] {
+            let mut judge = LocalJudge::new(problem, num_rooms, 5);
+            let plans = layered_plans(num_rooms, 4, &mut StdRng::seed_from_u64(6));
+            let labels = judge.explore(&plans);
+            let guess = solve_layered_explored(num_rooms, d, &plans, &labels).unwrap();
+            assert_eq!(first_divergence(&guess, &plans, &labels), None);
+            assert!(judge.check_guess(&guess).is_ok(), "{}", problem);
+        }
+    }
+}
@@ -0,0 +1,236 @@
+#![allow(non_snake_case)]
+//! SAT solver for explorations with charcoal marks.
+//!
+//! The counterpart of `solve_no_marks` for plans whose steps may rewrite the
+//! label of the current room (`Step = (Option<usize>, usize)`), the model the
+//! `bin/chokudai_sat*.rs` solvers each carry a copy of. Room `u` starts with
+//! label `u % k` for `k` labels (as in `solve_no_marks`), every plan starts in
+//! room `labels[0][0]` with all labels restored, and the variables are
+//!
+//! - `V[t][u]`: the walk is in room `u` at time `t` (one per time step of the
+//!   concatenated plans),
+//! - `F[u][e][v]` and `M[u][v][e][f]`: door `e` of `u` leads to `v`, and it is
+//!   the passage to door `f` of `v`,
+//! - `C[u][c]`: the current label of room `u`, for each stretch of a plan
+//!   after a rewrite; before the first rewrite of a plan labels are constants.
+//!
+//! The label propagation is split into [`add_observation`] and
+//! [`next_colors`] so other models can reuse it.
+
+use crate::judge::{Guess, MAX_DOORS, Step, num_doors, num_labels};
+use crate::solve_no_marks::Cnf;
+
+/// The edge layer: `F[u][e]` picks the room behind door `e` of `u`, and
+/// `M[u][v][e][f]` (shared with `M[v][u][f][e]`) the door it arrives through.
+pub struct Edges {
+    pub F: Vec<Vec<Vec<i32>>>,
+    pub M: Vec<Vec<[[i32; MAX_DOORS]; MAX_DOORS]>>,
+}
+
+/// Adds the variables and clauses of an undirected map on `n` rooms with
+/// `doors` doors each; the remaining doors loop back to themselves.
+pub fn add_edges(cnf: &mut Cnf, n: usize, doors: usize) -> Edges {
+    let mut F = vec![vec![vec![0; n]; MAX_DOORS]; n];
+    for u in 0..n {
+        for e in 0..MAX_DOORS {
+            for v in 0..n {
+                F[u][e][v] = cnf.var();
+            }
+            cnf.choose_one(&F[u][e]);
+        }
+    }
+    let mut M = vec![vec![[[0; MAX_DOORS]; MAX_DOORS]; n]; n];
+    for u in 0..n {
+        for v in u..n {
+            for e in 0..MAX_DOORS {
+                for f in 0..MAX_DOORS {
+                    if u == v && f < e {
+                        continue;
+                    }
+                    let var = cnf.var();
+                    M[u][v][e][f] = var;
+                    M[v][u][f][e] = var;
+                }
+            }
+        }
+    }
+    for u in 0..n {
+        for v in 0..n {
+            for e in 0..MAX_DOORS {
+                // A passage implies both directions...
+                for f in 0..MAX_DOORS {
+                    cnf.clause([-M[u][v][e][f], F[u][e][v]]);
+                    cnf.clause([-M[u][v][e][f], F[v][f][u]]);
+                }
+                // ...and a direction arrives through exactly one door; the
+                // reverse direction is the same constraint for `(v, u, f)`.
+                let row = M[u][v][e];
+                cnf.clause(std::iter::once(-F[u][e][v]).chain(row));
+                crate::solve_no_marks::amo_pairwise(cnf, &row);
+            }
+        }
+    }
+    for u in 0..n {
+        for e in doors..MAX_DOORS {
+            cnf.clause([M[u][u][e][e]]);
+        }
+    }
+    Edges { F, M }
+}
+
+/// The labels of all rooms at some time.
+#[derive(Clone)]
+pub enum Colors {
+    /// Room `u` has label `labels[u]`, as at the start of every plan.
+    Initial(Vec<usize>),
+    /// Room `u` has label `c` iff `C[u][c]`.
+    Vars(Vec<Vec<i32>>),
+}
+
+/// Seeing `label` while in the room chosen by `V` (one literal per room).
+pub fn add_observation(cnf: &mut Cnf, V: &[i32], colors: &Colors, label: usize) {
+    for (u, &v) in V.iter().enumerate() {
+        match colors {
+            Colors::Initial(labels) if labels[u] != label => cnf.clause([-v]),
+            Colors::Initial(_) => {}
+            Colors::Vars(C) => cnf.clause([-v, C[u][label]]),
+        }
+    }
+}
+
+/// The labels after a step taken from the room chosen by `V`, which rewrote
+/// that room's label to `rewrite` if given; other rooms keep theirs.
+pub fn next_colors(cnf: &mut Cnf, V: &[i32], colors: &Colors, rewrite: Option<usize>) -> Colors {
+    let Some(new) = rewrite else {
+        return colors.clone();
+    };
+    let k = num_labels();
+    let mut C = vec![vec![0; k]; V.len()];
+    for (u, &v) in V.iter().enumerate() {
+        for c in 0..k {
+            C[u][c] = cnf.var();
+        }
+        cnf.choose_one(&C[u]);
+        cnf.clause([-v, C[u][new]]);
+        match colors {
+            Colors::Initial(labels) => cnf.clause([v, C[u][labels[u]]]),
+            Colors::Vars(old) => {
+                for c in 0..k {
+                    cnf.clause([v, -old[u][c], C[u][c]]);
+                    cnf.clause([v, old[u][c], -C[u][c]]);
+                }
+            }
+        }
+    }
+    Colors::Vars(C)
+}
+
+/// Moving from the room chosen by `V` through door `e` leads to the room
+/// chosen by `next`.
+fn add_move(cnf: &mut Cnf, edges: &Edges, V: &[i32], next: &[i32], e: usize) {
+    for (u, &vu) in V.iter().enumerate() {
+        for (v, &nv) in next.iter().enumerate() {
+            cnf.clause([-vu, -edges.F[u][e][v], nv]);
+        }
+    }
+}
+
+fn extract_guess(cnf: &Cnf, edges: &Edges, n: usize, start: usize) -> Guess {
+    let k = num_labels();
+    let is_true = |x: i32| cnf.sat.value(x) == Some(true);
+    let mut graph = vec![[(0, 0); MAX_DOORS]; n];
+    for u in 0..n {
+        for e in 0..MAX_DOORS {
+            let v = (0..n).find(|&v| is_true(edges.F[u][e][v])).unwrap_or(u);
+            let f = (0..MAX_DOORS)
+                .find(|&f| is_true(edges.M[u][v][e][f]))
+                .unwrap_or(e);
+            graph[u][e] = (v, f);
+        }
+    }
+    Guess {
+        rooms: (0..n).map(|u| u % k).collect(),
+        start,
+        graph,
+    }
+}
+
+/// Finds a map on `num_rooms` rooms that reproduces `labels` for `plans`,
+/// where `labels[i]` is the `/explore` result of `plans[i]` with mark echoes
+/// stripped (one label per room visited). Returns `None` if there is none.
+pub fn solve(num_rooms: usize, plans: &[Vec<Step>], labels: &[Vec<usize>]) -> Option<Guess> {
+    assert_eq!(plans.len(), labels.len());
+    let n = num_rooms;
+    let start = *labels.first()?.first()?;
+    if start >= n {
+        return None;
+    }
+    let mut cnf = Cnf::new();
+    let edges = add_edges(&mut cnf, n, num_doors());
+    let initial: Vec<usize> = (0..n).map(|u| u % num_labels()).collect();
+    for (plan, seen) in plans.iter().zip(labels) {
+        assert_eq!(plan.len() + 1, seen.len(), "one label per room visited");
+        let mut V: Vec<i32> = (0..n).map(|_| cnf.var()).collect();
+        cnf.choose_one(&V);
+        cnf.clause([V[start]]);
+        let mut colors = Colors::Initial(initial.clone());
+        add_observation(&mut cnf, &V, &colors, seen[0]);
+        for (&(rewrite, e), &label) in plan.iter().zip(&seen[1..]) {
+            let next: Vec<i32> = (0..n).map(|_| cnf.var()).collect();
+            cnf.choose_one(&next);
+            add_move(&mut cnf, &edges, &V, &next, e);
+            colors = next_colors(&mut cnf, &V, &colors, rewrite);
+            add_observation(&mut cnf, &next, &colors, label);
+            V = next;
+        }
+    }
+    crate::progress::phase(
+        "cnf",
+        serde_json::json!({
+            "solver": "marks",
+            "rooms": n,
+            "vars": cnf.num_vars(),
+            "clauses": cnf.num_clauses(),
+        }),
+    );
+    if cnf.sat.solve() != Some(true) {
+        return None;
+    }
+    Some(extract_guess(&cnf, &edges, n, start))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::judge::{Judge, LocalJudge, first_divergence};
+    use rand::prelude::*;
+
+    #[test]
+    fn marked_explorations_are_solved() {
+        let n = 8;
+        let mut judge = LocalJudge::new("random", n, 11);
+        let mut rng = StdRng::seed_from_u64(12);
+        let plans: Vec<Vec<Step>> = (0..4)
+            .map(|_| {
+                (0..6 * n)
+                    .map(|_| {
+                        let mark = rng.random_bool(0.5).then(|| rng.random_range(0..4));
+                        (mark, rng.random_range(0..6))
+                    })
+                    .collect()
+            })
+            .collect();
+        let labels = judge.explore(&plans);
+        let guess = solve(n, &plans, &labels).unwrap();
+        assert_eq!(first_divergence(&guess, &plans, &labels), None);
+        assert!(judge.check_guess(&guess).is_ok());
+    }
+
+    #[test]
+    fn contradictions_are_unsatisfiable() {
+        // The start room cannot change its label without a mark.
+        let plans = vec![vec![(None, 0)], vec![(None, 0)]];
+        let labels = vec![vec![0, 0], vec![1, 0]];
+        assert!(solve(4, &plans, &labels).is_none());
+    }
+}
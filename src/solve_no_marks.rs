@@ -5,7 +5,7 @@ use itertools::Itertools;
 use std::path::Path;
 
 use crate::{
-    judge::{Guess, check_explore},
+    judge::{Guess, Rules, StartPolicy, check_explore_with_policy},
     mat,
 };
 
@@ -35,22 +35,189 @@ pub fn amo_pairwise(cnf: &mut Cnf, xs: &[i32]) {
     }
 }
 
+/// Generates the clauses of each index in `range` on the rayon pool and adds
+/// them to `cnf` in index order, so the CNF is the same whatever the thread count.
+fn add_par_clauses<F>(cnf: &mut Cnf, range: std::ops::Range<usize>, clauses_of: F)
+where
+    F: Fn(usize, &mut Vec<Vec<i32>>) + Sync,
+{
+    use rayon::prelude::*;
+    let chunks: Vec<Vec<Vec<i32>>> = range
+        .into_par_iter()
+        .map(|i| {
+            let mut out = vec![];
+            clauses_of(i, &mut out);
+            out
+        })
+        .collect();
+    cnf.add_clauses(chunks.into_iter().flatten());
+}
+
 pub fn choose_one(cnf: &mut Cnf, xs: &[i32], id: &mut Counter) {}
 
+/// At-most-one encoding used by [`Cnf::choose_one`].
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord)]
+pub enum AmoEncoding {
+    /// Pairwise up to [`EncodingConfig::pairwise_threshold`] literals, sequential above.
+    Auto,
+    Pairwise,
+    /// Sequential counter (Sinz): `k - 1` auxiliary variables, `3k - 4` clauses.
+    Sequential,
+    /// Commander encoding (Klieber & Kwon) with groups of three.
+    Commander,
+    /// Totalizer (Bailleux & Boufkhad) cut off at two, asserting the count stays below two.
+    Totalizer,
+}
+
+impl AmoEncoding {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            AmoEncoding::Auto => "auto",
+            AmoEncoding::Pairwise => "pairwise",
+            AmoEncoding::Sequential => "sequential",
+            AmoEncoding::Commander => "commander",
+            AmoEncoding::Totalizer => "totalizer",
+        }
+    }
+}
+
+impl std::str::FromStr for AmoEncoding {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> anyhow::Result<Self> {
+        Ok(match s {
+            "auto" => AmoEncoding::Auto,
+            "pairwise" => AmoEncoding::Pairwise,
+            "sequential" => AmoEncoding::Sequential,
+            "commander" => AmoEncoding::Commander,
+            "totalizer" => AmoEncoding::Totalizer,
+            _ => anyhow::bail!("unknown AMO encoding: {}", s),
+        })
+    }
+}
+
+/// How a [`Cnf`] encodes cardinality constraints.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct EncodingConfig {
+    pub amo: AmoEncoding,
+    /// Largest constraint encoded pairwise under [`AmoEncoding::Auto`].
+    pub pairwise_threshold: usize,
+}
+
+impl Default for EncodingConfig {
+    fn default() -> Self {
+        EncodingConfig {
+            amo: AmoEncoding::Auto,
+            pairwise_threshold: AMO_PAIRWISE_THRESHOLD,
+        }
+    }
+}
+
+impl EncodingConfig {
+    /// The default, with the encoding overridden by `UNAGI_AMO_ENCODING`.
+    pub fn from_env() -> Self {
+        let mut config = Self::default();
+        if let Ok(s) = std::env::var("UNAGI_AMO_ENCODING") {
+            match s.parse() {
+                Ok(amo) => config.amo = amo,
+                Err(e) => eprintln!("ignoring UNAGI_AMO_ENCODING: {}", e),
+            }
+        }
+        config
+    }
+
+    /// The encoding used for a constraint over `len` literals.
+    fn resolve(&self, len: usize) -> AmoEncoding {
+        match self.amo {
+            AmoEncoding::Auto if len <= self.pairwise_threshold => AmoEncoding::Pairwise,
+            AmoEncoding::Auto => AmoEncoding::Sequential,
+            amo => amo,
+        }
+    }
+}
+
+/// Size of the at-most-one constraints encoded one way (for `choose_one`,
+/// including its at-least-one clause).
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct EncodingStats {
+    pub constraints: usize,
+    pub clauses: usize,
+    pub vars: usize,
+}
+
+/// Part of the encoding a clause comes from, so an UNSAT result can be traced
+/// back to the constraints responsible (see [`Cnf::explain_unsat`]).
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum ClauseGroup {
+    /// Anything added outside a tagged phase (blocking clauses, fixed prefixes, ...).
+    #[default]
+    Other,
+    /// Each time step is in exactly one room.
+    Candidates,
+    /// Time steps told apart by the observations are in different rooms.
+    Diff,
+    /// Symmetry breaking over room numbering.
+    Sbp,
+    /// Same-door equalization.
+    Equalization,
+    /// Door targets, pairings and their consistency.
+    Edge,
+    /// Transitions taken by the plans.
+    Plan,
+    /// Plans starting in the same room.
+    Start,
+}
+
+impl ClauseGroup {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            ClauseGroup::Other => "other",
+            ClauseGroup::Candidates => "candidates",
+            ClauseGroup::Diff => "diff",
+            ClauseGroup::Sbp => "sbp",
+            ClauseGroup::Equalization => "equalization",
+            ClauseGroup::Edge => "edge",
+            ClauseGroup::Plan => "plan",
+            ClauseGroup::Start => "start",
+        }
+    }
+}
+
+impl std::fmt::Display for ClauseGroup {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
 pub struct Cnf {
     pub sat: cadical::Solver,
     id: Counter,
     buf: Vec<i32>,
     clauses: Vec<Vec<i32>>,
+    /// `groups[i]`: group of `clauses[i]`.
+    groups: Vec<ClauseGroup>,
+    /// Group of the clauses being added.
+    group: ClauseGroup,
+    encoding: EncodingConfig,
+    stats: std::collections::BTreeMap<AmoEncoding, EncodingStats>,
 }
 
 impl Cnf {
+    /// A CNF with the encoding from the environment (see [`EncodingConfig::from_env`]).
     pub fn new() -> Self {
+        Self::with_encoding(EncodingConfig::from_env())
+    }
+
+    pub fn with_encoding(encoding: EncodingConfig) -> Self {
         Self {
             sat: cadical::Solver::with_config("sat").unwrap(),
             id: Counter::new(),
             buf: Vec::with_capacity(128),
             clauses: vec![],
+            groups: vec![],
+            group: ClauseGroup::default(),
+            encoding,
+            stats: Default::default(),
         }
     }
     #[inline]
@@ -61,12 +228,35 @@ impl Cnf {
     pub fn clause<I: IntoIterator<Item = i32>>(&mut self, lits: I) {
         let lits: Vec<i32> = lits.into_iter().collect();
         self.clauses.push(lits.clone());
+        self.groups.push(self.group);
         self.sat.add_clause(lits.clone());
 
         // caddicalは1変数のclauseをclauseだと認めずカウントしてくれないようだ！
         // assert_eq!(self.sat.num_clauses(), self.clauses.len());
     }
 
+    /// Adds clauses generated elsewhere (typically on other threads), in order.
+    pub fn add_clauses(&mut self, clauses: impl IntoIterator<Item = Vec<i32>>) {
+        for lits in clauses {
+            self.sat.add_clause(lits.iter().copied());
+            self.clauses.push(lits);
+            self.groups.push(self.group);
+        }
+    }
+
+    /// Runs `f` with the clauses it adds tagged as `group`.
+    pub fn in_group<T>(&mut self, group: ClauseGroup, f: impl FnOnce(&mut Self) -> T) -> T {
+        let prev = std::mem::replace(&mut self.group, group);
+        let ret = f(self);
+        self.group = prev;
+        ret
+    }
+
+    /// Number of clauses in each group.
+    pub fn group_sizes(&self) -> std::collections::BTreeMap<ClauseGroup, usize> {
+        self.groups.iter().copied().counts().into_iter().collect()
+    }
+
     pub fn amo_sequential(&mut self, xs: &[i32]) {
         let k = xs.len();
         if k <= 1 {
@@ -88,62 +278,304 @@ impl Cnf {
         }
     }
 
+    /// Commander encoding: each group of three gets a commander implied by its
+    /// members, and at most one commander may be true.
+    pub fn amo_commander(&mut self, xs: &[i32]) {
+        const GROUP: usize = 3;
+        if xs.len() <= GROUP + 1 {
+            amo_pairwise(self, xs);
+            return;
+        }
+        let mut commanders = Vec::with_capacity(xs.len().div_ceil(GROUP));
+        for group in xs.chunks(GROUP) {
+            amo_pairwise(self, group);
+            let c = self.var();
+            for &x in group {
+                self.clause([-x, c]);
+            }
+            self.clause(std::iter::once(-c).chain(group.iter().copied()));
+            commanders.push(c);
+        }
+        self.amo_commander(&commanders);
+    }
+
+    /// Totalizer: a tree of unary counters over `xs`, each cut off at two, with
+    /// the root's "at least two" output forbidden.
+    pub fn amo_totalizer(&mut self, xs: &[i32]) {
+        if xs.len() <= 1 {
+            return;
+        }
+        let out = self.totalizer(xs);
+        self.clause([-out[1]]);
+    }
+
+    /// Outputs `o[i]` ("more than `i` of `xs` are true", for `i < 2`) of a
+    /// totalizer subtree; only the upward implications are encoded.
+    fn totalizer(&mut self, xs: &[i32]) -> Vec<i32> {
+        if xs.len() == 1 {
+            return vec![xs[0]];
+        }
+        let (a, b) = xs.split_at(xs.len() / 2);
+        let a = self.totalizer(a);
+        let b = self.totalizer(b);
+        let out: Vec<i32> = (0..(a.len() + b.len()).min(2))
+            .map(|_| self.var())
+            .collect();
+        for i in 0..=a.len() {
+            for j in 0..=b.len() {
+                let sum = i + j;
+                if sum == 0 {
+                    continue;
+                }
+                let mut clause = vec![];
+                if i > 0 {
+                    clause.push(-a[i - 1]);
+                }
+                if j > 0 {
+                    clause.push(-b[j - 1]);
+                }
+                clause.push(out[sum.min(2) - 1]);
+                self.clause(clause);
+            }
+        }
+        out
+    }
+
+    /// Exactly one of `xs`, with the at-most-one part encoded as configured.
     #[inline]
     pub fn choose_one(&mut self, xs: &[i32]) {
-        self.clause(xs.iter().copied());
-        if xs.len() <= AMO_PAIRWISE_THRESHOLD {
-            amo_pairwise(self, xs);
-        } else {
-            self.amo_sequential(xs);
+        self.encode_amo(xs, true);
+    }
+
+    /// At most one of `xs`, encoded as configured.
+    #[inline]
+    pub fn at_most_one(&mut self, xs: &[i32]) {
+        self.encode_amo(xs, false);
+    }
+
+    /// At most one (and, if `at_least_one`, exactly one) of `xs`, counted in
+    /// [`Cnf::encoding_stats`].
+    fn encode_amo(&mut self, xs: &[i32], at_least_one: bool) {
+        let (clauses, vars) = (self.clauses.len(), self.num_vars());
+        if at_least_one {
+            self.clause(xs.iter().copied());
+        }
+        let amo = self.encoding.resolve(xs.len());
+        match amo {
+            AmoEncoding::Pairwise | AmoEncoding::Auto => amo_pairwise(self, xs),
+            AmoEncoding::Sequential => self.amo_sequential(xs),
+            AmoEncoding::Commander => self.amo_commander(xs),
+            AmoEncoding::Totalizer => self.amo_totalizer(xs),
         }
+        let (clauses, vars) = (self.clauses.len() - clauses, self.num_vars() - vars);
+        let stats = self.stats.entry(amo).or_default();
+        stats.constraints += 1;
+        stats.clauses += clauses;
+        stats.vars += vars;
+    }
+
+    /// Sizes of the `choose_one` and `at_most_one` constraints so far, per
+    /// encoding actually used.
+    pub fn encoding_stats(&self) -> &std::collections::BTreeMap<AmoEncoding, EncodingStats> {
+        &self.stats
+    }
+
+    /// Number of variables allocated so far.
+    pub fn num_vars(&self) -> usize {
+        self.id.cnt as usize
+    }
+
+    /// Number of clauses added so far (including unit clauses).
+    pub fn num_clauses(&self) -> usize {
+        self.clauses.len()
     }
 
+    /// Writes the clauses in DIMACS after [`crate::cnf_preprocess::normalize`]
+    /// (duplicates and subsumed clauses dropped; same variables and models).
     pub fn write_dimacs(&self, path: &std::path::Path) -> std::io::Result<()> {
         use std::io::Write;
-        let mut f = std::fs::File::create(path)?;
-        writeln!(f, "p cnf {} {}", self.id.cnt, self.clauses.len())?;
-        for c in &self.clauses {
+        let clauses = crate::cnf_preprocess::normalize(self.id.cnt as usize, &self.clauses);
+        eprintln!(
+            "DIMACS {}: clauses {} -> {} after dedup/subsumption",
+            path.display(),
+            self.clauses.len(),
+            clauses.len()
+        );
+        let mut f = std::io::BufWriter::new(std::fs::File::create(path)?);
+        writeln!(f, "p cnf {} {}", self.id.cnt, clauses.len())?;
+        for c in &clauses {
             for &l in c {
                 write!(f, "{} ", l)?;
             }
             writeln!(f, "0")?;
         }
-        Ok(())
+        f.flush()
+    }
+
+    /// Groups of clauses that are unsatisfiable together and each needed for
+    /// it, or `None` if the CNF is satisfiable.
+    ///
+    /// The clauses are loaded into a fresh solver with one selector literal
+    /// per group, the failed selectors give a first set, and groups are then
+    /// dropped one at a time while the rest stays UNSAT.
+    pub fn explain_unsat(&self) -> Option<Vec<ClauseGroup>> {
+        let groups = self.groups.iter().copied().unique().sorted().collect_vec();
+        let selector = |g: ClauseGroup| {
+            let i = groups.binary_search(&g).unwrap();
+            self.id.cnt + 1 + i as i32
+        };
+        let mut sat: cadical::Solver = Default::default();
+        for (c, &g) in self.clauses.iter().zip(&self.groups) {
+            sat.add_clause(c.iter().copied().chain([-selector(g)]));
+        }
+        if sat.solve_with(groups.iter().map(|&g| selector(g))) != Some(false) {
+            return None;
+        }
+        let mut core = groups
+            .iter()
+            .copied()
+            .filter(|&g| sat.failed(selector(g)))
+            .collect_vec();
+        let mut i = 0;
+        while i < core.len() {
+            let rest = core
+                .iter()
+                .enumerate()
+                .filter(|&(j, _)| j != i)
+                .map(|(_, &g)| g)
+                .collect_vec();
+            if sat.solve_with(rest.iter().map(|&g| selector(g))) == Some(false) {
+                core = rest
+                    .into_iter()
+                    .filter(|&g| sat.failed(selector(g)))
+                    .collect();
+            } else {
+                i += 1;
+            }
+        }
+        Some(core)
+    }
+
+    /// Writes the CNF to `{stem}.cnf` and has the external CaDiCaL
+    /// (`CADICAL_PATH`) solve it, logging a DRAT proof to `{stem}.drat` in
+    /// text format (checkable with `drat-trim`). Returns whether it is UNSAT.
+    pub fn write_drat_proof(&self, stem: &std::path::Path) -> anyhow::Result<bool> {
+        use anyhow::Context;
+        let dimacs = stem.with_extension("cnf");
+        let proof = stem.with_extension("drat");
+        self.write_dimacs(&dimacs)
+            .with_context(|| format!("write {}", dimacs.display()))?;
+        let status = std::process::Command::new(cadical_path())
+            .arg("--binary=false")
+            .arg("-q")
+            .arg(&dimacs)
+            .arg(&proof)
+            .stdout(std::process::Stdio::null())
+            .status()
+            .context("run cadical")?;
+        match status.code() {
+            Some(20) => Ok(true),
+            Some(10) => Ok(false),
+            _ => anyhow::bail!("cadical failed: {}", status),
+        }
     }
 }
 
+/// The CaDiCaL binary used outside the linked library: `CADICAL_PATH`.
+fn cadical_path() -> String {
+    std::env::var("CADICAL_PATH")
+        .unwrap_or_else(|_| "/home/iwiwi/tmp/cadical-rel-2.1.3/build/cadical".to_owned())
+}
+
 // -------------------------- Combinatorial helpers ------------------------
 
-#[inline]
+/// Timelines at least this long try `compute_diff_bucketed` before the full DP.
+const DIFF_BUCKETED_MIN_LEN: usize = 1000;
+
+/// Computes `diff[i][j]`: whether time steps `i` and `j` are provably in different rooms.
+///
+/// Two positions are distinguishable if their labels differ, or if both take the same door
+/// and the next positions are distinguishable. `diff[i][j]` only depends on `diff[i+1][j+1]`,
+/// so each diagonal `j - i = d` is an independent backward scan; diagonals are computed in
+/// parallel and then scattered into rows. The relation is symmetric, so only `d > 0` is needed.
 fn compute_diff(door: &[Option<usize>], labels: &[usize]) -> Vec<Vec<bool>> {
+    use rayon::prelude::*;
     let m = labels.len();
+    // diags[d - 1][i] = diff[i][i + d]
+    let diags: Vec<Vec<bool>> = (1..m)
+        .into_par_iter()
+        .map(|d| {
+            let len = m - d;
+            let mut diag = vec![false; len];
+            for i in (0..len).rev() {
+                let j = i + d;
+                diag[i] = labels[i] != labels[j]
+                    || (i + 1 < len && door[i].is_some() && door[i] == door[j] && diag[i + 1]);
+            }
+            diag
+        })
+        .collect();
     let mut diff = mat![false; m; m];
-    // DP: two positions i,j are distinguishable if label differs OR if next states with same door distinguishable
-    for i in (0..m).rev() {
-        for j in (0..m).rev() {
-            if labels[i] != labels[j] {
-                diff[i][j] = true;
-            } else if i + 1 < m && j + 1 < m {
-                match (door[i], door[j]) {
-                    (Some(e1), Some(e2)) if e1 == e2 => {
-                        if diff[i + 1][j + 1] {
-                            diff[i][j] = true;
+    diff.par_iter_mut().enumerate().for_each(|(i, row)| {
+        for (j, v) in row.iter_mut().enumerate() {
+            if i < j {
+                *v = diags[j - i - 1][i];
+            } else if j < i {
+                *v = diags[i - j - 1][j];
+            }
+        }
+    });
+    diff
+}
+
+/// Longest forward scan `compute_diff_bucketed` does for one pair before giving up.
+const DIFF_BUCKETED_MAX_RUN: usize = 64;
+
+/// Same result as `compute_diff`, but only pairs within the same label bucket are examined.
+///
+/// Pairs with different labels are trivially distinguishable. For a same-label pair the
+/// recurrence is unrolled forward and stops at the first label mismatch or door mismatch,
+/// which with random walks ends after a couple of steps, so this does roughly a quarter of
+/// the work of the full DP. Rows are computed in parallel.
+///
+/// Periodic walks (e.g. one door repeated) make the scans as long as the timeline, which
+/// would be O(m³). Returns `None` as soon as any scan exceeds `DIFF_BUCKETED_MAX_RUN`
+/// steps, so the caller can fall back to the O(m²) `compute_diff`.
+fn compute_diff_bucketed(door: &[Option<usize>], labels: &[usize]) -> Option<Vec<Vec<bool>>> {
+    use rayon::prelude::*;
+    use std::sync::atomic::{AtomicBool, Ordering};
+    let m = labels.len();
+    let gave_up = AtomicBool::new(false);
+    let mut diff = mat![true; m; m];
+    diff.par_iter_mut().enumerate().for_each(|(i, row)| {
+        row[i] = false;
+        for j in 0..m {
+            if j == i || labels[i] != labels[j] {
+                continue;
+            }
+            if gave_up.load(Ordering::Relaxed) {
+                return;
+            }
+            let (mut a, mut b) = (i, j);
+            row[j] = loop {
+                match (door[a], door[b]) {
+                    (Some(e1), Some(e2)) if e1 == e2 && a + 1 < m && b + 1 < m => {
+                        a += 1;
+                        b += 1;
+                        if labels[a] != labels[b] {
+                            break true;
+                        }
+                        if a - i > DIFF_BUCKETED_MAX_RUN {
+                            gave_up.store(true, Ordering::Relaxed);
+                            return;
                         }
                     }
-                    _ => {}
+                    _ => break false,
                 }
-            }
+            };
         }
-    }
-    for i in 0..m {
-        diff[i][i] = false;
-        for j in 0..i {
-            let v = diff[i][j] || diff[j][i];
-            diff[i][j] = v;
-            diff[j][i] = v;
-        }
-    }
-    diff
+    });
+    (!gave_up.into_inner()).then_some(diff)
 }
 
 // ------------------------------ Problem view -----------------------------
@@ -156,9 +588,20 @@ struct PlanInfo {
     diff: Vec<Vec<bool>>,
     // Indices in the flattened timeline that correspond to the start of each plan
     starts: Vec<usize>,
+    // Doors per room (`Rules::doors`); the remaining doors are pinned to self-loops.
+    doors: usize,
+    // Size of the label alphabet (`Rules::labels`); every label is below it.
+    num_labels: usize,
+    // Whether all plans start from the same room (`Rules::start_policy`).
+    start_policy: StartPolicy,
 }
 
-fn build_info(num_rooms: usize, plans: &Vec<Vec<usize>>, labels: &Vec<Vec<usize>>) -> PlanInfo {
+fn build_info(
+    num_rooms: usize,
+    plans: &Vec<Vec<usize>>,
+    labels: &Vec<Vec<usize>>,
+    rules: Rules,
+) -> PlanInfo {
     assert_eq!(plans.len(), labels.len());
     let n = num_rooms;
 
@@ -181,7 +624,18 @@ fn build_info(num_rooms: usize, plans: &Vec<Vec<usize>>, labels: &Vec<Vec<usize>
     }
     let m = labels_flat.len();
     assert_eq!(door_flat.len(), m); // last entry must be None for the last plan as well
-    let diff = compute_diff(&door_flat, &labels_flat);
+    let num_labels = rules.labels;
+    assert!(
+        labels_flat.iter().all(|&k| k < num_labels),
+        "observed label out of range for {} labels",
+        num_labels
+    );
+    let diff = if m >= DIFF_BUCKETED_MIN_LEN {
+        compute_diff_bucketed(&door_flat, &labels_flat)
+            .unwrap_or_else(|| compute_diff(&door_flat, &labels_flat))
+    } else {
+        compute_diff(&door_flat, &labels_flat)
+    };
 
     PlanInfo {
         n,
@@ -190,47 +644,376 @@ fn build_info(num_rooms: usize, plans: &Vec<Vec<usize>>, labels: &Vec<Vec<usize>
         m,
         diff,
         starts,
+        doors: rules.doors,
+        num_labels,
+        start_policy: rules.start_policy,
+    }
+}
+
+/// How room labels are tied to room indices in the encoding.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum LabelMode {
+    /// Room `u` has label `u % k` for `k` labels (the judge's convention; smallest CNF).
+    Modulo,
+    /// Each room gets an explicit label variable, so label counts may be arbitrary.
+    Free,
+    /// `Free` if the observed label frequencies do not fit the `u % k` split, else `Modulo`.
+    Auto,
+}
+
+// A random walk on a 6-regular graph visits rooms uniformly in the limit, so the observed
+// label shares should approach the share of rooms carrying that label. Only judge this once
+// the walk is long enough to be meaningful.
+const UNEVEN_MIN_STEPS_PER_ROOM: usize = 6;
+const UNEVEN_RELATIVE_TOLERANCE: f64 = 0.5;
+
+/// Returns true if the observed labels are inconsistent with rooms being labeled `u % k`.
+fn labels_look_uneven(info: &PlanInfo) -> bool {
+    let mut observed = vec![0usize; info.num_labels];
+    for &k in &info.labels {
+        observed[k] += 1;
+    }
+    let mut expected = vec![0usize; info.num_labels];
+    for u in 0..info.n {
+        expected[u % info.num_labels] += 1;
+    }
+    if (0..info.num_labels).any(|k| observed[k] > 0 && expected[k] == 0) {
+        return true;
+    }
+    if info.m < UNEVEN_MIN_STEPS_PER_ROOM * info.n {
+        return false;
     }
+    (0..info.num_labels).any(|k| {
+        let exp = expected[k] as f64 / info.n as f64;
+        let obs = observed[k] as f64 / info.m as f64;
+        (obs - exp).abs() > UNEVEN_RELATIVE_TOLERANCE * exp
+    })
+}
+
+fn resolve_label_mode(info: &PlanInfo, mode: LabelMode) -> bool {
+    match mode {
+        LabelMode::Modulo => false,
+        LabelMode::Free => true,
+        LabelMode::Auto => {
+            let free = labels_look_uneven(info);
+            if free {
+                eprintln!("solve_no_marks: uneven label counts, using free room labels");
+            }
+            free
+        }
+    }
+}
+
+// ---------------------------- Observation coverage ---------------------------
+
+/// Label × door transition coverage of a set of explorations.
+///
+/// A SAT solve on data where some door of some label was (almost) never taken is
+/// underdetermined: the solver can wire that door anywhere. Drivers check this before
+/// solving and explore more when it is too thin.
+#[derive(Clone, Debug)]
+pub struct Coverage {
+    /// `label_door[a][d]`: how many times door `d` was taken from a room labeled `a`.
+    pub label_door: Vec<[usize; 6]>,
+    /// `aib[a][d][b]`: whether door `d` from a room labeled `a` was seen to reach label `b`.
+    pub aib: Vec<[Vec<bool>; 6]>,
+    /// Whether each label (`0..Rules::labels`) was observed at all.
+    pub seen: Vec<bool>,
+    /// Doors per room ([`Rules::doors`]); the counts of the other doors stay zero.
+    pub doors: usize,
+}
+
+impl Coverage {
+    /// Coverage under [`Rules::global`].
+    pub fn new(plans: &[Vec<usize>], labels: &[Vec<usize>]) -> Self {
+        Coverage::with_rules(plans, labels, Rules::global())
+    }
+
+    pub fn with_rules(plans: &[Vec<usize>], labels: &[Vec<usize>], rules: Rules) -> Self {
+        let k = rules.labels;
+        let mut cov = Coverage {
+            label_door: vec![[0; 6]; k],
+            aib: vec![std::array::from_fn(|_| vec![false; k]); k],
+            seen: vec![false; k],
+            doors: rules.doors,
+        };
+        for (plan, l) in plans.iter().zip(labels) {
+            for &k in l {
+                cov.seen[k] = true;
+            }
+            for (t, &d) in plan.iter().enumerate() {
+                cov.label_door[l[t]][d] += 1;
+                cov.aib[l[t]][d][l[t + 1]] = true;
+            }
+        }
+        cov
+    }
+
+    /// Number of unseen `(a, d, b)` transitions, counting only labels that were observed.
+    pub fn aib_missing(&self) -> usize {
+        let mut cnt = 0;
+        let k = self.seen.len();
+        for a in (0..k).filter(|&a| self.seen[a]) {
+            for d in 0..self.doors {
+                cnt += (0..k)
+                    .filter(|&b| self.seen[b] && !self.aib[a][d][b])
+                    .count();
+            }
+        }
+        cnt
+    }
+
+    /// `(label, door)` pairs of observed labels taken fewer than `min` times.
+    pub fn underrepresented(&self, min: usize) -> Vec<(usize, usize)> {
+        (0..self.seen.len())
+            .filter(|&a| self.seen[a])
+            .flat_map(|a| (0..self.doors).map(move |d| (a, d)))
+            .filter(|&(a, d)| self.label_door[a][d] < min)
+            .collect()
+    }
+}
+
+/// Thresholds a set of explorations has to meet before it is worth solving.
+#[derive(Clone, Copy, Debug)]
+pub struct CoverageGate {
+    /// Minimum number of times each door must be taken from each observed label.
+    pub min_label_door: usize,
+    /// Maximum allowed `Coverage::aib_missing`, if any.
+    pub max_aib_missing: Option<usize>,
+}
+
+impl CoverageGate {
+    pub fn passes(&self, cov: &Coverage) -> bool {
+        cov.underrepresented(self.min_label_door).is_empty()
+            && self
+                .max_aib_missing
+                .is_none_or(|max| cov.aib_missing() <= max)
+    }
+}
+
+/// Builds one top-up plan per `(label, door)` target.
+///
+/// Each plan replays an existing plan up to the earliest time a room with that label was
+/// observed (the walk is deterministic, so it reaches the same room), takes the target door
+/// there, and continues with a random walk up to `max_len` doors. Targets whose label was
+/// never observed are skipped.
+pub fn top_up_plans<R: rand::Rng>(
+    plans: &[Vec<usize>],
+    labels: &[Vec<usize>],
+    targets: &[(usize, usize)],
+    max_len: usize,
+    rng: &mut R,
+) -> Vec<Vec<usize>> {
+    let mut out = vec![];
+    for &(a, d) in targets {
+        let Some((p, t)) = plans
+            .iter()
+            .zip(labels)
+            .enumerate()
+            .filter_map(|(p, (plan, l))| (0..=plan.len()).find(|&t| l[t] == a).map(|t| (p, t)))
+            .min_by_key(|&(_, t)| t)
+        else {
+            continue;
+        };
+        if t >= max_len {
+            continue;
+        }
+        let mut plan = plans[p][..t].to_vec();
+        plan.push(d);
+        while plan.len() < max_len {
+            plan.push(rng.random_range(0..crate::judge::num_doors()));
+        }
+        out.push(plan);
+    }
+    out
+}
+
+/// Builds one exploration that complements `explored` instead of starting from scratch.
+///
+/// The existing plans (those without charcoal marks) are analyzed for times that are still
+/// hard to tell apart: same label and not separated by the diff recurrence. The new plan
+/// replays an existing plan up to the time with the most such partners per replayed door
+/// (so it reaches the same room; at most half of `budget`), leaves it through the door least
+/// taken from its ambiguity class, and spends the rest on a walk that favors doors used
+/// least so far. If
+/// nothing is ambiguous, the anchor is instead the earliest visit of the thinnest
+/// `(label, door)` pair of [`Coverage`]. The result has exactly `budget` doors, all
+/// below `rules.doors`.
+pub fn plan_next<R: rand::Rng>(
+    explored: &crate::judge::Explored,
+    budget: usize,
+    rules: Rules,
+    rng: &mut R,
+) -> Vec<usize> {
+    let (plans, labels): (Vec<Vec<usize>>, Vec<Vec<usize>>) = explored
+        .plans
+        .iter()
+        .zip(&explored.results)
+        .filter(|(plan, _)| plan.iter().all(|&(mark, _)| mark.is_none()))
+        .map(|(plan, l)| (plan.iter().map(|&(_, d)| d).collect(), l.clone()))
+        .unzip();
+
+    let doors = rules.doors;
+    // Door usage so far, the baseline the walk evens out.
+    let mut door_count = [0usize; 6];
+    for &d in plans.iter().flatten() {
+        door_count[d] += 1;
+    }
+    let mut plan = vec![];
+    if !plans.is_empty() && budget > 0 {
+        let info = build_info(0, &plans, &labels, rules);
+        let ambiguous = |i: usize| {
+            (0..info.m)
+                .filter(|&j| j != i && info.labels[j] == info.labels[i] && !info.diff[i][j])
+                .collect::<Vec<_>>()
+        };
+        // (offset within its plan, flattened time) of every time reachable within budget / 2.
+        let reachable = info
+            .starts
+            .iter()
+            .zip(&plans)
+            .flat_map(|(&s, p)| (0..=p.len().min(budget / 2)).map(move |t| (t, s + t)))
+            .collect::<Vec<_>>();
+        let cov = Coverage::with_rules(&plans, &labels, rules);
+        let scores = reachable
+            .iter()
+            .map(|&(t, i)| ambiguous(i).len() as f64 / (t + 1) as f64)
+            .collect::<Vec<_>>();
+        let anchor = match crate::argmax(scores.iter()) {
+            Some(k) if scores[k] > 0.0 => {
+                let (_, i) = reachable[k];
+                let class = ambiguous(i);
+                let first = crate::argmin((0..doors).map(|d| {
+                    std::iter::once(i)
+                        .chain(class.iter().copied())
+                        .filter(|&j| info.door[j] == Some(d))
+                        .count()
+                }));
+                Some((reachable[k], first.unwrap()))
+            }
+            _ => {
+                // Everything is distinguished: aim at the thinnest (label, door) pair instead.
+                let (a, d) = (0..cov.seen.len())
+                    .filter(|&a| cov.seen[a])
+                    .flat_map(|a| (0..doors).map(move |d| (a, d)))
+                    .min_by_key(|&(a, d)| cov.label_door[a][d])
+                    .unwrap();
+                reachable
+                    .iter()
+                    .filter(|&&(_, i)| info.labels[i] == a)
+                    .min_by_key(|&&(t, _)| t)
+                    .map(|&r| (r, d))
+            }
+        };
+        if let Some(((t, i), first)) = anchor {
+            let p = info.starts.partition_point(|&s| s <= i) - 1;
+            plan.extend_from_slice(&plans[p][..t]);
+            plan.push(first);
+        }
+    }
+    plan.truncate(budget);
+    let mut used = door_count;
+    for &d in &plan {
+        used[d] += 1;
+    }
+    while plan.len() < budget {
+        let min = *used[..doors].iter().min().unwrap();
+        let candidates = (0..doors).filter(|&d| used[d] == min).collect::<Vec<_>>();
+        let d = candidates[rng.random_range(0..candidates.len())];
+        used[d] += 1;
+        plan.push(d);
+    }
+    plan
 }
 
 struct Buckets {
-    rooms_by_label: [Vec<usize>; 4],
-    times_by_label: [Vec<usize>; 4],
+    // Indexed by label, `0..info.num_labels`.
+    rooms_by_label: Vec<Vec<usize>>,
+    times_by_label: Vec<Vec<usize>>,
+    // True if room labels are decided by the solver (every room is in every bucket).
+    free_labels: bool,
 }
-fn build_buckets(info: &PlanInfo) -> Buckets {
-    let mut rooms_by_label: [Vec<usize>; 4] = [Vec::new(), Vec::new(), Vec::new(), Vec::new()];
+fn build_buckets(info: &PlanInfo, free_labels: bool) -> Buckets {
+    let mut rooms_by_label = vec![Vec::new(); info.num_labels];
     for u in 0..info.n {
-        rooms_by_label[u % 4].push(u);
+        if free_labels {
+            for rooms in rooms_by_label.iter_mut() {
+                rooms.push(u);
+            }
+        } else {
+            rooms_by_label[u % info.num_labels].push(u);
+        }
     }
-    let mut times_by_label: [Vec<usize>; 4] = [Vec::new(), Vec::new(), Vec::new(), Vec::new()];
+    let mut times_by_label = vec![Vec::new(); info.num_labels];
     for i in 0..info.m {
         times_by_label[info.labels[i]].push(i);
     }
     Buckets {
         rooms_by_label,
         times_by_label,
+        free_labels,
     }
 }
 
 struct Candidates {
     // V_map[i][u] = Some(var) if room u allowed at time i (label match).
     V_map: Vec<Vec<Option<i32>>>,
+    // L[u][k]: room u has label k. Only present with free labels.
+    L: Option<Vec<Vec<i32>>>,
 }
 fn build_candidates(cnf: &mut Cnf, info: &PlanInfo, buckets: &Buckets) -> Candidates {
-    let mut V_map = vec![vec![None; info.n]; info.m];
-    let mut V_rows: Vec<Vec<i32>> = vec![Vec::new(); info.m];
-    for i in 0..info.m {
+    let L = buckets.free_labels.then(|| build_label_vars(cnf, info));
+    let mut cand = Candidates { V_map: vec![], L };
+    extend_candidates(cnf, info, buckets, &mut cand);
+    cand
+}
+
+/// Adds room variables for the times `cand.V_map.len()..info.m`.
+fn extend_candidates(cnf: &mut Cnf, info: &PlanInfo, buckets: &Buckets, cand: &mut Candidates) {
+    for i in cand.V_map.len()..info.m {
         let k = info.labels[i];
         let rooms = &buckets.rooms_by_label[k];
-        V_rows[i].reserve(rooms.len());
+        let mut row = vec![None; info.n];
+        let mut vars = Vec::with_capacity(rooms.len());
         for &u in rooms {
             let v = cnf.var();
-            V_map[i][u] = Some(v);
-            V_rows[i].push(v);
+            row[u] = Some(v);
+            vars.push(v);
+            if let Some(L) = &cand.L {
+                cnf.clause([-v, L[u][k]]);
+            }
+        }
+        cnf.choose_one(&vars);
+        cand.V_map.push(row);
+    }
+}
+
+// Room-label layer for free labels: every room has exactly one label and every observed
+// label is carried by at least one room.
+fn build_label_vars(cnf: &mut Cnf, info: &PlanInfo) -> Vec<Vec<i32>> {
+    let mut L = vec![vec![0i32; info.num_labels]; info.n];
+    for u in 0..info.n {
+        for k in 0..info.num_labels {
+            L[u][k] = cnf.var();
+        }
+        cnf.choose_one(&L[u]);
+    }
+    for k in 0..info.num_labels {
+        if info.labels.contains(&k) {
+            cnf.clause((0..info.n).map(|u| L[u][k]));
         }
-        cnf.choose_one(&V_rows[i]);
     }
-    Candidates { V_map }
+    L
+}
+
+/// Label of room `u`: `u % k`, or the value of its label variable with free labels.
+fn room_label(cnf: &Cnf, info: &PlanInfo, cand: &Candidates, u: usize) -> usize {
+    match &cand.L {
+        Some(L) => (0..info.num_labels)
+            .find(|&k| cnf.sat.value(L[u][k]) == Some(true))
+            .unwrap_or(0),
+        None => u % info.num_labels,
+    }
 }
 
 // -------------------------- Symmetry breaking ----------------------------
@@ -278,8 +1061,17 @@ fn first_use_sbp_rect_truncated(cnf: &mut Cnf, W_full: &Vec<Vec<i32>>) {
 }
 
 fn add_sbp(cnf: &mut Cnf, info: &PlanInfo, buckets: &Buckets, cand: &Candidates) {
+    if buckets.free_labels {
+        // All rooms are interchangeable: order them by first visit over the whole timeline.
+        let W: Vec<Vec<i32>> = (0..info.m)
+            .map(|i| (0..info.n).map(|u| cand.V_map[i][u].unwrap()).collect())
+            .collect();
+        cnf.clause([W[0][0]]);
+        first_use_sbp_rect_truncated(cnf, &W);
+        return;
+    }
     // Per-label rectangular first-use SBP with truncation and anchor earliest to smallest room.
-    for k in 0..4 {
+    for k in 0..info.num_labels {
         let times = &buckets.times_by_label[k];
         if times.is_empty() {
             continue;
@@ -300,7 +1092,7 @@ fn add_sbp(cnf: &mut Cnf, info: &PlanInfo, buckets: &Buckets, cand: &Candidates)
     }
 
     // Also pin the first seen time of each label to the canonical u=k if present.
-    let mut seen = [false; 4];
+    let mut seen = vec![false; info.num_labels];
     for i in 0..info.m {
         let k = info.labels[i];
         if !seen[k] {
@@ -335,13 +1127,13 @@ fn add_same_door_equalization(
     buckets: &Buckets,
     cand: &Candidates,
 ) {
-    let mut idx_by_ke: Vec<Vec<Vec<usize>>> = vec![vec![Vec::new(); 6]; 4];
+    let mut idx_by_ke: Vec<Vec<Vec<usize>>> = vec![vec![Vec::new(); 6]; info.num_labels];
     for i in 0..info.m.saturating_sub(1) {
         if let Some(e) = info.door[i] {
             idx_by_ke[info.labels[i]][e].push(i);
         }
     }
-    for k in 0..4 {
+    for k in 0..info.num_labels {
         for e in 0..6 {
             let idxs = &idx_by_ke[k][e];
             if idxs.len() <= 1 {
@@ -377,45 +1169,56 @@ fn add_same_door_equalization(
 
 struct EdgeVars {
     // Tlab[u][e][k]
-    Tlab: Vec<Vec<[i32; 4]>>,
+    Tlab: Vec<Vec<Vec<i32>>>,
     // F[u][e][v]
     F: Vec<Vec<Vec<i32>>>,
     // M[u][v][e][f] symmetric shared
     M: Vec<Vec<[[i32; 6]; 6]>>,
 }
-fn build_edge_vars(cnf: &mut Cnf, info: &PlanInfo) -> EdgeVars {
+fn build_edge_vars(cnf: &mut Cnf, info: &PlanInfo, cand: &Candidates) -> EdgeVars {
     let n = info.n;
-    let mut Tlab = vec![vec![[0i32; 4]; 6]; n];
+    let num_labels = info.num_labels;
+    let mut Tlab = vec![vec![vec![0i32; num_labels]; 6]; n];
     let mut F = mat![0; n; 6; n];
 
     for u in 0..n {
         for e in 0..6 {
-            let mut trow = [0i32; 4];
-            for k in 0..4 {
+            for k in 0..num_labels {
                 Tlab[u][e][k] = cnf.var();
-                trow[k] = Tlab[u][e][k];
             }
-            cnf.choose_one(&trow);
+            cnf.choose_one(&Tlab[u][e]);
 
             let mut frow = Vec::with_capacity(n);
             for v in 0..n {
                 F[u][e][v] = cnf.var();
                 frow.push(F[u][e][v]);
-                cnf.clause([-F[u][e][v], Tlab[u][e][v % 4]]);
+                match &cand.L {
+                    // F[u][e][v] -> (Tlab[u][e][k] <-> L[v][k])
+                    Some(L) => {
+                        for k in 0..num_labels {
+                            cnf.clause([-F[u][e][v], -L[v][k], Tlab[u][e][k]]);
+                            cnf.clause([-F[u][e][v], -Tlab[u][e][k], L[v][k]]);
+                        }
+                    }
+                    None => cnf.clause([-F[u][e][v], Tlab[u][e][v % num_labels]]),
+                }
             }
             cnf.choose_one(&frow);
         }
     }
 
-    for u in 0..n {
-        for e in 0..6 {
-            for k in 0..4 {
-                cnf.buf.clear();
-                cnf.buf.push(-Tlab[u][e][k]);
-                for v in (k..n).step_by(4) {
-                    cnf.buf.push(F[u][e][v]);
+    // With free labels, Tlab is already pinned down by the label-equivalence clauses above.
+    if cand.L.is_none() {
+        for u in 0..n {
+            for e in 0..6 {
+                for k in 0..num_labels {
+                    cnf.buf.clear();
+                    cnf.buf.push(-Tlab[u][e][k]);
+                    for v in (k..n).step_by(num_labels) {
+                        cnf.buf.push(F[u][e][v]);
+                    }
+                    cnf.clause(cnf.buf.clone());
                 }
-                cnf.clause(cnf.buf.clone());
             }
         }
     }
@@ -432,50 +1235,60 @@ fn build_edge_vars(cnf: &mut Cnf, info: &PlanInfo) -> EdgeVars {
             }
         }
     }
+    // The M layer makes up most of the clauses for large n; rows of u are
+    // generated in parallel.
     // M -> F both endpoints
-    for u in 0..n {
+    add_par_clauses(cnf, 0..n, |u, out| {
         for v in 0..n {
             for e in 0..6 {
                 for f in 0..6 {
                     let mv = M[u][v][e][f];
-                    cnf.clause([-mv, F[u][e][v]]);
-                    cnf.clause([-mv, F[v][f][u]]);
+                    out.push(vec![-mv, F[u][e][v]]);
+                    out.push(vec![-mv, F[v][f][u]]);
                 }
             }
         }
-    }
-    // Row-wise: F[u][e][v] -> OR_f M[u][v][e][f]; AMO on f
-    for u in 0..n {
+    });
+    // Row-wise: F[u][e][v] -> OR_f M[u][v][e][f]
+    add_par_clauses(cnf, 0..n, |u, out| {
         for v in 0..n {
             for e in 0..6 {
-                let mut row = [0i32; 6];
-                for f in 0..6 {
-                    row[f] = M[u][v][e][f];
-                }
-                cnf.buf.clear();
-                cnf.buf.push(-F[u][e][v]);
-                cnf.buf.extend_from_slice(&row);
-                cnf.clause(cnf.buf.clone());
-                amo_pairwise(cnf, &row);
+                let mut clause = Vec::with_capacity(7);
+                clause.push(-F[u][e][v]);
+                clause.extend_from_slice(&M[u][v][e]);
+                out.push(clause);
             }
         }
-    }
-    // Column-wise: F[v][f][u] -> OR_e M[u][v][e][f]; AMO on e
+    });
+    // Column-wise: F[v][f][u] -> OR_e M[u][v][e][f]
+    add_par_clauses(cnf, 0..n, |u, out| {
+        for v in 0..n {
+            for f in 0..6 {
+                let mut clause = Vec::with_capacity(7);
+                clause.push(-F[v][f][u]);
+                clause.extend((0..6).map(|e| M[u][v][e][f]));
+                out.push(clause);
+            }
+        }
+    });
+    // AMO on f for each row and on e for each column, in the configured
+    // encoding (auxiliary variables are allocated here, not on the workers).
     for u in 0..n {
         for v in 0..n {
+            for e in 0..6 {
+                cnf.at_most_one(&M[u][v][e]);
+            }
             for f in 0..6 {
-                let mut col = [0i32; 6];
-                for e in 0..6 {
-                    col[e] = M[u][v][e][f];
-                }
-                cnf.buf.clear();
-                cnf.buf.push(-F[v][f][u]);
-                cnf.buf.extend_from_slice(&col);
-                cnf.clause(cnf.buf.clone());
-                amo_pairwise(cnf, &col);
+                cnf.at_most_one(&(0..6).map(|e| M[u][v][e][f]).collect_vec());
             }
         }
     }
+    // Doors that do not exist under the current rules lead back to themselves.
+    for u in 0..n {
+        for e in info.doors..6 {
+            cnf.clause([M[u][u][e][e]]);
+        }
+    }
 
     EdgeVars { Tlab, F, M }
 }
@@ -487,19 +1300,33 @@ fn add_plan_constraints(
     cand: &Candidates,
     edges: &EdgeVars,
 ) {
+    add_plan_constraints_from(cnf, info, buckets, cand, edges, 0);
+}
+
+/// `add_plan_constraints` restricted to steps starting at time `from` or later.
+fn add_plan_constraints_from(
+    cnf: &mut Cnf,
+    info: &PlanInfo,
+    buckets: &Buckets,
+    cand: &Candidates,
+    edges: &EdgeVars,
+    from: usize,
+) {
+    // Steps are independent, so their clauses are generated in parallel.
+    let steps = from..info.m.saturating_sub(1);
     // V[i]=u -> Tlab[u, door[i], labels[i+1]] for valid steps
-    for i in 0..info.m.saturating_sub(1) {
+    add_par_clauses(cnf, steps.clone(), |i, out| {
         if let Some(e) = info.door[i] {
             let h = info.labels[i + 1];
             let k = info.labels[i];
             for &u in &buckets.rooms_by_label[k] {
                 let vi = cand.V_map[i][u].unwrap();
-                cnf.clause([-vi, edges.Tlab[u][e][h]]);
+                out.push(vec![-vi, edges.Tlab[u][e][h]]);
             }
         }
-    }
+    });
     // (V[i]=u ∧ V[i+1]=v) -> F[u, door[i], v]
-    for i in 0..info.m.saturating_sub(1) {
+    add_par_clauses(cnf, steps, |i, out| {
         if let Some(e) = info.door[i] {
             let k = info.labels[i];
             let h = info.labels[i + 1];
@@ -507,34 +1334,50 @@ fn add_plan_constraints(
                 let vi = cand.V_map[i][u].unwrap();
                 for &v in &buckets.rooms_by_label[h] {
                     let vj = cand.V_map[i + 1][v].unwrap();
-                    cnf.clause([-vi, -vj, edges.F[u][e][v]]);
+                    out.push(vec![-vi, -vj, edges.F[u][e][v]]);
                 }
             }
         }
-    }
+    });
 }
 
 // All plans start from the same room. For each label k that appears at plan starts,
 // unify the selected room variable across all start times with that label.
+// Under `StartPolicy::PerPlanUnknown` the starts are independent and nothing is added.
 fn add_start_room_unification(
     cnf: &mut Cnf,
     info: &PlanInfo,
     buckets: &Buckets,
     cand: &Candidates,
 ) {
+    add_start_room_unification_from(cnf, info, buckets, cand, 0);
+}
+
+/// `add_start_room_unification` for plans starting at time `from` or later;
+/// they are tied to the earliest start with the same label.
+fn add_start_room_unification_from(
+    cnf: &mut Cnf,
+    info: &PlanInfo,
+    buckets: &Buckets,
+    cand: &Candidates,
+    from: usize,
+) {
+    if info.start_policy == StartPolicy::PerPlanUnknown {
+        return;
+    }
     // Group start indices by their observed label
-    let mut starts_by_label: [Vec<usize>; 4] = [Vec::new(), Vec::new(), Vec::new(), Vec::new()];
+    let mut starts_by_label = vec![Vec::new(); info.num_labels];
     for &i in &info.starts {
         let k = info.labels[i];
         starts_by_label[k].push(i);
     }
-    for k in 0..4 {
+    for k in 0..info.num_labels {
         let starts = &starts_by_label[k];
         if starts.len() <= 1 {
             continue;
         }
         let s0 = starts[0];
-        for &si in &starts[1..] {
+        for &si in starts[1..].iter().filter(|&&si| si >= from) {
             for &u in &buckets.rooms_by_label[k] {
                 // Enforce equivalence: V[s0,u] <-> V[si,u]
                 let v0 = cand.V_map[s0][u].unwrap();
@@ -578,7 +1421,7 @@ fn extract_guess(
     }
 
     for u in 0..n {
-        guess.rooms[u] = u % 4;
+        guess.rooms[u] = room_label(cnf, info, cand, u);
     }
 
     for u in 0..n {
@@ -603,42 +1446,404 @@ fn extract_guess(
     guess
 }
 
-// -------------------------- CNF construction wrapper ---------------------
+/// Makes every room reachable from the start (see `repair_connectivity`).
+/// Failing that, the guess is returned as is and the judge decides.
+fn connect_guess(guess: &mut Guess, plans: &[Vec<usize>], policy: StartPolicy) {
+    match crate::judge::repair_connectivity(guess, plans, policy) {
+        Ok(0) => {}
+        Ok(swaps) => eprintln!("Connected unreachable rooms with {} rewirings", swaps),
+        Err(e) => eprintln!("Guess has unreachable rooms: {}", e),
+    }
+}
 
-fn build_cnf_for_plans(
-    num_rooms: usize,
-    plans: &Vec<Vec<usize>>,
-    labels: &Vec<Vec<usize>>,
-) -> (PlanInfo, Buckets, Cnf, Candidates, EdgeVars) {
-    // 1) Build flattened info from provided plans and labels
-    let info = build_info(num_rooms, plans, labels);
+// -------------------------- Offline model import ------------------------
+
+/// Version of the `CnfMeta` JSON layout. Bump when fields change meaning.
+///
+/// - 2: `num_labels` records the label alphabet.
+/// - 3: `doors` records the doors per room.
+/// - 4: `start_policy` records where the plans start.
+pub const CNF_META_VERSION: u32 = 4;
+
+/// Variable layout of a CNF built by this module, saved next to the DIMACS file so a model
+/// produced elsewhere (e.g. by a standalone SAT solver) can be decoded into a `Guess`.
+///
+/// Variable ids are DIMACS ids; `0` never appears as a real variable.
+#[derive(Clone, Debug, serde::Serialize, serde::Deserialize)]
+pub struct CnfMeta {
+    pub version: u32,
+    pub num_rooms: usize,
+    /// The explorations the CNF encodes, used to validate the decoded guess.
+    pub plans: Vec<Vec<usize>>,
+    pub labels: Vec<Vec<usize>>,
+    /// `V[i][u]`: room `u` at flattened time `i` (`None` if excluded by its label).
+    pub V: Vec<Vec<Option<i32>>>,
+    /// Doors per room (`Rules::doors`); doors `doors..6` are self-loops.
+    pub doors: usize,
+    /// Room each plan starts from (`Rules::start_policy`), used to validate and
+    /// connect the decoded guess.
+    pub start_policy: StartPolicy,
+    /// Size of the label alphabet; room `u` has label `u % num_labels` without `L`.
+    pub num_labels: usize,
+    /// `L[u][k]`: room `u` has label `k`. Only present with free labels.
+    pub L: Option<Vec<Vec<i32>>>,
+    /// `Tlab[u][e][k]`: door `e` of room `u` leads to a room labeled `k`.
+    pub Tlab: Vec<Vec<Vec<i32>>>,
+    /// `F[u][e][v]`: door `e` of room `u` leads to room `v`.
+    pub F: Vec<Vec<Vec<i32>>>,
+    /// `M[u][v][e][f]`: door `e` of `u` is paired with door `f` of `v`.
+    pub M: Vec<Vec<[[i32; 6]; 6]>>,
+}
 
-    // 2) Build buckets and candidates
-    let buckets = build_buckets(&info);
-    let mut cnf = Cnf::new();
-    let cand = build_candidates(&mut cnf, &info, &buckets);
+impl CnfMeta {
+    fn new(
+        plans: &[Vec<usize>],
+        labels: &[Vec<usize>],
+        info: &PlanInfo,
+        cand: &Candidates,
+        edges: &EdgeVars,
+    ) -> Self {
+        CnfMeta {
+            version: CNF_META_VERSION,
+            num_rooms: info.n,
+            plans: plans.to_vec(),
+            labels: labels.to_vec(),
+            doors: info.doors,
+            start_policy: info.start_policy,
+            num_labels: info.num_labels,
+            V: cand.V_map.clone(),
+            L: cand.L.clone(),
+            Tlab: edges.Tlab.clone(),
+            F: edges.F.clone(),
+            M: edges.M.clone(),
+        }
+    }
 
-    // 3) Add pruning and symmetry breaking
-    add_diff_pruning(&mut cnf, &info, &buckets, &cand);
-    add_sbp(&mut cnf, &info, &buckets, &cand);
-    add_same_door_equalization(&mut cnf, &info, &buckets, &cand);
+    pub fn write(&self, path: &Path) -> anyhow::Result<()> {
+        std::fs::write(path, serde_json::to_string(self)?)?;
+        Ok(())
+    }
+
+    pub fn read(path: &Path) -> anyhow::Result<Self> {
+        let meta: CnfMeta = serde_json::from_str(&std::fs::read_to_string(path)?)?;
+        anyhow::ensure!(
+            meta.version == CNF_META_VERSION,
+            "unsupported CNF metadata version {} (expected {})",
+            meta.version,
+            CNF_META_VERSION
+        );
+        // The explorations must have been made under the recorded rules.
+        anyhow::ensure!(
+            (1..=crate::judge::MAX_DOORS).contains(&meta.doors)
+                && (1..=crate::judge::MAX_LABELS).contains(&meta.num_labels),
+            "invalid rules in CNF metadata: {} doors, {} labels",
+            meta.doors,
+            meta.num_labels
+        );
+        anyhow::ensure!(
+            meta.plans.iter().flatten().all(|&e| e < meta.doors)
+                && meta.labels.iter().flatten().all(|&k| k < meta.num_labels),
+            "CNF metadata explorations use doors or labels outside {} doors and {} labels",
+            meta.doors,
+            meta.num_labels
+        );
+        Ok(meta)
+    }
+
+    /// Decodes a model (set of true literals) into a guess, mirroring `extract_guess`.
+    pub fn decode(&self, model: &std::collections::HashSet<i32>) -> Guess {
+        let n = self.num_rooms;
+        let is_true = |v: i32| model.contains(&v);
+        let start = (0..n)
+            .find(|&u| self.V[0][u].is_some_and(is_true))
+            .unwrap_or(0);
+        let rooms = (0..n)
+            .map(|u| match &self.L {
+                Some(L) => (0..self.num_labels)
+                    .find(|&k| is_true(L[u][k]))
+                    .unwrap_or(0),
+                None => u % self.num_labels,
+            })
+            .collect();
+        let mut graph = vec![[(!0, !0); 6]; n];
+        for u in 0..n {
+            for e in self.doors..6 {
+                graph[u][e] = (u, e);
+            }
+            for e in 0..self.doors {
+                let v = (0..n).find(|&v| is_true(self.F[u][e][v])).unwrap_or(0);
+                let f = (0..6).find(|&f| is_true(self.M[u][v][e][f])).unwrap_or(0);
+                graph[u][e] = (v, f);
+            }
+        }
+        Guess {
+            start,
+            rooms,
+            graph,
+        }
+    }
+}
+
+/// Path of the `CnfMeta` sidecar for a DIMACS file: `foo.cnf` -> `foo.meta.json`.
+pub fn cnf_meta_path(dimacs_path: &Path) -> std::path::PathBuf {
+    dimacs_path.with_extension("meta.json")
+}
+
+/// Parses solver output `v` lines into the set of true-or-false literals.
+fn parse_model_lines(buf: &str) -> std::collections::HashSet<i32> {
+    let mut solution = std::collections::HashSet::new();
+    for line in buf.lines() {
+        if !(line.starts_with('v') || line.starts_with('V')) {
+            continue;
+        }
+        for tok in line.split_whitespace() {
+            if tok == "v" || tok == "V" {
+                continue;
+            }
+            if let Ok(x) = tok.parse::<i32>() {
+                if x == 0 {
+                    break;
+                }
+                solution.insert(x);
+            }
+        }
+    }
+    solution
+}
+
+/// Reconstructs a guess from a saved `CnfMeta` file and an external solver's output
+/// (`v` lines), without rebuilding or re-solving the CNF.
+///
+/// Fails if the model is empty or the decoded guess does not reproduce the explorations
+/// recorded in the metadata.
+pub fn extract_from_model_file(meta_path: &Path, model_path: &Path) -> anyhow::Result<Guess> {
+    let meta = CnfMeta::read(meta_path)?;
+    let model = parse_model_lines(&std::fs::read_to_string(model_path)?);
+    anyhow::ensure!(
+        !model.is_empty(),
+        "no 'v' lines in {}",
+        model_path.display()
+    );
+    let mut guess = meta.decode(&model);
+    anyhow::ensure!(
+        check_explore_with_policy(&guess, &meta.plans, &meta.labels, meta.start_policy),
+        "decoded guess is inconsistent with the recorded explorations"
+    );
+    connect_guess(&mut guess, &meta.plans, meta.start_policy);
+    Ok(guess)
+}
+
+// -------------------------- CNF construction wrapper ---------------------
+
+fn build_cnf_for_plans(
+    num_rooms: usize,
+    plans: &Vec<Vec<usize>>,
+    labels: &Vec<Vec<usize>>,
+    mode: LabelMode,
+    rules: Rules,
+) -> (PlanInfo, Buckets, Cnf, Candidates, EdgeVars) {
+    use crate::profiling::scope;
+
+    // 1) Build flattened info from provided plans and labels
+    let info = {
+        let _p = scope("info+diff");
+        build_info(num_rooms, plans, labels, rules)
+    };
+
+    // 2) Build buckets and candidates
+    let free_labels = resolve_label_mode(&info, mode);
+    let buckets = build_buckets(&info, free_labels);
+    let mut cnf = Cnf::new();
+    let cand = {
+        let _p = scope("candidates");
+        cnf.in_group(ClauseGroup::Candidates, |cnf| {
+            build_candidates(cnf, &info, &buckets)
+        })
+    };
+
+    // 3) Add pruning and symmetry breaking
+    {
+        let _p = scope("diff pruning");
+        cnf.in_group(ClauseGroup::Diff, |cnf| {
+            add_diff_pruning(cnf, &info, &buckets, &cand)
+        });
+    }
+    {
+        let _p = scope("sbp");
+        cnf.in_group(ClauseGroup::Sbp, |cnf| add_sbp(cnf, &info, &buckets, &cand));
+    }
+    {
+        let _p = scope("same-door equalization");
+        cnf.in_group(ClauseGroup::Equalization, |cnf| {
+            add_same_door_equalization(cnf, &info, &buckets, &cand)
+        });
+    }
 
     // 4) Edge layer and plan constraints
-    let edges = build_edge_vars(&mut cnf, &info);
-    add_plan_constraints(&mut cnf, &info, &buckets, &cand, &edges);
-    // 4.5) Unify starting room across all plans
-    add_start_room_unification(&mut cnf, &info, &buckets, &cand);
+    let edges = {
+        let _p = scope("edge vars");
+        cnf.in_group(ClauseGroup::Edge, |cnf| build_edge_vars(cnf, &info, &cand))
+    };
+    {
+        let _p = scope("plan constraints");
+        cnf.in_group(ClauseGroup::Plan, |cnf| {
+            add_plan_constraints(cnf, &info, &buckets, &cand, &edges)
+        });
+        // 4.5) Unify starting room across all plans
+        cnf.in_group(ClauseGroup::Start, |cnf| {
+            add_start_room_unification(cnf, &info, &buckets, &cand)
+        });
+    }
+
+    crate::progress::phase(
+        "cnf",
+        serde_json::json!({
+            "rooms": num_rooms,
+            "steps": info.labels.len(),
+            "vars": cnf.num_vars(),
+            "clauses": cnf.num_clauses(),
+            "amo": cnf
+                .encoding_stats()
+                .iter()
+                .map(|(amo, st)| {
+                    (
+                        amo.as_str().to_string(),
+                        serde_json::json!({
+                            "constraints": st.constraints,
+                            "clauses": st.clauses,
+                            "vars": st.vars,
+                        }),
+                    )
+                })
+                .collect::<serde_json::Map<_, _>>(),
+        }),
+    );
 
     (info, buckets, cnf, cand, edges)
 }
 
+/// Builds the CNF for the explorations without solving it and returns its
+/// numbers of variables and clauses (see `bin/bench_cnf.rs`).
+pub fn cnf_size(
+    num_rooms: usize,
+    plans: &Vec<Vec<usize>>,
+    labels: &Vec<Vec<usize>>,
+) -> (usize, usize) {
+    let (_, _, cnf, _, _) =
+        build_cnf_for_plans(num_rooms, plans, labels, LabelMode::Auto, Rules::global());
+    (cnf.num_vars(), cnf.num_clauses())
+}
+
+/// Whether some map explains the explorations, or `None` if the SAT solver
+/// does not decide within `timeout` (CNF construction is not bounded).
+pub fn is_satisfiable_within(
+    num_rooms: usize,
+    plans: &Vec<Vec<usize>>,
+    labels: &Vec<Vec<usize>>,
+    timeout: std::time::Duration,
+) -> Option<bool> {
+    let (_, _, mut cnf, _, _) =
+        build_cnf_for_plans(num_rooms, plans, labels, LabelMode::Auto, Rules::global());
+    cnf.sat
+        .set_callbacks(Some(cadical::Timeout::new(timeout.as_secs_f32())));
+    cnf.sat.solve()
+}
+
+/// Up to `limit` maps that explain the explorations and that the judge would
+/// tell apart, i.e. not equal after renumbering the rooms from the start. A
+/// single map means the explorations pin the map down, so a guess of it is
+/// safe; more show what is still ambiguous.
+///
+/// Each model found is excluded with a blocking clause over its door targets
+/// `F[u][e][v]` and the SAT solver is asked again. Models that only renumber
+/// a map already found (symmetry breaking does not rule them all out) are
+/// blocked without being returned.
+pub fn enumerate_solutions(
+    num_rooms: usize,
+    plans: &Vec<Vec<usize>>,
+    labels: &Vec<Vec<usize>>,
+    limit: usize,
+) -> Vec<Guess> {
+    let (info, buckets, mut cnf, cand, edges) =
+        build_cnf_for_plans(num_rooms, plans, labels, LabelMode::Auto, Rules::global());
+    let mut seen = std::collections::HashSet::new();
+    let mut found = vec![];
+    while found.len() < limit && cnf.sat.solve() == Some(true) {
+        let guess = extract_guess(&cnf, &info, &buckets, &cand, &edges);
+        debug_assert!(check_explore_with_policy(
+            &guess,
+            plans,
+            labels,
+            info.start_policy
+        ));
+        cnf.clause(
+            (0..num_rooms)
+                .flat_map(|u| (0..6).map(move |e| (u, e)))
+                .map(|(u, e)| -edges.F[u][e][guess.graph[u][e].0]),
+        );
+        if seen.insert(crate::graph_iso::guess_form(&guess)) {
+            found.push(guess);
+        }
+    }
+    found
+}
+
 pub fn solve(num_rooms: usize, plans: &Vec<Vec<usize>>, labels: &Vec<Vec<usize>>) -> Guess {
-    let (info, buckets, mut cnf, cand, edges) = build_cnf_for_plans(num_rooms, plans, labels);
+    solve_with_label_mode(num_rooms, plans, labels, LabelMode::Auto)
+}
+
+/// Same as `solve`, with explicit control over how room labels are encoded.
+pub fn solve_with_label_mode(
+    num_rooms: usize,
+    plans: &Vec<Vec<usize>>,
+    labels: &Vec<Vec<usize>>,
+    mode: LabelMode,
+) -> Guess {
+    solve_with_rules(num_rooms, plans, labels, mode, Rules::global())
+}
+
+/// Same as `solve_with_label_mode`, for a map played under `rules` instead of
+/// [`Rules::global`].
+pub fn solve_with_rules(
+    num_rooms: usize,
+    plans: &Vec<Vec<usize>>,
+    labels: &Vec<Vec<usize>>,
+    mode: LabelMode,
+    rules: Rules,
+) -> Guess {
+    let (info, buckets, mut cnf, cand, edges) =
+        build_cnf_for_plans(num_rooms, plans, labels, mode, rules);
 
     // 5) Solve
-    assert_eq!(cnf.sat.solve(), Some(true));
-    let guess = extract_guess(&cnf, &info, &buckets, &cand, &edges);
-    assert!(check_explore(&guess, plans, labels));
+    {
+        let _p = crate::profiling::scope("sat solve");
+        if cnf.sat.solve() != Some(true) {
+            if let Ok(stem) = std::env::var("UNAGI_DRAT_PROOF") {
+                match cnf.write_drat_proof(std::path::Path::new(&stem)) {
+                    Ok(unsat) => {
+                        eprintln!("DRAT proof written to {}.drat (UNSAT: {})", stem, unsat)
+                    }
+                    Err(e) => eprintln!("failed to write DRAT proof: {:#}", e),
+                }
+            }
+            match diagnose_unsat(num_rooms, plans, labels, mode, rules) {
+                Some(core) => panic!("no map explains the explorations: {}", core),
+                None => panic!(
+                    "no map explains the explorations (pruned encoding only; conflicting groups: {})",
+                    cnf.explain_unsat().unwrap_or_default().iter().join(", ")
+                ),
+            }
+        }
+    }
+    crate::progress::phase("solved", serde_json::json!({ "solver": "cadical" }));
+    let mut guess = extract_guess(&cnf, &info, &buckets, &cand, &edges);
+    assert!(check_explore_with_policy(
+        &guess,
+        plans,
+        labels,
+        info.start_policy
+    ));
+    connect_guess(&mut guess, plans, info.start_policy);
     guess
 }
 
@@ -652,10 +1857,10 @@ pub fn solve_with_edge_prefix_fixed(
     prefix: &[(usize, usize, usize, Option<usize>)],
 ) -> Option<Guess> {
     // 1) Build flattened info from provided plans and labels
-    let info = build_info(num_rooms, plans, labels);
+    let info = build_info(num_rooms, plans, labels, Rules::global());
 
-    // 2) Build buckets and candidates
-    let buckets = build_buckets(&info);
+    // 2) Build buckets and candidates (prefixes are given in terms of `u % k` rooms)
+    let buckets = build_buckets(&info, false);
     let mut cnf = Cnf::new();
     let cand = build_candidates(&mut cnf, &info, &buckets);
 
@@ -665,7 +1870,7 @@ pub fn solve_with_edge_prefix_fixed(
     add_same_door_equalization(&mut cnf, &info, &buckets, &cand);
 
     // 4) Edge layer and plan constraints
-    let edges = build_edge_vars(&mut cnf, &info);
+    let edges = build_edge_vars(&mut cnf, &info, &cand);
     // Apply prefix edge constraints
     for &(u, e, v, f_opt) in prefix.iter() {
         if u >= info.n || v >= info.n || e >= 6 {
@@ -685,8 +1890,14 @@ pub fn solve_with_edge_prefix_fixed(
     // 5) Solve
     match cnf.sat.solve() {
         Some(true) => {
-            let guess = extract_guess(&cnf, &info, &buckets, &cand, &edges);
-            assert!(check_explore(&guess, plans, labels));
+            let mut guess = extract_guess(&cnf, &info, &buckets, &cand, &edges);
+            assert!(check_explore_with_policy(
+                &guess,
+                plans,
+                labels,
+                info.start_policy
+            ));
+            connect_guess(&mut guess, plans, info.start_policy);
             Some(guess)
         }
         _ => None,
@@ -703,10 +1914,10 @@ pub fn solve_with_edge_prefixes_any(
     prefixes: &[Vec<(usize, usize, usize, Option<usize>)>],
 ) -> Option<Guess> {
     // 1) Build flattened info from provided plans and labels
-    let info = build_info(num_rooms, plans, labels);
+    let info = build_info(num_rooms, plans, labels, Rules::global());
 
-    // 2) Build buckets and candidates
-    let buckets = build_buckets(&info);
+    // 2) Build buckets and candidates (prefixes are given in terms of `u % k` rooms)
+    let buckets = build_buckets(&info, false);
     let mut cnf = Cnf::new();
     let cand = build_candidates(&mut cnf, &info, &buckets);
 
@@ -716,7 +1927,7 @@ pub fn solve_with_edge_prefixes_any(
     add_same_door_equalization(&mut cnf, &info, &buckets, &cand);
 
     // 4) Edge layer and plan constraints
-    let edges = build_edge_vars(&mut cnf, &info);
+    let edges = build_edge_vars(&mut cnf, &info, &cand);
     // Build selector vars for each prefix and add guarded implications.
     let mut sels: Vec<i32> = Vec::with_capacity(prefixes.len());
     for pref in prefixes {
@@ -749,26 +1960,533 @@ pub fn solve_with_edge_prefixes_any(
     // 5) Solve
     match cnf.sat.solve() {
         Some(true) => {
-            let guess = extract_guess(&cnf, &info, &buckets, &cand, &edges);
-            assert!(check_explore(&guess, plans, labels));
+            let mut guess = extract_guess(&cnf, &info, &buckets, &cand, &edges);
+            assert!(check_explore_with_policy(
+                &guess,
+                plans,
+                labels,
+                info.start_policy
+            ));
+            connect_guess(&mut guess, plans, info.start_policy);
             Some(guess)
         }
         _ => None,
     }
 }
 
+/// Transitions an annealed edge needs before `solve_hybrid` fixes it.
+const HYBRID_MIN_SUPPORT: usize = 2;
+
+/// Anneals for the edges the explorations support well and fixes them with
+/// `solve_with_edge_prefix_fixed`, which is much faster than the open CNF when
+/// they are right. Falls back to `solve` when the fixed edges are unsatisfiable.
+pub fn solve_hybrid(
+    num_rooms: usize,
+    plans: &Vec<Vec<usize>>,
+    labels: &Vec<Vec<usize>>,
+    params: &crate::solve_anneal::AnnealParams,
+) -> Guess {
+    let prefix =
+        crate::solve_anneal::confident_edges(num_rooms, plans, labels, params, HYBRID_MIN_SUPPORT)
+            .into_iter()
+            .map(|(u, e, v)| (u, e, v, None))
+            .collect_vec();
+    solve_with_prefix_or_open(num_rooms, plans, labels, &prefix)
+}
+
+/// `solve_with_edge_prefix_fixed`, or `solve` when `prefix` is unsatisfiable.
+fn solve_with_prefix_or_open(
+    num_rooms: usize,
+    plans: &Vec<Vec<usize>>,
+    labels: &Vec<Vec<usize>>,
+    prefix: &[(usize, usize, usize, Option<usize>)],
+) -> Guess {
+    if !prefix.is_empty() {
+        if let Some(guess) = solve_with_edge_prefix_fixed(num_rooms, plans, labels, prefix) {
+            crate::progress::phase(
+                "solved",
+                serde_json::json!({ "solver": "hybrid", "fixed_edges": prefix.len() }),
+            );
+            return guess;
+        }
+        eprintln!(
+            "solve_hybrid: {} annealed edges are unsatisfiable; solving without them",
+            prefix.len()
+        );
+    }
+    solve(num_rooms, plans, labels)
+}
+
+// -------------------------- Incremental solving -------------------------
+
+/// Keeps one CaDiCaL instance alive across explore rounds.
+///
+/// The first batch is encoded exactly as in `solve`, including pruning and
+/// symmetry breaking (both stay valid when data is appended). Later batches only
+/// add their room variables, transitions and start-room links, so each round
+/// costs as much as its own plans instead of the whole history. The label mode
+/// is resolved on the first batch.
+pub struct IncrementalSolver {
+    info: PlanInfo,
+    buckets: Buckets,
+    cnf: Cnf,
+    cand: Candidates,
+    edges: EdgeVars,
+    plans: Vec<Vec<usize>>,
+    labels: Vec<Vec<usize>>,
+}
+
+impl IncrementalSolver {
+    pub fn new(
+        num_rooms: usize,
+        plans: &Vec<Vec<usize>>,
+        labels: &Vec<Vec<usize>>,
+        mode: LabelMode,
+    ) -> Self {
+        Self::with_rules(num_rooms, plans, labels, mode, Rules::global())
+    }
+
+    /// Same as `new`, for a map played under `rules`.
+    pub fn with_rules(
+        num_rooms: usize,
+        plans: &Vec<Vec<usize>>,
+        labels: &Vec<Vec<usize>>,
+        mode: LabelMode,
+        rules: Rules,
+    ) -> Self {
+        let (mut info, buckets, cnf, cand, edges) =
+            build_cnf_for_plans(num_rooms, plans, labels, mode, rules);
+        // Only the first batch is pruned with `diff`; drop the O(m^2) table.
+        info.diff = vec![];
+        Self {
+            info,
+            buckets,
+            cnf,
+            cand,
+            edges,
+            plans: plans.clone(),
+            labels: labels.clone(),
+        }
+    }
+
+    /// Appends the plans and results of another explore.
+    pub fn add_explores(&mut self, plans: &[Vec<usize>], labels: &[Vec<usize>]) {
+        assert_eq!(plans.len(), labels.len());
+        let from = self.info.m;
+        for (p, l) in plans.iter().zip(labels) {
+            assert_eq!(l.len(), p.len() + 1);
+            self.info.starts.push(self.info.labels.len());
+            for &k in l {
+                assert!(k < self.info.num_labels, "label {} out of range", k);
+                if let Some(L) = &self.cand.L
+                    && !self.info.labels.contains(&k)
+                {
+                    self.cnf.clause((0..self.info.n).map(|u| L[u][k]));
+                }
+                self.info.labels.push(k);
+            }
+            self.info.door.extend(p.iter().map(|&e| Some(e)));
+            self.info.door.push(None);
+        }
+        self.info.m = self.info.labels.len();
+        for k in 0..self.info.num_labels {
+            self.buckets.times_by_label[k]
+                .extend((from..self.info.m).filter(|&i| self.info.labels[i] == k));
+        }
+        extend_candidates(&mut self.cnf, &self.info, &self.buckets, &mut self.cand);
+        add_plan_constraints_from(
+            &mut self.cnf,
+            &self.info,
+            &self.buckets,
+            &self.cand,
+            &self.edges,
+            from,
+        );
+        add_start_room_unification_from(&mut self.cnf, &self.info, &self.buckets, &self.cand, from);
+        self.plans.extend_from_slice(plans);
+        self.labels.extend_from_slice(labels);
+    }
+
+    /// Solves the explorations added so far. Returns `None` if unsatisfiable.
+    pub fn solve(&mut self) -> Option<Guess> {
+        self.solve_assuming(&[])
+    }
+
+    /// Solves with edges `(u, e, v)` (door `e` of room `u` leads to `v`)
+    /// assumed for this call only. Returns `None` if unsatisfiable under them.
+    pub fn solve_assuming(&mut self, edges: &[(usize, usize, usize)]) -> Option<Guess> {
+        let n = self.info.n;
+        if edges.iter().any(|&(u, e, v)| u >= n || e >= 6 || v >= n) {
+            return None;
+        }
+        let assumptions = edges
+            .iter()
+            .map(|&(u, e, v)| self.edges.F[u][e][v])
+            .collect_vec();
+        if self.cnf.sat.solve_with(assumptions) != Some(true) {
+            return None;
+        }
+        let mut guess = extract_guess(
+            &self.cnf,
+            &self.info,
+            &self.buckets,
+            &self.cand,
+            &self.edges,
+        );
+        assert!(check_explore_with_policy(
+            &guess,
+            &self.plans,
+            &self.labels,
+            self.info.start_policy
+        ));
+        connect_guess(&mut guess, &self.plans, self.info.start_policy);
+        Some(guess)
+    }
+}
+
+// -------------------------- Soft structural priors ----------------------
+
+/// Structural beliefs about the map that usually hold but do not follow from the
+/// explorations. Each room's prior is guarded by a relaxation variable, and at
+/// most `max_violations` rooms may be relaxed.
+#[derive(Clone, Copy, Debug)]
+pub struct Priors {
+    /// At most this many of a room's doors lead back into the same room.
+    pub max_self_loops: usize,
+    /// Number of rooms allowed to break the prior.
+    pub max_violations: usize,
+}
+
+impl Default for Priors {
+    fn default() -> Self {
+        Priors {
+            max_self_loops: 1,
+            max_violations: 2,
+        }
+    }
+}
+
+/// Adds the priors with one relaxation variable per room and returns
+/// `(relax, at_least)`, where `at_least[j]` is implied when `j + 1` or more
+/// rooms are relaxed (a sequential counter up to `max_violations + 1`, whose
+/// last output is forbidden).
+fn add_soft_priors(
+    cnf: &mut Cnf,
+    info: &PlanInfo,
+    edges: &EdgeVars,
+    priors: &Priors,
+) -> (Vec<i32>, Vec<i32>) {
+    let n = info.n;
+    let relax = (0..n).map(|_| cnf.var()).collect_vec();
+    // Any max_self_loops + 1 self-loops of a room need its relaxation variable.
+    for u in 0..n {
+        let loops = (0..info.doors).map(|e| edges.F[u][e][u]).collect_vec();
+        for subset in loops.iter().combinations(priors.max_self_loops + 1) {
+            cnf.clause(std::iter::once(relax[u]).chain(subset.into_iter().map(|&x| -x)));
+        }
+    }
+
+    let k = priors.max_violations + 1;
+    let mut prev: Vec<i32> = vec![];
+    for (i, &r) in relax.iter().enumerate() {
+        let cur = (0..k.min(i + 1)).map(|_| cnf.var()).collect_vec();
+        cnf.clause([-r, cur[0]]);
+        for j in 0..cur.len() {
+            if j < prev.len() {
+                cnf.clause([-prev[j], cur[j]]);
+            }
+            if j > 0 {
+                cnf.clause([-r, -prev[j - 1], cur[j]]);
+            }
+        }
+        prev = cur;
+    }
+    if prev.len() == k {
+        cnf.clause([-prev[k - 1]]);
+    }
+    (relax, prev)
+}
+
+/// Solves with [`Priors`] as soft constraints.
+///
+/// The first call assumes every room keeps the prior. While that is
+/// unsatisfiable, the rooms whose assumptions appear in the failed core are
+/// relaxed and the solve is repeated; once a model is found, the number of
+/// relaxed rooms is tightened one at a time through the counter. Returns the
+/// guess and how many rooms break the prior, or `None` if the data cannot be
+/// explained within `max_violations`.
+pub fn solve_with_priors(
+    num_rooms: usize,
+    plans: &Vec<Vec<usize>>,
+    labels: &Vec<Vec<usize>>,
+    mode: LabelMode,
+    priors: &Priors,
+) -> Option<(Guess, usize)> {
+    let (info, buckets, mut cnf, cand, edges) =
+        build_cnf_for_plans(num_rooms, plans, labels, mode, Rules::global());
+    let (relax, at_least) = add_soft_priors(&mut cnf, &info, &edges, priors);
+
+    let mut kept = vec![true; relax.len()];
+    loop {
+        let assumptions = (0..relax.len())
+            .filter(|&u| kept[u])
+            .map(|u| -relax[u])
+            .collect_vec();
+        if cnf.sat.solve_with(assumptions.iter().copied()) == Some(true) {
+            break;
+        }
+        let core = assumptions
+            .iter()
+            .filter(|&&a| cnf.sat.failed(a))
+            .map(|&a| relax.iter().position(|&r| r == -a).unwrap())
+            .collect_vec();
+        if core.is_empty() {
+            return None;
+        }
+        for u in core {
+            kept[u] = false;
+        }
+    }
+
+    // Relaxation variables may be true without need, so count the rooms instead.
+    let broken = |guess: &Guess| {
+        (0..info.n)
+            .filter(|&u| {
+                (0..info.doors)
+                    .filter(|&e| guess.graph[u][e].0 == u)
+                    .count()
+                    > priors.max_self_loops
+            })
+            .count()
+    };
+    let mut guess = extract_guess(&cnf, &info, &buckets, &cand, &edges);
+    let mut violations = broken(&guess);
+    while violations > 0 {
+        let mut assumptions = (0..relax.len())
+            .filter(|&u| kept[u])
+            .map(|u| -relax[u])
+            .collect_vec();
+        assumptions.push(-at_least[violations - 1]);
+        if cnf.sat.solve_with(assumptions) != Some(true) {
+            break;
+        }
+        guess = extract_guess(&cnf, &info, &buckets, &cand, &edges);
+        violations = broken(&guess);
+    }
+    crate::progress::phase(
+        "solved",
+        serde_json::json!({ "solver": "cadical", "prior_violations": violations }),
+    );
+    assert!(check_explore_with_policy(
+        &guess,
+        plans,
+        labels,
+        info.start_policy
+    ));
+    connect_guess(&mut guess, plans, info.start_policy);
+    Some((guess, violations))
+}
+
+// -------------------------- UNSAT diagnosis ------------------------------
+
+/// Conflict budget of each solve while shrinking a core.
+const CORE_MINIMIZE_CONFLICTS: i32 = 10_000;
+/// Cores larger than this are reported without deletion-based minimization.
+const CORE_MINIMIZE_MAX: usize = 200;
+
+/// Observations implicated in the unsatisfiability of a session.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct UnsatCore {
+    /// `(plan, step)`: door `step` of `plan` together with the labels seen
+    /// before and after it.
+    pub steps: Vec<(usize, usize)>,
+    /// Plans whose starting label is implicated (every plan starts in the
+    /// room the first plan with that label started in).
+    pub starts: Vec<usize>,
+}
+
+impl UnsatCore {
+    /// Plans involved in the core, ascending.
+    pub fn plans(&self) -> Vec<usize> {
+        self.steps
+            .iter()
+            .map(|&(p, _)| p)
+            .chain(self.starts.iter().copied())
+            .sorted()
+            .dedup()
+            .collect()
+    }
+}
+
+impl std::fmt::Display for UnsatCore {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        if self.steps.is_empty() && self.starts.is_empty() {
+            return write!(
+                f,
+                "unsatisfiable without any transition: the observed labels do not fit numRooms"
+            );
+        }
+        let parts = self.plans().into_iter().map(|p| {
+            let steps = self
+                .steps
+                .iter()
+                .filter(|&&(q, _)| q == p)
+                .map(|&(_, t)| t.to_string())
+                .join(",");
+            let start = if self.starts.contains(&p) {
+                " start"
+            } else {
+                ""
+            };
+            if steps.is_empty() {
+                format!("plan {}:{}", p, start)
+            } else {
+                format!("plan {}:{} steps {}", p, start, steps)
+            }
+        });
+        write!(f, "inconsistent observations in {}", parts.format("; "))
+    }
+}
+
+/// Finds which observations make the session unsatisfiable, or `None` if it
+/// is satisfiable.
+///
+/// Every transition and start-room link is guarded by its own activation
+/// literal, and the failed assumptions give the core, which is then shrunk by
+/// re-solving on it and, if small enough, by deleting one observation at a
+/// time. Pruning and symmetry breaking are left out, as they derive from all
+/// observations at once. An empty core means `numRooms` (or the label split)
+/// itself is wrong.
+pub fn diagnose_unsat(
+    num_rooms: usize,
+    plans: &Vec<Vec<usize>>,
+    labels: &Vec<Vec<usize>>,
+    mode: LabelMode,
+    rules: Rules,
+) -> Option<UnsatCore> {
+    let info = build_info(num_rooms, plans, labels, rules);
+    let free_labels = resolve_label_mode(&info, mode);
+    let buckets = build_buckets(&info, free_labels);
+    let mut cnf = Cnf::new();
+    let cand = build_candidates(&mut cnf, &info, &buckets);
+    let edges = build_edge_vars(&mut cnf, &info, &cand);
+
+    // (activation literal, observation); starts are marked with step = usize::MAX.
+    let mut acts: Vec<(i32, (usize, usize))> = vec![];
+    for (p, &s) in info.starts.iter().enumerate() {
+        for t in 0..plans[p].len() {
+            let i = s + t;
+            let a = cnf.var();
+            acts.push((a, (p, t)));
+            let (e, k, h) = (info.door[i].unwrap(), info.labels[i], info.labels[i + 1]);
+            for &u in &buckets.rooms_by_label[k] {
+                let vi = cand.V_map[i][u].unwrap();
+                cnf.clause([-a, -vi, edges.Tlab[u][e][h]]);
+                for &v in &buckets.rooms_by_label[h] {
+                    let vj = cand.V_map[i + 1][v].unwrap();
+                    cnf.clause([-a, -vi, -vj, edges.F[u][e][v]]);
+                }
+            }
+        }
+        if let Some(&s0) = info.starts[..p]
+            .iter()
+            .find(|&&s0| info.labels[s0] == info.labels[s])
+        {
+            let a = cnf.var();
+            acts.push((a, (p, usize::MAX)));
+            for &u in &buckets.rooms_by_label[info.labels[s]] {
+                let v0 = cand.V_map[s0][u].unwrap();
+                let vi = cand.V_map[s][u].unwrap();
+                cnf.clause([-a, -v0, vi]);
+                cnf.clause([-a, -vi, v0]);
+            }
+        }
+    }
+
+    let mut core = acts.iter().map(|&(a, _)| a).collect_vec();
+    if cnf.sat.solve_with(core.iter().copied()) != Some(false) {
+        return None;
+    }
+    // Re-solving on the core alone usually returns a smaller one.
+    loop {
+        let next = core
+            .iter()
+            .copied()
+            .filter(|&a| cnf.sat.failed(a))
+            .collect_vec();
+        let shrunk = next.len() < core.len();
+        core = next;
+        if !shrunk || cnf.sat.solve_with(core.iter().copied()) != Some(false) {
+            break;
+        }
+    }
+    if core.len() <= CORE_MINIMIZE_MAX {
+        let mut i = 0;
+        while i < core.len() {
+            let rest = core
+                .iter()
+                .enumerate()
+                .filter(|&(j, _)| j != i)
+                .map(|(_, &a)| a)
+                .collect_vec();
+            cnf.sat
+                .set_limit("conflicts", CORE_MINIMIZE_CONFLICTS)
+                .unwrap();
+            if cnf.sat.solve_with(rest.iter().copied()) == Some(false) {
+                core = rest.into_iter().filter(|&a| cnf.sat.failed(a)).collect();
+            } else {
+                i += 1;
+            }
+        }
+    }
+
+    let mut result = UnsatCore::default();
+    for &(a, (p, t)) in &acts {
+        if core.contains(&a) {
+            if t == usize::MAX {
+                result.starts.push(p);
+            } else {
+                result.steps.push((p, t));
+            }
+        }
+    }
+    Some(result)
+}
+
 // ------------------------------ Portfolio Solver -------------------------------------
 
 pub struct SATSolver {
     pub path: String,
     pub args: Vec<String>,
+    /// Feed the CNF on stdin instead of passing the DIMACS path as the last
+    /// argument, for solvers that cannot read files.
+    pub stdin: bool,
 }
 
-pub fn launch_portfolio(
-    dimacs_path: &std::path::Path,
-    solvers: &[SATSolver],
-) -> std::collections::HashSet<i32> {
-    use std::collections::HashSet;
+/// Outcome of `launch_portfolio`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum PortfolioResult {
+    /// Model (set of true/false literals) of the first solver that found one.
+    Sat(std::collections::HashSet<i32>),
+    /// A solver proved UNSAT; the others were cancelled.
+    Unsat,
+    /// Every solver exited without a model or an UNSAT verdict (crashes,
+    /// unexpected exit codes, ...).
+    Unknown,
+}
+
+impl PortfolioResult {
+    /// The model, or an error saying why there is none.
+    pub fn into_model(self) -> anyhow::Result<std::collections::HashSet<i32>> {
+        match self {
+            PortfolioResult::Sat(model) => Ok(model),
+            PortfolioResult::Unsat => anyhow::bail!("a portfolio solver proved UNSAT"),
+            PortfolioResult::Unknown => anyhow::bail!("no solver produced a satisfiable model"),
+        }
+    }
+}
+
+pub fn launch_portfolio(dimacs_path: &std::path::Path, solvers: &[SATSolver]) -> PortfolioResult {
     use std::io::{BufRead, BufReader, Write};
     use std::process::{Child, Command, Stdio};
     use std::sync::{Arc, Mutex, mpsc};
@@ -776,20 +2494,40 @@ pub fn launch_portfolio(
 
     assert!(!solvers.is_empty(), "no solvers provided");
 
+    // Read once for all stdin solvers.
+    let dimacs: Option<Arc<Vec<u8>>> = solvers.iter().any(|s| s.stdin).then(|| {
+        Arc::new(std::fs::read(dimacs_path).expect("failed to read DIMACS for stdin solvers"))
+    });
+
     // Spawn all solvers
     let mut children: Vec<Arc<Mutex<Child>>> = Vec::with_capacity(solvers.len());
     let (tx, rx) = mpsc::channel();
     let mut handles = Vec::with_capacity(solvers.len());
 
     for (idx, s) in solvers.iter().enumerate() {
-        let mut child = Command::new(&s.path)
-            .args(&s.args)
-            .arg(dimacs_path)
+        let mut command = Command::new(&s.path);
+        command.args(&s.args);
+        if s.stdin {
+            command.stdin(Stdio::piped());
+        } else {
+            command.arg(dimacs_path).stdin(Stdio::null());
+        }
+        let mut child = command
             .stdout(Stdio::piped())
             .stderr(Stdio::inherit())
             .spawn()
             .expect("failed to spawn portfolio solver");
 
+        if let Some(mut stdin) = child.stdin.take() {
+            // Write from a separate thread so that a solver which starts
+            // printing before it has read everything cannot block us. Errors
+            // (e.g. the solver was killed) are ignored.
+            let dimacs = Arc::clone(dimacs.as_ref().unwrap());
+            thread::spawn(move || {
+                let _ = stdin.write_all(&dimacs);
+            });
+        }
+
         let stdout = child
             .stdout
             .take()
@@ -832,8 +2570,9 @@ pub fn launch_portfolio(
 
     drop(tx); // close sender in main thread
 
-    // Receive first acceptable result
+    // Receive the first model or UNSAT verdict; either ends the race.
     let mut winner: Option<(usize, String)> = None;
+    let mut unsat = false;
     for received in rx.iter() {
         let (idx, code, buf, saw_unsat, saw_v) = received;
         if (code == Some(0) || code == Some(10)) && !saw_unsat && saw_v {
@@ -843,9 +2582,24 @@ pub fn launch_portfolio(
             winner = Some((idx, buf));
             break;
         }
+        // Exit code 20 is the SAT competition convention for UNSAT.
+        if saw_unsat || code == Some(20) {
+            let s = &solvers[idx];
+            eprintln!("Portfolio: UNSAT by {} {}", s.path, s.args.join(" "));
+            unsat = true;
+            break;
+        } else {
+            let s = &solvers[idx];
+            eprintln!(
+                "Portfolio solver gave no answer (exit code {:?}): {} {}",
+                code,
+                s.path,
+                s.args.join(" ")
+            );
+        }
     }
 
-    // Kill all losers
+    // Kill all losers (all solvers still running after an UNSAT verdict)
     if let Some((win_idx, _)) = &winner {
         for (i, ch) in children.iter().enumerate() {
             if i != *win_idx {
@@ -864,31 +2618,56 @@ pub fn launch_portfolio(
         let _ = h.join();
     }
 
-    let (_, buf) = winner.expect("no solver produced a satisfiable model");
+    let Some((_, buf)) = winner else {
+        return if unsat {
+            PortfolioResult::Unsat
+        } else {
+            PortfolioResult::Unknown
+        };
+    };
 
     // Parse 'v' lines into a model set
-    let mut solution: HashSet<i32> = HashSet::new();
-    for line in buf.lines() {
-        if !(line.starts_with('v') || line.starts_with('V')) {
-            continue;
-        }
-        for tok in line.split_whitespace() {
-            if tok == "v" || tok == "V" {
-                continue;
-            }
-            if let Ok(x) = tok.parse::<i32>() {
-                if x == 0 {
-                    break;
-                }
-                solution.insert(x);
-            }
-        }
-    }
+    let solution = parse_model_lines(&buf);
     assert!(
         !solution.is_empty(),
         "winner solver produced no 'v' assignment lines"
     );
-    solution
+    PortfolioResult::Sat(solution)
+}
+
+/// Runs the portfolio on the DIMACS already written to `dimacs_path`.
+///
+/// With `CNF_PREPROCESS=1`, the clauses are first simplified in-crate (see
+/// `cnf_preprocess`) and the solvers get `*.pre.cnf` instead. The returned
+/// model then only covers variables that survived; the caller's CaDiCaL
+/// instance assigns the eliminated ones when the model is injected.
+fn run_portfolio(
+    cnf: &Cnf,
+    dimacs_path: &std::path::Path,
+    solvers: &[SATSolver],
+) -> PortfolioResult {
+    if std::env::var("CNF_PREPROCESS").is_ok_and(|v| v == "1") {
+        let pre = crate::cnf_preprocess::preprocess(
+            cnf.id.cnt as usize,
+            &cnf.clauses,
+            &crate::cnf_preprocess::Options::default(),
+        );
+        eprintln!(
+            "Preprocessed: clauses {} -> {}, eliminated {} variables",
+            cnf.clauses.len(),
+            pre.clauses.len(),
+            pre.eliminated.len(),
+        );
+        let pre_path = dimacs_path.with_extension("pre.cnf");
+        pre.write_dimacs(&pre_path)
+            .expect("failed to write preprocessed DIMACS");
+        match launch_portfolio(&pre_path, solvers) {
+            PortfolioResult::Sat(solution) => PortfolioResult::Sat(pre.restrict_model(&solution)),
+            other => other,
+        }
+    } else {
+        launch_portfolio(dimacs_path, solvers)
+    }
 }
 
 // High-level: build CNF, write DIMACS, run portfolio, inject model, extract Guess
@@ -898,13 +2677,17 @@ pub fn solve_portfolio(
     labels: &Vec<Vec<usize>>,
     solvers: &[SATSolver],
     dimacs_path: &std::path::Path,
-) -> Guess {
+) -> anyhow::Result<Guess> {
     // 1) CNF 構築（solve と共通化）
-    let (info, buckets, mut cnf, cand, edges) = build_cnf_for_plans(num_rooms, plans, labels);
+    let (info, buckets, mut cnf, cand, edges) =
+        build_cnf_for_plans(num_rooms, plans, labels, LabelMode::Auto, Rules::global());
 
-    // 2) DIMACS 書き出し
+    // 2) DIMACS 書き出し（変数レイアウトの JSON も隣に保存）
     cnf.write_dimacs(dimacs_path)
         .expect("failed to write DIMACS");
+    CnfMeta::new(plans, labels, &info, &cand, &edges)
+        .write(&cnf_meta_path(dimacs_path))
+        .expect("failed to write CNF metadata");
     eprintln!(
         "Original: num_clauses={}, num_variables={}, clauses={}",
         cnf.sat.num_clauses(),
@@ -912,22 +2695,35 @@ pub fn solve_portfolio(
         cnf.clauses.len(),
     );
 
-    // 3) 外部ソルバを並列実行（ポートフォリオ）
-    let solution = launch_portfolio(dimacs_path, solvers);
+    // 3) 外部ソルバを並列実行（ポートフォリオ、CNF_PREPROCESS=1 なら前処理後の CNF で）
+    let solution = {
+        let _p = crate::profiling::scope("portfolio");
+        run_portfolio(&cnf, dimacs_path, solvers).into_model()?
+    };
+    crate::progress::phase("solved", serde_json::json!({ "solver": "portfolio" }));
 
     // 4) モデルを単位節として注入 → CaDiCaL で充足化
     for &v in &solution {
         cnf.clause([v]);
     }
-    assert_eq!(cnf.sat.solve(), Some(true));
+    {
+        let _p = crate::profiling::scope("sat solve");
+        assert_eq!(cnf.sat.solve(), Some(true));
+    }
     for &v in &solution {
         assert_eq!(cnf.sat.value(v.abs()), Some(v > 0));
     }
 
     // 5) 既存の抽出ロジックをそのまま利用
-    let guess = extract_guess(&cnf, &info, &buckets, &cand, &edges);
-    assert!(check_explore(&guess, plans, labels));
-    guess
+    let mut guess = extract_guess(&cnf, &info, &buckets, &cand, &edges);
+    assert!(check_explore_with_policy(
+        &guess,
+        plans,
+        labels,
+        info.start_policy
+    ));
+    connect_guess(&mut guess, plans, info.start_policy);
+    Ok(guess)
 }
 
 pub fn solve_cadical_multi(
@@ -935,14 +2731,14 @@ pub fn solve_cadical_multi(
     plans: &Vec<Vec<usize>>,
     labels: &Vec<Vec<usize>>,
     n_workers: usize,
-) -> Guess {
-    let cadical_path = std::env::var("CADICAL_PATH")
-        .unwrap_or_else(|_| "/home/iwiwi/tmp/cadical-rel-2.1.3/build/cadical".to_owned());
+) -> anyhow::Result<Guess> {
+    let cadical_path = cadical_path();
 
     let solvers = (0..n_workers)
         .map(|seed| SATSolver {
             path: cadical_path.to_owned(),
             args: [format!("--seed={}", seed), "--sat".to_owned()].to_vec(),
+            stdin: false,
         })
         .collect_vec();
 
@@ -955,9 +2751,15 @@ pub fn solve_cadical_multi(
     solve_portfolio(num_rooms, &plans, &labels, &solvers, dimacs_path)
 }
 
-pub fn solve_cnf_parallel(cnf: &mut Cnf, n_cadical_workers: usize, n_kissat_workers: usize) {
-    let cadical_path = std::env::var("CADICAL_PATH")
-        .unwrap_or_else(|_| "/home/iwiwi/tmp/cadical-rel-2.1.3/build/cadical".to_owned());
+/// Solves `cnf` with a portfolio of CaDiCaL and kissat processes and injects
+/// the model into its CaDiCaL instance. Fails if the CNF is UNSAT or no
+/// solver answered.
+pub fn solve_cnf_parallel(
+    cnf: &mut Cnf,
+    n_cadical_workers: usize,
+    n_kissat_workers: usize,
+) -> anyhow::Result<()> {
+    let cadical_path = cadical_path();
 
     let kissat_path = std::env::var("KISSAT_PATH")
         .unwrap_or_else(|_| "/home/iwiwi/tmp/kissat-4.0.3-linux-amd64".to_owned());
@@ -966,10 +2768,12 @@ pub fn solve_cnf_parallel(cnf: &mut Cnf, n_cadical_workers: usize, n_kissat_work
         .map(|seed| SATSolver {
             path: cadical_path.to_owned(),
             args: [format!("--seed={}", seed), "--sat".to_owned()].to_vec(),
+            stdin: false,
         })
         .chain((0..n_kissat_workers).map(|seed| SATSolver {
             path: kissat_path.to_owned(),
             args: [format!("--seed={}", seed), "--sat".to_owned()].to_vec(),
+            stdin: false,
         }))
         .collect_vec();
 
@@ -979,10 +2783,744 @@ pub fn solve_cnf_parallel(cnf: &mut Cnf, n_cadical_workers: usize, n_kissat_work
         std::fs::create_dir_all(parent).unwrap();
     }
     cnf.write_dimacs(dimacs_path).unwrap();
-    let solution = launch_portfolio(dimacs_path, &solvers);
+    let solution = run_portfolio(cnf, dimacs_path, &solvers).into_model()?;
 
     for &v in &solution {
         cnf.clause([v]);
     }
     assert_eq!(cnf.sat.solve(), Some(true));
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::judge::check_explore;
+    use rand::prelude::*;
+
+    /// The original sequential DP, kept as a reference.
+    fn compute_diff_naive(door: &[Option<usize>], labels: &[usize]) -> Vec<Vec<bool>> {
+        let m = labels.len();
+        let mut diff = mat![false; m; m];
+        for i in (0..m).rev() {
+            for j in (0..m).rev() {
+                diff[i][j] = labels[i] != labels[j]
+                    || (i + 1 < m
+                        && j + 1 < m
+                        && door[i].is_some()
+                        && door[i] == door[j]
+                        && diff[i + 1][j + 1]);
+            }
+        }
+        diff
+    }
+
+    #[test]
+    fn enumerated_solutions_are_distinct_and_explain_the_data() {
+        use crate::judge::{Judge, LocalJudge};
+        let n = 4;
+        let judge_of = || LocalJudge::new("random", n, 3);
+        let explore = |plan: &[usize]| {
+            let steps = plan.iter().map(|&d| (None, d)).collect::<Vec<_>>();
+            judge_of().explore(&[steps])
+        };
+
+        // A few steps leave most doors open.
+        let plans = vec![vec![0, 1, 2]];
+        let labels = explore(&plans[0]);
+        let maps = enumerate_solutions(n, &plans, &labels, 5);
+        assert_eq!(maps.len(), 5);
+        let forms: std::collections::HashSet<_> =
+            maps.iter().map(crate::graph_iso::guess_form).collect();
+        assert_eq!(forms.len(), 5);
+        assert!(maps.iter().all(|g| check_explore(g, &plans, &labels)));
+
+        // A long balanced plan pins the map down.
+        use crate::routes::planner::{PlanStrategy, RandomBalanced};
+        let len = crate::routes::long_plan_len(n);
+        let plans = vec![RandomBalanced.plan(n, len, &mut StdRng::seed_from_u64(4))];
+        let labels = explore(&plans[0]);
+        let maps = enumerate_solutions(n, &plans, &labels, 3);
+        assert_eq!(maps.len(), 1);
+        assert_eq!(judge_of().check_guess(&maps[0]), Ok(()));
+    }
+
+    #[test]
+    fn auto_label_mode_choice() {
+        let auto = |n: usize, plans: &Vec<Vec<usize>>, labels: &Vec<Vec<usize>>| {
+            resolve_label_mode(
+                &build_info(n, plans, labels, Rules::default()),
+                LabelMode::Auto,
+            )
+        };
+
+        // Judge data follows `u % 4`, so Auto keeps the modulo encoding.
+        let mut judge = crate::judge::LocalJudge::new("random", 6, 3);
+        let mut rng = StdRng::seed_from_u64(4);
+        let plans = vec![(0..108).map(|_| rng.random_range(0..6)).collect_vec()];
+        let steps = plans
+            .iter()
+            .map(|p| p.iter().map(|&d| (None, d)).collect_vec())
+            .collect_vec();
+        let labels = crate::judge::Judge::explore(&mut judge, &steps);
+        assert!(!auto(6, &plans, &labels));
+
+        // A long walk that almost only sees label 0 cannot come from `u % 4` on 4 rooms.
+        let plans = vec![vec![0; 30]];
+        let mut skewed = vec![0; 31];
+        skewed[10] = 1;
+        assert!(auto(4, &plans, &vec![skewed.clone()]));
+        // The same skew on a short walk is not enough evidence.
+        assert!(!auto(4, &vec![vec![0; 4]], &vec![skewed[..5].to_vec()]));
+
+        // Label 3 has no room under `u % 4` with 2 rooms, however short the walk.
+        assert!(auto(2, &vec![vec![0]], &vec![vec![0, 3]]));
+    }
+
+    #[test]
+    fn coverage_and_top_up() {
+        let plans = vec![vec![0, 1, 2, 0]];
+        let labels = vec![vec![0, 1, 2, 1, 0]];
+        let cov = Coverage::new(&plans, &labels);
+        assert_eq!(cov.label_door[1][1], 1);
+        assert_eq!(cov.seen, [true, true, true, false]);
+        // 3 observed labels × 6 doors × 3 observed labels, 4 transitions seen.
+        assert_eq!(cov.aib_missing(), 3 * 6 * 3 - 4);
+        let under = cov.underrepresented(1);
+        assert_eq!(under.len(), 3 * 6 - 4);
+        assert!(!under.contains(&(2, 2)));
+        let gate = CoverageGate {
+            min_label_door: 1,
+            max_aib_missing: None,
+        };
+        assert!(!gate.passes(&cov));
+
+        let mut rng = StdRng::seed_from_u64(0);
+        let extra = top_up_plans(&plans, &labels, &[(2, 5), (3, 0)], 6, &mut rng);
+        assert_eq!(extra.len(), 1);
+        assert_eq!(&extra[0][..3], &[0, 1, 5]);
+        assert_eq!(extra[0].len(), 6);
+    }
+
+    #[test]
+    fn plan_next_complements_existing_data() {
+        use crate::judge::{Explored, Judge};
+        // Doors (room, door) of the true map that the plans traverse.
+        let traversed = |judge: &crate::judge::LocalJudge, plans: &[Vec<usize>]| {
+            let mut seen = std::collections::HashSet::new();
+            for plan in plans {
+                let mut u = judge.starting_room;
+                for &d in plan {
+                    seen.insert((u, d));
+                    u = judge.graph[u][d];
+                }
+            }
+            seen
+        };
+        let (mut gain_next, mut gain_random) = (0, 0);
+        for seed in 0..20 {
+            let mut judge = crate::judge::LocalJudge::new("random", 12, seed);
+            let mut rng = StdRng::seed_from_u64(seed);
+            let first: Vec<usize> = (0..40).map(|_| rng.random_range(0..6)).collect();
+            let steps = vec![first.iter().map(|&d| (None, d)).collect::<Vec<_>>()];
+            let explored = Explored {
+                results: judge.explore(&steps),
+                plans: steps,
+            };
+            let next = plan_next(&explored, 72, Rules::default(), &mut rng);
+            assert_eq!(next.len(), 72);
+            let random: Vec<usize> = (0..72).map(|_| rng.random_range(0..6)).collect();
+            let before = traversed(&judge, std::slice::from_ref(&first)).len();
+            gain_next += traversed(&judge, &[first.clone(), next]).len() - before;
+            gain_random += traversed(&judge, &[first, random]).len() - before;
+        }
+        assert!(gain_next > gain_random, "{} vs {}", gain_next, gain_random);
+
+        // A session without data still gets a door-balanced plan.
+        let empty = Explored {
+            plans: vec![],
+            results: vec![],
+        };
+        let plan = plan_next(&empty, 12, Rules::default(), &mut StdRng::seed_from_u64(0));
+        assert!((0..6).all(|d| plan.iter().filter(|&&e| e == d).count() == 2));
+    }
+
+    #[test]
+    fn extract_from_model_file_single_room() {
+        // One room whose doors all loop back to themselves: F[0][e][0] = 1 + e,
+        // M[0][0][e][f] = 7 + 6e + f.
+        let mut M = vec![vec![[[0; 6]; 6]]];
+        for e in 0..6 {
+            for f in 0..6 {
+                M[0][0][e][f] = 7 + 6 * e as i32 + f as i32;
+            }
+        }
+        let meta = CnfMeta {
+            version: CNF_META_VERSION,
+            num_rooms: 1,
+            plans: vec![vec![0, 3]],
+            labels: vec![vec![0, 0, 0]],
+            V: vec![vec![Some(100)]; 3],
+            doors: 6,
+            start_policy: StartPolicy::FixedRoom,
+            num_labels: 4,
+            L: None,
+            Tlab: vec![vec![vec![0; 4]; 6]],
+            F: vec![(1..=6).map(|v| vec![v]).collect()],
+            M,
+        };
+        let mut model = String::from("s SATISFIABLE\nv 100");
+        for e in 0..6 {
+            model += &format!(" {} {}", 1 + e, 7 + 7 * e);
+        }
+        model += " 0\n";
+
+        let dir = std::env::temp_dir().join(format!("cnf_meta_test_{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let (meta_path, model_path) = (dir.join("meta.json"), dir.join("model.txt"));
+        meta.write(&meta_path).unwrap();
+        std::fs::write(&model_path, model).unwrap();
+        let guess = extract_from_model_file(&meta_path, &model_path).unwrap();
+        assert_eq!(guess.rooms, vec![0]);
+        assert_eq!(guess.graph[0][3], (0, 3));
+
+        // Doors beyond `doors` are self-loops whatever the model says.
+        let four_doors = CnfMeta {
+            doors: 4,
+            ..meta.clone()
+        };
+        let no_edges = std::collections::HashSet::from([100]);
+        assert_eq!(meta.decode(&no_edges).graph[0][5], (0, 0));
+        assert_eq!(four_doors.decode(&no_edges).graph[0][5], (0, 5));
+
+        // Explorations through door 3 were not made with two doors.
+        let two_doors = CnfMeta {
+            doors: 2,
+            ..meta.clone()
+        };
+        two_doors.write(&meta_path).unwrap();
+        assert!(extract_from_model_file(&meta_path, &model_path).is_err());
+
+        let stale = CnfMeta {
+            version: CNF_META_VERSION + 1,
+            ..meta
+        };
+        stale.write(&meta_path).unwrap();
+        assert!(extract_from_model_file(&meta_path, &model_path).is_err());
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn incremental_solver_matches_rounds() {
+        let mut judge = crate::judge::LocalJudge::new("random", 6, 5);
+        let mut rng = StdRng::seed_from_u64(6);
+        let mut explore = |len: usize| {
+            let plans = vec![(0..len).map(|_| rng.random_range(0..6)).collect_vec()];
+            let steps = plans
+                .iter()
+                .map(|p| p.iter().map(|&d| (None, d)).collect_vec())
+                .collect_vec();
+            let labels = crate::judge::Judge::explore(&mut judge, &steps);
+            (plans, labels)
+        };
+        let (plans, labels) = explore(12);
+        let mut solver = IncrementalSolver::new(6, &plans, &labels, LabelMode::Modulo);
+        assert!(solver.solve().is_some());
+        for _ in 0..3 {
+            let (plans, labels) = explore(36);
+            solver.add_explores(&plans, &labels);
+            // `solve` itself checks the guess against every round so far.
+            assert!(solver.solve().is_some());
+        }
+        // Assumptions only hold for one call.
+        assert!(solver.solve_assuming(&[(0, 0, 1), (0, 0, 2)]).is_none());
+        let guess = solver.solve().unwrap();
+        assert!(judge.check_guess(&guess).is_ok());
+    }
+
+    #[test]
+    fn amo_encodings_choose_exactly_one() {
+        for amo in [
+            AmoEncoding::Auto,
+            AmoEncoding::Pairwise,
+            AmoEncoding::Sequential,
+            AmoEncoding::Commander,
+            AmoEncoding::Totalizer,
+        ] {
+            assert_eq!(amo.as_str().parse::<AmoEncoding>().unwrap(), amo);
+            for len in 1..=9 {
+                let mut cnf = Cnf::with_encoding(EncodingConfig {
+                    amo,
+                    ..EncodingConfig::default()
+                });
+                let xs = (0..len).map(|_| cnf.var()).collect_vec();
+                cnf.choose_one(&xs);
+                for mask in 0u32..1 << len {
+                    let assumptions = xs
+                        .iter()
+                        .enumerate()
+                        .map(|(i, &x)| if mask >> i & 1 == 1 { x } else { -x });
+                    assert_eq!(
+                        cnf.sat.solve_with(assumptions),
+                        Some(mask.count_ones() == 1),
+                        "{:?} over {} literals, assignment {:b}",
+                        amo,
+                        len,
+                        mask
+                    );
+                }
+                let stats = cnf.encoding_stats();
+                assert_eq!(stats.values().map(|st| st.constraints).sum::<usize>(), 1);
+                assert_eq!(
+                    stats.values().map(|st| st.clauses).sum::<usize>(),
+                    cnf.num_clauses()
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn pairing_layer_uses_the_configured_amo_encoding() {
+        let mut judge = crate::judge::LocalJudge::new("random", 4, 2);
+        let plans = vec![vec![0, 1, 2, 3, 4, 5, 0, 1]];
+        let steps = plans
+            .iter()
+            .map(|p| p.iter().map(|&d| (None, d)).collect_vec())
+            .collect_vec();
+        let labels = crate::judge::Judge::explore(&mut judge, &steps);
+        let info = build_info(4, &plans, &labels, Rules::default());
+        let buckets = build_buckets(&info, false);
+        let mut cnf = Cnf::with_encoding(EncodingConfig {
+            amo: AmoEncoding::Sequential,
+            ..EncodingConfig::default()
+        });
+        let cand = build_candidates(&mut cnf, &info, &buckets);
+        let before = cnf
+            .encoding_stats()
+            .get(&AmoEncoding::Sequential)
+            .map_or(0, |st| st.constraints);
+        build_edge_vars(&mut cnf, &info, &cand);
+        let stats = cnf.encoding_stats();
+        assert_eq!(stats.keys().collect_vec(), [&AmoEncoding::Sequential]);
+        // One row and one column constraint per (u, v, door) of the pairing layer.
+        assert!(stats[&AmoEncoding::Sequential].constraints - before >= 2 * 4 * 4 * 6);
+    }
+
+    #[test]
+    fn explain_unsat_names_conflicting_groups() {
+        let mut cnf = Cnf::new();
+        let (x, y) = (cnf.var(), cnf.var());
+        cnf.in_group(ClauseGroup::Sbp, |cnf| cnf.clause([y]));
+        cnf.in_group(ClauseGroup::Plan, |cnf| cnf.clause([x, y]));
+        cnf.in_group(ClauseGroup::Diff, |cnf| cnf.clause([-x]));
+        assert_eq!(cnf.explain_unsat(), None);
+        cnf.in_group(ClauseGroup::Edge, |cnf| cnf.clause([-y]));
+        cnf.clause([x, -y]);
+        assert_eq!(
+            cnf.explain_unsat(),
+            Some(vec![ClauseGroup::Sbp, ClauseGroup::Edge])
+        );
+        assert_eq!(cnf.group_sizes()[&ClauseGroup::Other], 1);
+
+        // A consistent session has no conflict in the full encoding.
+        let mut judge = crate::judge::LocalJudge::new("random", 6, 3);
+        let plans = vec![crate::routes::long_plan(6)];
+        let steps = plans
+            .iter()
+            .map(|p| p.iter().map(|&d| (None, d)).collect_vec())
+            .collect_vec();
+        let labels = crate::judge::Judge::explore(&mut judge, &steps);
+        let (_, _, cnf, _, _) =
+            build_cnf_for_plans(6, &plans, &labels, LabelMode::Auto, Rules::default());
+        assert!(cnf.group_sizes().contains_key(&ClauseGroup::Plan));
+        assert_eq!(cnf.explain_unsat(), None);
+    }
+
+    #[test]
+    fn parallel_construction_is_deterministic() {
+        let mut judge = crate::judge::LocalJudge::new("random", 12, 5);
+        let mut rng = StdRng::seed_from_u64(6);
+        let plans = vec![(0..216).map(|_| rng.random_range(0..6)).collect_vec()];
+        let steps = plans
+            .iter()
+            .map(|p| p.iter().map(|&d| (None, d)).collect_vec())
+            .collect_vec();
+        let labels = crate::judge::Judge::explore(&mut judge, &steps);
+        let build = |threads: usize| {
+            rayon::ThreadPoolBuilder::new()
+                .num_threads(threads)
+                .build()
+                .unwrap()
+                .install(|| {
+                    build_cnf_for_plans(12, &plans, &labels, LabelMode::Auto, Rules::default()).2
+                })
+        };
+        let (a, b) = (build(1), build(4));
+        assert_eq!(a.num_vars(), b.num_vars());
+        assert_eq!(a.clauses, b.clauses);
+    }
+
+    #[test]
+    fn preprocessed_model_completes_original_cnf() {
+        let mut judge = crate::judge::LocalJudge::new("random", 6, 3);
+        let mut rng = StdRng::seed_from_u64(4);
+        let plans = vec![(0..108).map(|_| rng.random_range(0..6)).collect_vec()];
+        let steps = plans
+            .iter()
+            .map(|p| p.iter().map(|&d| (None, d)).collect_vec())
+            .collect_vec();
+        let labels = crate::judge::Judge::explore(&mut judge, &steps);
+
+        let (info, buckets, mut cnf, cand, edges) =
+            build_cnf_for_plans(6, &plans, &labels, LabelMode::Auto, Rules::default());
+        let pre = crate::cnf_preprocess::preprocess(
+            cnf.id.cnt as usize,
+            &cnf.clauses,
+            &crate::cnf_preprocess::Options::default(),
+        );
+        assert!(pre.clauses.len() < cnf.clauses.len());
+
+        // Stand-in for an external solver run on the simplified formula.
+        let mut sat: cadical::Solver = Default::default();
+        for c in &pre.clauses {
+            sat.add_clause(c.iter().copied());
+        }
+        assert_eq!(sat.solve(), Some(true));
+        let model = (1..=cnf.id.cnt)
+            .map(|v| if sat.value(v) == Some(true) { v } else { -v })
+            .collect();
+        for v in pre.restrict_model(&model) {
+            cnf.clause([v]);
+        }
+        assert_eq!(cnf.sat.solve(), Some(true));
+        let guess = extract_guess(&cnf, &info, &buckets, &cand, &edges);
+        assert!(check_explore(&guess, &plans, &labels));
+    }
+
+    #[test]
+    fn four_door_maps_solve() {
+        let rules = Rules {
+            doors: 4,
+            ..Rules::default()
+        };
+        let mut judge = crate::judge::LocalJudge::new_with_rules("random", 6, 7, rules);
+        assert!(
+            judge
+                .graph
+                .iter()
+                .enumerate()
+                .all(|(u, g)| g[4] == u && g[5] == u)
+        );
+        let mut rng = StdRng::seed_from_u64(8);
+        let explored = crate::judge::Judge::explored(&judge);
+        let plans = vec![
+            (0..108).map(|_| rng.random_range(0..4)).collect_vec(),
+            plan_next(&explored, 108, rules, &mut rng),
+        ];
+        assert!(plans[1].iter().all(|&d| d < 4));
+        let steps = plans
+            .iter()
+            .map(|p| p.iter().map(|&d| (None, d)).collect_vec())
+            .collect_vec();
+        let labels = crate::judge::Judge::explore(&mut judge, &steps);
+
+        // The encoding is built on rayon workers, which must see the same rules.
+        let (info, buckets, mut cnf, cand, edges) = rayon::ThreadPoolBuilder::new()
+            .num_threads(2)
+            .build()
+            .unwrap()
+            .install(|| build_cnf_for_plans(6, &plans, &labels, LabelMode::Modulo, rules));
+        assert_eq!(cnf.sat.solve(), Some(true));
+        let guess = extract_guess(&cnf, &info, &buckets, &cand, &edges);
+        assert!((0..6).all(|u| guess.graph[u][4] == (u, 4) && guess.graph[u][5] == (u, 5)));
+        assert!(judge.check_guess(&guess).is_ok());
+        let guess = solve_with_rules(6, &plans, &labels, LabelMode::Modulo, rules);
+        assert!(judge.check_guess(&guess).is_ok());
+    }
+
+    #[test]
+    fn other_label_alphabets_solve() {
+        for (k, mode) in [
+            (2, LabelMode::Modulo),
+            (8, LabelMode::Modulo),
+            (8, LabelMode::Free),
+        ] {
+            let rules = Rules {
+                labels: k,
+                ..Rules::default()
+            };
+            let mut judge = crate::judge::LocalJudge::new_with_rules("random", 8, 3, rules);
+            let mut rng = StdRng::seed_from_u64(5);
+            let plans = (0..4)
+                .map(|_| (0..144).map(|_| rng.random_range(0..6)).collect_vec())
+                .collect_vec();
+            let steps = plans
+                .iter()
+                .map(|p| p.iter().map(|&d| (None, d)).collect_vec())
+                .collect_vec();
+            let labels = crate::judge::Judge::explore(&mut judge, &steps);
+            assert!(labels.iter().flatten().all(|&l| l < k));
+            let guess = solve_with_rules(8, &plans, &labels, mode, rules);
+            assert!(guess.rooms.iter().all(|&l| l < k));
+            assert!(check_explore(&guess, &plans, &labels));
+            if k == 8 {
+                // Every room has its own label, so the walks pin the map down.
+                assert!(judge.check_guess(&guess).is_ok(), "{:?}", mode);
+            }
+        }
+    }
+
+    #[test]
+    fn per_plan_starts_recover_the_map() {
+        let rules = Rules {
+            start_policy: StartPolicy::PerPlanUnknown,
+            ..Rules::default()
+        };
+        let mut judge = crate::judge::LocalJudge::new_with_rules("random", 6, 4, rules);
+        let mut rng = StdRng::seed_from_u64(9);
+        let mut explore = |count: usize| {
+            let plans = (0..count)
+                .map(|_| (0..108).map(|_| rng.random_range(0..6)).collect_vec())
+                .collect_vec();
+            let steps = plans
+                .iter()
+                .map(|p| p.iter().map(|&d| (None, d)).collect_vec())
+                .collect_vec();
+            let labels = crate::judge::Judge::explore(&mut judge, &steps);
+            (plans, labels)
+        };
+        let (plans, labels) = explore(4);
+        let (more_plans, more_labels) = explore(2);
+
+        let guess = solve_with_rules(6, &plans, &labels, LabelMode::Modulo, rules);
+        assert!(check_explore_with_policy(
+            &guess,
+            &plans,
+            &labels,
+            StartPolicy::PerPlanUnknown
+        ));
+        assert!(judge.check_guess(&guess).is_ok());
+
+        let mut solver =
+            IncrementalSolver::with_rules(6, &plans, &labels, LabelMode::Modulo, rules);
+        solver.add_explores(&more_plans, &more_labels);
+        let guess = solver.solve().unwrap();
+        assert!(judge.check_guess(&guess).is_ok());
+    }
+
+    #[test]
+    fn hybrid_fixes_annealed_edges() {
+        let mut judge = crate::judge::LocalJudge::new("random", 6, 1);
+        let mut rng = StdRng::seed_from_u64(2);
+        let plans = (0..2)
+            .map(|_| (0..108).map(|_| rng.random_range(0..6)).collect_vec())
+            .collect_vec();
+        let steps = plans
+            .iter()
+            .map(|p| p.iter().map(|&d| (None, d)).collect_vec())
+            .collect_vec();
+        let labels = crate::judge::Judge::explore(&mut judge, &steps);
+        let params = crate::solve_anneal::AnnealParams {
+            iterations: 20_000,
+            ..Default::default()
+        };
+        // Edges of a consistent assignment survive the symmetry breaking.
+        let edges = crate::solve_anneal::confident_edges(6, &plans, &labels, &params, 1);
+        assert!(edges.len() > 20, "{}", edges.len());
+        let prefix = edges.iter().map(|&(u, e, v)| (u, e, v, None)).collect_vec();
+        assert!(solve_with_edge_prefix_fixed(6, &plans, &labels, &prefix).is_some());
+
+        let guess = solve_hybrid(6, &plans, &labels, &params);
+        assert!(check_explore(&guess, &plans, &labels));
+        // An edge leading to the wrong label cannot be fixed; `solve_hybrid` falls back then.
+        let (u, e, v) = edges[0];
+        let wrong = [(u, e, (v + 1) % 6, None)];
+        assert!(solve_with_edge_prefix_fixed(6, &plans, &labels, &wrong).is_none());
+        let guess = solve_with_prefix_or_open(6, &plans, &labels, &wrong);
+        assert_eq!(judge.check_guess(&guess), Ok(()));
+    }
+
+    #[test]
+    fn priors_are_relaxed_only_where_the_data_needs_it() {
+        let mut judge = crate::judge::LocalJudge::new("random", 6, 3);
+        let truth = (0..6)
+            .filter(|&u| judge.graph[u].iter().any(|&v| v == u))
+            .count();
+        assert!(truth > 0);
+        let mut rng = StdRng::seed_from_u64(4);
+        let plans = vec![(0..108).map(|_| rng.random_range(0..6)).collect_vec()];
+        let steps = plans
+            .iter()
+            .map(|p| p.iter().map(|&d| (None, d)).collect_vec())
+            .collect_vec();
+        let labels = crate::judge::Judge::explore(&mut judge, &steps);
+
+        // No self-loops at all is wrong for `truth` rooms.
+        let strict = Priors {
+            max_self_loops: 0,
+            max_violations: truth,
+        };
+        let (guess, violations) =
+            solve_with_priors(6, &plans, &labels, LabelMode::Modulo, &strict).unwrap();
+        assert_eq!(violations, truth);
+        assert!(judge.check_guess(&guess).is_ok());
+        let too_strict = Priors {
+            max_violations: truth - 1,
+            ..strict
+        };
+        assert!(solve_with_priors(6, &plans, &labels, LabelMode::Modulo, &too_strict).is_none());
+
+        let (guess, violations) =
+            solve_with_priors(6, &plans, &labels, LabelMode::Modulo, &Priors::default()).unwrap();
+        assert!(judge.check_guess(&guess).is_ok());
+        assert!(violations <= truth);
+    }
+
+    #[test]
+    fn unsat_core_points_at_corrupted_label() {
+        let mut judge = crate::judge::LocalJudge::new("random", 6, 3);
+        let mut rng = StdRng::seed_from_u64(4);
+        let plans = (0..2)
+            .map(|_| (0..108).map(|_| rng.random_range(0..6)).collect_vec())
+            .collect_vec();
+        let steps = plans
+            .iter()
+            .map(|p| p.iter().map(|&d| (None, d)).collect_vec())
+            .collect_vec();
+        let mut labels = crate::judge::Judge::explore(&mut judge, &steps);
+        assert_eq!(
+            diagnose_unsat(6, &plans, &labels, LabelMode::Modulo, Rules::default()),
+            None
+        );
+
+        labels[1][40] = (labels[1][40] + 1) % 4;
+        let core = diagnose_unsat(6, &plans, &labels, LabelMode::Modulo, Rules::default()).unwrap();
+        // Dropping both transitions around the bad label makes the data consistent.
+        assert!(core.steps.contains(&(1, 39)) || core.steps.contains(&(1, 40)));
+        assert!(core.to_string().contains("plan 1:"));
+
+        // With three rooms label 3 cannot occur at all.
+        let core = diagnose_unsat(3, &plans, &labels, LabelMode::Modulo, Rules::default()).unwrap();
+        assert_eq!(core, UnsatCore::default());
+    }
+
+    #[test]
+    fn cnf_meta_roundtrip() {
+        let dir = std::env::temp_dir().join(format!("cnf_meta_roundtrip_{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let dimacs_path = dir.join("x.cnf");
+        let meta_path = cnf_meta_path(&dimacs_path);
+        assert_eq!(meta_path, dir.join("x.meta.json"));
+        let model_path = dir.join("model.txt");
+
+        // The policy comes from the metadata, not from `UNAGI_START_POLICY`.
+        for start_policy in [StartPolicy::FixedRoom, StartPolicy::PerPlanUnknown] {
+            let rules = Rules {
+                start_policy,
+                ..Rules::default()
+            };
+            let mut judge = crate::judge::LocalJudge::new_with_rules("random", 4, 1, rules);
+            let mut rng = StdRng::seed_from_u64(2);
+            let plans = (0..3)
+                .map(|_| (0..72).map(|_| rng.random_range(0..6)).collect_vec())
+                .collect_vec();
+            let steps = plans
+                .iter()
+                .map(|p| p.iter().map(|&d| (None, d)).collect_vec())
+                .collect_vec();
+            let labels = crate::judge::Judge::explore(&mut judge, &steps);
+
+            let (info, buckets, mut cnf, cand, edges) =
+                build_cnf_for_plans(4, &plans, &labels, LabelMode::Auto, rules);
+            assert_eq!(cnf.sat.solve(), Some(true));
+            let mut expected = extract_guess(&cnf, &info, &buckets, &cand, &edges);
+            connect_guess(&mut expected, &plans, start_policy);
+
+            CnfMeta::new(&plans, &labels, &info, &cand, &edges)
+                .write(&meta_path)
+                .unwrap();
+            let model = (1..=cnf.id.cnt)
+                .map(|v| {
+                    if cnf.sat.value(v) == Some(true) {
+                        v
+                    } else {
+                        -v
+                    }
+                })
+                .join(" ");
+            std::fs::write(&model_path, format!("s SATISFIABLE\nv {} 0\n", model)).unwrap();
+
+            let guess = extract_from_model_file(&meta_path, &model_path).unwrap();
+            assert_eq!(guess, expected, "{:?}", start_policy);
+        }
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn compute_diff_variants_agree() {
+        let mut rng = StdRng::seed_from_u64(1);
+        for _ in 0..5 {
+            // A few plans over a small alphabet so that long equal runs occur.
+            let mut door = vec![];
+            let mut labels = vec![];
+            for _ in 0..3 {
+                let len = rng.random_range(1..150);
+                for _ in 0..len {
+                    door.push(Some(rng.random_range(0..2)));
+                    labels.push(rng.random_range(0..2));
+                }
+                door.push(None);
+                labels.push(rng.random_range(0..2));
+            }
+            let expected = compute_diff_naive(&door, &labels);
+            assert_eq!(compute_diff(&door, &labels), expected);
+            assert_eq!(compute_diff_bucketed(&door, &labels), Some(expected));
+        }
+
+        // A periodic walk makes every same-label scan run to the end: give up instead.
+        let door = (0..300).map(|i| (i < 299).then_some(0)).collect_vec();
+        let labels = (0..300).map(|i| i % 2).collect_vec();
+        assert_eq!(compute_diff_bucketed(&door, &labels), None);
+        assert_eq!(
+            compute_diff(&door, &labels),
+            compute_diff_naive(&door, &labels)
+        );
+    }
+
+    #[test]
+    fn portfolio_stdin_and_unsat_cancellation() {
+        let dir = std::env::temp_dir().join(format!("portfolio_test_{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let dimacs_path = dir.join("x.cnf");
+        std::fs::write(&dimacs_path, "p cnf 2 1\n1 -2 0\n").unwrap();
+        // `sh -c SCRIPT` takes the DIMACS path (if any) as `$0`.
+        let sh = |script: &str, stdin: bool| SATSolver {
+            path: "sh".to_owned(),
+            args: vec!["-c".to_owned(), script.to_owned()],
+            stdin,
+        };
+        let unsat = sh("echo 's UNSATISFIABLE'; exit 20", false);
+        // Only answers if the CNF arrived on stdin.
+        let from_stdin = sh(
+            "grep -q '^p cnf 2 1' && echo 's SATISFIABLE' && echo 'v 1 -2 0' && exit 10; exit 1",
+            true,
+        );
+
+        let result = launch_portfolio(&dimacs_path, &[sh("exit 1", false), from_stdin]);
+        assert_eq!(result, PortfolioResult::Sat([1, -2].into_iter().collect()));
+        // One UNSAT verdict is a proof: the other solvers are cancelled
+        // instead of waited for.
+        let start = std::time::Instant::now();
+        assert_eq!(
+            launch_portfolio(&dimacs_path, &[unsat, sh("exec sleep 60", false)]),
+            PortfolioResult::Unsat
+        );
+        assert!(start.elapsed() < std::time::Duration::from_secs(30));
+        assert_eq!(
+            launch_portfolio(&dimacs_path, &[sh("exit 1", false), sh("exit 1", false)]),
+            PortfolioResult::Unknown
+        );
+        assert!(PortfolioResult::Unsat.into_model().is_err());
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
 }
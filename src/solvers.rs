@@ -0,0 +1,115 @@
+//! Solvers selectable by name.
+//!
+//! Each entry explores the judge as it likes, submits one guess and reports
+//! whether it was correct. The `solve` binary picks one with `SOLVER`, and
+//! `benchmark` runs them side by side, so a solver added here needs no
+//! binary of its own.
+
+use itertools::Itertools;
+use rand::prelude::*;
+
+use crate::judge::{Guess, Judge};
+
+pub type SolverFn = fn(&mut dyn Judge) -> bool;
+
+pub struct Solver {
+    pub name: &'static str,
+    pub description: &'static str,
+    pub run: SolverFn,
+}
+
+pub const SOLVERS: &[Solver] = &[
+    Solver {
+        name: "no-marks",
+        description: "preset plan, solve_no_marks SAT",
+        run: |judge| {
+            let (plan, labels) = explore_preset(judge);
+            let guess = crate::solve_no_marks::solve(judge.num_rooms(), &vec![plan], &labels);
+            judge.guess(&guess)
+        },
+    },
+    Solver {
+        name: "anneal",
+        description: "preset plan, solve_anneal with default parameters",
+        run: |judge| {
+            let (plan, labels) = explore_preset(judge);
+            let params = crate::solve_anneal::AnnealParams::default();
+            let guess = crate::solve_anneal::solve(judge.num_rooms(), &[plan], &labels, &params);
+            submit(judge, guess)
+        },
+    },
+    Solver {
+        name: "marks",
+        description: "one layered plan, solve_marks SAT",
+        run: |judge| {
+            let n = judge.num_rooms();
+            let mut rng = rand_chacha::ChaCha12Rng::seed_from_u64(0);
+            let plans = crate::solve_layered::layered_plans(n, 1, &mut rng);
+            let labels = judge.explore(&plans);
+            let guess = crate::solve_marks::solve(n, &plans, &labels);
+            submit(judge, guess)
+        },
+    },
+    Solver {
+        name: "layered2",
+        description: "solve_layered for maps of 2 copies",
+        run: |judge| {
+            let guess = crate::solve_layered::solve_layered(judge.num_rooms(), 2, judge);
+            submit(judge, guess)
+        },
+    },
+    Solver {
+        name: "layered3",
+        description: "solve_layered for maps of 3 copies",
+        run: |judge| {
+            let guess = crate::solve_layered::solve_layered(judge.num_rooms(), 3, judge);
+            submit(judge, guess)
+        },
+    },
+];
+
+/// The solver registered as `name`.
+pub fn find(name: &str) -> Option<&'static Solver> {
+    SOLVERS.iter().find(|s| s.name == name)
+}
+
+/// One `  name: description` line per solver, for error messages and `--help`.
+pub fn list() -> String {
+    SOLVERS
+        .iter()
+        .map(|s| format!("  {}: {}", s.name, s.description))
+        .join("\n")
+}
+
+/// Explores the preset plan of the size; returns the plan and its labels.
+fn explore_preset(judge: &mut dyn Judge) -> (Vec<usize>, Vec<Vec<usize>>) {
+    let plan = crate::routes::long_plan(judge.num_rooms());
+    let steps = plan.iter().map(|&d| (None, d)).collect_vec();
+    let labels = judge.explore(&[steps]);
+    (plan, labels)
+}
+
+/// Guesses `guess`, or gives up with `false` if the solver found none.
+fn submit(judge: &mut dyn Judge, guess: Option<Guess>) -> bool {
+    match guess {
+        Some(guess) => judge.guess(&guess),
+        None => {
+            eprintln!("no guess found");
+            false
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn names_are_unique_and_found() {
+        assert!(SOLVERS.iter().map(|s| s.name).all_unique());
+        for s in SOLVERS {
+            assert_eq!(find(s.name).unwrap().name, s.name);
+        }
+        assert!(find("no-such-solver").is_none());
+    }
+}
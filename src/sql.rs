@@ -82,6 +82,15 @@ pub fn cell<T: FromValue>(query: &str, params: impl Into<Params>) -> Result<Opti
     }
 }
 
+/// Returns the database server's current time (`CURRENT_TIMESTAMP`).
+///
+/// Use this instead of the local clock when comparing against timestamps the
+/// database wrote, since worker VMs may have skewed clocks.
+pub fn db_now() -> Result<chrono::NaiveDateTime> {
+    cell("SELECT CURRENT_TIMESTAMP", ())?
+        .ok_or_else(|| anyhow::anyhow!("CURRENT_TIMESTAMP returned no row"))
+}
+
 /// Executes a statement that does not return rows (e.g., UPDATE, DELETE, DDL).
 ///
 /// # Returns
@@ -116,6 +125,23 @@ where
     Ok(())
 }
 
+/// Executes several statements in one transaction on a single connection.
+///
+/// Either all statements take effect or, if any of them fails, none does.
+pub fn exec_transaction<'a, P, I>(statements: I) -> Result<()>
+where
+    P: Into<Params>,
+    I: IntoIterator<Item = (&'a str, P)>,
+{
+    let mut conn = CLIENT.get_conn()?;
+    let mut tx = conn.start_transaction(TxOpts::default())?;
+    for (query, params) in statements {
+        tx.exec_drop(query, params)?;
+    }
+    tx.commit()?;
+    Ok(())
+}
+
 /// A wrapper around `mysql::Row` that provides more ergonomic data access methods.
 pub struct Row {
     row: mysql::Row,
@@ -3,13 +3,18 @@
 //! This module provides functionality to generate an SVG visualization of an
 //! Aedificium map structure (`api::Map`). It uses a simple physics-based
 //! force-directed layout engine to position the rooms (nodes) in a visually
-//! appealing way.
+//! appealing way. [`render_with_trace`] additionally numbers the doors and
+//! overlays the walk of an exploration plan, for debugging wrong guesses.
+//! [`render_layered`] draws a map made of copies of a smaller one layer by
+//! layer, from its [`layered::reduce_graph`] quotient.
 
 use crate::api;
+use crate::judge::Step;
+use crate::layered::{self, Perm3};
 use rand::Rng;
 use svg::Document;
 use svg::node::element::path::Data;
-use svg::node::element::{Path, Text};
+use svg::node::element::{Group, Path, Script, Text};
 
 /// Represents a node (a room) in the force-directed layout simulation.
 #[derive(Debug, Clone)]
@@ -168,15 +173,9 @@ impl LayoutEngine {
     }
 }
 
-/// Renders a given `api::Map` into an SVG string.
-///
-/// The process involves:
-/// 1. Creating a `LayoutEngine` to calculate node positions.
-/// 2. Running the simulation to stabilize the layout.
-/// 3. Normalizing and scaling the final positions to fit in a viewbox.
-/// 4. Drawing the passages (connections) as cubic Bezier curves.
-/// 5. Drawing the rooms as colored circles with text labels.
-pub fn render(map: &api::Map) -> String {
+/// Runs the layout for `map` and returns the room centers, scaled to fit a
+/// standard viewbox, together with the room radius.
+fn layout(map: &api::Map) -> (Vec<(f64, f64)>, f64) {
     let n_rooms = map.rooms.len();
     let radius: f64 = 15.0 + 5.0 * (100.0 / n_rooms as f64).sqrt();
 
@@ -224,48 +223,108 @@ pub fn render(map: &api::Map) -> String {
         pos.0 = (pos.0 - min_x) * scale + radius;
         pos.1 = (pos.1 - min_y) * scale + radius;
     }
+    (positions, radius)
+}
+
+/// The point at distance `r` from `center` in the direction of `door`
+/// (doors are spaced 60 degrees apart, door 0 pointing right).
+fn port(center: (f64, f64), r: f64, door: f64) -> (f64, f64) {
+    let angle = door * std::f64::consts::PI / 3.0;
+    (center.0 + r * angle.cos(), center.1 + r * angle.sin())
+}
+
+/// Control points of the cubic Bezier curve drawn for the passage between
+/// `door1` of the room at `p1` and `door2` of the room at `p2`.
+fn passage_curve(
+    p1: (f64, f64),
+    door1: usize,
+    p2: (f64, f64),
+    door2: usize,
+    radius: f64,
+) -> [(f64, f64); 4] {
+    if p1 == p2 && door1 == door2 {
+        // A door leading back to itself: a small loop outside the port.
+        return [
+            port(p1, radius, door1 as f64),
+            port(p1, radius * 2.2, door1 as f64 - 0.4),
+            port(p1, radius * 2.2, door1 as f64 + 0.4),
+            port(p1, radius, door1 as f64),
+        ];
+    }
+    let c1 = port(p1, radius, door1 as f64);
+    let c2 = port(p2, radius, door2 as f64);
+    let dist = if p1 == p2 {
+        3.0 * radius
+    } else {
+        ((p1.0 - p2.0).powi(2) + (p1.1 - p2.1).powi(2)).sqrt()
+    };
+
+    // Use a cubic Bezier curve for a nice arc.
+    let a1x = c1.0 + (c1.0 - p1.0) / radius * dist * 0.4;
+    let a1y = c1.1 + (c1.1 - p1.1) / radius * dist * 0.4;
+    let a2x = c2.0 + (c2.0 - p2.0) / radius * dist * 0.4;
+    let a2y = c2.1 + (c2.1 - p2.1) / radius * dist * 0.4;
+    [c1, (a1x, a1y), (a2x, a2y), c2]
+}
+
+/// The point at parameter `t` on a cubic Bezier curve.
+fn curve_point(c: &[(f64, f64); 4], t: f64) -> (f64, f64) {
+    let s = 1.0 - t;
+    let w = [s * s * s, 3.0 * s * s * t, 3.0 * s * t * t, t * t * t];
+    (
+        (0..4).map(|i| w[i] * c[i].0).sum(),
+        (0..4).map(|i| w[i] * c[i].1).sum(),
+    )
+}
+
+fn curve_data(c: &[(f64, f64); 4]) -> Data {
+    Data::new()
+        .move_to(c[0])
+        .cubic_curve_to((c[1].0, c[1].1, c[2].0, c[2].1, c[3].0, c[3].1))
+}
+
+fn signature_color(signature: usize) -> &'static str {
+    match signature {
+        0 => "#1f77b4",
+        1 => "#ff7f0e",
+        2 => "#2ca02c",
+        _ => "#d62728",
+    }
+}
 
+/// Draws the passages and rooms of `map` and returns the document together
+/// with the bounding box `(min_x, min_y, max_x, max_y)` of the passages.
+fn draw_map(map: &api::Map, positions: &[(f64, f64)], radius: f64) -> (Document, [f64; 4]) {
     let mut document = Document::new();
 
     // Draw connections (passages) as curved paths.
-    let mut min_x = f64::MAX;
-    let mut min_y = f64::MAX;
-    let mut max_x = f64::MIN;
-    let mut max_y = f64::MIN;
+    let mut bbox = [f64::MAX, f64::MAX, f64::MIN, f64::MIN];
     for conn in &map.connections {
         // Only draw each edge once for an undirected graph.
         if conn.from.room >= conn.to.room {
             continue;
         }
-        let p1 = positions[conn.from.room];
-        let p2 = positions[conn.to.room];
-
-        let angle1 = (conn.from.door as f64) * std::f64::consts::PI / 3.0;
-        let c1 = (p1.0 + radius * angle1.cos(), p1.1 + radius * angle1.sin());
-
-        let angle2 = (conn.to.door as f64) * std::f64::consts::PI / 3.0;
-        let c2 = (p2.0 + radius * angle2.cos(), p2.1 + radius * angle2.sin());
-
-        let dist = ((p1.0 - p2.0).powi(2) + (p1.1 - p2.1).powi(2)).sqrt();
-
-        // Use a cubic Bezier curve for a nice arc.
-        let a1x = c1.0 + (c1.0 - p1.0) / radius * dist * 0.4;
-        let a1y = c1.1 + (c1.1 - p1.1) / radius * dist * 0.4;
-        let a2x = c2.0 + (c2.0 - p2.0) / radius * dist * 0.4;
-        let a2y = c2.1 + (c2.1 - p2.1) / radius * dist * 0.4;
-        let data = Data::new()
-            .move_to((c1.0, c1.1))
-            .cubic_curve_to((a1x, a1y, a2x, a2y, c2.0, c2.1));
-        min_x = min_x.min(c1.0).min(c2.0).min(a1x).min(a2x);
-        min_y = min_y.min(c1.1).min(c2.1).min(a1y).min(a2y);
-        max_x = max_x.max(c1.0).max(c2.0).max(a1x).max(a2x);
-        max_y = max_y.max(c1.1).max(c2.1).max(a1y).max(a2y);
+        let curve = passage_curve(
+            positions[conn.from.room],
+            conn.from.door,
+            positions[conn.to.room],
+            conn.to.door,
+            radius,
+        );
+        for &(x, y) in &curve {
+            bbox = [
+                bbox[0].min(x),
+                bbox[1].min(y),
+                bbox[2].max(x),
+                bbox[3].max(y),
+            ];
+        }
 
         let path = Path::new()
             .set("fill", "none")
             .set("stroke", "black")
             .set("stroke-width", 2)
-            .set("d", data)
+            .set("d", curve_data(&curve))
             .set("title", format!("{} <-> {}", conn.from.room, conn.to.room))
             .set("onmouseover", "this.setAttribute('stroke-width', 4)")
             .set("onmouseout", "this.setAttribute('stroke-width', 2)");
@@ -275,18 +334,11 @@ pub fn render(map: &api::Map) -> String {
 
     // Draw rooms as circles.
     for (i, pos) in positions.iter().enumerate() {
-        let color = match map.rooms[i] {
-            0 => "#1f77b4",
-            1 => "#ff7f0e",
-            2 => "#2ca02c",
-            _ => "#d62728",
-        };
-
         let circle = svg::node::element::Circle::new()
             .set("cx", pos.0)
             .set("cy", pos.1)
             .set("r", radius)
-            .set("fill", color)
+            .set("fill", signature_color(map.rooms[i]))
             .set("stroke", "black")
             .set("stroke-width", 2)
             .set("title", format!("Room {}, Signature {}", i, map.rooms[i]));
@@ -300,12 +352,329 @@ pub fn render(map: &api::Map) -> String {
             .set("font-size", "20px");
         document = document.add(text);
     }
-    document = document
+    (document, bbox)
+}
+
+/// Renders a given `api::Map` into an SVG string.
+///
+/// The process involves:
+/// 1. Creating a `LayoutEngine` to calculate node positions.
+/// 2. Running the simulation to stabilize the layout.
+/// 3. Normalizing and scaling the final positions to fit in a viewbox.
+/// 4. Drawing the passages (connections) as cubic Bezier curves.
+/// 5. Drawing the rooms as colored circles with text labels.
+pub fn render(map: &api::Map) -> String {
+    let (positions, radius) = layout(map);
+    let (document, [min_x, min_y, max_x, max_y]) = draw_map(map, &positions, radius);
+    document
         .set("width", max_x - min_x)
         .set("height", max_y - min_y)
-        .set("viewBox", (min_x, min_y, max_x - min_x, max_y - min_y));
+        .set("viewBox", (min_x, min_y, max_x - min_x, max_y - min_y))
+        .to_string()
+}
 
-    document.to_string()
+/// Fill colors of the layers of [`render_layered`].
+const LAYER_COLORS: [&str; 3] = ["#c7e9c0", "#fdd0a2", "#c6dbef"];
+
+/// Stroke colors of passages that change layer, by the permutation of their door.
+fn permutation_color(perm: Perm3) -> &'static str {
+    match perm {
+        Perm3::I => "black",
+        Perm3::X => "#d62728",
+        Perm3::Y => "#2ca02c",
+        Perm3::Z => "#1f77b4",
+        Perm3::P | Perm3::Q => "#9467bd",
+    }
+}
+
+/// Renders the map behind a [`layered::reduce_graph`] result as its layers
+/// side by side, each laid out like the quotient map.
+///
+/// Rooms are filled with the color of their layer (their index within their
+/// class) and outlined with the color of their signature. Passages within a
+/// layer are drawn in black; passages whose door permutes the layers are
+/// dashed and colored by the permutation, so the coset structure shows at a
+/// glance. Unlike [`render`], the output is self-contained (no script), for
+/// static reports.
+pub fn render_layered(reduced: &layered::JsonOut) -> String {
+    let (positions, radius) = layout(&reduced.map);
+    let k = reduced.classes.first().map_or(1, |c| c.len());
+    let layer_width = positions.iter().map(|p| p.0).fold(0.0, f64::max) + 3.0 * radius;
+    let center = |class: usize, layer: usize| {
+        let (x, y) = positions[class];
+        (x + layer as f64 * layer_width, y)
+    };
+
+    let mut document = Document::new();
+    let mut bbox = [f64::MAX, f64::MAX, f64::MIN, f64::MIN];
+    let extend = |bbox: &mut [f64; 4], (x, y): (f64, f64)| {
+        *bbox = [
+            bbox[0].min(x),
+            bbox[1].min(y),
+            bbox[2].max(x),
+            bbox[3].max(y),
+        ];
+    };
+
+    // Every quotient passage lifts to one passage per layer; a passage from a
+    // class to itself may lift to the same passage twice.
+    let mut drawn = std::collections::HashSet::new();
+    for conn in &reduced.map.connections {
+        let (u, du, v, dv) = (conn.from.room, conn.from.door, conn.to.room, conn.to.door);
+        let perm = reduced.permutations[u][du];
+        for j in 0..k {
+            let (a, b) = ((u, j, du), (v, perm.apply(j), dv));
+            if !drawn.insert(a.min(b)) {
+                continue;
+            }
+            let curve = passage_curve(center(a.0, a.1), a.2, center(b.0, b.1), b.2, radius);
+            for &p in &curve {
+                extend(&mut bbox, p);
+            }
+            let mut path = Path::new()
+                .set("fill", "none")
+                .set("stroke-width", 2)
+                .set("d", curve_data(&curve))
+                .set(
+                    "title",
+                    format!(
+                        "{} <-> {} ({:?})",
+                        reduced.classes[u][j], reduced.classes[v][b.1], perm
+                    ),
+                );
+            path = if a.1 == b.1 {
+                path.set("stroke", "black")
+            } else {
+                path.set("stroke", permutation_color(perm))
+                    .set("stroke-dasharray", "8 4")
+            };
+            document = document.add(path);
+        }
+    }
+
+    for (class, rooms) in reduced.classes.iter().enumerate() {
+        let signature = reduced.map.rooms[class];
+        for (layer, &room) in rooms.iter().enumerate() {
+            let (x, y) = center(class, layer);
+            extend(&mut bbox, (x - radius, y - radius));
+            extend(&mut bbox, (x + radius, y + radius));
+            document = document
+                .add(
+                    svg::node::element::Circle::new()
+                        .set("cx", x)
+                        .set("cy", y)
+                        .set("r", radius)
+                        .set("fill", LAYER_COLORS[layer % LAYER_COLORS.len()])
+                        .set("stroke", signature_color(signature))
+                        .set("stroke-width", 4)
+                        .set(
+                            "title",
+                            format!(
+                                "Room {}, class {}, layer {}, Signature {}",
+                                room, class, layer, signature
+                            ),
+                        ),
+                )
+                .add(
+                    Text::new(format!("{}#{}", room, signature))
+                        .set("x", x)
+                        .set("y", y + 7.0)
+                        .set("text-anchor", "middle")
+                        .set("font-size", "20px"),
+                );
+        }
+    }
+
+    // Layer captions above the layers.
+    for layer in 0..k {
+        document = document.add(
+            Text::new(format!("layer {}", layer))
+                .set("x", layer as f64 * layer_width)
+                .set("y", bbox[1] - 10.0)
+                .set("font-size", "20px")
+                .set("font-weight", "bold"),
+        );
+    }
+    bbox[1] -= 40.0;
+
+    let [min_x, min_y, max_x, max_y] = bbox;
+    document
+        .set("width", max_x - min_x)
+        .set("height", max_y - min_y)
+        .set("viewBox", (min_x, min_y, max_x - min_x, max_y - min_y))
+        .to_string()
+}
+
+/// Steps through the `trace-step` groups of the enclosing SVG: ArrowLeft and
+/// ArrowRight hide or show one more step, Home and End show none or all, and
+/// `d` toggles the door numbers.
+const TRACE_SCRIPT: &str = r#"
+(function () {
+  var root = (document.currentScript && document.currentScript.closest('svg')) || document;
+  var steps = root.querySelectorAll('.trace-step');
+  var doors = root.querySelector('.door-labels');
+  var shown = steps.length;
+  function update() {
+    steps.forEach(function (g) {
+      g.style.display = Number(g.getAttribute('data-step')) >= shown ? 'none' : '';
+    });
+  }
+  document.addEventListener('keydown', function (e) {
+    if (e.key === 'ArrowRight') shown = Math.min(shown + 1, steps.length);
+    else if (e.key === 'ArrowLeft') shown = Math.max(shown - 1, 0);
+    else if (e.key === 'Home') shown = 0;
+    else if (e.key === 'End') shown = steps.length;
+    else if (e.key === 'd') {
+      doors.style.display = doors.style.display === 'none' ? '' : 'none';
+      return;
+    } else return;
+    update();
+  });
+})();
+"#;
+
+/// Renders `map` like [`render`], adds the door number next to every port and
+/// overlays the walk of `plan` from the starting room.
+///
+/// Each step is a `<g class="trace-step" data-step="i">` holding the passage
+/// taken, highlighted and numbered with `i`; an embedded script steps through
+/// them with the arrow keys and `d` toggles the door numbers. `labels` are the labels
+/// observed for the plan, one per room visited as returned by
+/// [`crate::judge::Judge::explore`]; rooms where they disagree with the map
+/// (after applying the plan's `[k]` rewrites) are circled in red, which is
+/// usually where a wrong guess and the judge part ways. Pass an empty slice to
+/// draw the walk alone. The walk stops early at a door the map leaves open.
+pub fn render_with_trace(map: &api::Map, plan: &[Step], labels: &[usize]) -> String {
+    let (positions, radius) = layout(map);
+    let (mut document, mut bbox) = draw_map(map, &positions, radius);
+    for pos in &positions {
+        bbox = [
+            bbox[0].min(pos.0 - 1.6 * radius),
+            bbox[1].min(pos.1 - 1.6 * radius),
+            bbox[2].max(pos.0 + 1.6 * radius),
+            bbox[3].max(pos.1 + 1.6 * radius),
+        ];
+    }
+
+    let mut door_to = vec![[None; 6]; map.rooms.len()];
+    for conn in &map.connections {
+        door_to[conn.from.room][conn.from.door] = Some((conn.to.room, conn.to.door));
+        door_to[conn.to.room][conn.to.door] = Some((conn.from.room, conn.from.door));
+    }
+
+    let mut door_labels = Group::new().set("class", "door-labels");
+    for pos in &positions {
+        for door in 0..6 {
+            let (x, y) = port(*pos, radius * 1.35, door as f64);
+            door_labels = door_labels.add(
+                Text::new(door.to_string())
+                    .set("x", x)
+                    .set("y", y + 4.0)
+                    .set("text-anchor", "middle")
+                    .set("font-size", "11px")
+                    .set("fill", "#555"),
+            );
+        }
+    }
+    document = document.add(door_labels);
+
+    // Walk the plan first so repeated passages can spread their step numbers.
+    let mut current = map.rooms.clone();
+    let mut room = map.starting_room;
+    let mut walk = vec![];
+    for (i, &(rewrite, door)) in plan.iter().enumerate() {
+        if let Some(k) = rewrite {
+            current[room] = k;
+        }
+        let Some((next, next_door)) = door_to[room][door] else {
+            break;
+        };
+        walk.push((i, room, door, next, next_door, current[next]));
+        room = next;
+    }
+    let passage = |u: usize, d: usize, v: usize, e: usize| (u, d).min((v, e));
+    let mut traversals = std::collections::HashMap::<_, usize>::new();
+    for &(_, u, d, v, e, _) in &walk {
+        *traversals.entry(passage(u, d, v, e)).or_default() += 1;
+    }
+
+    let mismatch = |room: usize, expected: usize, observed: Option<&usize>, title: String| {
+        observed.filter(|&&o| o != expected).map(|&o| {
+            svg::node::element::Circle::new()
+                .set("cx", positions[room].0)
+                .set("cy", positions[room].1)
+                .set("r", radius * 1.15)
+                .set("fill", "none")
+                .set("stroke", "red")
+                .set("stroke-width", 4)
+                .set(
+                    "title",
+                    format!("{}: expected {}, observed {}", title, expected, o),
+                )
+        })
+    };
+    let mut trace = Group::new().set("class", "trace").add(
+        svg::node::element::Circle::new()
+            .set("cx", positions[map.starting_room].0)
+            .set("cy", positions[map.starting_room].1)
+            .set("r", radius * 1.15)
+            .set("fill", "none")
+            .set("stroke", "#9467bd")
+            .set("stroke-width", 3)
+            .set("stroke-dasharray", "6 4")
+            .set("title", "start"),
+    );
+    if let Some(ring) = mismatch(
+        map.starting_room,
+        map.rooms[map.starting_room],
+        labels.first(),
+        "start".to_string(),
+    ) {
+        trace = trace.add(ring);
+    }
+    let mut seen = std::collections::HashMap::<_, usize>::new();
+    for &(i, u, d, v, e, expected) in &walk {
+        let key = passage(u, d, v, e);
+        let nth = seen.entry(key).or_default();
+        *nth += 1;
+        let curve = passage_curve(positions[u], d, positions[v], e, radius);
+        let (x, y) = curve_point(&curve, *nth as f64 / (traversals[&key] + 1) as f64);
+        let wrong = labels.get(i + 1).is_some_and(|&o| o != expected);
+        let mut step = Group::new()
+            .set("class", "trace-step")
+            .set("data-step", i)
+            .add(
+                Path::new()
+                    .set("fill", "none")
+                    .set("stroke", "#9467bd")
+                    .set("stroke-opacity", 0.6)
+                    .set("stroke-width", 5)
+                    .set("d", curve_data(&curve))
+                    .set("title", format!("step {}: {}.{} -> {}.{}", i, u, d, v, e)),
+            )
+            .add(
+                Text::new(i.to_string())
+                    .set("x", x)
+                    .set("y", y + 5.0)
+                    .set("text-anchor", "middle")
+                    .set("font-size", "14px")
+                    .set("font-weight", "bold")
+                    .set("fill", if wrong { "red" } else { "#9467bd" }),
+            );
+        if let Some(ring) = mismatch(v, expected, labels.get(i + 1), format!("step {}", i)) {
+            step = step.add(ring);
+        }
+        trace = trace.add(step);
+    }
+
+    let [min_x, min_y, max_x, max_y] = bbox;
+    document
+        .add(trace)
+        .add(Script::new(TRACE_SCRIPT))
+        .set("width", max_x - min_x)
+        .set("height", max_y - min_y)
+        .set("viewBox", (min_x, min_y, max_x - min_x, max_y - min_y))
+        .to_string()
 }
 
 #[cfg(test)]
@@ -330,6 +699,58 @@ mod tests {
         assert!(svg_str.contains("Room 1, Signature 1"));
     }
 
+    #[test]
+    fn test_svg_render_with_trace() {
+        // Room 0 door 0 <-> room 1 door 1, and door 2 of room 1 loops back to itself.
+        let map = api::Map {
+            rooms: vec![0, 1],
+            starting_room: 0,
+            connections: vec![
+                api::MapConnection {
+                    from: api::MapConnectionEnd { room: 0, door: 0 },
+                    to: api::MapConnectionEnd { room: 1, door: 1 },
+                },
+                api::MapConnection {
+                    from: api::MapConnectionEnd { room: 1, door: 2 },
+                    to: api::MapConnectionEnd { room: 1, door: 2 },
+                },
+            ],
+        };
+        let plan = crate::judge::parse_plan("02[3]21");
+        let svg_str = svg::render_with_trace(&map, &plan, &[0, 1, 1, 3, 0]);
+        for i in 0..4 {
+            assert!(svg_str.contains(&format!(r#"data-step="{}""#, i)));
+        }
+        assert!(svg_str.contains("step 2: 1.2 -&gt; 1.2"));
+        assert!(svg_str.contains("step 3: 1.1 -&gt; 0.0"));
+        assert!(svg_str.contains("<script>"));
+        assert!(!svg_str.contains("expected"));
+        // Room 0 is observed as 2 at the end of the walk.
+        let svg_str = svg::render_with_trace(&map, &plan, &[0, 1, 1, 3, 2]);
+        assert!(svg_str.contains("step 3: expected 0, observed 2"));
+        // The walk stops at a door the map leaves open.
+        let svg_str = svg::render_with_trace(&map, &crate::judge::parse_plan("03"), &[]);
+        assert!(svg_str.contains(r#"data-step="0""#));
+        assert!(!svg_str.contains(r#"data-step="1""#));
+    }
+
+    #[test]
+    fn test_svg_render_layered() {
+        // Two rooms with the same label swapped by door 0: one class, two layers.
+        let mut graph = vec![[0; 6], [1; 6]];
+        graph[0][0] = 1;
+        graph[1][0] = 0;
+        let reduced = crate::layered::reduce_graph_without_to_door(0, vec![0, 0], graph).unwrap();
+        let svg_str = svg::render_layered(&reduced);
+        assert!(svg_str.contains("Room 0, class 0, layer 0, Signature 0"));
+        assert!(svg_str.contains("Room 1, class 0, layer 1, Signature 0"));
+        assert!(svg_str.contains("layer 1</text>"));
+        // Door 0 crosses the layers; the five self-loops stay in their layer.
+        assert_eq!(svg_str.matches("stroke-dasharray").count(), 1);
+        assert!(svg_str.contains("0 &lt;-&gt; 1 (X)"));
+        assert_eq!(svg_str.matches("<path").count(), 11);
+    }
+
     #[test]
     fn test_svg_render_single_room() {
         let map = api::Map {
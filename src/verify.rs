@@ -0,0 +1,52 @@
+//! # Uniqueness Checks
+//!
+//! A wrong guess costs a whole session, so before guessing a solver can ask
+//! whether its explorations leave any room for doubt: [`is_uniquely_determined`]
+//! looks for a second map that explains the same observations but that the
+//! judge would tell apart from the first (they are not isomorphic from the
+//! starting room). If there is one, exploring more is cheaper than guessing.
+
+use crate::solve_no_marks::enumerate_solutions;
+
+/// Whether exactly one map, up to renumbering the rooms, explains the
+/// explorations (`labels[i]` observed along `plans[i]`).
+///
+/// One model is found, blocked, and the SAT solver asked for another; models
+/// isomorphic to the first are blocked in turn (see
+/// [`enumerate_solutions`]). Observations no map explains are not uniquely
+/// determined either.
+pub fn is_uniquely_determined(
+    plans: &Vec<Vec<usize>>,
+    labels: &Vec<Vec<usize>>,
+    num_rooms: usize,
+) -> bool {
+    enumerate_solutions(num_rooms, plans, labels, 2).len() == 1
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::judge::{Judge, LocalJudge};
+    use crate::routes::planner::{PlanStrategy, RandomBalanced};
+    use rand::prelude::*;
+
+    #[test]
+    fn longer_explorations_determine_the_map() {
+        let n = 5;
+        let explore = |plan: &[usize]| {
+            let steps = plan.iter().map(|&d| (None, d)).collect::<Vec<_>>();
+            LocalJudge::new("random", n, 11).explore(&[steps])
+        };
+        let short = vec![vec![0, 1, 2, 3]];
+        assert!(!is_uniquely_determined(&short, &explore(&short[0]), n));
+        let len = crate::routes::long_plan_len(n);
+        let long = vec![RandomBalanced.plan(n, len, &mut StdRng::seed_from_u64(5))];
+        assert!(is_uniquely_determined(&long, &explore(&long[0]), n));
+        // Labels no map with a single room can produce.
+        assert!(!is_uniquely_determined(
+            &vec![vec![0]],
+            &vec![vec![0, 1]],
+            1
+        ));
+    }
+}
@@ -0,0 +1,292 @@
+//! # Wrong-Guess Analysis
+//!
+//! A `/guess` that comes back WA only tells us "wrong". This module re-checks
+//! the submitted guess against what we know client-side and classifies the
+//! most likely cause:
+//!
+//! - `serialization-bug`: the guess does not survive conversion to the
+//!   submitted `api::Map` (invalid values, doors used twice or not at all, or
+//!   the round-tripped map no longer explains the data while the guess does).
+//! - `disconnected`: some rooms of the guess are unreachable from the start.
+//! - `inconsistent-with-data`: the guess contradicts an explored route, i.e. the
+//!   solver (or the way its model was decoded) is wrong.
+//! - `ambiguous-data`: the guess explains every observation, so the
+//!   explorations did not pin the map down.
+//!
+//! `RemoteJudge::guess` prints the result as `!log wa_reason ...` lines and
+//! stores it in the metadata of the `/guess` row in `api_logs`.
+
+use crate::api;
+use crate::judge::{self, Explored, Guess, Step};
+
+/// Most likely cause of a wrong answer, in the order the checks run.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum WaReason {
+    SerializationBug,
+    Disconnected,
+    InconsistentWithData,
+    AmbiguousData,
+}
+
+impl WaReason {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            WaReason::SerializationBug => "serialization-bug",
+            WaReason::Disconnected => "disconnected",
+            WaReason::InconsistentWithData => "inconsistent-with-data",
+            WaReason::AmbiguousData => "ambiguous-data",
+        }
+    }
+}
+
+#[derive(Clone, Debug)]
+pub struct WaAnalysis {
+    pub reason: WaReason,
+    /// Human-readable details supporting the classification.
+    pub hints: Vec<String>,
+}
+
+impl WaAnalysis {
+    pub fn to_json(&self) -> serde_json::Value {
+        serde_json::json!({
+            "reason": self.reason.as_str(),
+            "hints": self.hints,
+        })
+    }
+}
+
+/// First `(plan, step)` where simulating the guess disagrees with the recorded
+/// labels; `step` indexes the result sequence.
+fn first_mismatch(
+    guess: &Guess,
+    plans: &[Vec<Step>],
+    results: &[Vec<usize>],
+) -> Option<(usize, usize)> {
+    for (id, (plan, result)) in plans.iter().zip(results).enumerate() {
+        let mut labels = guess.rooms.clone();
+        let mut u = guess.start;
+        if result.first() != Some(&labels[u]) {
+            return Some((id, 0));
+        }
+        for (i, &(new_label, door)) in plan.iter().enumerate() {
+            if let Some(new_label) = new_label {
+                labels[u] = new_label;
+            }
+            u = guess.graph[u][door].0;
+            if result.get(i + 1) != Some(&labels[u]) {
+                return Some((id, i + 1));
+            }
+        }
+    }
+    None
+}
+
+/// Rooms unreachable from the start of the guess.
+fn unreachable_rooms(guess: &Guess) -> Vec<usize> {
+    let n = guess.rooms.len();
+    let mut seen = vec![false; n];
+    let mut stack = vec![guess.start];
+    seen[guess.start] = true;
+    while let Some(u) = stack.pop() {
+        for &(v, _) in &guess.graph[u] {
+            if !seen[v] {
+                seen[v] = true;
+                stack.push(v);
+            }
+        }
+    }
+    (0..n).filter(|&u| !seen[u]).collect()
+}
+
+/// Problems of the map that would actually be submitted for `guess`.
+fn serialization_problems(guess: &Guess, explored: &Explored) -> Vec<String> {
+    let map = match api::Map::try_from(guess) {
+        Ok(map) => map,
+        Err(e) => return vec![format!("guess cannot be converted to a map: {}", e)],
+    };
+    if let Err(e) = api::validate_map(&map) {
+        return vec![format!("submitted map is invalid: {}", e)];
+    }
+    let n = map.rooms.len();
+    let mut uses = vec![[0usize; 6]; n];
+    for c in &map.connections {
+        uses[c.from.room][c.from.door] += 1;
+        if (c.from.room, c.from.door) != (c.to.room, c.to.door) {
+            uses[c.to.room][c.to.door] += 1;
+        }
+    }
+    let mut problems = vec![];
+    for (u, doors) in uses.iter().enumerate() {
+        for (d, &k) in doors.iter().enumerate() {
+            if k != 1 {
+                problems.push(format!(
+                    "door {} of room {} appears in {} connections",
+                    d, u, k
+                ));
+            }
+        }
+    }
+    if problems.is_empty()
+        && first_mismatch(guess, &explored.plans, &explored.results).is_none()
+        && let Some((plan, step)) =
+            first_mismatch(&Guess::from(&map), &explored.plans, &explored.results)
+    {
+        problems.push(format!(
+            "guess explains the data but the submitted map does not (plan #{}, step {})",
+            plan, step
+        ));
+    }
+    problems
+}
+
+/// Classifies a guess the judge rejected. `num_rooms` is the problem size and
+/// `explored` everything observed in the session.
+pub fn analyze_wrong_guess(guess: &Guess, num_rooms: usize, explored: &Explored) -> WaAnalysis {
+    let mut hints = vec![];
+    if guess.rooms.len() != num_rooms {
+        hints.push(format!(
+            "guess has {} rooms but the problem has {}",
+            guess.rooms.len(),
+            num_rooms
+        ));
+    }
+    let counts = judge::label_histogram(&explored.plans, &explored.results, judge::num_labels());
+    hints.extend(
+        judge::label_histogram_warnings(num_rooms, &counts)
+            .into_iter()
+            .map(|w| format!("label histogram: {}", w)),
+    );
+
+    let problems = serialization_problems(guess, explored);
+    if !problems.is_empty() {
+        hints.extend(problems);
+        return WaAnalysis {
+            reason: WaReason::SerializationBug,
+            hints,
+        };
+    }
+    let unreachable = unreachable_rooms(guess);
+    if !unreachable.is_empty() {
+        hints.push(format!(
+            "rooms unreachable from the start: {:?}",
+            unreachable
+        ));
+        return WaAnalysis {
+            reason: WaReason::Disconnected,
+            hints,
+        };
+    }
+    if let Some((plan, step)) = first_mismatch(guess, &explored.plans, &explored.results) {
+        hints.push(format!(
+            "plan #{} diverges from the recorded labels at step {}",
+            plan, step
+        ));
+        return WaAnalysis {
+            reason: WaReason::InconsistentWithData,
+            hints,
+        };
+    }
+    // Consistent with everything: report how much of the guess the data covers.
+    let mut traversed = vec![[false; 6]; guess.rooms.len()];
+    for plan in &explored.plans {
+        let mut u = guess.start;
+        for &(_, door) in plan {
+            traversed[u][door] = true;
+            u = guess.graph[u][door].0;
+        }
+    }
+    let missing = traversed.iter().flatten().filter(|&&t| !t).count();
+    hints.push(format!(
+        "guess explains all {} plans; {} of {} doors never traversed",
+        explored.plans.len(),
+        missing,
+        6 * guess.rooms.len()
+    ));
+    WaAnalysis {
+        reason: WaReason::AmbiguousData,
+        hints,
+    }
+}
+
+/// Adds the analysis to the metadata of the `/guess` row `log_id` in `api_logs`.
+#[cfg(feature = "mysql")]
+pub fn record(log_id: u64, analysis: &WaAnalysis) -> anyhow::Result<()> {
+    use mysql::params;
+    let meta: Option<String> = crate::sql::cell(
+        "SELECT api_log_metadata FROM api_logs WHERE api_log_id = :id",
+        params! { "id" => log_id },
+    )?;
+    let mut meta = meta
+        .and_then(|m| serde_json::from_str::<serde_json::Value>(&m).ok())
+        .filter(|m| m.is_object())
+        .unwrap_or_else(|| serde_json::json!({}));
+    meta["wa_analysis"] = analysis.to_json();
+    crate::sql::exec(
+        "UPDATE api_logs SET api_log_metadata = :meta WHERE api_log_id = :id",
+        params! { "meta" => meta.to_string(), "id" => log_id },
+    )?;
+    Ok(())
+}
+
+#[cfg(not(feature = "mysql"))]
+pub fn record(_log_id: u64, _analysis: &WaAnalysis) -> anyhow::Result<()> {
+    anyhow::bail!("recording requires the mysql feature")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::judge::parse_plan;
+
+    fn walk(guess: &Guess, plan: &[Step]) -> Vec<usize> {
+        let mut u = guess.start;
+        let mut route = vec![guess.rooms[u]];
+        for &(_, door) in plan {
+            u = guess.graph[u][door].0;
+            route.push(guess.rooms[u]);
+        }
+        route
+    }
+
+    #[test]
+    fn classifies_wrong_guesses() {
+        let map = crate::mapgen::graph_file::parse_edge_list(
+            "e 1 2\ne 2 3\ne 3 4\ne 4 1\ne 1 3\ne 2 4\n",
+        )
+        .unwrap();
+        let guess = Guess::from(&map);
+        let plan = parse_plan("0123450011223344");
+        let explored = Explored {
+            results: vec![walk(&guess, &plan)],
+            plans: vec![plan],
+        };
+
+        let a = analyze_wrong_guess(&guess, 4, &explored);
+        assert_eq!(a.reason, WaReason::AmbiguousData, "{:?}", a.hints);
+
+        let mut wrong_label = guess.clone();
+        let u = wrong_label.graph[wrong_label.start][0].0;
+        wrong_label.rooms[u] = (wrong_label.rooms[u] + 1) % 4;
+        let a = analyze_wrong_guess(&wrong_label, 4, &explored);
+        assert_eq!(a.reason, WaReason::InconsistentWithData);
+
+        let mut broken = guess.clone();
+        broken.graph[0][0] = (0, 0);
+        let a = analyze_wrong_guess(&broken, 4, &explored);
+        assert_eq!(a.reason, WaReason::SerializationBug);
+
+        // Two rooms with self-loops only; the data never leaves room 0.
+        let split = Guess {
+            rooms: vec![0, 1],
+            start: 0,
+            graph: (0..2).map(|u| [0, 1, 2, 3, 4, 5].map(|d| (u, d))).collect(),
+        };
+        let explored = Explored {
+            plans: vec![parse_plan("012")],
+            results: vec![vec![0, 0, 0, 0]],
+        };
+        let a = analyze_wrong_guess(&split, 2, &explored);
+        assert_eq!(a.reason, WaReason::Disconnected);
+        assert!(a.to_json()["hints"][0].as_str().unwrap().contains("[1]"));
+    }
+}
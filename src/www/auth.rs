@@ -0,0 +1,136 @@
+//! # Write Access
+//!
+//! The dashboard has no login, but some forms change what the executor fleet
+//! runs (`/agents/save` stores shell code every VM executes, `/agents/sweep`
+//! queues tasks, `/task/requeue` puts a task back in the queue and
+//! `/task/cancel` stops one at its runner's next heartbeat). Those POSTs must
+//! carry:
+//!
+//! - the admin password, `WWW_ADMIN_PASSWORD` (falling back to
+//!   `UNAGI_PASSWORD`); with neither set, every such POST is refused;
+//! - a CSRF token for the form's action, derived from a secret generated at
+//!   server start, and no `Origin` header naming another host.
+
+use actix_web::{HttpRequest, HttpResponse};
+use anyhow::{Result, bail, ensure};
+use once_cell::sync::Lazy;
+use sha1::{Digest, Sha1};
+
+use crate::www::handlers::template;
+
+/// Per-process secret the CSRF tokens are derived from.
+static CSRF_SECRET: Lazy<[u8; 32]> = Lazy::new(rand::random);
+
+/// The password write forms must carry, if one is configured.
+fn admin_password() -> Option<String> {
+    ["WWW_ADMIN_PASSWORD", "UNAGI_PASSWORD"]
+        .iter()
+        .filter_map(|k| std::env::var(k).ok())
+        .find(|v| !v.is_empty())
+}
+
+/// CSRF token of the form posting to `action`.
+pub fn csrf_token(action: &str) -> String {
+    let mut h = Sha1::new();
+    h.update(CSRF_SECRET.as_slice());
+    h.update(action.as_bytes());
+    hex::encode(h.finalize())
+}
+
+/// Hidden CSRF input and password input for a form posting to `action`.
+pub fn form_fields(action: &str) -> String {
+    format!(
+        r#"<input type="hidden" name="csrf" value="{}">パスワード <input type="password" name="password" size="12" autocomplete="current-password">"#,
+        csrf_token(action)
+    )
+}
+
+/// Byte comparison whose time does not depend on where the inputs differ.
+fn constant_time_eq(a: &[u8], b: &[u8]) -> bool {
+    a.len() == b.len() && a.iter().zip(b).fold(0u8, |acc, (x, y)| acc | (x ^ y)) == 0
+}
+
+/// Checks the credentials of a POST to `action`; `expected` is the
+/// configured password.
+fn verify(
+    expected: Option<&str>,
+    action: &str,
+    origin: Option<&str>,
+    host: &str,
+    password: &str,
+    csrf: &str,
+) -> Result<()> {
+    if let Some(origin) = origin {
+        let origin_host = origin.split_once("://").map_or(origin, |(_, h)| h);
+        ensure!(origin_host == host, "cross-origin request from {}", origin);
+    }
+    ensure!(
+        constant_time_eq(csrf.as_bytes(), csrf_token(action).as_bytes()),
+        "invalid CSRF token; reload the form"
+    );
+    let Some(expected) = expected else {
+        bail!("writes are disabled: set WWW_ADMIN_PASSWORD on the server");
+    };
+    ensure!(
+        constant_time_eq(password.as_bytes(), expected.as_bytes()),
+        "wrong password"
+    );
+    Ok(())
+}
+
+/// Checks a POST to `action` carrying `password` and `csrf` (see the module docs).
+pub fn check(req: &HttpRequest, action: &str, password: &str, csrf: &str) -> Result<()> {
+    let header = |name| {
+        req.headers()
+            .get(name)
+            .and_then(|v| v.to_str().ok())
+            .map(str::to_owned)
+    };
+    let host = header("host").unwrap_or_default();
+    verify(
+        admin_password().as_deref(),
+        action,
+        header("origin").as_deref(),
+        &host,
+        password,
+        csrf,
+    )
+}
+
+/// A 403 page for a failed [`check`].
+pub fn forbidden(e: &anyhow::Error) -> HttpResponse {
+    HttpResponse::Forbidden()
+        .content_type("text/html")
+        .body(template::render(&format!(
+            "<h1>拒否</h1><p>{}</p>",
+            template::escape_html(&format!("{:#}", e))
+        )))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn write_posts_need_password_and_token() {
+        let token = csrf_token("/agents/save");
+        let ok = |expected, origin, password: &str, csrf: &str| {
+            verify(
+                expected,
+                "/agents/save",
+                origin,
+                "dash:8080",
+                password,
+                csrf,
+            )
+            .is_ok()
+        };
+        assert!(ok(Some("pw"), None, "pw", &token));
+        assert!(ok(Some("pw"), Some("http://dash:8080"), "pw", &token));
+        assert!(!ok(Some("pw"), Some("https://evil.example"), "pw", &token));
+        assert!(!ok(Some("pw"), None, "pw!", &token));
+        assert!(!ok(Some("pw"), None, "pw", &csrf_token("/agents/sweep")));
+        assert!(!ok(Some("pw"), None, "pw", ""));
+        assert!(!ok(None, None, "", &token));
+    }
+}
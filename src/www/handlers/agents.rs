@@ -0,0 +1,319 @@
+//! # Agents
+//!
+//! `/agents` lists the rows of `agents` that the executor runs and edits them:
+//! a name, a shell script template and an optional `gs://` URL of a binary the
+//! executor downloads into the working directory first. The template may use
+//! the placeholders the executor substitutes (see `executor::run_task`); any
+//! other `{{...}}` is rejected, since it would reach the shell verbatim.
+//!
+//! Each agent also has a sweep button that queues one task per problem.
+//!
+//! Both POSTs let their sender run code on the executor fleet, so they need
+//! the admin password and a CSRF token (see `www::auth`).
+
+use actix_web::{HttpRequest, HttpResponse, Responder, web};
+use anyhow::{Result, bail, ensure};
+use mysql::params;
+use serde::Deserialize;
+
+use crate::executor::queue;
+use crate::www::auth;
+use crate::www::handlers::template;
+use crate::www::handlers::template::escape_html;
+use crate::{problems, sql};
+
+/// Placeholders substituted by the executor.
+pub const PLACEHOLDERS: [&str; 4] = ["problem_name", "problem_variant", "task_id", "agent_name"];
+
+/// Checks that every `{{` opens a known placeholder and that no `}}` is left over.
+pub fn validate_code(code: &str) -> Result<()> {
+    ensure!(!code.trim().is_empty(), "code is empty");
+    let mut rest = code;
+    while let Some(open) = rest.find("{{") {
+        if let Some(close) = rest[..open].find("}}") {
+            bail!("unmatched }} at {:?}", snippet(&rest[close..]));
+        }
+        let after = &rest[open + 2..];
+        let Some(close) = after.find("}}") else {
+            bail!("unclosed {{{{ at {:?}", snippet(&rest[open..]));
+        };
+        let name = &after[..close];
+        ensure!(
+            PLACEHOLDERS.contains(&name),
+            "unknown placeholder {{{{{}}}}} (expected one of {})",
+            name,
+            PLACEHOLDERS.join(", ")
+        );
+        rest = &after[close + 2..];
+    }
+    if let Some(close) = rest.find("}}") {
+        bail!("unmatched }} at {:?}", snippet(&rest[close..]));
+    }
+    Ok(())
+}
+
+/// The first few characters of `s`, for error messages.
+fn snippet(s: &str) -> String {
+    s.chars().take(20).collect()
+}
+
+fn validate_name(name: &str) -> Result<()> {
+    ensure!(
+        !name.is_empty() && name.len() <= 64,
+        "name must be 1 to 64 characters"
+    );
+    ensure!(
+        name.chars()
+            .all(|c| c.is_ascii_alphanumeric() || "_-.".contains(c)),
+        "name may only contain letters, digits, '_', '-' and '.'"
+    );
+    Ok(())
+}
+
+fn validate_bin(bin: &str) -> Result<()> {
+    if bin.is_empty() {
+        return Ok(());
+    }
+    let (_, object) = crate::gcp::gcs::parse_gs_url(bin)?;
+    ensure!(!object.is_empty(), "agent_bin must name an object: {}", bin);
+    Ok(())
+}
+
+#[derive(Deserialize)]
+pub struct AgentForm {
+    /// Empty for a new agent.
+    #[serde(default)]
+    agent_id: String,
+    agent_name: String,
+    agent_code: String,
+    #[serde(default)]
+    agent_bin: String,
+    #[serde(default)]
+    password: String,
+    #[serde(default)]
+    csrf: String,
+}
+
+/// Inserts or updates an agent and returns its id.
+fn save_agent(form: &AgentForm) -> Result<i64> {
+    let name = form.agent_name.trim();
+    let code = form.agent_code.replace('\r', "");
+    let bin = form.agent_bin.trim();
+    validate_name(name)?;
+    validate_code(&code)?;
+    validate_bin(bin)?;
+    let bin = (!bin.is_empty()).then_some(bin);
+    if form.agent_id.is_empty() {
+        let id = sql::insert(
+            "INSERT INTO agents (agent_name, agent_code, agent_bin)
+             VALUES (:name, :code, :bin)",
+            params! { "name" => name, "code" => &code, "bin" => bin },
+        )?;
+        return Ok(id as i64);
+    }
+    let id: i64 = form.agent_id.parse()?;
+    let affected = sql::exec(
+        "UPDATE agents SET agent_name = :name, agent_code = :code, agent_bin = :bin
+         WHERE agent_id = :id",
+        params! { "id" => id, "name" => name, "code" => &code, "bin" => bin },
+    )?;
+    if affected == 0
+        && sql::cell::<i64>(
+            "SELECT agent_id FROM agents WHERE agent_id = :id",
+            params! { "id" => id },
+        )?
+        .is_none()
+    {
+        bail!("agent {} not found", id);
+    }
+    Ok(id)
+}
+
+fn render_index() -> Result<String> {
+    let rows = sql::select(
+        "SELECT a.agent_id, a.agent_name, a.agent_bin, COUNT(t.task_id) AS tasks
+         FROM agents a
+         LEFT JOIN tasks t ON t.agent_id = a.agent_id
+         GROUP BY a.agent_id, a.agent_name, a.agent_bin
+         ORDER BY a.agent_id DESC",
+        params::Params::Empty,
+    )?;
+    let mut html = String::from(
+        "<h1>エージェント</h1>\n<p><a href=\"/agents/edit\">新規作成</a></p>\n<table class=\"table\">\n<tr><th>ID</th><th>名前</th><th>バイナリ</th><th>タスク数</th><th></th></tr>\n",
+    );
+    for r in &rows {
+        let id: i64 = r.get("agent_id")?;
+        let name: String = r.get("agent_name")?;
+        let bin: Option<String> = r.get_option("agent_bin")?;
+        let tasks: i64 = r.get("tasks")?;
+        html.push_str(&format!(
+            "<tr><td>{id}</td><td><a href=\"/agents/edit?agent_id={id}\">{name}</a></td><td>{bin}</td><td>{tasks}</td><td>{sweep}</td></tr>\n",
+            id = id,
+            name = escape_html(&name),
+            bin = escape_html(bin.as_deref().unwrap_or_default()),
+            tasks = tasks,
+            sweep = sweep_form(id),
+        ));
+    }
+    html.push_str("</table>\n");
+    Ok(html)
+}
+
+fn sweep_form(agent_id: i64) -> String {
+    format!(
+        r#"<form method="POST" action="/agents/sweep"><input type="hidden" name="agent_id" value="{}">シード数 <input name="variants" value="1" size="3"> <select name="priority"><option>bulk</option><option>normal</option><option>urgent</option></select> {} <button type="submit">全問題に投入</button></form>"#,
+        agent_id,
+        auth::form_fields("/agents/sweep")
+    )
+}
+
+#[derive(Deserialize)]
+pub struct EditQuery {
+    agent_id: Option<i64>,
+}
+
+fn render_edit(agent_id: Option<i64>) -> Result<String> {
+    let (name, code, bin) = match agent_id {
+        Some(id) => {
+            let Some(r) = sql::row(
+                "SELECT agent_name, agent_code, agent_bin FROM agents WHERE agent_id = :id",
+                params! { "id" => id },
+            )?
+            else {
+                bail!("agent {} not found", id);
+            };
+            (
+                r.get::<String>("agent_name")?,
+                r.get::<String>("agent_code")?,
+                r.get_option::<String>("agent_bin")?.unwrap_or_default(),
+            )
+        }
+        None => Default::default(),
+    };
+    Ok(format!(
+        r#"<h1>{title}</h1>
+<form method="POST" action="/agents/save">
+<input type="hidden" name="agent_id" value="{id}">
+<p>名前<br><input name="agent_name" value="{name}" maxlength="64" size="40"></p>
+<p>実行コード（使えるプレースホルダ: {placeholders}）<br><textarea name="agent_code" rows="12" style="width: 100%">{code}</textarea></p>
+<p>バイナリ（任意、gs:// URL）<br><input name="agent_bin" value="{bin}" size="80"></p>
+<p>{auth}</p>
+<button type="submit">保存</button>
+</form>
+{sweep}"#,
+        title = if agent_id.is_some() {
+            "エージェント編集"
+        } else {
+            "エージェント作成"
+        },
+        id = agent_id.map(|id| id.to_string()).unwrap_or_default(),
+        name = escape_html(&name),
+        placeholders = PLACEHOLDERS
+            .iter()
+            .map(|p| format!("<code>{{{{{}}}}}</code>", p))
+            .collect::<Vec<_>>()
+            .join(" "),
+        code = escape_html(&code),
+        bin = escape_html(&bin),
+        auth = auth::form_fields("/agents/save"),
+        sweep = agent_id.map(sweep_form).unwrap_or_default(),
+    ))
+}
+
+/// Handler for `GET /agents`: the list of agents.
+pub async fn index() -> impl Responder {
+    template::to_response(render_index())
+}
+
+/// Handler for `GET /agents/edit`: the form for an agent, or for a new one
+/// without `agent_id`.
+pub async fn edit(query: web::Query<EditQuery>) -> impl Responder {
+    template::to_response(render_edit(query.agent_id))
+}
+
+/// Handler for `POST /agents/save`: validates and saves the form.
+pub async fn save(req: HttpRequest, form: web::Form<AgentForm>) -> impl Responder {
+    if let Err(e) = auth::check(&req, "/agents/save", &form.password, &form.csrf) {
+        return auth::forbidden(&e);
+    }
+    match save_agent(&form) {
+        Ok(id) => HttpResponse::Found()
+            .append_header(("Location", format!("/agents/edit?agent_id={}", id)))
+            .finish(),
+        Err(e) => template::to_error_response(&e),
+    }
+}
+
+#[derive(Deserialize)]
+pub struct SweepForm {
+    agent_id: i64,
+    /// Problem variants (seeds) `0..variants` per problem.
+    variants: i64,
+    /// A class name or raw priority, as in `queue::parse_priority`.
+    priority: String,
+    #[serde(default)]
+    password: String,
+    #[serde(default)]
+    csrf: String,
+}
+
+/// Most variants per problem a single sweep may queue.
+const MAX_SWEEP_VARIANTS: i64 = 100;
+
+fn sweep(form: &SweepForm) -> Result<usize> {
+    ensure!(
+        (1..=MAX_SWEEP_VARIANTS).contains(&form.variants),
+        "variants must be 1 to {}",
+        MAX_SWEEP_VARIANTS
+    );
+    let priority = queue::parse_priority(&form.priority)?;
+    let mut queued = 0;
+    for p in problems::all_problems() {
+        for variant in 0..form.variants {
+            queue::enqueue_task(priority, form.agent_id, &p.problem, variant)?;
+            queued += 1;
+        }
+    }
+    Ok(queued)
+}
+
+/// Handler for `POST /agents/sweep`: queues the agent on every problem.
+pub async fn sweep_post(req: HttpRequest, form: web::Form<SweepForm>) -> impl Responder {
+    if let Err(e) = auth::check(&req, "/agents/sweep", &form.password, &form.csrf) {
+        return auth::forbidden(&e);
+    }
+    match sweep(&form) {
+        Ok(_) => HttpResponse::Found()
+            .append_header(("Location", "/tasks?state=queued"))
+            .finish(),
+        Err(e) => template::to_error_response(&e),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn placeholders_are_checked() {
+        assert!(validate_code("./run solver {{problem_name}} --seed={{problem_variant}}").is_ok());
+        assert!(validate_code("echo {{task_id}}{{agent_name}}; echo '{}'").is_ok());
+        let err = |code: &str| validate_code(code).unwrap_err().to_string();
+        assert!(err("./run {{problem}}").contains("unknown placeholder {{problem}}"));
+        assert!(err("./run {{problem_name").contains("unclosed"));
+        assert!(err("./run problem_name}} {{task_id}}").contains("unmatched"));
+        assert!(err("{{task_id}} x}}").contains("unmatched"));
+        assert!(err("  \n").contains("empty"));
+    }
+
+    #[test]
+    fn names_and_bins_are_checked() {
+        assert!(validate_name("wata_sat2").is_ok());
+        assert!(validate_name("").is_err());
+        assert!(validate_name("rm -rf").is_err());
+        assert!(validate_bin("").is_ok());
+        assert!(validate_bin("gs://icfpc2025-data/bin/solver").is_ok());
+        assert!(validate_bin("gs://icfpc2025-data").is_err());
+        assert!(validate_bin("https://example.com/solver").is_err());
+    }
+}
@@ -10,7 +10,7 @@
 //! 3. The response from the official server is then returned to the original caller,
 //!    with an additional `X-Unagi-Log` header containing the log ID.
 
-use crate::client;
+use crate::client::{self, SendTracked};
 use crate::sql;
 
 use actix_web::{HttpRequest, HttpResponse, Responder, http::header, web};
@@ -47,7 +47,7 @@ async fn forward_and_log(path: &str, body: web::Bytes, req: &HttpRequest) -> Htt
         .post(&backend_url)
         .header(reqwest_header::CONTENT_TYPE, "application/json")
         .body(body.clone())
-        .send()
+        .send_tracked()
         .await
     {
         Ok(resp) => {
@@ -87,13 +87,21 @@ async fn forward_and_log(path: &str, body: web::Bytes, req: &HttpRequest) -> Htt
 
     // Log the transaction to the database.
     let duration_ms = started.elapsed().as_millis() as u64;
-    let meta = serde_json::json!({
+    let mut meta = serde_json::json!({
         "method": req.method().as_str(),
         "path": path_for_log,
         "time": Utc::now().to_rfc3339(),
         "duration_ms": duration_ms,
-    })
-    .to_string();
+    });
+    // The solver that sent the request, for query cost accounting (`costs`).
+    if let Some(solver) = req
+        .headers()
+        .get("X-Unagi-Solver")
+        .and_then(|v| v.to_str().ok())
+    {
+        meta["solver"] = solver.into();
+    }
+    let meta = meta.to_string();
 
     let req_body = String::from_utf8(body.to_vec()).unwrap_or_default();
     let log_id: u64 = sql::insert(
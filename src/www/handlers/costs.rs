@@ -0,0 +1,312 @@
+//! # Query costs
+//!
+//! `/costs` aggregates `api_logs` to show how much of the query budget each
+//! problem and each solver spends: explore calls, plans, plan characters, the
+//! query count the contest server reports, and guesses. A `/select` session
+//! is one solver invocation; its solver is the `solver` field the logging
+//! proxy records in the `/select` metadata from the `X-Unagi-Solver` header
+//! (see `api::solver_name`). Sessions logged before that header existed show
+//! up as `unknown`.
+
+use actix_web::{HttpRequest, Responder, web};
+use anyhow::Result;
+use chrono::NaiveDateTime;
+use mysql::params;
+use serde::Deserialize;
+use std::collections::{BTreeMap, HashMap};
+
+use crate::sql;
+use crate::www::handlers::template::escape_html;
+use crate::www::handlers::{prefs::Prefs, template};
+
+/// Sessions shown when the query does not say.
+const DEFAULT_SESSIONS: usize = 200;
+/// Most sessions a single page aggregates.
+const MAX_SESSIONS: usize = 2000;
+
+#[derive(Deserialize)]
+pub struct CostsQuery {
+    /// Number of most recent `/select` sessions to aggregate.
+    sessions: Option<usize>,
+    /// Only sessions of this problem.
+    #[serde(default)]
+    problem: String,
+}
+
+/// Budget spent by one or more sessions.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct Cost {
+    pub sessions: usize,
+    pub explores: usize,
+    pub plans: usize,
+    pub plan_chars: usize,
+    /// Queries as counted by the contest server.
+    pub queries: u64,
+    pub guesses: usize,
+    pub correct: usize,
+}
+
+impl Cost {
+    /// Counts a logged `/explore` call of the session. The server reports the
+    /// session's running query count, so the largest one seen is kept; calls
+    /// whose response cannot be read are charged one query per plan plus one
+    /// for the call.
+    fn add_explore(&mut self, request: &str, response: &str) {
+        let Ok(req) = serde_json::from_str::<crate::api::ExploreLog>(request) else {
+            return;
+        };
+        self.explores += 1;
+        self.plans += req.plans.len();
+        self.plan_chars += req.plans.iter().map(|p| p.len()).sum::<usize>();
+        match serde_json::from_str::<crate::api::ExploreResponse>(response) {
+            Ok(resp) => self.queries = self.queries.max(resp.query_count),
+            Err(_) => self.queries += req.plans.len() as u64 + 1,
+        }
+    }
+
+    fn add(&mut self, other: &Cost) {
+        self.sessions += other.sessions;
+        self.explores += other.explores;
+        self.plans += other.plans;
+        self.plan_chars += other.plan_chars;
+        self.queries += other.queries;
+        self.guesses += other.guesses;
+        self.correct += other.correct;
+    }
+
+    /// Queries spent per correct guess, or `None` if nothing was solved.
+    pub fn queries_per_solve(&self) -> Option<f64> {
+        (self.correct > 0).then(|| self.queries as f64 / self.correct as f64)
+    }
+}
+
+struct Session {
+    id: i64,
+    problem: String,
+    solver: String,
+    created: NaiveDateTime,
+    cost: Cost,
+}
+
+fn fetch_sessions(limit: usize, problem: &str) -> Result<Vec<Session>> {
+    let rows = sql::select(
+        "SELECT api_log_id, api_log_request__problem_name, api_log_created,
+                JSON_UNQUOTE(JSON_EXTRACT(api_log_metadata, '$.solver')) AS solver
+         FROM api_logs
+         WHERE api_log_path = '/select' AND api_log_response_code = 200
+           AND (:problem = '' OR api_log_request__problem_name = :problem)
+         ORDER BY api_log_id DESC
+         LIMIT :limit",
+        params! { "problem" => problem, "limit" => limit },
+    )?;
+    let mut sessions = vec![];
+    for r in &rows {
+        sessions.push(Session {
+            id: r.at(0)?,
+            problem: r
+                .get_option("api_log_request__problem_name")?
+                .unwrap_or_default(),
+            created: r.at(2)?,
+            solver: r
+                .get_option("solver")?
+                .unwrap_or_else(|| "unknown".to_string()),
+            cost: Cost {
+                sessions: 1,
+                ..Cost::default()
+            },
+        });
+    }
+    let Some(min_id) = sessions.iter().map(|s| s.id).min() else {
+        return Ok(sessions);
+    };
+    let index: HashMap<i64, usize> = sessions
+        .iter()
+        .enumerate()
+        .map(|(i, s)| (s.id, i))
+        .collect();
+
+    let explores = sql::select(
+        "SELECT api_log_select_id, api_log_request, api_log_response
+         FROM api_logs
+         WHERE api_log_path = '/explore'
+           AND api_log_response_code = 200
+           AND api_log_select_id >= :min_id",
+        params! { "min_id" => min_id },
+    )?;
+    for r in &explores {
+        if let Some(&i) = index.get(&r.at::<i64>(0)?) {
+            sessions[i]
+                .cost
+                .add_explore(&r.at::<String>(1)?, &r.at::<String>(2)?);
+        }
+    }
+
+    let guesses = sql::select(
+        "SELECT api_log_select_id, COUNT(*),
+                COALESCE(SUM(JSON_VALUE(api_log_response, '$.correct' RETURNING UNSIGNED)), 0)
+         FROM api_logs
+         WHERE api_log_path = '/guess'
+           AND api_log_response_code = 200
+           AND api_log_select_id >= :min_id
+         GROUP BY api_log_select_id",
+        params! { "min_id" => min_id },
+    )?;
+    for r in &guesses {
+        if let Some(&i) = index.get(&r.at::<i64>(0)?) {
+            sessions[i].cost.guesses = r.at::<i64>(1)? as usize;
+            sessions[i].cost.correct = r.at::<i64>(2)? as usize;
+        }
+    }
+    Ok(sessions)
+}
+
+/// Sums the costs of `sessions` by `key`.
+fn totals_by<'a>(
+    sessions: impl IntoIterator<Item = (&'a str, &'a Cost)>,
+) -> BTreeMap<&'a str, Cost> {
+    let mut totals: BTreeMap<&str, Cost> = BTreeMap::new();
+    for (key, cost) in sessions {
+        totals.entry(key).or_default().add(cost);
+    }
+    totals
+}
+
+fn cost_cells(cost: &Cost) -> String {
+    format!(
+        "<td>{}</td><td>{}</td><td>{}</td><td>{}</td><td>{}</td><td>{}</td><td>{}</td>",
+        cost.explores,
+        cost.plans,
+        cost.plan_chars,
+        cost.queries,
+        cost.guesses,
+        cost.correct,
+        cost.queries_per_solve()
+            .map(|q| format!("{:.0}", q))
+            .unwrap_or_else(|| "-".to_string()),
+    )
+}
+
+const COST_HEADERS: &str = "<th>explore</th><th>プラン</th><th>プラン文字数</th><th>クエリ</th><th>guess</th><th>正解</th><th>クエリ/正解</th>";
+
+fn render_totals(title: &str, key: &str, totals: &BTreeMap<&str, Cost>) -> String {
+    let mut html = format!(
+        "<h2>{}</h2>\n<table class=\"table\">\n<tr><th>{}</th><th>セッション</th>{}</tr>\n",
+        title, key, COST_HEADERS
+    );
+    for (k, cost) in totals {
+        html.push_str(&format!(
+            "<tr><td>{}</td><td>{}</td>{}</tr>\n",
+            escape_html(k),
+            cost.sessions,
+            cost_cells(cost)
+        ));
+    }
+    html.push_str("</table>\n");
+    html
+}
+
+fn render_costs(query: &CostsQuery, prefs: &Prefs) -> Result<String> {
+    let limit = query
+        .sessions
+        .unwrap_or(DEFAULT_SESSIONS)
+        .clamp(1, MAX_SESSIONS);
+    let sessions = fetch_sessions(limit, &query.problem)?;
+
+    let mut html = String::from("<h1>クエリコスト</h1>\n");
+    html.push_str(&format!(
+        "<form method=\"GET\" action=\"/costs\">直近 <input name=\"sessions\" value=\"{}\" size=\"5\"> セッション、問題 <input name=\"problem\" value=\"{}\" size=\"12\"> <button type=\"submit\">表示</button></form>\n",
+        limit,
+        escape_html(&query.problem)
+    ));
+    if sessions.is_empty() {
+        html.push_str("<p>セッションがありません</p>\n");
+        return Ok(html);
+    }
+    html.push_str(&render_totals(
+        "問題別",
+        "問題",
+        &totals_by(sessions.iter().map(|s| (s.problem.as_str(), &s.cost))),
+    ));
+    html.push_str(&render_totals(
+        "ソルバ別",
+        "ソルバ",
+        &totals_by(sessions.iter().map(|s| (s.solver.as_str(), &s.cost))),
+    ));
+
+    html.push_str(&format!(
+        "<h2>セッション</h2>\n<table class=\"table\">\n<tr><th>セッション</th><th>問題</th><th>ソルバ</th><th>開始時刻</th>{}</tr>\n",
+        COST_HEADERS
+    ));
+    for s in &sessions {
+        html.push_str(&format!(
+            "<tr><td>{}</td><td>{}</td><td>{}</td><td>{}</td>{}</tr>\n",
+            s.id,
+            escape_html(&s.problem),
+            escape_html(&s.solver),
+            escape_html(&prefs.format_utc(s.created)),
+            cost_cells(&s.cost)
+        ));
+    }
+    html.push_str("</table>\n");
+    Ok(html)
+}
+
+/// Handler for `GET /costs`.
+pub async fn index(req: HttpRequest, query: web::Query<CostsQuery>) -> impl Responder {
+    let prefs = Prefs::from_request(&req);
+    template::to_response(render_costs(&query, &prefs))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn explores_are_charged_per_session() {
+        let mut cost = Cost::default();
+        cost.add_explore(
+            r#"{"id":"x","plans":["012","34"]}"#,
+            r#"{"results":[[0,1,2,3],[0,1,2]],"queryCount":3}"#,
+        );
+        cost.add_explore(
+            r#"{"id":"x","plans":["5"]}"#,
+            r#"{"results":[[0,1]],"queryCount":5}"#,
+        );
+        // An unreadable response is charged as plans + 1.
+        cost.add_explore(r#"{"id":"x","plans":["0","1"]}"#, "oops");
+        // An unreadable request is not a call we can account for.
+        cost.add_explore("oops", "oops");
+        assert_eq!(
+            cost,
+            Cost {
+                explores: 3,
+                plans: 5,
+                plan_chars: 8,
+                queries: 8,
+                ..Cost::default()
+            }
+        );
+        assert_eq!(cost.queries_per_solve(), None);
+    }
+
+    #[test]
+    fn totals_are_summed_by_key() {
+        let a = Cost {
+            sessions: 1,
+            queries: 10,
+            correct: 1,
+            ..Cost::default()
+        };
+        let b = Cost {
+            sessions: 1,
+            queries: 30,
+            guesses: 2,
+            ..Cost::default()
+        };
+        let totals = totals_by([("sat", &a), ("anneal", &b), ("sat", &b)]);
+        assert_eq!(totals["sat"].sessions, 2);
+        assert_eq!(totals["sat"].queries, 40);
+        assert_eq!(totals["sat"].queries_per_solve(), Some(40.0));
+        assert_eq!(totals["anneal"].guesses, 2);
+    }
+}
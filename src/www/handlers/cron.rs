@@ -3,7 +3,7 @@
 //! This module contains handlers designed to be called periodically by a cron
 //! job or a similar scheduling service.
 
-use crate::client;
+use crate::client::{self, SendTracked};
 use crate::sql;
 use actix_web::{HttpResponse, Responder};
 
@@ -25,12 +25,16 @@ struct ProblemEntry {
 
 /// Determines the base endpoint for the Aedificium API.
 ///
-/// Uses the `AEDIFICIUM_ENDPOINT` environment variable if set, otherwise
-/// defaults to the official contest server URL.
+/// Uses the first entry of the `AEDIFICIUM_ENDPOINT` environment variable if set,
+/// otherwise defaults to the official contest server URL.
 fn base_endpoint() -> String {
     std::env::var("AEDIFICIUM_ENDPOINT")
         .ok()
-        .map(|s| s.trim_end_matches('/').to_string())
+        .and_then(|s| {
+            s.split(',')
+                .next()
+                .map(|s| s.trim().trim_end_matches('/').to_string())
+        })
         .unwrap_or_else(|| "https://31pwr5t6ij.execute-api.eu-west-2.amazonaws.com".to_string())
 }
 
@@ -80,7 +84,7 @@ async fn run_impl() -> Result<serde_json::Value> {
     // 1. Fetch problem list.
     let probs: Vec<ProblemEntry> = client
         .get(format!("{}/select", base))
-        .send()
+        .send_tracked()
         .await
         .context("Failed to GET /select for problem list")?
         .json()
@@ -100,7 +104,7 @@ async fn run_impl() -> Result<serde_json::Value> {
             let url = format!("{}/leaderboard/{}", base, problem);
             let body = client
                 .get(&url)
-                .send()
+                .send_tracked()
                 .await
                 .with_context(|| format!("Failed to GET leaderboard for {}", &problem))?
                 .text()
@@ -128,7 +132,7 @@ async fn run_impl() -> Result<serde_json::Value> {
         set.spawn(async move {
             let body = client
                 .get(format!("{}/leaderboard/global", base))
-                .send()
+                .send_tracked()
                 .await
                 .context("Failed to GET leaderboard/global")?
                 .text()
@@ -0,0 +1,233 @@
+//! # Executor Daily Digest
+//!
+//! `/cron/digest` compiles one day (JST) of executor statistics per problem:
+//! finished tasks, successes, total run time and whether the day's best score
+//! beat everything before it. The report is stored as HTML in GCS under
+//! `digests/YYYY-MM-DD.html` and listed at `/digests`.
+
+use actix_web::{HttpResponse, Responder, web};
+use anyhow::{Context, Result};
+use chrono::{Duration, FixedOffset, NaiveDate, Utc};
+use mysql::params;
+use serde::Deserialize;
+use std::collections::HashMap;
+
+use crate::sql;
+use crate::www::handlers::template;
+
+const BUCKET: &str = "icfpc2025-data";
+const PREFIX: &str = "digests/";
+
+/// Days are cut at midnight JST, when the team wraps up.
+fn jst() -> FixedOffset {
+    FixedOffset::east_opt(9 * 3600).unwrap()
+}
+
+#[derive(Deserialize)]
+pub struct DigestQuery {
+    /// Day to summarize (`YYYY-MM-DD`); defaults to yesterday in JST.
+    pub date: Option<String>,
+}
+
+/// Per-problem statistics of one day.
+#[derive(Debug, Clone, PartialEq)]
+pub struct DigestRow {
+    pub problem: String,
+    pub runs: i64,
+    pub successes: i64,
+    pub duration_ms: i64,
+    /// Best (lowest) score among successful runs of the day.
+    pub best_score: Option<i64>,
+    /// Best score of all runs before the day.
+    pub previous_best: Option<i64>,
+}
+
+impl DigestRow {
+    fn improved(&self) -> bool {
+        match (self.best_score, self.previous_best) {
+            (Some(b), Some(p)) => b < p,
+            (Some(_), None) => true,
+            _ => false,
+        }
+    }
+}
+
+fn load_rows(date: NaiveDate) -> Result<Vec<DigestRow>> {
+    let from =
+        date.and_hms_opt(0, 0, 0).unwrap() - Duration::seconds(jst().local_minus_utc() as i64);
+    let to = from + Duration::days(1);
+    let rows = sql::select(
+        "
+        SELECT problem_name,
+               COUNT(*) AS runs,
+               CAST(SUM(task_exit_code = 0) AS SIGNED) AS successes,
+               CAST(COALESCE(SUM(task_duration_ms), 0) AS SIGNED) AS duration_ms,
+               MIN(CASE WHEN task_exit_code = 0 THEN task_score END) AS best_score
+        FROM tasks
+        WHERE task_exit_code IS NOT NULL
+          AND task_updated >= :from AND task_updated < :to
+        GROUP BY problem_name
+        ORDER BY problem_name",
+        params! { "from" => from, "to" => to },
+    )?;
+    let previous = sql::select(
+        "
+        SELECT problem_name, MIN(task_score) AS best_score
+        FROM tasks
+        WHERE task_exit_code = 0 AND task_score IS NOT NULL AND task_updated < :from
+        GROUP BY problem_name",
+        params! { "from" => from },
+    )?;
+    let mut previous_best = HashMap::new();
+    for r in &previous {
+        previous_best.insert(
+            r.get::<String>("problem_name")?,
+            r.get::<i64>("best_score")?,
+        );
+    }
+    rows.iter()
+        .map(|r| {
+            let problem: String = r.get("problem_name")?;
+            Ok(DigestRow {
+                previous_best: previous_best.get(&problem).copied(),
+                problem,
+                runs: r.get("runs")?,
+                successes: r.get("successes")?,
+                duration_ms: r.get("duration_ms")?,
+                best_score: r.get_option("best_score")?,
+            })
+        })
+        .collect()
+}
+
+/// Renders the report body (without the page layout).
+pub fn render_digest(date: NaiveDate, rows: &[DigestRow]) -> String {
+    let mut html = format!("<h1>日報 {}</h1>\n", date);
+    if rows.is_empty() {
+        html.push_str("<p>この日に完了したタスクはありません</p>\n");
+        return html;
+    }
+    let runs: i64 = rows.iter().map(|r| r.runs).sum();
+    let successes: i64 = rows.iter().map(|r| r.successes).sum();
+    let hours = rows.iter().map(|r| r.duration_ms).sum::<i64>() as f64 / 3.6e6;
+    html.push_str(&format!(
+        "<p>タスク {} 件（成功 {} 件）、実行時間 {:.1} 時間、スコア更新 {} 問</p>\n",
+        runs,
+        successes,
+        hours,
+        rows.iter().filter(|r| r.improved()).count(),
+    ));
+    html.push_str("<table class=\"table\">\n");
+    html.push_str(
+        "<tr><th>問題</th><th>タスク</th><th>成功</th><th>実行時間 (h)</th><th>当日ベスト</th><th>前日までのベスト</th></tr>\n",
+    );
+    let opt = |v: Option<i64>| v.map(|v| v.to_string()).unwrap_or_else(|| "-".to_string());
+    for r in rows {
+        html.push_str(&format!(
+            "<tr><td><a href=\"/leaderboard/{p}\">{p}</a></td><td>{}</td><td>{}</td><td>{:.2}</td><td>{}{}</td><td>{}</td></tr>\n",
+            r.runs,
+            r.successes,
+            r.duration_ms as f64 / 3.6e6,
+            opt(r.best_score),
+            if r.improved() { " ★" } else { "" },
+            opt(r.previous_best),
+            p = r.problem,
+        ));
+    }
+    html.push_str("</table>\n");
+    html
+}
+
+fn parse_date(date: Option<&str>) -> Result<NaiveDate> {
+    match date {
+        Some(s) => NaiveDate::parse_from_str(s, "%Y-%m-%d").context("date must be YYYY-MM-DD"),
+        None => Ok(Utc::now().with_timezone(&jst()).date_naive() - Duration::days(1)),
+    }
+}
+
+async fn run_impl(date: Option<&str>) -> Result<serde_json::Value> {
+    let date = parse_date(date)?;
+    let rows = load_rows(date)?;
+    let html = template::render(&render_digest(date, &rows));
+    let object = format!("{}{}.html", PREFIX, date);
+    crate::gcp::gcs::upload_object(BUCKET, &object, html.as_bytes(), "text/html")
+        .await
+        .with_context(|| format!("Failed to upload {}", object))?;
+    Ok(serde_json::json!({ "date": date.to_string(), "saved": object }))
+}
+
+/// Handler for `/cron/digest`: builds and stores the digest of one day.
+pub async fn run(query: web::Query<DigestQuery>) -> impl Responder {
+    match run_impl(query.date.as_deref()).await {
+        Ok(v) => HttpResponse::Ok()
+            .content_type("application/json")
+            .body(v.to_string()),
+        Err(e) => HttpResponse::InternalServerError().body(e.to_string()),
+    }
+}
+
+async fn render_index() -> Result<String> {
+    let (_, mut files) = crate::gcp::gcs::list_dir(BUCKET, PREFIX).await?;
+    files.sort();
+    files.reverse();
+    let mut html = String::from("<h1>日報</h1>\n<ul>\n");
+    for f in files {
+        if let Some(date) = f.strip_suffix(".html") {
+            html.push_str(&format!(
+                "<li><a href=\"/digests/{d}\">{d}</a></li>\n",
+                d = date
+            ));
+        }
+    }
+    html.push_str("</ul>\n");
+    Ok(html)
+}
+
+/// Handler for `GET /digests`: lists stored digests, newest first.
+pub async fn index() -> impl Responder {
+    template::to_response(render_index().await)
+}
+
+/// Handler for `GET /digests/{date}`: serves a stored digest.
+pub async fn show(path: web::Path<String>) -> impl Responder {
+    let date = match parse_date(Some(&path.into_inner())) {
+        Ok(d) => d,
+        Err(e) => return template::to_error_response(&e),
+    };
+    match crate::gcp::gcs::download_object(BUCKET, &format!("{}{}.html", PREFIX, date)).await {
+        Ok(body) => HttpResponse::Ok().content_type("text/html").body(body),
+        Err(e) => template::to_error_response(&e),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn render_digest_marks_improvements() {
+        let date = NaiveDate::from_ymd_opt(2025, 9, 6).unwrap();
+        let rows = vec![
+            DigestRow {
+                problem: "aleph".to_string(),
+                runs: 10,
+                successes: 7,
+                duration_ms: 3_600_000,
+                best_score: Some(5),
+                previous_best: Some(6),
+            },
+            DigestRow {
+                problem: "beth".to_string(),
+                runs: 2,
+                successes: 0,
+                duration_ms: 0,
+                best_score: None,
+                previous_best: Some(9),
+            },
+        ];
+        let html = render_digest(date, &rows);
+        assert!(html.contains("タスク 12 件（成功 7 件）、実行時間 1.0 時間、スコア更新 1 問"));
+        assert!(html.contains("<td>5 ★</td>"));
+        assert!(!html.contains("<td>- ★</td>"));
+    }
+}
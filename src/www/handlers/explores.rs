@@ -0,0 +1,129 @@
+//! Recent `/explore` sessions with a label histogram sanity check.
+//!
+//! For each of the latest `/select` sessions, the labels returned by its
+//! explorations are counted and compared with what the selected problem size
+//! should produce (see `judge::label_histogram_warnings`).
+
+use actix_web::{HttpRequest, Responder};
+use anyhow::Result;
+use chrono::NaiveDateTime;
+use mysql::params;
+use std::collections::HashMap;
+
+use crate::judge;
+use crate::problems;
+use crate::www::handlers::template::escape_html;
+use crate::www::handlers::{prefs::Prefs, template};
+
+/// Number of most recent sessions shown.
+const SESSIONS: usize = 20;
+
+struct Session {
+    problem: String,
+    created: NaiveDateTime,
+    explores: usize,
+    plans: Vec<Vec<judge::Step>>,
+    results: Vec<Vec<usize>>,
+}
+
+pub async fn index(req: HttpRequest) -> impl Responder {
+    let prefs = Prefs::from_request(&req);
+    template::to_response(render_explores(&prefs))
+}
+
+fn render_explores(prefs: &Prefs) -> Result<String> {
+    let selects = crate::sql::select(
+        "SELECT api_log_id, api_log_request__problem_name, api_log_created
+         FROM api_logs
+         WHERE api_log_path = '/select' AND api_log_response_code = 200
+         ORDER BY api_log_id DESC
+         LIMIT :limit",
+        params! { "limit" => SESSIONS },
+    )?;
+    let mut sessions: Vec<(i64, Session)> = vec![];
+    for r in &selects {
+        sessions.push((
+            r.at(0)?,
+            Session {
+                problem: r
+                    .get_option("api_log_request__problem_name")?
+                    .unwrap_or_default(),
+                created: r.at(2)?,
+                explores: 0,
+                plans: vec![],
+                results: vec![],
+            },
+        ));
+    }
+    let Some(min_id) = sessions.iter().map(|s| s.0).min() else {
+        return Ok("<h1>探索ログ</h1><p>セッションがありません</p>".to_string());
+    };
+    let index: HashMap<i64, usize> = sessions.iter().enumerate().map(|(i, s)| (s.0, i)).collect();
+
+    let rows = crate::sql::select(
+        "SELECT api_log_select_id, api_log_request, api_log_response
+         FROM api_logs
+         WHERE api_log_path = '/explore'
+           AND api_log_response_code = 200
+           AND api_log_select_id >= :min_id
+         ORDER BY api_log_id",
+        params! { "min_id" => min_id },
+    )?;
+    for r in &rows {
+        let Some(&i) = index.get(&r.at::<i64>(0)?) else {
+            continue;
+        };
+        let (Ok(req), Ok(resp)) = (
+            serde_json::from_str::<crate::api::ExploreLog>(&r.at::<String>(1)?),
+            serde_json::from_str::<crate::api::ExploreResponse>(&r.at::<String>(2)?),
+        ) else {
+            continue;
+        };
+        // Responses echo charcoal marks, but `label_histogram` stops at the
+        // first mark of each plan, so the raw results can be used as is.
+        let session = &mut sessions[i].1;
+        session.explores += 1;
+        session
+            .plans
+            .extend(req.plans.iter().map(|p| judge::parse_plan(p)));
+        session.results.extend(resp.results);
+    }
+
+    let mut html = String::new();
+    html.push_str("<h1>探索ログ</h1>\n");
+    html.push_str("<table class=\"table\">\n");
+    let num_labels = judge::num_labels();
+    html.push_str(&format!(
+        "<tr><th>セッション</th><th>問題</th><th>開始時刻</th><th>explore 回数</th><th>ラベル {}</th><th>警告</th></tr>\n",
+        (0..num_labels)
+            .map(|l| l.to_string())
+            .collect::<Vec<_>>()
+            .join("/")
+    ));
+    for (id, s) in &sessions {
+        let counts = judge::label_histogram(&s.plans, &s.results, num_labels);
+        let warnings = match problems::get_problem(&s.problem) {
+            Some(p) => judge::label_histogram_warnings(p.size, &counts),
+            None => vec![format!("unknown problem: {}", s.problem)],
+        };
+        html.push_str(&format!(
+            "<tr><td>{}</td><td>{}</td><td>{}</td><td>{}</td><td>{}</td><td>{}</td></tr>\n",
+            id,
+            escape_html(&s.problem),
+            escape_html(&prefs.format_utc(s.created)),
+            s.explores,
+            counts
+                .iter()
+                .map(|c| c.to_string())
+                .collect::<Vec<_>>()
+                .join(" / "),
+            warnings
+                .iter()
+                .map(|w| format!("<span style=\"color:red\">{}</span>", escape_html(w)))
+                .collect::<Vec<_>>()
+                .join("<br>"),
+        ));
+    }
+    html.push_str("</table>\n");
+    Ok(html)
+}
@@ -3,9 +3,23 @@
 //! This module contains the handlers for rendering the leaderboard pages.
 //! It fetches historical leaderboard data, visualizes it using Chart.js,
 //! and displays the latest solved map for a given problem.
+//!
+//! A problem page keeps itself current through `/leaderboard/{problem}/events`,
+//! a server-sent event stream of score rows inserted after the page was
+//! rendered; the chart and table are updated in place. One poller per problem
+//! looks for new rows and feeds every open stream of that problem. The
+//! guesses, the map and the rest of the page are not streamed, so the page
+//! still reloads itself, every 5 minutes without a stream and every 30 with one.
+//!
+//! `/leaderboard/{problem}/history` shows our rank over time instead of raw
+//! scores, and traces each improvement to the guess and executor task that
+//! produced it.
 
+use crate::www::handlers::owners;
+use crate::www::handlers::prefs::Prefs;
+use crate::www::handlers::template::escape_html;
 use crate::{api, problems, sql, svg};
-use actix_web::{HttpResponse, Responder, web};
+use actix_web::{HttpRequest, HttpResponse, Responder, web};
 use anyhow::Result;
 use cached::proc_macro::cached;
 use chrono::NaiveDateTime;
@@ -16,32 +30,55 @@ use std::collections::HashMap;
 use std::fmt::Write;
 use tokio::time::Duration;
 
-const _TZ: chrono::FixedOffset = chrono::FixedOffset::east_opt(9 * 3600).unwrap();
-
 #[derive(Deserialize)]
 pub struct LeaderboardQuery {
     #[serde(default)]
     nocache: bool,
+    /// 1-based page of the recent guesses table.
+    #[serde(default)]
+    guess_page: Option<usize>,
 }
 
 /// A helper to wrap content in the standard HTML page template.
 fn html_page(title: &str, body: &str, banner: &str) -> String {
-    // Auto-refresh leaderboard pages every minute
-    let auto_refresh = "<script>setTimeout(() => location.reload(), 5*60*1000);</script>";
+    // Auto-refresh leaderboard pages every 5 minutes; pages with a live event
+    // stream slow this down once the stream is open.
+    let auto_refresh =
+        "<script>window.lbReload = setTimeout(() => location.reload(), 5*60*1000);</script>";
     crate::www::handlers::template::render(&format!(
         "{}<h1>{}</h1>\n{}\n{}",
         banner, title, auto_refresh, body
     ))
 }
 
+/// Problems in display order: the viewer's favorites first, then the rest.
+fn ordered_problems(prefs: &Prefs) -> Vec<&'static problems::Problem> {
+    let all = problems::all_problems();
+    let mut list: Vec<_> = prefs
+        .favorites
+        .iter()
+        .filter_map(|f| all.iter().find(|p| &p.problem == f))
+        .collect();
+    list.extend(all.iter().filter(|p| !prefs.is_favorite(&p.problem)));
+    list
+}
+
 /// Renders the main leaderboard index page, which lists all available problems.
-pub async fn index() -> impl Responder {
-    let list = crate::problems::all_problems()
+pub async fn index(req: HttpRequest) -> impl Responder {
+    let prefs = Prefs::from_request(&req);
+    let list = ordered_problems(&prefs)
         .iter()
         .map(|p| {
             format!(
-                "<li><a href=\"/leaderboard/{}\">{}</a> (size {})</li>",
-                p.problem, p.problem, p.size
+                "<li>{}<a href=\"/leaderboard/{}\">{}</a> (size {})</li>",
+                if prefs.is_favorite(&p.problem) {
+                    "★ "
+                } else {
+                    ""
+                },
+                p.problem,
+                p.problem,
+                p.size
             )
         })
         .collect::<Vec<_>>()
@@ -61,12 +98,22 @@ pub struct ProblemPath {
 
 /// The main handler for showing a leaderboard for a specific problem.
 pub async fn show(
+    req: HttpRequest,
     path: web::Path<ProblemPath>,
     query: web::Query<LeaderboardQuery>,
 ) -> impl Responder {
     let problem = &path.problem;
+    let prefs = Prefs::from_request(&req);
 
-    let result = async move { render_problem_leaderboard(problem, query.nocache).await };
+    let result = async move {
+        render_problem_leaderboard(
+            problem,
+            query.nocache,
+            query.guess_page.unwrap_or(1),
+            &prefs,
+        )
+        .await
+    };
     match result.await {
         Ok(html) => HttpResponse::Ok().content_type("text/html").body(html),
         Err(e) => crate::www::handlers::template::to_error_response(&e),
@@ -74,7 +121,12 @@ pub async fn show(
 }
 
 /// The core logic for fetching data and rendering the leaderboard page for a single problem.
-async fn render_problem_leaderboard(problem: &str, nocache: bool) -> Result<String> {
+async fn render_problem_leaderboard(
+    problem: &str,
+    nocache: bool,
+    guess_page: usize,
+    prefs: &Prefs,
+) -> Result<String> {
     let mut timings = vec![];
     // Fetch active lock
     // Build notification banner if active_lock_user exists
@@ -89,11 +141,16 @@ async fn render_problem_leaderboard(problem: &str, nocache: bool) -> Result<Stri
           ",
         params::Params::Empty,
     )? {
+        // Queued waiters (`lock::wait_for_lock`); the table may not exist yet.
+        let waiting = match crate::lock::waiters_ahead(None).unwrap_or(0) {
+            0 => String::new(),
+            n => format!(" ({} waiting)", n),
+        };
         format!(
             r#"<div style="width:100vw;position:relative;left:50%;right:50%;margin-left:-50vw;margin-right:-50vw;background-color:#66bb6a;color:white;font-weight:bold;padding:4px 0;text-align:center;font-size:2.4em;box-shadow:0 2px 8px rgba(0,0,0,0.08);z-index:1000;">
       <a href="/unlock"><img style="height:1em;vertical-align:text-bottom;" src="/static/sansho.png" alt="Lock icon">
       {user}
-      🔒️</a>
+      🔒️{waiting}</a>
       </div>"#
         )
     } else {
@@ -141,13 +198,21 @@ async fn render_problem_leaderboard(problem: &str, nocache: bool) -> Result<Stri
     let best_scores = best_scores()?;
     timings.push(("best_scores", t0.elapsed().as_millis()));
 
+    // Owners are informational; a missing table must not break the leaderboard.
+    let t0 = std::time::Instant::now();
+    let owners = owners::load_all().unwrap_or_else(|e| {
+        eprintln!("Failed to load problem owners: {}", e);
+        HashMap::new()
+    });
+    timings.push(("owners", t0.elapsed().as_millis()));
+
     let mut nav_links: Vec<String> = Vec::new();
     if problem == "global" {
         nav_links.push("<b>[Global]</b>".to_string());
     } else {
         nav_links.push("[<a href=\"/leaderboard/global\">Global</a>]".to_string());
     }
-    for problems::Problem { problem: p, .. } in problems::all_problems() {
+    for problems::Problem { problem: p, .. } in ordered_problems(prefs) {
         let score = scores.get(p);
         let best = best_scores.get(p);
         let mut link = format!(
@@ -165,11 +230,17 @@ async fn render_problem_leaderboard(problem: &str, nocache: bool) -> Result<Stri
         } else if score < best {
             link = format!(r#"<span style="color:lime;">{}</span>"#, link);
         }
+        if prefs.is_favorite(p) {
+            link = format!("★{link}");
+        }
         if problem == p {
             link = format!("<b>{link}</b>");
         } else {
             link = format!(r#"<a href="/leaderboard/{p}">{link}</a>"#);
         }
+        if let Some(owner) = owners.get(p) {
+            link.push_str(&owner.nav_badge());
+        }
         nav_links.push(link);
     }
     let nav_html = format!(
@@ -179,7 +250,7 @@ async fn render_problem_leaderboard(problem: &str, nocache: bool) -> Result<Stri
 
     // Fetch recent guesses for the problem to display.
     let t0 = std::time::Instant::now();
-    let guesses_html = recent_guesses(problem).await?;
+    let guesses_html = recent_guesses(problem, guess_page, prefs).await?;
     timings.push(("recent_guesses", t0.elapsed().as_millis()));
 
     // Fetch the latest correct guess for the problem, optionally bypassing the cache.
@@ -244,7 +315,7 @@ async fn render_problem_leaderboard(problem: &str, nocache: bool) -> Result<Stri
         r#"
 {nav}
 <div>
-  <h2>Problem: {problem}</h2>
+  <h2>Problem: {problem} <small><a href="/leaderboard/{problem}/history">rank history</a></small></h2>
 </div>
 <div id="chart" style="width: 100%; height: 500px;"></div>
 <div style="display: flex">
@@ -257,9 +328,12 @@ async fn render_problem_leaderboard(problem: &str, nocache: bool) -> Result<Stri
 <script src="https://cdn.jsdelivr.net/npm/chartjs-adapter-luxon"></script>
 <script>
 const history = {history};
+const since = "{since}";
 const problem = "{problem}";
 const perProblem = {per_problem_scores};
 const problemList = {problem_list};
+const chartScale = "{chart_scale}";
+const chartZone = "{chart_zone}";
 
 // === Chart.js Data Preparation ===
 
@@ -281,16 +355,19 @@ function colorFor(name) {{
 }}
 
 // Create the dataset objects for Chart.js.
-const datasets = Array.from(teamToData.entries()).map(([team, data]) => ({{
-  label: team,
-  data,
-  borderColor: team === 'Unagi' ? '#e53935' : colorFor(team),
-  backgroundColor: 'transparent',
-  spanGaps: false,
-  tension: 0.2,
-  pointRadius: 1,
-  borderWidth: team === 'Unagi' ? 3 : 1,
-}}));
+function makeDataset(team, data) {{
+  return {{
+    label: team,
+    data,
+    borderColor: team === 'Unagi' ? '#e53935' : colorFor(team),
+    backgroundColor: 'transparent',
+    spanGaps: false,
+    tension: 0.2,
+    pointRadius: 1,
+    borderWidth: team === 'Unagi' ? 3 : 1,
+  }};
+}}
+const datasets = Array.from(teamToData.entries()).map(([team, data]) => makeDataset(team, data));
 
 // === Chart.js Rendering ===
 
@@ -311,11 +388,12 @@ const chart = new Chart(canvas.getContext('2d'), {{
     }},
     scales: {{
       x: {{ type: 'time', time: {{ unit: 'minute' }} }},
-      // Use a logarithmic scale for scores, except for the global board.
-      y: ((problem === 'global') ? {{ beginAtZero: true }} : {{ type: 'logarithmic' }}),
+      // By default use a logarithmic scale for scores, except for the global board.
+      y: (chartScale === 'linear' || (chartScale === 'auto' && problem === 'global'))
+        ? {{ beginAtZero: true }} : {{ type: 'logarithmic' }},
     }},
     adapters: {{
-      date: {{ zone: 'Asia/Tokyo' }}, // Display times in JST.
+      date: {{ zone: chartZone }}, // Display times in the viewer's time zone.
     }},
   }},
 }});
@@ -327,62 +405,65 @@ function esc(s) {{
     '&':'&amp;','<':'&lt;','>':'&gt;','"':'&quot;','\'':'&#39;'
   }})[c]);
 }}
-const latest = [];
-for (const [team, data] of teamToData.entries()) {{
-  let last = null;
-  for (let i = data.length - 1; i >= 0; i--) {{
-    if (data[i][1] != null) {{ last = data[i][1]; break; }}
+function renderTable() {{
+  const latest = [];
+  for (const [team, data] of teamToData.entries()) {{
+    let last = null;
+    for (let i = data.length - 1; i >= 0; i--) {{
+      if (data[i][1] != null) {{ last = data[i][1]; break; }}
+    }}
+    if (last == null) continue;
+    latest.push({{ team, score: last }});
   }}
-  if (last == null) continue;
-  latest.push({{ team, score: last }});
-}}
-// Sort by score (ascending for problems, descending for global).
-if (problem === 'global') {{
-  latest.sort((a,b) => b.score - a.score);
-}} else {{
-  latest.sort((a,b) => a.score - b.score);
-}}
-// Compute rows with tie-aware ranks.
-let rows = '';
-let lastScore = null;
-let lastRank = 0;
-latest.forEach((r, i) => {{
-  const rank = (lastScore === r.score) ? lastRank : (i + 1);
-  lastScore = r.score; lastRank = rank;
-  // Skip zero scores.
-  if (r.score == 0) return;
-  const nameHtml = r.team === 'Unagi' ? `<strong>${{esc(r.team)}}</strong>` : esc(r.team);
-  const teamAttr = esc(r.team);
-  const nameLink = `<a href='#' data-team=\"${{teamAttr}}\">${{nameHtml}}</a>`;
-  let extraCols = '';
+  // Sort by score (ascending for problems, descending for global).
   if (problem === 'global') {{
-    const m = perProblem[r.team] || {{}};
-    extraCols = problemList.map(p => {{
-      const v = m[p];
-      return `<td style=\"padding:4px 8px; text-align:right;\">${{v ?? ''}}</td>`;
-    }}).join('');
+    latest.sort((a,b) => b.score - a.score);
+  }} else {{
+    latest.sort((a,b) => a.score - b.score);
   }}
-  rows += `<tr>
-    <td style=\"padding:4px 8px; text-align:right;\">${{rank}}</td>
-    <td style=\"padding:4px 8px;\">${{nameLink}}</td>
-    <td style=\"padding:4px 8px; text-align:right;\">${{r.score}}</td>${{extraCols}}
-  </tr>`;
-}});
-let headerExtra = '';
-if (problem === 'global') {{
-  headerExtra = problemList.map(p => `<th style=\"text-align:right; padding:4px 8px;\">${{esc(p)}}</th>`).join('');
+  // Compute rows with tie-aware ranks.
+  let rows = '';
+  let lastScore = null;
+  let lastRank = 0;
+  latest.forEach((r, i) => {{
+    const rank = (lastScore === r.score) ? lastRank : (i + 1);
+    lastScore = r.score; lastRank = rank;
+    // Skip zero scores.
+    if (r.score == 0) return;
+    const nameHtml = r.team === 'Unagi' ? `<strong>${{esc(r.team)}}</strong>` : esc(r.team);
+    const teamAttr = esc(r.team);
+    const nameLink = `<a href='#' data-team=\"${{teamAttr}}\">${{nameHtml}}</a>`;
+    let extraCols = '';
+    if (problem === 'global') {{
+      const m = perProblem[r.team] || {{}};
+      extraCols = problemList.map(p => {{
+        const v = m[p];
+        return `<td style=\"padding:4px 8px; text-align:right;\">${{v ?? ''}}</td>`;
+      }}).join('');
+    }}
+    rows += `<tr>
+      <td style=\"padding:4px 8px; text-align:right;\">${{rank}}</td>
+      <td style=\"padding:4px 8px;\">${{nameLink}}</td>
+      <td style=\"padding:4px 8px; text-align:right;\">${{r.score}}</td>${{extraCols}}
+    </tr>`;
+  }});
+  let headerExtra = '';
+  if (problem === 'global') {{
+    headerExtra = problemList.map(p => `<th style=\"text-align:right; padding:4px 8px;\">${{esc(p)}}</th>`).join('');
+  }}
+  document.getElementById('lb-table').innerHTML = `
+    <table style="border-collapse:collapse; font: 13px sans-serif; box-sizing: border-box;">
+      <thead>
+        <tr>
+          <th style="text-align:right; padding:4px 8px;">Rank</th>
+          <th style="text-align:left; padding:4px 8px;">Team</th>
+          <th style="text-align:right; padding:4px 8px; white-space: nowrap">Score</th>${{headerExtra}}
+        </tr>
+      </thead>
+      <tbody>${{rows}}</tbody>
+    </table>`;
 }}
-document.getElementById('lb-table').innerHTML = `
-  <table style="border-collapse:collapse; font: 13px sans-serif; box-sizing: border-box;">
-    <thead>
-      <tr>
-        <th style="text-align:right; padding:4px 8px;">Rank</th>
-        <th style="text-align:left; padding:4px 8px;">Team</th>
-        <th style="text-align:right; padding:4px 8px; white-space: nowrap">Score</th>${{headerExtra}}
-      </tr>
-    </thead>
-    <tbody>${{rows}}</tbody>
-  </table>`;
+renderTable();
 
 // === Table/Chart Interactivity ===
 
@@ -417,6 +498,30 @@ document.getElementById('lb-table').addEventListener('click', (ev) => {{
   const team = a.getAttribute('data-team');
   highlightTeam(team);
 }});
+
+// === Live Updates ===
+
+// Scores inserted after this page was rendered arrive as server-sent events.
+const source = new EventSource(`/leaderboard/${{encodeURIComponent(problem)}}/events?since=${{since}}`);
+// The stream only carries scores: keep reloading, less often, for the rest.
+source.addEventListener('open', () => {{
+  if (window.lbSlowReload) return;
+  window.lbSlowReload = true;
+  clearTimeout(window.lbReload);
+  window.lbReload = setTimeout(() => location.reload(), 30*60*1000);
+}});
+source.addEventListener('score', (ev) => {{
+  const {{ team, ts, score }} = JSON.parse(ev.data);
+  let data = teamToData.get(team);
+  if (!data) {{
+    data = [];
+    teamToData.set(team, data);
+    chart.data.datasets.push(makeDataset(team, data));
+  }}
+  data.push([parseTs(ts), score]);
+  chart.update('none');
+  renderTable();
+}});
 </script>
 <h3>Recent guesses submitted</h3>
 {guesses_html}
@@ -426,6 +531,9 @@ document.getElementById('lb-table').addEventListener('click', (ev) => {{
         nav = nav_html,
         problem = problem,
         history = serde_json::to_string(&history)?,
+        since = latest_timestamp(&history),
+        chart_scale = prefs.chart_scale.as_str(),
+        chart_zone = prefs.luxon_zone(),
     );
     // Append timing information at the end of the HTML body.
     let timings_html = format!(
@@ -546,8 +654,559 @@ async fn fetch_history(problem: &str) -> Result<HashMap<String, Vec<(String, i64
     Ok(history)
 }
 
+/// The newest timestamp in `history`, where the page's event stream resumes.
+fn latest_timestamp(history: &HashMap<String, Vec<(String, i64)>>) -> String {
+    history
+        .values()
+        .filter_map(|series| series.last())
+        .map(|(ts, _)| ts.clone())
+        .max()
+        .unwrap_or_default()
+}
+
+/// How often the poller of a problem looks for new score rows.
+const EVENT_POLL_INTERVAL: Duration = Duration::from_secs(10);
+
+/// New score rows of a problem: team, timestamp and score, oldest first.
+type ScoreRows = std::sync::Arc<Vec<(String, NaiveDateTime, i64)>>;
+
+/// Pollers of the problems with open event streams, one per problem.
+static SCORE_POLLERS: once_cell::sync::Lazy<
+    std::sync::Mutex<HashMap<String, tokio::sync::broadcast::Sender<ScoreRows>>>,
+> = once_cell::sync::Lazy::new(Default::default);
+
+#[derive(Deserialize)]
+pub struct EventsQuery {
+    /// Only rows after this "YYYYMMDD-HHMMSS" timestamp are sent; empty for
+    /// rows inserted after the stream opened.
+    #[serde(default)]
+    since: String,
+}
+
+/// A score row as a server-sent `score` event.
+fn score_event(team: &str, ts: &NaiveDateTime, score: i64) -> String {
+    format!(
+        "event: score\ndata: {}\n\n",
+        serde_json::json!({
+            "team": team,
+            "ts": ts.format("%Y%m%d-%H%M%S").to_string(),
+            "score": score,
+        })
+    )
+}
+
+/// Positive score rows of `problem` after `since`, oldest first.
+fn scores_since(problem: &str, since: NaiveDateTime) -> Result<Vec<(String, NaiveDateTime, i64)>> {
+    let rows = sql::select(
+        r#"
+        SELECT team_name, timestamp, score
+        FROM scores
+        WHERE problem = :problem AND timestamp > :since AND score > 0
+        ORDER BY timestamp, team_name
+        "#,
+        params! { "problem" => problem, "since" => since },
+    )?;
+    rows.iter()
+        .map(|r| Ok((r.at(0)?, r.at(1)?, r.at(2)?)))
+        .collect()
+}
+
+/// The newest score timestamp of `problem`, or now if it has none.
+fn newest_score(problem: &str) -> Result<NaiveDateTime> {
+    Ok(sql::cell::<Option<NaiveDateTime>>(
+        "SELECT MAX(timestamp) FROM scores WHERE problem = :problem",
+        params! { "problem" => problem },
+    )?
+    .flatten()
+    .unwrap_or_else(|| chrono::Utc::now().naive_utc()))
+}
+
+/// Subscribes to the new score rows of `problem`, starting its poller if no
+/// stream of the problem is open yet.
+fn subscribe_scores(problem: &str) -> tokio::sync::broadcast::Receiver<ScoreRows> {
+    let mut pollers = SCORE_POLLERS.lock().unwrap();
+    if let Some(tx) = pollers.get(problem) {
+        return tx.subscribe();
+    }
+    let (tx, rx) = tokio::sync::broadcast::channel(16);
+    pollers.insert(problem.to_string(), tx.clone());
+    let problem = problem.to_string();
+    actix_web::rt::spawn(async move {
+        if let Err(e) = poll_scores(&problem, &tx).await {
+            eprintln!("leaderboard poller for {} stopped: {:#}", problem, e);
+            // Dropping the senders ends the streams; their clients reconnect.
+            let mut pollers = SCORE_POLLERS.lock().unwrap();
+            if pollers.get(&problem).is_some_and(|t| t.same_channel(&tx)) {
+                pollers.remove(&problem);
+            }
+        }
+    });
+    rx
+}
+
+/// Broadcasts the new score rows of `problem` every `EVENT_POLL_INTERVAL`,
+/// an empty batch if there are none, until no stream is left.
+async fn poll_scores(problem: &str, tx: &tokio::sync::broadcast::Sender<ScoreRows>) -> Result<()> {
+    let mut since = {
+        let problem = problem.to_string();
+        web::block(move || newest_score(&problem)).await??
+    };
+    loop {
+        tokio::time::sleep(EVENT_POLL_INTERVAL).await;
+        // Checked under the lock, so that no stream subscribes in between.
+        if tx.receiver_count() == 0 {
+            let mut pollers = SCORE_POLLERS.lock().unwrap();
+            if tx.receiver_count() == 0 {
+                pollers.remove(problem);
+                return Ok(());
+            }
+        }
+        let rows = {
+            let problem = problem.to_string();
+            web::block(move || scores_since(&problem, since)).await??
+        };
+        if let Some((_, ts, _)) = rows.last() {
+            since = since.max(*ts);
+        }
+        let _ = tx.send(ScoreRows::new(rows));
+    }
+}
+
+/// Sends score events for `problem` after `since` (or after the stream
+/// opened) to `tx` until the client goes away.
+async fn send_score_events(
+    problem: String,
+    mut since: Option<NaiveDateTime>,
+    tx: tokio::sync::mpsc::Sender<web::Bytes>,
+) -> Result<()> {
+    use tokio::sync::broadcast::error::RecvError;
+
+    // Subscribe before reading the backlog, so that no row falls in between.
+    let mut rows_rx = subscribe_scores(&problem);
+    let mut backlog = ScoreRows::default();
+    if let Some(since) = since {
+        let problem = problem.clone();
+        backlog = ScoreRows::new(web::block(move || scores_since(&problem, since)).await??);
+    }
+    let mut rows = backlog;
+    loop {
+        // A comment line keeps proxies from timing the stream out and tells
+        // us when the client has gone.
+        let mut chunk = String::from(": keepalive\n\n");
+        for (team, ts, score) in rows.iter() {
+            if since.is_some_and(|since| *ts <= since) {
+                continue;
+            }
+            chunk.push_str(&score_event(team, ts, *score));
+            since = Some(*ts);
+        }
+        if tx.send(web::Bytes::from(chunk)).await.is_err() {
+            return Ok(());
+        }
+        rows = loop {
+            match rows_rx.recv().await {
+                Ok(rows) => break rows,
+                Err(RecvError::Lagged(_)) => continue,
+                Err(RecvError::Closed) => anyhow::bail!("the score poller stopped"),
+            }
+        };
+    }
+}
+
+/// Handler for `GET /leaderboard/{problem}/events`: a server-sent event stream
+/// of score rows as they are inserted.
+pub async fn events(
+    path: web::Path<ProblemPath>,
+    query: web::Query<EventsQuery>,
+) -> impl Responder {
+    let since = if query.since.is_empty() {
+        None
+    } else {
+        match NaiveDateTime::parse_from_str(&query.since, "%Y%m%d-%H%M%S") {
+            Ok(ts) => Some(ts),
+            Err(_) => {
+                return HttpResponse::BadRequest().body("since must be YYYYMMDD-HHMMSS");
+            }
+        }
+    };
+    let problem = path.into_inner().problem;
+    let (tx, rx) = tokio::sync::mpsc::channel(4);
+    actix_web::rt::spawn(async move {
+        if let Err(e) = send_score_events(problem.clone(), since, tx).await {
+            eprintln!("leaderboard events for {} stopped: {:#}", problem, e);
+        }
+    });
+    HttpResponse::Ok()
+        .content_type("text/event-stream")
+        .append_header(("Cache-Control", "no-cache"))
+        .append_header(("X-Accel-Buffering", "no"))
+        .body(crate::www::handlers::template::ChannelBody(rx))
+}
+
+/// One change of our standing on a problem's board.
+#[derive(Debug, PartialEq)]
+struct RankPoint {
+    ts: NaiveDateTime,
+    /// 1 + the number of teams with a strictly better score.
+    rank: usize,
+    /// Teams with a positive score at `ts`.
+    teams: usize,
+    score: i64,
+    /// Our score got better at `ts` (as opposed to others overtaking us).
+    improved: bool,
+}
+
+/// Our rank over time from `(team, timestamp, score)` rows, oldest first.
+/// A team keeps its last score until it has a newer row. A point is emitted
+/// whenever our rank, our score or the number of teams changes.
+fn rank_history(
+    rows: &[(String, NaiveDateTime, i64)],
+    team: &str,
+    higher_is_better: bool,
+) -> Vec<RankPoint> {
+    let better = |a: i64, b: i64| if higher_is_better { a > b } else { a < b };
+    let mut latest: HashMap<&str, i64> = HashMap::new();
+    let mut points: Vec<RankPoint> = vec![];
+    let mut i = 0;
+    while i < rows.len() {
+        let ts = rows[i].1;
+        let prev_score = latest.get(team).copied();
+        while i < rows.len() && rows[i].1 == ts {
+            let (name, _, score) = &rows[i];
+            latest.insert(name, *score);
+            i += 1;
+        }
+        let Some(&score) = latest.get(team) else {
+            continue;
+        };
+        let rank = 1 + latest.values().filter(|&&s| better(s, score)).count();
+        let teams = latest.len();
+        if let Some(last) = points.last()
+            && (last.rank, last.score, last.teams) == (rank, score, teams)
+        {
+            continue;
+        }
+        let improved = prev_score.is_some_and(|p| better(score, p));
+        points.push(RankPoint {
+            ts,
+            rank,
+            teams,
+            score,
+            improved,
+        });
+    }
+    points
+}
+
+/// The correct guess behind an improvement of our score.
+struct ImprovementSource {
+    guess_id: i64,
+    guess_ts: NaiveDateTime,
+    /// `X-Unagi-Solver` of the guess (the agent name for executor tasks).
+    solver: Option<String>,
+    task_id: Option<i64>,
+}
+
+/// The last correct guess for `problem` in `(after, until]`, and the executor
+/// task of its solver that was running at the time, if any.
+fn improvement_source(
+    problem: &str,
+    after: Option<NaiveDateTime>,
+    until: NaiveDateTime,
+) -> Result<Option<ImprovementSource>> {
+    let Some(row) = sql::row(
+        r#"
+        SELECT g.api_log_id,
+               g.api_log_created,
+               JSON_UNQUOTE(JSON_EXTRACT(g.api_log_metadata, '$.solver')) AS solver
+        FROM api_logs s
+        JOIN api_logs g
+          ON g.api_log_select_id = s.api_log_id
+        WHERE s.api_log_path = '/select'
+          AND s.api_log_request__problem_name = :problem
+          AND g.api_log_path = '/guess'
+          AND g.api_log_response_code = 200
+          AND JSON_EXTRACT(g.api_log_response, '$.correct') = true
+          AND g.api_log_created > :after
+          AND g.api_log_created <= :until
+        ORDER BY g.api_log_id DESC
+        LIMIT 1
+        "#,
+        params! {
+            "problem" => problem,
+            "after" => after.unwrap_or_default(),
+            "until" => until,
+        },
+    )?
+    else {
+        return Ok(None);
+    };
+    let guess_ts: NaiveDateTime = row.at(1)?;
+    let solver: Option<String> = row.get_option("solver")?;
+    // The task that finished at or after the guess and had started before it.
+    let task_id = match &solver {
+        Some(agent) => sql::cell::<i64>(
+            r#"
+            SELECT t.task_id
+            FROM tasks t
+            JOIN agents a ON a.agent_id = t.agent_id
+            WHERE a.agent_name = :agent
+              AND t.problem_name = :problem
+              AND t.task_updated >= :ts
+              AND DATE_SUB(t.task_updated,
+                           INTERVAL (COALESCE(t.task_duration_ms, 0) + 1000) * 1000 MICROSECOND) <= :ts
+            ORDER BY t.task_updated
+            LIMIT 1
+            "#,
+            params! { "agent" => agent, "problem" => problem, "ts" => guess_ts },
+        )?,
+        None => None,
+    };
+    Ok(Some(ImprovementSource {
+        guess_id: row.at(0)?,
+        guess_ts,
+        solver,
+        task_id,
+    }))
+}
+
+/// Handler for `GET /leaderboard/{problem}/history`: our rank over time, with
+/// each improvement of our score traced to the guess, agent and task behind it.
+pub async fn history(req: HttpRequest, path: web::Path<ProblemPath>) -> impl Responder {
+    let prefs = Prefs::from_request(&req);
+    crate::www::handlers::template::to_response(render_rank_history(&path.problem, &prefs))
+}
+
+fn render_rank_history(problem: &str, prefs: &Prefs) -> Result<String> {
+    // Only the rows where a team's score changed; a team keeps its score in
+    // between, so these are enough to rank every snapshot.
+    let rows = sql::select(
+        r#"
+        SELECT team_name, timestamp, score
+        FROM (
+          SELECT
+            team_name,
+            timestamp,
+            score,
+            LAG(score) OVER (PARTITION BY team_name ORDER BY timestamp) AS prev_score
+          FROM scores
+          WHERE problem = :problem
+            AND score > 0
+        ) t
+        WHERE score != prev_score OR prev_score IS NULL
+        ORDER BY timestamp, team_name
+        "#,
+        params! { "problem" => problem },
+    )?;
+    let rows = rows
+        .iter()
+        .map(|r| Ok((r.at(0)?, r.at(1)?, r.at(2)?)))
+        .collect::<Result<Vec<(String, NaiveDateTime, i64)>>>()?;
+    let points = rank_history(&rows, "Unagi", problem == "global");
+
+    // Guesses are per problem, so the global board has no sources.
+    let mut sources = vec![];
+    for (i, p) in points.iter().enumerate() {
+        let source = if p.improved && problem != "global" {
+            improvement_source(problem, i.checked_sub(1).map(|j| points[j].ts), p.ts)?
+        } else {
+            None
+        };
+        sources.push(source);
+    }
+
+    let source_label = |s: &ImprovementSource| {
+        format!(
+            "{} (guess #{})",
+            s.solver.as_deref().unwrap_or("unknown solver"),
+            s.guess_id
+        )
+    };
+    let chart_points = points
+        .iter()
+        .zip(&sources)
+        .map(|(p, s)| {
+            serde_json::json!({
+                "ts": p.ts.format("%Y%m%d-%H%M%S").to_string(),
+                "rank": p.rank,
+                "teams": p.teams,
+                "score": p.score,
+                "improved": p.improved,
+                "source": s.as_ref().map(source_label),
+            })
+        })
+        .collect::<Vec<_>>();
+
+    let mut table = String::from(
+        r#"<table style="border-collapse:collapse;font-size:13px;">
+        <tr><th>Time</th><th>Rank</th><th>Score</th><th>Source</th></tr>"#,
+    );
+    for (i, (p, s)) in points.iter().zip(&sources).enumerate().rev() {
+        let rank_change = match i.checked_sub(1).map(|j| points[j].rank) {
+            Some(prev) if prev > p.rank => {
+                format!(" <span style=\"color:green;\">▲{}</span>", prev - p.rank)
+            }
+            Some(prev) if prev < p.rank => {
+                format!(" <span style=\"color:red;\">▼{}</span>", p.rank - prev)
+            }
+            _ => String::new(),
+        };
+        let source = match s {
+            Some(s) => {
+                let mut cell = format!(
+                    "{} at {}",
+                    escape_html(&source_label(s)),
+                    prefs.format_utc(s.guess_ts)
+                );
+                if let Some(task_id) = s.task_id {
+                    write!(
+                        cell,
+                        r#" (<a href="/task?task_id={0}">task #{0}</a>)"#,
+                        task_id
+                    )?;
+                }
+                cell
+            }
+            None if p.improved => "(no guess found)".to_string(),
+            None => String::new(),
+        };
+        write!(
+            table,
+            r#"<tr><td>{}</td><td style="text-align:right;">{} / {}{}</td><td style="text-align:right;">{}</td><td>{}</td></tr>"#,
+            prefs.format_utc(p.ts),
+            p.rank,
+            p.teams,
+            rank_change,
+            if p.improved {
+                format!("<b>{}</b>", p.score)
+            } else {
+                p.score.to_string()
+            },
+            source,
+        )?;
+    }
+    table.push_str("</table>");
+
+    let body = format!(
+        r#"
+<div><a href="/leaderboard/{problem}">&laquo; Leaderboard</a></div>
+<div id="chart" style="width: 100%; height: 400px;"></div>
+<script src="https://cdn.jsdelivr.net/npm/luxon@3/build/global/luxon.min.js"></script>
+<script src="https://cdn.jsdelivr.net/npm/chart.js"></script>
+<script src="https://cdn.jsdelivr.net/npm/chartjs-adapter-luxon"></script>
+<script>
+const points = {points};
+function parseTs(ts) {{
+  const y = +ts.slice(0,4), mo = +ts.slice(4,6)-1, d = +ts.slice(6,8);
+  const h = +ts.slice(9,11), mi = +ts.slice(11,13), s = +ts.slice(13,15);
+  return new Date(Date.UTC(y, mo, d, h, mi, s));
+}}
+const canvas = document.createElement('canvas');
+document.getElementById('chart').appendChild(canvas);
+new Chart(canvas.getContext('2d'), {{
+  type: 'line',
+  data: {{
+    datasets: [{{
+      label: 'Rank',
+      data: points.map(p => ({{ x: parseTs(p.ts), y: p.rank, p }})),
+      borderColor: '#e53935',
+      backgroundColor: '#e53935',
+      stepped: true,
+      // Improvements of our own score are drawn as larger points.
+      pointRadius: points.map(p => p.improved ? 5 : 1),
+    }}],
+  }},
+  options: {{
+    responsive: true,
+    maintainAspectRatio: false,
+    plugins: {{
+      legend: {{ display: false }},
+      tooltip: {{
+        callbacks: {{
+          label: (ctx) => {{
+            const p = ctx.raw.p;
+            const lines = [`rank ${{p.rank}} / ${{p.teams}}, score ${{p.score}}`];
+            if (p.source) lines.push(p.source);
+            return lines;
+          }},
+        }},
+      }},
+    }},
+    scales: {{
+      x: {{ type: 'time', time: {{ unit: 'hour' }} }},
+      y: {{ reverse: true, min: 1, ticks: {{ precision: 0 }} }},
+    }},
+    adapters: {{ date: {{ zone: "{chart_zone}" }} }},
+  }},
+}});
+</script>
+<h3>Rank changes</h3>
+{table}
+"#,
+        problem = problem,
+        points = serde_json::to_string(&chart_points)?,
+        chart_zone = prefs.luxon_zone(),
+    );
+    Ok(html_page(&format!("Rank history - {problem}"), &body, ""))
+}
+
+/// Number of recent guesses shown per page (problem boards / global board).
+const GUESSES_PER_PAGE: usize = 20;
+const GLOBAL_GUESSES_PER_PAGE: usize = 100;
+/// Deepest page served; older guesses are rarely useful and deep OFFSETs are slow.
+const MAX_GUESS_PAGE: usize = 50;
+
+/// Number of accepted guesses for a problem (or all problems for `global`).
+#[cached(
+    result = true,
+    time = 60,
+    key = "String",
+    convert = "{problem.to_string()}",
+    sync_writes = "by_key"
+)]
+fn guess_count(problem: &str) -> Result<usize> {
+    let count = if problem != "global" {
+        sql::cell::<i64>(
+            "
+            SELECT COUNT(*)
+            FROM api_logs s
+            JOIN api_logs g
+              ON g.api_log_select_id = s.api_log_id
+            WHERE s.api_log_path = '/select'
+              AND s.api_log_request__problem_name = :problem
+              AND g.api_log_path = '/guess'
+              AND g.api_log_response_code = 200",
+            params! { "problem" => problem },
+        )?
+    } else {
+        sql::cell::<i64>(
+            "
+            SELECT COUNT(*)
+            FROM api_logs
+            WHERE api_log_path = '/guess'
+              AND api_log_response_code = 200",
+            params::Params::Empty,
+        )?
+    };
+    Ok(count.unwrap_or(0) as usize)
+}
+
 /// 最近の提出（guess）を取得してHTMLとして返す関数
-async fn recent_guesses(problem: &str) -> Result<String> {
+///
+/// `page` is 1-based. Guess rows are picked first (by path, newest first) and only the
+/// selected page is joined with its `/select` row, so the cost does not grow with the
+/// size of `api_logs`.
+async fn recent_guesses(problem: &str, page: usize, prefs: &Prefs) -> Result<String> {
+    let per_page = if problem == "global" {
+        GLOBAL_GUESSES_PER_PAGE
+    } else {
+        GUESSES_PER_PAGE
+    };
+    let total = guess_count(problem)?;
+    let num_pages = total.div_ceil(per_page).clamp(1, MAX_GUESS_PAGE);
+    let page = page.clamp(1, num_pages);
+    let offset = (page - 1) * per_page;
+
     // 直近の提出（guess）を取得
     let rows = if problem != "global" {
         sql::select(
@@ -556,16 +1215,16 @@ async fn recent_guesses(problem: &str) -> Result<String> {
                g.api_log_created AS ts,
                s.api_log_request__problem_name AS problem,
                JSON_VALUE(g.api_log_response, '$.correct' RETURNING UNSIGNED) AS correct
-        FROM api_logs g
-        JOIN api_logs s
+        FROM api_logs s
+        JOIN api_logs g
           ON g.api_log_select_id = s.api_log_id
-            AND g.api_log_path = '/guess'
-            AND s.api_log_path = '/select'
-        WHERE s.api_log_request__problem_name = :problem
+        WHERE s.api_log_path = '/select'
+          AND s.api_log_request__problem_name = :problem
+          AND g.api_log_path = '/guess'
           AND g.api_log_response_code = 200
         ORDER BY g.api_log_id DESC
-        LIMIT 20",
-            params! { "problem" => problem },
+        LIMIT :limit OFFSET :offset",
+            params! { "problem" => problem, "limit" => per_page, "offset" => offset },
         )?
     } else {
         sql::select(
@@ -574,15 +1233,18 @@ async fn recent_guesses(problem: &str) -> Result<String> {
                g.api_log_created AS ts,
                s.api_log_request__problem_name AS problem,
                JSON_VALUE(g.api_log_response, '$.correct' RETURNING UNSIGNED) AS correct
-        FROM api_logs g
+        FROM (
+            SELECT api_log_id, api_log_created, api_log_select_id, api_log_response
+            FROM api_logs
+            WHERE api_log_path = '/guess'
+              AND api_log_response_code = 200
+            ORDER BY api_log_id DESC
+            LIMIT :limit OFFSET :offset
+        ) g
         JOIN api_logs s
-          ON g.api_log_select_id = s.api_log_id
-            AND g.api_log_path = '/guess'
-            AND s.api_log_path = '/select'
-        WHERE g.api_log_response_code = 200
-        ORDER BY g.api_log_id DESC
-        LIMIT 100",
-            params::Params::Empty,
+          ON s.api_log_id = g.api_log_select_id
+        ORDER BY g.api_log_id DESC",
+            params! { "limit" => per_page, "offset" => offset },
         )?
     };
 
@@ -601,13 +1263,32 @@ async fn recent_guesses(problem: &str) -> Result<String> {
             w,
             r#"<tr><td>{}</td><td title="{}">{}</td><td>{}</td><td>{}</td></tr>"#,
             id,
-            ts.and_local_timezone(_TZ).unwrap().naive_local(),
+            prefs.format_utc(ts),
             ts.signed_duration_since(now).humanize(),
             problem,
             if correct { "✅" } else { "❌" }
         )?;
     }
     w.push_str("</table>");
+
+    // Paging controls.
+    w.push_str("<div class=\"pager\">");
+    if page > 1 {
+        write!(
+            w,
+            "<a href=\"/leaderboard/{problem}?guess_page={}\">&laquo; 前のページ</a> ",
+            page - 1
+        )?;
+    }
+    write!(w, "{page} / {num_pages} ({total} guesses)")?;
+    if page < num_pages {
+        write!(
+            w,
+            " <a href=\"/leaderboard/{problem}?guess_page={}\">次のページ &raquo;</a>",
+            page + 1
+        )?;
+    }
+    w.push_str("</div>");
     Ok(w)
 }
 
@@ -707,3 +1388,61 @@ fn last_correct_guess(problem: &str) -> Result<String> {
     }
     Ok(w)
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn score_events_match_page_timestamps() {
+        let ts = NaiveDateTime::parse_from_str("20250906-123456", "%Y%m%d-%H%M%S").unwrap();
+        assert_eq!(
+            score_event("Unagi", &ts, 42),
+            "event: score\ndata: {\"team\":\"Unagi\",\"ts\":\"20250906-123456\",\"score\":42}\n\n"
+        );
+        let history = HashMap::from([
+            (
+                "a".to_string(),
+                vec![
+                    ("20250906-120000".to_string(), 5),
+                    ("20250906-130000".to_string(), 4),
+                ],
+            ),
+            ("b".to_string(), vec![("20250906-125959".to_string(), 3)]),
+        ]);
+        assert_eq!(latest_timestamp(&history), "20250906-130000");
+        assert_eq!(latest_timestamp(&HashMap::new()), "");
+    }
+
+    #[test]
+    fn rank_history_tracks_overtakes_and_improvements() {
+        let ts = |s: &str| NaiveDateTime::parse_from_str(s, "%Y%m%d-%H%M%S").unwrap();
+        let row = |team: &str, t: &str, score| (team.to_string(), ts(t), score);
+        let rows = vec![
+            row("a", "20250906-100000", 50),
+            row("Unagi", "20250906-110000", 40),
+            row("b", "20250906-110000", 30),
+            row("a", "20250906-120000", 20),
+            row("Unagi", "20250906-130000", 20),
+            row("b", "20250906-140000", 30),
+        ];
+        let points = rank_history(&rows, "Unagi", false);
+        let summary = points
+            .iter()
+            .map(|p| (p.ts, p.rank, p.teams, p.score, p.improved))
+            .collect::<Vec<_>>();
+        assert_eq!(
+            summary,
+            vec![
+                (ts("20250906-110000"), 2, 3, 40, false),
+                (ts("20250906-120000"), 3, 3, 40, false),
+                (ts("20250906-130000"), 1, 3, 20, true),
+            ]
+        );
+        // Higher is better on the global board.
+        let points = rank_history(&rows[1..3], "Unagi", true);
+        assert_eq!(points[0].rank, 1);
+        assert_eq!(rank_history(&rows[1..3], "Unagi", false)[0].rank, 2);
+        assert!(rank_history(&rows[..1], "Unagi", false).is_empty());
+    }
+}
@@ -0,0 +1,339 @@
+//! # Mock Aedificium API
+//!
+//! `/select`, `/explore` and `/guess` served from a [`LocalJudge`], so that
+//! `RemoteJudge`, the retries of `api` and the lock can be run end to end
+//! with `AEDIFICIUM_ENDPOINT=http://localhost:PORT` (see
+//! `bin/mock_aedificium.rs`) without spending queries on the contest server.
+//!
+//! The answers follow the contest server: `/explore` echoes every label
+//! written with `[k]` and counts `queryCount` over the session, and `/guess`
+//! ends the session. Maps are `random2` for the single-layer problems and
+//! `random_2layers` / `random_3layers` for the others, seeded by the server
+//! seed and the number of `/select`s so far. Every `fail_every`-th request
+//! answers 503 without touching the session, to exercise the retries.
+
+use std::sync::Mutex;
+
+use actix_web::{HttpResponse, Responder, web};
+use serde::Deserialize;
+use serde_json::json;
+
+use crate::api;
+use crate::judge::{Guess, LocalJudge, explore_cost, parse_plan};
+use crate::problems;
+
+/// Problems whose maps are two copies of a smaller map.
+const TWO_LAYERS: [&str; 5] = ["aleph", "beth", "gimel", "daleth", "he"];
+/// Problems whose maps are three copies of a smaller map.
+const THREE_LAYERS: [&str; 5] = ["vau", "zain", "hhet", "teth", "iod"];
+
+/// Shared state of the mock server.
+pub struct MockApi {
+    seed: u64,
+    /// Every `fail_every`-th request fails with 503; 0 never.
+    fail_every: usize,
+    inner: Mutex<Inner>,
+}
+
+#[derive(Default)]
+struct Inner {
+    /// The selected problem's map, until its guess.
+    judge: Option<LocalJudge>,
+    query_count: usize,
+    selects: u64,
+    requests: usize,
+}
+
+#[derive(Deserialize)]
+pub struct SelectRequest {
+    #[serde(rename = "problemName")]
+    problem_name: String,
+}
+
+#[derive(Deserialize)]
+pub struct ExploreRequest {
+    plans: Vec<String>,
+}
+
+#[derive(Deserialize)]
+pub struct GuessRequest {
+    map: api::Map,
+}
+
+/// The `LocalJudge` map type used for a problem.
+fn map_type(problem: &str) -> &'static str {
+    if TWO_LAYERS.contains(&problem) {
+        "random_2layers"
+    } else if THREE_LAYERS.contains(&problem) {
+        "random_3layers"
+    } else {
+        "random2"
+    }
+}
+
+fn bad_request(message: impl std::fmt::Display) -> HttpResponse {
+    HttpResponse::BadRequest().json(json!({ "error": message.to_string() }))
+}
+
+impl MockApi {
+    pub fn new(seed: u64, fail_every: usize) -> Self {
+        MockApi {
+            seed,
+            fail_every,
+            inner: Mutex::new(Inner::default()),
+        }
+    }
+
+    /// Counts a request; `Some` is the injected failure to answer with.
+    fn inject_failure(&self, inner: &mut Inner) -> Option<HttpResponse> {
+        inner.requests += 1;
+        (self.fail_every > 0 && inner.requests.is_multiple_of(self.fail_every)).then(|| {
+            HttpResponse::ServiceUnavailable().json(json!({ "error": "injected failure" }))
+        })
+    }
+
+    fn select(&self, req: &SelectRequest) -> HttpResponse {
+        let mut inner = self.inner.lock().unwrap();
+        if let Some(res) = self.inject_failure(&mut inner) {
+            return res;
+        }
+        let Some(problem) = problems::get_problem(&req.problem_name) else {
+            return bad_request(format!("unknown problem: {}", req.problem_name));
+        };
+        let seed = self.seed.wrapping_add(inner.selects);
+        inner.selects += 1;
+        inner.judge = Some(LocalJudge::new(
+            map_type(&problem.problem),
+            problem.size,
+            seed,
+        ));
+        inner.query_count = 0;
+        eprintln!("mock: selected {} (seed {})", problem.problem, seed);
+        HttpResponse::Ok().json(json!({ "problemName": problem.problem }))
+    }
+
+    fn explore(&self, req: &ExploreRequest) -> HttpResponse {
+        let mut inner = self.inner.lock().unwrap();
+        if let Some(res) = self.inject_failure(&mut inner) {
+            return res;
+        }
+        let Some(judge) = &inner.judge else {
+            return bad_request("no problem selected");
+        };
+        let mut plans = vec![];
+        for plan in &req.plans {
+            if !plan
+                .chars()
+                .all(|c| c.is_ascii_digit() || c == '[' || c == ']')
+            {
+                return bad_request(format!("invalid plan: {}", plan));
+            }
+            let plan = parse_plan(plan);
+            if plan.len() > 18 * judge.rooms.len() {
+                return bad_request("plan too long");
+            }
+            let rules = judge.rules();
+            if plan
+                .iter()
+                .any(|&(k, e)| e >= rules.doors || k.is_some_and(|k| k >= rules.labels))
+            {
+                return bad_request("door or label out of range");
+            }
+            plans.push(plan);
+        }
+        let results: Vec<Vec<usize>> = plans
+            .iter()
+            .map(|plan| {
+                let mut labels = judge.rooms.clone();
+                let mut u = judge.starting_room;
+                let mut route = vec![labels[u]];
+                for &(rewrite, door) in plan {
+                    if let Some(k) = rewrite {
+                        labels[u] = k;
+                        route.push(k);
+                    }
+                    u = judge.graph[u][door];
+                    route.push(labels[u]);
+                }
+                route
+            })
+            .collect();
+        inner.query_count += explore_cost(&plans);
+        HttpResponse::Ok().json(json!({
+            "results": results,
+            "queryCount": inner.query_count,
+        }))
+    }
+
+    fn guess(&self, req: &GuessRequest) -> HttpResponse {
+        let mut inner = self.inner.lock().unwrap();
+        if let Some(res) = self.inject_failure(&mut inner) {
+            return res;
+        }
+        if let Err(e) = api::validate_map(&req.map) {
+            return bad_request(format!("{:#}", e));
+        }
+        // `check_guess` insists on an undirected graph.
+        let guess = match Guess::from_map(&req.map) {
+            Ok(guess) => guess,
+            Err(e) => return bad_request(e),
+        };
+        let Some(judge) = inner.judge.take() else {
+            return bad_request("no problem selected");
+        };
+        let correct = judge.check_guess(&guess).is_ok();
+        eprintln!(
+            "mock: guess {} after {} queries",
+            if correct { "correct" } else { "wrong" },
+            inner.query_count
+        );
+        HttpResponse::Ok().json(json!({ "correct": correct }))
+    }
+}
+
+/// Handles `/select`.
+pub async fn post_select(
+    state: web::Data<MockApi>,
+    req: web::Json<SelectRequest>,
+) -> impl Responder {
+    state.select(&req)
+}
+
+/// Handles `/explore`.
+pub async fn post_explore(
+    state: web::Data<MockApi>,
+    req: web::Json<ExploreRequest>,
+) -> impl Responder {
+    state.explore(&req)
+}
+
+/// Handles `/guess`.
+pub async fn post_guess(state: web::Data<MockApi>, req: web::Json<GuessRequest>) -> impl Responder {
+    state.guess(&req)
+}
+
+/// Registers the mock routes at `/select`, `/explore` and `/guess`.
+pub fn configure(cfg: &mut web::ServiceConfig) {
+    cfg.route("/select", web::post().to(post_select))
+        .route("/explore", web::post().to(post_explore))
+        .route("/guess", web::post().to(post_guess));
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use actix_web::{App, test};
+
+    fn post(uri: &str, body: serde_json::Value) -> test::TestRequest {
+        test::TestRequest::post().uri(uri).set_json(body)
+    }
+
+    /// The judge's map, with the doors of each pair of rooms matched up.
+    fn true_map(judge: &LocalJudge) -> serde_json::Value {
+        let n = judge.rooms.len();
+        let doors = judge.rules().doors;
+        let mut done = vec![[false; 6]; n];
+        let mut connections = vec![];
+        for u in 0..n {
+            for d in 0..doors {
+                if done[u][d] {
+                    continue;
+                }
+                done[u][d] = true;
+                let v = judge.graph[u][d];
+                let f = (0..doors)
+                    .find(|&f| !done[v][f] && judge.graph[v][f] == u)
+                    .unwrap_or(d);
+                done[v][f] = true;
+                connections.push(json!({
+                    "from": { "room": u, "door": d },
+                    "to": { "room": v, "door": f },
+                }));
+            }
+        }
+        json!({
+            "rooms": judge.rooms,
+            "startingRoom": judge.starting_room,
+            "connections": connections,
+        })
+    }
+
+    #[actix_web::test]
+    async fn mock_sessions_follow_the_contest_server() {
+        let state = web::Data::new(MockApi::new(7, 0));
+        let app = test::init_service(App::new().app_data(state.clone()).configure(configure)).await;
+
+        let res = test::call_service(
+            &app,
+            post("/explore", json!({ "id": "x", "plans": [] })).to_request(),
+        )
+        .await;
+        assert_eq!(res.status(), 400);
+
+        let res: serde_json::Value = test::call_and_read_body_json(
+            &app,
+            post("/select", json!({ "id": "x", "problemName": "probatio" })).to_request(),
+        )
+        .await;
+        assert_eq!(res["problemName"], "probatio");
+
+        // Marks are echoed, and the query count adds one per call.
+        let res: serde_json::Value = test::call_and_read_body_json(
+            &app,
+            post("/explore", json!({ "id": "x", "plans": ["0[1]23", "45"] })).to_request(),
+        )
+        .await;
+        assert_eq!(res["results"][0].as_array().unwrap().len(), 5);
+        assert_eq!(res["results"][0][2], 1);
+        assert_eq!(res["results"][1].as_array().unwrap().len(), 3);
+        assert_eq!(res["queryCount"], 3);
+
+        // The true map is accepted and ends the session.
+        let map = true_map(state.inner.lock().unwrap().judge.as_ref().unwrap());
+        let res: serde_json::Value = test::call_and_read_body_json(
+            &app,
+            post("/guess", json!({ "id": "x", "map": map })).to_request(),
+        )
+        .await;
+        assert_eq!(res["correct"], true);
+
+        // A door connected twice is refused rather than judged.
+        let res = test::call_service(
+            &app,
+            post("/select", json!({ "id": "x", "problemName": "probatio" })).to_request(),
+        )
+        .await;
+        assert_eq!(res.status(), 200);
+        let mut twice = map.clone();
+        let first = twice["connections"][0].clone();
+        twice["connections"].as_array_mut().unwrap().push(first);
+        let res = test::call_service(
+            &app,
+            post("/guess", json!({ "id": "x", "map": twice })).to_request(),
+        )
+        .await;
+        assert_eq!(res.status(), 400);
+        let res: serde_json::Value = test::call_and_read_body_json(
+            &app,
+            post("/guess", json!({ "id": "x", "map": map })).to_request(),
+        )
+        .await;
+        assert_eq!(res["correct"], false);
+        let res = test::call_service(
+            &app,
+            post("/guess", json!({ "id": "x", "map": map })).to_request(),
+        )
+        .await;
+        assert_eq!(res.status(), 400);
+    }
+
+    #[actix_web::test]
+    async fn failures_are_injected() {
+        let state = web::Data::new(MockApi::new(7, 2));
+        let app = test::init_service(App::new().app_data(state).configure(configure)).await;
+        let select = || post("/select", json!({ "id": "x", "problemName": "aleph" })).to_request();
+        assert_eq!(test::call_service(&app, select()).await.status(), 200);
+        assert_eq!(test::call_service(&app, select()).await.status(), 503);
+        assert_eq!(test::call_service(&app, select()).await.status(), 200);
+    }
+}
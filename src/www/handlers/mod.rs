@@ -13,8 +13,17 @@ pub mod cron;
 // pub mod submissions;
 pub mod template;
 // pub mod visualize;
+pub mod agents;
 pub mod api;
+pub mod costs;
+pub mod digest;
+pub mod explores;
 pub mod leaderboard;
+pub mod mock_api;
+pub mod owners;
+pub mod prefs;
+pub mod queue;
+pub mod replay;
 pub mod task;
 pub mod tasks;
 pub mod unlock;
@@ -0,0 +1,186 @@
+//! # Problem Owners
+//!
+//! Who is working on which problem and what they are waiting on, e.g.
+//! "iwiwi: waiting on 90-room encode". Shown next to each problem in the
+//! leaderboard navigation and edited at `/owners`.
+//!
+//! ```sql
+//! CREATE TABLE problem_owners (
+//!   problem_name VARCHAR(64) NOT NULL PRIMARY KEY,
+//!   owner_user VARCHAR(64) NOT NULL,
+//!   owner_note TEXT NOT NULL,
+//!   owner_updated TIMESTAMP NOT NULL DEFAULT CURRENT_TIMESTAMP ON UPDATE CURRENT_TIMESTAMP
+//! );
+//! ```
+
+use actix_web::{HttpRequest, HttpResponse, Responder, web};
+use anyhow::{Result, bail};
+use chrono::NaiveDateTime;
+use mysql::params;
+use serde::Deserialize;
+use std::collections::HashMap;
+
+use crate::www::handlers::prefs::Prefs;
+use crate::www::handlers::template;
+use crate::www::handlers::template::escape_html;
+use crate::{problems, sql};
+
+/// Assignment of one problem.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Owner {
+    pub user: String,
+    pub note: String,
+    pub updated: Option<NaiveDateTime>,
+}
+
+impl Owner {
+    /// Badge for the leaderboard navigation: the owner's name, with the note
+    /// as tooltip.
+    pub fn nav_badge(&self) -> String {
+        let title = if self.note.is_empty() {
+            escape_html(&self.user)
+        } else {
+            escape_html(&format!("{}: {}", self.user, self.note))
+        };
+        let name = if self.user.is_empty() {
+            "?".to_string()
+        } else {
+            escape_html(&self.user)
+        };
+        format!(
+            r#"<small class="lb-owner" title="{}" style="color:gray;">@{}{}</small>"#,
+            title,
+            name,
+            if self.note.is_empty() { "" } else { "*" }
+        )
+    }
+}
+
+/// All assignments keyed by problem name.
+pub fn load_all() -> Result<HashMap<String, Owner>> {
+    let rows = sql::select(
+        "SELECT problem_name, owner_user, owner_note, owner_updated FROM problem_owners",
+        params::Params::Empty,
+    )?;
+    let mut owners = HashMap::new();
+    for row in rows {
+        owners.insert(
+            row.at::<String>(0)?,
+            Owner {
+                user: row.at(1)?,
+                note: row.at(2)?,
+                updated: row.at_option(3)?,
+            },
+        );
+    }
+    Ok(owners)
+}
+
+#[derive(Deserialize)]
+pub struct OwnerForm {
+    problem: String,
+    #[serde(default)]
+    user: String,
+    #[serde(default)]
+    note: String,
+}
+
+/// Saves an assignment; clearing both fields removes it.
+fn save_owner(problem: &str, user: &str, note: &str) -> Result<()> {
+    if problems::all_problems()
+        .iter()
+        .all(|p| p.problem != problem)
+    {
+        bail!("unknown problem: {}", problem);
+    }
+    if user.len() > 64 {
+        bail!("user name too long");
+    }
+    if user.is_empty() && note.is_empty() {
+        sql::exec(
+            "DELETE FROM problem_owners WHERE problem_name = :problem",
+            params! { "problem" => problem },
+        )?;
+    } else {
+        sql::exec(
+            "INSERT INTO problem_owners (problem_name, owner_user, owner_note)
+             VALUES (:problem, :user, :note)
+             ON DUPLICATE KEY UPDATE
+               owner_user = VALUES(owner_user),
+               owner_note = VALUES(owner_note)",
+            params! { "problem" => problem, "user" => user, "note" => note },
+        )?;
+    }
+    Ok(())
+}
+
+fn render_index(prefs: &Prefs) -> Result<String> {
+    let owners = load_all()?;
+    let mut html = String::from(
+        "<h1>担当</h1>\n<table class=\"table\">\n<tr><th>問題</th><th>担当者</th><th>メモ</th><th>更新</th><th></th></tr>\n",
+    );
+    for p in problems::all_problems() {
+        let owner = owners.get(&p.problem);
+        html.push_str(&format!(
+            r#"<tr>
+<td><form id="owner-{p}" method="POST" action="/owners"><input type="hidden" name="problem" value="{p}"></form><a href="/leaderboard/{p}">{p}</a></td>
+<td><input form="owner-{p}" name="user" value="{user}" placeholder="{me}" maxlength="64" size="10"></td>
+<td><input form="owner-{p}" name="note" value="{note}" size="50"></td>
+<td>{updated}</td>
+<td><button form="owner-{p}" type="submit">保存</button></td>
+</tr>
+"#,
+            p = p.problem,
+            user = escape_html(owner.map_or("", |o| o.user.as_str())),
+            me = escape_html(prefs.user.as_deref().unwrap_or("")),
+            note = escape_html(owner.map_or("", |o| o.note.as_str())),
+            updated = owner
+                .and_then(|o| o.updated)
+                .map(|t| prefs.format_utc(t))
+                .unwrap_or_default(),
+        ));
+    }
+    html.push_str("</table>\n<p>担当者とメモを両方空にすると割り当てを解除します。</p>\n");
+    Ok(html)
+}
+
+/// Handler for `GET /owners`: lists assignments with an edit form per problem.
+pub async fn index(req: HttpRequest) -> impl Responder {
+    let prefs = Prefs::from_request(&req);
+    template::to_response(render_index(&prefs))
+}
+
+/// Handler for `POST /owners`: updates one problem and returns to the list.
+pub async fn save(form: web::Form<OwnerForm>) -> impl Responder {
+    let form = form.into_inner();
+    if let Err(e) = save_owner(&form.problem, form.user.trim(), form.note.trim()) {
+        return template::to_error_response(&e);
+    }
+    HttpResponse::Found()
+        .append_header(("Location", "/owners"))
+        .finish()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn nav_badge_escapes_note() {
+        let owner = Owner {
+            user: "iwiwi".to_string(),
+            note: "waiting on <90-room> encode".to_string(),
+            updated: None,
+        };
+        assert_eq!(
+            owner.nav_badge(),
+            r#"<small class="lb-owner" title="iwiwi: waiting on &lt;90-room&gt; encode" style="color:gray;">@iwiwi*</small>"#
+        );
+        let note_only = Owner {
+            user: String::new(),
+            note: "free".to_string(),
+            updated: None,
+        };
+        assert!(note_only.nav_badge().contains(">@?*<"));
+    }
+}
@@ -0,0 +1,272 @@
+//! # Per-user Dashboard Preferences
+//!
+//! Team members identify themselves with a name stored in the `unagi_user`
+//! cookie (no authentication). Preferences are kept per name in the `users`
+//! table:
+//!
+//! ```sql
+//! CREATE TABLE users (
+//!   user_name VARCHAR(64) NOT NULL PRIMARY KEY,
+//!   user_favorites TEXT NOT NULL,            -- comma-separated problem names
+//!   user_chart_scale VARCHAR(16) NOT NULL,   -- auto / linear / log
+//!   user_tz_offset_minutes INT NOT NULL,     -- e.g. 540 for JST
+//!   user_updated TIMESTAMP NOT NULL DEFAULT CURRENT_TIMESTAMP ON UPDATE CURRENT_TIMESTAMP
+//! );
+//! ```
+
+use crate::{problems, sql};
+use actix_web::cookie::{Cookie, time::Duration as CookieDuration};
+use actix_web::{HttpRequest, HttpResponse, Responder, web};
+use anyhow::Result;
+use chrono::{FixedOffset, NaiveDateTime, TimeZone};
+use mysql::params;
+
+use crate::www::handlers::template;
+
+/// Name of the cookie holding the user name.
+pub const USER_COOKIE: &str = "unagi_user";
+
+/// JST, used when no preference is set.
+const DEFAULT_TZ_OFFSET_MINUTES: i32 = 9 * 60;
+
+/// Y axis scale of the leaderboard chart.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ChartScale {
+    /// Logarithmic for problems, linear for the global board.
+    Auto,
+    Linear,
+    Log,
+}
+
+impl ChartScale {
+    fn parse(s: &str) -> Self {
+        match s {
+            "linear" => ChartScale::Linear,
+            "log" => ChartScale::Log,
+            _ => ChartScale::Auto,
+        }
+    }
+
+    pub fn as_str(self) -> &'static str {
+        match self {
+            ChartScale::Auto => "auto",
+            ChartScale::Linear => "linear",
+            ChartScale::Log => "log",
+        }
+    }
+}
+
+/// Preferences of the current viewer (defaults if anonymous or unknown).
+#[derive(Clone, Debug)]
+pub struct Prefs {
+    pub user: Option<String>,
+    /// Problems pinned to the front of navigation lists.
+    pub favorites: Vec<String>,
+    pub chart_scale: ChartScale,
+    pub tz_offset_minutes: i32,
+}
+
+impl Default for Prefs {
+    fn default() -> Self {
+        Prefs {
+            user: None,
+            favorites: vec![],
+            chart_scale: ChartScale::Auto,
+            tz_offset_minutes: DEFAULT_TZ_OFFSET_MINUTES,
+        }
+    }
+}
+
+impl Prefs {
+    /// Loads the preferences of the user named in the request cookie.
+    ///
+    /// Database errors are logged and fall back to defaults so pages still render.
+    pub fn from_request(req: &HttpRequest) -> Self {
+        let Some(user) = req
+            .cookie(USER_COOKIE)
+            .map(|c| c.value().trim().to_string())
+            .filter(|u| !u.is_empty())
+        else {
+            return Prefs::default();
+        };
+        match load(&user) {
+            Ok(prefs) => prefs,
+            Err(e) => {
+                eprintln!("Failed to load preferences of {}: {}", user, e);
+                Prefs {
+                    user: Some(user),
+                    ..Prefs::default()
+                }
+            }
+        }
+    }
+
+    pub fn tz(&self) -> FixedOffset {
+        FixedOffset::east_opt(self.tz_offset_minutes * 60)
+            .unwrap_or(FixedOffset::east_opt(DEFAULT_TZ_OFFSET_MINUTES * 60).unwrap())
+    }
+
+    /// Luxon zone name for the chart adapter, e.g. `UTC+9` or `UTC+5:30`.
+    pub fn luxon_zone(&self) -> String {
+        let m = self.tz_offset_minutes;
+        let sign = if m < 0 { '-' } else { '+' };
+        let (h, mm) = (m.abs() / 60, m.abs() % 60);
+        if mm == 0 {
+            format!("UTC{sign}{h}")
+        } else {
+            format!("UTC{sign}{h}:{mm:02}")
+        }
+    }
+
+    /// Formats a UTC timestamp in the user's time zone.
+    pub fn format_utc(&self, dt: NaiveDateTime) -> String {
+        self.tz()
+            .from_utc_datetime(&dt)
+            .format("%Y-%m-%d %H:%M:%S %:z")
+            .to_string()
+    }
+
+    pub fn is_favorite(&self, problem: &str) -> bool {
+        self.favorites.iter().any(|f| f == problem)
+    }
+}
+
+fn load(user: &str) -> Result<Prefs> {
+    let row = sql::row(
+        "SELECT user_favorites, user_chart_scale, user_tz_offset_minutes
+         FROM users WHERE user_name = :user",
+        params! { "user" => user },
+    )?;
+    let mut prefs = Prefs {
+        user: Some(user.to_string()),
+        ..Prefs::default()
+    };
+    if let Some(row) = row {
+        prefs.favorites = parse_favorites(&row.at::<String>(0)?);
+        prefs.chart_scale = ChartScale::parse(&row.at::<String>(1)?);
+        prefs.tz_offset_minutes = row.at::<i32>(2)?;
+    }
+    Ok(prefs)
+}
+
+/// Keeps only known problem names, in the order given.
+fn parse_favorites(s: &str) -> Vec<String> {
+    let known = problems::all_problems();
+    s.split(',')
+        .map(str::trim)
+        .filter(|p| known.iter().any(|k| k.problem == *p))
+        .map(str::to_string)
+        .collect()
+}
+
+/// Handler for `GET /prefs`: shows the preference form.
+pub async fn show(req: HttpRequest) -> impl Responder {
+    let prefs = Prefs::from_request(&req);
+    let user = prefs.user.as_deref().unwrap_or("");
+    let mut problems_html = String::new();
+    for p in problems::all_problems() {
+        problems_html.push_str(&format!(
+            "<label><input type='checkbox' name='favorite' value='{p}'{checked}> {p}</label> ",
+            p = p.problem,
+            checked = if prefs.is_favorite(&p.problem) {
+                " checked"
+            } else {
+                ""
+            },
+        ));
+    }
+    let mut scale_html = String::new();
+    for scale in [ChartScale::Auto, ChartScale::Linear, ChartScale::Log] {
+        scale_html.push_str(&format!(
+            "<option value='{s}'{selected}>{s}</option>",
+            s = scale.as_str(),
+            selected = if scale == prefs.chart_scale {
+                " selected"
+            } else {
+                ""
+            },
+        ));
+    }
+    let contents = format!(
+        r#"<h1>設定</h1>
+<form method='POST' action='/prefs'>
+<p>名前: <input name='user' value='{user}' required maxlength='64'></p>
+<p>お気に入り問題: {problems_html}</p>
+<p>グラフのスケール: <select name='chart_scale'>{scale_html}</select></p>
+<p>タイムゾーン (UTC からの分): <input name='tz_offset_minutes' type='number' value='{tz}'></p>
+<button type='submit'>保存</button>
+</form>"#,
+        user = template::escape_html(user),
+        tz = prefs.tz_offset_minutes,
+    );
+    template::to_html_response(&contents)
+}
+
+/// Handler for `POST /prefs`: saves preferences and sets the user cookie.
+///
+/// The form is taken as key/value pairs because `favorite` may repeat.
+pub async fn save(form: web::Form<Vec<(String, String)>>) -> impl Responder {
+    let mut user = String::new();
+    let mut favorites = vec![];
+    let mut chart_scale = ChartScale::Auto;
+    let mut tz_offset_minutes = DEFAULT_TZ_OFFSET_MINUTES;
+    for (k, v) in form.into_inner() {
+        match k.as_str() {
+            "user" => user = v.trim().to_string(),
+            "favorite" => favorites.push(v),
+            "chart_scale" => chart_scale = ChartScale::parse(&v),
+            "tz_offset_minutes" => {
+                tz_offset_minutes = v
+                    .trim()
+                    .parse::<i32>()
+                    .unwrap_or(DEFAULT_TZ_OFFSET_MINUTES)
+                    .clamp(-12 * 60, 14 * 60)
+            }
+            _ => {}
+        }
+    }
+    if user.is_empty() || user.len() > 64 {
+        return template::to_error_response(&anyhow::anyhow!("invalid user name"));
+    }
+    let favorites = parse_favorites(&favorites.join(","));
+    if let Err(e) = sql::exec(
+        "INSERT INTO users
+           (user_name, user_favorites, user_chart_scale, user_tz_offset_minutes)
+         VALUES (:user, :favorites, :scale, :tz)
+         ON DUPLICATE KEY UPDATE
+           user_favorites = VALUES(user_favorites),
+           user_chart_scale = VALUES(user_chart_scale),
+           user_tz_offset_minutes = VALUES(user_tz_offset_minutes)",
+        params! {
+            "user" => &user,
+            "favorites" => favorites.join(","),
+            "scale" => chart_scale.as_str(),
+            "tz" => tz_offset_minutes,
+        },
+    ) {
+        return template::to_error_response(&e);
+    }
+    let cookie = Cookie::build(USER_COOKIE, user)
+        .path("/")
+        .max_age(CookieDuration::days(365))
+        .finish();
+    HttpResponse::Found()
+        .cookie(cookie)
+        .append_header(("Location", "/prefs"))
+        .finish()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn luxon_zone_format() {
+        let mut prefs = Prefs::default();
+        assert_eq!(prefs.luxon_zone(), "UTC+9");
+        prefs.tz_offset_minutes = 330;
+        assert_eq!(prefs.luxon_zone(), "UTC+5:30");
+        prefs.tz_offset_minutes = -420;
+        assert_eq!(prefs.luxon_zone(), "UTC-7");
+    }
+}
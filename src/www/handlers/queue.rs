@@ -0,0 +1,304 @@
+//! # Queue History
+//!
+//! `/cron/queue` records how many tasks each agent has pending, running and
+//! recently failed into `queue_snapshots`; `/queue` draws the recorded history
+//! as sparklines, so a fleet that stalled overnight shows up as a flat
+//! `running` line next to a growing `pending` one, with timestamps to match
+//! against API or GCS incidents.
+//!
+//! ```sql
+//! CREATE TABLE queue_snapshots (
+//!   snapshot_time DATETIME NOT NULL,
+//!   agent_id BIGINT NOT NULL,
+//!   snapshot_pending INT NOT NULL,
+//!   snapshot_running INT NOT NULL,
+//!   snapshot_failed INT NOT NULL,
+//!   PRIMARY KEY (snapshot_time, agent_id)
+//! );
+//! ```
+//!
+//! Only agents with queued, running or recently failed tasks get a row, so an
+//! instant without any row means the queue was empty (or the cron did not run).
+
+use actix_web::{HttpRequest, HttpResponse, Responder, web};
+use anyhow::Result;
+use chrono::{Duration, NaiveDateTime, Utc};
+use mysql::params;
+use serde::Deserialize;
+use std::collections::BTreeMap;
+
+use crate::sql;
+use crate::www::handlers::template::escape_html;
+use crate::www::handlers::{prefs::Prefs, template};
+
+/// Failures are counted over this many minutes before each snapshot.
+const FAILED_WINDOW_MINUTES: i64 = 60;
+
+/// Records one snapshot of the queue taken at `now` and returns the number of
+/// agents recorded.
+pub fn take_snapshot(now: NaiveDateTime) -> Result<u64> {
+    sql::exec(
+        "
+        INSERT INTO queue_snapshots
+            (snapshot_time, agent_id, snapshot_pending, snapshot_running, snapshot_failed)
+        SELECT :now, agent_id,
+               CAST(SUM(task_state IN ('pending', 'failed')) AS SIGNED),
+               CAST(SUM(task_state = 'running') AS SIGNED),
+               CAST(SUM(task_state IN ('failed', 'dead') AND task_updated >= :since) AS SIGNED)
+        FROM tasks
+        WHERE task_state IN ('pending', 'running', 'failed')
+           OR (task_state = 'dead' AND task_updated >= :since)
+        GROUP BY agent_id",
+        params! {
+            "now" => now,
+            "since" => now - Duration::minutes(FAILED_WINDOW_MINUTES),
+        },
+    )
+}
+
+/// Handler for `/cron/queue`: records a snapshot of the queue.
+pub async fn run() -> impl Responder {
+    let now = Utc::now().naive_utc();
+    match take_snapshot(now) {
+        Ok(agents) => HttpResponse::Ok()
+            .content_type("application/json")
+            .body(serde_json::json!({ "time": now.to_string(), "agents": agents }).to_string()),
+        Err(e) => HttpResponse::InternalServerError().body(e.to_string()),
+    }
+}
+
+/// Pending, running and failed counts of one agent at one snapshot.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct Counts {
+    pub pending: i64,
+    pub running: i64,
+    pub failed: i64,
+}
+
+impl std::ops::AddAssign for Counts {
+    fn add_assign(&mut self, other: Self) {
+        self.pending += other.pending;
+        self.running += other.running;
+        self.failed += other.failed;
+    }
+}
+
+/// The snapshots of one agent (or of all agents together), aligned with
+/// [`History::times`].
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Series {
+    pub agent: String,
+    pub counts: Vec<Counts>,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct History {
+    /// Snapshot times in ascending order.
+    pub times: Vec<NaiveDateTime>,
+    /// The sum over all agents first, then each agent by name.
+    pub series: Vec<Series>,
+}
+
+impl History {
+    /// Builds the history from `(time, agent, counts)` rows; an agent missing
+    /// from a snapshot had nothing queued at that time.
+    pub fn from_rows(rows: &[(NaiveDateTime, String, Counts)]) -> Self {
+        let mut times = rows.iter().map(|r| r.0).collect::<Vec<_>>();
+        times.sort();
+        times.dedup();
+        let index = |t: &NaiveDateTime| times.binary_search(t).unwrap();
+        let mut total = vec![Counts::default(); times.len()];
+        let mut agents = BTreeMap::<&str, Vec<Counts>>::new();
+        for (t, agent, c) in rows {
+            let i = index(t);
+            total[i] += *c;
+            agents
+                .entry(agent)
+                .or_insert_with(|| vec![Counts::default(); times.len()])[i] += *c;
+        }
+        let mut series = vec![Series {
+            agent: "(全体)".to_string(),
+            counts: total,
+        }];
+        series.extend(agents.into_iter().map(|(agent, counts)| Series {
+            agent: agent.to_string(),
+            counts,
+        }));
+        Self { times, series }
+    }
+}
+
+/// An inline SVG line chart of `points` `(x, y)`, with `x` spanning `x_range`
+/// and `y` scaled from zero to the largest value.
+pub fn sparkline(points: &[(i64, i64)], x_range: (i64, i64), color: &str) -> String {
+    const WIDTH: f64 = 240.0;
+    const HEIGHT: f64 = 24.0;
+    let max = points.iter().map(|p| p.1).max().unwrap_or(0);
+    let span = (x_range.1 - x_range.0).max(1) as f64;
+    let coords = points
+        .iter()
+        .map(|&(x, y)| {
+            format!(
+                "{:.1},{:.1}",
+                (x - x_range.0) as f64 / span * WIDTH,
+                HEIGHT - 1.0 - y as f64 / max.max(1) as f64 * (HEIGHT - 2.0)
+            )
+        })
+        .collect::<Vec<_>>()
+        .join(" ");
+    format!(
+        "<svg width=\"{w}\" height=\"{h}\" viewBox=\"0 0 {w} {h}\"><title>max {max}, latest {last}</title><polyline fill=\"none\" stroke=\"{color}\" stroke-width=\"1.5\" points=\"{coords}\"/></svg>",
+        w = WIDTH,
+        h = HEIGHT,
+        max = max,
+        last = points.last().map_or(0, |p| p.1),
+        color = color,
+        coords = coords,
+    )
+}
+
+#[derive(Deserialize)]
+pub struct QueueQuery {
+    /// How far back to show, in hours.
+    #[serde(default = "default_hours")]
+    pub hours: i64,
+}
+
+fn default_hours() -> i64 {
+    48
+}
+
+fn load_history(since: NaiveDateTime) -> Result<History> {
+    let rows = sql::select(
+        "
+        SELECT s.snapshot_time, a.agent_name, s.agent_id,
+               s.snapshot_pending, s.snapshot_running, s.snapshot_failed
+        FROM queue_snapshots s
+        LEFT JOIN agents a ON a.agent_id = s.agent_id
+        WHERE s.snapshot_time >= :since
+        ORDER BY s.snapshot_time",
+        params! { "since" => since },
+    )?;
+    let rows = rows
+        .iter()
+        .map(|r| {
+            let agent = match r.get_option::<String>("agent_name")? {
+                Some(name) => name,
+                None => format!("#{}", r.get::<i64>("agent_id")?),
+            };
+            Ok((
+                r.get("snapshot_time")?,
+                agent,
+                Counts {
+                    pending: r.get("snapshot_pending")?,
+                    running: r.get("snapshot_running")?,
+                    failed: r.get("snapshot_failed")?,
+                },
+            ))
+        })
+        .collect::<Result<Vec<_>>>()?;
+    Ok(History::from_rows(&rows))
+}
+
+fn render_history(
+    history: &History,
+    from: NaiveDateTime,
+    to: NaiveDateTime,
+    prefs: &Prefs,
+) -> String {
+    let mut html = format!(
+        "<h1>キュー推移</h1>\n<p>{} 〜 {}（失敗は各時点の直前 {} 分間）</p>\n",
+        escape_html(&prefs.format_utc(from)),
+        escape_html(&prefs.format_utc(to)),
+        FAILED_WINDOW_MINUTES,
+    );
+    if history.times.is_empty() {
+        html.push_str("<p>この期間のスナップショットはありません</p>\n");
+        return html;
+    }
+    let x = history
+        .times
+        .iter()
+        .map(|t| t.and_utc().timestamp())
+        .collect::<Vec<_>>();
+    let x_range = (from.and_utc().timestamp(), to.and_utc().timestamp());
+    html.push_str("<table class=\"table\">\n");
+    html.push_str("<tr><th>プログラム名</th><th>待機中</th><th>実行中</th><th>失敗</th></tr>\n");
+    for s in &history.series {
+        let line = |f: fn(&Counts) -> i64, color: &str| {
+            let points = x
+                .iter()
+                .zip(&s.counts)
+                .map(|(&x, c)| (x, f(c)))
+                .collect::<Vec<_>>();
+            format!(
+                "{} {}",
+                sparkline(&points, x_range, color),
+                points.last().map_or(0, |p| p.1)
+            )
+        };
+        html.push_str(&format!(
+            "<tr><td>{}</td><td>{}</td><td>{}</td><td>{}</td></tr>\n",
+            escape_html(&s.agent),
+            line(|c| c.pending, "#1f77b4"),
+            line(|c| c.running, "#2ca02c"),
+            line(|c| c.failed, "#d62728"),
+        ));
+    }
+    html.push_str("</table>\n");
+    html
+}
+
+/// Handler for `GET /queue`: the queue history of the last `hours` hours.
+pub async fn index(req: HttpRequest, query: web::Query<QueueQuery>) -> impl Responder {
+    let prefs = Prefs::from_request(&req);
+    let to = Utc::now().naive_utc();
+    let from = to - Duration::hours(query.hours.clamp(1, 24 * 14));
+    template::to_response(load_history(from).map(|h| render_history(&h, from, to, &prefs)))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn t(s: &str) -> NaiveDateTime {
+        NaiveDateTime::parse_from_str(s, "%Y-%m-%d %H:%M").unwrap()
+    }
+
+    #[test]
+    fn history_aligns_agents_on_snapshot_times() {
+        let c = |pending, running, failed| Counts {
+            pending,
+            running,
+            failed,
+        };
+        let rows = vec![
+            (t("2025-09-06 00:00"), "wata".to_string(), c(3, 2, 0)),
+            (t("2025-09-06 00:00"), "chokudai".to_string(), c(1, 1, 1)),
+            // chokudai had nothing queued at 00:10.
+            (t("2025-09-06 00:10"), "wata".to_string(), c(5, 0, 0)),
+        ];
+        let history = History::from_rows(&rows);
+        assert_eq!(
+            history.times,
+            [t("2025-09-06 00:00"), t("2025-09-06 00:10")]
+        );
+        let names = history
+            .series
+            .iter()
+            .map(|s| s.agent.as_str())
+            .collect::<Vec<_>>();
+        assert_eq!(names, ["(全体)", "chokudai", "wata"]);
+        assert_eq!(history.series[0].counts, [c(4, 3, 1), c(5, 0, 0)]);
+        assert_eq!(history.series[1].counts, [c(1, 1, 1), c(0, 0, 0)]);
+    }
+
+    #[test]
+    fn sparkline_scales_to_range() {
+        let svg = sparkline(&[(0, 0), (50, 4), (100, 2)], (0, 100), "red");
+        assert!(svg.contains("points=\"0.0,23.0 120.0,1.0 240.0,12.0\""));
+        assert!(svg.contains("<title>max 4, latest 2</title>"));
+        // All zeros stay on the baseline instead of dividing by zero.
+        assert!(sparkline(&[(0, 0), (1, 0)], (0, 1), "red").contains("0.0,23.0 240.0,23.0"));
+    }
+}
@@ -0,0 +1,301 @@
+//! # Guess Replay
+//!
+//! `/replay` takes a guessed map and an exploration log, replays every plan on
+//! the map with `judge::check_explore_detailed` (the check behind
+//! `judge::check_explore2`) and shows where the guess first disagrees with the
+//! judge and which rooms of the guess the walk went through, with the walk up
+//! to that point drawn by `svg::render_with_trace`.
+//!
+//! The map may be pasted as `api::Map` JSON, a `/guess` request body or a
+//! `<UNAGI::MAP>` log line. The log may be a `{"plans": [...], "results":
+//! [...]}` object or a solver's output, from which the `<UNAGI::EXPLORE>`
+//! lines are taken. Labels (of rooms and charcoal marks) must be below the
+//! label count of the form, by default `judge::num_labels()`.
+
+use actix_web::{Responder, web};
+use anyhow::{Context, Result, bail, ensure};
+use serde::Deserialize;
+
+use crate::api;
+use crate::judge::{self, Explored, Guess};
+use crate::svg;
+use crate::www::handlers::template;
+use crate::www::handlers::template::escape_html;
+
+/// Labels shown on each side of the divergence.
+const CONTEXT: usize = 20;
+
+#[derive(Deserialize, Default)]
+pub struct ReplayForm {
+    #[serde(default)]
+    pub guess: String,
+    #[serde(default)]
+    pub explores: String,
+    /// Size of the label alphabet; `judge::num_labels()` if missing.
+    #[serde(default)]
+    pub labels: Option<usize>,
+}
+
+#[derive(Deserialize)]
+struct ExploreLog {
+    plans: Vec<String>,
+    results: Vec<Vec<usize>>,
+}
+
+/// Text from the first `{` on, so that log prefixes such as `<UNAGI::MAP>: ` are skipped.
+fn json_part(s: &str) -> &str {
+    s.find('{').map_or(s, |i| &s[i..])
+}
+
+fn parse_map(text: &str, num_labels: usize) -> Result<api::Map> {
+    let value: serde_json::Value =
+        serde_json::from_str(json_part(text.trim())).context("guess is not JSON")?;
+    let value = match value.get("map") {
+        Some(map) => map.clone(),
+        None => value,
+    };
+    let map: api::Map = serde_json::from_value(value).context("guess is not a map")?;
+    let n = map.rooms.len();
+    ensure!(n > 0, "map has no rooms");
+    ensure!(
+        map.starting_room < n,
+        "startingRoom {} out of range",
+        map.starting_room
+    );
+    ensure!(
+        map.rooms.iter().all(|&l| l < num_labels),
+        "labels must be 0..{}",
+        num_labels
+    );
+    for c in &map.connections {
+        for end in [&c.from, &c.to] {
+            ensure!(
+                end.room < n && end.door < judge::MAX_DOORS,
+                "connection end {}.{} out of range",
+                end.room,
+                end.door
+            );
+        }
+    }
+    Ok(map)
+}
+
+/// Whether `plan` is a sequence of doors `0..6`, each optionally preceded by
+/// `[k]` with `k < num_labels`.
+fn is_valid_plan(plan: &str, num_labels: usize) -> bool {
+    let mut chars = plan.chars();
+    while let Some(c) = chars.next() {
+        let door = if c == '[' {
+            let label = chars.next().and_then(|c| c.to_digit(10));
+            if !(label.is_some_and(|k| (k as usize) < num_labels) && chars.next() == Some(']')) {
+                return false;
+            }
+            chars.next()
+        } else {
+            Some(c)
+        };
+        if !matches!(door, Some('0'..='5')) {
+            return false;
+        }
+    }
+    true
+}
+
+fn parse_explores(text: &str, num_labels: usize) -> Result<Explored> {
+    let logs = match serde_json::from_str::<ExploreLog>(text.trim()) {
+        Ok(log) => vec![log],
+        Err(_) => text
+            .lines()
+            .enumerate()
+            .filter(|(_, line)| line.starts_with("<UNAGI::EXPLORE>") || line.starts_with('{'))
+            .map(|(i, line)| {
+                serde_json::from_str::<ExploreLog>(json_part(line))
+                    .with_context(|| format!("line {}: not an explore log", i + 1))
+            })
+            .collect::<Result<Vec<_>>>()?,
+    };
+    let mut plans = vec![];
+    let mut results = vec![];
+    for log in logs {
+        ensure!(
+            log.plans.len() == log.results.len(),
+            "{} plans but {} results",
+            log.plans.len(),
+            log.results.len()
+        );
+        for (plan, result) in log.plans.iter().zip(log.results) {
+            if !is_valid_plan(plan, num_labels) {
+                bail!("plan #{}: malformed plan {:?}", plans.len(), plan);
+            }
+            let plan = judge::parse_plan(plan);
+            ensure!(
+                result.len() == plan.len() + 1,
+                "plan #{}: {} labels for {} steps",
+                plans.len(),
+                result.len(),
+                plan.len()
+            );
+            plans.push(plan);
+            results.push(result);
+        }
+    }
+    ensure!(!plans.is_empty(), "no explore log found");
+    Ok(Explored { plans, results })
+}
+
+/// Renders the values around `step`, separated by `sep`, with the differing
+/// one in bold.
+fn label_context(labels: &[usize], step: usize, sep: &str) -> String {
+    let from = step.saturating_sub(CONTEXT);
+    let to = (step + CONTEXT + 1).min(labels.len());
+    let mut parts = vec![];
+    if from > 0 {
+        parts.push("…".to_string());
+    }
+    parts.extend(labels[from..step].iter().map(|l| l.to_string()));
+    parts.push(format!("<b style=\"color: red\">{}</b>", labels[step]));
+    parts.extend(labels[step + 1..to].iter().map(|l| l.to_string()));
+    if to < labels.len() {
+        parts.push("…".to_string());
+    }
+    parts.join(sep)
+}
+
+fn render_replay(guess: &str, explores: &str, num_labels: usize) -> Result<String> {
+    ensure!(
+        (1..=judge::MAX_LABELS).contains(&num_labels),
+        "label count must be 1..={}",
+        judge::MAX_LABELS
+    );
+    let map = parse_map(guess, num_labels)?;
+    let Explored { plans, results } = parse_explores(explores, num_labels)?;
+    let guess = Guess::from(&map);
+    for (u, doors) in guess.graph.iter().enumerate() {
+        if let Some(d) = doors.iter().position(|&(v, _)| v == !0) {
+            bail!("room {} door {} is not connected", u, d);
+        }
+    }
+    let labels = results.iter().map(|r| r.len()).sum::<usize>();
+    let check = judge::check_explore_detailed(&guess, &plans, &results);
+    let Some((id, replay)) = check.first_mismatch() else {
+        return Ok(format!(
+            "<p>{} 件のプラン（ラベル {} 個）すべてと一致しました</p>\n",
+            plans.len(),
+            labels
+        ));
+    };
+    let step = replay.mismatch.unwrap();
+    let plan = &plans[id];
+    let result = &replay.expected;
+    let mut html = format!(
+        "<p>プラン #{} の {} 番目のラベルで不一致: 探索結果 {}、推測 {}",
+        id, step, result[step], replay.actual[step]
+    );
+    if step > 0 {
+        html.push_str(&format!("（ステップ {} の後）", step - 1));
+    }
+    let failed = check.plans.iter().filter(|p| p.mismatch.is_some()).count();
+    html.push_str(&format!(
+        "。不一致のプランは {} 件中 {} 件",
+        plans.len(),
+        failed
+    ));
+    html.push_str("</p>\n<table class=\"table\">\n");
+    html.push_str(&format!(
+        "<tr><th>プラン</th><td><code>{}</code></td></tr>\n",
+        escape_html(&judge::format_plan(plan))
+    ));
+    html.push_str(&format!(
+        "<tr><th>探索結果</th><td><code>{}</code></td></tr>\n<tr><th>推測</th><td><code>{}</code></td></tr>\n",
+        label_context(result, step, ""),
+        label_context(&replay.actual, step, ""),
+    ));
+    html.push_str(&format!(
+        "<tr><th>推測の部屋</th><td><code>{}</code></td></tr>\n",
+        label_context(&replay.rooms, step, " "),
+    ));
+    html.push_str("</table>\n");
+    html.push_str("<p>不一致までの経路（← → で1ステップずつ、d でドア番号の表示切替）</p>\n");
+    html.push_str(&svg::render_with_trace(
+        &map,
+        &plan[..step],
+        &result[..=step],
+    ));
+    Ok(html)
+}
+
+fn render_form(form: &ReplayForm) -> String {
+    format!(
+        r#"<h1>リプレイ</h1>
+<form method="POST" action="/replay">
+<p>推測マップ（JSON）<br><textarea name="guess" rows="8" style="width: 100%">{}</textarea></p>
+<p>探索ログ<br><textarea name="explores" rows="8" style="width: 100%">{}</textarea></p>
+<p>ラベル数 <input type="number" name="labels" min="1" max="{}" value="{}"></p>
+<button type="submit">照合</button>
+</form>
+"#,
+        escape_html(&form.guess),
+        escape_html(&form.explores),
+        judge::MAX_LABELS,
+        form.labels.unwrap_or_else(judge::num_labels),
+    )
+}
+
+/// Handler for `GET /replay`: the empty form.
+pub async fn show() -> impl Responder {
+    template::to_response(Ok(render_form(&ReplayForm::default())))
+}
+
+/// Handler for `POST /replay`: the form again, followed by the replay result.
+pub async fn run(form: web::Form<ReplayForm>) -> impl Responder {
+    let mut html = render_form(&form);
+    let num_labels = form.labels.unwrap_or_else(judge::num_labels);
+    match render_replay(&form.guess, &form.explores, num_labels) {
+        Ok(result) => html.push_str(&result),
+        Err(e) => html.push_str(&format!(
+            "<p style=\"color: red\">{}</p>\n",
+            escape_html(&format!("{:#}", e))
+        )),
+    }
+    template::to_response(Ok(html))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const MAP: &str = r#"<UNAGI::MAP>: {"problemName":"random","rooms":[0,1],"startingRoom":0,"connections":[{"from":{"room":0,"door":0},"to":{"room":1,"door":0}},{"from":{"room":0,"door":1},"to":{"room":0,"door":1}},{"from":{"room":0,"door":2},"to":{"room":0,"door":3}},{"from":{"room":0,"door":4},"to":{"room":0,"door":5}},{"from":{"room":1,"door":1},"to":{"room":1,"door":2}},{"from":{"room":1,"door":3},"to":{"room":1,"door":4}},{"from":{"room":1,"door":5},"to":{"room":1,"door":5}}]}"#;
+
+    #[test]
+    fn plans_are_validated_before_parsing() {
+        assert!(is_valid_plan("0[1]23", 4));
+        assert!(is_valid_plan("", 4));
+        assert!(!is_valid_plan("6", 4));
+        assert!(!is_valid_plan("[4]0", 4));
+        assert!(is_valid_plan("[4]0", 8));
+        assert!(!is_valid_plan("[2]0", 2));
+        assert!(!is_valid_plan("[1]", 4));
+        assert!(!is_valid_plan("0[12]3", 4));
+    }
+
+    #[test]
+    fn replay_reports_first_divergence() {
+        let log = "explore 1\n<UNAGI::EXPLORE>: {\"plans\":[\"00\",\"0[2]0\"],\"results\":[[0,1,0],[0,1,0]]}\n";
+        let html = render_replay(MAP, log, 4).unwrap();
+        assert!(html.contains("2 件のプラン"), "{}", html);
+
+        // The server saw label 3 where the guess has the rewritten room 1.
+        let log = r#"{"plans": ["00", "0[2]00"], "results": [[0, 1, 0], [0, 1, 0, 3]]}"#;
+        let html = render_replay(MAP, log, 4).unwrap();
+        assert!(html.contains("プラン #1 の 3 番目"), "{}", html);
+        assert!(html.contains("2 件中 1 件"), "{}", html);
+        assert!(html.contains("<code>0 1 0 <b style=\"color: red\">1</b></code>"));
+        assert!(html.contains("data-step=\"2\""));
+        assert!(!html.contains("data-step=\"3\""));
+
+        let err = render_replay(MAP, r#"{"plans": ["00"], "results": [[0, 1]]}"#, 4).unwrap_err();
+        assert_eq!(err.to_string(), "plan #0: 2 labels for 2 steps");
+        let err = render_replay(MAP, log, 1).unwrap_err();
+        assert_eq!(err.to_string(), "labels must be 0..1");
+    }
+}
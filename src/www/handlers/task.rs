@@ -1,28 +1,44 @@
-use actix_web::{Responder, web};
+use actix_web::web::Bytes;
+use actix_web::{HttpRequest, HttpResponse, Responder, web};
 use anyhow::{Context, Result};
-use chrono::{FixedOffset, NaiveDateTime, TimeZone};
+use chrono::NaiveDateTime;
 use mysql::params;
 
-use crate::gcp::gcs::{download_object, get_object_metadata};
-use crate::www::handlers::template;
+use crate::executor::queue;
+use crate::gcp::gcs::{download_object, get_object_metadata, stream_object};
+use crate::www::auth;
+use crate::www::handlers::template::escape_html;
+use crate::www::handlers::{prefs::Prefs, template};
+
+/// Bytes fetched from GCS per range request when streaming a log.
+const LOG_CHUNK: u64 = 1 << 20;
 
 #[derive(serde::Deserialize)]
 pub struct TaskQuery {
     pub task_id: i64,
 }
 
-pub async fn show(query: web::Query<TaskQuery>) -> impl Responder {
-    template::to_response(render_task_page(query.task_id).await)
+#[derive(serde::Deserialize)]
+pub struct LogQuery {
+    pub task_id: i64,
+    /// `stdout` or `stderr`.
+    pub stream: String,
+}
+
+pub async fn show(req: HttpRequest, query: web::Query<TaskQuery>) -> impl Responder {
+    let prefs = Prefs::from_request(&req);
+    template::to_response(render_task_page(query.task_id, &prefs).await)
 }
 
-async fn render_task_page(task_id: i64) -> Result<String> {
+async fn render_task_page(task_id: i64, prefs: &Prefs) -> Result<String> {
     // Fetch task row
     let row = crate::sql::row(
         r#"
         SELECT t.task_id, t.agent_id, a.agent_name, a.agent_bin, a.agent_code,
                t.problem_name, t.problem_variant,
                t.task_host, t.task_exit_code, t.task_score, t.task_duration_ms,
-               t.task_lock, t.task_locked, t.task_failed, t.task_created, t.task_updated
+               t.task_lock, t.task_locked, t.task_failed, t.task_created, t.task_updated,
+               t.task_state, t.task_error
         FROM tasks t
         LEFT JOIN agents a ON a.agent_id = t.agent_id
         WHERE t.task_id = :task_id
@@ -47,6 +63,8 @@ async fn render_task_page(task_id: i64) -> Result<String> {
     let task_failed: i64 = row.get("task_failed")?;
     let task_created: NaiveDateTime = row.get("task_created")?;
     let task_updated: NaiveDateTime = row.get("task_updated")?;
+    let task_state: String = row.get("task_state")?;
+    let task_error: Option<String> = row.get_option("task_error")?;
 
     // Build table
     let mut html = String::new();
@@ -107,12 +125,41 @@ async fn render_task_page(task_id: i64) -> Result<String> {
         task_duration_ms.map(|v| v.to_string()).unwrap_or_default(),
     );
     add(&mut html, "ロック署名", task_lock.unwrap_or_default());
-    add(&mut html, "ロック期限", fmt_jst_opt(task_locked));
+    add(
+        &mut html,
+        "ロック期限",
+        task_locked.map(|t| prefs.format_utc(t)).unwrap_or_default(),
+    );
+    add(&mut html, "状態", task_state.clone());
     add(&mut html, "失敗回数", format!("{}", task_failed));
-    add(&mut html, "作成時刻", fmt_jst(task_created));
-    add(&mut html, "更新時刻", fmt_jst(task_updated));
+    add(&mut html, "作成時刻", prefs.format_utc(task_created));
+    add(&mut html, "更新時刻", prefs.format_utc(task_updated));
     html.push_str("</table>\n");
 
+    // 最後に失敗した試行の stderr 末尾
+    if let Some(error) = task_error.filter(|e| !e.is_empty()) {
+        html.push_str("<h2>エラー</h2><pre><code>");
+        html.push_str(&escape_html(&error));
+        html.push_str("</code></pre>");
+    }
+
+    // 再実行・中止
+    let action = match task_state.as_str() {
+        "failed" | "dead" | "done" => Some(("requeue", "再実行")),
+        "pending" | "running" => Some(("cancel", "中止")),
+        _ => None,
+    };
+    if let Some((path, label)) = action {
+        let action = format!("/task/{}", path);
+        html.push_str(&format!(
+            "<form method=\"POST\" action=\"{}\"><input type=\"hidden\" name=\"task_id\" value=\"{}\">{} <button type=\"submit\">{}</button></form>\n",
+            action,
+            task_id,
+            auth::form_fields(&action),
+            label
+        ));
+    }
+
     // 実行コード（複数行のシェルスクリプト）
     if let Some(code) = agent_code.filter(|c| !c.is_empty()) {
         html.push_str("<h2>実行コード</h2><pre><code>");
@@ -141,6 +188,10 @@ async fn render_task_page(task_id: i64) -> Result<String> {
     let out_render = render_with_omission(&stdout_text, 500 * 1024, 500 * 1024);
     let err_render = render_with_omission(&stderr_text, 500 * 1024, 500 * 1024);
 
+    html.push_str(&format!(
+        "<p>全文: <a href=\"/task/log?task_id={0}&stream=stdout\">標準出力</a> | <a href=\"/task/log?task_id={0}&stream=stderr\">標準エラー</a></p>\n",
+        task_id
+    ));
     html.push_str("<h2>標準出力</h2><pre><code>");
     html.push_str(&escape_html(&out_render));
     html.push_str("</code></pre>");
@@ -152,17 +203,107 @@ async fn render_task_page(task_id: i64) -> Result<String> {
     Ok(html)
 }
 
-fn fmt_jst_opt(dt: Option<NaiveDateTime>) -> String {
-    dt.map(fmt_jst).unwrap_or_default()
+#[derive(serde::Deserialize)]
+pub struct TaskActionForm {
+    pub task_id: i64,
+    #[serde(default)]
+    pub password: String,
+    #[serde(default)]
+    pub csrf: String,
+}
+
+fn redirect_to_task(task_id: i64) -> HttpResponse {
+    HttpResponse::Found()
+        .append_header(("Location", format!("/task?task_id={}", task_id)))
+        .finish()
+}
+
+/// Handler for `POST /task/requeue`: runs a finished task again.
+pub async fn requeue(req: HttpRequest, form: web::Form<TaskActionForm>) -> impl Responder {
+    if let Err(e) = auth::check(&req, "/task/requeue", &form.password, &form.csrf) {
+        return auth::forbidden(&e);
+    }
+    match queue::requeue_task(form.task_id) {
+        Ok(_) => redirect_to_task(form.task_id),
+        Err(e) => template::to_error_response(&e),
+    }
+}
+
+/// Handler for `POST /task/cancel`: gives up a queued or running task.
+pub async fn cancel(req: HttpRequest, form: web::Form<TaskActionForm>) -> impl Responder {
+    if let Err(e) = auth::check(&req, "/task/cancel", &form.password, &form.csrf) {
+        return auth::forbidden(&e);
+    }
+    match queue::cancel_task(form.task_id) {
+        Ok(_) => redirect_to_task(form.task_id),
+        Err(e) => template::to_error_response(&e),
+    }
+}
+
+/// Sends the text of a JSONL log object to `tx` a range request at a time,
+/// stopping early if the client goes away.
+async fn send_log(object: String, tx: tokio::sync::mpsc::Sender<Bytes>) -> Result<()> {
+    let mut offset = 0;
+    let mut partial: Vec<u8> = vec![];
+    loop {
+        let mut chunk = vec![];
+        let n = stream_object(
+            "icfpc2025-data",
+            &object,
+            offset,
+            Some(offset + LOG_CHUNK),
+            |b| {
+                chunk.extend_from_slice(b);
+                Ok(true)
+            },
+        )
+        .await?;
+        offset += n;
+        partial.extend_from_slice(&chunk);
+        let complete = if n < LOG_CHUNK {
+            partial.len()
+        } else {
+            partial
+                .iter()
+                .rposition(|&b| b == b'\n')
+                .map_or(0, |i| i + 1)
+        };
+        let text = jsonl_to_text(&partial[..complete]);
+        partial.drain(..complete);
+        if !text.is_empty() && tx.send(Bytes::from(text)).await.is_err() {
+            return Ok(());
+        }
+        if n < LOG_CHUNK {
+            return Ok(());
+        }
+    }
 }
 
-fn fmt_jst(dt: NaiveDateTime) -> String {
-    let jst = FixedOffset::east_opt(9 * 3600).unwrap();
-    jst.from_utc_datetime(&dt)
-        .format("%Y-%m-%d %H:%M:%S %Z")
-        .to_string()
+/// Handler for `GET /task/log`: the full text of a task's stdout or stderr,
+/// streamed from GCS.
+pub async fn log(query: web::Query<LogQuery>) -> impl Responder {
+    if query.stream != "stdout" && query.stream != "stderr" {
+        return HttpResponse::BadRequest().body("stream must be stdout or stderr");
+    }
+    let object = format!("logs/{}/{}.jsonl", query.task_id, query.stream);
+    let (tx, rx) = tokio::sync::mpsc::channel(4);
+    actix_web::rt::spawn(async move {
+        if let Err(e) = send_log(object, tx.clone()).await {
+            let _ = tx
+                .send(Bytes::from(format!(
+                    "\n… ログの取得に失敗しました: {:#} …\n",
+                    e
+                )))
+                .await;
+        }
+    });
+    HttpResponse::Ok()
+        .content_type("text/plain; charset=utf-8")
+        .body(template::ChannelBody(rx))
 }
 
+/// The text of the JSONL log lines in `bytes`, with a marker where the logger
+/// dropped output.
 fn jsonl_to_text(bytes: &[u8]) -> String {
     if bytes.is_empty() {
         return String::new();
@@ -215,20 +356,6 @@ fn render_with_omission(text: &str, head_bytes: usize, tail_bytes: usize) -> Str
     out
 }
 
-fn escape_html(s: &str) -> String {
-    s.chars()
-        .map(|c| match c {
-            '&' => "&amp;".to_string(),
-            '<' => "&lt;".to_string(),
-            '>' => "&gt;".to_string(),
-            '"' => "&quot;".to_string(),
-            '\'' => "&#x27;".to_string(),
-            '/' => "&#x2F;".to_string(),
-            _ => c.to_string(),
-        })
-        .collect()
-}
-
 fn escape_attr(s: &str) -> String {
     s.replace('&', "&amp;")
         .replace('"', "&quot;")
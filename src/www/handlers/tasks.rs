@@ -1,31 +1,67 @@
-use actix_web::{Responder, web};
+use actix_web::{HttpRequest, Responder, web};
 use anyhow::Result;
-use chrono::{FixedOffset, NaiveDateTime, TimeZone};
+use chrono::NaiveDateTime;
 use mysql::params;
 
-use crate::www::handlers::template;
+use crate::www::handlers::template::escape_html;
+use crate::www::handlers::{prefs::Prefs, template};
 
 #[derive(serde::Deserialize, Default)]
 pub struct TasksQuery {
     #[serde(default = "default_page")] // 1-based page index
     pub page: i64,
+    /// One of `FILTERS`; all tasks when empty or unknown.
+    #[serde(default)]
+    pub state: String,
 }
 
 fn default_page() -> i64 {
     1
 }
 
-pub async fn index(query: web::Query<TasksQuery>) -> impl Responder {
-    template::to_response(render_tasks_page(query.page).await)
+/// `(state parameter, tab title, task states shown)`.
+const FILTERS: [(&str, &str, &[&str]); 4] = [
+    ("", "すべて", &[]),
+    ("queued", "待機中", &["pending", "failed"]),
+    ("running", "実行中", &["running"]),
+    ("finished", "終了", &["done", "dead"]),
+];
+
+/// The task states shown for a `state` parameter, or `None` for all tasks.
+fn filter_states(state: &str) -> Option<&'static [&'static str]> {
+    FILTERS
+        .iter()
+        .find(|f| f.0 == state && !f.2.is_empty())
+        .map(|f| f.2)
+}
+
+pub async fn index(req: HttpRequest, query: web::Query<TasksQuery>) -> impl Responder {
+    let prefs = Prefs::from_request(&req);
+    template::to_response(render_tasks_page(query.page, &query.state, &prefs).await)
 }
 
-async fn render_tasks_page(page: i64) -> Result<String> {
+async fn render_tasks_page(page: i64, state: &str, prefs: &Prefs) -> Result<String> {
     let page = if page < 1 { 1 } else { page };
     let limit: i64 = 100; // fixed as requested
     let offset: i64 = (page - 1) * limit;
+    let states = filter_states(state);
+    let state = if states.is_some() { state } else { "" };
+    // The states are constants, so they can go into the query as they are.
+    let where_clause = match states {
+        Some(states) => format!(
+            "WHERE t.task_state IN ({})",
+            states
+                .iter()
+                .map(|s| format!("'{}'", s))
+                .collect::<Vec<_>>()
+                .join(", ")
+        ),
+        None => String::new(),
+    };
 
     let rows = crate::sql::select(
-        r#"
+        &format!(
+            r#"
         SELECT
             t.task_id,
             a.agent_name,
@@ -33,19 +69,27 @@ async fn render_tasks_page(page: i64) -> Result<String> {
             t.problem_variant,
             t.task_score,
             t.task_exit_code,
+            t.task_host,
+            t.task_duration_ms,
             t.task_locked,
             t.task_updated,
-            CASE
-              WHEN t.task_exit_code IS NULL THEN
-                CASE WHEN t.task_locked > CURRENT_TIMESTAMP THEN '実行中' ELSE '待機中' END
-              ELSE
-                CASE WHEN t.task_exit_code = 0 THEN '成功' ELSE CONCAT('失敗(', t.task_exit_code, ')') END
+            t.task_state,
+            t.task_progress,
+            CASE t.task_state
+              WHEN 'pending' THEN '待機中'
+              WHEN 'running' THEN '実行中'
+              WHEN 'failed' THEN CONCAT('再試行待ち(', t.task_failed, ')')
+              WHEN 'dead' THEN CONCAT('中止(', COALESCE(t.task_exit_code, 'ロック切れ'), ')')
+              ELSE '成功'
             END AS task_status
         FROM tasks t
         LEFT JOIN agents a ON a.agent_id = t.agent_id
+        {}
         ORDER BY t.task_id DESC
         LIMIT :limit_plus_one OFFSET :offset
         "#,
+            where_clause
+        ),
         params! { "limit_plus_one" => (limit + 1), "offset" => offset },
     )?;
 
@@ -58,13 +102,23 @@ async fn render_tasks_page(page: i64) -> Result<String> {
         let task_score: Option<i64> = r.get_option("task_score")?;
         let task_status: String = r.get("task_status")?;
         let task_updated: NaiveDateTime = r.get("task_updated")?;
+        let task_state: String = r.get("task_state")?;
+        // Only a running task's progress is news; the heartbeat writes it.
+        let task_progress = match task_state.as_str() {
+            "running" => r.get_option("task_progress")?,
+            _ => None,
+        };
         items.push(TaskRow {
             task_id,
             agent_name: agent_name.unwrap_or_else(|| "(unknown)".to_string()),
             problem_name,
             problem_variant,
             task_score,
+            task_exit_code: r.get_option("task_exit_code")?,
+            task_host: r.get_option("task_host")?,
+            task_duration_ms: r.get_option("task_duration_ms")?,
             task_status,
+            task_progress,
             task_updated,
         });
     }
@@ -73,9 +127,21 @@ async fn render_tasks_page(page: i64) -> Result<String> {
     // Render HTML
     let mut html = String::new();
     html.push_str("<h1>タスク一覧</h1>\n");
+    html.push_str("<p>");
+    for (i, (value, title, _)) in FILTERS.iter().enumerate() {
+        if i > 0 {
+            html.push_str(" | ");
+        }
+        if *value == state {
+            html.push_str(&format!("<b>{}</b>", title));
+        } else {
+            html.push_str(&format!("<a href=\"/tasks?state={}\">{}</a>", value, title));
+        }
+    }
+    html.push_str("</p>\n");
     html.push_str("<table class=\"table\">\n");
     html.push_str(
-        "<tr><th>タスクID</th><th>プログラム名</th><th>問題名（問題シード）</th><th>スコア</th><th>ステータス</th><th>更新時刻</th></tr>\n",
+        "<tr><th>タスクID</th><th>プログラム名</th><th>問題名（問題シード）</th><th>ホスト名</th><th>実行時間</th><th>スコア</th><th>終了コード</th><th>ステータス</th><th>更新時刻</th></tr>\n",
     );
     for it in items {
         let id_html = format!(
@@ -84,13 +150,25 @@ async fn render_tasks_page(page: i64) -> Result<String> {
         );
         let prob = format!("{} ({})", escape_html(&it.problem_name), it.problem_variant);
         html.push_str(&format!(
-            "<tr><td>{}</td><td>{}</td><td>{}</td><td>{}</td><td>{}</td><td>{}</td></tr>\n",
+            "<tr><td>{}</td><td>{}</td><td>{}</td><td>{}</td><td>{}</td><td>{}</td><td>{}</td><td>{}</td><td>{}</td></tr>\n",
             id_html,
             escape_html(&it.agent_name),
             prob,
+            escape_html(it.task_host.as_deref().unwrap_or_default()),
+            it.task_duration_ms
+                .map(|ms| format!("{:.1}s", ms as f64 / 1000.0))
+                .unwrap_or_default(),
             it.task_score.map(|v| v.to_string()).unwrap_or_default(),
-            escape_html(&it.task_status),
-            escape_html(&fmt_jst(it.task_updated)),
+            it.task_exit_code.map(|v| v.to_string()).unwrap_or_default(),
+            match &it.task_progress {
+                Some(p) => format!(
+                    "{}<br><small>{}</small>",
+                    escape_html(&it.task_status),
+                    escape_html(p)
+                ),
+                None => escape_html(&it.task_status),
+            },
+            escape_html(&prefs.format_utc(it.task_updated)),
         ));
     }
     html.push_str("</table>\n");
@@ -99,8 +177,9 @@ async fn render_tasks_page(page: i64) -> Result<String> {
     html.push_str("<div class=\"pager\">");
     if page > 1 {
         html.push_str(&format!(
-            "<a href=\"/tasks?page={}\">&laquo; 前のページ</a>",
-            page - 1
+            "<a href=\"/tasks?page={}&state={}\">&laquo; 前のページ</a>",
+            page - 1,
+            state
         ));
     }
     if has_next {
@@ -108,8 +187,9 @@ async fn render_tasks_page(page: i64) -> Result<String> {
             html.push_str(" &nbsp;| &nbsp;");
         }
         html.push_str(&format!(
-            "<a href=\"/tasks?page={}\">次のページ &raquo;</a>",
-            page + 1
+            "<a href=\"/tasks?page={}&state={}\">次のページ &raquo;</a>",
+            page + 1,
+            state
         ));
     }
     html.push_str("</div>");
@@ -123,27 +203,24 @@ struct TaskRow {
     problem_name: String,
     problem_variant: i64,
     task_score: Option<i64>,
+    task_exit_code: Option<i64>,
+    task_host: Option<String>,
+    task_duration_ms: Option<i64>,
     task_status: String,
+    /// Latest progress summary, for running tasks.
+    task_progress: Option<String>,
     task_updated: NaiveDateTime,
 }
 
-fn fmt_jst(dt: NaiveDateTime) -> String {
-    let jst = FixedOffset::east_opt(9 * 3600).unwrap();
-    jst.from_utc_datetime(&dt)
-        .format("%Y-%m-%d %H:%M:%S %Z")
-        .to_string()
-}
+#[cfg(test)]
+mod tests {
+    use super::*;
 
-fn escape_html(s: &str) -> String {
-    s.chars()
-        .map(|c| match c {
-            '&' => "&amp;".to_string(),
-            '<' => "&lt;".to_string(),
-            '>' => "&gt;".to_string(),
-            '"' => "&quot;".to_string(),
-            '\'' => "&#x27;".to_string(),
-            '/' => "&#x2F;".to_string(),
-            _ => c.to_string(),
-        })
-        .collect()
+    #[test]
+    fn unknown_filters_show_all_tasks() {
+        assert_eq!(filter_states("queued"), Some(&["pending", "failed"][..]));
+        assert_eq!(filter_states("finished"), Some(&["done", "dead"][..]));
+        assert_eq!(filter_states(""), None);
+        assert_eq!(filter_states("'; DROP TABLE tasks; --"), None);
+    }
 }
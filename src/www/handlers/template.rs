@@ -5,11 +5,15 @@
 //! to render content within this layout. It also includes several utility
 //! functions for creating common `actix_web::HttpResponse` objects.
 
+use actix_web::body::{BodySize, MessageBody};
+use actix_web::web::Bytes;
 use actix_web::{HttpResponse, Responder};
 use anyhow::Result;
 use handlebars::Handlebars;
 use once_cell::sync::Lazy;
 use serde_json::json;
+use std::pin::Pin;
+use std::task::Poll;
 
 /// A lazily-initialized, global instance of the Handlebars templating engine.
 static ENGINE: Lazy<Handlebars> = Lazy::new(new_engine);
@@ -43,6 +47,14 @@ pub fn new_engine() -> Handlebars<'static> {
 <ul>
 <li><a href="/leaderboard/global">リーダーボード</a></li>
 <li><a href="/tasks">タスク</a></li>
+<li><a href="/agents">エージェント</a></li>
+<li><a href="/queue">キュー推移</a></li>
+<li><a href="/explores">探索ログ</a></li>
+<li><a href="/costs">クエリコスト</a></li>
+<li><a href="/replay">リプレイ</a></li>
+<li><a href="/digests">日報</a></li>
+<li><a href="/owners">担当</a></li>
+<li><a href="/prefs">設定</a></li>
 </ul>
 </nav>
 <main>
@@ -57,8 +69,8 @@ pub fn new_engine() -> Handlebars<'static> {
     handlebars
 }
 
-/// A simple utility to escape HTML special characters.
-fn escape_html(s: &str) -> String {
+/// Escapes HTML special characters, for text and attribute values alike.
+pub(crate) fn escape_html(s: &str) -> String {
     s.chars()
         .map(|c| match c {
             '&' => "&amp;".to_string(),
@@ -120,3 +132,22 @@ pub fn to_response(result: Result<String>) -> impl Responder {
         Err(e) => to_error_response(&e),
     }
 }
+
+/// A response body fed by a channel, so that a page can be sent while it is
+/// still being produced (log downloads, server-sent events).
+pub struct ChannelBody(pub tokio::sync::mpsc::Receiver<Bytes>);
+
+impl MessageBody for ChannelBody {
+    type Error = std::convert::Infallible;
+
+    fn size(&self) -> BodySize {
+        BodySize::Stream
+    }
+
+    fn poll_next(
+        self: Pin<&mut Self>,
+        cx: &mut std::task::Context<'_>,
+    ) -> Poll<Option<Result<Bytes, Self::Error>>> {
+        self.get_mut().0.poll_recv(cx).map(|chunk| chunk.map(Ok))
+    }
+}
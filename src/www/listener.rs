@@ -0,0 +1,104 @@
+//! # Listener Setup for Rolling Upgrades
+//!
+//! The dashboard is redeployed during the contest while people keep the
+//! leaderboard open, so a restart must not drop connections. `bin/www` gets
+//! its listening socket from [`listener_from_env`], which supports two ways of
+//! overlapping the old and the new process:
+//!
+//! - **Socket activation** (`LISTEN_FDS`/`LISTEN_PID`, as set by systemd or
+//!   `systemfd`): the socket is owned by the supervisor and inherited as fd 3,
+//!   so it keeps accepting while the server process is replaced.
+//! - **`REUSE_PORT=1`**: the socket is bound with `SO_REUSEPORT`, so the new
+//!   process can bind the same port while the old one is still serving. Send
+//!   the old process SIGTERM once the new one is up.
+//!
+//! Otherwise the address is bound normally. Either way, SIGTERM makes actix
+//! stop accepting and drain in-flight requests for up to
+//! `SHUTDOWN_TIMEOUT_SECS` (default 30) before exiting.
+
+use std::io;
+use std::net::{SocketAddr, TcpListener, ToSocketAddrs};
+use std::os::fd::FromRawFd;
+
+/// First inherited fd under the socket activation protocol.
+const SD_LISTEN_FDS_START: i32 = 3;
+
+/// Returns the socket passed by the supervisor, if this process was
+/// socket-activated.
+pub fn activated_listener() -> io::Result<Option<TcpListener>> {
+    let Ok(fds) = std::env::var("LISTEN_FDS") else {
+        return Ok(None);
+    };
+    // LISTEN_PID guards against the variables leaking into child processes.
+    if let Ok(pid) = std::env::var("LISTEN_PID")
+        && pid.parse::<u32>().ok() != Some(std::process::id())
+    {
+        return Ok(None);
+    }
+    let fds: i32 = fds
+        .parse()
+        .map_err(|_| io::Error::other(format!("invalid LISTEN_FDS: {}", fds)))?;
+    if fds < 1 {
+        return Ok(None);
+    }
+    // SAFETY: the activation protocol hands fds starting at 3 to this process,
+    // and nothing else in `bin/www` takes ownership of them.
+    let listener = unsafe { TcpListener::from_raw_fd(SD_LISTEN_FDS_START) };
+    // Fails if fd 3 is not a TCP socket.
+    listener.local_addr()?;
+    Ok(Some(listener))
+}
+
+/// Binds `addr`, optionally with `SO_REUSEPORT` so that another process can
+/// listen on the same port at the same time.
+pub fn bind(addr: impl ToSocketAddrs, reuse_port: bool) -> io::Result<TcpListener> {
+    if !reuse_port {
+        return TcpListener::bind(addr);
+    }
+    let addr: SocketAddr = addr
+        .to_socket_addrs()?
+        .next()
+        .ok_or_else(|| io::Error::other("address resolved to nothing"))?;
+    let socket = socket2::Socket::new(
+        socket2::Domain::for_address(addr),
+        socket2::Type::STREAM,
+        Some(socket2::Protocol::TCP),
+    )?;
+    socket.set_reuse_address(true)?;
+    socket.set_reuse_port(true)?;
+    socket.bind(&addr.into())?;
+    socket.listen(1024)?;
+    Ok(socket.into())
+}
+
+/// Picks the listener as described in the module docs.
+pub fn listener_from_env(bind_address: &str) -> io::Result<TcpListener> {
+    if let Some(listener) = activated_listener()? {
+        eprintln!("Using activated socket {}", listener.local_addr()?);
+        return Ok(listener);
+    }
+    let reuse_port = std::env::var("REUSE_PORT").is_ok_and(|v| v == "1");
+    bind(bind_address, reuse_port)
+}
+
+/// Grace period for in-flight requests on shutdown.
+pub fn shutdown_timeout_secs() -> u64 {
+    std::env::var("SHUTDOWN_TIMEOUT_SECS")
+        .ok()
+        .and_then(|v| v.parse().ok())
+        .unwrap_or(30)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn reuse_port_allows_two_listeners() {
+        let first = bind("127.0.0.1:0", true).unwrap();
+        let addr = first.local_addr().unwrap();
+        let second = bind(addr, true).unwrap();
+        assert_eq!(second.local_addr().unwrap(), addr);
+        assert!(bind(addr, false).is_err());
+    }
+}
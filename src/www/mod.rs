@@ -5,10 +5,19 @@
 //! interfaces for the project.
 //!
 //! ## Submodules
+//! - `auth`: Password and CSRF checks for forms that change what the fleet runs.
 //! - `handlers`: Contains the Axum request handlers for different API routes.
+//! - `listener`: Sets up the listening socket for zero-downtime restarts.
+//! - `scores_ingester`: Polls our scores into the `scores` table in the background.
 //! - `utils`: Provides utility functions used by the web server.
 
+/// Password and CSRF checks for write forms.
+pub mod auth;
 /// Request handlers for the web server's API routes.
 pub mod handlers;
+/// Listening socket setup (socket activation, `SO_REUSEPORT`).
+pub mod listener;
+/// Background polling of `api::scores` into the `scores` table.
+pub mod scores_ingester;
 /// Utility functions for the web server.
 pub mod utils;
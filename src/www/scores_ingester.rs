@@ -0,0 +1,120 @@
+//! # Scores Ingester
+//!
+//! A background task of the www server that polls [`api::scores`] every
+//! minute and writes our scores into the `scores` table as `Unagi` rows, so
+//! the leaderboard stays current even when the external cron is down.
+//!
+//! Several servers may run at once (rolling upgrades, replicas), so a poll
+//! only happens while holding a lease on the `locks` row `INGESTER_LOCK_ID`;
+//! the other servers skip their polls until it expires. Every poll is
+//! recorded in `score_fetches`, with the error if it failed.
+//!
+//! ```sql
+//! INSERT INTO locks (lock_id, lock_user, lock_token, lock_expired)
+//! VALUES (2, '', '', '1970-01-01 00:00:01');
+//! CREATE TABLE score_fetches (
+//!   score_fetch_id BIGINT NOT NULL AUTO_INCREMENT PRIMARY KEY,
+//!   score_fetch_created TIMESTAMP NOT NULL DEFAULT CURRENT_TIMESTAMP,
+//!   score_fetch_rows INT NOT NULL DEFAULT 0,
+//!   score_fetch_error TEXT NULL
+//! );
+//! ```
+//!
+//! Set `SCORES_INGESTER=0` to disable it.
+
+use crate::{api, sql};
+use anyhow::Result;
+use chrono::Utc;
+use mysql::params;
+use std::time::Duration;
+
+/// The `locks` row leased by the ingester (`lock_id = 1` is the API lock).
+const INGESTER_LOCK_ID: i64 = 2;
+/// Time between polls.
+const POLL_INTERVAL: Duration = Duration::from_secs(60);
+/// How long a lease outlives its last poll; longer than `POLL_INTERVAL` so
+/// the holder keeps it, short enough that another server takes over soon
+/// after the holder stops.
+const LEASE_TTL: Duration = Duration::from_secs(150);
+
+/// Whether `SCORES_INGESTER` leaves the ingester enabled (the default).
+pub fn enabled() -> bool {
+    std::env::var("SCORES_INGESTER").as_deref() != Ok("0")
+}
+
+/// Starts the polling loop on the current actix runtime.
+pub fn spawn() {
+    let token = hex::encode(rand::random::<[u8; 5]>());
+    actix_web::rt::spawn(async move {
+        let mut interval = tokio::time::interval(POLL_INTERVAL);
+        interval.set_missed_tick_behavior(tokio::time::MissedTickBehavior::Delay);
+        loop {
+            interval.tick().await;
+            let token = token.clone();
+            match actix_web::web::block(move || poll(&token)).await {
+                Ok(Ok(_)) => {}
+                Ok(Err(e)) => eprintln!("[scores_ingester] {:#}", e),
+                Err(e) => eprintln!("[scores_ingester] poll panicked: {}", e),
+            }
+        }
+    });
+}
+
+/// One poll: takes or renews the lease, then fetches and stores the scores.
+/// Returns the number of rows written, or `None` if another server holds
+/// the lease.
+fn poll(token: &str) -> Result<Option<usize>> {
+    if !acquire_lease(token)? {
+        return Ok(None);
+    }
+    let (rows, error) = match ingest() {
+        Ok(rows) => (rows, None),
+        Err(e) => (0, Some(format!("{:#}", e))),
+    };
+    sql::exec(
+        "INSERT INTO score_fetches (score_fetch_rows, score_fetch_error) VALUES (:rows, :error)",
+        params! { "rows" => rows, "error" => &error },
+    )?;
+    match error {
+        Some(e) => anyhow::bail!("failed to ingest scores: {}", e),
+        None => Ok(Some(rows)),
+    }
+}
+
+/// Takes the lease if it is free or renews it if `token` holds it.
+fn acquire_lease(token: &str) -> Result<bool> {
+    let affected = sql::exec(
+        r#"
+        UPDATE locks
+        SET
+            lock_user = 'scores_ingester',
+            lock_token = :token,
+            lock_expired = DATE_ADD(CURRENT_TIMESTAMP, INTERVAL :ttl SECOND)
+        WHERE lock_id = :lock_id
+          AND (lock_expired < CURRENT_TIMESTAMP OR lock_token = :token)
+        "#,
+        params! {
+            "token" => token,
+            "ttl" => LEASE_TTL.as_secs(),
+            "lock_id" => INGESTER_LOCK_ID,
+        },
+    )?;
+    Ok(affected > 0)
+}
+
+/// Fetches our scores and inserts them with the current timestamp.
+fn ingest() -> Result<usize> {
+    let scores = api::scores()?;
+    let ts = Utc::now().naive_utc();
+    sql::exec_batch(
+        "INSERT IGNORE INTO scores (timestamp, problem, team_name, score) VALUES (:timestamp, :problem, 'Unagi', :score)",
+        scores.iter().map(|(problem, score)| {
+            params! {
+                "timestamp" => ts,
+                "problem" => problem,
+                "score" => score,
+            }
+        }),
+    )?;
+    Ok(scores.len())
+}